@@ -0,0 +1,78 @@
+use std::{fs, path::Path};
+
+use alloy::primitives::{address, U256};
+use amms::amm::{uniswap_v3::UniswapV3Pool, AutomatedMarketMaker};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Directory of serialized `UniswapV3Pool` snapshots (`serde_json::to_string(&pool)` of a populated pool), recorded
+/// with `ETHEREUM_RPC_ENDPOINT=<url> cargo test record_bench_fixtures -- --ignored`.
+const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/pools");
+
+/// Builds a pool around tick 0 with `positions` nested ranges, `spacing_multiple` tick spacings apart.
+fn synthetic_pool(positions: i32, spacing_multiple: i32) -> UniswapV3Pool {
+    let mut pool = UniswapV3Pool {
+        address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+        token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+        token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+        fee: 3000,
+        tick_spacing: 60,
+        sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+        ..Default::default()
+    };
+
+    for i in 1..=positions {
+        let width = i * spacing_multiple * pool.tick_spacing;
+        pool.modify_position(-width, width, 1_000_000_000_000_000_000);
+    }
+
+    pool
+}
+
+fn fixtures() -> Vec<(String, UniswapV3Pool)> {
+    let mut fixtures = vec![
+        ("dense".to_string(), synthetic_pool(500, 1)),
+        ("sparse".to_string(), synthetic_pool(50, 200)),
+    ];
+
+    if let Ok(entries) = fs::read_dir(Path::new(FIXTURES_DIR)) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let pool = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+                let name = path.file_stem().unwrap().to_string_lossy().to_string();
+                fixtures.push((name, pool));
+            }
+        }
+    }
+
+    fixtures
+}
+
+fn simulate_swap(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulate_swap");
+
+    for (name, pool) in fixtures() {
+        for amount_in in [
+            U256::from(10_u128.pow(15)),
+            U256::from(10_u128.pow(18)),
+            U256::from(10_u128.pow(21)),
+        ] {
+            for token_in in [pool.token_a, pool.token_b] {
+                let id = format!("{name}/{token_in}/{amount_in}");
+                group.bench_with_input(
+                    BenchmarkId::from_parameter(id),
+                    &amount_in,
+                    |b, amount_in| b.iter(|| black_box(pool.simulate_swap(token_in, *amount_in))),
+                );
+            }
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, simulate_swap);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+use amms::amm::uniswap_v3::UniswapV3Pool;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use uniswap_v3_math::{
+    tick_bitmap::next_initialized_tick_within_one_word,
+    tick_math::{MAX_TICK, MIN_TICK},
+};
+
+const TICK_SPACING: i32 = 60;
+
+/// Builds a pool with sparse liquidity, so that initialized ticks are spread over many bitmap words.
+fn sparse_pool() -> UniswapV3Pool {
+    let mut pool = UniswapV3Pool {
+        tick_spacing: TICK_SPACING,
+        ..Default::default()
+    };
+
+    for i in 1..=250 {
+        let width = i * TICK_SPACING * 50;
+        pool.modify_position(-width, width, 1_000_000);
+    }
+
+    pool
+}
+
+/// Finds the next initialized tick by walking the tick bitmap one word at a time.
+fn next_initialized_tick_bitmap(pool: &UniswapV3Pool, mut tick: i32, lte: bool) -> i32 {
+    loop {
+        let (tick_next, initialized) =
+            next_initialized_tick_within_one_word(&pool.tick_bitmap, tick, pool.tick_spacing, lte)
+                .unwrap();
+
+        if initialized || tick_next <= MIN_TICK || tick_next >= MAX_TICK {
+            return tick_next;
+        }
+
+        tick = if lte { tick_next - 1 } else { tick_next };
+    }
+}
+
+fn next_initialized_tick(c: &mut Criterion) {
+    let pool = sparse_pool();
+    let start_ticks = (-200..200)
+        .map(|i| i * TICK_SPACING * 97)
+        .collect::<Vec<i32>>();
+
+    let mut group = c.benchmark_group("next_initialized_tick");
+
+    group.bench_function("tick_bitmap", |b| {
+        b.iter(|| {
+            for tick in &start_ticks {
+                black_box(next_initialized_tick_bitmap(&pool, *tick, true));
+                black_box(next_initialized_tick_bitmap(&pool, *tick, false));
+            }
+        })
+    });
+
+    group.bench_function("btree_map", |b| {
+        b.iter(|| {
+            for tick in &start_ticks {
+                black_box(pool.next_initialized_tick(*tick, true));
+                black_box(pool.next_initialized_tick(*tick, false));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, next_initialized_tick);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+interface IERC20 {
+    function decimals() external view returns (uint8);
+
+    function symbol() external view returns (string memory);
+
+    function name() external view returns (string memory);
+
+    function totalSupply() external view returns (uint256);
+}
+
+/**
+ * @dev This contract is not meant to be deployed. Instead, use a static call with the
+ *       deployment bytecode as payload.
+ *
+ *       Returns the raw return data of `decimals`, `symbol`, `name` and `totalSupply` of each token, in this order,
+ *       with empty data for failed calls. The return data is decoded off chain, as some tokens return `bytes32`
+ *       symbols and names.
+ *
+ *       The bytecode in GetTokenMetadataBatchRequestABI.json is assembled by hand from this constructor and returns
+ *       the same data, without the trailing memory.
+ */
+contract GetTokenMetadataBatchRequest {
+    constructor(address[] memory tokens) {
+        bytes4[4] memory selectors =
+            [IERC20.decimals.selector, IERC20.symbol.selector, IERC20.name.selector, IERC20.totalSupply.selector];
+        bytes[] memory results = new bytes[](tokens.length * 4);
+
+        for (uint256 i = 0; i < results.length; ++i) {
+            (bool success, bytes memory data) =
+                tokens[i / 4].staticcall{gas: 100000}(abi.encodeWithSelector(selectors[i % 4]));
+
+            if (success) {
+                results[i] = data;
+            }
+        }
+
+        // ensure abi encoding, not needed here but increase reusability for different return types
+        // note: abi.encode add a first 32 bytes word with the address of the original data
+        bytes memory _abiEncodedData = abi.encode(results);
+
+        assembly {
+            // Return from the start of the data (discarding the original data address)
+            // up to the end of the memory used
+            let dataStart := add(_abiEncodedData, 0x20)
+            return(dataStart, sub(msize(), dataStart))
+        }
+    }
+}
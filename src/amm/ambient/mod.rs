@@ -0,0 +1,770 @@
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+use alloy::{
+    primitives::{keccak256, Address, B256, I256, U256},
+    rpc::types::eth::Log,
+    sol,
+    sol_types::{SolEvent, SolValue},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use uniswap_v3_math::{error::UniswapV3MathError, full_math::mul_div, tick_math};
+
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{multicall, AMM},
+    errors::{AMMError, ErrorContext, ResultExt},
+};
+use crate::{
+    amm::{
+        uniswap_v3::{serde_maps, Info},
+        AutomatedMarketMaker, Protocol,
+    },
+    core::{
+        price,
+        uniswap_v3::{self as math, CurrentState, TickSource},
+    },
+    errors::{ArithmeticError, EventLogError, SwapSimulationError},
+};
+
+sol! {
+    /// Events of the CrocSwap dex, the singleton holding all Ambient pools
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract ICrocSwapDex {
+        event CrocSwap(address indexed base, address indexed quote, uint256 poolIdx, bool isBuy, bool inBaseQty, uint128 qty, uint16 poolTip, uint128 limitPrice, uint128 minOut, uint8 reserveFlags, int128 baseFlow, int128 quoteFlow);
+        event CrocHotCmd(bytes input, int128 baseFlow, int128 quoteFlow);
+        event CrocWarmCmd(bytes input, int128 baseFlow, int128 quoteFlow);
+    }
+}
+
+sol! {
+    /// Interface of the CrocSwap query contract
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract ICrocQuery {
+        struct CurveState {
+            uint128 priceRoot_;
+            uint128 ambientSeeds_;
+            uint128 concLiq_;
+            uint64 seedDeflator_;
+            uint64 concGrowth_;
+        }
+
+        struct PoolSpec {
+            uint8 schema_;
+            uint16 feeRate_;
+            uint8 protocolTake_;
+            uint16 tickSize_;
+            uint8 jitThresh_;
+            uint8 knockoutBits_;
+            uint8 oracleFlags_;
+        }
+
+        function queryCurve(address base, address quote, uint256 poolIdx) external view returns (CurveState memory);
+        function queryPoolParams(address base, address quote, uint256 poolIdx) external view returns (PoolSpec memory);
+        function queryLiquidity(address base, address quote, uint256 poolIdx) external view returns (uint128);
+        function queryLevel(address base, address quote, uint256 poolIdx, int24 tick) external view returns (uint96 bidLots, uint96 askLots);
+    }
+}
+
+sol! {
+    /// Input of a swap through the hot path of the dex, in `CrocHotCmd` logs
+    #[derive(Debug, PartialEq, Eq)]
+    struct HotSwapInput {
+        address base;
+        address quote;
+        uint256 poolIdx;
+        bool isBuy;
+        bool inBaseQty;
+        uint128 qty;
+        uint16 poolTip;
+        uint128 limitPrice;
+        uint128 minOut;
+        uint8 reserveFlags;
+    }
+
+    /// Input of a liquidity command through the warm path of the dex, in `CrocWarmCmd` logs
+    #[derive(Debug, PartialEq, Eq)]
+    struct WarmCmdInput {
+        uint8 code;
+        address base;
+        address quote;
+        uint256 poolIdx;
+        int24 bidTick;
+        int24 askTick;
+        uint128 liq;
+        uint128 limitLower;
+        uint128 limitHigher;
+        uint8 reserveFlags;
+        address lpConduit;
+    }
+}
+
+/// Liquidity units of a lot, the granularity of concentrated liquidity.
+pub const LOT_SIZE: u128 = 1024;
+
+/// Number of `tick_bitmap` words loaded on each side of the current tick.
+pub const LEVEL_WORD_RADIUS: i16 = 1;
+
+/// Estimated gas used by a swap, including the token transfers.
+pub const SWAP_GAS_ESTIMATE: u64 = 110_000;
+
+const Q96: U256 = U256::from_limbs([0, 4_294_967_296, 0, 0]);
+
+/// A pool of the Ambient (CrocSwap) dex, identified by its base and quote tokens and pool index.
+///
+/// All pools live in the dex contract, so the address of a pool is derived from its pool hash, see
+/// [`pool_address`]. The liquidity of the pool is the sum of its ambient (full range) liquidity and the concentrated
+/// liquidity in range, swaps walk the concentrated liquidity levels like Uniswap V3 ticks.
+///
+/// Pools are synced from the swap and liquidity logs of the dex. Fees are charged on the input of swaps and are not
+/// reinvested into the ambient liquidity locally, and knockout liquidity is not tracked: both are picked up by
+/// [`AutomatedMarketMaker::sync`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AmbientPool {
+    pub address: Address,
+    /// Base token of the pool, the zero address for the native token.
+    pub base_token: Address,
+    pub base_token_decimals: u8,
+    pub quote_token: Address,
+    pub quote_token_decimals: u8,
+    pub pool_idx: u64,
+    /// Square root of the price of the base token in quote tokens, as a Q64.96.
+    pub sqrt_price: U256,
+    pub tick: i32,
+    pub ambient_liquidity: u128,
+    /// Concentrated liquidity in range.
+    pub concentrated_liquidity: u128,
+    /// Fee in hundredths of a basis point.
+    pub fee: u32,
+    pub tick_size: i32,
+    #[serde(with = "serde_maps::tick_bitmap")]
+    pub tick_bitmap: HashMap<i16, U256>,
+    #[serde(with = "serde_maps::ticks")]
+    pub ticks: BTreeMap<i32, Info>,
+    /// Inclusive range of `tick_bitmap` words with loaded levels.
+    pub tick_window: (i16, i16),
+    pub dex: Address,
+    pub query: Address,
+}
+
+#[async_trait]
+impl AutomatedMarketMaker for AmbientPool {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::Ambient
+    }
+
+    fn fee_bps(&self) -> u32 {
+        self.fee / 100
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        self.populate_data(None, provider)
+            .await
+            .context(ErrorContext::sync(self.address))?;
+        tracing::info!(sqrt_price = ?self.sqrt_price, liquidity = self.liquidity(), address = ?self.address, "Ambient sync");
+
+        Ok(())
+    }
+
+    fn sync_on_event_signatures(&self) -> Vec<B256> {
+        vec![
+            ICrocSwapDex::CrocSwap::SIGNATURE_HASH,
+            ICrocSwapDex::CrocHotCmd::SIGNATURE_HASH,
+            ICrocSwapDex::CrocWarmCmd::SIGNATURE_HASH,
+        ]
+    }
+
+    /// Applies a swap or liquidity log of the dex, ignoring the logs of other pools.
+    #[instrument(skip(self), level = "debug")]
+    fn sync_from_log(&mut self, log: Log) -> Result<(), EventLogError> {
+        let event_signature = log.topics()[0];
+        let is_dex_log = log.address() == self.dex;
+
+        if event_signature == ICrocSwapDex::CrocSwap::SIGNATURE_HASH {
+            let swap_event = ICrocSwapDex::CrocSwap::decode_log(log.as_ref(), true)?;
+            if is_dex_log && self.is_pool(swap_event.base, swap_event.quote, swap_event.poolIdx) {
+                self.apply_swap_flows(swap_event.baseFlow, swap_event.quoteFlow);
+            }
+        } else if event_signature == ICrocSwapDex::CrocHotCmd::SIGNATURE_HASH {
+            let hot_event = ICrocSwapDex::CrocHotCmd::decode_log(log.as_ref(), true)?;
+            let input = HotSwapInput::abi_decode(&hot_event.input, true)?;
+            if is_dex_log && self.is_pool(input.base, input.quote, input.poolIdx) {
+                self.apply_swap_flows(hot_event.baseFlow, hot_event.quoteFlow);
+            }
+        } else if event_signature == ICrocSwapDex::CrocWarmCmd::SIGNATURE_HASH {
+            let warm_event = ICrocSwapDex::CrocWarmCmd::decode_log(log.as_ref(), true)?;
+            let input = WarmCmdInput::abi_decode(&warm_event.input, true)?;
+            if is_dex_log && self.is_pool(input.base, input.quote, input.poolIdx) {
+                if let Err(error) =
+                    self.apply_warm_cmd(&input, warm_event.baseFlow, warm_event.quoteFlow)
+                {
+                    tracing::warn!(?error, address = ?self.address, "Could not apply Ambient liquidity command");
+                }
+            }
+        } else {
+            return Err(EventLogError::InvalidEventSignature);
+        }
+
+        tracing::debug!(sqrt_price = ?self.sqrt_price, liquidity = self.liquidity(), address = ?self.address, "Ambient event");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn populate_data<T, N, P>(
+        &mut self,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut amms = [AMM::AmbientPool(self.clone())];
+        multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+            .await
+            .context(ErrorContext::populate(self.address, block_number))?;
+        if let [AMM::AmbientPool(pool)] = amms {
+            *self = pool;
+        }
+
+        Ok(())
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.base_token, self.quote_token]
+    }
+
+    fn calculate_price(&self, base_token: Address) -> Result<f64, ArithmeticError> {
+        let tick = tick_math::get_tick_at_sqrt_ratio(self.sqrt_price)?;
+        let price = price::tick_to_price(tick, self.base_token_decimals, self.quote_token_decimals);
+
+        if base_token == self.base_token {
+            Ok(price)
+        } else {
+            Ok(1.0 / price)
+        }
+    }
+
+    fn simulate_swap(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let state = self.swap(token_in, amount_in)?;
+
+        Ok((-state.amount_calculated).into_raw())
+    }
+
+    fn simulate_swap_mut(
+        &mut self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let state = self.swap(token_in, amount_in)?;
+
+        self.sqrt_price = state.sqrt_price_x_96;
+        self.tick = state.tick;
+        self.concentrated_liquidity = state
+            .liquidity
+            .checked_sub(self.ambient_liquidity)
+            .ok_or(SwapSimulationError::LiquidityUnderflow)?;
+
+        Ok((-state.amount_calculated).into_raw())
+    }
+
+    fn get_token_out(&self, token_in: Address) -> Address {
+        if token_in == self.base_token {
+            self.quote_token
+        } else {
+            self.base_token
+        }
+    }
+
+    fn swap_gas_estimate(&self, _token_in: Address, _amount_in: U256) -> u64 {
+        SWAP_GAS_ESTIMATE
+    }
+}
+
+impl TickSource for AmbientPool {
+    fn tick_bitmap_word(&self, word_pos: i16) -> U256 {
+        self.tick_bitmap.get(&word_pos).copied().unwrap_or_default()
+    }
+
+    fn liquidity_net(&self, tick: i32) -> i128 {
+        self.ticks.get(&tick).map_or(0, |info| info.liquidity_net)
+    }
+
+    fn check_tick_loaded(&self, tick: i32) -> Result<(), SwapSimulationError> {
+        let word = self.tick_word_position(tick);
+        if (self.tick_window.0..=self.tick_window.1).contains(&word) {
+            Ok(())
+        } else {
+            Err(SwapSimulationError::TickWordNotLoaded(word))
+        }
+    }
+}
+
+impl AmbientPool {
+    /// Returns an unpopulated pool, see [`AutomatedMarketMaker::populate_data`].
+    ///
+    /// The tokens are sorted into the base and quote tokens of the pool.
+    pub fn new(
+        token_a: Address,
+        token_b: Address,
+        pool_idx: u64,
+        dex: Address,
+        query: Address,
+    ) -> Self {
+        let (base_token, quote_token) = if token_a < token_b {
+            (token_a, token_b)
+        } else {
+            (token_b, token_a)
+        };
+
+        AmbientPool {
+            address: pool_address(base_token, quote_token, pool_idx),
+            base_token,
+            quote_token,
+            pool_idx,
+            dex,
+            query,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "provider")]
+    pub async fn new_from_tokens<T, N, P>(
+        token_a: Address,
+        token_b: Address,
+        pool_idx: u64,
+        dex: Address,
+        query: Address,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut pool = AmbientPool::new(token_a, token_b, pool_idx, dex, query);
+        pool.populate_data(None, provider).await?;
+
+        if !pool.data_is_populated() {
+            return Err(AMMError::PoolDataError);
+        }
+
+        Ok(pool)
+    }
+
+    pub fn data_is_populated(&self) -> bool {
+        !(self.quote_token.is_zero() || self.sqrt_price.is_zero() || self.tick_size == 0)
+    }
+
+    /// Returns the active liquidity of the pool, ambient and concentrated.
+    pub fn liquidity(&self) -> u128 {
+        self.ambient_liquidity + self.concentrated_liquidity
+    }
+
+    pub fn tick_word_position(&self, tick: i32) -> i16 {
+        uniswap_v3_math::tick_bitmap::position(tick.div_euclid(self.tick_size)).0
+    }
+
+    /// Returns the ticks of the levels to load around `tick`, see [`AmbientPool::apply_levels`].
+    pub fn level_ticks(tick: i32, tick_size: i32) -> ((i16, i16), Vec<i32>) {
+        let word = uniswap_v3_math::tick_bitmap::position(tick.div_euclid(tick_size)).0;
+        let window = (
+            word.saturating_sub(LEVEL_WORD_RADIUS),
+            word.saturating_add(LEVEL_WORD_RADIUS),
+        );
+
+        let ticks = (window.0 as i32 * 256..(window.1 as i32 + 1) * 256)
+            .map(|compressed| compressed * tick_size)
+            .filter(|tick| (tick_math::MIN_TICK..=tick_math::MAX_TICK).contains(tick))
+            .collect();
+
+        (window, ticks)
+    }
+
+    /// Replaces the concentrated liquidity levels of the pool with the bid and ask lots of each tick of `window`.
+    pub fn apply_levels(
+        &mut self,
+        window: (i16, i16),
+        levels: impl IntoIterator<Item = (i32, u128, u128)>,
+    ) {
+        self.tick_window = window;
+        self.ticks.clear();
+        self.tick_bitmap.clear();
+
+        for (tick, bid_lots, ask_lots) in levels {
+            if bid_lots == 0 && ask_lots == 0 {
+                continue;
+            }
+
+            // Ranges start at their bid tick and end at their ask tick
+            let info = Info::new(
+                (bid_lots + ask_lots) * LOT_SIZE,
+                (bid_lots as i128 - ask_lots as i128) * LOT_SIZE as i128,
+                true,
+            );
+            self.ticks.insert(tick, info);
+            self.flip_tick(tick);
+        }
+    }
+
+    /// Adds `liquidity_delta` to the range between `bid_tick` and `ask_tick`.
+    pub fn modify_position(&mut self, bid_tick: i32, ask_tick: i32, liquidity_delta: i128) {
+        for (tick, upper) in [(bid_tick, false), (ask_tick, true)] {
+            // Levels outside of the loaded words are not tracked
+            if self.check_tick_loaded(tick).is_err() {
+                continue;
+            }
+
+            let info = self.ticks.entry(tick).or_default();
+            let liquidity_gross_before = info.liquidity_gross;
+            info.liquidity_gross = liquidity_gross_before.saturating_add_signed(liquidity_delta);
+            info.liquidity_net += if upper {
+                -liquidity_delta
+            } else {
+                liquidity_delta
+            };
+            info.initialized = info.liquidity_gross != 0;
+
+            if (info.liquidity_gross == 0) != (liquidity_gross_before == 0) {
+                if info.liquidity_gross == 0 {
+                    self.ticks.remove(&tick);
+                }
+                self.flip_tick(tick);
+            }
+        }
+
+        if self.tick >= bid_tick && self.tick < ask_tick {
+            self.concentrated_liquidity = self
+                .concentrated_liquidity
+                .saturating_add_signed(liquidity_delta);
+        }
+    }
+
+    fn flip_tick(&mut self, tick: i32) {
+        let (word_pos, bit_pos) =
+            uniswap_v3_math::tick_bitmap::position(tick.div_euclid(self.tick_size));
+        *self.tick_bitmap.entry(word_pos).or_default() ^= U256::from(1) << bit_pos;
+    }
+
+    fn is_pool(&self, base: Address, quote: Address, pool_idx: U256) -> bool {
+        base == self.base_token
+            && quote == self.quote_token
+            && pool_idx == U256::from(self.pool_idx)
+    }
+
+    fn swap(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<CurrentState, SwapSimulationError> {
+        let state = CurrentState {
+            amount_specified_remaining: I256::from_raw(amount_in),
+            amount_calculated: I256::ZERO,
+            sqrt_price_x_96: self.sqrt_price,
+            tick: self.tick,
+            liquidity: self.liquidity(),
+        };
+
+        if amount_in.is_zero() || self.sqrt_price.is_zero() {
+            return Ok(state);
+        }
+
+        math::swap(
+            self,
+            state,
+            self.tick_size,
+            self.fee,
+            token_in == self.base_token,
+        )
+    }
+
+    /// Replays a swap from the token flows of the dex, positive flows being paid into the pool.
+    fn apply_swap_flows(&mut self, base_flow: i128, quote_flow: i128) {
+        let (token_in, amount_in) = if base_flow > 0 {
+            (self.base_token, base_flow)
+        } else {
+            (self.quote_token, quote_flow)
+        };
+
+        if amount_in <= 0 {
+            return;
+        }
+
+        if let Err(error) = self.simulate_swap_mut(token_in, U256::from(amount_in as u128)) {
+            tracing::warn!(?error, address = ?self.address, "Could not replay Ambient swap");
+        }
+    }
+
+    /// Applies a mint or burn of ambient or concentrated liquidity, deriving the liquidity from the token flows for
+    /// commands denominated in tokens.
+    fn apply_warm_cmd(
+        &mut self,
+        input: &WarmCmdInput,
+        base_flow: i128,
+        quote_flow: i128,
+    ) -> Result<(), UniswapV3MathError> {
+        let (base_flow, quote_flow) = (
+            U256::from(base_flow.unsigned_abs()),
+            U256::from(quote_flow.unsigned_abs()),
+        );
+
+        match input.code {
+            // Mint and burn of concentrated liquidity
+            1 | 11 | 12 | 2 | 21 | 22 => {
+                let liquidity = if matches!(input.code, 1 | 2) {
+                    input.liq
+                } else {
+                    self.range_liquidity(input.bidTick, input.askTick, base_flow, quote_flow)?
+                };
+                let liquidity = liquidity as i128;
+
+                self.modify_position(
+                    input.bidTick,
+                    input.askTick,
+                    if matches!(input.code, 1 | 11 | 12) {
+                        liquidity
+                    } else {
+                        -liquidity
+                    },
+                );
+            }
+            // Mint and burn of ambient liquidity
+            3 | 31 | 32 | 4 | 41 | 42 => {
+                let liquidity = if matches!(input.code, 3 | 4) {
+                    input.liq
+                } else {
+                    self.ambient_liquidity_from_flows(base_flow, quote_flow)?
+                };
+
+                self.ambient_liquidity = if matches!(input.code, 3 | 31 | 32) {
+                    self.ambient_liquidity.saturating_add(liquidity)
+                } else {
+                    self.ambient_liquidity.saturating_sub(liquidity)
+                };
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Returns the concentrated liquidity of a range given the tokens it holds at the current price.
+    fn range_liquidity(
+        &self,
+        bid_tick: i32,
+        ask_tick: i32,
+        base_amount: U256,
+        quote_amount: U256,
+    ) -> Result<u128, UniswapV3MathError> {
+        let sqrt_lower = tick_math::get_sqrt_ratio_at_tick(bid_tick)?;
+        let sqrt_upper = tick_math::get_sqrt_ratio_at_tick(ask_tick)?;
+        if sqrt_upper <= sqrt_lower {
+            return Ok(0);
+        }
+
+        let liquidity = if self.sqrt_price <= sqrt_lower {
+            // Only base tokens below the range
+            mul_div(
+                mul_div(base_amount, sqrt_lower, Q96)?,
+                sqrt_upper,
+                sqrt_upper - sqrt_lower,
+            )?
+        } else if self.sqrt_price >= sqrt_upper {
+            mul_div(quote_amount, Q96, sqrt_upper - sqrt_lower)?
+        } else {
+            mul_div(quote_amount, Q96, self.sqrt_price - sqrt_lower)?
+        };
+
+        Ok(liquidity.saturating_to())
+    }
+
+    /// Returns the ambient liquidity given the tokens it holds at the current price.
+    fn ambient_liquidity_from_flows(
+        &self,
+        base_amount: U256,
+        quote_amount: U256,
+    ) -> Result<u128, UniswapV3MathError> {
+        if self.sqrt_price.is_zero() {
+            return Ok(0);
+        }
+
+        let liquidity = if quote_amount.is_zero() {
+            mul_div(base_amount, self.sqrt_price, Q96)?
+        } else {
+            mul_div(quote_amount, Q96, self.sqrt_price)?
+        };
+
+        Ok(liquidity.saturating_to())
+    }
+}
+
+/// Returns the address of the pool of `base_token` and `quote_token` at `pool_idx`, the last 20 bytes of its pool
+/// hash.
+pub fn pool_address(base_token: Address, quote_token: Address, pool_idx: u64) -> Address {
+    Address::from_word(keccak256(
+        (base_token, quote_token, U256::from(pool_idx)).abi_encode(),
+    ))
+}
+
+/// Returns the address of the pool a swap or liquidity log of the dex applies to, `None` for other logs.
+pub fn pool_address_from_log(log: &Log) -> Option<Address> {
+    let event_signature = *log.topics().first()?;
+
+    let (base, quote, pool_idx) = if event_signature == ICrocSwapDex::CrocSwap::SIGNATURE_HASH {
+        let swap_event = ICrocSwapDex::CrocSwap::decode_log(log.as_ref(), true).ok()?;
+        (swap_event.base, swap_event.quote, swap_event.poolIdx)
+    } else if event_signature == ICrocSwapDex::CrocHotCmd::SIGNATURE_HASH {
+        let hot_event = ICrocSwapDex::CrocHotCmd::decode_log(log.as_ref(), true).ok()?;
+        let input = HotSwapInput::abi_decode(&hot_event.input, true).ok()?;
+        (input.base, input.quote, input.poolIdx)
+    } else if event_signature == ICrocSwapDex::CrocWarmCmd::SIGNATURE_HASH {
+        let warm_event = ICrocSwapDex::CrocWarmCmd::decode_log(log.as_ref(), true).ok()?;
+        let input = WarmCmdInput::abi_decode(&warm_event.input, true).ok()?;
+        (input.base, input.quote, input.poolIdx)
+    } else {
+        return None;
+    };
+
+    Some(pool_address(base, quote, pool_idx.saturating_to()))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Bytes, Log as PrimitiveLog, U256},
+        rpc::types::eth::Log,
+        sol_types::{SolEvent, SolValue},
+    };
+    use uniswap_v3_math::tick_math;
+
+    use crate::amm::AutomatedMarketMaker;
+
+    use super::{pool_address_from_log, AmbientPool, ICrocSwapDex, WarmCmdInput, LOT_SIZE};
+
+    const DEX: Address = address!("AaAaAAAaA24eEeb8d57D431224f73832bC34f688");
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+    /// ETH/USDC pool at tick 0 with ambient liquidity and a range around the price.
+    fn pool() -> AmbientPool {
+        let mut pool = AmbientPool::new(Address::ZERO, USDC, 420, DEX, Address::ZERO);
+        pool.sqrt_price = tick_math::get_sqrt_ratio_at_tick(0).unwrap();
+        pool.tick = 0;
+        pool.tick_size = 16;
+        pool.fee = 500;
+        pool.ambient_liquidity = 1_000_000_000_000_000_000;
+        pool.concentrated_liquidity = 1_024_000_000_000_000_000;
+
+        let (window, _) = AmbientPool::level_ticks(pool.tick, pool.tick_size);
+        let lots = 1_000_000_000_000_000;
+        pool.apply_levels(window, [(-160, lots, 0), (160, 0, lots)]);
+
+        pool
+    }
+
+    fn warm_cmd_log(code: u8, base_flow: i128, quote_flow: i128) -> Log {
+        let input = WarmCmdInput {
+            code,
+            base: Address::ZERO,
+            quote: USDC,
+            poolIdx: U256::from(420),
+            bidTick: -32,
+            askTick: 32,
+            liq: 1_024_000,
+            limitLower: 0,
+            limitHigher: u128::MAX,
+            reserveFlags: 0,
+            lpConduit: Address::ZERO,
+        };
+
+        Log {
+            inner: PrimitiveLog {
+                address: DEX,
+                data: ICrocSwapDex::CrocWarmCmd {
+                    input: Bytes::from(input.abi_encode()),
+                    baseFlow: base_flow,
+                    quoteFlow: quote_flow,
+                }
+                .encode_log_data(),
+            },
+            block_number: Some(20_000_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_simulate_swap() {
+        let mut pool = pool();
+        assert_eq!(pool.liquidity(), 2_024_000_000_000_000_000);
+
+        // At tick 0 one base token is worth one quote token, minus the 0.05% fee
+        let amount_in = U256::from(1_000_000_000_000_000_u64);
+        let amount_out = pool.simulate_swap(Address::ZERO, amount_in).unwrap();
+        assert!(amount_out < amount_in * U256::from(9_995) / U256::from(10_000));
+        assert!(amount_out > amount_in * U256::from(9_985) / U256::from(10_000));
+        assert_eq!(pool.get_token_out(Address::ZERO), USDC);
+
+        // Crossing the bid tick of the range leaves the ambient liquidity
+        let amount_in = U256::from(100_000_000_000_000_000_u64);
+        pool.simulate_swap_mut(Address::ZERO, amount_in).unwrap();
+        assert!(pool.tick < -160);
+        assert_eq!(pool.concentrated_liquidity, 0);
+        assert_eq!(pool.liquidity(), pool.ambient_liquidity);
+
+        // Swaps leaving the loaded levels fail
+        assert!(pool
+            .simulate_swap(Address::ZERO, U256::from(10).pow(U256::from(30)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_sync_from_log() {
+        let mut pool = pool();
+        let liquidity = pool.liquidity();
+
+        // Mint and burn of concentrated liquidity in lots
+        let log = warm_cmd_log(1, 1, 1);
+        assert_eq!(pool_address_from_log(&log), Some(pool.address));
+        pool.sync_from_log(log).unwrap();
+        assert_eq!(pool.liquidity(), liquidity + 1_000 * LOT_SIZE);
+        assert_eq!(pool.ticks[&-32].liquidity_net, 1_024_000);
+
+        pool.sync_from_log(warm_cmd_log(2, -1, -1)).unwrap();
+        assert_eq!(pool.liquidity(), liquidity);
+        assert!(!pool.ticks.contains_key(&-32));
+
+        // Mint of ambient liquidity in quote tokens
+        pool.sync_from_log(warm_cmd_log(32, 1_000_000, 1_000_000))
+            .unwrap();
+        assert_eq!(pool.ambient_liquidity, 1_000_000_000_001_000_000);
+
+        // Logs of other pools are ignored
+        let mut other_pool = AmbientPool::new(Address::ZERO, USDC, 36_000, DEX, Address::ZERO);
+        other_pool.sync_from_log(warm_cmd_log(3, 1, 1)).unwrap();
+        assert_eq!(other_pool.ambient_liquidity, 0);
+    }
+}
@@ -0,0 +1,533 @@
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+use alloy::{
+    primitives::{address, Address, B256, U256},
+    rpc::types::eth::Log,
+    sol,
+    sol_types::SolEvent,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{multicall, AMM},
+    errors::{AMMError, ErrorContext, ResultExt},
+};
+use crate::{
+    amm::{AutomatedMarketMaker, Protocol},
+    errors::{ArithmeticError, EventLogError, SwapSimulationError},
+};
+
+sol! {
+    /// Interface of the Bancor V3 network, which emits the trades of all pools
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract IBancorNetwork {
+        event TokensTraded(bytes32 indexed contextId, address indexed sourceToken, address indexed targetToken, uint256 sourceAmount, uint256 targetAmount, uint256 bntAmount, uint256 targetFeeAmount, uint256 bntFeeAmount, address trader);
+    }
+}
+
+sol! {
+    /// Interface of the Bancor V3 pool collection
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract IBancorPoolCollection {
+        struct TradingLiquidity {
+            uint128 bntTradingLiquidity;
+            uint128 baseTokenTradingLiquidity;
+        }
+
+        function pools() external view returns (address[] memory);
+        function poolToken(address pool) external view returns (address);
+        function tradingLiquidity(address pool) external view returns (TradingLiquidity memory);
+        function tradingFeePPM(address pool) external view returns (uint32);
+        function tradingEnabled(address pool) external view returns (bool);
+    }
+}
+
+sol! {
+    /// Interface of the Bancor V3 network settings
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract IBancorNetworkSettings {
+        function networkFeePPM() external view returns (uint32);
+    }
+}
+
+/// BNT, the token every Bancor V3 pool is paired with.
+pub const BNT: Address = address!("1F573D6Fb3F13d689FF844B4cE37794d79a7FF1C");
+pub const BNT_DECIMALS: u8 = 18;
+
+/// Denominator of the trading and network fees.
+pub const PPM_RESOLUTION: u32 = 1_000_000;
+
+/// Estimated gas used by a trade through the Bancor network, including the token transfers.
+pub const SWAP_GAS_ESTIMATE: u64 = 200_000;
+
+/// A Bancor V3 omnipool pool, trading its base token against BNT from the trading liquidity of the pool.
+///
+/// Trades between two base tokens go through BNT, in two pools. The pools are synced from the `TokensTraded` logs
+/// of the Bancor network, deposits and withdrawals change the trading liquidity without a trade and are picked up by
+/// [`AutomatedMarketMaker::sync`]. The native ETH pool is not supported, as ETH has no decimals to read.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BancorV3Pool {
+    /// Pool token (bnToken) of the pool.
+    pub address: Address,
+    pub base_token: Address,
+    pub base_token_decimals: u8,
+    pub bnt_trading_liquidity: u128,
+    pub base_token_trading_liquidity: u128,
+    /// Trading fee, see [`PPM_RESOLUTION`].
+    pub trading_fee_ppm: u32,
+    /// Share of the trading fee taken by the network, see [`PPM_RESOLUTION`].
+    pub network_fee_ppm: u32,
+    pub trading_enabled: bool,
+    /// Bancor network, the emitter of the `TokensTraded` logs.
+    pub network: Address,
+    pub pool_collection: Address,
+    pub network_settings: Address,
+}
+
+/// Outcome of a trade in a [`BancorV3Pool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Trade {
+    amount_out: U256,
+    bnt_trading_liquidity: u128,
+    base_token_trading_liquidity: u128,
+}
+
+#[async_trait]
+impl AutomatedMarketMaker for BancorV3Pool {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::BancorV3
+    }
+
+    fn fee_bps(&self) -> u32 {
+        self.trading_fee_ppm / 100
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        self.populate_data(None, provider)
+            .await
+            .context(ErrorContext::sync(self.address))?;
+        tracing::info!(bnt_trading_liquidity = ?self.bnt_trading_liquidity, base_token_trading_liquidity = ?self.base_token_trading_liquidity, address = ?self.address, "BancorV3 sync");
+
+        Ok(())
+    }
+
+    fn sync_on_event_signatures(&self) -> Vec<B256> {
+        vec![IBancorNetwork::TokensTraded::SIGNATURE_HASH]
+    }
+
+    /// Applies a `TokensTraded` log of the Bancor network, ignoring the trades of other pools.
+    #[instrument(skip(self), level = "debug")]
+    fn sync_from_log(&mut self, log: Log) -> Result<(), EventLogError> {
+        let event_signature = log.topics()[0];
+
+        if event_signature != IBancorNetwork::TokensTraded::SIGNATURE_HASH {
+            return Err(EventLogError::InvalidEventSignature);
+        }
+
+        let trade_event = IBancorNetwork::TokensTraded::decode_log(log.as_ref(), true)?;
+        if log.address() != self.network {
+            return Ok(());
+        }
+
+        // Replay the leg of the trade in this pool, the BNT amount is the output of the first leg of two leg trades
+        let leg = if trade_event.sourceToken == self.base_token {
+            Some((false, trade_event.sourceAmount))
+        } else if trade_event.targetToken == self.base_token {
+            if trade_event.sourceToken == BNT {
+                Some((true, trade_event.sourceAmount))
+            } else {
+                Some((true, trade_event.bntAmount))
+            }
+        } else {
+            None
+        };
+
+        if let Some((is_source_bnt, source_amount)) = leg {
+            match self.trade(is_source_bnt, source_amount) {
+                Ok(trade) => self.apply_trade(trade),
+                Err(error) => {
+                    tracing::warn!(?error, address = ?self.address, "Could not replay BancorV3 trade")
+                }
+            }
+            tracing::debug!(bnt_trading_liquidity = ?self.bnt_trading_liquidity, base_token_trading_liquidity = ?self.base_token_trading_liquidity, address = ?self.address, "BancorV3 trade event");
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn populate_data<T, N, P>(
+        &mut self,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut amms = [AMM::BancorV3Pool(self.clone())];
+        multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+            .await
+            .context(ErrorContext::populate(self.address, block_number))?;
+        if let [AMM::BancorV3Pool(pool)] = amms {
+            *self = pool;
+        }
+
+        Ok(())
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.base_token, BNT]
+    }
+
+    /// Returns the price of `base_token` at the trading liquidity of the pool.
+    fn calculate_price(&self, base_token: Address) -> Result<f64, ArithmeticError> {
+        if self.bnt_trading_liquidity == 0 || self.base_token_trading_liquidity == 0 {
+            return Err(ArithmeticError::YIsZero);
+        }
+
+        let bnt = self.bnt_trading_liquidity as f64 / 10f64.powi(BNT_DECIMALS as i32);
+        let base =
+            self.base_token_trading_liquidity as f64 / 10f64.powi(self.base_token_decimals as i32);
+
+        if base_token == self.base_token {
+            Ok(bnt / base)
+        } else {
+            Ok(base / bnt)
+        }
+    }
+
+    fn simulate_swap(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        Ok(self.trade(token_in == BNT, amount_in)?.amount_out)
+    }
+
+    fn simulate_swap_mut(
+        &mut self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let trade = self.trade(token_in == BNT, amount_in)?;
+        self.apply_trade(trade);
+
+        Ok(trade.amount_out)
+    }
+
+    fn get_token_out(&self, token_in: Address) -> Address {
+        if token_in == BNT {
+            self.base_token
+        } else {
+            BNT
+        }
+    }
+
+    fn swap_gas_estimate(&self, _token_in: Address, _amount_in: U256) -> u64 {
+        SWAP_GAS_ESTIMATE
+    }
+}
+
+impl BancorV3Pool {
+    /// Returns an unpopulated pool of `base_token`, see [`AutomatedMarketMaker::populate_data`].
+    pub fn new(
+        base_token: Address,
+        network: Address,
+        pool_collection: Address,
+        network_settings: Address,
+    ) -> Self {
+        BancorV3Pool {
+            base_token,
+            network,
+            pool_collection,
+            network_settings,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "provider")]
+    pub async fn new_from_base_token<T, N, P>(
+        base_token: Address,
+        network: Address,
+        pool_collection: Address,
+        network_settings: Address,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut pool = BancorV3Pool::new(base_token, network, pool_collection, network_settings);
+        pool.populate_data(None, provider).await?;
+
+        if !pool.data_is_populated() {
+            return Err(AMMError::PoolDataError);
+        }
+
+        Ok(pool)
+    }
+
+    /// Returns the base tokens of the pools of `pool_collection`.
+    #[cfg(feature = "provider")]
+    pub async fn get_base_tokens<T, N, P>(
+        pool_collection: Address,
+        provider: Arc<P>,
+    ) -> Result<Vec<Address>, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let pool_collection = IBancorPoolCollection::new(pool_collection, provider);
+
+        Ok(pool_collection.pools().call().await?._0)
+    }
+
+    pub fn data_is_populated(&self) -> bool {
+        !(self.address.is_zero()
+            || self.base_token.is_zero()
+            || self.bnt_trading_liquidity == 0
+            || self.base_token_trading_liquidity == 0)
+    }
+
+    /// Returns whether `log` is a trade of the Bancor network in this pool.
+    pub fn is_traded_in(&self, log: &Log) -> bool {
+        if log.address() != self.network {
+            return false;
+        }
+
+        let topics = log.topics();
+        topics.len() == 4
+            && topics[0] == IBancorNetwork::TokensTraded::SIGNATURE_HASH
+            && [topics[2], topics[3]].contains(&self.base_token.into_word())
+    }
+
+    /// Returns the outcome of a trade of `source_amount`, mirroring the trade processing of the pool collection.
+    ///
+    /// The network fee is a share of the trading fee, charged in the target token. When the target token is the base
+    /// token, the network fee is traded to BNT without fees.
+    fn trade(
+        &self,
+        is_source_bnt: bool,
+        source_amount: U256,
+    ) -> Result<Trade, SwapSimulationError> {
+        let bnt = U256::from(self.bnt_trading_liquidity);
+        let base = U256::from(self.base_token_trading_liquidity);
+
+        if !self.trading_enabled || source_amount.is_zero() || bnt.is_zero() || base.is_zero() {
+            return Ok(Trade {
+                amount_out: U256::ZERO,
+                bnt_trading_liquidity: self.bnt_trading_liquidity,
+                base_token_trading_liquidity: self.base_token_trading_liquidity,
+            });
+        }
+
+        let (amount_out, bnt, base) = if is_source_bnt {
+            let (amount_out, trading_fee) =
+                trade_amount_and_fee(bnt, base, self.trading_fee_ppm, source_amount);
+            let network_fee = self.network_fee(trading_fee);
+
+            let bnt = bnt + source_amount;
+            let base = base - amount_out;
+            let (network_fee_bnt, _) = trade_amount_and_fee(base, bnt, 0, network_fee);
+
+            (
+                amount_out,
+                bnt.checked_sub(network_fee_bnt)
+                    .ok_or(SwapSimulationError::LiquidityUnderflow)?,
+                base + network_fee,
+            )
+        } else {
+            let (amount_out, trading_fee) =
+                trade_amount_and_fee(base, bnt, self.trading_fee_ppm, source_amount);
+
+            (
+                amount_out,
+                (bnt - amount_out)
+                    .checked_sub(self.network_fee(trading_fee))
+                    .ok_or(SwapSimulationError::LiquidityUnderflow)?,
+                base + source_amount,
+            )
+        };
+
+        Ok(Trade {
+            amount_out,
+            bnt_trading_liquidity: u128::try_from(bnt)
+                .map_err(|_| ArithmeticError::U128ConversionError)?,
+            base_token_trading_liquidity: u128::try_from(base)
+                .map_err(|_| ArithmeticError::U128ConversionError)?,
+        })
+    }
+
+    fn apply_trade(&mut self, trade: Trade) {
+        self.bnt_trading_liquidity = trade.bnt_trading_liquidity;
+        self.base_token_trading_liquidity = trade.base_token_trading_liquidity;
+    }
+
+    fn network_fee(&self, trading_fee: U256) -> U256 {
+        trading_fee * U256::from(self.network_fee_ppm) / U256::from(PPM_RESOLUTION)
+    }
+}
+
+/// Returns the target amount, net of the trading fee, and the trading fee of a trade of `source_amount` against the
+/// `source_balance` and `target_balance` trading liquidities.
+pub fn trade_amount_and_fee(
+    source_balance: U256,
+    target_balance: U256,
+    fee_ppm: u32,
+    source_amount: U256,
+) -> (U256, U256) {
+    let target_amount = target_balance * source_amount / (source_balance + source_amount);
+    let trading_fee = target_amount * U256::from(fee_ppm) / U256::from(PPM_RESOLUTION);
+
+    (target_amount - trading_fee, trading_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog, B256, U256},
+        rpc::types::eth::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::amm::AutomatedMarketMaker;
+
+    use super::{trade_amount_and_fee, BancorV3Pool, IBancorNetwork, BNT};
+
+    const NETWORK: Address = address!("eEF417e1D5CC832e619ae18D2F140De2999dD4fB");
+
+    fn pool(base_token: Address) -> BancorV3Pool {
+        BancorV3Pool {
+            address: Address::repeat_byte(0x01),
+            base_token_decimals: 18,
+            bnt_trading_liquidity: 2_000_000_000_000_000_000_000,
+            base_token_trading_liquidity: 1_000_000_000_000_000_000_000,
+            trading_fee_ppm: 2_000,
+            network_fee_ppm: 200_000,
+            trading_enabled: true,
+            ..BancorV3Pool::new(base_token, NETWORK, Address::ZERO, Address::ZERO)
+        }
+    }
+
+    fn trade_log(source_token: Address, target_token: Address, source_amount: U256) -> Log {
+        Log {
+            inner: PrimitiveLog {
+                address: NETWORK,
+                data: IBancorNetwork::TokensTraded {
+                    contextId: B256::ZERO,
+                    sourceToken: source_token,
+                    targetToken: target_token,
+                    sourceAmount: source_amount,
+                    targetAmount: U256::ZERO,
+                    bntAmount: source_amount,
+                    targetFeeAmount: U256::ZERO,
+                    bntFeeAmount: U256::ZERO,
+                    trader: Address::ZERO,
+                }
+                .encode_log_data(),
+            },
+            block_number: Some(20_000_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_trade_amount_and_fee() {
+        let (amount, fee) = trade_amount_and_fee(
+            U256::from(1_000),
+            U256::from(2_000),
+            10_000,
+            U256::from(1_000),
+        );
+
+        assert_eq!(amount, U256::from(990));
+        assert_eq!(fee, U256::from(10));
+    }
+
+    #[test]
+    fn test_simulate_swap() {
+        let link = address!("514910771AF9Ca656af840dff83E8264EcF986CA");
+        let mut pool = pool(link);
+        let amount_in = U256::from(1_000_000_000_000_000_000_u128);
+
+        // 1 LINK is worth about 2 BNT, minus the 0.2% trading fee
+        let amount_out = pool.simulate_swap(link, amount_in).unwrap();
+        assert!(amount_out < U256::from(1_996_000_000_000_000_000_u128));
+        assert!(amount_out > U256::from(1_990_000_000_000_000_000_u128));
+        assert_eq!(pool.get_token_out(link), BNT);
+
+        let bnt_trading_liquidity = pool.bnt_trading_liquidity;
+        assert_eq!(pool.simulate_swap_mut(link, amount_in).unwrap(), amount_out);
+        assert_eq!(
+            pool.base_token_trading_liquidity,
+            1_001_000_000_000_000_000_000
+        );
+
+        // The network fee leaves the trading liquidity along with the amount out
+        let network_fee =
+            bnt_trading_liquidity - pool.bnt_trading_liquidity - amount_out.to::<u128>();
+        assert!(network_fee > 0);
+
+        // Trades of BNT for the base token go the other way
+        assert!(pool.simulate_swap(BNT, amount_in).unwrap() < amount_in / U256::from(2));
+
+        pool.trading_enabled = false;
+        assert_eq!(pool.simulate_swap(link, amount_in).unwrap(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_sync_from_log() {
+        let link = address!("514910771AF9Ca656af840dff83E8264EcF986CA");
+        let dai = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+        let amount_in = U256::from(1_000_000_000_000_000_000_u128);
+
+        let mut link_pool = pool(link);
+        let mut expected = link_pool.clone();
+        expected.simulate_swap_mut(link, amount_in).unwrap();
+
+        // Two leg trade of LINK for DAI, through BNT
+        let log = trade_log(link, dai, amount_in);
+        assert!(link_pool.is_traded_in(&log));
+        link_pool.sync_from_log(log.clone()).unwrap();
+        assert_eq!(
+            link_pool.bnt_trading_liquidity,
+            expected.bnt_trading_liquidity
+        );
+        assert_eq!(
+            link_pool.base_token_trading_liquidity,
+            expected.base_token_trading_liquidity
+        );
+
+        // Trades of other pools are ignored
+        let mut eth_pool = pool(address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"));
+        assert!(!eth_pool.is_traded_in(&log));
+        let bnt_trading_liquidity = eth_pool.bnt_trading_liquidity;
+        eth_pool.sync_from_log(log).unwrap();
+        assert_eq!(eth_pool.bnt_trading_liquidity, bnt_trading_liquidity);
+    }
+}
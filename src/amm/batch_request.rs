@@ -0,0 +1,419 @@
+use std::{collections::VecDeque, ops::Range, sync::Arc};
+
+use alloy::{network::Network, providers::Provider, transports::Transport};
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{
+    errors::AMMError,
+    sync::config::{BatchStrategy, SyncConfig},
+};
+
+use super::{erc_4626, multicall, uniswap_v2, uniswap_v3, AMM};
+
+/// Populates the data of a heterogeneous set of AMMs via batched static calls.
+///
+/// Pools are grouped by type and each group is fetched in chunks through the deployless batch contract of its protocol,
+/// falling back to Multicall3 on chains that do not support deployless static calls. The config of the chain of the
+/// provider is used, see [`SyncConfig::for_chain`].
+/// If `block_number` is `None`, the data is fetched at the latest block.
+pub async fn populate_amms<T, N, P>(
+    amms: &mut [AMM],
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let config = SyncConfig::for_chain(provider.get_chain_id().await?);
+
+    populate_amms_with_config(amms, block_number, &config, provider).await
+}
+
+/// Populates the data of a heterogeneous set of AMMs, using the batch sizes, batch strategy and retry policy from `config`.
+///
+/// A chunk that fails because the response or the gas used by the batch call is too large is split in half and retried.
+pub async fn populate_amms_with_config<T, N, P>(
+    amms: &mut [AMM],
+    block_number: Option<u64>,
+    config: &SyncConfig,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    // Pin all groups to the same block so that the populated state is consistent
+    let block_number = match block_number {
+        Some(block_number) => block_number,
+        None => provider.get_block_number().await?,
+    };
+
+    let mut uniswap_v2_pools = vec![];
+    let mut uniswap_v3_pools = vec![];
+    let mut erc_4626_vaults = vec![];
+    let mut curve_v2_pools = vec![];
+    let mut rate_adapters = vec![];
+    let mut bancor_v3_pools = vec![];
+    let mut gmx_markets = vec![];
+    let mut ambient_pools = vec![];
+    let mut constant_product_pools = vec![];
+    for (idx, amm) in amms.iter().enumerate() {
+        match amm {
+            AMM::UniswapV2Pool(_) => uniswap_v2_pools.push(idx),
+            AMM::UniswapV3Pool(_) => uniswap_v3_pools.push(idx),
+            AMM::ERC4626Vault(_) => erc_4626_vaults.push(idx),
+            AMM::CurveV2Pool(_) => curve_v2_pools.push(idx),
+            AMM::RateAdapter(_) => rate_adapters.push(idx),
+            AMM::BancorV3Pool(_) => bancor_v3_pools.push(idx),
+            AMM::GmxMarket(_) => gmx_markets.push(idx),
+            AMM::AmbientPool(_) => ambient_pools.push(idx),
+            AMM::ConstantProductPool(_) => constant_product_pools.push(idx),
+        }
+    }
+
+    for (group, batch_size) in [
+        (uniswap_v2_pools, config.v2_batch_size),
+        (uniswap_v3_pools, config.v3_batch_size),
+        (erc_4626_vaults, config.erc_4626_batch_size),
+        (curve_v2_pools, config.curve_v2_batch_size),
+        (rate_adapters, config.rate_adapter_batch_size),
+        (bancor_v3_pools, config.bancor_v3_batch_size),
+        (gmx_markets, config.gmx_batch_size),
+        (ambient_pools, config.ambient_batch_size),
+        (constant_product_pools, config.constant_product_batch_size),
+    ] {
+        if group.is_empty() {
+            continue;
+        }
+
+        let mut group_amms = group
+            .iter()
+            .map(|idx| amms[*idx].clone())
+            .collect::<Vec<AMM>>();
+
+        populate_congruent_amms(
+            &mut group_amms,
+            block_number,
+            batch_size,
+            config,
+            provider.clone(),
+        )
+        .await?;
+
+        for (idx, amm) in group.into_iter().zip(group_amms) {
+            amms[idx] = amm;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(
+    feature = "tracing-spans",
+    tracing::instrument(skip(amms, config, provider), fields(amms = amms.len()), level = "debug")
+)]
+async fn populate_congruent_amms<T, N, P>(
+    amms: &mut [AMM],
+    block_number: u64,
+    batch_size: usize,
+    config: &SyncConfig,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let mut chunks = BatchChunks::new(amms.len(), batch_size);
+
+    #[cfg(feature = "metrics")]
+    let metrics = Metrics::new(provider.get_chain_id().await?);
+
+    while let Some(chunk) = chunks.next() {
+        let result = config
+            .retry
+            .retry_if(
+                || {
+                    // Each attempt populates a copy of the chunk, so a failed attempt leaves no partial state
+                    let mut chunk_amms = amms[chunk.clone()].to_vec();
+                    let provider = provider.clone();
+                    #[cfg(feature = "tracing-spans")]
+                    let chunk = chunk.clone();
+                    #[cfg(feature = "metrics")]
+                    let metrics = &metrics;
+
+                    async move {
+                        #[cfg(any(feature = "tracing-spans", feature = "metrics"))]
+                        let start = std::time::Instant::now();
+
+                        let result = get_amm_data_batch_request(
+                            &mut chunk_amms,
+                            block_number,
+                            config.batch_strategy,
+                            provider,
+                        )
+                        .await;
+
+                        #[cfg(feature = "tracing-spans")]
+                        tracing::debug!(
+                            ?chunk,
+                            size = chunk.len(),
+                            elapsed = ?start.elapsed(),
+                            ok = result.is_ok(),
+                            "Batch request"
+                        );
+
+                        #[cfg(feature = "metrics")]
+                        {
+                            metrics.record_batch_request_latency(
+                                crate::metrics::protocol(&chunk_amms[0]),
+                                start.elapsed(),
+                            );
+                            if result.is_err() {
+                                metrics.record_rpc_error("eth_call");
+                            }
+                        }
+
+                        result.map(|_| chunk_amms)
+                    }
+                },
+                // A chunk that is too large is split instead of retried
+                |err| chunk.len() <= 1 || !is_response_size_error(err),
+            )
+            .await;
+
+        match result {
+            Ok(chunk_amms) => {
+                for (amm, populated_amm) in amms[chunk].iter_mut().zip(chunk_amms) {
+                    *amm = populated_amm;
+                }
+            }
+
+            Err(err) if chunks.split(&chunk, &err) => {}
+
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+async fn get_amm_data_batch_request<T, N, P>(
+    amms: &mut [AMM],
+    block_number: u64,
+    batch_strategy: BatchStrategy,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    match batch_strategy {
+        BatchStrategy::Deployless => {
+            get_amm_data_deployless_batch_request(amms, block_number, provider).await
+        }
+
+        BatchStrategy::Multicall3 => {
+            multicall::get_amm_data_batch_request(amms, Some(block_number), provider).await
+        }
+
+        BatchStrategy::Individual => {
+            multicall::get_amm_data_individual_calls(amms, Some(block_number), provider).await
+        }
+
+        BatchStrategy::Auto => {
+            match get_amm_data_deployless_batch_request(amms, block_number, provider.clone()).await
+            {
+                // Split the chunk instead of falling back, Multicall3 would hit the same limits
+                Err(err) if !is_response_size_error(&err) => {
+                    tracing::debug!(
+                        ?err,
+                        "Deployless batch request failed, falling back to Multicall3"
+                    );
+                    multicall::get_amm_data_batch_request(amms, Some(block_number), provider).await
+                }
+                res => res,
+            }
+        }
+    }
+}
+
+async fn get_amm_data_deployless_batch_request<T, N, P>(
+    amms: &mut [AMM],
+    block_number: u64,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    match amms[0] {
+        AMM::UniswapV2Pool(_) => {
+            uniswap_v2::batch_request::get_amm_data_batch_request(
+                amms,
+                Some(block_number),
+                provider,
+            )
+            .await
+        }
+        AMM::UniswapV3Pool(_) => {
+            uniswap_v3::batch_request::get_amm_data_batch_request(amms, block_number, provider)
+                .await
+        }
+        AMM::ERC4626Vault(_) => {
+            erc_4626::batch_request::get_amm_data_batch_request(amms, Some(block_number), provider)
+                .await
+        }
+        // Curve V2 pools, rate adapters, Bancor V3 pools, GMX markets, Ambient pools and constant product pools have no
+        // deployless batch contract
+        AMM::CurveV2Pool(_)
+        | AMM::RateAdapter(_)
+        | AMM::BancorV3Pool(_)
+        | AMM::GmxMarket(_)
+        | AMM::AmbientPool(_)
+        | AMM::ConstantProductPool(_) => {
+            multicall::get_amm_data_batch_request(amms, Some(block_number), provider).await
+        }
+    }
+}
+
+/// Index ranges of a batch request, starting at a target chunk size.
+///
+/// Chunks that exceed the gas or response size limits of the node are split in half and requeued,
+/// so that providers with low `eth_call` limits do not fail the whole batch.
+#[derive(Debug, Clone)]
+pub struct BatchChunks {
+    chunks: VecDeque<Range<usize>>,
+}
+
+impl BatchChunks {
+    /// Splits `0..len` into chunks of `chunk_size` items.
+    pub fn new(len: usize, chunk_size: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+        let chunks = (0..len)
+            .step_by(chunk_size)
+            .map(|start| start..(start + chunk_size).min(len))
+            .collect();
+
+        Self { chunks }
+    }
+
+    /// Splits `chunk` in half if it failed with a response size error and holds more than one item.
+    ///
+    /// Returns whether the chunk was split. Both halves are requested before the remaining chunks.
+    pub fn split(&mut self, chunk: &Range<usize>, err: &AMMError) -> bool {
+        if chunk.len() <= 1 || !is_response_size_error(err) {
+            return false;
+        }
+
+        tracing::warn!(?chunk, "Batch request too large, splitting chunk");
+
+        let mid = chunk.start + chunk.len() / 2;
+        self.chunks.push_front(mid..chunk.end);
+        self.chunks.push_front(chunk.start..mid);
+
+        true
+    }
+}
+
+impl Iterator for BatchChunks {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.pop_front()
+    }
+}
+
+/// Returns whether the error was caused by a batch call exceeding the gas or response size limits of the node.
+pub fn is_response_size_error(err: &AMMError) -> bool {
+    let message = match err {
+        AMMError::TransportError(err) => err.to_string(),
+        AMMError::ContractError(err) => err.to_string(),
+        _ => return false,
+    }
+    .to_lowercase();
+
+    [
+        "out of gas",
+        "gas required exceeds",
+        "exceeds block gas limit",
+        "response size",
+        "too large",
+        "limit exceeded",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{primitives::address, providers::ProviderBuilder, transports::TransportErrorKind};
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, AMM},
+        errors::AMMError,
+    };
+
+    use super::{populate_amms, BatchChunks};
+
+    #[test]
+    fn test_batch_chunks_split() {
+        let mut chunks = BatchChunks::new(10, 4);
+        let response_size_error = AMMError::TransportError(TransportErrorKind::custom_str(
+            "execution reverted: out of gas",
+        ));
+
+        let chunk = chunks.next().unwrap();
+        assert_eq!(chunk, 0..4);
+
+        // Only response size errors split the chunk
+        assert!(!chunks.split(&chunk, &AMMError::PoolDataError));
+        assert!(chunks.split(&chunk, &response_size_error));
+
+        assert_eq!(chunks.next(), Some(0..2));
+        assert_eq!(chunks.next(), Some(2..4));
+
+        // A single item can not be split further
+        assert!(!chunks.split(&(2..3), &response_size_error));
+
+        assert_eq!(chunks.collect::<Vec<_>>(), vec![4..8, 8..10]);
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_populate_heterogeneous_amms() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let mut amms = vec![
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+                ..Default::default()
+            }),
+            AMM::UniswapV3Pool(UniswapV3Pool {
+                address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+                ..Default::default()
+            }),
+        ];
+
+        populate_amms(&mut amms, None, provider).await.unwrap();
+
+        for amm in amms.iter() {
+            assert_eq!(
+                amm.tokens(),
+                vec![
+                    address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+                    address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2")
+                ]
+            );
+        }
+    }
+}
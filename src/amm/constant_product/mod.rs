@@ -0,0 +1,399 @@
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+use alloy::{
+    primitives::{Address, FixedBytes, B256, U256},
+    rpc::types::eth::Log,
+    sol_types::{SolCall, SolEvent},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{multicall, AMM},
+    errors::{AMMError, ErrorContext, ResultExt},
+};
+use crate::{
+    amm::{uniswap_v2::IUniswapV2Pair, AutomatedMarketMaker, Protocol},
+    core::{
+        price::{self, q64_to_f64},
+        uniswap_v2 as math,
+    },
+    errors::{ArithmeticError, EventLogError, SwapSimulationError},
+};
+
+/// Estimated gas used by a pair swap, including the token transfer out.
+pub const SWAP_GAS_ESTIMATE: u64 = 70_000;
+
+/// Parameters of a constant product fork, so that forks that only differ from Uniswap V2 in their fee, reserve
+/// events or reserve getters can be added from a config file.
+///
+/// The default config is the one of Uniswap V2 pairs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstantProductConfig {
+    /// Swap fee in basis points, including admin fees taken from the input.
+    pub fee_bps: u32,
+    /// Signature of the event emitted with the new reserves of the pool, `Sync(uint112,uint112)` for Uniswap V2.
+    pub sync_event_signature: B256,
+    /// Indices of the 32 byte words of reserve 0 and reserve 1 in the data of the sync event.
+    pub sync_event_reserve_words: (usize, usize),
+    /// Selector of the reserves getter, `getReserves()` for Uniswap V2.
+    pub reserves_selector: FixedBytes<4>,
+    /// Indices of the 32 byte words of reserve 0 and reserve 1 in the return data of the reserves getter.
+    pub reserves_return_words: (usize, usize),
+}
+
+impl Default for ConstantProductConfig {
+    fn default() -> Self {
+        ConstantProductConfig {
+            fee_bps: 30,
+            sync_event_signature: IUniswapV2Pair::Sync::SIGNATURE_HASH,
+            sync_event_reserve_words: (0, 1),
+            reserves_selector: IUniswapV2Pair::getReservesCall::SELECTOR.into(),
+            reserves_return_words: (0, 1),
+        }
+    }
+}
+
+impl ConstantProductConfig {
+    /// Returns the reserves in the data of a sync event, `None` if the data is too short or a reserve does not fit in
+    /// a `u128`.
+    pub fn decode_sync_event(&self, data: &[u8]) -> Option<(u128, u128)> {
+        decode_reserves(data, self.sync_event_reserve_words)
+    }
+
+    /// Returns the reserves in the return data of the reserves getter, `None` if the data is too short or a reserve
+    /// does not fit in a `u128`.
+    pub fn decode_reserves_return(&self, data: &[u8]) -> Option<(u128, u128)> {
+        decode_reserves(data, self.reserves_return_words)
+    }
+}
+
+/// A Uniswap V2 style pair of a fork described by a [`ConstantProductConfig`].
+///
+/// Pools are populated through Multicall3 with the `token0()` and `token1()` getters of Uniswap V2 pairs and the
+/// reserves getter of the config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConstantProductPool {
+    pub address: Address,
+    pub token_a: Address,
+    pub token_a_decimals: u8,
+    pub token_b: Address,
+    pub token_b_decimals: u8,
+    pub reserve_0: u128,
+    pub reserve_1: u128,
+    pub config: ConstantProductConfig,
+    /// Factory that created the pool, if it is known.
+    #[serde(default)]
+    pub factory: Option<Address>,
+}
+
+#[async_trait]
+impl AutomatedMarketMaker for ConstantProductPool {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::ConstantProduct
+    }
+
+    fn fee_bps(&self) -> u32 {
+        self.config.fee_bps
+    }
+
+    fn factory(&self) -> Option<Address> {
+        self.factory
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        self.populate_data(None, provider)
+            .await
+            .context(ErrorContext::sync(self.address))?;
+        tracing::info!(reserve_0 = self.reserve_0, reserve_1 = self.reserve_1, address = ?self.address, "ConstantProduct sync");
+
+        Ok(())
+    }
+
+    fn sync_on_event_signatures(&self) -> Vec<B256> {
+        vec![self.config.sync_event_signature]
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn sync_from_log(&mut self, log: Log) -> Result<(), EventLogError> {
+        let event_signature = log.topics()[0];
+
+        if event_signature == self.config.sync_event_signature {
+            let (reserve_0, reserve_1) = self
+                .config
+                .decode_sync_event(&log.data().data)
+                .ok_or(EventLogError::InvalidEventData)?;
+            tracing::info!(reserve_0, reserve_1, address = ?self.address, "ConstantProduct sync event");
+
+            self.reserve_0 = reserve_0;
+            self.reserve_1 = reserve_1;
+
+            Ok(())
+        } else {
+            Err(EventLogError::InvalidEventSignature)
+        }
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn populate_data<T, N, P>(
+        &mut self,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut amms = [AMM::ConstantProductPool(self.clone())];
+        multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+            .await
+            .context(ErrorContext::populate(self.address, block_number))?;
+        if let [AMM::ConstantProductPool(pool)] = amms {
+            *self = pool;
+        }
+
+        Ok(())
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.token_a, self.token_b]
+    }
+
+    fn calculate_price(&self, base_token: Address) -> Result<f64, ArithmeticError> {
+        let price = if base_token == self.token_a {
+            price::reserves_to_price_64_x_64(
+                self.reserve_0,
+                self.reserve_1,
+                self.token_a_decimals,
+                self.token_b_decimals,
+            )?
+        } else {
+            price::reserves_to_price_64_x_64(
+                self.reserve_1,
+                self.reserve_0,
+                self.token_b_decimals,
+                self.token_a_decimals,
+            )?
+        };
+
+        Ok(q64_to_f64(price))
+    }
+
+    fn simulate_swap(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let (reserve_in, reserve_out) = self.reserves(token_in);
+
+        Ok(self.get_amount_out(amount_in, reserve_in, reserve_out))
+    }
+
+    fn simulate_swap_mut(
+        &mut self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let (reserve_in, reserve_out) = self.reserves(token_in);
+        let amount_out = self.get_amount_out(amount_in, reserve_in, reserve_out);
+
+        let reserve_in = (reserve_in + amount_in).to::<u128>();
+        let reserve_out = (reserve_out - amount_out).to::<u128>();
+        if self.token_a == token_in {
+            (self.reserve_0, self.reserve_1) = (reserve_in, reserve_out);
+        } else {
+            (self.reserve_1, self.reserve_0) = (reserve_in, reserve_out);
+        }
+
+        Ok(amount_out)
+    }
+
+    fn get_token_out(&self, token_in: Address) -> Address {
+        if self.token_a == token_in {
+            self.token_b
+        } else {
+            self.token_a
+        }
+    }
+
+    fn swap_gas_estimate(&self, _token_in: Address, _amount_in: U256) -> u64 {
+        SWAP_GAS_ESTIMATE
+    }
+}
+
+impl ConstantProductPool {
+    /// Returns an unpopulated pool, see [`AutomatedMarketMaker::populate_data`].
+    pub fn new(address: Address, config: ConstantProductConfig) -> Self {
+        ConstantProductPool {
+            address,
+            config,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "provider")]
+    /// Creates a new instance of the pool from the pair address, and syncs the pool data.
+    pub async fn new_from_address<T, N, P>(
+        address: Address,
+        config: ConstantProductConfig,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut pool = ConstantProductPool::new(address, config);
+        pool.populate_data(None, provider).await?;
+
+        if !pool.data_is_populated() {
+            return Err(AMMError::PoolDataError);
+        }
+
+        Ok(pool)
+    }
+
+    /// Returns whether the pool data is populated.
+    pub fn data_is_populated(&self) -> bool {
+        !(self.token_a.is_zero()
+            || self.token_b.is_zero()
+            || self.reserve_0 == 0
+            || self.reserve_1 == 0)
+    }
+
+    /// Calculates the amount received for a given `amount_in` `reserve_in` and `reserve_out`.
+    pub fn get_amount_out(&self, amount_in: U256, reserve_in: U256, reserve_out: U256) -> U256 {
+        math::get_amount_out_bps(amount_in, reserve_in, reserve_out, self.config.fee_bps)
+    }
+
+    fn reserves(&self, token_in: Address) -> (U256, U256) {
+        if self.token_a == token_in {
+            (U256::from(self.reserve_0), U256::from(self.reserve_1))
+        } else {
+            (U256::from(self.reserve_1), U256::from(self.reserve_0))
+        }
+    }
+}
+
+/// Returns the 32 byte words of `data` at the indices of `words` as reserves.
+fn decode_reserves(data: &[u8], words: (usize, usize)) -> Option<(u128, u128)> {
+    let word = |index: usize| {
+        let bytes = data.get(index * 32..(index + 1) * 32)?;
+        u128::try_from(U256::from_be_slice(bytes)).ok()
+    };
+
+    Some((word(words.0)?, word(words.1)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, keccak256, Address, Bytes, Log as PrimitiveLog, LogData, U256},
+        rpc::types::eth::Log,
+    };
+
+    use crate::amm::{uniswap_v2::UniswapV2Pool, AutomatedMarketMaker};
+
+    use super::{ConstantProductConfig, ConstantProductPool};
+
+    const TOKEN_A: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const TOKEN_B: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    fn usdc_weth_pool(config: ConstantProductConfig) -> ConstantProductPool {
+        ConstantProductPool {
+            token_a: TOKEN_A,
+            token_a_decimals: 6,
+            token_b: TOKEN_B,
+            token_b_decimals: 18,
+            reserve_0: 47_092_140_895_915,
+            reserve_1: 28_396_598_565_590_008_529_300,
+            ..ConstantProductPool::new(Address::ZERO, config)
+        }
+    }
+
+    #[test]
+    fn test_simulate_swap() {
+        // The default config quotes like a Uniswap V2 pair
+        let mut pool = usdc_weth_pool(ConstantProductConfig::default());
+        let uniswap_v2_pool = UniswapV2Pool {
+            token_a: TOKEN_A,
+            token_b: TOKEN_B,
+            reserve_0: pool.reserve_0,
+            reserve_1: pool.reserve_1,
+            fee: 300,
+            ..Default::default()
+        };
+
+        let amount_in = U256::from(1_000_000_000_u64);
+        let amount_out = pool.simulate_swap(TOKEN_A, amount_in).unwrap();
+        assert_eq!(
+            amount_out,
+            uniswap_v2_pool.simulate_swap(TOKEN_A, amount_in).unwrap()
+        );
+
+        pool.simulate_swap_mut(TOKEN_A, amount_in).unwrap();
+        assert_eq!(pool.reserve_0, 47_092_140_895_915 + 1_000_000_000);
+        assert_eq!(
+            U256::from(pool.reserve_1),
+            U256::from(28_396_598_565_590_008_529_300_u128) - amount_out
+        );
+
+        // Lower fees give more tokens out
+        let cheap_pool = usdc_weth_pool(ConstantProductConfig {
+            fee_bps: 25,
+            ..Default::default()
+        });
+        assert!(cheap_pool.simulate_swap(TOKEN_A, amount_in).unwrap() > amount_out);
+    }
+
+    #[test]
+    fn test_sync_from_log() {
+        // Fork emitting `Sync(uint256 reserve0, uint256 reserve1, uint256 fee)` with the fee first
+        let signature = keccak256("Sync(uint256,uint256,uint256)");
+        let mut pool = usdc_weth_pool(ConstantProductConfig {
+            sync_event_signature: signature,
+            sync_event_reserve_words: (1, 2),
+            ..Default::default()
+        });
+        assert_eq!(pool.sync_on_event_signatures(), vec![signature]);
+
+        let data = [U256::from(25), U256::from(1_000), U256::from(2_000)]
+            .iter()
+            .flat_map(|word| word.to_be_bytes::<32>())
+            .collect::<Vec<u8>>();
+        let log = Log {
+            inner: PrimitiveLog {
+                address: pool.address,
+                data: LogData::new_unchecked(vec![signature], Bytes::from(data)),
+            },
+            block_number: Some(20_000_000),
+            ..Default::default()
+        };
+        pool.sync_from_log(log.clone()).unwrap();
+        assert_eq!((pool.reserve_0, pool.reserve_1), (1_000, 2_000));
+
+        // Truncated data is rejected
+        let mut truncated_log = log;
+        truncated_log.inner.data =
+            LogData::new_unchecked(vec![signature], Bytes::from(vec![0; 64]));
+        assert!(pool.sync_from_log(truncated_log).is_err());
+    }
+}
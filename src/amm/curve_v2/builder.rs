@@ -0,0 +1,174 @@
+use alloy::primitives::{Address, U256};
+
+use crate::errors::PoolBuilderError;
+
+use super::{math::FEE_DENOMINATOR, CurveV2Pool};
+
+/// Builder for a [`CurveV2Pool`].
+///
+/// `address`, `token_a`, `token_b`, the price scale and `a` and `gamma` are required. The invariant is computed from
+/// the balances on build.
+#[derive(Debug, Clone, Default)]
+pub struct CurveV2PoolBuilder {
+    address: Option<Address>,
+    token_a: Option<Address>,
+    token_a_decimals: u8,
+    token_b: Option<Address>,
+    token_b_decimals: u8,
+    balance_0: U256,
+    balance_1: U256,
+    price_scale: Option<U256>,
+    a_gamma: Option<(U256, U256)>,
+    mid_fee: U256,
+    out_fee: U256,
+    fee_gamma: U256,
+    factory: Option<Address>,
+}
+
+impl CurveV2PoolBuilder {
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    pub fn token_a(mut self, token_a: Address, decimals: u8) -> Self {
+        self.token_a = Some(token_a);
+        self.token_a_decimals = decimals;
+        self
+    }
+
+    pub fn token_b(mut self, token_b: Address, decimals: u8) -> Self {
+        self.token_b = Some(token_b);
+        self.token_b_decimals = decimals;
+        self
+    }
+
+    pub fn balances(mut self, balance_0: U256, balance_1: U256) -> Self {
+        self.balance_0 = balance_0;
+        self.balance_1 = balance_1;
+        self
+    }
+
+    /// Sets the price of token b in token a, with 18 decimals.
+    pub fn price_scale(mut self, price_scale: U256) -> Self {
+        self.price_scale = Some(price_scale);
+        self
+    }
+
+    /// Sets the amplification coefficient, multiplied by `N_COINS**N_COINS * A_MULTIPLIER`, and gamma.
+    pub fn a_gamma(mut self, a: U256, gamma: U256) -> Self {
+        self.a_gamma = Some((a, gamma));
+        self
+    }
+
+    /// Sets the fees in units of [`FEE_DENOMINATOR`] and the fee gamma with 18 decimals.
+    pub fn fees(mut self, mid_fee: U256, out_fee: U256, fee_gamma: U256) -> Self {
+        self.mid_fee = mid_fee;
+        self.out_fee = out_fee;
+        self.fee_gamma = fee_gamma;
+        self
+    }
+
+    pub fn factory(mut self, factory: Address) -> Self {
+        self.factory = Some(factory);
+        self
+    }
+
+    /// Validates the configuration and builds the pool.
+    pub fn build(self) -> Result<CurveV2Pool, PoolBuilderError> {
+        let address = self
+            .address
+            .ok_or(PoolBuilderError::MissingField("address"))?;
+        let token_a = self
+            .token_a
+            .ok_or(PoolBuilderError::MissingField("token_a"))?;
+        let token_b = self
+            .token_b
+            .ok_or(PoolBuilderError::MissingField("token_b"))?;
+        let price_scale = self
+            .price_scale
+            .ok_or(PoolBuilderError::MissingField("price_scale"))?;
+        let (a, gamma) = self
+            .a_gamma
+            .ok_or(PoolBuilderError::MissingField("a_gamma"))?;
+
+        if token_a == token_b {
+            return Err(PoolBuilderError::IdenticalTokens);
+        }
+
+        for fee in [self.mid_fee, self.out_fee] {
+            if fee > FEE_DENOMINATOR {
+                return Err(PoolBuilderError::InvalidFee(fee.saturating_to()));
+            }
+        }
+
+        let mut pool = CurveV2Pool {
+            address,
+            token_a,
+            token_a_decimals: self.token_a_decimals,
+            token_b,
+            token_b_decimals: self.token_b_decimals,
+            balance_0: self.balance_0,
+            balance_1: self.balance_1,
+            price_scale,
+            a,
+            gamma,
+            mid_fee: self.mid_fee,
+            out_fee: self.out_fee,
+            fee_gamma: self.fee_gamma,
+            factory: self.factory,
+            ..Default::default()
+        };
+
+        // An empty pool has no invariant yet
+        if !(pool.balance_0.is_zero() && pool.balance_1.is_zero()) {
+            pool.d = pool
+                .calculate_d()
+                .map_err(|_| PoolBuilderError::InvalidInvariant)?;
+        }
+
+        Ok(pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, U256};
+
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let e18 = U256::from(1_000_000_000_000_000_000_u64);
+
+        let builder = CurveV2Pool::builder()
+            .address(Address::repeat_byte(0x01))
+            .token_a(usdc, 6)
+            .token_b(weth, 18)
+            .price_scale(U256::from(4_000) * e18)
+            .a_gamma(U256::from(400_000), U256::from(145_000_000_000_000_u64))
+            .fees(
+                U256::from(26_000_000),
+                U256::from(45_000_000),
+                U256::from(230_000_000_000_000_u64),
+            );
+
+        let pool = builder
+            .clone()
+            .balances(U256::from(4_000_000_000_000_u64), U256::from(1_000) * e18)
+            .build()
+            .unwrap();
+        assert_eq!(pool.d, U256::from(8_000_000) * e18);
+
+        // Balances far from the price scale are outside of the safe range of the invariant
+        assert_eq!(
+            builder
+                .balances(U256::from(4_000_000_000_000_u64), e18 / U256::from(100))
+                .build()
+                .unwrap_err(),
+            PoolBuilderError::InvalidInvariant
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Invariant math of two coin Curve V2 pools, ported from the Curve CryptoSwap contracts.
+//!
+//! Balances are in the internal representation of the pool (`xp`), i.e. scaled to 18 decimals and, for the second
+//! coin, multiplied by the price scale.
+
+use alloy::primitives::U256;
+
+use crate::errors::ArithmeticError;
+
+pub const N_COINS: usize = 2;
+/// Multiplier of the amplification coefficient, on top of `N_COINS**N_COINS`.
+pub const A_MULTIPLIER: U256 = U256::from_limbs([10_000, 0, 0, 0]);
+pub const PRECISION: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+/// Denominator of the fees.
+pub const FEE_DENOMINATOR: U256 = U256::from_limbs([10_000_000_000, 0, 0, 0]);
+
+const MAX_ITERATIONS: usize = 255;
+const N: U256 = U256::from_limbs([N_COINS as u64, 0, 0, 0]);
+const E14: U256 = U256::from_limbs([100_000_000_000_000, 0, 0, 0]);
+const E16: U256 = U256::from_limbs([10_000_000_000_000_000, 0, 0, 0]);
+const E20: U256 = U256::from_limbs([7_766_279_631_452_241_920, 5, 0, 0]);
+
+/// Solves the invariant for `D` given the balances `x`, with Newton's method.
+pub fn newton_d(ann: U256, gamma: U256, x: [U256; N_COINS]) -> Result<U256, ArithmeticError> {
+    let [x_0, x_1] = if x[0] < x[1] { [x[1], x[0]] } else { x };
+    if x_1.is_zero() || ann.is_zero() || gamma.is_zero() {
+        return Err(ArithmeticError::UnsafeInvariantValues);
+    }
+
+    let s = x_0 + x_1;
+    let mut d = N * (x_0 * x_1).root(2);
+    let g1k0_base = gamma + PRECISION;
+
+    for _ in 0..MAX_ITERATIONS {
+        let d_prev = d;
+        if d.is_zero() {
+            return Err(ArithmeticError::UnsafeInvariantValues);
+        }
+
+        let k0 = PRECISION * N * N * x_0 / d * x_1 / d;
+        if k0.is_zero() {
+            return Err(ArithmeticError::UnsafeInvariantValues);
+        }
+
+        let g1k0 = abs_diff(g1k0_base, k0) + U256::from(1);
+
+        // D / (A * N**N) * g1k0**2 / gamma**2
+        let mul1 = PRECISION * d / gamma * g1k0 / gamma * g1k0 * A_MULTIPLIER / ann;
+        // 2 * N * K0 / g1k0
+        let mul2 = PRECISION * U256::from(2) * N * k0 / g1k0;
+
+        let neg_fprime = (s + s * mul2 / PRECISION + mul1 * N / k0)
+            .checked_sub(mul2 * d / PRECISION)
+            .filter(|neg_fprime| !neg_fprime.is_zero())
+            .ok_or(ArithmeticError::UnsafeInvariantValues)?;
+
+        // D -= f / fprime
+        let d_plus = d * (neg_fprime + s) / neg_fprime;
+        let mut d_minus = d * d / neg_fprime;
+        let correction = d * (mul1 / neg_fprime) / PRECISION * abs_diff(PRECISION, k0) / k0;
+        if PRECISION > k0 {
+            d_minus += correction;
+        } else {
+            d_minus = d_minus.saturating_sub(correction);
+        }
+
+        d = if d_plus > d_minus {
+            d_plus - d_minus
+        } else {
+            (d_minus - d_plus) / U256::from(2)
+        };
+
+        if abs_diff(d, d_prev) * E14 < E16.max(d) {
+            for x in [x_0, x_1] {
+                check_frac(x * PRECISION / d)?;
+            }
+
+            return Ok(d);
+        }
+    }
+
+    Err(ArithmeticError::InvariantDidNotConverge)
+}
+
+/// Solves the invariant for the balance of coin `i` given `D` and the balance of the other coin, with Newton's method.
+pub fn newton_y(
+    ann: U256,
+    gamma: U256,
+    x: [U256; N_COINS],
+    d: U256,
+    i: usize,
+) -> Result<U256, ArithmeticError> {
+    let x_j = x[1 - i];
+    if x_j.is_zero() || d.is_zero() || ann.is_zero() || gamma.is_zero() {
+        return Err(ArithmeticError::UnsafeInvariantValues);
+    }
+
+    let mut y = d * d / (x_j * N * N);
+    let k0_i = PRECISION * N * x_j / d;
+    let convergence_limit = (x_j / E14).max(d / E14).max(U256::from(100));
+    let g1k0_base = gamma + PRECISION;
+
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        let k0 = k0_i * y * N / d;
+        if y.is_zero() || k0.is_zero() {
+            return Err(ArithmeticError::UnsafeInvariantValues);
+        }
+
+        let s = x_j + y;
+        let g1k0 = abs_diff(g1k0_base, k0) + U256::from(1);
+
+        // D / (A * N**N) * g1k0**2 / gamma**2
+        let mul1 = PRECISION * d / gamma * g1k0 / gamma * g1k0 * A_MULTIPLIER / ann;
+        // 2 * K0 / g1k0
+        let mul2 = PRECISION + PRECISION * U256::from(2) * k0 / g1k0;
+
+        let yfprime = PRECISION * y + s * mul2 + mul1;
+        let dyfprime = d * mul2;
+        if yfprime < dyfprime {
+            y = y_prev / U256::from(2);
+            continue;
+        }
+
+        let yfprime = yfprime - dyfprime;
+        let fprime = yfprime / y;
+        if fprime.is_zero() {
+            return Err(ArithmeticError::UnsafeInvariantValues);
+        }
+
+        // y -= f / fprime
+        let mut y_minus = mul1 / fprime;
+        let y_plus = (yfprime + PRECISION * d) / fprime + y_minus * PRECISION / k0;
+        y_minus += PRECISION * s / fprime;
+
+        y = if y_plus < y_minus {
+            y_prev / U256::from(2)
+        } else {
+            y_plus - y_minus
+        };
+
+        if abs_diff(y, y_prev) < convergence_limit.max(y / E14) {
+            check_frac(y * PRECISION / d)?;
+
+            return Ok(y);
+        }
+    }
+
+    Err(ArithmeticError::InvariantDidNotConverge)
+}
+
+/// Returns the fee of a pool with balances `xp`, in units of [`FEE_DENOMINATOR`].
+///
+/// The fee ramps from `mid_fee` when the pool is balanced to `out_fee` as it moves away from balance, at a rate set
+/// by `fee_gamma`.
+pub fn fee(xp: [U256; N_COINS], mid_fee: U256, out_fee: U256, fee_gamma: U256) -> U256 {
+    let s = xp[0] + xp[1];
+    if s.is_zero() {
+        return mid_fee;
+    }
+
+    let k = PRECISION * N * N * xp[0] / s * xp[1] / s;
+    let f = fee_gamma * PRECISION / (fee_gamma + PRECISION - k.min(PRECISION));
+
+    (mid_fee * f + out_fee * (PRECISION - f.min(PRECISION))) / PRECISION
+}
+
+fn check_frac(frac: U256) -> Result<(), ArithmeticError> {
+    if frac < E16 || frac > E20 {
+        return Err(ArithmeticError::UnsafeInvariantValues);
+    }
+
+    Ok(())
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::{abs_diff, fee, newton_d, newton_y, E14, E20, PRECISION};
+
+    #[test]
+    fn test_invariant() {
+        let ann = U256::from(400_000);
+        let gamma = U256::from(145_000_000_000_000_u64);
+        let balance = U256::from(1_000_000) * PRECISION;
+
+        assert_eq!(E20, U256::from(100) * PRECISION);
+
+        // The invariant of a balanced pool is the sum of its balances
+        let d = newton_d(ann, gamma, [balance, balance]).unwrap();
+        assert_eq!(d, balance * U256::from(2));
+
+        // Solving for a balance at the invariant returns the balance
+        let y = newton_y(ann, gamma, [balance, balance], d, 1).unwrap();
+        assert!(abs_diff(y, balance) <= balance / E14);
+
+        // Adding to one balance removes less from the other one
+        let dx = U256::from(1_000) * PRECISION;
+        let y = newton_y(ann, gamma, [balance + dx, balance], d, 1).unwrap();
+        assert!(y < balance);
+        assert!(balance - y < dx);
+        assert!(balance - y > dx * U256::from(999) / U256::from(1_000));
+    }
+
+    #[test]
+    fn test_fee() {
+        let mid_fee = U256::from(26_000_000);
+        let out_fee = U256::from(45_000_000);
+        let fee_gamma = U256::from(230_000_000_000_000_u64);
+        let balance = U256::from(1_000_000) * PRECISION;
+
+        assert_eq!(
+            fee([balance, balance], mid_fee, out_fee, fee_gamma),
+            mid_fee
+        );
+
+        let imbalanced_fee = fee(
+            [balance, balance / U256::from(2)],
+            mid_fee,
+            out_fee,
+            fee_gamma,
+        );
+        assert!(imbalanced_fee > mid_fee);
+        assert!(imbalanced_fee <= out_fee);
+    }
+}
@@ -0,0 +1,666 @@
+pub mod builder;
+pub mod math;
+
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+use alloy::{
+    primitives::{Address, Bytes, B256, U256},
+    rpc::types::eth::Log,
+    sol,
+    sol_types::{SolCall, SolEvent},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{multicall, AMM},
+    errors::{AMMError, ErrorContext, ResultExt},
+};
+use crate::{
+    amm::{AutomatedMarketMaker, Protocol, SwapParams},
+    core::price::u256_to_f64,
+    errors::{ArithmeticError, EventLogError, ExecutionError, SwapSimulationError},
+};
+
+use self::math::{FEE_DENOMINATOR, PRECISION};
+
+sol! {
+    /// Interface of the two coin Curve V2 (twocrypto-ng) pool
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract ICurveV2Pool {
+        event TokenExchange(address indexed buyer, uint256 sold_id, uint256 tokens_sold, uint256 bought_id, uint256 tokens_bought, uint256 fee, uint256 price_scale);
+        event AddLiquidity(address indexed provider, uint256[2] token_amounts, uint256 fee, uint256 token_supply, uint256 price_scale);
+        event RemoveLiquidity(address indexed provider, uint256[2] token_amounts, uint256 token_supply);
+        event RemoveLiquidityOne(address indexed provider, uint256 token_amount, uint256 coin_index, uint256 coin_amount, uint256 approx_fee, uint256 price_scale);
+        event ClaimAdminFee(address indexed admin, uint256[2] tokens);
+        event NewParameters(uint256 mid_fee, uint256 out_fee, uint256 fee_gamma, uint256 allowed_extra_profit, uint256 adjustment_step, uint256 ma_time, uint256 xcp_ma_time);
+        event RampAgamma(uint256 initial_A, uint256 future_A, uint256 initial_gamma, uint256 future_gamma, uint256 initial_time, uint256 future_time);
+        event StopRampA(uint256 current_A, uint256 current_gamma, uint256 time);
+        function coins(uint256 i) external view returns (address);
+        function balances(uint256 i) external view returns (uint256);
+        function price_scale() external view returns (uint256);
+        function D() external view returns (uint256);
+        function A() external view returns (uint256);
+        function gamma() external view returns (uint256);
+        function mid_fee() external view returns (uint256);
+        function out_fee() external view returns (uint256);
+        function fee_gamma() external view returns (uint256);
+        function get_dy(uint256 i, uint256 j, uint256 dx) external view returns (uint256);
+        function exchange(uint256 i, uint256 j, uint256 dx, uint256 min_dy, address receiver) external returns (uint256);
+    }
+}
+
+/// Estimated gas used by an exchange, including the token transfers.
+pub const SWAP_GAS_ESTIMATE: u64 = 180_000;
+
+/// A two coin Curve V2 (CryptoSwap) pool, whose liquidity is concentrated around a moving price scale.
+///
+/// Balances and the price scale are synced from the logs of the pool, and the invariant `D` is recomputed from them
+/// after every log. The price scale is not moved by local swap simulations, it is updated by the next exchange log.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CurveV2Pool {
+    pub address: Address,
+    pub token_a: Address,
+    pub token_a_decimals: u8,
+    pub token_b: Address,
+    pub token_b_decimals: u8,
+    /// Balance of token a held by the pool.
+    pub balance_0: U256,
+    /// Balance of token b held by the pool.
+    pub balance_1: U256,
+    /// Price of token b in token a that the liquidity is concentrated around, with 18 decimals and normalized to 18
+    /// token decimals.
+    pub price_scale: U256,
+    /// Invariant of the pool.
+    pub d: U256,
+    /// Amplification coefficient, multiplied by `N_COINS**N_COINS * A_MULTIPLIER`.
+    pub a: U256,
+    /// Curvature of the invariant, with 18 decimals.
+    pub gamma: U256,
+    /// Fee of a balanced pool, see [`math::FEE_DENOMINATOR`].
+    pub mid_fee: U256,
+    /// Fee of an imbalanced pool, see [`math::FEE_DENOMINATOR`].
+    pub out_fee: U256,
+    /// Rate at which the fee ramps from `mid_fee` to `out_fee` as the pool gets imbalanced, with 18 decimals.
+    pub fee_gamma: U256,
+    /// Ramp of `a` and `gamma` in progress, if any.
+    #[serde(default)]
+    pub ramp: Option<AGammaRamp>,
+    #[serde(default)]
+    pub factory: Option<Address>,
+}
+
+/// Ramp of the amplification coefficient and gamma of a [`CurveV2Pool`], from a `RampAgamma` log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AGammaRamp {
+    pub initial_a: U256,
+    pub initial_gamma: U256,
+    pub initial_time: u64,
+    pub future_a: U256,
+    pub future_gamma: U256,
+    pub future_time: u64,
+}
+
+impl AGammaRamp {
+    /// Returns the amplification coefficient and gamma at `timestamp`, interpolated linearly along the ramp.
+    pub fn a_gamma(&self, timestamp: u64) -> (U256, U256) {
+        if timestamp >= self.future_time {
+            return (self.future_a, self.future_gamma);
+        }
+
+        let timestamp = timestamp.max(self.initial_time);
+        let duration = U256::from(self.future_time - self.initial_time);
+        let elapsed = U256::from(timestamp - self.initial_time);
+        let remaining = duration - elapsed;
+
+        (
+            (self.initial_a * remaining + self.future_a * elapsed) / duration,
+            (self.initial_gamma * remaining + self.future_gamma * elapsed) / duration,
+        )
+    }
+}
+
+#[async_trait]
+impl AutomatedMarketMaker for CurveV2Pool {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::CurveV2
+    }
+
+    /// Returns the fee of a balanced pool, the fee grows up to `out_fee` as the pool gets imbalanced.
+    fn fee_bps(&self) -> u32 {
+        (self.mid_fee * U256::from(10_000) / FEE_DENOMINATOR).saturating_to()
+    }
+
+    fn factory(&self) -> Option<Address> {
+        self.factory
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        self.populate_data(None, provider)
+            .await
+            .context(ErrorContext::sync(self.address))?;
+        tracing::info!(balance_0 = ?self.balance_0, balance_1 = ?self.balance_1, price_scale = ?self.price_scale, address = ?self.address, "CurveV2 sync");
+
+        Ok(())
+    }
+
+    fn sync_on_event_signatures(&self) -> Vec<B256> {
+        vec![
+            ICurveV2Pool::TokenExchange::SIGNATURE_HASH,
+            ICurveV2Pool::AddLiquidity::SIGNATURE_HASH,
+            ICurveV2Pool::RemoveLiquidity::SIGNATURE_HASH,
+            ICurveV2Pool::RemoveLiquidityOne::SIGNATURE_HASH,
+            ICurveV2Pool::ClaimAdminFee::SIGNATURE_HASH,
+            ICurveV2Pool::NewParameters::SIGNATURE_HASH,
+            ICurveV2Pool::RampAgamma::SIGNATURE_HASH,
+            ICurveV2Pool::StopRampA::SIGNATURE_HASH,
+        ]
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn sync_from_log(&mut self, log: Log) -> Result<(), EventLogError> {
+        let event_signature = log.topics()[0];
+
+        if event_signature == ICurveV2Pool::TokenExchange::SIGNATURE_HASH {
+            let exchange_event = ICurveV2Pool::TokenExchange::decode_log(log.as_ref(), true)?;
+
+            self.apply_amounts(
+                coin_amounts(exchange_event.sold_id, exchange_event.tokens_sold),
+                coin_amounts(exchange_event.bought_id, exchange_event.tokens_bought),
+            )?;
+            self.price_scale = exchange_event.price_scale;
+        } else if event_signature == ICurveV2Pool::AddLiquidity::SIGNATURE_HASH {
+            let add_liquidity_event = ICurveV2Pool::AddLiquidity::decode_log(log.as_ref(), true)?;
+
+            self.apply_amounts(add_liquidity_event.token_amounts, [U256::ZERO; 2])?;
+            self.price_scale = add_liquidity_event.price_scale;
+        } else if event_signature == ICurveV2Pool::RemoveLiquidity::SIGNATURE_HASH {
+            let remove_liquidity_event =
+                ICurveV2Pool::RemoveLiquidity::decode_log(log.as_ref(), true)?;
+
+            self.apply_amounts([U256::ZERO; 2], remove_liquidity_event.token_amounts)?;
+        } else if event_signature == ICurveV2Pool::RemoveLiquidityOne::SIGNATURE_HASH {
+            let remove_liquidity_event =
+                ICurveV2Pool::RemoveLiquidityOne::decode_log(log.as_ref(), true)?;
+
+            self.apply_amounts(
+                [U256::ZERO; 2],
+                coin_amounts(
+                    remove_liquidity_event.coin_index,
+                    remove_liquidity_event.coin_amount,
+                ),
+            )?;
+            self.price_scale = remove_liquidity_event.price_scale;
+        } else if event_signature == ICurveV2Pool::ClaimAdminFee::SIGNATURE_HASH {
+            let claim_event = ICurveV2Pool::ClaimAdminFee::decode_log(log.as_ref(), true)?;
+
+            self.apply_amounts([U256::ZERO; 2], claim_event.tokens)?;
+        } else if event_signature == ICurveV2Pool::NewParameters::SIGNATURE_HASH {
+            let parameters_event = ICurveV2Pool::NewParameters::decode_log(log.as_ref(), true)?;
+
+            self.mid_fee = parameters_event.mid_fee;
+            self.out_fee = parameters_event.out_fee;
+            self.fee_gamma = parameters_event.fee_gamma;
+        } else if event_signature == ICurveV2Pool::RampAgamma::SIGNATURE_HASH {
+            let ramp_event = ICurveV2Pool::RampAgamma::decode_log(log.as_ref(), true)?;
+
+            self.a = ramp_event.initial_A;
+            self.gamma = ramp_event.initial_gamma;
+            self.ramp = Some(AGammaRamp {
+                initial_a: ramp_event.initial_A,
+                initial_gamma: ramp_event.initial_gamma,
+                initial_time: ramp_event.initial_time.saturating_to(),
+                future_a: ramp_event.future_A,
+                future_gamma: ramp_event.future_gamma,
+                future_time: ramp_event.future_time.saturating_to(),
+            });
+        } else if event_signature == ICurveV2Pool::StopRampA::SIGNATURE_HASH {
+            let stop_ramp_event = ICurveV2Pool::StopRampA::decode_log(log.as_ref(), true)?;
+
+            self.a = stop_ramp_event.current_A;
+            self.gamma = stop_ramp_event.current_gamma;
+            self.ramp = None;
+        } else {
+            return Err(EventLogError::InvalidEventSignature);
+        }
+
+        // Keep the previous invariant if the pool was emptied
+        if let Ok(d) = self.calculate_d() {
+            self.d = d;
+        }
+        tracing::debug!(balance_0 = ?self.balance_0, balance_1 = ?self.balance_1, price_scale = ?self.price_scale, d = ?self.d, address = ?self.address, "CurveV2 event");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn populate_data<T, N, P>(
+        &mut self,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut amms = [AMM::CurveV2Pool(self.clone())];
+        multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+            .await
+            .context(ErrorContext::populate(self.address, block_number))?;
+        if let [AMM::CurveV2Pool(pool)] = amms {
+            *self = pool;
+        }
+
+        Ok(())
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.token_a, self.token_b]
+    }
+
+    /// Returns the price of `base_token` at the price scale of the pool.
+    fn calculate_price(&self, base_token: Address) -> Result<f64, ArithmeticError> {
+        if self.price_scale.is_zero() {
+            return Err(ArithmeticError::YIsZero);
+        }
+
+        let price_scale = u256_to_f64(self.price_scale) / u256_to_f64(PRECISION);
+        if base_token == self.token_a {
+            Ok(1.0 / price_scale)
+        } else {
+            Ok(price_scale)
+        }
+    }
+
+    fn simulate_swap(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let (i, j) = self.coin_indices(token_in);
+
+        self.get_dy(i, j, amount_in)
+    }
+
+    fn simulate_swap_mut(
+        &mut self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let (i, j) = self.coin_indices(token_in);
+        let amount_out = self.get_dy(i, j, amount_in)?;
+
+        *self.balance_mut(U256::from(i)) += amount_in;
+        *self.balance_mut(U256::from(j)) -= amount_out;
+        self.d = self.calculate_d()?;
+
+        Ok(amount_out)
+    }
+
+    fn get_token_out(&self, token_in: Address) -> Address {
+        if self.token_a == token_in {
+            self.token_b
+        } else {
+            self.token_a
+        }
+    }
+
+    fn swap_gas_estimate(&self, _token_in: Address, _amount_in: U256) -> u64 {
+        SWAP_GAS_ESTIMATE
+    }
+
+    /// Returns the calldata of `exchange` on the pool, which pulls `amount_in` from the caller with `transferFrom`
+    /// and reverts below `amount_out_min`.
+    fn encode_swap(&self, params: SwapParams) -> Result<Bytes, ExecutionError> {
+        if params.token_in != self.token_a && params.token_in != self.token_b {
+            return Err(ExecutionError::TokenNotInPool(
+                params.token_in,
+                self.address,
+            ));
+        }
+
+        let (i, j) = self.coin_indices(params.token_in);
+
+        Ok(ICurveV2Pool::exchangeCall {
+            i: U256::from(i),
+            j: U256::from(j),
+            dx: params.amount_in,
+            min_dy: params.amount_out_min,
+            receiver: params.recipient,
+        }
+        .abi_encode()
+        .into())
+    }
+}
+
+impl CurveV2Pool {
+    /// Returns a builder for the pool.
+    pub fn builder() -> builder::CurveV2PoolBuilder {
+        builder::CurveV2PoolBuilder::default()
+    }
+
+    #[cfg(feature = "provider")]
+    pub async fn new_from_address<T, N, P>(
+        pair_address: Address,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut pool = CurveV2Pool {
+            address: pair_address,
+            ..Default::default()
+        };
+
+        pool.populate_data(None, provider).await?;
+
+        if !pool.data_is_populated() {
+            return Err(AMMError::PoolDataError);
+        }
+
+        Ok(pool)
+    }
+
+    pub fn data_is_populated(&self) -> bool {
+        !(self.token_a.is_zero()
+            || self.token_b.is_zero()
+            || self.balance_0.is_zero()
+            || self.balance_1.is_zero()
+            || self.price_scale.is_zero()
+            || self.d.is_zero())
+    }
+
+    /// Moves `a` and `gamma` along the ramp in progress to their values at `timestamp`, e.g. the timestamp of the
+    /// latest block, and recomputes the invariant.
+    pub fn advance_ramp(&mut self, timestamp: u64) -> Result<(), ArithmeticError> {
+        let Some(ramp) = self.ramp else {
+            return Ok(());
+        };
+
+        (self.a, self.gamma) = ramp.a_gamma(timestamp);
+        if timestamp >= ramp.future_time {
+            self.ramp = None;
+        }
+        self.d = self.calculate_d()?;
+
+        Ok(())
+    }
+
+    /// Returns the balances of the pool in its internal representation, scaled to 18 decimals and, for token b, to
+    /// the price of token a.
+    pub fn xp(&self, balances: [U256; 2]) -> [U256; 2] {
+        let [precision_0, precision_1] = self.precisions();
+
+        [
+            balances[0] * precision_0,
+            balances[1] * precision_1 * self.price_scale / PRECISION,
+        ]
+    }
+
+    /// Recomputes the invariant from the balances and price scale of the pool.
+    pub fn calculate_d(&self) -> Result<U256, ArithmeticError> {
+        math::newton_d(
+            self.a,
+            self.gamma,
+            self.xp([self.balance_0, self.balance_1]),
+        )
+    }
+
+    /// Returns the amount of coin `j` received for `dx` of coin `i`, net of the dynamic fee.
+    pub fn get_dy(&self, i: usize, j: usize, dx: U256) -> Result<U256, SwapSimulationError> {
+        if dx.is_zero() || self.balance_0.is_zero() || self.balance_1.is_zero() {
+            return Ok(U256::ZERO);
+        }
+
+        let mut balances = [self.balance_0, self.balance_1];
+        balances[i] += dx;
+
+        let mut xp = self.xp(balances);
+        let y = math::newton_y(self.a, self.gamma, xp, self.d, j)?;
+        let Some(dy) = xp[j].checked_sub(y + U256::from(1)) else {
+            return Ok(U256::ZERO);
+        };
+        xp[j] = y;
+
+        let [precision_0, precision_1] = self.precisions();
+        let dy = if j > 0 {
+            dy * PRECISION / (self.price_scale * precision_1)
+        } else {
+            dy / precision_0
+        };
+        let fee = math::fee(xp, self.mid_fee, self.out_fee, self.fee_gamma) * dy / FEE_DENOMINATOR;
+
+        Ok(dy - fee)
+    }
+
+    /// Returns the indices of `token_in` and the token out.
+    fn coin_indices(&self, token_in: Address) -> (usize, usize) {
+        if self.token_a == token_in {
+            (0, 1)
+        } else {
+            (1, 0)
+        }
+    }
+
+    fn balance_mut(&mut self, index: U256) -> &mut U256 {
+        if index.is_zero() {
+            &mut self.balance_0
+        } else {
+            &mut self.balance_1
+        }
+    }
+
+    /// Adds `amounts_in` to and subtracts `amounts_out` from the balances, leaving them unchanged if the balances
+    /// would overflow or underflow.
+    fn apply_amounts(
+        &mut self,
+        amounts_in: [U256; 2],
+        amounts_out: [U256; 2],
+    ) -> Result<(), EventLogError> {
+        let apply = |balance: U256, amount_in: U256, amount_out: U256| {
+            balance
+                .checked_add(amount_in)
+                .ok_or(EventLogError::ReserveOverflow)?
+                .checked_sub(amount_out)
+                .ok_or(EventLogError::ReserveUnderflow)
+        };
+
+        let balance_0 = apply(self.balance_0, amounts_in[0], amounts_out[0])?;
+        let balance_1 = apply(self.balance_1, amounts_in[1], amounts_out[1])?;
+        self.balance_0 = balance_0;
+        self.balance_1 = balance_1;
+
+        Ok(())
+    }
+
+    fn precisions(&self) -> [U256; 2] {
+        [self.token_a_decimals, self.token_b_decimals]
+            .map(|decimals| U256::from(10).pow(U256::from(18_u8.saturating_sub(decimals))))
+    }
+}
+
+/// Returns the amounts of the coins of a pool with `amount` of the coin at `index`.
+fn coin_amounts(index: U256, amount: U256) -> [U256; 2] {
+    let mut amounts = [U256::ZERO; 2];
+    amounts[usize::from(!index.is_zero())] = amount;
+    amounts
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog, U256},
+        rpc::types::eth::Log,
+        sol_types::{SolCall, SolEvent},
+    };
+
+    use crate::{
+        amm::{AutomatedMarketMaker, SwapParams},
+        errors::EventLogError,
+    };
+
+    use super::{CurveV2Pool, ICurveV2Pool};
+
+    fn pool() -> CurveV2Pool {
+        let mut pool = CurveV2Pool {
+            address: Address::repeat_byte(0x01),
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_a_decimals: 6,
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            token_b_decimals: 18,
+            balance_0: U256::from(4_000_000_000_000_u64),
+            balance_1: U256::from(1_000) * U256::from(1_000_000_000_000_000_000_u64),
+            price_scale: U256::from(4_000) * U256::from(1_000_000_000_000_000_000_u64),
+            a: U256::from(400_000),
+            gamma: U256::from(145_000_000_000_000_u64),
+            mid_fee: U256::from(26_000_000),
+            out_fee: U256::from(45_000_000),
+            fee_gamma: U256::from(230_000_000_000_000_u64),
+            ..Default::default()
+        };
+        pool.d = pool.calculate_d().unwrap();
+
+        pool
+    }
+
+    #[test]
+    fn test_simulate_swap() {
+        let pool = pool();
+        assert!(pool.data_is_populated());
+        assert_eq!(pool.fee_bps(), 26);
+        assert_eq!(pool.calculate_price(pool.token_b).unwrap(), 4_000.0);
+
+        // 4_000 USDC buys a little less than 1 WETH, charged the mid fee of a balanced pool
+        let amount_out = pool
+            .simulate_swap(pool.token_a, U256::from(4_000_000_000_u64))
+            .unwrap();
+        let one_weth = U256::from(1_000_000_000_000_000_000_u64);
+        assert!(amount_out < one_weth * U256::from(9_974) / U256::from(10_000));
+        assert!(amount_out > one_weth * U256::from(9_960) / U256::from(10_000));
+
+        let mut pool_after = pool.clone();
+        assert_eq!(
+            pool_after
+                .simulate_swap_mut(pool.token_a, U256::from(4_000_000_000_u64))
+                .unwrap(),
+            amount_out
+        );
+        assert_eq!(pool_after.balance_1, pool.balance_1 - amount_out);
+
+        // The pool is now imbalanced towards USDC, so WETH buys more USDC than before
+        let usdc_out = pool_after.simulate_swap(pool.token_b, one_weth).unwrap();
+        let usdc_out_before = pool.simulate_swap(pool.token_b, one_weth).unwrap();
+        assert!(usdc_out > usdc_out_before);
+    }
+
+    #[test]
+    fn test_sync_from_log() {
+        let mut pool = pool();
+        let price_scale = U256::from(4_100) * U256::from(1_000_000_000_000_000_000_u64);
+
+        let address = pool.address;
+        let log = |data| Log {
+            inner: PrimitiveLog { address, data },
+            block_number: Some(20_000_000),
+            ..Default::default()
+        };
+
+        let exchange_log = log(ICurveV2Pool::TokenExchange {
+            buyer: Address::ZERO,
+            sold_id: U256::from(0),
+            tokens_sold: U256::from(4_000_000_000_u64),
+            bought_id: U256::from(1),
+            tokens_bought: U256::from(997_000_000_000_000_000_u64),
+            fee: U256::ZERO,
+            price_scale,
+        }
+        .encode_log_data());
+        pool.sync_from_log(exchange_log).unwrap();
+
+        assert_eq!(pool.balance_0, U256::from(4_004_000_000_000_u64));
+        assert_eq!(pool.balance_1, U256::from(999_003_000_000_000_000_000_u128));
+        assert_eq!(pool.price_scale, price_scale);
+        assert_eq!(pool.d, pool.calculate_d().unwrap());
+
+        let ramp_log = log(ICurveV2Pool::RampAgamma {
+            initial_A: U256::from(400_000),
+            future_A: U256::from(800_000),
+            initial_gamma: pool.gamma,
+            future_gamma: pool.gamma,
+            initial_time: U256::from(1_000),
+            future_time: U256::from(2_000),
+        }
+        .encode_log_data());
+        pool.sync_from_log(ramp_log).unwrap();
+
+        assert_eq!(
+            pool.ramp.unwrap().a_gamma(1_500),
+            (U256::from(600_000), pool.gamma)
+        );
+        pool.advance_ramp(1_500).unwrap();
+        assert_eq!(pool.a, U256::from(600_000));
+        assert!(pool.ramp.is_some());
+        pool.advance_ramp(3_000).unwrap();
+        assert_eq!(pool.a, U256::from(800_000));
+        assert!(pool.ramp.is_none());
+
+        // Removing more than the balance of a coin is rejected, leaving the balances unchanged
+        let (balance_0, balance_1) = (pool.balance_0, pool.balance_1);
+        let remove_log = log(ICurveV2Pool::RemoveLiquidityOne {
+            provider: Address::ZERO,
+            token_amount: U256::from(1),
+            coin_index: U256::from(1),
+            coin_amount: balance_1 + U256::from(1),
+            approx_fee: U256::ZERO,
+            price_scale,
+        }
+        .encode_log_data());
+        assert!(matches!(
+            pool.sync_from_log(remove_log),
+            Err(EventLogError::ReserveUnderflow)
+        ));
+        assert_eq!((pool.balance_0, pool.balance_1), (balance_0, balance_1));
+    }
+
+    #[test]
+    fn test_encode_swap() {
+        let pool = pool();
+        let recipient = Address::repeat_byte(0x02);
+
+        let calldata = pool
+            .encode_swap(SwapParams {
+                token_in: pool.token_b,
+                amount_in: U256::from(1_000),
+                amount_out_min: U256::from(900),
+                recipient,
+                ..Default::default()
+            })
+            .unwrap();
+        let call = ICurveV2Pool::exchangeCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!((call.i, call.j), (U256::from(1), U256::ZERO));
+        assert_eq!(call.dx, U256::from(1_000));
+        assert_eq!(call.min_dy, U256::from(900));
+        assert_eq!(call.receiver, recipient);
+    }
+}
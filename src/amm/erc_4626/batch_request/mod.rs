@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use crate::{amm::AutomatedMarketMaker, errors::AMMError};
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    errors::AMMError,
+};
 
 use alloy::{
     dyn_abi::{DynSolType, DynSolValue},
@@ -32,29 +35,10 @@ fn populate_pool_data_from_tokens(
     let withdraw_fee_delta_2 = tokens[10].as_uint()?.0;
     let withdraw_no_fee = tokens[11].as_uint()?.0;
 
-    // If both deltas are zero, the fee is zero
-    if deposit_fee_delta_1.is_zero() && deposit_fee_delta_2.is_zero() {
-        vault.deposit_fee = 0;
-    // Assuming 18 decimals, if the delta of 1e20 is half the delta of 2e20, relative fee.
-    // Delta / (amount without fee / 10000) to give us the fee in basis points
-    } else if deposit_fee_delta_1 * U256::from(2) == deposit_fee_delta_2 {
-        vault.deposit_fee = (deposit_fee_delta_1 / (deposit_no_fee / U256::from(10_000))).to();
-    } else {
-        // If not a relative fee or zero, ignore vault
-        return None;
-    }
-
-    // If both deltas are zero, the fee is zero
-    if withdraw_fee_delta_1.is_zero() && withdraw_fee_delta_2.is_zero() {
-        vault.withdraw_fee = 0;
-    // Assuming 18 decimals, if the delta of 1e20 is half the delta of 2e20, relative fee.
-    // Delta / (amount without fee / 10000) to give us the fee in basis points
-    } else if withdraw_fee_delta_1 * U256::from(2) == withdraw_fee_delta_2 {
-        vault.withdraw_fee = (withdraw_fee_delta_1 / (withdraw_no_fee / U256::from(10_000))).to();
-    } else {
-        // If not a relative fee or zero, ignore vault
-        return None;
-    }
+    // If not a relative fee or zero, ignore vault
+    vault.deposit_fee = fee_from_deltas(deposit_fee_delta_1, deposit_fee_delta_2, deposit_no_fee)?;
+    vault.withdraw_fee =
+        fee_from_deltas(withdraw_fee_delta_1, withdraw_fee_delta_2, withdraw_no_fee)?;
 
     // if above does not error => populate the vault
     vault.vault_token = tokens[0].as_address()?;
@@ -67,6 +51,27 @@ fn populate_pool_data_from_tokens(
     Some(vault)
 }
 
+/// Computes the fee in basis points from the fee deltas of 100 and 200 tokens,
+/// returning `None` if the fee is not relative to the amount, or if the amount without fee is too small to express
+/// the fee in basis points.
+pub(crate) fn fee_from_deltas(delta_1: U256, delta_2: U256, no_fee: U256) -> Option<u32> {
+    // If both deltas are zero, the fee is zero
+    if delta_1.is_zero() && delta_2.is_zero() {
+        Some(0)
+    // Assuming 18 decimals, if the delta of 1e20 is half the delta of 2e20, relative fee.
+    // Delta / (amount without fee / 10000) to give us the fee in basis points
+    } else if delta_1 * U256::from(2) == delta_2 {
+        let no_fee_bps = no_fee / U256::from(10_000);
+        if no_fee_bps.is_zero() {
+            return None;
+        }
+
+        (delta_1 / no_fee_bps).try_into().ok()
+    } else {
+        None
+    }
+}
+
 pub async fn get_4626_vault_data_batch_request<T, N, P>(
     vault: &mut ERC4626Vault,
     provider: Arc<P>,
@@ -109,3 +114,94 @@ where
 
     Ok(())
 }
+
+pub async fn get_amm_data_batch_request<T, N, P>(
+    amms: &mut [AMM],
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let mut target_addresses = vec![];
+    for amm in amms.iter() {
+        target_addresses.push(amm.address());
+    }
+
+    let deployer = IGetERC4626VaultDataBatchRequest::deploy_builder(provider, target_addresses);
+    let res = if let Some(block_number) = block_number {
+        deployer.block(block_number.into()).call_raw().await?
+    } else {
+        deployer.call_raw().await?
+    };
+
+    let constructor_return = DynSolType::Array(Box::new(DynSolType::Tuple(vec![
+        DynSolType::Address,
+        DynSolType::Uint(8),
+        DynSolType::Address,
+        DynSolType::Uint(8),
+        DynSolType::Uint(256),
+        DynSolType::Uint(256),
+        DynSolType::Uint(256),
+        DynSolType::Uint(256),
+        DynSolType::Uint(256),
+        DynSolType::Uint(256),
+        DynSolType::Uint(256),
+        DynSolType::Uint(256),
+    ])));
+    let return_data_tokens = constructor_return.abi_decode_sequence(&res)?;
+
+    let mut vault_idx = 0;
+    if let Some(tokens_arr) = return_data_tokens.as_array() {
+        for token in tokens_arr {
+            if let Some(vault_data) = token.as_tuple() {
+                // If the vault token is not zero, signaling that the vault data was populated
+                if let Some(address) = vault_data[0].as_address() {
+                    if !address.is_zero() {
+                        if let AMM::ERC4626Vault(vault) = amms
+                            .get_mut(vault_idx)
+                            .expect("Vault idx should be in bounds")
+                        {
+                            if let Some(populated_vault) =
+                                populate_pool_data_from_tokens(vault.to_owned(), vault_data)
+                            {
+                                tracing::trace!(?populated_vault);
+                                *vault = populated_vault;
+                            }
+                        }
+                    }
+                }
+
+                vault_idx += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::fee_from_deltas;
+
+    #[test]
+    fn test_fee_from_deltas() {
+        let no_fee = U256::from(100_000_000_000_000_000_000_u128);
+        assert_eq!(fee_from_deltas(U256::ZERO, U256::ZERO, no_fee), Some(0));
+        assert_eq!(
+            fee_from_deltas(no_fee / U256::from(100), no_fee / U256::from(50), no_fee),
+            Some(100)
+        );
+        assert_eq!(fee_from_deltas(U256::from(1), U256::from(3), no_fee), None);
+
+        // The amount without fee of a vault with few decimals is too small to express the fee in basis points
+        assert_eq!(
+            fee_from_deltas(U256::from(1), U256::from(2), U256::from(9_999)),
+            None
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use alloy::primitives::{Address, U256};
+
+use crate::errors::PoolBuilderError;
+
+use super::ERC4626Vault;
+
+/// Builder for an [`ERC4626Vault`].
+///
+/// `vault_token` and `asset_token` are required.
+#[derive(Debug, Clone, Default)]
+pub struct ERC4626VaultBuilder {
+    vault_token: Option<Address>,
+    vault_token_decimals: u8,
+    asset_token: Option<Address>,
+    asset_token_decimals: u8,
+    vault_reserve: U256,
+    asset_reserve: U256,
+    deposit_fee: u32,
+    withdraw_fee: u32,
+}
+
+impl ERC4626VaultBuilder {
+    pub fn vault_token(mut self, vault_token: Address, decimals: u8) -> Self {
+        self.vault_token = Some(vault_token);
+        self.vault_token_decimals = decimals;
+        self
+    }
+
+    pub fn asset_token(mut self, asset_token: Address, decimals: u8) -> Self {
+        self.asset_token = Some(asset_token);
+        self.asset_token_decimals = decimals;
+        self
+    }
+
+    pub fn reserves(mut self, vault_reserve: U256, asset_reserve: U256) -> Self {
+        self.vault_reserve = vault_reserve;
+        self.asset_reserve = asset_reserve;
+        self
+    }
+
+    /// Sets the deposit and withdraw fees in basis points.
+    pub fn fees(mut self, deposit_fee: u32, withdraw_fee: u32) -> Self {
+        self.deposit_fee = deposit_fee;
+        self.withdraw_fee = withdraw_fee;
+        self
+    }
+
+    /// Validates the configuration and builds the vault.
+    pub fn build(self) -> Result<ERC4626Vault, PoolBuilderError> {
+        let vault_token = self
+            .vault_token
+            .ok_or(PoolBuilderError::MissingField("vault_token"))?;
+        let asset_token = self
+            .asset_token
+            .ok_or(PoolBuilderError::MissingField("asset_token"))?;
+
+        if vault_token == asset_token {
+            return Err(PoolBuilderError::IdenticalTokens);
+        }
+
+        for fee in [self.deposit_fee, self.withdraw_fee] {
+            if fee > 10_000 {
+                return Err(PoolBuilderError::InvalidFee(fee));
+            }
+        }
+
+        Ok(ERC4626Vault {
+            vault_token,
+            vault_token_decimals: self.vault_token_decimals,
+            asset_token,
+            asset_token_decimals: self.asset_token_decimals,
+            vault_reserve: self.vault_reserve,
+            asset_reserve: self.asset_reserve,
+            deposit_fee: self.deposit_fee,
+            withdraw_fee: self.withdraw_fee,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let vault = ERC4626Vault::builder()
+            .vault_token(address!("163538E22F4d38c1eb21B79939f3d2ee274198Ff"), 18)
+            .asset_token(address!("6B175474E89094C44Da98b954EedeAC495271d0F"), 18)
+            .fees(0, 10)
+            .build()
+            .unwrap();
+        assert_eq!(vault.withdraw_fee, 10);
+
+        assert_eq!(
+            ERC4626Vault::builder()
+                .vault_token(vault.vault_token, 18)
+                .asset_token(vault.asset_token, 18)
+                .fees(10_001, 0)
+                .build()
+                .unwrap_err(),
+            PoolBuilderError::InvalidFee(10_001)
+        );
+    }
+}
@@ -1,26 +1,34 @@
+#[cfg(feature = "provider")]
 pub mod batch_request;
+pub mod builder;
 
-use std::{cmp::Ordering, sync::Arc};
+use std::cmp::Ordering;
+#[cfg(feature = "provider")]
+use std::sync::Arc;
 
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
 use alloy::{
-    network::Network,
     primitives::{Address, B256, U256},
-    providers::Provider,
     rpc::types::eth::Log,
     sol,
     sol_types::SolEvent,
-    transports::Transport,
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
 use crate::{
-    amm::{consts::U128_0X10000000000000000, AutomatedMarketMaker},
-    errors::{AMMError, ArithmeticError, EventLogError, SwapSimulationError},
+    amm::{consts::U128_0X10000000000000000, AutomatedMarketMaker, Protocol},
+    errors::{ArithmeticError, EventLogError, SwapSimulationError},
+};
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{multicall, AMM},
+    errors::{AMMError, ErrorContext, ResultExt},
 };
 
-use super::uniswap_v2::{div_uu, q64_to_f64};
+use crate::core::price::{div_uu, q64_to_f64};
 
 sol! {
     /// Interface of the IERC4626Valut contract
@@ -29,12 +37,20 @@ sol! {
     contract IERC4626Vault {
         event Withdraw(address indexed sender, address indexed receiver, address indexed owner, uint256 assets, uint256 shares);
         event Deposit(address indexed sender,address indexed owner, uint256 assets, uint256 shares);
+        function asset() external view returns (address);
         function totalAssets() external view returns (uint256);
         function totalSupply() external view returns (uint256);
         function decimals() external view returns (uint8);
+        function convertToShares(uint256 assets) external view returns (uint256);
+        function convertToAssets(uint256 shares) external view returns (uint256);
+        function previewDeposit(uint256 assets) external view returns (uint256);
+        function previewRedeem(uint256 shares) external view returns (uint256);
     }
 }
 
+/// Estimated gas used by a deposit or redemption.
+pub const SWAP_GAS_ESTIMATE: u64 = 90_000;
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ERC4626Vault {
     /// token received from depositing, i.e. shares token
@@ -59,6 +75,15 @@ impl AutomatedMarketMaker for ERC4626Vault {
         self.vault_token
     }
 
+    fn protocol(&self) -> Protocol {
+        Protocol::ERC4626
+    }
+
+    /// Returns the larger of the deposit and withdraw fees.
+    fn fee_bps(&self) -> u32 {
+        self.deposit_fee.max(self.withdraw_fee)
+    }
+
     fn tokens(&self) -> Vec<Address> {
         vec![self.vault_token, self.asset_token]
     }
@@ -67,6 +92,7 @@ impl AutomatedMarketMaker for ERC4626Vault {
         Ok(q64_to_f64(self.calculate_price_64_x_64(base_token)?))
     }
 
+    #[cfg(feature = "provider")]
     #[instrument(skip(self, provider), level = "debug")]
     async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
     where
@@ -74,7 +100,10 @@ impl AutomatedMarketMaker for ERC4626Vault {
         N: Network,
         P: Provider<T, N>,
     {
-        let (vault_reserve, asset_reserve) = self.get_reserves(provider).await?;
+        let (vault_reserve, asset_reserve) = self
+            .get_reserves(provider)
+            .await
+            .context(ErrorContext::sync(self.vault_token))?;
         tracing::debug!(vault_reserve = ?vault_reserve, asset_reserve = ?asset_reserve, address = ?self.vault_token, "ER4626 sync");
 
         self.vault_reserve = vault_reserve;
@@ -110,10 +139,11 @@ impl AutomatedMarketMaker for ERC4626Vault {
         Ok(())
     }
 
+    #[cfg(feature = "provider")]
     #[instrument(skip(self, provider), level = "debug")]
     async fn populate_data<T, N, P>(
         &mut self,
-        _block_number: Option<u64>,
+        block_number: Option<u64>,
         provider: Arc<P>,
     ) -> Result<(), AMMError>
     where
@@ -121,7 +151,19 @@ impl AutomatedMarketMaker for ERC4626Vault {
         N: Network,
         P: Provider<T, N>,
     {
-        batch_request::get_4626_vault_data_batch_request(self, provider.clone()).await?;
+        if let Err(err) =
+            batch_request::get_4626_vault_data_batch_request(self, provider.clone()).await
+        {
+            tracing::debug!(?err, address = ?self.vault_token, "Deployless batch request failed, falling back to Multicall3");
+
+            let mut amms = [AMM::ERC4626Vault(self.clone())];
+            multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+                .await
+                .context(ErrorContext::populate(self.vault_token, block_number))?;
+            if let [AMM::ERC4626Vault(vault)] = amms {
+                *self = vault;
+            }
+        }
 
         Ok(())
     }
@@ -167,9 +209,18 @@ impl AutomatedMarketMaker for ERC4626Vault {
             self.vault_token
         }
     }
+
+    fn swap_gas_estimate(&self, _token_in: Address, _amount_in: U256) -> u64 {
+        SWAP_GAS_ESTIMATE
+    }
 }
 
 impl ERC4626Vault {
+    /// Returns a builder for the vault.
+    pub fn builder() -> builder::ERC4626VaultBuilder {
+        builder::ERC4626VaultBuilder::default()
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         vault_token: Address,
@@ -193,6 +244,7 @@ impl ERC4626Vault {
         }
     }
 
+    #[cfg(feature = "provider")]
     pub async fn new_from_address<T, N, P>(
         vault_token: Address,
         provider: Arc<P>,
@@ -229,6 +281,7 @@ impl ERC4626Vault {
             || self.asset_reserve.is_zero())
     }
 
+    #[cfg(feature = "provider")]
     pub async fn get_reserves<T, N, P>(&self, provider: Arc<P>) -> Result<(U256, U256), AMMError>
     where
         T: Transport + Clone,
@@ -320,6 +373,7 @@ mod tests {
     use super::ERC4626Vault;
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_get_vault_data() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -342,6 +396,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_calculate_price_varying_decimals() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -365,6 +420,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_calculate_price_zero_reserve() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -387,6 +443,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_calculate_price() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -409,6 +466,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_calculate_price_64_x_64() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -431,6 +489,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_simulate_swap() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -12,7 +12,7 @@ use async_trait::async_trait;
 use futures::stream::{FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
 
-use crate::errors::{AMMError, EventLogError};
+use crate::errors::{AMMError, ErrorContext, EventLogError, Operation, ResultExt};
 
 use super::{
     uniswap_v2::factory::{IUniswapV2Factory, UniswapV2Factory},
@@ -160,6 +160,10 @@ macro_rules! factory {
 factory!(UniswapV2Factory, UniswapV3Factory);
 
 impl Factory {
+    #[cfg_attr(
+        feature = "tracing-spans",
+        tracing::instrument(skip(self, provider), fields(factory = ?self.address()), level = "debug")
+    )]
     pub async fn get_all_pools_from_logs<T, N, P>(
         &self,
         mut from_block: u64,
@@ -191,7 +195,16 @@ impl Factory {
                 .from_block(from_block)
                 .to_block(target_block);
 
-            futures.push(async move { provider.get_logs(&filter).await });
+            futures.push(async move {
+                let logs = provider.get_logs(&filter).await;
+
+                #[cfg(feature = "tracing-spans")]
+                if let Ok(logs) = &logs {
+                    tracing::debug!(from_block, target_block, logs = logs.len(), "Fetched logs");
+                }
+
+                logs
+            });
 
             from_block += step;
         }
@@ -200,12 +213,44 @@ impl Factory {
             let logs = result.map_err(AMMError::TransportError)?;
 
             for log in logs {
-                aggregated_amms.push(self.new_empty_amm_from_log(log).unwrap());
+                let context =
+                    ErrorContext::new(Operation::Discover, Some(factory_address), log.block_number);
+                aggregated_amms.push(self.new_empty_amm_from_log(log).context(context)?);
             }
         }
 
+        #[cfg(feature = "tracing-spans")]
+        tracing::debug!(amms = aggregated_amms.len(), "Scanned factory");
+
         Ok(aggregated_amms)
     }
+
+    /// Returns the empty AMMs created by the factory in `logs`, e.g. the logs of a block.
+    ///
+    /// Logs of other addresses and events are skipped. Uniswap V2 pools get the fee of the factory.
+    pub fn amms_created_in_block(&self, logs: &[Log]) -> Result<Vec<AMM>, EventLogError> {
+        let factory_address = self.address();
+        let amm_created_event_signature = self.amm_created_event_signature();
+        let mut amms = vec![];
+
+        for log in logs {
+            if log.address() != factory_address
+                || log.topics().first() != Some(&amm_created_event_signature)
+            {
+                continue;
+            }
+
+            let mut amm = self.new_empty_amm_from_log(log.clone())?;
+            if let (Factory::UniswapV2Factory(factory), AMM::UniswapV2Pool(pool)) = (self, &mut amm)
+            {
+                pool.fee = factory.fee;
+            }
+
+            amms.push(amm);
+        }
+
+        Ok(amms)
+    }
 }
 
 impl TryFrom<B256> for Factory {
@@ -221,3 +266,59 @@ impl TryFrom<B256> for Factory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog, U256},
+        rpc::types::eth::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::amm::{
+        uniswap_v2::factory::{IUniswapV2Factory, UniswapV2Factory},
+        AMM,
+    };
+
+    use super::Factory;
+
+    #[test]
+    fn test_amms_created_in_block() {
+        let factory_address = address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f");
+        let factory = Factory::UniswapV2Factory(UniswapV2Factory::new(factory_address, 0, 300));
+
+        let pair_created_log = |address, pair| {
+            let event = IUniswapV2Factory::PairCreated {
+                token0: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+                token1: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+                pair,
+                index: U256::from(1),
+            };
+
+            Log {
+                inner: PrimitiveLog {
+                    address,
+                    data: event.encode_log_data(),
+                },
+                block_number: Some(100),
+                ..Default::default()
+            }
+        };
+
+        let pair = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let logs = vec![
+            pair_created_log(factory_address, pair),
+            // Created by another factory
+            pair_created_log(Address::ZERO, Address::with_last_byte(1)),
+        ];
+
+        let amms = factory.amms_created_in_block(&logs).unwrap();
+        assert_eq!(amms.len(), 1);
+
+        let AMM::UniswapV2Pool(pool) = &amms[0] else {
+            panic!("Unexpected AMM variant")
+        };
+        assert_eq!(pool.address, pair);
+        assert_eq!(pool.fee, 300);
+    }
+}
@@ -0,0 +1,630 @@
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+use alloy::{
+    primitives::{keccak256, Address, B256, U256},
+    rpc::types::eth::Log,
+    sol,
+    sol_types::SolValue,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "provider")]
+use tracing::instrument;
+
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{multicall, AMM},
+    errors::{AMMError, ErrorContext, ResultExt},
+};
+use crate::{
+    amm::{AutomatedMarketMaker, Protocol},
+    core::price::u256_to_f64,
+    errors::{ArithmeticError, EventLogError, SwapSimulationError},
+};
+
+sol! {
+    /// Interface of the GMX V2 data store, holding the state of all markets
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract IGmxDataStore {
+        function getUint(bytes32 key) external view returns (uint256);
+    }
+}
+
+/// Precision of the GMX factors and USD values.
+pub const FLOAT_PRECISION: U256 =
+    U256::from_limbs([5_076_944_270_305_263_616, 54_210_108_624, 0, 0]);
+
+/// Estimated gas of a swap order, executed by a keeper.
+pub const SWAP_GAS_ESTIMATE: u64 = 1_000_000;
+
+const E12: U256 = U256::from_limbs([1_000_000_000_000, 0, 0, 0]);
+const E18: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+
+/// Minimum and maximum oracle price of a token, in USD per unit of the token with `30 - decimals` decimals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OraclePrice {
+    pub min: U256,
+    pub max: U256,
+}
+
+impl OraclePrice {
+    pub fn new(min: U256, max: U256) -> Self {
+        OraclePrice { min, max }
+    }
+
+    pub fn mid(&self) -> U256 {
+        (self.min + self.max) / U256::from(2)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.min.is_zero() || self.max.is_zero()
+    }
+}
+
+/// A GMX V2 GM market, swapping its long and short tokens at oracle prices.
+///
+/// Swaps pay a fee and a price impact, both depending on whether the swap moves the USD value of the pool towards or
+/// away from balance. Oracle prices are signed off chain and not readable on chain: set them with
+/// [`GmxMarket::set_prices`] before quoting. The pool amounts and fee parameters are read from the data store by
+/// [`AutomatedMarketMaker::populate_data`] and refreshed by [`AutomatedMarketMaker::sync`], as GMX emits its state
+/// changes through a generic event emitter rather than from the market.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GmxMarket {
+    /// Market token (GM token) of the market.
+    pub address: Address,
+    pub long_token: Address,
+    pub long_token_decimals: u8,
+    pub short_token: Address,
+    pub short_token_decimals: u8,
+    pub long_pool_amount: U256,
+    pub short_pool_amount: U256,
+    /// Long tokens paid out as positive price impact.
+    pub long_swap_impact_pool_amount: U256,
+    /// Short tokens paid out as positive price impact.
+    pub short_swap_impact_pool_amount: U256,
+    /// Fee of swaps improving the balance of the pool, see [`FLOAT_PRECISION`].
+    pub swap_fee_factor_positive_impact: U256,
+    /// Fee of swaps worsening the balance of the pool, see [`FLOAT_PRECISION`].
+    pub swap_fee_factor_negative_impact: U256,
+    pub swap_impact_factor_positive: U256,
+    pub swap_impact_factor_negative: U256,
+    pub swap_impact_exponent_factor: U256,
+    pub long_token_price: OraclePrice,
+    pub short_token_price: OraclePrice,
+    pub data_store: Address,
+}
+
+/// Outcome of a swap in a [`GmxMarket`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Swap {
+    amount_out: U256,
+    /// Tokens in moved to the swap impact pool, for a negative price impact.
+    negative_impact_amount: U256,
+    /// Tokens out paid from the swap impact pool, for a positive price impact.
+    positive_impact_amount: U256,
+}
+
+#[async_trait]
+impl AutomatedMarketMaker for GmxMarket {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::Gmx
+    }
+
+    /// Returns the fee of swaps worsening the balance of the pool.
+    fn fee_bps(&self) -> u32 {
+        (self.swap_fee_factor_negative_impact * U256::from(10_000) / FLOAT_PRECISION)
+            .saturating_to()
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        self.populate_data(None, provider)
+            .await
+            .context(ErrorContext::sync(self.address))?;
+        tracing::info!(long_pool_amount = ?self.long_pool_amount, short_pool_amount = ?self.short_pool_amount, address = ?self.address, "Gmx sync");
+
+        Ok(())
+    }
+
+    fn sync_on_event_signatures(&self) -> Vec<B256> {
+        vec![]
+    }
+
+    fn sync_from_log(&mut self, _log: Log) -> Result<(), EventLogError> {
+        Err(EventLogError::InvalidEventSignature)
+    }
+
+    /// Populates the pool amounts and fee parameters, keeping the oracle prices.
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn populate_data<T, N, P>(
+        &mut self,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut amms = [AMM::GmxMarket(self.clone())];
+        multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+            .await
+            .context(ErrorContext::populate(self.address, block_number))?;
+        if let [AMM::GmxMarket(market)] = amms {
+            *self = market;
+        }
+
+        Ok(())
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.long_token, self.short_token]
+    }
+
+    /// Returns the price of `base_token` at the mid oracle prices.
+    fn calculate_price(&self, base_token: Address) -> Result<f64, ArithmeticError> {
+        if self.long_token_price.is_zero() || self.short_token_price.is_zero() {
+            return Err(ArithmeticError::YIsZero);
+        }
+
+        let long_price =
+            u256_to_f64(self.long_token_price.mid()) * 10f64.powi(self.long_token_decimals as i32);
+        let short_price = u256_to_f64(self.short_token_price.mid())
+            * 10f64.powi(self.short_token_decimals as i32);
+
+        if base_token == self.long_token {
+            Ok(long_price / short_price)
+        } else {
+            Ok(short_price / long_price)
+        }
+    }
+
+    fn simulate_swap(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        Ok(self.swap(token_in, amount_in)?.amount_out)
+    }
+
+    fn simulate_swap_mut(
+        &mut self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let swap = self.swap(token_in, amount_in)?;
+        let is_long_in = token_in == self.long_token;
+
+        let (pool_in, pool_out, impact_pool_in, impact_pool_out) = if is_long_in {
+            (
+                &mut self.long_pool_amount,
+                &mut self.short_pool_amount,
+                &mut self.long_swap_impact_pool_amount,
+                &mut self.short_swap_impact_pool_amount,
+            )
+        } else {
+            (
+                &mut self.short_pool_amount,
+                &mut self.long_pool_amount,
+                &mut self.short_swap_impact_pool_amount,
+                &mut self.long_swap_impact_pool_amount,
+            )
+        };
+
+        // Fees stay in the pool, the price impact moves through the swap impact pools
+        *pool_in += amount_in - swap.negative_impact_amount;
+        *impact_pool_in += swap.negative_impact_amount;
+        *pool_out -= swap.amount_out - swap.positive_impact_amount;
+        *impact_pool_out -= swap.positive_impact_amount;
+
+        Ok(swap.amount_out)
+    }
+
+    fn get_token_out(&self, token_in: Address) -> Address {
+        if token_in == self.long_token {
+            self.short_token
+        } else {
+            self.long_token
+        }
+    }
+
+    fn swap_gas_estimate(&self, _token_in: Address, _amount_in: U256) -> u64 {
+        SWAP_GAS_ESTIMATE
+    }
+}
+
+impl GmxMarket {
+    /// Returns an unpopulated market, see [`AutomatedMarketMaker::populate_data`].
+    pub fn new(
+        market_token: Address,
+        long_token: Address,
+        short_token: Address,
+        data_store: Address,
+    ) -> Self {
+        GmxMarket {
+            address: market_token,
+            long_token,
+            short_token,
+            data_store,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "provider")]
+    pub async fn new_from_address<T, N, P>(
+        market_token: Address,
+        long_token: Address,
+        short_token: Address,
+        data_store: Address,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut market = GmxMarket::new(market_token, long_token, short_token, data_store);
+        market.populate_data(None, provider).await?;
+
+        if !market.data_is_populated() {
+            return Err(AMMError::PoolDataError);
+        }
+
+        Ok(market)
+    }
+
+    pub fn data_is_populated(&self) -> bool {
+        !(self.long_token.is_zero()
+            || self.short_token.is_zero()
+            || self.long_pool_amount.is_zero()
+            || self.short_pool_amount.is_zero())
+    }
+
+    /// Sets the oracle prices of the long and short tokens.
+    pub fn set_prices(&mut self, long_token_price: OraclePrice, short_token_price: OraclePrice) {
+        self.long_token_price = long_token_price;
+        self.short_token_price = short_token_price;
+    }
+
+    /// Returns the data store keys of the market state, in the order of [`GmxMarket::apply_data_store_values`].
+    pub fn data_store_keys(&self) -> [B256; 9] {
+        let market = self.address;
+
+        [
+            hash_key(&(key("POOL_AMOUNT"), market, self.long_token)),
+            hash_key(&(key("POOL_AMOUNT"), market, self.short_token)),
+            hash_key(&(key("SWAP_IMPACT_POOL_AMOUNT"), market, self.long_token)),
+            hash_key(&(key("SWAP_IMPACT_POOL_AMOUNT"), market, self.short_token)),
+            hash_key(&(key("SWAP_FEE_FACTOR"), market, true)),
+            hash_key(&(key("SWAP_FEE_FACTOR"), market, false)),
+            hash_key(&(key("SWAP_IMPACT_FACTOR"), market, true)),
+            hash_key(&(key("SWAP_IMPACT_FACTOR"), market, false)),
+            hash_key(&(key("SWAP_IMPACT_EXPONENT_FACTOR"), market)),
+        ]
+    }
+
+    /// Sets the market state from the data store values at [`GmxMarket::data_store_keys`].
+    pub fn apply_data_store_values(&mut self, values: [U256; 9]) {
+        [
+            self.long_pool_amount,
+            self.short_pool_amount,
+            self.long_swap_impact_pool_amount,
+            self.short_swap_impact_pool_amount,
+            self.swap_fee_factor_positive_impact,
+            self.swap_fee_factor_negative_impact,
+            self.swap_impact_factor_positive,
+            self.swap_impact_factor_negative,
+            self.swap_impact_exponent_factor,
+        ] = values;
+    }
+
+    /// Returns the outcome of a swap of `amount_in`, mirroring the swap of the GMX swap handler.
+    fn swap(&self, token_in: Address, amount_in: U256) -> Result<Swap, SwapSimulationError> {
+        let is_long_in = token_in == self.long_token;
+        let (price_in, price_out, pool_out, impact_pool_out) = if is_long_in {
+            (
+                self.long_token_price,
+                self.short_token_price,
+                self.short_pool_amount,
+                self.short_swap_impact_pool_amount,
+            )
+        } else {
+            (
+                self.short_token_price,
+                self.long_token_price,
+                self.long_pool_amount,
+                self.long_swap_impact_pool_amount,
+            )
+        };
+
+        if amount_in.is_zero() || price_in.is_zero() || price_out.is_zero() {
+            return Ok(Swap::default());
+        }
+
+        let (is_positive_impact, price_impact_usd) =
+            self.price_impact_usd(is_long_in, amount_in * price_in.mid())?;
+
+        let fee_factor = if is_positive_impact {
+            self.swap_fee_factor_positive_impact
+        } else {
+            self.swap_fee_factor_negative_impact
+        };
+        let amount_after_fees = amount_in - amount_in * fee_factor / FLOAT_PRECISION;
+
+        let swap = if is_positive_impact {
+            let positive_impact_amount = (price_impact_usd / price_out.max).min(impact_pool_out);
+
+            Swap {
+                amount_out: amount_after_fees * price_in.min / price_out.max
+                    + positive_impact_amount,
+                negative_impact_amount: U256::ZERO,
+                positive_impact_amount,
+            }
+        } else {
+            let negative_impact_amount = price_impact_usd.div_ceil(price_in.min);
+            let Some(amount_in) = amount_after_fees.checked_sub(negative_impact_amount) else {
+                return Ok(Swap::default());
+            };
+
+            Swap {
+                amount_out: amount_in * price_in.min / price_out.max,
+                negative_impact_amount,
+                positive_impact_amount: U256::ZERO,
+            }
+        };
+
+        if swap.amount_out - swap.positive_impact_amount > pool_out {
+            return Err(SwapSimulationError::LiquidityUnderflow);
+        }
+
+        Ok(swap)
+    }
+
+    /// Returns whether the price impact of a swap of `usd_delta` is positive, and its absolute value in USD.
+    fn price_impact_usd(
+        &self,
+        is_long_in: bool,
+        usd_delta: U256,
+    ) -> Result<(bool, U256), ArithmeticError> {
+        let long_usd = self.long_pool_amount * self.long_token_price.mid();
+        let short_usd = self.short_pool_amount * self.short_token_price.mid();
+        let (pool_in_usd, pool_out_usd) = if is_long_in {
+            (long_usd, short_usd)
+        } else {
+            (short_usd, long_usd)
+        };
+
+        let next_pool_in_usd = pool_in_usd + usd_delta;
+        let next_pool_out_usd = pool_out_usd.saturating_sub(usd_delta);
+
+        let initial_diff_usd = abs_diff(pool_in_usd, pool_out_usd);
+        let next_diff_usd = abs_diff(next_pool_in_usd, next_pool_out_usd);
+
+        let exponent = self.swap_impact_exponent_factor;
+        let is_same_side_rebalance =
+            (pool_in_usd <= pool_out_usd) == (next_pool_in_usd <= next_pool_out_usd);
+
+        if is_same_side_rebalance {
+            let is_positive_impact = next_diff_usd < initial_diff_usd;
+            let impact_factor = if is_positive_impact {
+                self.swap_impact_factor_positive
+            } else {
+                self.swap_impact_factor_negative
+            };
+
+            let delta_diff_usd = abs_diff(
+                apply_exponent_factor(initial_diff_usd, exponent)?,
+                apply_exponent_factor(next_diff_usd, exponent)?,
+            );
+
+            Ok((
+                is_positive_impact,
+                delta_diff_usd * impact_factor / FLOAT_PRECISION,
+            ))
+        } else {
+            // The swap crosses over the balance, improving the initial imbalance and creating a new one
+            let positive_impact_usd = apply_exponent_factor(initial_diff_usd, exponent)?
+                * self.swap_impact_factor_positive
+                / FLOAT_PRECISION;
+            let negative_impact_usd = apply_exponent_factor(next_diff_usd, exponent)?
+                * self.swap_impact_factor_negative
+                / FLOAT_PRECISION;
+
+            Ok((
+                positive_impact_usd >= negative_impact_usd,
+                abs_diff(positive_impact_usd, negative_impact_usd),
+            ))
+        }
+    }
+}
+
+/// Returns the data store key of `name`.
+fn key(name: &str) -> B256 {
+    keccak256(name.abi_encode())
+}
+
+fn hash_key<V: SolValue>(values: &V) -> B256 {
+    keccak256(values.abi_encode())
+}
+
+/// Raises a USD `value` to the power of `exponent`, both with [`FLOAT_PRECISION`].
+pub fn apply_exponent_factor(value: U256, exponent: U256) -> Result<U256, ArithmeticError> {
+    if value < FLOAT_PRECISION {
+        return Ok(U256::ZERO);
+    }
+
+    if exponent == FLOAT_PRECISION {
+        return Ok(value);
+    }
+
+    if (exponent % FLOAT_PRECISION).is_zero() {
+        // Integer exponents, with 18 decimals as the GMX contracts
+        let base = value / E12;
+        let mut result = base;
+        for _ in 1..(exponent / FLOAT_PRECISION).saturating_to::<u32>() {
+            result = result
+                .checked_mul(base)
+                .ok_or(ArithmeticError::ShadowOverflow(result))?
+                / E18;
+        }
+
+        Ok(result * E12)
+    } else {
+        let value = u256_to_f64(value) / u256_to_f64(FLOAT_PRECISION);
+        let result = value.powf(u256_to_f64(exponent) / u256_to_f64(FLOAT_PRECISION));
+
+        Ok(U256::from((result * 1e12) as u128) * E18)
+    }
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, U256};
+
+    use crate::amm::AutomatedMarketMaker;
+
+    use super::{abs_diff, apply_exponent_factor, GmxMarket, OraclePrice, FLOAT_PRECISION};
+
+    fn market() -> GmxMarket {
+        let e18 = U256::from(1_000_000_000_000_000_000_u64);
+
+        GmxMarket {
+            long_token_decimals: 18,
+            short_token_decimals: 6,
+            // 1,000 WETH and 3,000,000 USDC
+            long_pool_amount: U256::from(1_000) * e18,
+            short_pool_amount: U256::from(3_000_000_000_000_u64),
+            long_swap_impact_pool_amount: U256::from(10) * e18,
+            short_swap_impact_pool_amount: U256::from(30_000_000_000_u64),
+            // 0.05% and 0.07% fees
+            swap_fee_factor_positive_impact: FLOAT_PRECISION * U256::from(5) / U256::from(10_000),
+            swap_fee_factor_negative_impact: FLOAT_PRECISION * U256::from(7) / U256::from(10_000),
+            swap_impact_factor_positive: FLOAT_PRECISION / U256::from(1_000_000_000),
+            swap_impact_factor_negative: FLOAT_PRECISION * U256::from(2)
+                / U256::from(1_000_000_000),
+            swap_impact_exponent_factor: FLOAT_PRECISION * U256::from(2),
+            // $3,000 per WETH and $1 per USDC, per unit of the tokens
+            long_token_price: OraclePrice::new(
+                U256::from(2_999_000_000_000_000_u64),
+                U256::from(3_001_000_000_000_000_u64),
+            ),
+            short_token_price: OraclePrice::new(
+                U256::from(1_000_000_000_000_000_000_000_000_u128),
+                U256::from(1_000_000_000_000_000_000_000_000_u128),
+            ),
+            ..GmxMarket::new(
+                address!("70d95587d40A2caf56bd97485aB3Eec10Bee6336"),
+                address!("82aF49447D8a07e3bd95BD0d56f35241523fBab1"),
+                address!("af88d065e77c8cC2239327C5EDb3A432268e5831"),
+                address!("FD70de6b91282D8017aA4E741e9Ae325CAb992d8"),
+            )
+        }
+    }
+
+    #[test]
+    fn test_apply_exponent_factor() {
+        let usd = |value: u64| U256::from(value) * FLOAT_PRECISION;
+
+        assert_eq!(
+            apply_exponent_factor(usd(3_000), FLOAT_PRECISION * U256::from(2)).unwrap(),
+            usd(9_000_000)
+        );
+        assert_eq!(
+            apply_exponent_factor(usd(3_000), FLOAT_PRECISION).unwrap(),
+            usd(3_000)
+        );
+
+        // Fractional exponents are approximated
+        let result =
+            apply_exponent_factor(usd(9), FLOAT_PRECISION * U256::from(3) / U256::from(2)).unwrap();
+        assert!(abs_diff(result, usd(27)) < FLOAT_PRECISION / U256::from(1_000_000));
+        assert_eq!(
+            apply_exponent_factor(U256::from(1), FLOAT_PRECISION * U256::from(2)).unwrap(),
+            U256::ZERO
+        );
+    }
+
+    #[test]
+    fn test_simulate_swap() {
+        let mut market = market();
+        let weth = market.long_token;
+        let usdc = market.short_token;
+        let one_weth = U256::from(1_000_000_000_000_000_000_u64);
+
+        // Swapping WETH into a balanced pool pays the negative impact fee and price impact
+        let amount_out = market.simulate_swap(weth, one_weth).unwrap();
+        let without_impact = U256::from(2_999_000_000_u64) * U256::from(9_993) / U256::from(10_000);
+        assert!(amount_out < without_impact);
+        assert!(amount_out > without_impact - U256::from(1_000_000));
+        assert_eq!(market.get_token_out(weth), usdc);
+
+        // Rebalancing an imbalanced pool earns a positive price impact
+        market.long_pool_amount = market.long_pool_amount * U256::from(2);
+        let amount_out = market
+            .simulate_swap(usdc, U256::from(3_000_000_000_u64))
+            .unwrap();
+        let without_impact = U256::from(3_000_000_000_u64) * U256::from(9_995) / U256::from(10_000)
+            * U256::from(1_000_000_000_000_000_000_000_000_u128)
+            / U256::from(3_001_000_000_000_000_u64);
+        assert!(amount_out > without_impact);
+
+        let short_pool_amount = market.short_pool_amount;
+        let long_swap_impact_pool_amount = market.long_swap_impact_pool_amount;
+        assert_eq!(
+            market
+                .simulate_swap_mut(usdc, U256::from(3_000_000_000_u64))
+                .unwrap(),
+            amount_out
+        );
+        assert_eq!(
+            market.short_pool_amount,
+            short_pool_amount + U256::from(3_000_000_000_u64)
+        );
+        assert!(market.long_swap_impact_pool_amount < long_swap_impact_pool_amount);
+
+        // Swaps draining the pool fail
+        assert!(market
+            .simulate_swap(weth, one_weth * U256::from(10_000))
+            .is_err());
+    }
+
+    #[test]
+    fn test_data_store_keys() {
+        let mut market = market();
+        let keys = market.data_store_keys();
+
+        // Keys are distinct per token and impact side
+        for (i, key) in keys.iter().enumerate() {
+            assert!(!keys[i + 1..].contains(key));
+        }
+
+        let values = std::array::from_fn(|i| U256::from(i));
+        market.apply_data_store_values(values);
+        assert_eq!(market.short_pool_amount, U256::from(1));
+        assert_eq!(market.swap_impact_exponent_factor, U256::from(8));
+    }
+}
@@ -0,0 +1,92 @@
+//! User metadata attached to AMMs, e.g. labels, strategy tags and custom scores, kept by the state space and written
+//! to checkpoints along with the AMMs.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+
+/// Metadata of AMMs, keyed by AMM address.
+pub type AmmMetadataMap = HashMap<Address, AmmMetadata>;
+
+/// Metadata of an AMM. It is never read by the crate, and is left untouched by syncing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AmmMetadata {
+    pub tags: BTreeSet<String>,
+    pub labels: BTreeMap<String, String>,
+    pub scores: BTreeMap<String, f64>,
+}
+
+impl AmmMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `tag`, returning whether it was not set yet.
+    pub fn tag(&mut self, tag: impl Into<String>) -> bool {
+        self.tags.insert(tag.into())
+    }
+
+    /// Removes `tag`, returning whether it was set.
+    pub fn untag(&mut self, tag: &str) -> bool {
+        self.tags.remove(tag)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// Sets the label `key`, returning its previous value if any.
+    pub fn set_label(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Option<String> {
+        self.labels.insert(key.into(), value.into())
+    }
+
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(String::as_str)
+    }
+
+    /// Sets the score `key`, returning its previous value if any.
+    pub fn set_score(&mut self, key: impl Into<String>, score: f64) -> Option<f64> {
+        self.scores.insert(key.into(), score)
+    }
+
+    pub fn score(&self, key: &str) -> Option<f64> {
+        self.scores.get(key).copied()
+    }
+
+    /// Returns whether no tag, label or score is set.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.labels.is_empty() && self.scores.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AmmMetadata;
+
+    #[test]
+    fn test_metadata() {
+        let mut metadata = AmmMetadata::new();
+        assert!(metadata.is_empty());
+
+        assert!(metadata.tag("arbitrage"));
+        assert!(!metadata.tag("arbitrage"));
+        assert!(metadata.has_tag("arbitrage"));
+        assert_eq!(metadata.set_label("venue", "mainnet"), None);
+        assert_eq!(metadata.set_score("toxicity", 0.25), None);
+        assert_eq!(metadata.set_score("toxicity", 0.5), Some(0.25));
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        let metadata: AmmMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(metadata.label("venue"), Some("mainnet"));
+        assert_eq!(metadata.score("toxicity"), Some(0.5));
+
+        let mut metadata = metadata;
+        assert!(metadata.untag("arbitrage"));
+        assert!(!metadata.has_tag("arbitrage"));
+    }
+}
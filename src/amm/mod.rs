@@ -1,25 +1,48 @@
+pub mod ambient;
+pub mod bancor_v3;
+#[cfg(feature = "provider")]
+pub mod batch_request;
+pub mod constant_product;
 pub mod consts;
+pub mod curve_v2;
 pub mod erc_4626;
+#[cfg(feature = "provider")]
 pub mod factory;
+pub mod gmx;
+pub mod metadata;
+#[cfg(feature = "provider")]
+pub mod multicall;
+#[cfg(feature = "provider")]
+pub mod onchain;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod rate_adapter;
+pub mod stats;
 pub mod uniswap_v2;
 pub mod uniswap_v3;
 
+#[cfg(feature = "provider")]
 use std::sync::Arc;
 
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
 use alloy::{
-    network::Network,
-    primitives::{Address, B256, U256},
-    providers::Provider,
+    primitives::{Address, Bytes, B256, U256},
     rpc::types::eth::Log,
     sol,
-    transports::Transport,
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-use crate::errors::{AMMError, ArithmeticError, EventLogError, SwapSimulationError};
+#[cfg(feature = "provider")]
+use crate::errors::AMMError;
+use crate::errors::{ArithmeticError, EventLogError, ExecutionError, SwapSimulationError};
 
-use self::{erc_4626::ERC4626Vault, uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool};
+use self::{
+    ambient::AmbientPool, bancor_v3::BancorV3Pool, constant_product::ConstantProductPool,
+    curve_v2::CurveV2Pool, erc_4626::ERC4626Vault, gmx::GmxMarket, rate_adapter::RateAdapter,
+    uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool,
+};
 
 sol! {
     /// Interface of the ERC20
@@ -28,15 +51,74 @@ sol! {
     contract IErc20 {
         function balanceOf(address account) external view returns (uint256);
         function decimals() external view returns (uint8);
+        function symbol() external view returns (string);
+        function name() external view returns (string);
+        function totalSupply() external view returns (uint256);
+    }
+}
+
+/// Protocol of an AMM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Protocol {
+    UniswapV2,
+    UniswapV3,
+    ERC4626,
+    CurveV2,
+    RateAdapter,
+    BancorV3,
+    Gmx,
+    Ambient,
+    ConstantProduct,
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Protocol::UniswapV2 => "Uniswap V2",
+            Protocol::UniswapV3 => "Uniswap V3",
+            Protocol::ERC4626 => "ERC4626",
+            Protocol::CurveV2 => "Curve V2",
+            Protocol::RateAdapter => "Rate Adapter",
+            Protocol::BancorV3 => "Bancor V3",
+            Protocol::Gmx => "GMX",
+            Protocol::Ambient => "Ambient",
+            Protocol::ConstantProduct => "Constant Product",
+        };
+
+        f.write_str(name)
     }
 }
 
+/// Parameters of a swap called directly on an AMM, see [`AutomatedMarketMaker::encode_swap`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SwapParams {
+    pub token_in: Address,
+    pub amount_in: U256,
+    /// Minimum amount of the token out, enforced by the AMM where its swap function supports it.
+    pub amount_out_min: U256,
+    pub recipient: Address,
+    /// Data passed to the swap callback, for AMMs with one.
+    pub callback_data: Bytes,
+}
+
 #[async_trait]
 pub trait AutomatedMarketMaker {
     /// Returns the address of the AMM.
     fn address(&self) -> Address;
 
+    /// Returns the protocol of the AMM.
+    fn protocol(&self) -> Protocol;
+
+    /// Returns the swap fee of the AMM in basis points.
+    fn fee_bps(&self) -> u32;
+
+    /// Returns the address of the factory that created the AMM, if it is known.
+    fn factory(&self) -> Option<Address> {
+        None
+    }
+
     /// Syncs the AMM data on chain via batched static calls.
+    #[cfg(feature = "provider")]
     async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
     where
         T: Transport + Clone,
@@ -56,10 +138,11 @@ pub trait AutomatedMarketMaker {
     fn sync_from_log(&mut self, log: Log) -> Result<(), EventLogError>;
 
     /// Populates the AMM data via batched static calls.
+    #[cfg(feature = "provider")]
     async fn populate_data<T, N, P>(
         &mut self,
         block_number: Option<u64>,
-        middleware: Arc<P>,
+        provider: Arc<P>,
     ) -> Result<(), AMMError>
     where
         T: Transport + Clone,
@@ -84,8 +167,47 @@ pub trait AutomatedMarketMaker {
         amount_in: U256,
     ) -> Result<U256, SwapSimulationError>;
 
+    /// Locally simulates a swap in the AMM for an exact amount out.
+    ///
+    /// Returns the amount of `token_in` required to receive `amount_out` of the token out. AMMs that can only
+    /// simulate exact input swaps return [`SwapSimulationError::UnsupportedExactOutput`].
+    fn simulate_swap_exact_output(
+        &self,
+        _token_in: Address,
+        _amount_out: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        Err(SwapSimulationError::UnsupportedExactOutput)
+    }
+
     /// Returns the token out of the AMM for a given `token_in`.
     fn get_token_out(&self, token_in: Address) -> Address;
+
+    /// Returns the estimated gas used by a swap of `amount_in` of `token_in` in the AMM, excluding the transaction
+    /// and router overhead.
+    fn swap_gas_estimate(&self, token_in: Address, amount_in: U256) -> u64;
+
+    /// Simulates a swap of `amount_in` of `token_in`, returning the amount out along with the estimated gas used by
+    /// the swap, see [`AutomatedMarketMaker::swap_gas_estimate`].
+    ///
+    /// AMMs whose gas estimate depends on the path of the swap override this to only simulate the swap once.
+    fn simulate_swap_with_gas_estimate(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<(U256, u64), SwapSimulationError> {
+        Ok((
+            self.simulate_swap(token_in, amount_in)?,
+            self.swap_gas_estimate(token_in, amount_in),
+        ))
+    }
+
+    /// Returns the calldata of a swap called directly on the AMM, without a router.
+    ///
+    /// How the input is paid depends on the protocol, e.g. transferred before the swap or in the swap callback.
+    /// AMMs that cannot be swapped directly return [`ExecutionError::UnsupportedPool`].
+    fn encode_swap(&self, _params: SwapParams) -> Result<Bytes, ExecutionError> {
+        Err(ExecutionError::UnsupportedPool(self.address()))
+    }
 }
 
 macro_rules! amm {
@@ -103,14 +225,33 @@ macro_rules! amm {
                 }
             }
 
-            async fn sync<T, N, P>(&mut self, middleware: Arc<P>) -> Result<(), AMMError>
+            fn protocol(&self) -> Protocol {
+                match self {
+                    $(AMM::$pool_type(pool) => pool.protocol(),)+
+                }
+            }
+
+            fn fee_bps(&self) -> u32 {
+                match self {
+                    $(AMM::$pool_type(pool) => pool.fee_bps(),)+
+                }
+            }
+
+            fn factory(&self) -> Option<Address> {
+                match self {
+                    $(AMM::$pool_type(pool) => pool.factory(),)+
+                }
+            }
+
+            #[cfg(feature = "provider")]
+            async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
             where
                 T: Transport + Clone,
                 N: Network,
                 P: Provider<T, N>,
             {
                 match self {
-                    $(AMM::$pool_type(pool) => pool.sync(middleware).await,)+
+                    $(AMM::$pool_type(pool) => pool.sync(provider).await,)+
                 }
             }
 
@@ -144,14 +285,39 @@ macro_rules! amm {
                 }
             }
 
-            async fn populate_data<T, N, P>(&mut self, block_number: Option<u64>, middleware: Arc<P>) -> Result<(), AMMError>
+            fn simulate_swap_exact_output(&self, token_in: Address, amount_out: U256) -> Result<U256, SwapSimulationError> {
+                match self {
+                    $(AMM::$pool_type(pool) => pool.simulate_swap_exact_output(token_in, amount_out),)+
+                }
+            }
+
+            fn swap_gas_estimate(&self, token_in: Address, amount_in: U256) -> u64 {
+                match self {
+                    $(AMM::$pool_type(pool) => pool.swap_gas_estimate(token_in, amount_in),)+
+                }
+            }
+
+            fn simulate_swap_with_gas_estimate(&self, token_in: Address, amount_in: U256) -> Result<(U256, u64), SwapSimulationError> {
+                match self {
+                    $(AMM::$pool_type(pool) => pool.simulate_swap_with_gas_estimate(token_in, amount_in),)+
+                }
+            }
+
+            fn encode_swap(&self, params: SwapParams) -> Result<Bytes, ExecutionError> {
+                match self {
+                    $(AMM::$pool_type(pool) => pool.encode_swap(params),)+
+                }
+            }
+
+            #[cfg(feature = "provider")]
+            async fn populate_data<T, N, P>(&mut self, block_number: Option<u64>, provider: Arc<P>) -> Result<(), AMMError>
             where
                 T: Transport + Clone,
                 N: Network,
                 P: Provider<T, N>,
             {
                 match self {
-                    $(AMM::$pool_type(pool) => pool.populate_data(block_number, middleware).await,)+
+                    $(AMM::$pool_type(pool) => pool.populate_data(block_number, provider).await,)+
                 }
             }
 
@@ -170,4 +336,55 @@ macro_rules! amm {
     };
 }
 
-amm!(UniswapV2Pool, UniswapV3Pool, ERC4626Vault);
+amm!(
+    UniswapV2Pool,
+    UniswapV3Pool,
+    ERC4626Vault,
+    CurveV2Pool,
+    RateAdapter,
+    BancorV3Pool,
+    GmxMarket,
+    AmbientPool,
+    ConstantProductPool
+);
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::{
+        erc_4626::ERC4626Vault, uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool,
+        AutomatedMarketMaker, Protocol, AMM,
+    };
+
+    #[test]
+    fn test_protocol_metadata() {
+        let factory = address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f");
+        let uniswap_v2_pool = AMM::UniswapV2Pool(UniswapV2Pool {
+            fee: 300,
+            factory: Some(factory),
+            ..Default::default()
+        });
+        let uniswap_v3_pool = AMM::UniswapV3Pool(UniswapV3Pool {
+            fee: 500,
+            ..Default::default()
+        });
+        let vault = AMM::ERC4626Vault(ERC4626Vault {
+            deposit_fee: 10,
+            withdraw_fee: 25,
+            ..Default::default()
+        });
+
+        assert_eq!(uniswap_v2_pool.protocol(), Protocol::UniswapV2);
+        assert_eq!(uniswap_v2_pool.fee_bps(), 30);
+        assert_eq!(uniswap_v2_pool.factory(), Some(factory));
+
+        assert_eq!(uniswap_v3_pool.protocol(), Protocol::UniswapV3);
+        assert_eq!(uniswap_v3_pool.fee_bps(), 5);
+        assert_eq!(uniswap_v3_pool.factory(), None);
+
+        assert_eq!(vault.protocol(), Protocol::ERC4626);
+        assert_eq!(vault.fee_bps(), 25);
+        assert_eq!(vault.protocol().to_string(), "ERC4626");
+    }
+}
@@ -0,0 +1,846 @@
+//! Multicall3 batch requests, for chains that do not support the deployless static calls
+//! used by the batch request contracts.
+//!
+//! The same calls can be executed as individual `eth_call`s, for chains without Multicall3 at its usual address
+//! (e.g. zkSync Era).
+
+use std::sync::Arc;
+
+use alloy::{
+    network::Network,
+    primitives::{address, Address, Bytes, U256},
+    providers::Provider,
+    sol,
+    sol_types::SolCall,
+    transports::{RpcError, Transport},
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde_json::json;
+
+use crate::errors::AMMError;
+
+use super::{
+    ambient::{AmbientPool, ICrocQuery},
+    bancor_v3::{IBancorNetworkSettings, IBancorPoolCollection, BNT},
+    constant_product::ConstantProductPool,
+    curve_v2::ICurveV2Pool,
+    erc_4626::{batch_request::fee_from_deltas, IERC4626Vault},
+    gmx::IGmxDataStore,
+    rate_adapter::{IRateProvider, RateSource},
+    uniswap_v2::IUniswapV2Pair,
+    uniswap_v3::IUniswapV3Pool,
+    IErc20, AMM,
+};
+
+/// Address of Multicall3, deployed at the same address on most chains.
+pub const MULTICALL3_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+/// Max number of concurrent `eth_call` requests when executing calls individually.
+const MAX_CONCURRENT_CALLS: usize = 32;
+
+/// Method used to execute the calls of a batch request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CallMode {
+    /// One `aggregate3` call per stage.
+    Multicall3,
+    /// One `eth_call` per call.
+    Individual,
+}
+
+sol! {
+    /// Interface of the Multicall3 contract
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Call3Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Call3Result[] memory returnData);
+    }
+}
+
+/// Populates the data of a heterogeneous set of AMMs through Multicall3.
+///
+/// Pool state, token decimals and follow-up calls (fee previews of ERC4626 vaults and liquidity levels of Ambient
+/// pools) are fetched in one `aggregate3` call each.
+/// AMMs with a reverting call are left unpopulated, matching the behavior of the deployless batch requests.
+pub async fn get_amm_data_batch_request<T, N, P>(
+    amms: &mut [AMM],
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    get_amm_data(amms, block_number, CallMode::Multicall3, provider).await
+}
+
+/// Populates the data of a heterogeneous set of AMMs with the calls of [`get_amm_data_batch_request`], executed as
+/// individual `eth_call`s.
+///
+/// Slower than Multicall3, for chains where neither deployless static calls nor Multicall3 are available.
+pub async fn get_amm_data_individual_calls<T, N, P>(
+    amms: &mut [AMM],
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    get_amm_data(amms, block_number, CallMode::Individual, provider).await
+}
+
+async fn get_amm_data<T, N, P>(
+    amms: &mut [AMM],
+    block_number: Option<u64>,
+    mode: CallMode,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    // Get the pool tokens and reserves
+    let calls = amms
+        .iter()
+        .map(|amm| match amm {
+            AMM::UniswapV2Pool(pool) => vec![
+                call3(pool.address, IUniswapV2Pair::token0Call {}),
+                call3(pool.address, IUniswapV2Pair::token1Call {}),
+                call3(pool.address, IUniswapV2Pair::getReservesCall {}),
+            ],
+            AMM::UniswapV3Pool(pool) => vec![
+                call3(pool.address, IUniswapV3Pool::token0Call {}),
+                call3(pool.address, IUniswapV3Pool::token1Call {}),
+                call3(pool.address, IUniswapV3Pool::liquidityCall {}),
+                call3(pool.address, IUniswapV3Pool::slot0Call {}),
+                call3(pool.address, IUniswapV3Pool::tickSpacingCall {}),
+                call3(pool.address, IUniswapV3Pool::feeCall {}),
+            ],
+            AMM::ERC4626Vault(vault) => vec![
+                call3(vault.vault_token, IERC4626Vault::assetCall {}),
+                call3(vault.vault_token, IERC4626Vault::decimalsCall {}),
+                call3(vault.vault_token, IERC4626Vault::totalSupplyCall {}),
+                call3(vault.vault_token, IERC4626Vault::totalAssetsCall {}),
+            ],
+            AMM::CurveV2Pool(pool) => vec![
+                call3(pool.address, ICurveV2Pool::coinsCall { i: U256::ZERO }),
+                call3(pool.address, ICurveV2Pool::coinsCall { i: U256::from(1) }),
+                call3(pool.address, ICurveV2Pool::balancesCall { i: U256::ZERO }),
+                call3(
+                    pool.address,
+                    ICurveV2Pool::balancesCall { i: U256::from(1) },
+                ),
+                call3(pool.address, ICurveV2Pool::price_scaleCall {}),
+                call3(pool.address, ICurveV2Pool::DCall {}),
+                call3(pool.address, ICurveV2Pool::ACall {}),
+                call3(pool.address, ICurveV2Pool::gammaCall {}),
+                call3(pool.address, ICurveV2Pool::mid_feeCall {}),
+                call3(pool.address, ICurveV2Pool::out_feeCall {}),
+                call3(pool.address, ICurveV2Pool::fee_gammaCall {}),
+            ],
+            AMM::RateAdapter(adapter) => vec![match adapter.source {
+                RateSource::WstEth => {
+                    call3(adapter.wrapped_token, IRateProvider::stEthPerTokenCall {})
+                }
+                RateSource::REth => {
+                    call3(adapter.wrapped_token, IRateProvider::getExchangeRateCall {})
+                }
+                RateSource::CbEth => {
+                    call3(adapter.wrapped_token, IRateProvider::exchangeRateCall {})
+                }
+            }],
+            AMM::BancorV3Pool(pool) => vec![
+                call3(
+                    pool.pool_collection,
+                    IBancorPoolCollection::poolTokenCall {
+                        pool: pool.base_token,
+                    },
+                ),
+                call3(
+                    pool.pool_collection,
+                    IBancorPoolCollection::tradingLiquidityCall {
+                        pool: pool.base_token,
+                    },
+                ),
+                call3(
+                    pool.pool_collection,
+                    IBancorPoolCollection::tradingFeePPMCall {
+                        pool: pool.base_token,
+                    },
+                ),
+                call3(
+                    pool.pool_collection,
+                    IBancorPoolCollection::tradingEnabledCall {
+                        pool: pool.base_token,
+                    },
+                ),
+                call3(
+                    pool.network_settings,
+                    IBancorNetworkSettings::networkFeePPMCall {},
+                ),
+            ],
+            AMM::GmxMarket(market) => market
+                .data_store_keys()
+                .into_iter()
+                .map(|key| call3(market.data_store, IGmxDataStore::getUintCall { key }))
+                .collect(),
+            AMM::AmbientPool(pool) => vec![
+                call3(
+                    pool.query,
+                    ICrocQuery::queryCurveCall {
+                        base: pool.base_token,
+                        quote: pool.quote_token,
+                        poolIdx: U256::from(pool.pool_idx),
+                    },
+                ),
+                call3(
+                    pool.query,
+                    ICrocQuery::queryPoolParamsCall {
+                        base: pool.base_token,
+                        quote: pool.quote_token,
+                        poolIdx: U256::from(pool.pool_idx),
+                    },
+                ),
+                call3(
+                    pool.query,
+                    ICrocQuery::queryLiquidityCall {
+                        base: pool.base_token,
+                        quote: pool.quote_token,
+                        poolIdx: U256::from(pool.pool_idx),
+                    },
+                ),
+            ],
+            AMM::ConstantProductPool(pool) => vec![
+                call3(pool.address, IUniswapV2Pair::token0Call {}),
+                call3(pool.address, IUniswapV2Pair::token1Call {}),
+                reserves_call3(pool),
+            ],
+        })
+        .collect();
+    let pool_data = execute(calls, block_number, mode, provider.clone()).await?;
+
+    // Get the token decimals
+    let tokens = amms
+        .iter()
+        .zip(pool_data.iter())
+        .map(|(amm, data)| match amm {
+            AMM::UniswapV2Pool(_) => [
+                decode::<IUniswapV2Pair::token0Call>(&data[0]).map(|token| token._0),
+                decode::<IUniswapV2Pair::token1Call>(&data[1]).map(|token| token._0),
+            ]
+            .into_iter()
+            .collect::<Option<Vec<Address>>>(),
+            AMM::UniswapV3Pool(_) => [
+                decode::<IUniswapV3Pool::token0Call>(&data[0]).map(|token| token._0),
+                decode::<IUniswapV3Pool::token1Call>(&data[1]).map(|token| token._0),
+            ]
+            .into_iter()
+            .collect::<Option<Vec<Address>>>(),
+            AMM::ERC4626Vault(_) => {
+                decode::<IERC4626Vault::assetCall>(&data[0]).map(|asset| vec![asset._0])
+            }
+            AMM::CurveV2Pool(_) => [
+                decode::<ICurveV2Pool::coinsCall>(&data[0]).map(|token| token._0),
+                decode::<ICurveV2Pool::coinsCall>(&data[1]).map(|token| token._0),
+            ]
+            .into_iter()
+            .collect::<Option<Vec<Address>>>(),
+            AMM::RateAdapter(adapter) => {
+                Some(vec![adapter.wrapped_token, adapter.underlying_token])
+            }
+            AMM::BancorV3Pool(pool) => Some(vec![pool.base_token, BNT]),
+            AMM::GmxMarket(market) => Some(vec![market.long_token, market.short_token]),
+            AMM::AmbientPool(pool) => Some(vec![pool.base_token, pool.quote_token]),
+            AMM::ConstantProductPool(_) => [
+                decode::<IUniswapV2Pair::token0Call>(&data[0]).map(|token| token._0),
+                decode::<IUniswapV2Pair::token1Call>(&data[1]).map(|token| token._0),
+            ]
+            .into_iter()
+            .collect::<Option<Vec<Address>>>(),
+        })
+        .collect::<Vec<Option<Vec<Address>>>>();
+
+    let calls = tokens
+        .iter()
+        .map(|tokens| {
+            tokens
+                .iter()
+                .flatten()
+                .map(|token| call3(*token, IErc20::decimalsCall {}))
+                .collect()
+        })
+        .collect();
+    let decimals = execute(calls, block_number, mode, provider.clone()).await?;
+    let decimals = tokens
+        .iter()
+        .zip(decimals)
+        .map(|(tokens, data)| {
+            tokens
+                .as_ref()?
+                .iter()
+                .zip(data.iter())
+                .map(|(token, decimals)| {
+                    // The zero address stands for the native token (e.g. the base token of Ambient pools)
+                    if token.is_zero() {
+                        Some(18)
+                    } else {
+                        decode::<IErc20::decimalsCall>(decimals).map(|d| d._0)
+                    }
+                })
+                .collect::<Option<Vec<u8>>>()
+        })
+        .collect::<Vec<Option<Vec<u8>>>>();
+
+    // Preview deposits and redeems of 100 and 200 tokens to get the ERC4626 vault fees, and query the liquidity
+    // levels around the price of Ambient pools
+    let calls = amms
+        .iter()
+        .zip(pool_data.iter())
+        .zip(decimals.iter())
+        .map(|((amm, data), decimals)| match (amm, decimals) {
+            (AMM::ERC4626Vault(vault), Some(decimals)) => {
+                let Some(vault_decimals) = decode::<IERC4626Vault::decimalsCall>(&data[1]) else {
+                    return vec![];
+                };
+
+                let assets = U256::from(10).pow(U256::from(decimals[0]));
+                let shares = U256::from(10).pow(U256::from(vault_decimals._0));
+
+                let mut calls = vec![];
+                for multiplier in [U256::from(100), U256::from(200)] {
+                    calls.extend([
+                        call3(
+                            vault.vault_token,
+                            IERC4626Vault::convertToSharesCall {
+                                assets: assets * multiplier,
+                            },
+                        ),
+                        call3(
+                            vault.vault_token,
+                            IERC4626Vault::previewDepositCall {
+                                assets: assets * multiplier,
+                            },
+                        ),
+                        call3(
+                            vault.vault_token,
+                            IERC4626Vault::convertToAssetsCall {
+                                shares: shares * multiplier,
+                            },
+                        ),
+                        call3(
+                            vault.vault_token,
+                            IERC4626Vault::previewRedeemCall {
+                                shares: shares * multiplier,
+                            },
+                        ),
+                    ]);
+                }
+
+                calls
+            }
+            (AMM::AmbientPool(pool), Some(_)) => {
+                let (Some(curve), Some(pool_params)) = (
+                    decode::<ICrocQuery::queryCurveCall>(&data[0]),
+                    decode::<ICrocQuery::queryPoolParamsCall>(&data[1]),
+                ) else {
+                    return vec![];
+                };
+                let Some(tick) = ambient_tick(curve._0.priceRoot_) else {
+                    return vec![];
+                };
+                if pool_params._0.tickSize_ == 0 {
+                    return vec![];
+                }
+
+                let (_, ticks) = AmbientPool::level_ticks(tick, pool_params._0.tickSize_ as i32);
+                ticks
+                    .into_iter()
+                    .map(|tick| {
+                        call3(
+                            pool.query,
+                            ICrocQuery::queryLevelCall {
+                                base: pool.base_token,
+                                quote: pool.quote_token,
+                                poolIdx: U256::from(pool.pool_idx),
+                                tick,
+                            },
+                        )
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        })
+        .collect();
+    let follow_up_data = execute(calls, block_number, mode, provider).await?;
+
+    for ((((amm, data), tokens), decimals), follow_up_data) in amms
+        .iter_mut()
+        .zip(pool_data)
+        .zip(tokens)
+        .zip(decimals)
+        .zip(follow_up_data)
+    {
+        let (Some(tokens), Some(decimals)) = (tokens, decimals) else {
+            continue;
+        };
+
+        match amm {
+            AMM::UniswapV2Pool(pool) => {
+                let Some(reserves) = decode::<IUniswapV2Pair::getReservesCall>(&data[2]) else {
+                    continue;
+                };
+
+                pool.token_a = tokens[0];
+                pool.token_b = tokens[1];
+                pool.token_a_decimals = decimals[0];
+                pool.token_b_decimals = decimals[1];
+                pool.reserve_0 = reserves.reserve0;
+                pool.reserve_1 = reserves.reserve1;
+
+                tracing::trace!(?pool);
+            }
+
+            AMM::UniswapV3Pool(pool) => {
+                let (Some(liquidity), Some(slot_0), Some(tick_spacing), Some(fee)) = (
+                    decode::<IUniswapV3Pool::liquidityCall>(&data[2]),
+                    decode::<IUniswapV3Pool::slot0Call>(&data[3]),
+                    decode::<IUniswapV3Pool::tickSpacingCall>(&data[4]),
+                    decode::<IUniswapV3Pool::feeCall>(&data[5]),
+                ) else {
+                    continue;
+                };
+
+                pool.token_a = tokens[0];
+                pool.token_b = tokens[1];
+                pool.token_a_decimals = decimals[0];
+                pool.token_b_decimals = decimals[1];
+                pool.liquidity = liquidity._0;
+                pool.sqrt_price = slot_0._0;
+                pool.tick = slot_0._1;
+                pool.tick_spacing = tick_spacing._0;
+                pool.fee = fee._0;
+
+                tracing::trace!(?pool);
+            }
+
+            AMM::ERC4626Vault(vault) => {
+                let (Some(vault_decimals), Some(vault_reserve), Some(asset_reserve), Some(fees)) = (
+                    decode::<IERC4626Vault::decimalsCall>(&data[1]),
+                    decode::<IERC4626Vault::totalSupplyCall>(&data[2]),
+                    decode::<IERC4626Vault::totalAssetsCall>(&data[3]),
+                    decode_vault_fees(&follow_up_data),
+                ) else {
+                    continue;
+                };
+
+                // Ignore vaults without a relative fee, same as the batch request contract
+                let (Some(deposit_fee), Some(withdraw_fee)) = fees else {
+                    continue;
+                };
+
+                vault.asset_token = tokens[0];
+                vault.asset_token_decimals = decimals[0];
+                vault.vault_token_decimals = vault_decimals._0;
+                vault.vault_reserve = vault_reserve._0;
+                vault.asset_reserve = asset_reserve._0;
+                vault.deposit_fee = deposit_fee;
+                vault.withdraw_fee = withdraw_fee;
+
+                tracing::trace!(?vault);
+            }
+
+            AMM::CurveV2Pool(pool) => {
+                let (Some(balance_0), Some(balance_1), Some(price_scale), Some(d)) = (
+                    decode::<ICurveV2Pool::balancesCall>(&data[2]),
+                    decode::<ICurveV2Pool::balancesCall>(&data[3]),
+                    decode::<ICurveV2Pool::price_scaleCall>(&data[4]),
+                    decode::<ICurveV2Pool::DCall>(&data[5]),
+                ) else {
+                    continue;
+                };
+                let (Some(a), Some(gamma), Some(mid_fee), Some(out_fee), Some(fee_gamma)) = (
+                    decode::<ICurveV2Pool::ACall>(&data[6]),
+                    decode::<ICurveV2Pool::gammaCall>(&data[7]),
+                    decode::<ICurveV2Pool::mid_feeCall>(&data[8]),
+                    decode::<ICurveV2Pool::out_feeCall>(&data[9]),
+                    decode::<ICurveV2Pool::fee_gammaCall>(&data[10]),
+                ) else {
+                    continue;
+                };
+
+                pool.token_a = tokens[0];
+                pool.token_b = tokens[1];
+                pool.token_a_decimals = decimals[0];
+                pool.token_b_decimals = decimals[1];
+                pool.balance_0 = balance_0._0;
+                pool.balance_1 = balance_1._0;
+                pool.price_scale = price_scale._0;
+                pool.d = d._0;
+                pool.a = a._0;
+                pool.gamma = gamma._0;
+                pool.mid_fee = mid_fee._0;
+                pool.out_fee = out_fee._0;
+                pool.fee_gamma = fee_gamma._0;
+
+                tracing::trace!(?pool);
+            }
+
+            AMM::RateAdapter(adapter) => {
+                // All rate getters return a single uint256
+                let Some(rate) = decode::<IRateProvider::exchangeRateCall>(&data[0]) else {
+                    continue;
+                };
+
+                adapter.wrapped_token_decimals = decimals[0];
+                adapter.underlying_token_decimals = decimals[1];
+                adapter.rate = rate._0;
+
+                tracing::trace!(?adapter);
+            }
+
+            AMM::BancorV3Pool(pool) => {
+                let (
+                    Some(pool_token),
+                    Some(trading_liquidity),
+                    Some(trading_fee),
+                    Some(trading_enabled),
+                    Some(network_fee),
+                ) = (
+                    decode::<IBancorPoolCollection::poolTokenCall>(&data[0]),
+                    decode::<IBancorPoolCollection::tradingLiquidityCall>(&data[1]),
+                    decode::<IBancorPoolCollection::tradingFeePPMCall>(&data[2]),
+                    decode::<IBancorPoolCollection::tradingEnabledCall>(&data[3]),
+                    decode::<IBancorNetworkSettings::networkFeePPMCall>(&data[4]),
+                )
+                else {
+                    continue;
+                };
+
+                pool.address = pool_token._0;
+                pool.base_token_decimals = decimals[0];
+                pool.bnt_trading_liquidity = trading_liquidity._0.bntTradingLiquidity;
+                pool.base_token_trading_liquidity = trading_liquidity._0.baseTokenTradingLiquidity;
+                pool.trading_fee_ppm = trading_fee._0;
+                pool.network_fee_ppm = network_fee._0;
+                pool.trading_enabled = trading_enabled._0;
+
+                tracing::trace!(?pool);
+            }
+
+            AMM::GmxMarket(market) => {
+                let Some(values) = data
+                    .iter()
+                    .map(|value| decode::<IGmxDataStore::getUintCall>(value).map(|value| value._0))
+                    .collect::<Option<Vec<U256>>>()
+                    .and_then(|values| <[U256; 9]>::try_from(values).ok())
+                else {
+                    continue;
+                };
+
+                market.long_token_decimals = decimals[0];
+                market.short_token_decimals = decimals[1];
+                market.apply_data_store_values(values);
+
+                tracing::trace!(?market);
+            }
+
+            AMM::AmbientPool(pool) => {
+                let (Some(curve), Some(pool_params), Some(liquidity)) = (
+                    decode::<ICrocQuery::queryCurveCall>(&data[0]),
+                    decode::<ICrocQuery::queryPoolParamsCall>(&data[1]),
+                    decode::<ICrocQuery::queryLiquidityCall>(&data[2]),
+                ) else {
+                    continue;
+                };
+                let Some(tick) = ambient_tick(curve._0.priceRoot_) else {
+                    continue;
+                };
+                let tick_size = pool_params._0.tickSize_ as i32;
+                if tick_size == 0 {
+                    continue;
+                }
+
+                let (window, ticks) = AmbientPool::level_ticks(tick, tick_size);
+                let Some(levels) = ticks
+                    .into_iter()
+                    .zip(follow_up_data.iter())
+                    .map(|(tick, level)| {
+                        decode::<ICrocQuery::queryLevelCall>(level)
+                            .map(|level| (tick, level.bidLots.to(), level.askLots.to()))
+                    })
+                    .collect::<Option<Vec<(i32, u128, u128)>>>()
+                else {
+                    continue;
+                };
+
+                pool.base_token_decimals = decimals[0];
+                pool.quote_token_decimals = decimals[1];
+                pool.sqrt_price = U256::from(curve._0.priceRoot_) << 32;
+                pool.tick = tick;
+                pool.concentrated_liquidity = curve._0.concLiq_;
+                pool.ambient_liquidity = liquidity._0.saturating_sub(curve._0.concLiq_);
+                pool.fee = pool_params._0.feeRate_ as u32;
+                pool.tick_size = tick_size;
+                pool.apply_levels(window, levels);
+
+                tracing::trace!(?pool);
+            }
+
+            AMM::ConstantProductPool(pool) => {
+                let Some((reserve_0, reserve_1)) = data[2]
+                    .as_ref()
+                    .and_then(|data| pool.config.decode_reserves_return(data))
+                else {
+                    continue;
+                };
+
+                pool.token_a = tokens[0];
+                pool.token_b = tokens[1];
+                pool.token_a_decimals = decimals[0];
+                pool.token_b_decimals = decimals[1];
+                pool.reserve_0 = reserve_0;
+                pool.reserve_1 = reserve_1;
+
+                tracing::trace!(?pool);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the tick of an Ambient pool from the square root of its price, as a Q64.64.
+fn ambient_tick(price_root: u128) -> Option<i32> {
+    uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(U256::from(price_root) << 32).ok()
+}
+
+/// Decodes the deposit and withdraw fees from the `convertToShares`/`previewDeposit`/`convertToAssets`/`previewRedeem` previews.
+fn decode_vault_fees(data: &[Option<Bytes>]) -> Option<(Option<u32>, Option<u32>)> {
+    if data.len() != 8 {
+        return None;
+    }
+
+    let convert_to_shares_1 = decode::<IERC4626Vault::convertToSharesCall>(&data[0])?._0;
+    let preview_deposit_1 = decode::<IERC4626Vault::previewDepositCall>(&data[1])?._0;
+    let convert_to_assets_1 = decode::<IERC4626Vault::convertToAssetsCall>(&data[2])?._0;
+    let preview_redeem_1 = decode::<IERC4626Vault::previewRedeemCall>(&data[3])?._0;
+    let convert_to_shares_2 = decode::<IERC4626Vault::convertToSharesCall>(&data[4])?._0;
+    let preview_deposit_2 = decode::<IERC4626Vault::previewDepositCall>(&data[5])?._0;
+    let convert_to_assets_2 = decode::<IERC4626Vault::convertToAssetsCall>(&data[6])?._0;
+    let preview_redeem_2 = decode::<IERC4626Vault::previewRedeemCall>(&data[7])?._0;
+
+    let deposit_fee = fee_from_deltas(
+        convert_to_shares_1.checked_sub(preview_deposit_1)?,
+        convert_to_shares_2.checked_sub(preview_deposit_2)?,
+        convert_to_shares_1,
+    );
+    let withdraw_fee = fee_from_deltas(
+        convert_to_assets_1.checked_sub(preview_redeem_1)?,
+        convert_to_assets_2.checked_sub(preview_redeem_2)?,
+        convert_to_assets_1,
+    );
+
+    Some((deposit_fee, withdraw_fee))
+}
+
+/// Executes the calls of each AMM with `mode`, see [`aggregate`] and [`call_individually`].
+async fn execute<T, N, P>(
+    calls: Vec<Vec<IMulticall3::Call3>>,
+    block_number: Option<u64>,
+    mode: CallMode,
+    provider: Arc<P>,
+) -> Result<Vec<Vec<Option<Bytes>>>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    match mode {
+        CallMode::Multicall3 => aggregate(calls, block_number, provider).await,
+        CallMode::Individual => call_individually(calls, block_number, provider).await,
+    }
+}
+
+/// Executes the calls of each AMM as individual `eth_call`s.
+///
+/// Returns the return data of each call grouped per AMM, or `None` for calls that reverted.
+pub(crate) async fn call_individually<T, N, P>(
+    calls: Vec<Vec<IMulticall3::Call3>>,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<Vec<Vec<Option<Bytes>>>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let block = block_number.map_or("latest".to_string(), |block| format!("{block:#x}"));
+    let lengths = calls.iter().map(Vec::len).collect::<Vec<usize>>();
+
+    let return_data: Vec<Option<Bytes>> = stream::iter(calls.into_iter().flatten())
+        .map(|call| {
+            let provider = provider.clone();
+            let block = block.clone();
+            async move {
+                let tx = json!({ "to": call.target, "data": call.callData });
+                match provider
+                    .client()
+                    .request::<_, Bytes>("eth_call", (tx, block))
+                    .await
+                {
+                    Ok(return_data) => Ok(Some(return_data)),
+                    Err(RpcError::ErrorResp(err)) => {
+                        tracing::trace!(target = ?call.target, ?err, "call reverted");
+                        Ok(None)
+                    }
+                    Err(err) => Err(AMMError::from(err)),
+                }
+            }
+        })
+        .buffered(MAX_CONCURRENT_CALLS)
+        .try_collect()
+        .await?;
+
+    let mut results = return_data.into_iter();
+
+    Ok(lengths
+        .into_iter()
+        .map(|len| results.by_ref().take(len).collect())
+        .collect())
+}
+
+/// Executes the calls of each AMM in a single `aggregate3` call.
+///
+/// Returns the return data of each call grouped per AMM, or `None` for calls that reverted.
+pub(crate) async fn aggregate<T, N, P>(
+    calls: Vec<Vec<IMulticall3::Call3>>,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<Vec<Vec<Option<Bytes>>>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let lengths = calls.iter().map(Vec::len).collect::<Vec<usize>>();
+    let calls = calls
+        .into_iter()
+        .flatten()
+        .collect::<Vec<IMulticall3::Call3>>();
+
+    let return_data = if calls.is_empty() {
+        vec![]
+    } else {
+        let multicall = IMulticall3::new(MULTICALL3_ADDRESS, provider);
+        let call = multicall.aggregate3(calls);
+        let IMulticall3::aggregate3Return { returnData } = if let Some(block_number) = block_number
+        {
+            call.block(block_number.into()).call().await?
+        } else {
+            call.call().await?
+        };
+
+        returnData
+    };
+
+    let mut results = return_data
+        .into_iter()
+        .map(|result| result.success.then_some(result.returnData));
+
+    Ok(lengths
+        .into_iter()
+        .map(|len| results.by_ref().take(len).collect())
+        .collect())
+}
+
+pub(crate) fn call3<C: SolCall>(target: Address, call: C) -> IMulticall3::Call3 {
+    IMulticall3::Call3 {
+        target,
+        allowFailure: true,
+        callData: call.abi_encode().into(),
+    }
+}
+
+/// Calls the reserves getter of the config of `pool`.
+fn reserves_call3(pool: &ConstantProductPool) -> IMulticall3::Call3 {
+    IMulticall3::Call3 {
+        target: pool.address,
+        allowFailure: true,
+        callData: pool.config.reserves_selector.to_vec().into(),
+    }
+}
+
+pub(crate) fn decode<C: SolCall>(data: &Option<Bytes>) -> Option<C::Return> {
+    C::abi_decode_returns(data.as_ref()?, true).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{primitives::address, providers::ProviderBuilder};
+
+    use crate::amm::{
+        uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, AMM,
+    };
+
+    use super::get_amm_data_batch_request;
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_get_amm_data_multicall() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let mut amms = vec![
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+                ..Default::default()
+            }),
+            AMM::UniswapV3Pool(UniswapV3Pool {
+                address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+                ..Default::default()
+            }),
+        ];
+
+        get_amm_data_batch_request(&mut amms, None, provider)
+            .await
+            .unwrap();
+
+        for amm in amms.iter() {
+            assert_eq!(
+                amm.tokens(),
+                vec![
+                    address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+                    address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2")
+                ]
+            );
+        }
+
+        if let AMM::UniswapV2Pool(pool) = &amms[0] {
+            assert_eq!(pool.token_a_decimals, 6);
+            assert_eq!(pool.token_b_decimals, 18);
+            assert!(pool.reserve_0 > 0 && pool.reserve_1 > 0);
+        }
+
+        if let AMM::UniswapV3Pool(pool) = &amms[1] {
+            assert_eq!(pool.fee, 500);
+            assert_eq!(pool.tick_spacing, 10);
+            assert!(pool.liquidity > 0);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! On-chain swap simulation, for pools that can not be modeled locally.
+//!
+//! Swaps are executed with `eth_call` through a swap executor contract injected with a state override. The executor
+//! calls the pool and reverts in the swap callback with the token deltas, so no tokens need to be funded or approved.
+//! Pools with the Uniswap V3 `swap` interface and callback arguments are supported, including forks with modified
+//! logic.
+
+use std::sync::Arc;
+
+use alloy::{
+    network::Network,
+    primitives::{address, Address, Bytes, I256, U256},
+    providers::Provider,
+    rpc::types::eth::{
+        state::{AccountOverride, StateOverride},
+        BlockId, BlockNumberOrTag, TransactionInput, TransactionRequest,
+    },
+    transports::Transport,
+};
+use uniswap_v3_math::tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO};
+
+use crate::{
+    amm::{consts::U256_1, uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, AMM},
+    errors::AMMError,
+};
+
+/// Address the swap executor is deployed at.
+pub const SWAP_EXECUTOR_ADDRESS: Address = address!("00000000000000000000000000000000a3355e7e");
+
+/// Sender of the executed swaps.
+pub const SWAP_CALLER: Address = address!("00000000000000000000000000000000a3355ca1");
+
+/// Runtime bytecode of the swap executor.
+///
+/// Called by an EOA with the pool address followed by the swap calldata, it calls the pool and returns its revert
+/// data. Called by anything else, i.e. the swap callback of the pool, it reverts with the first two words of the
+/// arguments, the token deltas of the swap.
+pub const SWAP_EXECUTOR_CODE: [u8; 57] = [
+    0x32, 0x33, 0x14, 0x60, 0x12, 0x57, // if origin == caller jump to call
+    0x60, 0x40, 0x60, 0x04, 0x60, 0x00, 0x37, // calldatacopy(0, 4, 64)
+    0x60, 0x40, 0x60, 0x00, 0xfd, // revert(0, 64)
+    0x5b, 0x36, 0x60, 0x14, 0x90, 0x03, // call: len = calldatasize - 20
+    0x80, 0x60, 0x14, 0x60, 0x00, 0x37, // calldatacopy(0, 20, len)
+    0x60, 0x00, 0x60, 0x00, 0x82, 0x60, 0x00, 0x60, 0x00, // call(gas, pool, 0, 0, len, 0, 0)
+    0x60, 0x00, 0x35, 0x60, 0x60, 0x1c, 0x5a, 0xf1, //
+    0x3d, 0x60, 0x00, 0x60, 0x00, 0x3e, // returndatacopy(0, 0, returndatasize)
+    0x3d, 0x60, 0x00, 0xf3, // return(0, returndatasize)
+];
+
+/// Returns the input of the swap executor calling `pool` with `swap_calldata`.
+pub fn swap_executor_input(pool: Address, swap_calldata: &[u8]) -> Bytes {
+    let mut input = pool.to_vec();
+    input.extend_from_slice(swap_calldata);
+    input.into()
+}
+
+/// Decodes the token deltas returned by the swap executor, positive deltas are paid to the pool.
+pub fn decode_swap_deltas(output: &[u8]) -> Result<(I256, I256), AMMError> {
+    if output.len() != 64 {
+        return Err(AMMError::UnexpectedSwapOutput(Bytes::copy_from_slice(
+            output,
+        )));
+    }
+
+    Ok((
+        I256::from_raw(U256::from_be_slice(&output[..32])),
+        I256::from_raw(U256::from_be_slice(&output[32..])),
+    ))
+}
+
+impl UniswapV3Pool {
+    /// Returns the calldata of a swap of `amount_in` of `token_in` until the price limit, paid to the swap executor.
+    pub fn executor_swap_calldata(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<Bytes, AMMError> {
+        let zero_for_one = self.is_token0(token_in);
+        let sqrt_price_limit_x_96 = if zero_for_one {
+            MIN_SQRT_RATIO + U256_1
+        } else {
+            MAX_SQRT_RATIO - U256_1
+        };
+
+        let swap_calldata = self.swap_calldata(
+            SWAP_EXECUTOR_ADDRESS,
+            zero_for_one,
+            I256::from_raw(amount_in),
+            sqrt_price_limit_x_96,
+            vec![],
+        )?;
+
+        Ok(swap_executor_input(self.address, &swap_calldata))
+    }
+
+    /// Simulates a swap with `eth_call` at `block_number`, the latest block if `None`, returning the amount out.
+    ///
+    /// `overrides` are applied on top of the swap executor, e.g. to override the state of the pool.
+    pub async fn simulate_swap_onchain<T, N, P>(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+        provider: Arc<P>,
+        block_number: Option<u64>,
+        mut overrides: StateOverride,
+    ) -> Result<U256, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let tx = TransactionRequest {
+            from: Some(SWAP_CALLER),
+            to: Some(SWAP_EXECUTOR_ADDRESS.into()),
+            input: TransactionInput::new(self.executor_swap_calldata(token_in, amount_in)?),
+            ..Default::default()
+        };
+
+        overrides.insert(
+            SWAP_EXECUTOR_ADDRESS,
+            AccountOverride {
+                code: Some(Bytes::from_static(&SWAP_EXECUTOR_CODE)),
+                ..Default::default()
+            },
+        );
+
+        let block = block_number.map_or(BlockId::Number(BlockNumberOrTag::Latest), BlockId::from);
+        let output: Bytes = provider
+            .client()
+            .request("eth_call", (tx, block, overrides))
+            .await?;
+
+        let (amount_0, amount_1) = decode_swap_deltas(&output)?;
+
+        // Negative deltas are received by the swapper
+        let amount_out = if self.is_token0(token_in) {
+            amount_1
+        } else {
+            amount_0
+        };
+        Ok(amount_out.unsigned_abs())
+    }
+}
+
+impl AMM {
+    /// Simulates a swap with `eth_call`, see [`UniswapV3Pool::simulate_swap_onchain`].
+    ///
+    /// Fails with [`AMMError::UnsupportedOnchainSimulation`] for AMMs without the Uniswap V3 swap interface.
+    pub async fn simulate_swap_onchain<T, N, P>(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+        provider: Arc<P>,
+        block_number: Option<u64>,
+        overrides: StateOverride,
+    ) -> Result<U256, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        match self {
+            AMM::UniswapV3Pool(pool) => {
+                pool.simulate_swap_onchain(token_in, amount_in, provider, block_number, overrides)
+                    .await
+            }
+            amm => Err(AMMError::UnsupportedOnchainSimulation(amm.address())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, Address, I256, U256};
+
+    use crate::amm::uniswap_v3::UniswapV3Pool;
+
+    use super::{decode_swap_deltas, SWAP_EXECUTOR_ADDRESS};
+
+    #[test]
+    fn test_executor_swap_calldata() {
+        let pool = UniswapV3Pool {
+            address: address!("88e6a0c2ddd26feeb64f039a2c41296fcb3f5640"),
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            ..Default::default()
+        };
+
+        let input = pool
+            .executor_swap_calldata(pool.token_a, U256::from(1_000_000))
+            .unwrap();
+        assert_eq!(Address::from_slice(&input[..20]), pool.address);
+        // swap(address,bool,int256,uint160,bytes) paying the executor
+        assert_eq!(input[20..24], [0x12, 0x8a, 0xcb, 0x08]);
+        assert_eq!(Address::from_slice(&input[36..56]), SWAP_EXECUTOR_ADDRESS);
+
+        let mut output = [0u8; 64];
+        output[31] = 7;
+        output[32..].copy_from_slice(&(-I256::from_raw(U256::from(4))).to_be_bytes::<32>());
+        assert_eq!(
+            decode_swap_deltas(&output).unwrap(),
+            (
+                I256::from_raw(U256::from(7)),
+                -I256::from_raw(U256::from(4))
+            )
+        );
+        assert!(decode_swap_deltas(&output[..32]).is_err());
+    }
+}
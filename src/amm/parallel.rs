@@ -0,0 +1,68 @@
+use alloy::primitives::{Address, U256};
+use rayon::prelude::*;
+
+use crate::errors::SwapSimulationError;
+
+use super::{AutomatedMarketMaker, AMM};
+
+/// Simulates a swap of `amount_in` of `token_in` for `token_out` across all pools trading the pair, in parallel.
+///
+/// Pools that do not trade `token_in` for `token_out` are skipped.
+/// Returns the pool address and simulation result for each pool, in the order of `pools`.
+pub fn simulate_swap_parallel(
+    pools: &[AMM],
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+) -> Vec<(Address, Result<U256, SwapSimulationError>)> {
+    pools
+        .par_iter()
+        .filter(|amm| {
+            let tokens = amm.tokens();
+            tokens.contains(&token_in) && tokens.contains(&token_out)
+        })
+        .map(|amm| (amm.address(), amm.simulate_swap(token_in, amount_in)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, Address, U256};
+
+    use crate::amm::{uniswap_v2::UniswapV2Pool, AutomatedMarketMaker, AMM};
+
+    use super::simulate_swap_parallel;
+
+    #[test]
+    fn test_simulate_swap_parallel() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let dai = address!("6b175474e89094c44da98b954eedeac495271d0f");
+
+        let pools = (1..=100_u128)
+            .map(|i| {
+                AMM::UniswapV2Pool(UniswapV2Pool {
+                    address: Address::with_last_byte(i as u8),
+                    token_a: usdc,
+                    token_b: if i % 2 == 0 { weth } else { dai },
+                    reserve_0: i * 1_000_000_000,
+                    reserve_1: i * 1_000_000_000_000_000_000,
+                    fee: 300,
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<AMM>>();
+
+        let amount_in = U256::from(1_000_000);
+        let quotes = simulate_swap_parallel(&pools, usdc, weth, amount_in);
+        assert_eq!(quotes.len(), 50);
+
+        for (address, amount_out) in quotes {
+            let pool = pools.iter().find(|amm| amm.address() == address).unwrap();
+            assert_eq!(
+                amount_out.unwrap(),
+                pool.simulate_swap(usdc, amount_in).unwrap()
+            );
+        }
+    }
+}
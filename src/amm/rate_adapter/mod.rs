@@ -0,0 +1,349 @@
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+use alloy::{
+    primitives::{Address, B256, U256},
+    rpc::types::eth::Log,
+    sol,
+    sol_types::SolEvent,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{multicall, AMM},
+    errors::{AMMError, ErrorContext, ResultExt},
+};
+use crate::{
+    amm::{AutomatedMarketMaker, Protocol},
+    core::price::u256_to_f64,
+    errors::{ArithmeticError, EventLogError, SwapSimulationError},
+};
+
+sol! {
+    /// Exchange rate getters of wrapped liquid staking tokens
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract IRateProvider {
+        event ExchangeRateUpdated(address indexed oracle, uint256 newExchangeRate);
+        function stEthPerToken() external view returns (uint256);
+        function getExchangeRate() external view returns (uint256);
+        function exchangeRate() external view returns (uint256);
+    }
+}
+
+/// Precision of the exchange rates.
+pub const RATE_PRECISION: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+
+/// Estimated gas used by a wrap or unwrap.
+pub const SWAP_GAS_ESTIMATE: u64 = 60_000;
+
+/// Contract function the exchange rate of a [`RateAdapter`] is read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateSource {
+    /// `stEthPerToken()` of wstETH.
+    #[default]
+    WstEth,
+    /// `getExchangeRate()` of rETH.
+    REth,
+    /// `exchangeRate()` of cbETH, also updated from its `ExchangeRateUpdated` logs.
+    CbEth,
+}
+
+/// Wrapping and unwrapping of a wrapped token (e.g. wstETH) at the exchange rate of the wrapper, without slippage.
+///
+/// Adapters expose wrap and unwrap edges to routing, so that cross-rate arbitrage between the wrapped token and its
+/// underlying token can be detected. Rates that do not change through logs of the wrapper are refreshed with
+/// [`AutomatedMarketMaker::sync`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateAdapter {
+    /// Wrapped token, also the address of the adapter.
+    pub wrapped_token: Address,
+    pub wrapped_token_decimals: u8,
+    /// Token the wrapped token is redeemable for, e.g. stETH for wstETH or WETH for rETH.
+    pub underlying_token: Address,
+    pub underlying_token_decimals: u8,
+    /// Underlying tokens per wrapped token, see [`RATE_PRECISION`].
+    pub rate: U256,
+    pub source: RateSource,
+}
+
+#[async_trait]
+impl AutomatedMarketMaker for RateAdapter {
+    fn address(&self) -> Address {
+        self.wrapped_token
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::RateAdapter
+    }
+
+    fn fee_bps(&self) -> u32 {
+        0
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        self.rate = self
+            .get_rate(provider)
+            .await
+            .context(ErrorContext::sync(self.wrapped_token))?;
+        tracing::info!(rate = ?self.rate, address = ?self.wrapped_token, "RateAdapter sync");
+
+        Ok(())
+    }
+
+    fn sync_on_event_signatures(&self) -> Vec<B256> {
+        match self.source {
+            RateSource::CbEth => vec![IRateProvider::ExchangeRateUpdated::SIGNATURE_HASH],
+            RateSource::WstEth | RateSource::REth => vec![],
+        }
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn sync_from_log(&mut self, log: Log) -> Result<(), EventLogError> {
+        let event_signature = log.topics()[0];
+
+        if event_signature == IRateProvider::ExchangeRateUpdated::SIGNATURE_HASH {
+            let rate_event = IRateProvider::ExchangeRateUpdated::decode_log(log.as_ref(), true)?;
+            self.rate = rate_event.newExchangeRate;
+            tracing::debug!(rate = ?self.rate, address = ?self.wrapped_token, "RateAdapter exchange rate event");
+
+            Ok(())
+        } else {
+            Err(EventLogError::InvalidEventSignature)
+        }
+    }
+
+    #[cfg(feature = "provider")]
+    #[instrument(skip(self, provider), level = "debug")]
+    async fn populate_data<T, N, P>(
+        &mut self,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut amms = [AMM::RateAdapter(self.clone())];
+        multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+            .await
+            .context(ErrorContext::populate(self.wrapped_token, block_number))?;
+        if let [AMM::RateAdapter(adapter)] = amms {
+            *self = adapter;
+        }
+
+        Ok(())
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.wrapped_token, self.underlying_token]
+    }
+
+    fn calculate_price(&self, base_token: Address) -> Result<f64, ArithmeticError> {
+        if self.rate.is_zero() {
+            return Err(ArithmeticError::YIsZero);
+        }
+
+        let rate = u256_to_f64(self.rate) / u256_to_f64(RATE_PRECISION);
+        if base_token == self.wrapped_token {
+            Ok(rate)
+        } else {
+            Ok(1.0 / rate)
+        }
+    }
+
+    fn simulate_swap(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        if token_in == self.wrapped_token {
+            Ok(self.unwrap_amount(amount_in))
+        } else {
+            Ok(self.wrap_amount(amount_in))
+        }
+    }
+
+    /// The exchange rate does not depend on the amount swapped, so the adapter is not mutated.
+    fn simulate_swap_mut(
+        &mut self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        self.simulate_swap(token_in, amount_in)
+    }
+
+    fn get_token_out(&self, token_in: Address) -> Address {
+        if token_in == self.wrapped_token {
+            self.underlying_token
+        } else {
+            self.wrapped_token
+        }
+    }
+
+    fn swap_gas_estimate(&self, _token_in: Address, _amount_in: U256) -> u64 {
+        SWAP_GAS_ESTIMATE
+    }
+}
+
+impl RateAdapter {
+    pub fn new(wrapped_token: Address, underlying_token: Address, source: RateSource) -> Self {
+        RateAdapter {
+            wrapped_token,
+            wrapped_token_decimals: 18,
+            underlying_token,
+            underlying_token_decimals: 18,
+            rate: U256::ZERO,
+            source,
+        }
+    }
+
+    #[cfg(feature = "provider")]
+    pub async fn new_from_address<T, N, P>(
+        wrapped_token: Address,
+        underlying_token: Address,
+        source: RateSource,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut adapter = RateAdapter::new(wrapped_token, underlying_token, source);
+        adapter.populate_data(None, provider).await?;
+
+        if !adapter.data_is_populated() {
+            return Err(AMMError::PoolDataError);
+        }
+
+        Ok(adapter)
+    }
+
+    pub fn data_is_populated(&self) -> bool {
+        !(self.wrapped_token.is_zero() || self.underlying_token.is_zero() || self.rate.is_zero())
+    }
+
+    /// Returns the exchange rate of the wrapper.
+    #[cfg(feature = "provider")]
+    pub async fn get_rate<T, N, P>(&self, provider: Arc<P>) -> Result<U256, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let wrapper = IRateProvider::new(self.wrapped_token, provider);
+
+        let rate = match self.source {
+            RateSource::WstEth => wrapper.stEthPerToken().call().await?._0,
+            RateSource::REth => wrapper.getExchangeRate().call().await?._0,
+            RateSource::CbEth => wrapper.exchangeRate().call().await?._0,
+        };
+
+        Ok(rate)
+    }
+
+    /// Returns the underlying tokens received for unwrapping `amount_in` wrapped tokens.
+    pub fn unwrap_amount(&self, amount_in: U256) -> U256 {
+        amount_in * self.rate * self.decimals_factor(self.underlying_token_decimals)
+            / (RATE_PRECISION * self.decimals_factor(self.wrapped_token_decimals))
+    }
+
+    /// Returns the wrapped tokens received for wrapping `amount_in` underlying tokens.
+    pub fn wrap_amount(&self, amount_in: U256) -> U256 {
+        if self.rate.is_zero() {
+            return U256::ZERO;
+        }
+
+        amount_in * RATE_PRECISION * self.decimals_factor(self.wrapped_token_decimals)
+            / (self.rate * self.decimals_factor(self.underlying_token_decimals))
+    }
+
+    fn decimals_factor(&self, decimals: u8) -> U256 {
+        U256::from(10).pow(U256::from(decimals))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog, U256},
+        rpc::types::eth::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::amm::AutomatedMarketMaker;
+
+    use super::{IRateProvider, RateAdapter, RateSource};
+
+    #[test]
+    fn test_simulate_swap() {
+        let wsteth = address!("7f39C581F595B53c5cb19bD0b3f8dA6c935E2Ca0");
+        let steth = address!("ae7ab96520DE3A18E5e111B5EaAb095312D7fE84");
+
+        let adapter = RateAdapter {
+            rate: U256::from(1_150_000_000_000_000_000_u64),
+            ..RateAdapter::new(wsteth, steth, RateSource::WstEth)
+        };
+
+        let one = U256::from(1_000_000_000_000_000_000_u64);
+        assert_eq!(
+            adapter.simulate_swap(wsteth, one).unwrap(),
+            U256::from(1_150_000_000_000_000_000_u64)
+        );
+        assert_eq!(
+            adapter
+                .simulate_swap(steth, U256::from(1_150_000_000_000_000_000_u64))
+                .unwrap(),
+            one
+        );
+        assert_eq!(adapter.get_token_out(steth), wsteth);
+        assert_eq!(adapter.calculate_price(wsteth).unwrap(), 1.15);
+
+        // Quotes do not depend on the amount
+        assert_eq!(
+            adapter
+                .simulate_swap(wsteth, one * U256::from(1_000))
+                .unwrap(),
+            adapter.simulate_swap(wsteth, one).unwrap() * U256::from(1_000)
+        );
+    }
+
+    #[test]
+    fn test_sync_from_log() {
+        let cbeth = address!("Be9895146f7AF43049ca1c1AE358B0541Ea49704");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let mut adapter = RateAdapter::new(cbeth, weth, RateSource::CbEth);
+        assert_eq!(adapter.sync_on_event_signatures().len(), 1);
+
+        let log = Log {
+            inner: PrimitiveLog {
+                address: cbeth,
+                data: IRateProvider::ExchangeRateUpdated {
+                    oracle: Address::ZERO,
+                    newExchangeRate: U256::from(1_080_000_000_000_000_000_u64),
+                }
+                .encode_log_data(),
+            },
+            block_number: Some(20_000_000),
+            ..Default::default()
+        };
+        adapter.sync_from_log(log).unwrap();
+
+        assert_eq!(adapter.rate, U256::from(1_080_000_000_000_000_000_u64));
+        assert!(adapter.data_is_populated());
+    }
+}
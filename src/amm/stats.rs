@@ -0,0 +1,196 @@
+//! Cumulative swap volume and fee statistics of a pool, updated from its Swap logs.
+
+use std::collections::VecDeque;
+
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+
+use super::AMM;
+
+/// Amounts of a swap, from the point of view of the pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapRecord {
+    pub block_number: u64,
+    pub amount_0_in: U256,
+    pub amount_1_in: U256,
+    pub amount_0_out: U256,
+    pub amount_1_out: U256,
+    /// Fee paid in token 0, included in `amount_0_in`.
+    pub fee_0: U256,
+    /// Fee paid in token 1, included in `amount_1_in`.
+    pub fee_1: U256,
+}
+
+/// Volume and fees of the swaps since the last reset, or within the last `window` blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStats {
+    /// Amount of token 0 swapped in either direction.
+    pub volume_0: U256,
+    /// Amount of token 1 swapped in either direction.
+    pub volume_1: U256,
+    pub fees_0: U256,
+    pub fees_1: U256,
+    pub swaps: u64,
+    /// Block of the last swap, kept across window evictions.
+    pub last_swap_block: Option<u64>,
+    /// Number of blocks covered by the stats, up to the last swap block, `None` to accumulate since the last reset.
+    window: Option<u64>,
+    /// Swaps within the window, empty without a window.
+    records: VecDeque<SwapRecord>,
+}
+
+impl PoolStats {
+    /// Creates empty stats covering the last `window` blocks.
+    pub fn with_window(window: u64) -> Self {
+        Self {
+            window: Some(window),
+            ..Default::default()
+        }
+    }
+
+    pub fn window(&self) -> Option<u64> {
+        self.window
+    }
+
+    /// Sets the number of blocks covered by the stats and resets them.
+    pub fn set_window(&mut self, window: Option<u64>) {
+        self.reset();
+        self.window = window;
+    }
+
+    /// Clears the stats, keeping the window.
+    pub fn reset(&mut self) {
+        *self = Self {
+            window: self.window,
+            ..Default::default()
+        };
+    }
+
+    /// Returns whether the pool has not swapped since `block_number`.
+    pub fn is_inactive_since(&self, block_number: u64) -> bool {
+        self.last_swap_block
+            .map_or(true, |last_swap_block| last_swap_block < block_number)
+    }
+
+    /// Adds a swap to the stats, evicting swaps that fell out of the window.
+    pub fn record_swap(&mut self, record: SwapRecord) {
+        self.volume_0 += record.amount_0_in + record.amount_0_out;
+        self.volume_1 += record.amount_1_in + record.amount_1_out;
+        self.fees_0 += record.fee_0;
+        self.fees_1 += record.fee_1;
+        self.swaps += 1;
+        self.last_swap_block = Some(
+            self.last_swap_block
+                .map_or(record.block_number, |block| block.max(record.block_number)),
+        );
+
+        let Some(window) = self.window else {
+            return;
+        };
+
+        self.records.push_back(record);
+
+        let last_swap_block = self.last_swap_block.unwrap_or_default();
+        while let Some(oldest) = self.records.front() {
+            if oldest.block_number + window > last_swap_block {
+                break;
+            }
+
+            let oldest = self.records.pop_front().expect("record exists");
+            self.volume_0 -= oldest.amount_0_in + oldest.amount_0_out;
+            self.volume_1 -= oldest.amount_1_in + oldest.amount_1_out;
+            self.fees_0 -= oldest.fee_0;
+            self.fees_1 -= oldest.fee_1;
+            self.swaps -= 1;
+        }
+    }
+}
+
+impl AMM {
+    /// Returns the swap stats of the AMM, `None` if the AMM does not track them.
+    pub fn stats(&self) -> Option<&PoolStats> {
+        match self {
+            AMM::UniswapV2Pool(pool) => Some(&pool.stats),
+            AMM::UniswapV3Pool(pool) => Some(&pool.stats),
+            AMM::ERC4626Vault(_)
+            | AMM::CurveV2Pool(_)
+            | AMM::RateAdapter(_)
+            | AMM::BancorV3Pool(_)
+            | AMM::GmxMarket(_)
+            | AMM::AmbientPool(_)
+            | AMM::ConstantProductPool(_) => None,
+        }
+    }
+
+    /// Returns the swap stats of the AMM to reset them or change their window, `None` if the AMM does not track them.
+    pub fn stats_mut(&mut self) -> Option<&mut PoolStats> {
+        match self {
+            AMM::UniswapV2Pool(pool) => Some(&mut pool.stats),
+            AMM::UniswapV3Pool(pool) => Some(&mut pool.stats),
+            AMM::ERC4626Vault(_)
+            | AMM::CurveV2Pool(_)
+            | AMM::RateAdapter(_)
+            | AMM::BancorV3Pool(_)
+            | AMM::GmxMarket(_)
+            | AMM::AmbientPool(_)
+            | AMM::ConstantProductPool(_) => None,
+        }
+    }
+}
+
+/// Returns the fee of `amount_in`, with `fee` in units of `fee_denominator`.
+pub fn fee_amount(amount_in: U256, fee: u32, fee_denominator: u32) -> U256 {
+    amount_in * U256::from(fee) / U256::from(fee_denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::{PoolStats, SwapRecord};
+
+    fn record(block_number: u64, amount_0_in: u64, amount_1_out: u64) -> SwapRecord {
+        SwapRecord {
+            block_number,
+            amount_0_in: U256::from(amount_0_in),
+            amount_1_out: U256::from(amount_1_out),
+            fee_0: U256::from(amount_0_in / 100),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pool_stats() {
+        let mut stats = PoolStats::default();
+        stats.record_swap(record(10, 1000, 2000));
+        stats.record_swap(record(20, 500, 900));
+
+        assert_eq!(stats.volume_0, U256::from(1500));
+        assert_eq!(stats.volume_1, U256::from(2900));
+        assert_eq!(stats.fees_0, U256::from(15));
+        assert_eq!(stats.swaps, 2);
+        assert!(!stats.is_inactive_since(20));
+        assert!(stats.is_inactive_since(21));
+
+        stats.reset();
+        assert_eq!(stats, PoolStats::default());
+    }
+
+    #[test]
+    fn test_pool_stats_window() {
+        let mut stats = PoolStats::with_window(10);
+        stats.record_swap(record(10, 1000, 2000));
+        stats.record_swap(record(15, 500, 900));
+        assert_eq!(stats.swaps, 2);
+
+        // Block 10 falls out of the window of blocks 11 to 20
+        stats.record_swap(record(20, 100, 200));
+        assert_eq!(stats.swaps, 2);
+        assert_eq!(stats.volume_0, U256::from(600));
+        assert_eq!(stats.fees_0, U256::from(6));
+        assert_eq!(stats.last_swap_block, Some(20));
+
+        stats.set_window(None);
+        assert_eq!(stats, PoolStats::default());
+    }
+}
@@ -77,6 +77,7 @@ where
 
 pub async fn get_amm_data_batch_request<T, N, P>(
     amms: &mut [AMM],
+    block_number: Option<u64>,
     provider: Arc<P>,
 ) -> Result<(), AMMError>
 where
@@ -90,7 +91,11 @@ where
     }
 
     let deployer = IGetUniswapV2PoolDataBatchRequest::deploy_builder(provider, target_addresses);
-    let res = deployer.call().await?;
+    let res = if let Some(block_number) = block_number {
+        deployer.block(block_number.into()).call().await?
+    } else {
+        deployer.call().await?
+    };
 
     let constructor_return = DynSolType::Array(Box::new(DynSolType::Tuple(vec![
         DynSolType::Address,
@@ -0,0 +1,160 @@
+use alloy::primitives::Address;
+
+use crate::{amm::stats::PoolStats, errors::PoolBuilderError};
+
+use super::UniswapV2Pool;
+
+/// Builder for a [`UniswapV2Pool`].
+///
+/// `address`, `token_a`, `token_b` and `fee` are required.
+#[derive(Debug, Clone, Default)]
+pub struct UniswapV2PoolBuilder {
+    address: Option<Address>,
+    token_a: Option<Address>,
+    token_a_decimals: u8,
+    token_b: Option<Address>,
+    token_b_decimals: u8,
+    reserve_0: u128,
+    reserve_1: u128,
+    fee: Option<u32>,
+    sync_from_amount_logs: bool,
+    track_swap_stats: bool,
+    factory: Option<Address>,
+}
+
+impl UniswapV2PoolBuilder {
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Sets token 0 of the pool.
+    pub fn token_a(mut self, token_a: Address, decimals: u8) -> Self {
+        self.token_a = Some(token_a);
+        self.token_a_decimals = decimals;
+        self
+    }
+
+    /// Sets token 1 of the pool.
+    pub fn token_b(mut self, token_b: Address, decimals: u8) -> Self {
+        self.token_b = Some(token_b);
+        self.token_b_decimals = decimals;
+        self
+    }
+
+    pub fn reserves(mut self, reserve_0: u128, reserve_1: u128) -> Self {
+        self.reserve_0 = reserve_0;
+        self.reserve_1 = reserve_1;
+        self
+    }
+
+    /// Sets the swap fee in thousandths of a percent, i.e. 300 for 0.3%.
+    pub fn fee(mut self, fee: u32) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Syncs the reserves from Swap, Mint and Burn logs instead of Sync logs, see
+    /// [`UniswapV2Pool::sync_from_amount_logs`].
+    pub fn sync_from_amount_logs(mut self, sync_from_amount_logs: bool) -> Self {
+        self.sync_from_amount_logs = sync_from_amount_logs;
+        self
+    }
+
+    /// Subscribes to Swap logs to track the swap stats, see [`UniswapV2Pool::track_swap_stats`].
+    pub fn track_swap_stats(mut self, track_swap_stats: bool) -> Self {
+        self.track_swap_stats = track_swap_stats;
+        self
+    }
+
+    /// Sets the factory that created the pool.
+    pub fn factory(mut self, factory: Address) -> Self {
+        self.factory = Some(factory);
+        self
+    }
+
+    /// Validates the configuration and builds the pool.
+    pub fn build(self) -> Result<UniswapV2Pool, PoolBuilderError> {
+        let address = self
+            .address
+            .ok_or(PoolBuilderError::MissingField("address"))?;
+        let token_a = self
+            .token_a
+            .ok_or(PoolBuilderError::MissingField("token_a"))?;
+        let token_b = self
+            .token_b
+            .ok_or(PoolBuilderError::MissingField("token_b"))?;
+        let fee = self.fee.ok_or(PoolBuilderError::MissingField("fee"))?;
+
+        match token_a.cmp(&token_b) {
+            std::cmp::Ordering::Equal => return Err(PoolBuilderError::IdenticalTokens),
+            std::cmp::Ordering::Greater => return Err(PoolBuilderError::UnsortedTokens),
+            std::cmp::Ordering::Less => {}
+        }
+
+        if fee >= 100_000 {
+            return Err(PoolBuilderError::InvalidFee(fee));
+        }
+
+        Ok(UniswapV2Pool {
+            address,
+            token_a,
+            token_a_decimals: self.token_a_decimals,
+            token_b,
+            token_b_decimals: self.token_b_decimals,
+            reserve_0: self.reserve_0,
+            reserve_1: self.reserve_1,
+            fee,
+            token_a_transfer_tax_bps: 0,
+            token_b_transfer_tax_bps: 0,
+            stats: PoolStats::default(),
+            track_swap_stats: self.track_swap_stats,
+            sync_from_amount_logs: self.sync_from_amount_logs,
+            last_sync_transaction: None,
+            factory: self.factory,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let token_a = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let token_b = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+        let pool = UniswapV2Pool::builder()
+            .address(address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"))
+            .token_a(token_a, 6)
+            .token_b(token_b, 18)
+            .reserves(1_000_000, 2_000_000)
+            .fee(300)
+            .build()
+            .unwrap();
+        assert_eq!(pool.reserve_1, 2_000_000);
+
+        assert_eq!(
+            UniswapV2Pool::builder()
+                .address(pool.address)
+                .token_a(token_a, 6)
+                .token_b(token_a, 6)
+                .fee(300)
+                .build()
+                .unwrap_err(),
+            PoolBuilderError::IdenticalTokens
+        );
+        assert_eq!(
+            UniswapV2Pool::builder()
+                .address(pool.address)
+                .token_a(token_a, 6)
+                .token_b(token_b, 18)
+                .build()
+                .unwrap_err(),
+            PoolBuilderError::MissingField("fee")
+        );
+    }
+}
@@ -12,13 +12,26 @@ use alloy::{
 use async_trait::async_trait;
 
 use crate::{
-    amm::{factory::AutomatedMarketMakerFactory, AMM},
+    amm::{
+        batch_request::{populate_amms_with_config, BatchChunks},
+        factory::AutomatedMarketMakerFactory,
+        stats::PoolStats,
+        AMM,
+    },
     errors::AMMError,
+    filters,
+    sync::config::SyncConfig,
 };
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-use super::{batch_request, UniswapV2Pool, U256_1};
+use super::{batch_request, UniswapV2Pool};
+
+/// Max number of pairs per `allPairs` batch request until the codesize is too large.
+pub const PAIRS_BATCH_SIZE: usize = 766;
+
+/// Number of pairs listed and populated per chunk by [`UniswapV2Factory::get_all_pairs_with_data`].
+pub const PAIRS_DATA_BATCH_SIZE: usize = 500;
 
 sol! {
     /// Interface of the UniswapV2Factory contract
@@ -52,6 +65,23 @@ impl UniswapV2Factory {
         &self,
         provider: Arc<P>,
     ) -> Result<Vec<AMM>, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        self.get_all_pairs_via_batched_calls_with_chunk_size(PAIRS_BATCH_SIZE, provider)
+            .await
+    }
+
+    /// Gets all pairs of the factory, requesting `chunk_size` pairs per batch request.
+    ///
+    /// Chunks that exceed the gas or response size limits of the provider are halved and retried.
+    pub async fn get_all_pairs_via_batched_calls_with_chunk_size<T, N, P>(
+        &self,
+        chunk_size: usize,
+        provider: Arc<P>,
+    ) -> Result<Vec<AMM>, AMMError>
     where
         T: Transport + Clone,
         N: Network,
@@ -64,32 +94,19 @@ impl UniswapV2Factory {
         } = factory.allPairsLength().call().await?;
 
         let mut pairs = vec![];
-        // NOTE: max batch size for this call until codesize is too large
-        let step = 766;
-        let mut idx_from = U256::ZERO;
-        let mut idx_to = if step > pairs_length.to::<usize>() {
-            pairs_length
-        } else {
-            U256::from(step)
-        };
-
-        for _ in (0..pairs_length.to::<usize>()).step_by(step) {
-            pairs.append(
-                &mut batch_request::get_pairs_batch_request(
-                    self.address,
-                    idx_from,
-                    idx_to,
-                    provider.clone(),
-                )
-                .await?,
-            );
-
-            idx_from = idx_to;
-
-            if idx_to + U256::from(step) > pairs_length {
-                idx_to = pairs_length - U256_1
-            } else {
-                idx_to += U256::from(step);
+        let mut chunks = BatchChunks::new(pairs_length.to::<usize>(), chunk_size);
+        while let Some(chunk) = chunks.next() {
+            match batch_request::get_pairs_batch_request(
+                self.address,
+                U256::from(chunk.start),
+                U256::from(chunk.end),
+                provider.clone(),
+            )
+            .await
+            {
+                Ok(mut chunk_pairs) => pairs.append(&mut chunk_pairs),
+                Err(err) if chunks.split(&chunk, &err) => continue,
+                Err(err) => return Err(err),
             }
         }
 
@@ -99,6 +116,7 @@ impl UniswapV2Factory {
         for addr in pairs {
             let amm = UniswapV2Pool {
                 address: addr,
+                factory: Some(self.address),
                 ..Default::default()
             };
 
@@ -107,6 +125,89 @@ impl UniswapV2Factory {
 
         Ok(amms)
     }
+
+    /// Gets all pairs of the factory with their tokens, token decimals and reserves as of `block_number`, paging
+    /// through `allPairs` in chunks of `chunk_size` pairs, e.g. [`PAIRS_DATA_BATCH_SIZE`].
+    ///
+    /// Each chunk of pairs is listed and populated with one batch request each, which is much faster than scanning
+    /// the PairCreated logs of the factory. Chunks that exceed the gas or response size limits of the provider are
+    /// halved and retried. Pools get the fee of the factory, and pairs that could not be populated are dropped.
+    pub async fn get_all_pairs_with_data<T, N, P>(
+        &self,
+        chunk_size: usize,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<Vec<AMM>, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let factory = IUniswapV2Factory::new(self.address, provider.clone());
+        let all_pairs_length = factory.allPairsLength();
+        let IUniswapV2Factory::allPairsLengthReturn {
+            length: pairs_length,
+        } = if let Some(block_number) = block_number {
+            all_pairs_length.block(block_number.into()).call().await?
+        } else {
+            all_pairs_length.call().await?
+        };
+
+        let mut amms = vec![];
+        let mut chunks = BatchChunks::new(pairs_length.to::<usize>(), chunk_size);
+        while let Some(chunk) = chunks.next() {
+            match self
+                .get_pairs_with_data(chunk.start, chunk.end, block_number, provider.clone())
+                .await
+            {
+                Ok(chunk_amms) => amms.extend(chunk_amms),
+                Err(err) if chunks.split(&chunk, &err) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(amms)
+    }
+
+    /// Lists the pairs from index `from` to `to` of `allPairs` and populates them as of `block_number`.
+    async fn get_pairs_with_data<T, N, P>(
+        &self,
+        from: usize,
+        to: usize,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<Vec<AMM>, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let pairs = batch_request::get_pairs_batch_request(
+            self.address,
+            U256::from(from),
+            U256::from(to),
+            provider.clone(),
+        )
+        .await?;
+
+        let mut amms = pairs
+            .into_iter()
+            .map(|address| {
+                AMM::UniswapV2Pool(UniswapV2Pool {
+                    address,
+                    fee: self.fee,
+                    factory: Some(self.address),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<AMM>>();
+
+        if !amms.is_empty() {
+            batch_request::get_amm_data_batch_request(&mut amms, block_number, provider).await?;
+        }
+
+        Ok(filters::filter_empty_amms(amms))
+    }
 }
 
 #[async_trait]
@@ -143,14 +244,21 @@ impl AutomatedMarketMakerFactory for UniswapV2Factory {
             reserve_0: 0,
             reserve_1: 0,
             fee: 0,
+            token_a_transfer_tax_bps: 0,
+            token_b_transfer_tax_bps: 0,
+            stats: PoolStats::default(),
+            track_swap_stats: false,
+            sync_from_amount_logs: false,
+            last_sync_transaction: None,
+            factory: Some(self.address),
         }))
     }
 
-    #[instrument(skip(self, middleware) level = "debug")]
+    #[instrument(skip(self, provider) level = "debug")]
     async fn get_all_amms<T, N, P>(
         &self,
         _to_block: Option<u64>,
-        middleware: Arc<P>,
+        provider: Arc<P>,
         _step: u64,
     ) -> Result<Vec<AMM>, AMMError>
     where
@@ -158,29 +266,67 @@ impl AutomatedMarketMakerFactory for UniswapV2Factory {
         N: Network,
         P: Provider<T, N>,
     {
-        self.get_all_pairs_via_batched_calls(middleware).await
+        self.get_all_pairs_via_batched_calls(provider).await
     }
 
     async fn populate_amm_data<T, N, P>(
         &self,
         amms: &mut [AMM],
-        _block_number: Option<u64>,
-        middleware: Arc<P>,
+        block_number: Option<u64>,
+        provider: Arc<P>,
     ) -> Result<(), AMMError>
     where
         T: Transport + Clone,
         N: Network,
         P: Provider<T, N>,
     {
-        // Max batch size for call
-        let step = 127;
-        for amm_chunk in amms.chunks_mut(step) {
-            batch_request::get_amm_data_batch_request(amm_chunk, middleware.clone()).await?;
-        }
-        Ok(())
+        populate_amms_with_config(amms, block_number, &SyncConfig::default(), provider).await
     }
 
     fn creation_block(&self) -> u64 {
         self.creation_block
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{
+        primitives::address,
+        providers::{Provider, ProviderBuilder},
+    };
+
+    use crate::amm::AMM;
+
+    use super::{UniswapV2Factory, PAIRS_DATA_BATCH_SIZE};
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_get_all_pairs_with_data() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        // SushiSwap has fewer pairs than Uniswap V2
+        let factory = UniswapV2Factory::new(
+            address!("C0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac"),
+            10794229,
+            300,
+        );
+        let block_number = provider.get_block_number().await.unwrap();
+        let amms = factory
+            .get_all_pairs_with_data(PAIRS_DATA_BATCH_SIZE, Some(block_number), provider)
+            .await
+            .unwrap();
+
+        assert!(amms.len() > PAIRS_DATA_BATCH_SIZE);
+        for amm in amms {
+            let AMM::UniswapV2Pool(pool) = amm else {
+                panic!("Unexpected AMM variant")
+            };
+            assert!(!pool.token_a.is_zero() && !pool.token_b.is_zero());
+            assert_eq!(pool.fee, 300);
+            assert_eq!(pool.factory, Some(factory.address));
+        }
+    }
+}
@@ -1,27 +1,45 @@
+#[cfg(feature = "provider")]
 pub mod batch_request;
+pub mod builder;
+#[cfg(feature = "provider")]
 pub mod factory;
 
+#[cfg(feature = "provider")]
 use std::sync::Arc;
 
+#[cfg(feature = "provider")]
 use crate::{
-    amm::{consts::*, AutomatedMarketMaker, IErc20},
-    errors::{AMMError, ArithmeticError, EventLogError, SwapSimulationError},
+    amm::{multicall, AMM},
+    errors::{ErrorContext, ResultExt},
+    token::get_token_metadata_batch_request,
 };
+use crate::{
+    amm::{
+        stats::{fee_amount, PoolStats, SwapRecord},
+        AutomatedMarketMaker, Protocol, SwapParams,
+    },
+    core::{price, uniswap_v2 as math},
+    errors::{AMMError, ArithmeticError, EventLogError, ExecutionError, SwapSimulationError},
+};
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
 use alloy::{
-    network::Network,
     primitives::{Address, Bytes, B256, U256},
-    providers::Provider,
     rpc::types::eth::Log,
     sol,
     sol_types::{SolCall, SolEvent},
-    transports::Transport,
 };
 use async_trait::async_trait;
-use num_bigfloat::BigFloat;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-use self::factory::IUniswapV2Factory;
+#[cfg(feature = "provider")]
+use self::factory::{IUniswapV2Factory, UniswapV2Factory};
+
+pub use crate::core::{
+    price::{div_uu, q64_to_f64},
+    uniswap_v2::apply_transfer_tax,
+};
 
 sol! {
     /// Interface of the UniswapV2Pair
@@ -29,6 +47,9 @@ sol! {
     #[sol(rpc)]
     contract IUniswapV2Pair {
         event Sync(uint112 reserve0, uint112 reserve1);
+        event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to);
+        event Mint(address indexed sender, uint256 amount0, uint256 amount1);
+        event Burn(address indexed sender, uint256 amount0, uint256 amount1, address indexed to);
         function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
         function token0() external view returns (address);
         function token1() external view returns (address);
@@ -36,6 +57,12 @@ sol! {
     }
 }
 
+/// Denominator of the pool fee, a fee of 300 is 0.3%.
+pub const FEE_DENOMINATOR: u32 = 100_000;
+
+/// Estimated gas used by a pair swap, including the token transfer out.
+pub const SWAP_GAS_ESTIMATE: u64 = 70_000;
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UniswapV2Pool {
     pub address: Address,
@@ -46,6 +73,30 @@ pub struct UniswapV2Pool {
     pub reserve_0: u128,
     pub reserve_1: u128,
     pub fee: u32,
+    /// Transfer tax of token a in basis points, applied by swap simulations.
+    #[serde(default)]
+    pub token_a_transfer_tax_bps: u32,
+    /// Transfer tax of token b in basis points, applied by swap simulations.
+    #[serde(default)]
+    pub token_b_transfer_tax_bps: u32,
+    /// Swap volume and fees, updated from Swap logs if `track_swap_stats` is set.
+    #[serde(default)]
+    pub stats: PoolStats,
+    /// Subscribes to Swap logs to update `stats`. Reserves are synced from Sync logs alone otherwise, so the Swap logs
+    /// are only received when opted in.
+    #[serde(default)]
+    pub track_swap_stats: bool,
+    /// Also syncs the reserves from the amounts of Swap, Mint and Burn logs, e.g. to track liquidity changes or to
+    /// apply the logs of simulated pending transactions. Sync logs stay authoritative: the amounts of a log are not
+    /// applied when a Sync log of the same transaction already set the reserves.
+    #[serde(default)]
+    pub sync_from_amount_logs: bool,
+    /// Transaction of the last Sync log applied to the pool.
+    #[serde(skip)]
+    pub last_sync_transaction: Option<B256>,
+    /// Factory that created the pool, if it is known.
+    #[serde(default)]
+    pub factory: Option<Address>,
 }
 
 #[async_trait]
@@ -54,6 +105,19 @@ impl AutomatedMarketMaker for UniswapV2Pool {
         self.address
     }
 
+    fn protocol(&self) -> Protocol {
+        Protocol::UniswapV2
+    }
+
+    fn fee_bps(&self) -> u32 {
+        self.fee * 10_000 / FEE_DENOMINATOR
+    }
+
+    fn factory(&self) -> Option<Address> {
+        self.factory
+    }
+
+    #[cfg(feature = "provider")]
     #[instrument(skip(self, provider), level = "debug")]
     async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
     where
@@ -61,7 +125,10 @@ impl AutomatedMarketMaker for UniswapV2Pool {
         N: Network,
         P: Provider<T, N>,
     {
-        let (reserve_0, reserve_1) = self.get_reserves(provider.clone()).await?;
+        let (reserve_0, reserve_1) = self
+            .get_reserves(provider.clone())
+            .await
+            .context(ErrorContext::sync(self.address))?;
         tracing::info!(?reserve_0, ?reserve_1, address = ?self.address, "UniswapV2 sync");
 
         self.reserve_0 = reserve_0;
@@ -70,10 +137,11 @@ impl AutomatedMarketMaker for UniswapV2Pool {
         Ok(())
     }
 
+    #[cfg(feature = "provider")]
     #[instrument(skip(self, provider), level = "debug")]
     async fn populate_data<T, N, P>(
         &mut self,
-        _block_number: Option<u64>,
+        block_number: Option<u64>,
         provider: Arc<P>,
     ) -> Result<(), AMMError>
     where
@@ -81,30 +149,98 @@ impl AutomatedMarketMaker for UniswapV2Pool {
         N: Network,
         P: Provider<T, N>,
     {
-        batch_request::get_v2_pool_data_batch_request(self, provider.clone()).await?;
+        if let Err(err) =
+            batch_request::get_v2_pool_data_batch_request(self, provider.clone()).await
+        {
+            tracing::debug!(?err, address = ?self.address, "Deployless batch request failed, falling back to Multicall3");
+
+            let mut amms = [AMM::UniswapV2Pool(self.clone())];
+            multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+                .await
+                .context(ErrorContext::populate(self.address, block_number))?;
+            if let [AMM::UniswapV2Pool(pool)] = amms {
+                *self = pool;
+            }
+        }
 
         Ok(())
     }
 
     fn sync_on_event_signatures(&self) -> Vec<B256> {
-        vec![IUniswapV2Pair::Sync::SIGNATURE_HASH]
+        if self.sync_from_amount_logs {
+            vec![
+                IUniswapV2Pair::Sync::SIGNATURE_HASH,
+                IUniswapV2Pair::Swap::SIGNATURE_HASH,
+                IUniswapV2Pair::Mint::SIGNATURE_HASH,
+                IUniswapV2Pair::Burn::SIGNATURE_HASH,
+            ]
+        } else if self.track_swap_stats {
+            vec![
+                IUniswapV2Pair::Sync::SIGNATURE_HASH,
+                IUniswapV2Pair::Swap::SIGNATURE_HASH,
+            ]
+        } else {
+            vec![IUniswapV2Pair::Sync::SIGNATURE_HASH]
+        }
     }
 
     #[instrument(skip(self), level = "debug")]
     fn sync_from_log(&mut self, log: Log) -> Result<(), EventLogError> {
-        let event_signature = log.topics()[0];
-
-        if event_signature == IUniswapV2Pair::Sync::SIGNATURE_HASH {
-            let sync_event = IUniswapV2Pair::Sync::decode_log(log.as_ref(), true)?;
-            tracing::info!(reserve_0 = sync_event.reserve0, reserve_1 = sync_event.reserve1, address = ?self.address, "UniswapV2 sync event");
-
-            self.reserve_0 = sync_event.reserve0;
-            self.reserve_1 = sync_event.reserve1;
-
-            Ok(())
-        } else {
-            Err(EventLogError::InvalidEventSignature)
+        let block_number = log.block_number;
+        // Amounts of logs emitted along with an applied Sync log are already reflected in the reserves
+        let apply_amounts = self.sync_from_amount_logs
+            && (log.transaction_hash.is_none()
+                || log.transaction_hash != self.last_sync_transaction);
+
+        match UniswapV2Event::decode_log(&log)? {
+            UniswapV2Event::Sync(sync_event) => {
+                tracing::info!(reserve_0 = sync_event.reserve0, reserve_1 = sync_event.reserve1, address = ?self.address, "UniswapV2 sync event");
+
+                self.reserve_0 = sync_event.reserve0;
+                self.reserve_1 = sync_event.reserve1;
+                self.last_sync_transaction = log.transaction_hash;
+            }
+            UniswapV2Event::Swap(swap_event) => {
+                if self.track_swap_stats {
+                    self.stats.record_swap(SwapRecord {
+                        block_number: block_number
+                            .or(self.stats.last_swap_block)
+                            .unwrap_or_default(),
+                        amount_0_in: swap_event.amount0In,
+                        amount_1_in: swap_event.amount1In,
+                        amount_0_out: swap_event.amount0Out,
+                        amount_1_out: swap_event.amount1Out,
+                        fee_0: fee_amount(swap_event.amount0In, self.fee, FEE_DENOMINATOR),
+                        fee_1: fee_amount(swap_event.amount1In, self.fee, FEE_DENOMINATOR),
+                    });
+                }
+
+                if apply_amounts {
+                    self.apply_amounts(
+                        (swap_event.amount0In, swap_event.amount1In),
+                        (swap_event.amount0Out, swap_event.amount1Out),
+                    )?;
+                }
+            }
+            UniswapV2Event::Mint(mint_event) => {
+                if apply_amounts {
+                    self.apply_amounts(
+                        (mint_event.amount0, mint_event.amount1),
+                        (U256::ZERO, U256::ZERO),
+                    )?;
+                }
+            }
+            UniswapV2Event::Burn(burn_event) => {
+                if apply_amounts {
+                    self.apply_amounts(
+                        (U256::ZERO, U256::ZERO),
+                        (burn_event.amount0, burn_event.amount1),
+                    )?;
+                }
+            }
         }
+
+        Ok(())
     }
 
     // Calculates base/quote, meaning the price of base token per quote (ie. exchange rate is X base per 1 quote)
@@ -122,16 +258,26 @@ impl AutomatedMarketMaker for UniswapV2Pool {
         amount_in: U256,
     ) -> Result<U256, SwapSimulationError> {
         if self.token_a == token_in {
-            Ok(self.get_amount_out(
-                amount_in,
+            let amount_out = self.get_amount_out(
+                apply_transfer_tax(amount_in, self.token_a_transfer_tax_bps),
                 U256::from(self.reserve_0),
                 U256::from(self.reserve_1),
+            );
+
+            Ok(apply_transfer_tax(
+                amount_out,
+                self.token_b_transfer_tax_bps,
             ))
         } else {
-            Ok(self.get_amount_out(
-                amount_in,
+            let amount_out = self.get_amount_out(
+                apply_transfer_tax(amount_in, self.token_b_transfer_tax_bps),
                 U256::from(self.reserve_1),
                 U256::from(self.reserve_0),
+            );
+
+            Ok(apply_transfer_tax(
+                amount_out,
+                self.token_a_transfer_tax_bps,
             ))
         }
     }
@@ -142,6 +288,8 @@ impl AutomatedMarketMaker for UniswapV2Pool {
         amount_in: U256,
     ) -> Result<U256, SwapSimulationError> {
         if self.token_a == token_in {
+            // The pool receives the input net of the transfer tax
+            let amount_in = apply_transfer_tax(amount_in, self.token_a_transfer_tax_bps);
             let amount_out = self.get_amount_out(
                 amount_in,
                 U256::from(self.reserve_0),
@@ -156,8 +304,12 @@ impl AutomatedMarketMaker for UniswapV2Pool {
 
             tracing::trace!(?self.reserve_0, ?self.reserve_1, "pool reserves after");
 
-            Ok(amount_out)
+            Ok(apply_transfer_tax(
+                amount_out,
+                self.token_b_transfer_tax_bps,
+            ))
         } else {
+            let amount_in = apply_transfer_tax(amount_in, self.token_b_transfer_tax_bps);
             let amount_out = self.get_amount_out(
                 amount_in,
                 U256::from(self.reserve_1),
@@ -172,10 +324,31 @@ impl AutomatedMarketMaker for UniswapV2Pool {
 
             tracing::trace!(?self.reserve_0, ?self.reserve_1, "pool reserves after");
 
-            Ok(amount_out)
+            Ok(apply_transfer_tax(
+                amount_out,
+                self.token_a_transfer_tax_bps,
+            ))
         }
     }
 
+    fn simulate_swap_exact_output(
+        &self,
+        token_in: Address,
+        amount_out: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let (reserve_in, reserve_out, tax_in_bps, tax_out_bps) = self.swap_direction(token_in);
+
+        // The pool must send enough for `amount_out` to be received net of the transfer tax
+        let amount_out = math::amount_before_transfer_tax(amount_out, tax_out_bps)
+            .ok_or(SwapSimulationError::InsufficientLiquidity)?;
+        let amount_in = self
+            .get_amount_in(amount_out, U256::from(reserve_in), U256::from(reserve_out))
+            .ok_or(SwapSimulationError::InsufficientLiquidity)?;
+
+        math::amount_before_transfer_tax(amount_in, tax_in_bps)
+            .ok_or(SwapSimulationError::InsufficientLiquidity)
+    }
+
     fn get_token_out(&self, token_in: Address) -> Address {
         if self.token_a == token_in {
             self.token_b
@@ -183,9 +356,79 @@ impl AutomatedMarketMaker for UniswapV2Pool {
             self.token_a
         }
     }
+
+    fn swap_gas_estimate(&self, _token_in: Address, _amount_in: U256) -> u64 {
+        SWAP_GAS_ESTIMATE
+    }
+
+    /// Returns the calldata of `swap` on the pair, see [`UniswapV2Pool::swap_calldata_for_input`], checking the
+    /// amount received after the transfer taxes against `amount_out_min`.
+    fn encode_swap(&self, params: SwapParams) -> Result<Bytes, ExecutionError> {
+        if params.token_in != self.token_a && params.token_in != self.token_b {
+            return Err(ExecutionError::TokenNotInPool(
+                params.token_in,
+                self.address,
+            ));
+        }
+
+        let amount_received = self
+            .simulate_swap(params.token_in, params.amount_in)
+            .map_err(|err| ExecutionError::SwapSimulationFailed(self.address, err.to_string()))?;
+        if amount_received < params.amount_out_min {
+            return Err(ExecutionError::InsufficientOutputAmount {
+                amount_out: amount_received,
+                amount_out_min: params.amount_out_min,
+            });
+        }
+
+        self.swap_calldata_for_input(
+            params.token_in,
+            params.amount_in,
+            params.recipient,
+            params.callback_data.to_vec(),
+        )
+    }
+}
+
+/// Decoded log of a Uniswap V2 pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UniswapV2Event {
+    Sync(IUniswapV2Pair::Sync),
+    Swap(IUniswapV2Pair::Swap),
+    Mint(IUniswapV2Pair::Mint),
+    Burn(IUniswapV2Pair::Burn),
+}
+
+impl UniswapV2Event {
+    /// Decodes a Sync, Swap, Mint or Burn log of a pair.
+    pub fn decode_log(log: &Log) -> Result<Self, EventLogError> {
+        let event_signature = *log
+            .topics()
+            .first()
+            .ok_or(EventLogError::InvalidEventSignature)?;
+
+        let event = if event_signature == IUniswapV2Pair::Sync::SIGNATURE_HASH {
+            UniswapV2Event::Sync(IUniswapV2Pair::Sync::decode_log(log.as_ref(), true)?.data)
+        } else if event_signature == IUniswapV2Pair::Swap::SIGNATURE_HASH {
+            UniswapV2Event::Swap(IUniswapV2Pair::Swap::decode_log(log.as_ref(), true)?.data)
+        } else if event_signature == IUniswapV2Pair::Mint::SIGNATURE_HASH {
+            UniswapV2Event::Mint(IUniswapV2Pair::Mint::decode_log(log.as_ref(), true)?.data)
+        } else if event_signature == IUniswapV2Pair::Burn::SIGNATURE_HASH {
+            UniswapV2Event::Burn(IUniswapV2Pair::Burn::decode_log(log.as_ref(), true)?.data)
+        } else {
+            return Err(EventLogError::InvalidEventSignature);
+        };
+
+        Ok(event)
+    }
 }
 
 impl UniswapV2Pool {
+    /// Returns a builder for the pool.
+    pub fn builder() -> builder::UniswapV2PoolBuilder {
+        builder::UniswapV2PoolBuilder::default()
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         address: Address,
@@ -206,9 +449,17 @@ impl UniswapV2Pool {
             reserve_0,
             reserve_1,
             fee,
+            token_a_transfer_tax_bps: 0,
+            token_b_transfer_tax_bps: 0,
+            stats: PoolStats::default(),
+            track_swap_stats: false,
+            sync_from_amount_logs: false,
+            last_sync_transaction: None,
+            factory: None,
         }
     }
 
+    #[cfg(feature = "provider")]
     /// Creates a new instance of the pool from the pair address, and syncs the pool data.
     pub async fn new_from_address<T, N, P>(
         pair_address: Address,
@@ -229,6 +480,13 @@ impl UniswapV2Pool {
             reserve_0: 0,
             reserve_1: 0,
             fee,
+            token_a_transfer_tax_bps: 0,
+            token_b_transfer_tax_bps: 0,
+            stats: PoolStats::default(),
+            track_swap_stats: false,
+            sync_from_amount_logs: false,
+            last_sync_transaction: None,
+            factory: None,
         };
 
         pool.populate_data(None, provider.clone()).await?;
@@ -240,6 +498,39 @@ impl UniswapV2Pool {
         Ok(pool)
     }
 
+    #[cfg(feature = "provider")]
+    /// Creates a new instance of the pool of `token_a` and `token_b` from the `getPair` of `factory`, and syncs the
+    /// pool data.
+    ///
+    /// The pool gets the fee of the factory.
+    pub async fn new_from_tokens<T, N, P>(
+        factory: &UniswapV2Factory,
+        token_a: Address,
+        token_b: Address,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let IUniswapV2Factory::getPairReturn { pair } =
+            IUniswapV2Factory::new(factory.address, provider.clone())
+                .getPair(token_a, token_b)
+                .call()
+                .await?;
+
+        if pair.is_zero() {
+            return Err(AMMError::PairDoesNotExistInDexes(token_a, token_b));
+        }
+
+        let mut pool = UniswapV2Pool::new_from_address(pair, factory.fee, provider).await?;
+        pool.factory = Some(factory.address);
+
+        Ok(pool)
+    }
+
+    #[cfg(feature = "provider")]
     /// Creates a new instance of a the pool from a `PairCreated` event log.
     ///
     /// This method syncs the pool data.
@@ -264,6 +555,7 @@ impl UniswapV2Pool {
         }
     }
 
+    #[cfg(feature = "provider")]
     /// Creates a new instance of a the pool from a `PairCreated` event log.
     ///
     /// This method does not sync the pool data.
@@ -283,6 +575,13 @@ impl UniswapV2Pool {
                 reserve_0: 0,
                 reserve_1: 0,
                 fee: 0,
+                token_a_transfer_tax_bps: 0,
+                token_b_transfer_tax_bps: 0,
+                stats: PoolStats::default(),
+                track_swap_stats: false,
+                sync_from_amount_logs: false,
+                last_sync_transaction: None,
+                factory: None,
             })
         } else {
             Err(EventLogError::InvalidEventSignature)?
@@ -294,6 +593,30 @@ impl UniswapV2Pool {
         self.fee
     }
 
+    /// Adds the amounts paid into the pool to its reserves and subtracts the amounts paid out.
+    fn apply_amounts(
+        &mut self,
+        amounts_in: (U256, U256),
+        amounts_out: (U256, U256),
+    ) -> Result<(), EventLogError> {
+        let apply = |reserve: u128, amount_in: U256, amount_out: U256| {
+            U256::from(reserve)
+                .checked_add(amount_in)
+                .ok_or(EventLogError::ReserveOverflow)?
+                .checked_sub(amount_out)
+                .ok_or(EventLogError::ReserveUnderflow)?
+                .try_into()
+                .map_err(|_| EventLogError::ReserveOverflow)
+        };
+
+        let reserve_0 = apply(self.reserve_0, amounts_in.0, amounts_out.0)?;
+        let reserve_1 = apply(self.reserve_1, amounts_in.1, amounts_out.1)?;
+        self.reserve_0 = reserve_0;
+        self.reserve_1 = reserve_1;
+
+        Ok(())
+    }
+
     /// Returns whether the pool data is populated.
     pub fn data_is_populated(&self) -> bool {
         !(self.token_a.is_zero()
@@ -302,6 +625,7 @@ impl UniswapV2Pool {
             || self.reserve_1 == 0)
     }
 
+    #[cfg(feature = "provider")]
     /// Returns the reserves of the pool.
     pub async fn get_reserves<T, N, P>(&self, provider: Arc<P>) -> Result<(u128, u128), AMMError>
     where
@@ -329,6 +653,7 @@ impl UniswapV2Pool {
         Ok((reserve_0, reserve_1))
     }
 
+    #[cfg(feature = "provider")]
     pub async fn get_token_decimals<T, N, P>(
         &mut self,
         provider: Arc<P>,
@@ -338,25 +663,25 @@ impl UniswapV2Pool {
         N: Network,
         P: Provider<T, N>,
     {
-        let IErc20::decimalsReturn {
-            _0: token_a_decimals,
-        } = IErc20::new(self.token_a, provider.clone())
-            .decimals()
-            .call()
-            .await?;
-
-        let IErc20::decimalsReturn {
-            _0: token_b_decimals,
-        } = IErc20::new(self.token_b, provider)
-            .decimals()
-            .call()
-            .await?;
+        let tokens =
+            get_token_metadata_batch_request(&[self.token_a, self.token_b], None, provider).await?;
+        let decimals = |address: Address| {
+            tokens
+                .iter()
+                .find(|token| token.address == address)
+                .map(|token| token.decimals)
+                .ok_or(AMMError::BatchRequestError(address))
+        };
+
+        let token_a_decimals = decimals(self.token_a)?;
+        let token_b_decimals = decimals(self.token_b)?;
 
         tracing::trace!(token_a_decimals, token_b_decimals);
 
         Ok((token_a_decimals, token_b_decimals))
     }
 
+    #[cfg(feature = "provider")]
     pub async fn get_token_0<T, N, P>(
         &self,
         pair_address: Address,
@@ -377,17 +702,18 @@ impl UniswapV2Pool {
         Ok(token0)
     }
 
+    #[cfg(feature = "provider")]
     pub async fn get_token_1<T, N, P>(
         &self,
         pair_address: Address,
-        middleware: Arc<P>,
+        provider: Arc<P>,
     ) -> Result<Address, AMMError>
     where
         T: Transport + Clone,
         N: Network,
         P: Provider<T, N>,
     {
-        let v2_pair = IUniswapV2Pair::new(pair_address, middleware);
+        let v2_pair = IUniswapV2Pair::new(pair_address, provider);
 
         let IUniswapV2Pair::token1Return { _0: token1 } = match v2_pair.token1().call().await {
             Ok(result) => result,
@@ -401,31 +727,20 @@ impl UniswapV2Pool {
     ///
     /// Returned as a Q64 fixed point number.
     pub fn calculate_price_64_x_64(&self, base_token: Address) -> Result<u128, ArithmeticError> {
-        let decimal_shift = self.token_a_decimals as i8 - self.token_b_decimals as i8;
-
-        let (r_0, r_1) = if decimal_shift < 0 {
-            (
-                U256::from(self.reserve_0)
-                    * U256::from(10u128.pow(decimal_shift.unsigned_abs() as u32)),
-                U256::from(self.reserve_1),
+        if base_token == self.token_a {
+            price::reserves_to_price_64_x_64(
+                self.reserve_0,
+                self.reserve_1,
+                self.token_a_decimals,
+                self.token_b_decimals,
             )
         } else {
-            (
-                U256::from(self.reserve_0),
-                U256::from(self.reserve_1) * U256::from(10u128.pow(decimal_shift as u32)),
+            price::reserves_to_price_64_x_64(
+                self.reserve_1,
+                self.reserve_0,
+                self.token_b_decimals,
+                self.token_a_decimals,
             )
-        };
-
-        if base_token == self.token_a {
-            if r_0.is_zero() {
-                Ok(U128_0X10000000000000000)
-            } else {
-                div_uu(r_1, r_0)
-            }
-        } else if r_1.is_zero() {
-            Ok(U128_0X10000000000000000)
-        } else {
-            div_uu(r_0, r_1)
         }
     }
 
@@ -433,17 +748,56 @@ impl UniswapV2Pool {
     pub fn get_amount_out(&self, amount_in: U256, reserve_in: U256, reserve_out: U256) -> U256 {
         tracing::trace!(?amount_in, ?reserve_in, ?reserve_out);
 
-        if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
-            return U256::ZERO;
-        }
-        let fee = (10000 - (self.fee / 10)) / 10; //Fee of 300 => (10,000 - 30) / 10  = 997
-        let amount_in_with_fee = amount_in * U256::from(fee);
-        let numerator = amount_in_with_fee * reserve_out;
-        let denominator = reserve_in * U256::from(1000) + amount_in_with_fee;
+        math::get_amount_out(amount_in, reserve_in, reserve_out, self.fee)
+    }
 
-        tracing::trace!(?fee, ?amount_in_with_fee, ?numerator, ?denominator);
+    /// Calculates the amount required to receive `amount_out`, as `getAmountIn` of the Uniswap V2 router with the fee
+    /// of the pool.
+    ///
+    /// Returns `None` if `amount_out` is not less than `reserve_out`.
+    pub fn get_amount_in(
+        &self,
+        amount_out: U256,
+        reserve_in: U256,
+        reserve_out: U256,
+    ) -> Option<U256> {
+        tracing::trace!(?amount_out, ?reserve_in, ?reserve_out);
 
-        numerator / denominator
+        math::get_amount_in(amount_out, reserve_in, reserve_out, self.fee)
+    }
+
+    /// Returns the largest amount of `token_in` that can be swapped while the execution price stays
+    /// within `max_slippage_bps` of the fee adjusted spot price.
+    ///
+    /// With `f` the fee adjusted input, the execution price relative to spot is `r_in / (r_in + f * x)`,
+    /// so the bound holds for `x <= r_in * bps / ((10_000 - bps) * f)`.
+    pub fn max_input_for_slippage(&self, token_in: Address, max_slippage_bps: u32) -> U256 {
+        let reserve_in = if self.token_a == token_in {
+            U256::from(self.reserve_0)
+        } else {
+            U256::from(self.reserve_1)
+        };
+
+        math::max_input_for_slippage(reserve_in, self.fee, max_slippage_bps)
+    }
+
+    /// Returns the reserves in and out and the transfer taxes in and out of a swap of `token_in`.
+    fn swap_direction(&self, token_in: Address) -> (u128, u128, u32, u32) {
+        if self.token_a == token_in {
+            (
+                self.reserve_0,
+                self.reserve_1,
+                self.token_a_transfer_tax_bps,
+                self.token_b_transfer_tax_bps,
+            )
+        } else {
+            (
+                self.reserve_1,
+                self.reserve_0,
+                self.token_b_transfer_tax_bps,
+                self.token_a_transfer_tax_bps,
+            )
+        }
     }
 
     /// Returns the calldata for a swap.
@@ -463,108 +817,132 @@ impl UniswapV2Pool {
         .abi_encode()
         .into())
     }
-}
 
-pub fn div_uu(x: U256, y: U256) -> Result<u128, ArithmeticError> {
-    if !y.is_zero() {
-        let mut answer;
+    /// Returns the calldata of a swap of `amount_in` of `token_in` paid to `to`, with the amount out simulated from
+    /// the current reserves and transfer taxes.
+    ///
+    /// The pair only checks its invariant, so `amount_in` must be transferred to the pool before the swap. The amount
+    /// out is the amount sent by the pair, before the transfer tax of the token out. `calldata` is passed to the
+    /// `uniswapV2Call` callback of `to` if not empty.
+    pub fn swap_calldata_for_input(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+        to: Address,
+        calldata: Vec<u8>,
+    ) -> Result<Bytes, ExecutionError> {
+        if token_in != self.token_a && token_in != self.token_b {
+            return Err(ExecutionError::TokenNotInPool(token_in, self.address));
+        }
 
-        if x <= U256_0XFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF {
-            answer = (x << U256_64) / y;
+        let (reserve_in, reserve_out, tax_in_bps, _) = self.swap_direction(token_in);
+        let amount_out = self.get_amount_out(
+            apply_transfer_tax(amount_in, tax_in_bps),
+            U256::from(reserve_in),
+            U256::from(reserve_out),
+        );
+        let (amount_0_out, amount_1_out) = if token_in == self.token_a {
+            (U256::ZERO, amount_out)
         } else {
-            let mut msb = U256_192;
-            let mut xc = x >> U256_192;
-
-            if xc >= U256_0X100000000 {
-                xc >>= U256_32;
-                msb += U256_32;
-            }
-
-            if xc >= U256_0X10000 {
-                xc >>= U256_16;
-                msb += U256_16;
-            }
-
-            if xc >= U256_0X100 {
-                xc >>= U256_8;
-                msb += U256_8;
-            }
-
-            if xc >= U256_16 {
-                xc >>= U256_4;
-                msb += U256_4;
-            }
-
-            if xc >= U256_4 {
-                xc >>= U256_2;
-                msb += U256_2;
-            }
-
-            if xc >= U256_2 {
-                msb += U256_1;
-            }
-
-            answer = (x << (U256_255 - msb)) / (((y - U256_1) >> (msb - U256_191)) + U256_1);
-        }
+            (amount_out, U256::ZERO)
+        };
 
-        if answer > U256_0XFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF {
-            return Ok(0);
+        Ok(IUniswapV2Pair::swapCall {
+            amount0Out: amount_0_out,
+            amount1Out: amount_1_out,
+            to,
+            data: calldata.into(),
         }
+        .abi_encode()
+        .into())
+    }
+}
 
-        let hi = answer * (y >> U256_128);
-        let mut lo = answer * (y & U256_0XFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF);
-
-        let mut xh = x >> U256_192;
-        let mut xl = x << U256_64;
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
 
-        if xl < lo {
-            xh -= U256_1;
-        }
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog, B256, U256},
+        providers::ProviderBuilder,
+        rpc::types::eth::Log,
+        sol_types::{SolCall, SolEvent},
+    };
 
-        xl = xl.overflowing_sub(lo).0;
-        lo = hi << U256_128;
+    use crate::{
+        amm::{AutomatedMarketMaker, SwapParams, AMM},
+        errors::{EventLogError, ExecutionError, SwapSimulationError},
+        test_utils::usdc_weth_pool,
+    };
 
-        if xl < lo {
-            xh -= U256_1;
-        }
+    use super::{factory::UniswapV2Factory, IUniswapV2Pair, UniswapV2Event, UniswapV2Pool};
 
-        xl = xl.overflowing_sub(lo).0;
+    #[test]
+    fn test_simulate_swap_with_transfer_tax() {
+        let token_a = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let mut pool = UniswapV2Pool {
+            token_a,
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            reserve_0: 1_000_000_000_000,
+            reserve_1: 1_000_000_000_000,
+            fee: 300,
+            ..Default::default()
+        };
 
-        if xh != hi >> U256_128 {
-            return Err(ArithmeticError::RoundingError);
-        }
+        let amount_in = U256::from(1_000_000);
+        let untaxed = pool.simulate_swap(token_a, amount_in).unwrap();
 
-        answer += xl / y;
+        // A 5% tax on the input token reduces the amount received by the pool
+        pool.token_a_transfer_tax_bps = 500;
+        let taxed_in = pool.simulate_swap(token_a, amount_in).unwrap();
+        assert_eq!(
+            taxed_in,
+            pool.get_amount_out(
+                U256::from(950_000),
+                U256::from(pool.reserve_0),
+                U256::from(pool.reserve_1)
+            )
+        );
 
-        if answer > U256_0XFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF {
-            return Ok(0_u128);
-        }
+        // A 10% tax on the output token reduces the amount received by the recipient
+        pool.token_a_transfer_tax_bps = 0;
+        pool.token_b_transfer_tax_bps = 1_000;
+        let taxed_out = pool.simulate_swap(token_a, amount_in).unwrap();
+        assert_eq!(taxed_out, untaxed * U256::from(9) / U256::from(10));
 
-        Ok(answer.to::<u128>())
-    } else {
-        Err(ArithmeticError::YIsZero)
+        let reserve_1 = pool.reserve_1;
+        assert_eq!(
+            pool.simulate_swap_mut(token_a, amount_in).unwrap(),
+            taxed_out
+        );
+        assert_eq!(pool.reserve_1, reserve_1 - untaxed.to::<u128>());
     }
-}
 
-/// Converts a Q64 fixed point to a Q16 fixed point -> f64
-pub fn q64_to_f64(x: u128) -> f64 {
-    BigFloat::from(x)
-        .div(&BigFloat::from(U128_0X10000000000000000))
-        .to_f64()
-}
+    #[test]
+    fn test_max_input_for_slippage() {
+        let pool = UniswapV2Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            reserve_0: 1_000_000_000_000_000_000,
+            reserve_1: 2_000_000_000_000_000_000,
+            fee: 300,
+            ..Default::default()
+        };
 
-#[cfg(test)]
-mod tests {
-    use std::sync::Arc;
+        let max_amount_in = pool.max_input_for_slippage(pool.token_a, 50);
+        let amount_out = pool.simulate_swap(pool.token_a, max_amount_in).unwrap();
 
-    use alloy::{
-        primitives::{address, U256},
-        providers::ProviderBuilder,
-    };
+        // Fee adjusted spot price of 2 * 0.997, with a 0.5% bound
+        let min_amount_out = max_amount_in * U256::from(2 * 997 * 995) / U256::from(1_000_000);
+        assert!(amount_out + U256::from(1) >= min_amount_out);
 
-    use crate::amm::AutomatedMarketMaker;
+        let larger_amount_in = max_amount_in * U256::from(101) / U256::from(100);
+        let amount_out = pool.simulate_swap(pool.token_a, larger_amount_in).unwrap();
+        let min_amount_out = larger_amount_in * U256::from(2 * 997 * 995) / U256::from(1_000_000);
+        assert!(amount_out < min_amount_out);
 
-    use super::UniswapV2Pool;
+        assert_eq!(pool.max_input_for_slippage(pool.token_a, 10_000), U256::MAX);
+    }
 
     #[test]
     fn test_swap_calldata() {
@@ -578,7 +956,261 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_swap_calldata_for_input() {
+        let pool = usdc_weth_pool();
+        let to = address!("41c36f504BE664982e7519480409Caf36EE4f008");
+        let amount_in = U256::from(1_000_000);
+
+        let calldata = pool
+            .swap_calldata_for_input(pool.token_a, amount_in, to, vec![])
+            .unwrap();
+        let call = IUniswapV2Pair::swapCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(call.amount0Out, U256::ZERO);
+        assert_eq!(
+            call.amount1Out,
+            pool.simulate_swap(pool.token_a, amount_in).unwrap()
+        );
+        assert_eq!(call.to, to);
+    }
+
+    #[test]
+    fn test_encode_swap() {
+        let pool = UniswapV2Pool {
+            token_b_transfer_tax_bps: 100,
+            ..usdc_weth_pool()
+        };
+        let amount_in = U256::from(1_000_000);
+        let amount_received = pool.simulate_swap(pool.token_a, amount_in).unwrap();
+
+        let params = SwapParams {
+            token_in: pool.token_a,
+            amount_in,
+            amount_out_min: amount_received,
+            recipient: address!("41c36f504BE664982e7519480409Caf36EE4f008"),
+            ..Default::default()
+        };
+        let calldata = pool.encode_swap(params.clone()).unwrap();
+        let call = IUniswapV2Pair::swapCall::abi_decode(&calldata, true).unwrap();
+        // The pair sends the amount before the transfer tax
+        assert_eq!(call.amount0Out, U256::ZERO);
+        assert!(call.amount1Out > amount_received);
+        assert_eq!(call.to, params.recipient);
+
+        assert!(matches!(
+            pool.encode_swap(SwapParams {
+                amount_out_min: amount_received + U256::from(1),
+                ..params.clone()
+            }),
+            Err(ExecutionError::InsufficientOutputAmount { .. })
+        ));
+        assert!(matches!(
+            AMM::UniswapV2Pool(pool).encode_swap(SwapParams {
+                token_in: Address::ZERO,
+                ..params
+            }),
+            Err(ExecutionError::TokenNotInPool(..))
+        ));
+    }
+
+    #[test]
+    fn test_sync_from_swap_log() {
+        let mut pool = UniswapV2Pool {
+            fee: 300,
+            track_swap_stats: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            pool.sync_on_event_signatures(),
+            vec![
+                IUniswapV2Pair::Sync::SIGNATURE_HASH,
+                IUniswapV2Pair::Swap::SIGNATURE_HASH,
+            ]
+        );
+        let swap_event = IUniswapV2Pair::Swap {
+            sender: Address::ZERO,
+            amount0In: U256::from(1_000_000),
+            amount1In: U256::ZERO,
+            amount0Out: U256::ZERO,
+            amount1Out: U256::from(500),
+            to: Address::ZERO,
+        };
+        let log = Log {
+            inner: PrimitiveLog {
+                address: pool.address,
+                data: swap_event.encode_log_data(),
+            },
+            block_number: Some(100),
+            ..Default::default()
+        };
+
+        pool.sync_from_log(log.clone()).unwrap();
+        assert_eq!(pool.stats.volume_0, U256::from(1_000_000));
+        assert_eq!(pool.stats.volume_1, U256::from(500));
+        assert_eq!(pool.stats.fees_0, U256::from(3_000));
+        assert_eq!(pool.stats.last_swap_block, Some(100));
+
+        // Swap logs are only subscribed to when tracking the stats
+        pool.track_swap_stats = false;
+        assert_eq!(
+            pool.sync_on_event_signatures(),
+            vec![IUniswapV2Pair::Sync::SIGNATURE_HASH]
+        );
+        pool.sync_from_log(log).unwrap();
+        assert_eq!(pool.stats.volume_0, U256::from(1_000_000));
+    }
+
+    #[test]
+    fn test_simulate_swap_exact_output() {
+        let token_a = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let token_b = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let mut pool = UniswapV2Pool {
+            token_a,
+            token_b,
+            reserve_0: 47_092_140_895_915,
+            reserve_1: 28_396_598_565_590_008_529_300,
+            fee: 300,
+            ..Default::default()
+        };
+
+        for amount_out in [
+            U256::from(1),
+            U256::from(10_u128.pow(18)),
+            U256::from(10_u128.pow(22)),
+        ] {
+            let amount_in = pool
+                .simulate_swap_exact_output(token_a, amount_out)
+                .unwrap();
+            assert!(pool.simulate_swap(token_a, amount_in).unwrap() >= amount_out);
+            assert!(
+                pool.simulate_swap(token_a, amount_in - U256::from(1))
+                    .unwrap()
+                    < amount_out
+            );
+        }
+
+        pool.token_b_transfer_tax_bps = 500;
+        let amount_out = U256::from(10_u128.pow(18));
+        let amount_in = pool
+            .simulate_swap_exact_output(token_a, amount_out)
+            .unwrap();
+        assert!(pool.simulate_swap(token_a, amount_in).unwrap() >= amount_out);
+
+        assert!(matches!(
+            pool.simulate_swap_exact_output(token_a, U256::from(pool.reserve_1)),
+            Err(SwapSimulationError::InsufficientLiquidity)
+        ));
+    }
+
+    #[test]
+    fn test_sync_from_amount_logs() {
+        let mut pool = UniswapV2Pool {
+            reserve_0: 10_000_000,
+            reserve_1: 5_000,
+            fee: 300,
+            sync_from_amount_logs: true,
+            track_swap_stats: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            pool.sync_on_event_signatures(),
+            vec![
+                IUniswapV2Pair::Sync::SIGNATURE_HASH,
+                IUniswapV2Pair::Swap::SIGNATURE_HASH,
+                IUniswapV2Pair::Mint::SIGNATURE_HASH,
+                IUniswapV2Pair::Burn::SIGNATURE_HASH,
+            ]
+        );
+
+        let log = |data| Log {
+            inner: PrimitiveLog {
+                address: pool.address,
+                data,
+            },
+            block_number: Some(100),
+            ..Default::default()
+        };
+        let sync_log = log(IUniswapV2Pair::Sync {
+            reserve0: 1,
+            reserve1: 1,
+        }
+        .encode_log_data());
+        let swap_log = log(IUniswapV2Pair::Swap {
+            sender: Address::ZERO,
+            amount0In: U256::from(1_000_000),
+            amount1In: U256::ZERO,
+            amount0Out: U256::ZERO,
+            amount1Out: U256::from(500),
+            to: Address::ZERO,
+        }
+        .encode_log_data());
+        let mint_log = log(IUniswapV2Pair::Mint {
+            sender: Address::ZERO,
+            amount0: U256::from(2_000_000),
+            amount1: U256::from(1_000),
+        }
+        .encode_log_data());
+        let burn_log = log(IUniswapV2Pair::Burn {
+            sender: Address::ZERO,
+            amount0: U256::from(3_000_000),
+            amount1: U256::from(1_500),
+            to: Address::ZERO,
+        }
+        .encode_log_data());
+
+        assert!(matches!(
+            UniswapV2Event::decode_log(&mint_log).unwrap(),
+            UniswapV2Event::Mint(_)
+        ));
+
+        pool.sync_from_log(swap_log.clone()).unwrap();
+        assert_eq!((pool.reserve_0, pool.reserve_1), (11_000_000, 4_500));
+        assert_eq!(pool.stats.volume_0, U256::from(1_000_000));
+
+        pool.sync_from_log(mint_log.clone()).unwrap();
+        assert_eq!((pool.reserve_0, pool.reserve_1), (13_000_000, 5_500));
+
+        pool.sync_from_log(burn_log.clone()).unwrap();
+        assert_eq!((pool.reserve_0, pool.reserve_1), (10_000_000, 4_000));
+
+        // Burning more than the reserves is rejected without changing them
+        let excess_burn_log = log(IUniswapV2Pair::Burn {
+            sender: Address::ZERO,
+            amount0: U256::from(1_000),
+            amount1: U256::from(5_000),
+            to: Address::ZERO,
+        }
+        .encode_log_data());
+        assert!(matches!(
+            pool.sync_from_log(excess_burn_log),
+            Err(EventLogError::ReserveUnderflow)
+        ));
+        assert_eq!((pool.reserve_0, pool.reserve_1), (10_000_000, 4_000));
+
+        // Sync logs are authoritative, so the amounts of the logs of the same transaction are not applied again
+        let transaction_hash = Some(B256::repeat_byte(1));
+        let mut transaction_sync_log = log(IUniswapV2Pair::Sync {
+            reserve0: 11_000_000,
+            reserve1: 3_500,
+        }
+        .encode_log_data());
+        transaction_sync_log.transaction_hash = transaction_hash;
+        let mut transaction_swap_log = swap_log;
+        transaction_swap_log.transaction_hash = transaction_hash;
+        pool.sync_from_log(transaction_sync_log).unwrap();
+        pool.sync_from_log(transaction_swap_log).unwrap();
+        assert_eq!((pool.reserve_0, pool.reserve_1), (11_000_000, 3_500));
+
+        // Without the flag, only Sync logs update the reserves
+        pool.sync_from_amount_logs = false;
+        pool.sync_from_log(mint_log).unwrap();
+        assert_eq!((pool.reserve_0, pool.reserve_1), (11_000_000, 3_500));
+        pool.sync_from_log(sync_log).unwrap();
+        assert_eq!((pool.reserve_0, pool.reserve_1), (1, 1));
+    }
+
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_get_new_from_address() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -609,6 +1241,35 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_get_new_from_tokens() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let factory = UniswapV2Factory::new(
+            address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
+            10000835,
+            300,
+        );
+        let pool = UniswapV2Pool::new_from_tokens(
+            &factory,
+            address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            provider.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            pool.address,
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc")
+        );
+        assert_eq!(pool.fee, 300);
+        assert_eq!(pool.factory, Some(factory.address));
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_get_pool_data() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -649,6 +1310,7 @@ mod tests {
             reserve_0: 23595096345912178729927,
             reserve_1: 154664232014390554564,
             fee: 300,
+            ..Default::default()
         };
 
         assert!(x.calculate_price(token_a).unwrap() != 0.0);
@@ -656,6 +1318,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_calculate_price() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -680,6 +1343,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_calculate_price_64_x_64() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -1,8 +1,13 @@
-use std::{sync::Arc, vec};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    vec,
+};
 
 use alloy::{
     dyn_abi::{DynSolType, DynSolValue},
     network::Network,
+    primitives::{Address, U256},
     providers::Provider,
     sol,
     transports::Transport,
@@ -10,11 +15,14 @@ use alloy::{
 use tracing::instrument;
 
 use crate::{
-    amm::{AutomatedMarketMaker, AMM},
+    amm::{
+        multicall::{aggregate, call3, decode},
+        AutomatedMarketMaker, AMM,
+    },
     errors::AMMError,
 };
 
-use super::UniswapV3Pool;
+use super::{IUniswapV3Pool, Info, UniswapV3Pool};
 
 sol! {
     #[allow(missing_docs)]
@@ -309,3 +317,94 @@ where
 
     Ok(())
 }
+
+/// Number of `tick_bitmap` words fetched per Multicall3 call by [`get_tick_words_batch_request`].
+pub const TICK_WORDS_PER_BATCH: usize = 16;
+
+/// Number of ticks fetched per Multicall3 call by [`get_tick_words_batch_request`].
+pub const TICKS_PER_BATCH: usize = 500;
+
+/// Fetches the `tick_bitmap` words at `word_positions` of the pool at `pool`, and the [`Info`] of every initialized
+/// tick inside them, as of `block_number` or the latest block.
+///
+/// The words are fetched in Multicall3 calls of [`TICK_WORDS_PER_BATCH`] words, and the ticks of each range of words
+/// in calls of [`TICKS_PER_BATCH`] ticks, all pinned to the same block, instead of one static call per word and per
+/// tick. Empty words are skipped.
+///
+/// Unlike the other batch requests this does not use a single deployless call returning the words and the ticks
+/// together: the ticks to fetch are only known once the words are decoded, and a dense word holds up to 256
+/// initialized ticks, so a contract walking the words on chain has no bound on the gas of a call. Fetching the ticks
+/// in a second round keeps every call within [`TICKS_PER_BATCH`] ticks, and pinning both rounds to `block_number`
+/// keeps the ticks consistent with the words.
+pub async fn get_tick_words_batch_request<T, N, P>(
+    pool: Address,
+    tick_spacing: i32,
+    word_positions: &[i16],
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<(HashMap<i16, U256>, BTreeMap<i32, Info>), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    if word_positions.is_empty() {
+        return Ok((HashMap::new(), BTreeMap::new()));
+    }
+
+    let block_number = match block_number {
+        Some(block_number) => block_number,
+        None => provider.get_block_number().await?,
+    };
+
+    let mut words = HashMap::new();
+    let mut tick_infos = BTreeMap::new();
+    for word_positions in word_positions.chunks(TICK_WORDS_PER_BATCH) {
+        let calls = word_positions
+            .iter()
+            .map(|word_position| {
+                vec![call3(
+                    pool,
+                    IUniswapV3Pool::tickBitmapCall {
+                        wordPosition: *word_position,
+                    },
+                )]
+            })
+            .collect();
+        let results = aggregate(calls, Some(block_number), provider.clone()).await?;
+
+        let mut ticks = vec![];
+        for (word_position, data) in word_positions.iter().zip(results) {
+            let word = decode::<IUniswapV3Pool::tickBitmapCall>(&data[0])
+                .ok_or(AMMError::BatchRequestError(pool))?
+                ._0;
+
+            if word.is_zero() {
+                continue;
+            }
+
+            ticks.extend(
+                (0..256)
+                    .filter(|bit_pos| word.bit(*bit_pos))
+                    .map(|bit_pos| ((*word_position as i32) * 256 + bit_pos as i32) * tick_spacing),
+            );
+            words.insert(*word_position, word);
+        }
+
+        for ticks in ticks.chunks(TICKS_PER_BATCH) {
+            let calls = ticks
+                .iter()
+                .map(|tick| vec![call3(pool, IUniswapV3Pool::ticksCall { tick: *tick })])
+                .collect();
+            let results = aggregate(calls, Some(block_number), provider.clone()).await?;
+
+            for (tick, data) in ticks.iter().zip(results) {
+                let tick_info = decode::<IUniswapV3Pool::ticksCall>(&data[0])
+                    .ok_or(AMMError::BatchRequestError(pool))?;
+                tick_infos.insert(*tick, Info::new(tick_info._0, tick_info._1, tick_info._7));
+            }
+        }
+    }
+
+    Ok((words, tick_infos))
+}
@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::primitives::{Address, U256};
+use uniswap_v3_math::tick_math::{
+    get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO,
+    MIN_TICK,
+};
+
+use crate::{
+    amm::stats::PoolStats,
+    core::uniswap_v3::{MAX_FEE, MAX_TICK_SPACING},
+    errors::PoolBuilderError,
+};
+
+use super::{Info, UniswapV3Pool};
+
+/// Builder for a [`UniswapV3Pool`].
+///
+/// `address`, `token_a`, `token_b`, `fee` and `tick_spacing` are required. If only one of `sqrt_price` and `tick`
+/// is set, the other is derived from it.
+#[derive(Debug, Clone, Default)]
+pub struct UniswapV3PoolBuilder {
+    address: Option<Address>,
+    token_a: Option<Address>,
+    token_a_decimals: u8,
+    token_b: Option<Address>,
+    token_b_decimals: u8,
+    fee: Option<u32>,
+    tick_spacing: Option<i32>,
+    liquidity: u128,
+    sqrt_price: Option<U256>,
+    tick: Option<i32>,
+    tick_bitmap: HashMap<i16, U256>,
+    ticks: BTreeMap<i32, Info>,
+    factory: Option<Address>,
+}
+
+impl UniswapV3PoolBuilder {
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Sets token 0 of the pool.
+    pub fn token_a(mut self, token_a: Address, decimals: u8) -> Self {
+        self.token_a = Some(token_a);
+        self.token_a_decimals = decimals;
+        self
+    }
+
+    /// Sets token 1 of the pool.
+    pub fn token_b(mut self, token_b: Address, decimals: u8) -> Self {
+        self.token_b = Some(token_b);
+        self.token_b_decimals = decimals;
+        self
+    }
+
+    /// Sets the swap fee in hundredths of a basis point, i.e. 3000 for 0.3%.
+    pub fn fee(mut self, fee: u32) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    pub fn tick_spacing(mut self, tick_spacing: i32) -> Self {
+        self.tick_spacing = Some(tick_spacing);
+        self
+    }
+
+    pub fn liquidity(mut self, liquidity: u128) -> Self {
+        self.liquidity = liquidity;
+        self
+    }
+
+    pub fn sqrt_price(mut self, sqrt_price: U256) -> Self {
+        self.sqrt_price = Some(sqrt_price);
+        self
+    }
+
+    pub fn tick(mut self, tick: i32) -> Self {
+        self.tick = Some(tick);
+        self
+    }
+
+    pub fn tick_bitmap(mut self, tick_bitmap: HashMap<i16, U256>) -> Self {
+        self.tick_bitmap = tick_bitmap;
+        self
+    }
+
+    pub fn ticks(mut self, ticks: BTreeMap<i32, Info>) -> Self {
+        self.ticks = ticks;
+        self
+    }
+
+    /// Sets the factory that created the pool.
+    pub fn factory(mut self, factory: Address) -> Self {
+        self.factory = Some(factory);
+        self
+    }
+
+    /// Validates the configuration and builds the pool.
+    pub fn build(self) -> Result<UniswapV3Pool, PoolBuilderError> {
+        let address = self
+            .address
+            .ok_or(PoolBuilderError::MissingField("address"))?;
+        let token_a = self
+            .token_a
+            .ok_or(PoolBuilderError::MissingField("token_a"))?;
+        let token_b = self
+            .token_b
+            .ok_or(PoolBuilderError::MissingField("token_b"))?;
+        let fee = self.fee.ok_or(PoolBuilderError::MissingField("fee"))?;
+        let tick_spacing = self
+            .tick_spacing
+            .ok_or(PoolBuilderError::MissingField("tick_spacing"))?;
+
+        match token_a.cmp(&token_b) {
+            std::cmp::Ordering::Equal => return Err(PoolBuilderError::IdenticalTokens),
+            std::cmp::Ordering::Greater => return Err(PoolBuilderError::UnsortedTokens),
+            std::cmp::Ordering::Less => {}
+        }
+
+        if fee >= MAX_FEE {
+            return Err(PoolBuilderError::InvalidFee(fee));
+        }
+
+        if !(1..=MAX_TICK_SPACING).contains(&tick_spacing) {
+            return Err(PoolBuilderError::InvalidTickSpacing(tick_spacing));
+        }
+
+        let (sqrt_price, tick) = match (self.sqrt_price, self.tick) {
+            (Some(sqrt_price), tick) => {
+                if sqrt_price < MIN_SQRT_RATIO || sqrt_price >= MAX_SQRT_RATIO {
+                    return Err(PoolBuilderError::InvalidSqrtPrice(sqrt_price));
+                }
+
+                let sqrt_price_tick = get_tick_at_sqrt_ratio(sqrt_price)
+                    .map_err(|_| PoolBuilderError::InvalidSqrtPrice(sqrt_price))?;
+
+                match tick {
+                    Some(tick) if tick != sqrt_price_tick => {
+                        return Err(PoolBuilderError::InvalidTick(tick))
+                    }
+                    _ => (sqrt_price, sqrt_price_tick),
+                }
+            }
+            (None, Some(tick)) => {
+                if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+                    return Err(PoolBuilderError::InvalidTick(tick));
+                }
+
+                let sqrt_price = get_sqrt_ratio_at_tick(tick)
+                    .map_err(|_| PoolBuilderError::InvalidTick(tick))?;
+
+                (sqrt_price, tick)
+            }
+            (None, None) => (U256::ZERO, 0),
+        };
+
+        Ok(UniswapV3Pool {
+            address,
+            token_a,
+            token_a_decimals: self.token_a_decimals,
+            token_b,
+            token_b_decimals: self.token_b_decimals,
+            liquidity: self.liquidity,
+            sqrt_price,
+            fee,
+            tick,
+            tick_spacing,
+            tick_bitmap: self.tick_bitmap,
+            ticks: self.ticks,
+            tick_window: None,
+            stats: PoolStats::default(),
+            factory: self.factory,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    fn builder() -> UniswapV3PoolBuilder {
+        UniswapV3Pool::builder()
+            .address(address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"))
+            .token_a(address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"), 6)
+            .token_b(address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"), 18)
+            .fee(500)
+            .tick_spacing(10)
+    }
+
+    #[test]
+    fn test_build() {
+        let pool = builder().tick(200_000).build().unwrap();
+        assert_eq!(pool.tick, 200_000);
+        assert_eq!(pool.sqrt_price, get_sqrt_ratio_at_tick(200_000).unwrap());
+
+        let pool = builder().sqrt_price(pool.sqrt_price).build().unwrap();
+        assert_eq!(pool.tick, 200_000);
+    }
+
+    #[test]
+    fn test_build_invalid() {
+        assert_eq!(
+            UniswapV3Pool::builder().build().unwrap_err(),
+            PoolBuilderError::MissingField("address")
+        );
+
+        let token_a = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let token_b = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        assert_eq!(
+            builder()
+                .token_a(token_b, 18)
+                .token_b(token_a, 6)
+                .build()
+                .unwrap_err(),
+            PoolBuilderError::UnsortedTokens
+        );
+
+        assert_eq!(
+            builder().fee(1_000_000).build().unwrap_err(),
+            PoolBuilderError::InvalidFee(1_000_000)
+        );
+        assert_eq!(
+            builder().tick_spacing(0).build().unwrap_err(),
+            PoolBuilderError::InvalidTickSpacing(0)
+        );
+        assert_eq!(
+            builder().tick(MAX_TICK + 1).build().unwrap_err(),
+            PoolBuilderError::InvalidTick(MAX_TICK + 1)
+        );
+        assert_eq!(
+            builder()
+                .sqrt_price(get_sqrt_ratio_at_tick(100).unwrap())
+                .tick(0)
+                .build()
+                .unwrap_err(),
+            PoolBuilderError::InvalidTick(0)
+        );
+    }
+}
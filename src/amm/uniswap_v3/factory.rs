@@ -18,11 +18,18 @@ use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
 use crate::{
-    amm::{factory::AutomatedMarketMakerFactory, AutomatedMarketMaker, AMM},
+    amm::{
+        batch_request::populate_amms_with_config,
+        factory::AutomatedMarketMakerFactory,
+        multicall::{aggregate, call3, decode},
+        stats::PoolStats,
+        AutomatedMarketMaker, AMM,
+    },
     errors::{AMMError, EventLogError},
+    sync::config::SyncConfig,
 };
 
-use super::{batch_request, IUniswapV3Pool, UniswapV3Pool};
+use super::{IUniswapV3Pool, UniswapV3Pool};
 
 sol! {
     /// Interface of the UniswapV3Factory contract
@@ -30,6 +37,7 @@ sol! {
     #[sol(rpc)]
     contract IUniswapV3Factory {
         event PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool);
+        event FeeAmountEnabled(uint24 indexed fee, int24 indexed tickSpacing);
         function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool);
         function parameters() returns (address, address, uint24, int24);
         function feeAmountTickSpacing(uint24) returns (int24);
@@ -104,16 +112,8 @@ impl AutomatedMarketMakerFactory for UniswapV3Factory {
         P: Provider<T, N>,
     {
         if let Some(block_number) = block_number {
-            // Max batch size for call
-            let step = 76;
-            for amm_chunk in amms.chunks_mut(step) {
-                batch_request::get_amm_data_batch_request(
-                    amm_chunk,
-                    block_number,
-                    provider.clone(),
-                )
+            populate_amms_with_config(amms, Some(block_number), &SyncConfig::default(), provider)
                 .await?;
-            }
         } else {
             return Err(AMMError::BlockNumberNotFound);
         }
@@ -133,10 +133,13 @@ impl AutomatedMarketMakerFactory for UniswapV3Factory {
             fee: pool_created_event.fee,
             liquidity: 0,
             sqrt_price: U256::ZERO,
-            tick_spacing: 0,
+            tick_spacing: pool_created_event.tickSpacing,
             tick: 0,
             tick_bitmap: HashMap::new(),
-            ticks: HashMap::new(),
+            ticks: BTreeMap::new(),
+            tick_window: None,
+            stats: PoolStats::default(),
+            factory: Some(self.address),
         }))
     }
 }
@@ -149,7 +152,120 @@ impl UniswapV3Factory {
         }
     }
 
+    /// Returns the fee tiers enabled on the factory up to `to_block` with their tick spacing, from its
+    /// FeeAmountEnabled logs.
+    ///
+    /// This includes the default tiers enabled at deployment and the tiers enabled later through `enableFeeAmount`.
+    pub async fn get_fee_tiers<T, N, P>(
+        &self,
+        to_block: u64,
+        step: u64,
+        provider: Arc<P>,
+    ) -> Result<Vec<(u32, i32)>, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut fee_tiers = vec![];
+        let mut from_block = self.creation_block;
+
+        while from_block <= to_block {
+            let target_block = (from_block + step - 1).min(to_block);
+            let logs = provider
+                .get_logs(
+                    &Filter::new()
+                        .event_signature(IUniswapV3Factory::FeeAmountEnabled::SIGNATURE_HASH)
+                        .address(self.address)
+                        .from_block(from_block)
+                        .to_block(target_block),
+                )
+                .await?;
+
+            for log in logs {
+                let fee_amount_enabled =
+                    IUniswapV3Factory::FeeAmountEnabled::decode_log(log.as_ref(), true)?;
+                fee_tiers.push((fee_amount_enabled.fee, fee_amount_enabled.tickSpacing));
+            }
+
+            from_block += step;
+        }
+
+        Ok(fee_tiers)
+    }
+
+    /// Returns the pools of `token_a` and `token_b` in `fee_tiers`, e.g. from [`UniswapV3Factory::get_fee_tiers`]
+    /// or [`super::DEFAULT_FEE_TIERS`], with `getPool` and `feeAmountTickSpacing` queried for every tier in one Multicall3
+    /// call.
+    ///
+    /// Fee tiers without a pool are skipped. The pools hold their tokens, fee and tick spacing, and are ready to be
+    /// populated.
+    pub async fn get_pools_for_pair<T, N, P>(
+        &self,
+        token_a: Address,
+        token_b: Address,
+        fee_tiers: &[u32],
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<Vec<UniswapV3Pool>, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let (token_a, token_b) = if token_a < token_b {
+            (token_a, token_b)
+        } else {
+            (token_b, token_a)
+        };
+
+        let calls = fee_tiers
+            .iter()
+            .map(|fee| {
+                vec![
+                    call3(
+                        self.address,
+                        IUniswapV3Factory::getPoolCall {
+                            tokenA: token_a,
+                            tokenB: token_b,
+                            fee: *fee,
+                        },
+                    ),
+                    call3(
+                        self.address,
+                        IUniswapV3Factory::feeAmountTickSpacingCall { _0: *fee },
+                    ),
+                ]
+            })
+            .collect();
+        let results = aggregate(calls, block_number, provider).await?;
+
+        Ok(fee_tiers
+            .iter()
+            .zip(results)
+            .filter_map(|(fee, data)| {
+                let address = decode::<IUniswapV3Factory::getPoolCall>(&data[0])?.pool;
+                let tick_spacing =
+                    decode::<IUniswapV3Factory::feeAmountTickSpacingCall>(&data[1])?._0;
+
+                (!address.is_zero()).then_some(UniswapV3Pool {
+                    address,
+                    token_a,
+                    token_b,
+                    fee: *fee,
+                    tick_spacing,
+                    factory: Some(self.address),
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+
     // Function to get all pair created events for a given Dex factory address and sync pool data
+    #[cfg_attr(
+        feature = "tracing-spans",
+        instrument(skip(self, provider), fields(factory = ?self.address), level = "debug")
+    )]
     pub async fn get_all_pools_from_logs<T, N, P>(
         self,
         to_block: u64,
@@ -176,7 +292,7 @@ impl UniswapV3Factory {
             }
 
             futures.push_back(async move {
-                provider
+                let logs = provider
                     .get_logs(
                         &Filter::new()
                             .event_signature(vec![
@@ -187,7 +303,14 @@ impl UniswapV3Factory {
                             .from_block(from_block)
                             .to_block(target_block),
                     )
-                    .await
+                    .await;
+
+                #[cfg(feature = "tracing-spans")]
+                if let Ok(logs) = &logs {
+                    tracing::debug!(from_block, target_block, logs = logs.len(), "Fetched logs");
+                }
+
+                logs
             });
 
             from_block += step;
@@ -217,11 +340,9 @@ impl UniswapV3Factory {
                 //If the event sig is the pool created event sig, then the log is coming from the factory
                 if event_signature == IUniswapV3Factory::PoolCreated::SIGNATURE_HASH {
                     if log.address() == self.address {
-                        let mut new_pool = self.new_empty_amm_from_log(log)?;
-                        if let AMM::UniswapV3Pool(ref mut pool) = new_pool {
-                            pool.tick_spacing = pool.get_tick_spacing(provider.clone()).await?;
-                        }
-
+                        // The pool holds the tick spacing of the event, so that tiers enabled with
+                        // enableFeeAmount need no call to the pool
+                        let new_pool = self.new_empty_amm_from_log(log)?;
                         aggregated_amms.insert(new_pool.address(), new_pool);
                     }
                 } else if event_signature == IUniswapV3Pool::Burn::SIGNATURE_HASH {
@@ -242,3 +363,94 @@ impl UniswapV3Factory {
         Ok(aggregated_amms.into_values().collect::<Vec<AMM>>())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog},
+        providers::{Provider, ProviderBuilder},
+        rpc::types::eth::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::amm::{factory::AutomatedMarketMakerFactory, uniswap_v3::DEFAULT_FEE_TIERS, AMM};
+
+    use super::{IUniswapV3Factory, UniswapV3Factory};
+
+    #[test]
+    fn test_new_empty_amm_from_log() {
+        let factory = UniswapV3Factory::new(Address::with_last_byte(1), 0);
+        let pool = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+
+        // A tier enabled with enableFeeAmount, outside of the default fee tiers
+        let event = IUniswapV3Factory::PoolCreated {
+            token0: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token1: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 200,
+            tickSpacing: 4,
+            pool,
+        };
+        let log = Log {
+            inner: PrimitiveLog {
+                address: factory.address,
+                data: event.encode_log_data(),
+            },
+            block_number: Some(100),
+            ..Default::default()
+        };
+
+        let AMM::UniswapV3Pool(uniswap_v3_pool) = factory.new_empty_amm_from_log(log).unwrap()
+        else {
+            panic!("Unexpected AMM variant")
+        };
+        assert_eq!(uniswap_v3_pool.address, pool);
+        assert_eq!(uniswap_v3_pool.fee, 200);
+        assert_eq!(uniswap_v3_pool.tick_spacing, 4);
+        assert_eq!(uniswap_v3_pool.factory, Some(factory.address));
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_get_pools_for_pair() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let factory = UniswapV3Factory::new(
+            address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+            12369621,
+        );
+        let block_number = provider.get_block_number().await.unwrap();
+        let fee_tiers = factory
+            .get_fee_tiers(block_number, 100000, provider.clone())
+            .await
+            .unwrap();
+        for fee_tier in DEFAULT_FEE_TIERS {
+            assert!(fee_tiers.contains(&fee_tier));
+        }
+
+        let fees = fee_tiers.iter().map(|(fee, _)| *fee).collect::<Vec<_>>();
+        let pools = factory
+            .get_pools_for_pair(
+                address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+                address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+                &fees,
+                Some(block_number),
+                provider,
+            )
+            .await
+            .unwrap();
+
+        let usdc_weth_500 = pools
+            .iter()
+            .find(|pool| pool.fee == 500)
+            .expect("USDC/WETH 0.05% pool exists");
+        assert_eq!(
+            usdc_weth_500.address,
+            address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")
+        );
+        assert_eq!(usdc_weth_500.tick_spacing, 10);
+        assert!(usdc_weth_500.token_a < usdc_weth_500.token_b);
+    }
+}
@@ -0,0 +1,194 @@
+//! Port of Uniswap V3 periphery `LiquidityAmounts.sol`.
+
+use alloy::primitives::U256;
+use uniswap_v3_math::full_math::mul_div;
+
+use crate::{amm::consts::Q96, errors::ArithmeticError};
+
+/// Computes the amount of liquidity received for a given amount of token 0 and price range.
+pub fn get_liquidity_for_amount_0(
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    amount_0: U256,
+) -> Result<u128, ArithmeticError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        std::mem::swap(&mut sqrt_ratio_a_x_96, &mut sqrt_ratio_b_x_96);
+    }
+
+    let intermediate = mul_div(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, Q96)?;
+    to_u128(mul_div(
+        amount_0,
+        intermediate,
+        sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96,
+    )?)
+}
+
+/// Computes the amount of liquidity received for a given amount of token 1 and price range.
+pub fn get_liquidity_for_amount_1(
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    amount_1: U256,
+) -> Result<u128, ArithmeticError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        std::mem::swap(&mut sqrt_ratio_a_x_96, &mut sqrt_ratio_b_x_96);
+    }
+
+    to_u128(mul_div(
+        amount_1,
+        Q96,
+        sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96,
+    )?)
+}
+
+/// Computes the maximum amount of liquidity received for a given amount of token 0, token 1,
+/// the current pool price and the prices at the tick boundaries.
+pub fn get_liquidity_for_amounts(
+    sqrt_ratio_x_96: U256,
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    amount_0: U256,
+    amount_1: U256,
+) -> Result<u128, ArithmeticError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        std::mem::swap(&mut sqrt_ratio_a_x_96, &mut sqrt_ratio_b_x_96);
+    }
+
+    if sqrt_ratio_x_96 <= sqrt_ratio_a_x_96 {
+        get_liquidity_for_amount_0(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, amount_0)
+    } else if sqrt_ratio_x_96 < sqrt_ratio_b_x_96 {
+        let liquidity_0 = get_liquidity_for_amount_0(sqrt_ratio_x_96, sqrt_ratio_b_x_96, amount_0)?;
+        let liquidity_1 = get_liquidity_for_amount_1(sqrt_ratio_a_x_96, sqrt_ratio_x_96, amount_1)?;
+
+        Ok(liquidity_0.min(liquidity_1))
+    } else {
+        get_liquidity_for_amount_1(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, amount_1)
+    }
+}
+
+/// Computes the amount of token 0 for a given amount of liquidity and a price range.
+pub fn get_amount_0_for_liquidity(
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    liquidity: u128,
+) -> Result<U256, ArithmeticError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        std::mem::swap(&mut sqrt_ratio_a_x_96, &mut sqrt_ratio_b_x_96);
+    }
+
+    Ok(mul_div(
+        U256::from(liquidity) << 96,
+        sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96,
+        sqrt_ratio_b_x_96,
+    )? / sqrt_ratio_a_x_96)
+}
+
+/// Computes the amount of token 1 for a given amount of liquidity and a price range.
+pub fn get_amount_1_for_liquidity(
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    liquidity: u128,
+) -> Result<U256, ArithmeticError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        std::mem::swap(&mut sqrt_ratio_a_x_96, &mut sqrt_ratio_b_x_96);
+    }
+
+    Ok(mul_div(
+        U256::from(liquidity),
+        sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96,
+        Q96,
+    )?)
+}
+
+/// Computes the token 0 and token 1 value for a given amount of liquidity,
+/// the current pool price and the prices at the tick boundaries.
+pub fn get_amounts_for_liquidity(
+    sqrt_ratio_x_96: U256,
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    liquidity: u128,
+) -> Result<(U256, U256), ArithmeticError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        std::mem::swap(&mut sqrt_ratio_a_x_96, &mut sqrt_ratio_b_x_96);
+    }
+
+    if sqrt_ratio_x_96 <= sqrt_ratio_a_x_96 {
+        Ok((
+            get_amount_0_for_liquidity(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, liquidity)?,
+            U256::ZERO,
+        ))
+    } else if sqrt_ratio_x_96 < sqrt_ratio_b_x_96 {
+        Ok((
+            get_amount_0_for_liquidity(sqrt_ratio_x_96, sqrt_ratio_b_x_96, liquidity)?,
+            get_amount_1_for_liquidity(sqrt_ratio_a_x_96, sqrt_ratio_x_96, liquidity)?,
+        ))
+    } else {
+        Ok((
+            U256::ZERO,
+            get_amount_1_for_liquidity(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, liquidity)?,
+        ))
+    }
+}
+
+fn to_u128(x: U256) -> Result<u128, ArithmeticError> {
+    x.try_into()
+        .map_err(|_| ArithmeticError::U128ConversionError)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+    use uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick;
+
+    use super::*;
+
+    #[test]
+    fn test_liquidity_amounts_round_trip() {
+        let sqrt_price = get_sqrt_ratio_at_tick(0).unwrap();
+        let sqrt_price_lower = get_sqrt_ratio_at_tick(-600).unwrap();
+        let sqrt_price_upper = get_sqrt_ratio_at_tick(600).unwrap();
+        let liquidity = 1_000_000_000_000_000_000_u128;
+
+        let (amount_0, amount_1) =
+            get_amounts_for_liquidity(sqrt_price, sqrt_price_lower, sqrt_price_upper, liquidity)
+                .unwrap();
+
+        let round_trip = get_liquidity_for_amounts(
+            sqrt_price,
+            sqrt_price_lower,
+            sqrt_price_upper,
+            amount_0,
+            amount_1,
+        )
+        .unwrap();
+
+        // Amounts are rounded down, so the liquidity can only be lost to rounding
+        assert!(round_trip <= liquidity);
+        assert!(liquidity - round_trip < 1_000);
+    }
+
+    #[test]
+    fn test_amounts_for_liquidity_out_of_range() {
+        let sqrt_price_lower = get_sqrt_ratio_at_tick(-600).unwrap();
+        let sqrt_price_upper = get_sqrt_ratio_at_tick(600).unwrap();
+
+        let (amount_0, amount_1) = get_amounts_for_liquidity(
+            get_sqrt_ratio_at_tick(-1200).unwrap(),
+            sqrt_price_lower,
+            sqrt_price_upper,
+            1_000_000,
+        )
+        .unwrap();
+        assert!(amount_0 > U256::ZERO);
+        assert_eq!(amount_1, U256::ZERO);
+
+        let (amount_0, amount_1) = get_amounts_for_liquidity(
+            get_sqrt_ratio_at_tick(1200).unwrap(),
+            sqrt_price_lower,
+            sqrt_price_upper,
+            1_000_000,
+        )
+        .unwrap();
+        assert_eq!(amount_0, U256::ZERO);
+        assert!(amount_1 > U256::ZERO);
+    }
+}
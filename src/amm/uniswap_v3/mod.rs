@@ -1,32 +1,66 @@
+#[cfg(feature = "provider")]
 pub mod batch_request;
+pub mod builder;
+#[cfg(feature = "provider")]
 pub mod factory;
+pub mod liquidity_amounts;
+#[cfg(feature = "provider")]
+pub mod quoter;
+pub mod serde_maps;
+pub mod subgraph;
 
 use crate::{
-    amm::{consts::*, AutomatedMarketMaker, IErc20},
-    errors::{AMMError, ArithmeticError, EventLogError, SwapSimulationError},
+    amm::{
+        consts::*,
+        stats::{fee_amount, PoolStats, SwapRecord},
+        AutomatedMarketMaker, Protocol, SwapParams,
+    },
+    core::{
+        price,
+        uniswap_v3::{self as math, TickSource},
+    },
+    errors::{
+        ArithmeticError, EventLogError, ExecutionError, PoolBuilderError, SwapSimulationError,
+    },
+};
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{multicall, AMM},
+    errors::{AMMError, ErrorContext, ResultExt},
+    sync::config::SyncConfig,
+    token::get_token_metadata_batch_request,
+};
+#[cfg(feature = "provider")]
+use alloy::{
+    network::Network, providers::Provider, rpc::types::eth::Filter, transports::Transport,
 };
 use alloy::{
-    network::Network,
     primitives::{Address, Bytes, B256, I256, U256},
-    providers::Provider,
-    rpc::types::eth::{Filter, Log},
+    rpc::types::eth::Log,
     sol,
     sol_types::{SolCall, SolEvent},
-    transports::Transport,
 };
 use async_trait::async_trait;
-use futures::{stream::FuturesOrdered, StreamExt};
+#[cfg(feature = "provider")]
+use futures::StreamExt;
 use num_bigfloat::BigFloat;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "provider")]
+use std::sync::Arc;
 use std::{
-    cmp::Ordering,
     collections::{BTreeMap, HashMap},
-    sync::Arc,
+    ops::ControlFlow,
 };
 use tracing::instrument;
-use uniswap_v3_math::tick_math::{MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK};
+use uniswap_v3_math::{
+    full_math::{mul_div, mul_div_rounding_up},
+    tick_math::{MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK},
+};
+
+#[cfg(feature = "provider")]
+use self::factory::{IUniswapV3Factory, UniswapV3Factory};
 
-use self::factory::IUniswapV3Factory;
+pub use crate::core::uniswap_v3::{CurrentState, InitializedTicks, TickDirection};
 
 sol! {
     /// Interface of the IUniswapV3Pool
@@ -36,6 +70,7 @@ sol! {
         event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick);
         event Burn(address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1);
         event Mint(address sender, address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1);
+        event Collect(address indexed owner, address recipient, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount0, uint128 amount1);
         function token0() external view returns (address);
         function token1() external view returns (address);
         function liquidity() external view returns (uint128);
@@ -45,14 +80,44 @@ sol! {
         function ticks(int24 tick) external view returns (uint128, int128, uint256, uint256, int56, uint160, uint32, bool);
         function tickBitmap(int16 wordPosition) external view returns (uint256);
         function swap(address recipient, bool zeroForOne, int256 amountSpecified, uint160 sqrtPriceLimitX96, bytes calldata data) external returns (int256, int256);
+        function flash(address recipient, uint256 amount0, uint256 amount1, bytes calldata data) external;
     }
 }
 
+/// Estimated gas used by a swap within the current tick range, including the token transfers.
+pub const SWAP_BASE_GAS_ESTIMATE: u64 = 100_000;
+/// Estimated gas used for each initialized tick crossed by a swap.
+pub const TICK_CROSSED_GAS_ESTIMATE: u64 = 30_000;
+
+/// Denominator of the pool fee, a fee of 3000 is 0.3%.
+pub const FEE_DENOMINATOR: u32 = 1_000_000;
+
+/// Number of `tick_bitmap` words fetched at once by [`UniswapV3Pool::simulate_swap_lazy`] in the swap direction.
+pub const LAZY_TICK_WORDS: i16 = 4;
+
+/// Fee tiers enabled on the mainnet Uniswap V3 factory, with their tick spacing.
+pub const DEFAULT_FEE_TIERS: [(u32, i32); 4] = [(100, 1), (500, 10), (3000, 60), (10000, 200)];
+
+/// Returns the tick spacing of a fee tier in [`DEFAULT_FEE_TIERS`].
+///
+/// Factories can enable other tiers with `enableFeeAmount`, whose tick spacing is read from the factory with
+/// [`UniswapV3Factory::get_fee_tiers`](factory::UniswapV3Factory::get_fee_tiers).
+pub fn tick_spacing_for_fee(fee: u32) -> Option<i32> {
+    DEFAULT_FEE_TIERS
+        .iter()
+        .find(|(fee_tier, _)| *fee_tier == fee)
+        .map(|(_, tick_spacing)| *tick_spacing)
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UniswapV3Pool {
     pub address: Address,
+    /// Token 0 of the pool, prefer [`UniswapV3Pool::token0`] which does not depend on the field order.
+    #[doc(alias = "token0")]
     pub token_a: Address,
     pub token_a_decimals: u8,
+    /// Token 1 of the pool, prefer [`UniswapV3Pool::token1`] which does not depend on the field order.
+    #[doc(alias = "token1")]
     pub token_b: Address,
     pub token_b_decimals: u8,
     pub liquidity: u128,
@@ -60,11 +125,22 @@ pub struct UniswapV3Pool {
     pub fee: u32,
     pub tick: i32,
     pub tick_spacing: i32,
+    #[serde(with = "serde_maps::tick_bitmap")]
     pub tick_bitmap: HashMap<i16, U256>,
-    pub ticks: HashMap<i32, Info>,
+    #[serde(with = "serde_maps::ticks")]
+    pub ticks: BTreeMap<i32, Info>,
+    /// Inclusive range of `tick_bitmap` words with loaded tick data, `None` if all tick data is loaded.
+    #[serde(default)]
+    pub tick_window: Option<(i16, i16)>,
+    /// Swap volume and fees, updated from Swap logs.
+    #[serde(default)]
+    pub stats: PoolStats,
+    /// Factory that created the pool, if it is known.
+    #[serde(default)]
+    pub factory: Option<Address>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Info {
     pub liquidity_gross: u128,
     pub liquidity_net: i128,
@@ -87,6 +163,19 @@ impl AutomatedMarketMaker for UniswapV3Pool {
         self.address
     }
 
+    fn protocol(&self) -> Protocol {
+        Protocol::UniswapV3
+    }
+
+    fn fee_bps(&self) -> u32 {
+        self.fee * 10_000 / FEE_DENOMINATOR
+    }
+
+    fn factory(&self) -> Option<Address> {
+        self.factory
+    }
+
+    #[cfg(feature = "provider")]
     #[instrument(skip(self, provider), level = "debug")]
     async fn sync<T, N, P>(&mut self, provider: Arc<P>) -> Result<(), AMMError>
     where
@@ -94,7 +183,9 @@ impl AutomatedMarketMaker for UniswapV3Pool {
         N: Network,
         P: Provider<T, N>,
     {
-        batch_request::sync_v3_pool_batch_request(self, provider.clone()).await?;
+        batch_request::sync_v3_pool_batch_request(self, provider.clone())
+            .await
+            .context(ErrorContext::sync(self.address))?;
         Ok(())
     }
 
@@ -130,20 +221,15 @@ impl AutomatedMarketMaker for UniswapV3Pool {
 
     fn calculate_price(&self, base_token: Address) -> Result<f64, ArithmeticError> {
         let tick = uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(self.sqrt_price)?;
-        let shift = self.token_a_decimals as i8 - self.token_b_decimals as i8;
+        let price = price::tick_to_price(tick, self.token0_decimals(), self.token1_decimals());
 
-        let price = match shift.cmp(&0) {
-            Ordering::Less => 1.0001_f64.powi(tick) / 10_f64.powi(-shift as i32),
-            Ordering::Greater => 1.0001_f64.powi(tick) * 10_f64.powi(shift as i32),
-            Ordering::Equal => 1.0001_f64.powi(tick),
-        };
-
-        if base_token == self.token_a {
+        if self.is_token0(base_token) {
             Ok(price)
         } else {
             Ok(1.0 / price)
         }
     }
+    #[cfg(feature = "provider")]
     // NOTE: This function will not populate the tick_bitmap and ticks, if you want to populate those, you must call populate_tick_data on an initialized pool
     async fn populate_data<T, N, P>(
         &mut self,
@@ -155,7 +241,21 @@ impl AutomatedMarketMaker for UniswapV3Pool {
         N: Network,
         P: Provider<T, N>,
     {
-        batch_request::get_v3_pool_data_batch_request(self, block_number, provider.clone()).await?;
+        if let Err(err) =
+            batch_request::get_v3_pool_data_batch_request(self, block_number, provider.clone())
+                .await
+        {
+            tracing::debug!(?err, address = ?self.address, "Deployless batch request failed, falling back to Multicall3");
+
+            let mut amms = [AMM::UniswapV3Pool(self.clone())];
+            multicall::get_amm_data_batch_request(&mut amms, block_number, provider)
+                .await
+                .context(ErrorContext::populate(self.address, block_number))?;
+            if let [AMM::UniswapV3Pool(pool)] = amms {
+                *self = pool;
+            }
+        }
+
         Ok(())
     }
 
@@ -168,126 +268,7 @@ impl AutomatedMarketMaker for UniswapV3Pool {
             return Ok(U256::ZERO);
         }
 
-        let zero_for_one = token_in == self.token_a;
-
-        // Set sqrt_price_limit_x_96 to the max or min sqrt price in the pool depending on zero_for_one
-        let sqrt_price_limit_x_96 = if zero_for_one {
-            MIN_SQRT_RATIO + U256_1
-        } else {
-            MAX_SQRT_RATIO - U256_1
-        };
-
-        // Initialize a mutable state state struct to hold the dynamic simulated state of the pool
-        let mut current_state = CurrentState {
-            sqrt_price_x_96: self.sqrt_price, //Active price on the pool
-            amount_calculated: I256::ZERO,    //Amount of token_out that has been calculated
-            amount_specified_remaining: I256::from_raw(amount_in), //Amount of token_in that has not been swapped
-            tick: self.tick,                                       //Current i24 tick of the pool
-            liquidity: self.liquidity, //Current available liquidity in the tick range
-        };
-
-        while current_state.amount_specified_remaining != I256::ZERO
-            && current_state.sqrt_price_x_96 != sqrt_price_limit_x_96
-        {
-            // Initialize a new step struct to hold the dynamic state of the pool at each step
-            let mut step = StepComputations {
-                // Set the sqrt_price_start_x_96 to the current sqrt_price_x_96
-                sqrt_price_start_x_96: current_state.sqrt_price_x_96,
-                ..Default::default()
-            };
-
-            // Get the next tick from the current tick
-            (step.tick_next, step.initialized) =
-                uniswap_v3_math::tick_bitmap::next_initialized_tick_within_one_word(
-                    &self.tick_bitmap,
-                    current_state.tick,
-                    self.tick_spacing,
-                    zero_for_one,
-                )?;
-
-            // ensure that we do not overshoot the min/max tick, as the tick bitmap is not aware of these bounds
-            // Note: this could be removed as we are clamping in the batch contract
-            step.tick_next = step.tick_next.clamp(MIN_TICK, MAX_TICK);
-
-            // Get the next sqrt price from the input amount
-            step.sqrt_price_next_x96 =
-                uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(step.tick_next)?;
-
-            // Target spot price
-            let swap_target_sqrt_ratio = if zero_for_one {
-                if step.sqrt_price_next_x96 < sqrt_price_limit_x_96 {
-                    sqrt_price_limit_x_96
-                } else {
-                    step.sqrt_price_next_x96
-                }
-            } else if step.sqrt_price_next_x96 > sqrt_price_limit_x_96 {
-                sqrt_price_limit_x_96
-            } else {
-                step.sqrt_price_next_x96
-            };
-
-            // Compute swap step and update the current state
-            (
-                current_state.sqrt_price_x_96,
-                step.amount_in,
-                step.amount_out,
-                step.fee_amount,
-            ) = uniswap_v3_math::swap_math::compute_swap_step(
-                current_state.sqrt_price_x_96,
-                swap_target_sqrt_ratio,
-                current_state.liquidity,
-                current_state.amount_specified_remaining,
-                self.fee,
-            )?;
-
-            // Decrement the amount remaining to be swapped and amount received from the step
-            current_state.amount_specified_remaining = current_state
-                .amount_specified_remaining
-                .overflowing_sub(I256::from_raw(
-                    step.amount_in.overflowing_add(step.fee_amount).0,
-                ))
-                .0;
-
-            current_state.amount_calculated -= I256::from_raw(step.amount_out);
-
-            // If the price moved all the way to the next price, recompute the liquidity change for the next iteration
-            if current_state.sqrt_price_x_96 == step.sqrt_price_next_x96 {
-                if step.initialized {
-                    let mut liquidity_net = if let Some(info) = self.ticks.get(&step.tick_next) {
-                        info.liquidity_net
-                    } else {
-                        0
-                    };
-
-                    // we are on a tick boundary, and the next tick is initialized, so we must charge a protocol fee
-                    if zero_for_one {
-                        liquidity_net = -liquidity_net;
-                    }
-
-                    current_state.liquidity = if liquidity_net < 0 {
-                        if current_state.liquidity < (-liquidity_net as u128) {
-                            return Err(SwapSimulationError::LiquidityUnderflow);
-                        } else {
-                            current_state.liquidity - (-liquidity_net as u128)
-                        }
-                    } else {
-                        current_state.liquidity + (liquidity_net as u128)
-                    };
-                }
-                // Increment the current tick
-                current_state.tick = if zero_for_one {
-                    step.tick_next.wrapping_sub(1)
-                } else {
-                    step.tick_next
-                }
-                // If the current_state sqrt price is not equal to the step sqrt price, then we are not on the same tick.
-                // Update the current_state.tick to the tick at the current_state.sqrt_price_x_96
-            } else if current_state.sqrt_price_x_96 != step.sqrt_price_start_x_96 {
-                current_state.tick = uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(
-                    current_state.sqrt_price_x_96,
-                )?;
-            }
-        }
+        let current_state = self.swap_inner(token_in, amount_in)?;
 
         let amount_out = (-current_state.amount_calculated).into_raw();
 
@@ -305,154 +286,171 @@ impl AutomatedMarketMaker for UniswapV3Pool {
             return Ok(U256::ZERO);
         }
 
-        let zero_for_one = token_in == self.token_a;
+        let current_state = self.swap_inner(token_in, amount_in)?;
 
-        // Set sqrt_price_limit_x_96 to the max or min sqrt price in the pool depending on zero_for_one
-        let sqrt_price_limit_x_96 = if zero_for_one {
-            MIN_SQRT_RATIO + U256_1
-        } else {
-            MAX_SQRT_RATIO - U256_1
-        };
+        // Update the pool state
+        self.liquidity = current_state.liquidity;
+        self.sqrt_price = current_state.sqrt_price_x_96;
+        self.tick = current_state.tick;
 
-        // Initialize a mutable state state struct to hold the dynamic simulated state of the pool
-        let mut current_state = CurrentState {
-            // Active price on the pool
-            sqrt_price_x_96: self.sqrt_price,
-            // Amount of token_out that has been calculated
-            amount_calculated: I256::ZERO,
-            // Amount of token_in that has not been swapped
-            amount_specified_remaining: I256::from_raw(amount_in),
-            // Current i24 tick of the pool
-            tick: self.tick,
-            // Current available liquidity in the tick range
-            liquidity: self.liquidity,
-        };
+        let amount_out = (-current_state.amount_calculated).into_raw();
 
-        while current_state.amount_specified_remaining != I256::ZERO
-            && current_state.sqrt_price_x_96 != sqrt_price_limit_x_96
-        {
-            // Initialize a new step struct to hold the dynamic state of the pool at each step
-            let mut step = StepComputations {
-                // Set the sqrt_price_start_x_96 to the current sqrt_price_x_96
-                sqrt_price_start_x_96: current_state.sqrt_price_x_96,
-                ..Default::default()
-            };
+        tracing::trace!(?amount_out);
 
-            // Get the next tick from the current tick
-            (step.tick_next, step.initialized) =
-                uniswap_v3_math::tick_bitmap::next_initialized_tick_within_one_word(
-                    &self.tick_bitmap,
-                    current_state.tick,
-                    self.tick_spacing,
-                    zero_for_one,
-                )?;
+        Ok(amount_out)
+    }
 
-            // ensure that we do not overshoot the min/max tick, as the tick bitmap is not aware of these bounds
-            // Note: this could be removed as we are clamping in the batch contract
-            step.tick_next = step.tick_next.clamp(MIN_TICK, MAX_TICK);
+    fn get_token_out(&self, token_in: Address) -> Address {
+        if self.is_token0(token_in) {
+            self.token1()
+        } else {
+            self.token0()
+        }
+    }
 
-            // Get the next sqrt price from the input amount
-            step.sqrt_price_next_x96 =
-                uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(step.tick_next)?;
+    fn swap_gas_estimate(&self, token_in: Address, amount_in: U256) -> u64 {
+        // Swaps failing in the simulation are estimated without tick crossings
+        let ticks_crossed = self.ticks_crossed(token_in, amount_in).unwrap_or_default();
+        SWAP_BASE_GAS_ESTIMATE + TICK_CROSSED_GAS_ESTIMATE * ticks_crossed as u64
+    }
 
-            // Target spot price
-            let swap_target_sqrt_ratio = if zero_for_one {
-                if step.sqrt_price_next_x96 < sqrt_price_limit_x_96 {
-                    sqrt_price_limit_x_96
-                } else {
-                    step.sqrt_price_next_x96
-                }
-            } else if step.sqrt_price_next_x96 > sqrt_price_limit_x_96 {
-                sqrt_price_limit_x_96
-            } else {
-                step.sqrt_price_next_x96
-            };
+    fn simulate_swap_with_gas_estimate(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<(U256, u64), SwapSimulationError> {
+        if amount_in.is_zero() {
+            return Ok((U256::ZERO, SWAP_BASE_GAS_ESTIMATE));
+        }
 
-            // Compute swap step and update the current state
-            (
-                current_state.sqrt_price_x_96,
-                step.amount_in,
-                step.amount_out,
-                step.fee_amount,
-            ) = uniswap_v3_math::swap_math::compute_swap_step(
-                current_state.sqrt_price_x_96,
-                swap_target_sqrt_ratio,
-                current_state.liquidity,
-                current_state.amount_specified_remaining,
-                self.fee,
-            )?;
+        let current_state = self.swap_inner(token_in, amount_in)?;
+        let ticks_crossed = self.ticks_crossed_to(token_in, current_state.tick);
 
-            // Decrement the amount remaining to be swapped and amount received from the step
-            current_state.amount_specified_remaining = current_state
-                .amount_specified_remaining
-                .overflowing_sub(I256::from_raw(
-                    step.amount_in.overflowing_add(step.fee_amount).0,
-                ))
-                .0;
-
-            current_state.amount_calculated -= I256::from_raw(step.amount_out);
-
-            // If the price moved all the way to the next price, recompute the liquidity change for the next iteration
-            if current_state.sqrt_price_x_96 == step.sqrt_price_next_x96 {
-                if step.initialized {
-                    let mut liquidity_net = if let Some(info) = self.ticks.get(&step.tick_next) {
-                        info.liquidity_net
-                    } else {
-                        0
-                    };
+        Ok((
+            (-current_state.amount_calculated).into_raw(),
+            SWAP_BASE_GAS_ESTIMATE + TICK_CROSSED_GAS_ESTIMATE * ticks_crossed as u64,
+        ))
+    }
 
-                    // we are on a tick boundary, and the next tick is initialized, so we must charge a protocol fee
-                    if zero_for_one {
-                        liquidity_net = -liquidity_net;
-                    }
+    /// Returns the calldata of an exact input `swap` on the pool without a price limit.
+    ///
+    /// The pool does not check the amount out, so if `amount_out_min` is set the swap is simulated against it, which
+    /// requires the tick data. The input is paid in the swap callback of the caller.
+    fn encode_swap(&self, params: SwapParams) -> Result<Bytes, ExecutionError> {
+        if params.token_in != self.token_a && params.token_in != self.token_b {
+            return Err(ExecutionError::TokenNotInPool(
+                params.token_in,
+                self.address,
+            ));
+        }
 
-                    current_state.liquidity = if liquidity_net < 0 {
-                        if current_state.liquidity < (-liquidity_net as u128) {
-                            return Err(SwapSimulationError::LiquidityUnderflow);
-                        } else {
-                            current_state.liquidity - (-liquidity_net as u128)
-                        }
-                    } else {
-                        current_state.liquidity + (liquidity_net as u128)
-                    };
-                }
-                // Increment the current tick
-                current_state.tick = if zero_for_one {
-                    step.tick_next.wrapping_sub(1)
-                } else {
-                    step.tick_next
-                }
-                // If the current_state sqrt price is not equal to the step sqrt price, then we are not on the same tick.
-                // Update the current_state.tick to the tick at the current_state.sqrt_price_x_96
-            } else if current_state.sqrt_price_x_96 != step.sqrt_price_start_x_96 {
-                current_state.tick = uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(
-                    current_state.sqrt_price_x_96,
-                )?;
+        if !params.amount_out_min.is_zero() {
+            let amount_out = self
+                .simulate_swap(params.token_in, params.amount_in)
+                .map_err(|err| {
+                    ExecutionError::SwapSimulationFailed(self.address, err.to_string())
+                })?;
+            if amount_out < params.amount_out_min {
+                return Err(ExecutionError::InsufficientOutputAmount {
+                    amount_out,
+                    amount_out_min: params.amount_out_min,
+                });
             }
         }
 
-        // Update the pool state
-        self.liquidity = current_state.liquidity;
-        self.sqrt_price = current_state.sqrt_price_x_96;
-        self.tick = current_state.tick;
+        let zero_for_one = self.is_token0(params.token_in);
+        let sqrt_price_limit_x_96 = if zero_for_one {
+            MIN_SQRT_RATIO + U256_1
+        } else {
+            MAX_SQRT_RATIO - U256_1
+        };
 
-        let amount_out = (-current_state.amount_calculated).into_raw();
+        Ok(IUniswapV3Pool::swapCall {
+            recipient: params.recipient,
+            zeroForOne: zero_for_one,
+            amountSpecified: I256::from_raw(params.amount_in),
+            sqrtPriceLimitX96: sqrt_price_limit_x_96,
+            data: params.callback_data,
+        }
+        .abi_encode()
+        .into())
+    }
+}
 
-        tracing::trace!(?amount_out);
+impl UniswapV3Pool {
+    /// Returns a builder for the pool.
+    pub fn builder() -> builder::UniswapV3PoolBuilder {
+        builder::UniswapV3PoolBuilder::default()
+    }
 
-        Ok(amount_out)
+    /// Returns token 0 of the pool, the lower of the two token addresses.
+    pub fn token0(&self) -> Address {
+        self.token_a.min(self.token_b)
     }
 
-    fn get_token_out(&self, token_in: Address) -> Address {
-        if self.token_a == token_in {
-            self.token_b
+    /// Returns token 1 of the pool, the higher of the two token addresses.
+    pub fn token1(&self) -> Address {
+        self.token_a.max(self.token_b)
+    }
+
+    /// Returns the decimals of [`UniswapV3Pool::token0`].
+    pub fn token0_decimals(&self) -> u8 {
+        if self.token_a <= self.token_b {
+            self.token_a_decimals
         } else {
-            self.token_a
+            self.token_b_decimals
         }
     }
-}
 
-impl UniswapV3Pool {
+    /// Returns the decimals of [`UniswapV3Pool::token1`].
+    pub fn token1_decimals(&self) -> u8 {
+        if self.token_a <= self.token_b {
+            self.token_b_decimals
+        } else {
+            self.token_a_decimals
+        }
+    }
+
+    /// Returns whether `token` is token 0 of the pool, i.e. whether swapping it in is a `zero_for_one` swap.
+    pub fn is_token0(&self, token: Address) -> bool {
+        token == self.token0()
+    }
+
+    /// Returns token 0 of the pool, see [`UniswapV3Pool::token0`].
+    #[deprecated(
+        note = "the `token_a` field is not guaranteed to be token 0, use `token0` instead"
+    )]
+    pub fn token_a(&self) -> Address {
+        self.token0()
+    }
+
+    /// Returns token 1 of the pool, see [`UniswapV3Pool::token1`].
+    #[deprecated(
+        note = "the `token_b` field is not guaranteed to be token 1, use `token1` instead"
+    )]
+    pub fn token_b(&self) -> Address {
+        self.token1()
+    }
+
+    /// Returns the decimals of token 0 of the pool, see [`UniswapV3Pool::token0_decimals`].
+    #[deprecated(
+        note = "the `token_a_decimals` field is not guaranteed to be the decimals of token 0, use `token0_decimals` instead"
+    )]
+    pub fn token_a_decimals(&self) -> u8 {
+        self.token0_decimals()
+    }
+
+    /// Returns the decimals of token 1 of the pool, see [`UniswapV3Pool::token1_decimals`].
+    #[deprecated(
+        note = "the `token_b_decimals` field is not guaranteed to be the decimals of token 1, use `token1_decimals` instead"
+    )]
+    pub fn token_b_decimals(&self) -> u8 {
+        self.token1_decimals()
+    }
+
+    /// Creates a pool from its state, returning an error if the fee, tick spacing, tick or sqrt price are out of bounds.
+    ///
+    /// A zero sqrt price is accepted for pools that are not initialized yet.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         address: Address,
@@ -466,9 +464,19 @@ impl UniswapV3Pool {
         tick: i32,
         tick_spacing: i32,
         tick_bitmap: HashMap<i16, U256>,
-        ticks: HashMap<i32, Info>,
-    ) -> UniswapV3Pool {
-        UniswapV3Pool {
+        ticks: BTreeMap<i32, Info>,
+    ) -> Result<UniswapV3Pool, PoolBuilderError> {
+        math::check_fee_and_tick_spacing(fee, tick_spacing).map_err(|err| match err {
+            SwapSimulationError::InvalidFee(fee) => PoolBuilderError::InvalidFee(fee),
+            _ => PoolBuilderError::InvalidTickSpacing(tick_spacing),
+        })?;
+        math::check_tick(tick).map_err(|_| PoolBuilderError::InvalidTick(tick))?;
+        if !sqrt_price.is_zero() {
+            math::check_sqrt_price(sqrt_price)
+                .map_err(|_| PoolBuilderError::InvalidSqrtPrice(sqrt_price))?;
+        }
+
+        Ok(UniswapV3Pool {
             address,
             token_a,
             token_a_decimals,
@@ -481,9 +489,13 @@ impl UniswapV3Pool {
             tick_spacing,
             tick_bitmap,
             ticks,
-        }
+            tick_window: None,
+            stats: PoolStats::default(),
+            factory: None,
+        })
     }
 
+    #[cfg(feature = "provider")]
     /// Creates a new instance of the pool from the pair address.
     ///
     /// This function will populate all pool data.
@@ -509,7 +521,10 @@ impl UniswapV3Pool {
             tick_spacing: 0,
             fee: 0,
             tick_bitmap: HashMap::new(),
-            ticks: HashMap::new(),
+            ticks: BTreeMap::new(),
+            tick_window: None,
+            stats: PoolStats::default(),
+            factory: None,
         };
 
         // We need to get tick spacing before populating tick data because tick spacing can not be uninitialized when syncing burn and mint logs
@@ -529,6 +544,41 @@ impl UniswapV3Pool {
         Ok(pool)
     }
 
+    #[cfg(feature = "provider")]
+    /// Creates a new instance of the pool of `token_a` and `token_b` in the `fee` tier from the `getPool` of
+    /// `factory`.
+    ///
+    /// This function will populate all pool data, with tick data from the creation block of the factory.
+    pub async fn new_from_tokens<T, N, P>(
+        factory: &UniswapV3Factory,
+        token_a: Address,
+        token_b: Address,
+        fee: u32,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let IUniswapV3Factory::getPoolReturn { pool } =
+            IUniswapV3Factory::new(factory.address, provider.clone())
+                .getPool(token_a, token_b, fee)
+                .call()
+                .await?;
+
+        if pool.is_zero() {
+            return Err(AMMError::PairDoesNotExistInDexes(token_a, token_b));
+        }
+
+        let mut pool =
+            UniswapV3Pool::new_from_address(pool, factory.creation_block, provider).await?;
+        pool.factory = Some(factory.address);
+
+        Ok(pool)
+    }
+
+    #[cfg(feature = "provider")]
     /// Creates a new instance of the pool from a log.
     ///
     /// This function will populate all pool data.
@@ -554,6 +604,7 @@ impl UniswapV3Pool {
             Err(EventLogError::InvalidEventSignature)?
         }
     }
+    #[cfg(feature = "provider")]
     /// Creates a new instance of the pool from a log.
     ///
     /// This function will not populate all pool data.
@@ -573,22 +624,27 @@ impl UniswapV3Pool {
                 fee: pool_created_event.fee,
                 liquidity: 0,
                 sqrt_price: U256::ZERO,
-                tick_spacing: 0,
+                tick_spacing: pool_created_event.tickSpacing,
                 tick: 0,
                 tick_bitmap: HashMap::new(),
-                ticks: HashMap::new(),
+                ticks: BTreeMap::new(),
+                tick_window: None,
+                stats: PoolStats::default(),
+                factory: None,
             })
         } else {
             Err(EventLogError::InvalidEventSignature)
         }
     }
 
-    /// Populates the `tick_bitmap` and `ticks` fields of the pool to the current block.
+    #[cfg(feature = "provider")]
+    /// Populates the `tick_bitmap` and `ticks` fields of the pool to the current block, with the log step of the
+    /// chain of the provider, see [`SyncConfig::for_chain`].
     ///
     /// Returns the last synced block number.
     pub async fn populate_tick_data<T, N, P>(
         &mut self,
-        mut from_block: u64,
+        from_block: u64,
         provider: Arc<P>,
     ) -> Result<u64, AMMError>
     where
@@ -596,43 +652,86 @@ impl UniswapV3Pool {
         N: Network,
         P: Provider<T, N>,
     {
-        let current_block = provider
-            .get_block_number()
+        let chain_id = provider
+            .get_chain_id()
             .await
             .map_err(AMMError::TransportError)?;
 
-        let mut futures = FuturesOrdered::new();
+        self.populate_tick_data_with_config(from_block, &SyncConfig::for_chain(chain_id), provider)
+            .await
+    }
 
-        let mut ordered_logs: BTreeMap<u64, Vec<Log>> = BTreeMap::new();
+    #[cfg(feature = "provider")]
+    /// Populates the `tick_bitmap` and `ticks` fields of the pool to `config.finality_depth` blocks behind the current block,
+    /// fetching logs in ranges of `config.step` blocks.
+    ///
+    /// Returns the last synced block number.
+    pub async fn populate_tick_data_with_config<T, N, P>(
+        &mut self,
+        from_block: u64,
+        config: &SyncConfig,
+        provider: Arc<P>,
+    ) -> Result<u64, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let current_block = provider
+            .get_block_number()
+            .await
+            .map_err(AMMError::TransportError)?
+            .saturating_sub(config.finality_depth);
 
-        let pool_address: Address = self.address;
+        self.populate_tick_data_to_block(from_block, current_block, config, provider)
+            .await?;
 
-        while from_block <= current_block {
-            let middleware = provider.clone();
+        Ok(current_block)
+    }
 
-            let mut target_block = from_block + POPULATE_TICK_DATA_STEP - 1;
-            if target_block > current_block {
-                target_block = current_block;
-            }
+    #[cfg(feature = "provider")]
+    /// Populates the `tick_bitmap` and `ticks` fields of the pool from the Mint and Burn logs between `from_block`
+    /// and `to_block` inclusive, fetching logs in ranges of `config.step` blocks.
+    pub async fn populate_tick_data_to_block<T, N, P>(
+        &mut self,
+        mut from_block: u64,
+        to_block: u64,
+        config: &SyncConfig,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut ordered_logs: BTreeMap<u64, Vec<Log>> = BTreeMap::new();
 
-            futures.push_back(async move {
-                middleware
-                    .get_logs(
-                        &Filter::new()
-                            .event_signature(vec![
-                                IUniswapV3Pool::Burn::SIGNATURE_HASH,
-                                IUniswapV3Pool::Mint::SIGNATURE_HASH,
-                            ])
-                            .address(pool_address)
-                            .from_block(from_block)
-                            .to_block(target_block),
-                    )
-                    .await
-            });
+        let pool_address: Address = self.address;
 
-            from_block += POPULATE_TICK_DATA_STEP;
+        let mut block_ranges = vec![];
+        while from_block <= to_block {
+            let target_block = (from_block + config.step - 1).min(to_block);
+            block_ranges.push((from_block, target_block));
+            from_block += config.step;
         }
 
+        let retry = config.retry;
+        let mut futures =
+            futures::stream::iter(block_ranges.into_iter().map(|(from_block, target_block)| {
+                let provider = provider.clone();
+                let filter = Filter::new()
+                    .event_signature(vec![
+                        IUniswapV3Pool::Burn::SIGNATURE_HASH,
+                        IUniswapV3Pool::Mint::SIGNATURE_HASH,
+                    ])
+                    .address(pool_address)
+                    .from_block(from_block)
+                    .to_block(target_block);
+
+                async move { retry.retry(|| provider.get_logs(&filter)).await }
+            }))
+            .buffered(config.max_concurrency);
+
         // TODO: this could be more dry since we use this in another place
         while let Some(result) = futures.next().await {
             let logs = result.map_err(AMMError::TransportError)?;
@@ -650,13 +749,46 @@ impl UniswapV3Pool {
             }
         }
 
-        for (_, log_group) in ordered_logs {
+        for (block_number, log_group) in ordered_logs {
             for log in log_group {
-                self.sync_from_log(log)?;
+                self.sync_from_log(log)
+                    .context(ErrorContext::sync_from_log(
+                        self.address,
+                        Some(block_number),
+                    ))?;
             }
         }
 
-        Ok(current_block)
+        Ok(())
+    }
+
+    #[cfg(feature = "provider")]
+    /// Reconstructs the full state of the pool, including `tick_bitmap` and `ticks`, as of `block_number`.
+    ///
+    /// Existing tick data is discarded and rebuilt from the Mint and Burn logs since `creation_block`.
+    pub async fn populate_data_at_block<T, N, P>(
+        &mut self,
+        creation_block: u64,
+        block_number: u64,
+        config: &SyncConfig,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        if self.tick_spacing == 0 {
+            self.tick_spacing = self.get_tick_spacing(provider.clone()).await?;
+        }
+
+        self.tick_bitmap.clear();
+        self.ticks.clear();
+        self.tick_window = None;
+
+        self.populate_tick_data_to_block(creation_block, block_number, config, provider.clone())
+            .await?;
+        self.populate_data(Some(block_number), provider).await
     }
 
     /// Returns the swap fee of the pool.
@@ -669,6 +801,7 @@ impl UniswapV3Pool {
         !(self.token_a.is_zero() || self.token_b.is_zero())
     }
 
+    #[cfg(feature = "provider")]
     /// Returns the word position of a tick in the `tick_bitmap`.
     pub async fn get_tick_word<T, N, P>(
         &self,
@@ -687,6 +820,7 @@ impl UniswapV3Pool {
         Ok(bm)
     }
 
+    #[cfg(feature = "provider")]
     /// Returns the next word in the `tick_bitmap` after a given word position.
     pub async fn get_next_word<T, N, P>(
         &self,
@@ -704,6 +838,7 @@ impl UniswapV3Pool {
         Ok(bm)
     }
 
+    #[cfg(feature = "provider")]
     /// Returns the tick spacing of the pool.
     pub async fn get_tick_spacing<T, N, P>(&self, provider: Arc<P>) -> Result<i32, AMMError>
     where
@@ -716,6 +851,7 @@ impl UniswapV3Pool {
         Ok(ts)
     }
 
+    #[cfg(feature = "provider")]
     /// Fetches the current tick of the pool via static call.
     pub async fn get_tick<T, N, P>(&self, provider: Arc<P>) -> Result<i32, AMMError>
     where
@@ -726,6 +862,7 @@ impl UniswapV3Pool {
         Ok(self.get_slot_0(provider).await?.1)
     }
 
+    #[cfg(feature = "provider")]
     /// Fetches the tick info of a given tick via static call.
     pub async fn get_tick_info<T, N, P>(
         &self,
@@ -753,6 +890,7 @@ impl UniswapV3Pool {
         ))
     }
 
+    #[cfg(feature = "provider")]
     /// Fetches `liquidity_net` at a given tick via static call.
     pub async fn get_liquidity_net<T, N, P>(
         &self,
@@ -768,6 +906,7 @@ impl UniswapV3Pool {
         Ok(tick_info.1)
     }
 
+    #[cfg(feature = "provider")]
     /// Fetches whether a specified tick is initialized via static call.
     pub async fn get_initialized<T, N, P>(
         &self,
@@ -783,6 +922,7 @@ impl UniswapV3Pool {
         Ok(tick_info.7)
     }
 
+    #[cfg(feature = "provider")]
     /// Fetches the current slot 0 of the pool via static call.
     pub async fn get_slot_0<T, N, P>(
         &self,
@@ -797,6 +937,7 @@ impl UniswapV3Pool {
         Ok(v3_pool.slot0().call().await?.into())
     }
 
+    #[cfg(feature = "provider")]
     /// Fetches the current liquidity of the pool via static call.
     pub async fn get_liquidity<T, N, P>(&self, provider: Arc<P>) -> Result<u128, AMMError>
     where
@@ -809,6 +950,7 @@ impl UniswapV3Pool {
         Ok(liquidity)
     }
 
+    #[cfg(feature = "provider")]
     /// Fetches the current sqrt price of the pool via static call.
     pub async fn get_sqrt_price<T, N, P>(&self, provider: Arc<P>) -> Result<U256, AMMError>
     where
@@ -857,7 +999,7 @@ impl UniswapV3Pool {
 
         if liquidity_delta != 0 {
             //if the tick is between the tick lower and tick upper, update the liquidity between the ticks
-            if self.tick > tick_lower && self.tick < tick_upper {
+            if self.tick >= tick_lower && self.tick < tick_upper {
                 self.liquidity = if liquidity_delta < 0 {
                     self.liquidity - ((-liquidity_delta) as u128)
                 } else {
@@ -871,10 +1013,15 @@ impl UniswapV3Pool {
         let mut flipped_lower = false;
         let mut flipped_upper = false;
 
+        // Ticks outside of the tick window are not tracked
         if liquidity_delta != 0 {
-            flipped_lower = self.update_tick(tick_lower, liquidity_delta, false);
-            flipped_upper = self.update_tick(tick_upper, liquidity_delta, true);
-            if flipped_lower {
+            if self.tick_is_loaded(tick_lower) {
+                flipped_lower = self.update_tick(tick_lower, liquidity_delta, false);
+            }
+            if self.tick_is_loaded(tick_upper) {
+                flipped_upper = self.update_tick(tick_upper, liquidity_delta, true);
+            }
+            if flipped_lower {
                 self.flip_tick(tick_lower, self.tick_spacing);
             }
             if flipped_upper {
@@ -944,17 +1091,33 @@ impl UniswapV3Pool {
 
     /// Updates the pool state from a swap event log.
     pub fn sync_from_swap_log(&mut self, log: Log) -> Result<(), alloy::sol_types::Error> {
+        let block_number = log.block_number;
         let swap_event = IUniswapV3Pool::Swap::decode_log(log.as_ref(), true)?;
 
         self.sqrt_price = swap_event.sqrtPriceX96;
         self.liquidity = swap_event.liquidity;
         self.tick = swap_event.tick;
 
+        let (amount_0_in, amount_0_out) = split_amount(swap_event.amount0);
+        let (amount_1_in, amount_1_out) = split_amount(swap_event.amount1);
+        self.stats.record_swap(SwapRecord {
+            block_number: block_number
+                .or(self.stats.last_swap_block)
+                .unwrap_or_default(),
+            amount_0_in,
+            amount_1_in,
+            amount_0_out,
+            amount_1_out,
+            fee_0: fee_amount(amount_0_in, self.fee, FEE_DENOMINATOR),
+            fee_1: fee_amount(amount_1_in, self.fee, FEE_DENOMINATOR),
+        });
+
         tracing::debug!(?swap_event, address = ?self.address, sqrt_price = ?self.sqrt_price, liquidity = ?self.liquidity, tick = ?self.tick, "UniswapV3 swap event");
 
         Ok(())
     }
 
+    #[cfg(feature = "provider")]
     pub async fn get_token_decimals<T, N, P>(
         &mut self,
         provider: Arc<P>,
@@ -964,23 +1127,23 @@ impl UniswapV3Pool {
         N: Network,
         P: Provider<T, N>,
     {
-        let IErc20::decimalsReturn {
-            _0: token_a_decimals,
-        } = IErc20::new(self.token_a, provider.clone())
-            .decimals()
-            .call()
-            .await?;
+        let tokens =
+            get_token_metadata_batch_request(&[self.token_a, self.token_b], None, provider).await?;
+        let decimals = |address: Address| {
+            tokens
+                .iter()
+                .find(|token| token.address == address)
+                .map(|token| token.decimals)
+                .ok_or(AMMError::BatchRequestError(address))
+        };
 
-        let IErc20::decimalsReturn {
-            _0: token_b_decimals,
-        } = IErc20::new(self.token_b, provider)
-            .decimals()
-            .call()
-            .await?;
+        let token_a_decimals = decimals(self.token_a)?;
+        let token_b_decimals = decimals(self.token_b)?;
 
         Ok((token_a_decimals, token_b_decimals))
     }
 
+    #[cfg(feature = "provider")]
     pub async fn get_fee<T, N, P>(&mut self, provider: Arc<P>) -> Result<u32, AMMError>
     where
         T: Transport + Clone,
@@ -995,6 +1158,7 @@ impl UniswapV3Pool {
         Ok(fee)
     }
 
+    #[cfg(feature = "provider")]
     pub async fn get_token_0<T, N, P>(&self, provider: Arc<P>) -> Result<Address, AMMError>
     where
         T: Transport + Clone,
@@ -1011,6 +1175,7 @@ impl UniswapV3Pool {
         Ok(token_0)
     }
 
+    #[cfg(feature = "provider")]
     pub async fn get_token_1<T, N, P>(&self, provider: Arc<P>) -> Result<Address, AMMError>
     where
         T: Transport + Clone,
@@ -1032,6 +1197,8 @@ impl UniswapV3Pool {
        ==> x = L^2/price
        ==> y = L^2*price
     */
+    /// Returns the virtual reserves of the active liquidity, or [`ArithmeticError::ReserveOverflow`] if a reserve does
+    /// not fit in a u128, e.g. for a very large liquidity at an extreme price.
     pub fn calculate_virtual_reserves(&self) -> Result<(u128, u128), ArithmeticError> {
         let tick = uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(self.sqrt_price)?;
         let price = 1.0001_f64.powi(tick);
@@ -1054,10 +1221,10 @@ impl UniswapV3Pool {
         Ok((
             reserve_0
                 .to_u128()
-                .ok_or(ArithmeticError::U128ConversionError)?,
+                .ok_or(ArithmeticError::ReserveOverflow)?,
             reserve_1
                 .to_u128()
-                .ok_or(ArithmeticError::U128ConversionError)?,
+                .ok_or(ArithmeticError::ReserveOverflow)?,
         ))
     }
 
@@ -1073,6 +1240,739 @@ impl UniswapV3Pool {
         uniswap_v3_math::tick_bitmap::position(compressed)
     }
 
+    /// Returns the word position of `tick` in the `tick_bitmap`.
+    pub fn tick_word_position(&self, tick: i32) -> i16 {
+        self.calculate_word_pos_bit_pos(tick.div_euclid(self.tick_spacing))
+            .0
+    }
+
+    /// Returns whether the tick data for the word containing `tick` is loaded.
+    pub fn tick_is_loaded(&self, tick: i32) -> bool {
+        match self.tick_window {
+            Some((lower, upper)) => (lower..=upper).contains(&self.tick_word_position(tick)),
+            None => true,
+        }
+    }
+
+    /// Drops the tick data more than `radius_words` words of the `tick_bitmap` away from the current tick.
+    ///
+    /// Simulations that reach the pruned ticks return [`SwapSimulationError::TickWordNotLoaded`],
+    /// the tick data can be reloaded with [`UniswapV3Pool::load_tick_words`].
+    pub fn prune_ticks(&mut self, radius_words: i16) {
+        // Pools that have not been populated yet have no tick spacing to compute word positions with
+        if self.tick_spacing == 0 {
+            return;
+        }
+
+        let word = self.tick_word_position(self.tick);
+        let (mut lower, mut upper) = (
+            word.saturating_sub(radius_words),
+            word.saturating_add(radius_words),
+        );
+
+        if let Some((window_lower, window_upper)) = self.tick_window {
+            lower = lower.max(window_lower);
+            upper = upper.min(window_upper);
+        }
+
+        self.set_tick_window(lower, upper);
+    }
+
+    fn set_tick_window(&mut self, lower: i16, upper: i16) {
+        self.tick_window = Some((lower, upper));
+
+        self.tick_bitmap
+            .retain(|word, _| (lower..=upper).contains(word));
+
+        let tick_spacing = self.tick_spacing;
+        self.ticks.retain(|tick, _| {
+            let word = uniswap_v3_math::tick_bitmap::position(tick.div_euclid(tick_spacing)).0;
+            (lower..=upper).contains(&word)
+        });
+    }
+
+    #[cfg(feature = "provider")]
+    /// Fetches the `tick_bitmap` words from `lower` to `upper` (inclusive) and their initialized ticks via batched
+    /// static calls, adding them to the tick window.
+    ///
+    /// If the words are not adjacent to the current tick window, the previously loaded tick data is dropped.
+    pub async fn load_tick_words<T, N, P>(
+        &mut self,
+        lower: i16,
+        upper: i16,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let Some((window_lower, window_upper)) = self.tick_window else {
+            // All tick data is already loaded
+            return Ok(());
+        };
+
+        let word_positions = (lower..=upper)
+            .filter(|word_position| !(window_lower..=window_upper).contains(word_position))
+            .collect::<Vec<i16>>();
+        let (words, ticks) = batch_request::get_tick_words_batch_request(
+            self.address,
+            self.tick_spacing,
+            &word_positions,
+            block_number,
+            provider,
+        )
+        .await?;
+
+        if lower <= window_upper.saturating_add(1) && upper >= window_lower.saturating_sub(1) {
+            self.tick_window = Some((lower.min(window_lower), upper.max(window_upper)));
+        } else {
+            self.set_tick_window(lower, upper);
+        }
+
+        self.tick_bitmap.extend(words);
+        self.ticks.extend(ticks);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "provider")]
+    /// Simulates a swap, fetching the tick data outside of the tick window via static calls as the swap reaches it.
+    ///
+    /// Fetched tick data is cached on the pool. A pool without any tick data starts from an empty tick window,
+    /// so only the words reached by the swap are fetched, [`LAZY_TICK_WORDS`] at a time in the swap direction.
+    /// Pass the block the pool was synced at as `block_number` so that the tick data matches the pool state.
+    pub async fn simulate_swap_lazy<T, N, P>(
+        &mut self,
+        token_in: Address,
+        amount_in: U256,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<U256, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        if self.tick_window.is_none() && self.tick_bitmap.is_empty() {
+            self.tick_window = Some((0, -1));
+        }
+
+        let zero_for_one = token_in == self.token_a;
+        loop {
+            match self.simulate_swap(token_in, amount_in) {
+                Err(SwapSimulationError::TickWordNotLoaded(word)) => {
+                    // Prices move down the tick bitmap for zero for one swaps and up otherwise
+                    let (lower, upper) = if zero_for_one {
+                        (word.saturating_sub(LAZY_TICK_WORDS - 1), word)
+                    } else {
+                        (word, word.saturating_add(LAZY_TICK_WORDS - 1))
+                    };
+
+                    tracing::trace!(lower, upper, address = ?self.address, "fetching tick words");
+                    self.load_tick_words(lower, upper, block_number, provider.clone())
+                        .await?;
+                }
+                result => return result.context(ErrorContext::simulate(self.address)),
+            }
+        }
+    }
+
+    /// Returns the next initialized tick from `tick`, searching at or below `tick` if `lte` is true
+    /// and strictly above `tick` otherwise.
+    ///
+    /// Unlike the tick bitmap, this is not limited to a single word and returns `None`
+    /// if there is no initialized tick in the given direction.
+    pub fn next_initialized_tick(&self, tick: i32, lte: bool) -> Option<i32> {
+        if lte {
+            self.ticks
+                .range(..=tick)
+                .rev()
+                .find(|(_, info)| info.initialized)
+                .map(|(tick, _)| *tick)
+        } else {
+            self.ticks
+                .range(tick.saturating_add(1)..)
+                .find(|(_, info)| info.initialized)
+                .map(|(tick, _)| *tick)
+        }
+    }
+
+    /// Returns an iterator over the initialized ticks of the `tick_bitmap` from the current tick in `direction`, along
+    /// with their net liquidity, in the order they would be crossed by a swap.
+    ///
+    /// The walk ends at the tick bounds or at the edge of the `tick_window`.
+    pub fn initialized_ticks(&self, direction: TickDirection) -> InitializedTicks<'_, Self> {
+        math::initialized_ticks(self, self.tick, self.tick_spacing, direction)
+    }
+
+    /// Returns an error if `tick_lower`..`tick_upper` is not a valid position range of the pool, or if adding
+    /// `liquidity_delta` to the range would underflow the liquidity of the pool or of the range ticks.
+    pub fn check_position(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> Result<(), SwapSimulationError> {
+        math::check_fee_and_tick_spacing(self.fee, self.tick_spacing)?;
+        math::check_tick(tick_lower)?;
+        math::check_tick(tick_upper)?;
+        if tick_lower >= tick_upper
+            || tick_lower % self.tick_spacing != 0
+            || tick_upper % self.tick_spacing != 0
+        {
+            return Err(SwapSimulationError::InvalidTickRange(
+                tick_lower, tick_upper,
+            ));
+        }
+
+        if liquidity_delta < 0 {
+            let liquidity = liquidity_delta.unsigned_abs();
+            let in_range = self.tick >= tick_lower && self.tick < tick_upper;
+            if in_range && self.liquidity < liquidity {
+                return Err(SwapSimulationError::LiquidityUnderflow);
+            }
+
+            // Only the loaded ticks are updated
+            for tick in [tick_lower, tick_upper] {
+                let liquidity_gross = self.ticks.get(&tick).map_or(0, |info| info.liquidity_gross);
+                if self.tick_is_loaded(tick) && liquidity_gross < liquidity {
+                    return Err(SwapSimulationError::LiquidityUnderflow);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the amounts of token 0 and token 1 backing `liquidity` in the `tick_lower`..`tick_upper` range
+    /// at the current pool price.
+    pub fn amounts_for_liquidity(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    ) -> Result<(U256, U256), ArithmeticError> {
+        liquidity_amounts::get_amounts_for_liquidity(
+            self.sqrt_price,
+            uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(tick_lower)?,
+            uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(tick_upper)?,
+            liquidity,
+        )
+    }
+
+    /// Returns the maximum liquidity that can be minted in the `tick_lower`..`tick_upper` range
+    /// with `amount_0` of token 0 and `amount_1` of token 1 at the current pool price.
+    pub fn liquidity_for_amounts(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        amount_0: U256,
+        amount_1: U256,
+    ) -> Result<u128, ArithmeticError> {
+        liquidity_amounts::get_liquidity_for_amounts(
+            self.sqrt_price,
+            uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(tick_lower)?,
+            uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(tick_upper)?,
+            amount_0,
+            amount_1,
+        )
+    }
+
+    /// Returns the active liquidity and token amounts for each initialized tick range within `range_ticks` of the current tick.
+    ///
+    /// Buckets are ordered by tick and token amounts are computed at the current pool price,
+    /// so ranges below the current tick hold token 1 and ranges above hold token 0.
+    pub fn liquidity_distribution(
+        &self,
+        range_ticks: i32,
+    ) -> Result<Vec<LiquidityBucket>, ArithmeticError> {
+        let lower_bound = self.tick.saturating_sub(range_ticks).max(MIN_TICK);
+        let upper_bound = self.tick.saturating_add(range_ticks).min(MAX_TICK);
+
+        let boundaries = if lower_bound < upper_bound {
+            self.ticks
+                .range(lower_bound + 1..upper_bound)
+                .filter(|(_, info)| info.initialized)
+                .map(|(tick, info)| (*tick, info.liquidity_net))
+                .collect::<Vec<(i32, i128)>>()
+        } else {
+            vec![]
+        };
+
+        // Index of the first initialized tick above the current tick
+        let split = boundaries.partition_point(|(tick, _)| *tick <= self.tick);
+        let (below, above) = boundaries.split_at(split);
+
+        let mut buckets = vec![];
+
+        // Walk down from the current tick, removing liquidity as each initialized tick is crossed
+        let mut liquidity = self.liquidity as i128;
+        let mut tick_upper = above.first().map_or(upper_bound, |(tick, _)| *tick);
+        for (tick, liquidity_net) in below.iter().rev() {
+            buckets.push(self.liquidity_bucket(*tick, tick_upper, liquidity)?);
+            liquidity = liquidity
+                .checked_sub(*liquidity_net)
+                .ok_or(ArithmeticError::LiquidityUnderflow)?;
+            tick_upper = *tick;
+        }
+        buckets.push(self.liquidity_bucket(lower_bound, tick_upper, liquidity)?);
+        buckets.reverse();
+
+        // Walk up from the current tick, adding liquidity as each initialized tick is crossed
+        let mut liquidity = self.liquidity as i128;
+        for (i, (tick, liquidity_net)) in above.iter().enumerate() {
+            liquidity = liquidity
+                .checked_add(*liquidity_net)
+                .ok_or(ArithmeticError::LiquidityUnderflow)?;
+            let tick_upper = above.get(i + 1).map_or(upper_bound, |(tick, _)| *tick);
+            buckets.push(self.liquidity_bucket(*tick, tick_upper, liquidity)?);
+        }
+
+        Ok(buckets)
+    }
+
+    fn liquidity_bucket(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: i128,
+    ) -> Result<LiquidityBucket, ArithmeticError> {
+        let liquidity: u128 = liquidity
+            .try_into()
+            .map_err(|_| ArithmeticError::LiquidityUnderflow)?;
+
+        let (amount_0, amount_1) = if tick_lower < tick_upper {
+            self.amounts_for_liquidity(tick_lower, tick_upper, liquidity)?
+        } else {
+            (U256::ZERO, U256::ZERO)
+        };
+
+        Ok(LiquidityBucket {
+            tick_lower,
+            tick_upper,
+            liquidity,
+            amount_0,
+            amount_1,
+        })
+    }
+
+    /// Returns the amounts of token 0 and token 1 held by the liquidity between the Q64.96 sqrt prices
+    /// `lower_sqrt_price` and `upper_sqrt_price`, at the current pool price.
+    ///
+    /// Liquidity above the current price holds token 0 and liquidity below holds token 1, so the amounts are the
+    /// depth of the pool until the price reaches either bound. Bounds are clamped to the range of the tick math.
+    pub fn liquidity_between_prices(
+        &self,
+        lower_sqrt_price: U256,
+        upper_sqrt_price: U256,
+    ) -> Result<(U256, U256), SwapSimulationError> {
+        let lower_sqrt_price = lower_sqrt_price.max(MIN_SQRT_RATIO);
+        let upper_sqrt_price = upper_sqrt_price.min(MAX_SQRT_RATIO);
+        if lower_sqrt_price >= upper_sqrt_price {
+            return Ok((U256::ZERO, U256::ZERO));
+        }
+
+        let lower_tick = uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(lower_sqrt_price)?;
+        let upper_tick = uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(
+            upper_sqrt_price.min(MAX_SQRT_RATIO - U256_1),
+        )?;
+        for tick in [lower_tick, upper_tick] {
+            if !self.tick_is_loaded(tick) {
+                return Err(SwapSimulationError::TickWordNotLoaded(
+                    self.tick_word_position(tick),
+                ));
+            }
+        }
+
+        // Move the active liquidity from the current tick to the lower bound
+        let mut liquidity = self.liquidity as i128;
+        if lower_tick < self.tick {
+            for (_, info) in self.ticks.range(lower_tick + 1..=self.tick) {
+                liquidity = liquidity
+                    .checked_sub(info.liquidity_net)
+                    .ok_or(ArithmeticError::LiquidityUnderflow)?;
+            }
+        } else if lower_tick > self.tick {
+            for (_, info) in self.ticks.range(self.tick + 1..=lower_tick) {
+                liquidity = liquidity
+                    .checked_add(info.liquidity_net)
+                    .ok_or(ArithmeticError::LiquidityUnderflow)?;
+            }
+        }
+
+        // Walk up to the upper bound, one initialized tick range at a time
+        let (mut amount_0, mut amount_1) = (U256::ZERO, U256::ZERO);
+        let mut sqrt_price = lower_sqrt_price;
+        if lower_tick < upper_tick {
+            for (tick, info) in self.ticks.range(lower_tick + 1..=upper_tick) {
+                let next_sqrt_price = uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(*tick)?;
+                let (range_amount_0, range_amount_1) =
+                    self.range_amounts(sqrt_price, next_sqrt_price, liquidity)?;
+                amount_0 += range_amount_0;
+                amount_1 += range_amount_1;
+
+                liquidity = liquidity
+                    .checked_add(info.liquidity_net)
+                    .ok_or(ArithmeticError::LiquidityUnderflow)?;
+                sqrt_price = next_sqrt_price;
+            }
+        }
+
+        let (range_amount_0, range_amount_1) =
+            self.range_amounts(sqrt_price, upper_sqrt_price, liquidity)?;
+
+        Ok((amount_0 + range_amount_0, amount_1 + range_amount_1))
+    }
+
+    /// Returns the amounts held by `liquidity` between two sqrt prices at the current pool price.
+    fn range_amounts(
+        &self,
+        sqrt_price_a: U256,
+        sqrt_price_b: U256,
+        liquidity: i128,
+    ) -> Result<(U256, U256), ArithmeticError> {
+        let liquidity: u128 = liquidity
+            .try_into()
+            .map_err(|_| ArithmeticError::LiquidityUnderflow)?;
+        if liquidity == 0 || sqrt_price_a >= sqrt_price_b {
+            return Ok((U256::ZERO, U256::ZERO));
+        }
+
+        liquidity_amounts::get_amounts_for_liquidity(
+            self.sqrt_price,
+            sqrt_price_a,
+            sqrt_price_b,
+            liquidity,
+        )
+    }
+
+    /// Simulates a swap of `amount_in` of `token_in` without mutating the pool.
+    ///
+    /// Returns the full end state of the swap, `simulate_swap` and `simulate_swap_mut` are built on top of this.
+    pub fn swap_inner(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<CurrentState, SwapSimulationError> {
+        math::swap(
+            self,
+            self.initial_swap_state(amount_in),
+            self.tick_spacing,
+            self.fee,
+            self.is_token0(token_in),
+        )
+    }
+
+    fn initial_swap_state(&self, amount_in: U256) -> CurrentState {
+        CurrentState {
+            sqrt_price_x_96: self.sqrt_price, //Active price on the pool
+            amount_calculated: I256::ZERO,    //Amount of token_out that has been calculated
+            amount_specified_remaining: I256::from_raw(amount_in), //Amount of token_in that has not been swapped
+            tick: self.tick,                                       //Current i24 tick of the pool
+            liquidity: self.liquidity, //Current available liquidity in the tick range
+        }
+    }
+
+    /// Simulates a swap of up to `amount_in` of `token_in`, reporting how much of the amount in was consumed.
+    ///
+    /// Unlike [`AutomatedMarketMaker::simulate_swap`], which returns the amount out of whatever could be swapped, a
+    /// swap running out of initialized ticks or reaching the price limit is reported as partially filled.
+    pub fn simulate_swap_with_fill(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<SwapFill, SwapSimulationError> {
+        if amount_in.is_zero() {
+            return Ok(SwapFill {
+                fully_filled: true,
+                ..Default::default()
+            });
+        }
+
+        let current_state = self.swap_inner(token_in, amount_in)?;
+
+        Ok(SwapFill {
+            amount_in_consumed: amount_in - current_state.amount_specified_remaining.into_raw(),
+            amount_out: (-current_state.amount_calculated).into_raw(),
+            fully_filled: current_state.amount_specified_remaining.is_zero(),
+        })
+    }
+
+    /// Simulates a swap of up to `amount_in` of `token_in`, calling `on_tick` with the tick, its net liquidity and the
+    /// amount in swapped so far every time an initialized tick is crossed, see [`math::swap_with_tick_callback`].
+    ///
+    /// Returns the amount in actually swapped, less than `amount_in` if `on_tick` stopped the swap, and the amount out.
+    pub fn simulate_swap_with_tick_callback<F>(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+        on_tick: F,
+    ) -> Result<(U256, U256), SwapSimulationError>
+    where
+        F: FnMut(i32, i128, U256) -> ControlFlow<()>,
+    {
+        if amount_in.is_zero() {
+            return Ok((U256::ZERO, U256::ZERO));
+        }
+
+        let current_state = math::swap_with_tick_callback(
+            self,
+            self.initial_swap_state(amount_in),
+            self.tick_spacing,
+            self.fee,
+            self.is_token0(token_in),
+            on_tick,
+        )?;
+
+        Ok((
+            amount_in - current_state.amount_specified_remaining.into_raw(),
+            (-current_state.amount_calculated).into_raw(),
+        ))
+    }
+
+    /// Simulates just in time liquidity around a swap: minting `liquidity` in the `tick_lower`..`tick_upper` range,
+    /// swapping `amount_in` of `token_in`, then burning the liquidity and collecting its share of the swap fees.
+    ///
+    /// Fees are split between the liquidity in range at every step of the swap as by the pool fee growth, without a
+    /// protocol fee. The mint and burn amounts are rounded down, the pool rounds the mint amounts up by up to a wei.
+    pub fn simulate_jit(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<JitSimulation, SwapSimulationError> {
+        let liquidity_delta =
+            i128::try_from(liquidity).map_err(|_| ArithmeticError::U128ConversionError)?;
+        self.check_position(tick_lower, tick_upper, liquidity_delta)?;
+
+        let (mint_amount_0, mint_amount_1) =
+            self.amounts_for_liquidity(tick_lower, tick_upper, liquidity)?;
+        let mut pool = self.clone();
+        pool.modify_position(tick_lower, tick_upper, liquidity_delta);
+
+        let zero_for_one = pool.is_token0(token_in);
+        let mut fees = U256::ZERO;
+        let mut fee_error = None;
+        let current_state = math::swap_with_step_callback(
+            &pool,
+            pool.initial_swap_state(amount_in),
+            pool.tick_spacing,
+            pool.fee,
+            zero_for_one,
+            |step| {
+                if liquidity > 0 && step.tick >= tick_lower && step.tick < tick_upper {
+                    // Share of the step fees, through the fee growth per unit of liquidity as the pool does
+                    match mul_div(step.fee_amount, Q128, U256::from(step.liquidity))
+                        .and_then(|fee_growth| mul_div(fee_growth, U256::from(liquidity), Q128))
+                    {
+                        Ok(step_fees) => fees += step_fees,
+                        Err(err) => {
+                            fee_error = Some(err);
+                            return ControlFlow::Break(());
+                        }
+                    }
+                }
+
+                ControlFlow::Continue(())
+            },
+        )?;
+        if let Some(err) = fee_error {
+            return Err(err.into());
+        }
+
+        pool.sqrt_price = current_state.sqrt_price_x_96;
+        let (burn_amount_0, burn_amount_1) =
+            pool.amounts_for_liquidity(tick_lower, tick_upper, liquidity)?;
+
+        let (fees_0, fees_1) = if zero_for_one {
+            (fees, U256::ZERO)
+        } else {
+            (U256::ZERO, fees)
+        };
+
+        Ok(JitSimulation {
+            amount_out: (-current_state.amount_calculated).into_raw(),
+            mint_amount_0,
+            mint_amount_1,
+            burn_amount_0,
+            burn_amount_1,
+            fees_0,
+            fees_1,
+            inventory_delta_0: I256::from_raw(burn_amount_0 + fees_0)
+                - I256::from_raw(mint_amount_0),
+            inventory_delta_1: I256::from_raw(burn_amount_1 + fees_1)
+                - I256::from_raw(mint_amount_1),
+        })
+    }
+
+    /// Returns the number of initialized ticks crossed by a swap of `amount_in` of `token_in`.
+    pub fn ticks_crossed(
+        &self,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<usize, SwapSimulationError> {
+        if amount_in.is_zero() {
+            return Ok(0);
+        }
+
+        let end_tick = self.swap_inner(token_in, amount_in)?.tick;
+
+        Ok(self.ticks_crossed_to(token_in, end_tick))
+    }
+
+    /// Returns the number of initialized ticks crossed by a swap of `token_in` moving the current tick to `end_tick`.
+    fn ticks_crossed_to(&self, token_in: Address, end_tick: i32) -> usize {
+        let (lower, upper) = if self.is_token0(token_in) {
+            (end_tick, self.tick)
+        } else {
+            (self.tick, end_tick)
+        };
+
+        // Ticks are crossed moving past them, from the exclusive side of the range
+        if lower < upper {
+            self.ticks.range(lower + 1..=upper).count()
+        } else {
+            0
+        }
+    }
+
+    /// Returns the largest amount of `token_in` that can be swapped while the execution price stays
+    /// within `max_slippage_bps` of the fee adjusted spot price.
+    ///
+    /// Walks the initialized ticks from the current price and only searches within the tick range
+    /// where the bound is crossed, instead of repeatedly simulating the full swap.
+    pub fn max_input_for_slippage(
+        &self,
+        token_in: Address,
+        max_slippage_bps: u32,
+    ) -> Result<U256, SwapSimulationError> {
+        let zero_for_one = self.is_token0(token_in);
+
+        let sqrt_price_limit_x_96 = if zero_for_one {
+            MIN_SQRT_RATIO + U256_1
+        } else {
+            MAX_SQRT_RATIO - U256_1
+        };
+
+        // Minimum amount out for a given amount in to stay within the slippage bound
+        let price_factor =
+            U256::from(1_000_000 - self.fee) * U256::from(10_000 - max_slippage_bps.min(10_000));
+        let min_amount_out = |amount_in: U256| -> Result<U256, SwapSimulationError> {
+            let amount_at_spot = if zero_for_one {
+                let amount = mul_div(amount_in, self.sqrt_price, Q96)?;
+                mul_div(amount, self.sqrt_price, Q96)?
+            } else {
+                let amount = mul_div(amount_in, Q96, self.sqrt_price)?;
+                mul_div(amount, Q96, self.sqrt_price)?
+            };
+
+            Ok(mul_div(
+                amount_at_spot,
+                price_factor,
+                U256::from(10_000_000_000_u64),
+            )?)
+        };
+
+        let mut current_state = CurrentState {
+            sqrt_price_x_96: self.sqrt_price,
+            amount_calculated: I256::ZERO,
+            amount_specified_remaining: I256::MAX,
+            tick: self.tick,
+            liquidity: self.liquidity,
+        };
+        let mut total_amount_in = U256::ZERO;
+        let mut total_amount_out = U256::ZERO;
+
+        while current_state.sqrt_price_x_96 != sqrt_price_limit_x_96 {
+            let (tick_next, initialized) = math::next_initialized_tick_within_one_word(
+                self,
+                current_state.tick,
+                self.tick_spacing,
+                zero_for_one,
+            )?;
+            self.check_tick_loaded(tick_next)?;
+            let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+
+            let sqrt_price_next_x96 =
+                uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(tick_next)?;
+            let swap_target_sqrt_ratio = if zero_for_one {
+                sqrt_price_next_x96.max(sqrt_price_limit_x_96)
+            } else {
+                sqrt_price_next_x96.min(sqrt_price_limit_x_96)
+            };
+
+            // Swap all the way to the next initialized tick
+            let (sqrt_price_x_96, amount_in, amount_out, fee_amount) =
+                uniswap_v3_math::swap_math::compute_swap_step(
+                    current_state.sqrt_price_x_96,
+                    swap_target_sqrt_ratio,
+                    current_state.liquidity,
+                    current_state.amount_specified_remaining,
+                    self.fee,
+                )?;
+            let step_amount_in = amount_in + fee_amount;
+
+            if total_amount_out + amount_out < min_amount_out(total_amount_in + step_amount_in)? {
+                // The bound is crossed within this step, search for the largest input that satisfies it
+                let (mut low, mut high) = (U256::ZERO, step_amount_in);
+                while low < high {
+                    let mid = high - (high - low) / U256_2;
+                    let (_, _, amount_out, _) = uniswap_v3_math::swap_math::compute_swap_step(
+                        current_state.sqrt_price_x_96,
+                        swap_target_sqrt_ratio,
+                        current_state.liquidity,
+                        I256::from_raw(mid),
+                        self.fee,
+                    )?;
+
+                    if total_amount_out + amount_out >= min_amount_out(total_amount_in + mid)? {
+                        low = mid;
+                    } else {
+                        high = mid - U256_1;
+                    }
+                }
+
+                return Ok(total_amount_in + low);
+            }
+
+            total_amount_in += step_amount_in;
+            total_amount_out += amount_out;
+            current_state.sqrt_price_x_96 = sqrt_price_x_96;
+
+            if current_state.sqrt_price_x_96 == sqrt_price_next_x96 {
+                if initialized {
+                    let mut liquidity_net = self.liquidity_net(tick_next);
+
+                    if zero_for_one {
+                        liquidity_net = -liquidity_net;
+                    }
+
+                    current_state.liquidity = if liquidity_net < 0 {
+                        current_state
+                            .liquidity
+                            .checked_sub(-liquidity_net as u128)
+                            .ok_or(SwapSimulationError::LiquidityUnderflow)?
+                    } else {
+                        current_state.liquidity + (liquidity_net as u128)
+                    };
+                }
+
+                current_state.tick = if zero_for_one {
+                    tick_next.wrapping_sub(1)
+                } else {
+                    tick_next
+                };
+            }
+        }
+
+        // The full liquidity of the pool can be consumed within the slippage bound
+        Ok(total_amount_in)
+    }
+
     /// Returns the call data for a swap.
     pub fn swap_calldata(
         &self,
@@ -1092,14 +1992,119 @@ impl UniswapV3Pool {
         .abi_encode()
         .into())
     }
+
+    /// Returns the fees owed for a flash loan of `amount_0` of token 0 and `amount_1` of token 1, rounded up as by the
+    /// pool, and the amounts to pay back in the `uniswapV3FlashCallback`.
+    ///
+    /// The pool must hold the amounts, which is not checked as token balances are not tracked.
+    pub fn simulate_flash(
+        &self,
+        amount_0: U256,
+        amount_1: U256,
+    ) -> Result<FlashSimulation, SwapSimulationError> {
+        // The pool reverts flash loans without active liquidity, as no position would earn the fees
+        if self.liquidity == 0 {
+            return Err(SwapSimulationError::InsufficientLiquidity);
+        }
+
+        let fee = U256::from(self.fee);
+        let fee_denominator = U256::from(FEE_DENOMINATOR);
+        let fee_0 = mul_div_rounding_up(amount_0, fee, fee_denominator)?;
+        let fee_1 = mul_div_rounding_up(amount_1, fee, fee_denominator)?;
+
+        Ok(FlashSimulation {
+            fee_0,
+            fee_1,
+            payback_0: amount_0 + fee_0,
+            payback_1: amount_1 + fee_1,
+        })
+    }
+
+    /// Returns the call data for a flash loan of `amount_0` of token 0 and `amount_1` of token 1 to `recipient`,
+    /// `data` being passed to the `uniswapV3FlashCallback` of the caller.
+    pub fn flash_calldata(
+        &self,
+        recipient: Address,
+        amount_0: U256,
+        amount_1: U256,
+        data: Bytes,
+    ) -> Bytes {
+        IUniswapV3Pool::flashCall {
+            recipient,
+            amount0: amount_0,
+            amount1: amount_1,
+            data,
+        }
+        .abi_encode()
+        .into()
+    }
+}
+
+/// Outcome of a swap that may not consume its whole amount in, see [`UniswapV3Pool::simulate_swap_with_fill`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapFill {
+    /// Amount of the token in swapped, including fees.
+    pub amount_in_consumed: U256,
+    pub amount_out: U256,
+    /// Whether the whole amount in was swapped, `false` if the swap ran out of liquidity before the price limit.
+    pub fully_filled: bool,
+}
+
+/// Outcome of just in time liquidity around a swap, see [`UniswapV3Pool::simulate_jit`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JitSimulation {
+    /// Amount out of the swap, with the liquidity in the pool.
+    pub amount_out: U256,
+    pub mint_amount_0: U256,
+    pub mint_amount_1: U256,
+    /// Amounts withdrawn by the burn, excluding fees.
+    pub burn_amount_0: U256,
+    pub burn_amount_1: U256,
+    /// Swap fees earned by the liquidity, in the token in.
+    pub fees_0: U256,
+    pub fees_1: U256,
+    /// Net change of the token balances of the liquidity provider, the burn amounts and fees less the mint amounts.
+    pub inventory_delta_0: I256,
+    pub inventory_delta_1: I256,
+}
+
+/// Fees and payback amounts of a flash loan, see [`UniswapV3Pool::simulate_flash`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlashSimulation {
+    pub fee_0: U256,
+    pub fee_1: U256,
+    pub payback_0: U256,
+    pub payback_1: U256,
+}
+
+/// Active liquidity within a tick range, see [`UniswapV3Pool::liquidity_distribution`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiquidityBucket {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub amount_0: U256,
+    pub amount_1: U256,
 }
 
-pub struct CurrentState {
-    amount_specified_remaining: I256,
-    amount_calculated: I256,
-    sqrt_price_x_96: U256,
-    tick: i32,
-    liquidity: u128,
+impl TickSource for UniswapV3Pool {
+    fn tick_bitmap_word(&self, word_pos: i16) -> U256 {
+        self.tick_bitmap.get(&word_pos).copied().unwrap_or_default()
+    }
+
+    fn liquidity_net(&self, tick: i32) -> i128 {
+        self.ticks.get(&tick).map_or(0, |info| info.liquidity_net)
+    }
+
+    fn check_tick_loaded(&self, tick: i32) -> Result<(), SwapSimulationError> {
+        if self.tick_is_loaded(tick) {
+            Ok(())
+        } else {
+            Err(SwapSimulationError::TickWordNotLoaded(
+                self.tick_word_position(tick),
+            ))
+        }
+    }
 }
 
 #[derive(Default)]
@@ -1124,6 +2129,15 @@ pub struct Tick {
     pub initialized: bool,
 }
 
+/// Splits a signed change of a pool balance into the amounts in and out of the pool.
+fn split_amount(amount: I256) -> (U256, U256) {
+    if amount.is_negative() {
+        (U256::ZERO, amount.unsigned_abs())
+    } else {
+        (amount.into_raw(), U256::ZERO)
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -1189,6 +2203,43 @@ mod test {
         Ok((pool, synced_block))
     }
 
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_populate_data_at_block() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let block_number = 17000000;
+        let mut pool = UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            ..Default::default()
+        };
+        pool.populate_data_at_block(
+            12369620,
+            block_number,
+            &SyncConfig::default(),
+            provider.clone(),
+        )
+        .await
+        .unwrap();
+
+        let quoter = IQuoter::new(
+            address!("b27308f9f90d607463bb33ea1bebb41c27ce5ab6"),
+            provider.clone(),
+        );
+
+        let amount_in = U256::from(10000000000000_u128); // 10_000_000 USDC
+        let amount_out = pool.simulate_swap(pool.token_a, amount_in).unwrap();
+        let expected_amount_out = quoter
+            .quoteExactInputSingle(pool.token_a, pool.token_b, pool.fee, amount_in, U256::ZERO)
+            .block(block_number.into())
+            .call()
+            .await
+            .unwrap();
+
+        assert_eq!(amount_out, expected_amount_out.amountOut);
+    }
+
     #[tokio::test]
     #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_simulate_swap_usdc_weth() {
@@ -1639,6 +2690,68 @@ mod test {
         assert_eq!(amount_out_3, expected_amount_out_3.amountOut);
     }
 
+    #[tokio::test]
+    #[ignore] // Records the pools benchmarked in `benches/simulate_swap.rs`
+    async fn record_bench_fixtures() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/pools");
+        std::fs::create_dir_all(dir).unwrap();
+
+        let (usdc_weth, _) = initialize_usdc_weth_pool(provider.clone()).await.unwrap();
+        let (weth_link, _) = initialize_weth_link_pool(provider.clone()).await.unwrap();
+        for (name, pool) in [("usdc_weth", usdc_weth), ("weth_link", weth_link)] {
+            std::fs::write(
+                format!("{dir}/{name}.json"),
+                serde_json::to_string(&pool).unwrap(),
+            )
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_simulate_swap_lazy() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let mut pool = UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            ..Default::default()
+        };
+        let synced_block = provider.get_block_number().await.unwrap();
+        pool.populate_data(Some(synced_block), provider.clone())
+            .await
+            .unwrap();
+
+        let quoter = IQuoter::new(
+            address!("b27308f9f90d607463bb33ea1bebb41c27ce5ab6"),
+            provider.clone(),
+        );
+
+        let amount_in = U256::from(100000000000000000000_u128); // 100 ETH
+        let amount_out = pool
+            .simulate_swap_lazy(
+                pool.token_b,
+                amount_in,
+                Some(synced_block),
+                provider.clone(),
+            )
+            .await
+            .unwrap();
+        let expected_amount_out = quoter
+            .quoteExactInputSingle(pool.token_b, pool.token_a, pool.fee, amount_in, U256::ZERO)
+            .block(synced_block.into())
+            .call()
+            .await
+            .unwrap();
+
+        assert_eq!(amount_out, expected_amount_out.amountOut);
+        assert!(pool.tick_window.is_some());
+        assert!(!pool.ticks.is_empty());
+    }
+
     #[tokio::test]
     #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_simulate_swap_mut_link_weth() {
@@ -1849,6 +2962,7 @@ mod test {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_sync_pool() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -1864,6 +2978,7 @@ mod test {
     }
 
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_calculate_virtual_reserves() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -1903,7 +3018,618 @@ mod test {
         assert_eq!(649198362624067343572319, r_1);
     }
 
+    #[test]
+    fn test_token_ordering() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let mut pool = UniswapV3Pool {
+            token_a: usdc,
+            token_a_decimals: 6,
+            token_b: weth,
+            token_b_decimals: 18,
+            sqrt_price: U256::from(1) << 96,
+            tick_spacing: 10,
+            fee: 500,
+            ..Default::default()
+        };
+        pool.modify_position(-1000, 1000, 1_000_000_000_000_000_000);
+
+        assert_eq!(pool.token0(), usdc);
+        assert_eq!(pool.token1(), weth);
+        assert!(pool.is_token0(usdc));
+        assert!(!pool.is_token0(weth));
+
+        // Swapping the fields keeps the canonical order and the swap direction
+        let mut swapped_pool = pool.clone();
+        std::mem::swap(&mut swapped_pool.token_a, &mut swapped_pool.token_b);
+        std::mem::swap(
+            &mut swapped_pool.token_a_decimals,
+            &mut swapped_pool.token_b_decimals,
+        );
+
+        assert_eq!(swapped_pool.token0(), usdc);
+        assert_eq!(swapped_pool.token0_decimals(), 6);
+        assert_eq!(swapped_pool.token1_decimals(), 18);
+
+        // The deprecated accessors forward to the canonical ones
+        #[allow(deprecated)]
+        {
+            assert_eq!(swapped_pool.token_a(), usdc);
+            assert_eq!(swapped_pool.token_b(), weth);
+            assert_eq!(swapped_pool.token_a_decimals(), 6);
+            assert_eq!(swapped_pool.token_b_decimals(), 18);
+        }
+        assert_eq!(
+            swapped_pool.simulate_swap(usdc, U256::from(1_000_000)),
+            pool.simulate_swap(usdc, U256::from(1_000_000))
+        );
+    }
+
+    #[test]
+    fn test_next_initialized_tick() {
+        let mut pool = UniswapV3Pool {
+            tick_spacing: 10,
+            ..Default::default()
+        };
+
+        pool.modify_position(-100, 100, 1000);
+        pool.modify_position(-50, 2000, 500);
+
+        assert_eq!(pool.next_initialized_tick(0, true), Some(-50));
+        assert_eq!(pool.next_initialized_tick(-50, true), Some(-50));
+        assert_eq!(pool.next_initialized_tick(-51, true), Some(-100));
+        assert_eq!(pool.next_initialized_tick(-101, true), None);
+
+        assert_eq!(pool.next_initialized_tick(0, false), Some(100));
+        assert_eq!(pool.next_initialized_tick(100, false), Some(2000));
+        assert_eq!(pool.next_initialized_tick(2000, false), None);
+    }
+
+    #[test]
+    fn test_liquidity_between_prices() {
+        let mut pool = UniswapV3Pool {
+            tick_spacing: 10,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-100, 100, 1_000_000_000);
+        pool.modify_position(-50, 200, 500_000_000);
+
+        let sqrt_price_at =
+            |tick| uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(tick).unwrap();
+
+        // The band covering both positions holds all of their tokens
+        let (amount_0, amount_1) = pool
+            .liquidity_between_prices(sqrt_price_at(-1000), sqrt_price_at(1000))
+            .unwrap();
+        let position_0 = pool
+            .amounts_for_liquidity(-100, 100, 1_000_000_000)
+            .unwrap();
+        let position_1 = pool.amounts_for_liquidity(-50, 200, 500_000_000).unwrap();
+        assert!(amount_0.abs_diff(position_0.0 + position_1.0) <= U256::from(2));
+        assert!(amount_1.abs_diff(position_1.1 + position_0.1) <= U256::from(2));
+
+        // Below the current price, only token 1
+        let (amount_0, amount_1) = pool
+            .liquidity_between_prices(sqrt_price_at(-100), sqrt_price_at(-50))
+            .unwrap();
+        assert_eq!(amount_0, U256::ZERO);
+        assert_eq!(
+            amount_1,
+            liquidity_amounts::get_amount_1_for_liquidity(
+                sqrt_price_at(-100),
+                sqrt_price_at(-50),
+                1_000_000_000
+            )
+            .unwrap()
+        );
+
+        assert_eq!(
+            pool.liquidity_between_prices(sqrt_price_at(10), sqrt_price_at(10))
+                .unwrap(),
+            (U256::ZERO, U256::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_initialized_ticks() {
+        let mut pool = UniswapV3Pool {
+            tick_spacing: 10,
+            tick: 0,
+            ..Default::default()
+        };
+
+        pool.modify_position(-100, 100, 1000);
+        pool.modify_position(-50, 5000, 500);
+
+        assert_eq!(
+            pool.initialized_ticks(TickDirection::Down)
+                .collect::<Vec<(i32, i128)>>(),
+            vec![(-50, 500), (-100, 1000)]
+        );
+        assert_eq!(
+            pool.initialized_ticks(TickDirection::Up)
+                .collect::<Vec<(i32, i128)>>(),
+            vec![(100, -1000), (5000, -500)]
+        );
+
+        // The walk stops at the edge of the tick window
+        pool.prune_ticks(0);
+        assert_eq!(
+            pool.initialized_ticks(TickDirection::Up)
+                .map(|(tick, _)| tick)
+                .collect::<Vec<i32>>(),
+            vec![100]
+        );
+    }
+
+    #[test]
+    fn test_simulate_swap_with_tick_callback() {
+        let mut pool = UniswapV3Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 100,
+            tick_spacing: 1,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-100, 100, 1_000_000_000_000_000_000);
+        pool.modify_position(-1000, 1000, 1_000_000_000_000_000_000);
+        pool.modify_position(-5000, 5000, 1_000_000_000_000_000_000);
+
+        let amount_in = U256::from(10_u128.pow(21));
+
+        let mut crossed = vec![];
+        let (amount_in_swapped, amount_out) = pool
+            .simulate_swap_with_tick_callback(pool.token_a, amount_in, |tick, liquidity_net, _| {
+                crossed.push((tick, liquidity_net));
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(
+            crossed,
+            vec![
+                (-100, 1_000_000_000_000_000_000),
+                (-1000, 1_000_000_000_000_000_000),
+                (-5000, 1_000_000_000_000_000_000)
+            ]
+        );
+        assert_eq!(
+            amount_out,
+            pool.simulate_swap(pool.token_a, amount_in).unwrap()
+        );
+        assert!(amount_in_swapped <= amount_in);
+
+        // Stop once the price falls to tick -1000
+        let mut amounts_in = vec![];
+        let (amount_in_swapped, amount_out) = pool
+            .simulate_swap_with_tick_callback(pool.token_a, amount_in, |tick, _, amount_in| {
+                amounts_in.push(amount_in);
+                if tick <= -1000 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(amounts_in.len(), 2);
+        assert!(amounts_in[0] < amounts_in[1]);
+        assert_eq!(amount_in_swapped, amounts_in[1]);
+        assert!(amount_in_swapped < amount_in);
+        assert!(amount_out < pool.simulate_swap(pool.token_a, amount_in).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_virtual_reserves_overflow() {
+        let pool = |sqrt_price, liquidity| UniswapV3Pool {
+            sqrt_price,
+            liquidity,
+            ..Default::default()
+        };
+
+        // Reserve 0 overflows at the lowest price, reserve 1 at the highest price
+        assert!(matches!(
+            pool(MIN_SQRT_RATIO, u128::MAX).calculate_virtual_reserves(),
+            Err(ArithmeticError::ReserveOverflow)
+        ));
+        assert!(matches!(
+            pool(MAX_SQRT_RATIO - U256_1, u128::MAX).calculate_virtual_reserves(),
+            Err(ArithmeticError::ReserveOverflow)
+        ));
+
+        // Smaller liquidity fits at the same prices
+        let (reserve_0, reserve_1) = pool(MIN_SQRT_RATIO, 1_000_000)
+            .calculate_virtual_reserves()
+            .unwrap();
+        assert!(reserve_0 > 1_000_000 && reserve_1 == 0);
+        let (reserve_0, reserve_1) = pool(MAX_SQRT_RATIO - U256_1, 1_000_000)
+            .calculate_virtual_reserves()
+            .unwrap();
+        assert!(reserve_0 == 0 && reserve_1 > 1_000_000);
+
+        // Prices out of bounds are rejected instead of panicking
+        assert!(pool(U256::ZERO, 1_000_000)
+            .calculate_virtual_reserves()
+            .is_err());
+        assert!(pool(MAX_SQRT_RATIO, 1_000_000)
+            .calculate_virtual_reserves()
+            .is_err());
+    }
+
+    #[test]
+    fn test_simulate_swap_with_fill() {
+        let mut pool = UniswapV3Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 500,
+            tick_spacing: 10,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-1000, 1000, 1_000_000_000_000_000_000);
+
+        let amount_in = U256::from(1_000_000_000_000_000_u128);
+        let fill = pool
+            .simulate_swap_with_fill(pool.token_a, amount_in)
+            .unwrap();
+        assert!(fill.fully_filled);
+        assert_eq!(fill.amount_in_consumed, amount_in);
+        assert_eq!(
+            fill.amount_out,
+            pool.simulate_swap(pool.token_a, amount_in).unwrap()
+        );
+
+        // The liquidity of the pool is exhausted below tick -1000
+        let amount_in = U256::from(10_u128.pow(24));
+        let fill = pool
+            .simulate_swap_with_fill(pool.token_a, amount_in)
+            .unwrap();
+        assert!(!fill.fully_filled);
+        assert!(fill.amount_in_consumed < amount_in);
+        assert_eq!(
+            fill.amount_out,
+            pool.simulate_swap(pool.token_a, amount_in).unwrap()
+        );
+
+        let fill = pool
+            .simulate_swap_with_fill(pool.token_a, U256::ZERO)
+            .unwrap();
+        assert!(fill.fully_filled);
+    }
+
+    #[test]
+    fn test_simulate_jit() {
+        let mut pool = UniswapV3Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 500,
+            tick_spacing: 10,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-1000, 1000, 1_000_000_000_000_000_000);
+
+        let amount_in = U256::from(100_000_000_000_000_u128);
+        let jit = pool
+            .simulate_jit(-10, 10, 1_000_000_000_000_000_000, pool.token_a, amount_in)
+            .unwrap();
+
+        // Half of the liquidity in range earns half of the fees
+        let fees = amount_in * U256::from(500) / U256::from(1_000_000);
+        assert!(jit.fees_0.abs_diff(fees / U256::from(2)) <= U256::from(1));
+        assert_eq!(jit.fees_1, U256::ZERO);
+
+        // The liquidity sold token 1 for token 0 along the swap
+        assert!(jit.inventory_delta_0 > I256::ZERO);
+        assert!(jit.inventory_delta_1 < I256::ZERO);
+        assert!(jit.amount_out > pool.simulate_swap(pool.token_a, amount_in).unwrap());
+
+        // The pool is left as is
+        assert_eq!(pool.liquidity, 1_000_000_000_000_000_000);
+
+        assert!(matches!(
+            pool.simulate_jit(10, -10, 1, pool.token_a, amount_in),
+            Err(SwapSimulationError::InvalidTickRange(10, -10))
+        ));
+        assert!(matches!(
+            pool.simulate_jit(-5, 10, 1, pool.token_a, amount_in),
+            Err(SwapSimulationError::InvalidTickRange(-5, 10))
+        ));
+
+        // Pools without a tick spacing, e.g. not populated yet, are rejected instead of dividing by zero
+        pool.tick_spacing = 0;
+        assert!(matches!(
+            pool.simulate_jit(-10, 10, 1, pool.token_a, amount_in),
+            Err(SwapSimulationError::InvalidTickSpacing(0))
+        ));
+    }
+
+    #[test]
+    fn test_simulate_flash() {
+        let mut pool = UniswapV3Pool {
+            fee: 500,
+            ..Default::default()
+        };
+        assert!(matches!(
+            pool.simulate_flash(U256::from(1), U256::ZERO),
+            Err(SwapSimulationError::InsufficientLiquidity)
+        ));
+
+        pool.liquidity = 1_000_000;
+        let flash = pool
+            .simulate_flash(U256::from(1_000_000), U256::from(1))
+            .unwrap();
+        assert_eq!(flash.fee_0, U256::from(500));
+        assert_eq!(flash.payback_0, U256::from(1_000_500));
+        // Fees are rounded up
+        assert_eq!(flash.fee_1, U256::from(1));
+        assert_eq!(flash.payback_1, U256::from(2));
+
+        let recipient = Address::with_last_byte(1);
+        let calldata = pool.flash_calldata(
+            recipient,
+            U256::from(1_000_000),
+            U256::ZERO,
+            Bytes::from(vec![1, 2, 3]),
+        );
+        let call = IUniswapV3Pool::flashCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(call.recipient, recipient);
+        assert_eq!(call.amount0, U256::from(1_000_000));
+        assert_eq!(call.amount1, U256::ZERO);
+        assert_eq!(call.data, Bytes::from(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_new_validation() {
+        let new = |fee, tick_spacing, sqrt_price, tick| {
+            UniswapV3Pool::new(
+                Address::ZERO,
+                Address::ZERO,
+                18,
+                Address::ZERO,
+                18,
+                fee,
+                0,
+                sqrt_price,
+                tick,
+                tick_spacing,
+                HashMap::new(),
+                BTreeMap::new(),
+            )
+        };
+        let sqrt_price = U256::from(1) << 96;
+
+        assert!(new(3000, 60, sqrt_price, 0).is_ok());
+        assert!(new(3000, 60, U256::ZERO, 0).is_ok());
+        assert_eq!(
+            new(1_000_000, 60, sqrt_price, 0).unwrap_err(),
+            PoolBuilderError::InvalidFee(1_000_000)
+        );
+        assert_eq!(
+            new(3000, 0, sqrt_price, 0).unwrap_err(),
+            PoolBuilderError::InvalidTickSpacing(0)
+        );
+        assert_eq!(
+            new(3000, 60, sqrt_price, MAX_TICK + 1).unwrap_err(),
+            PoolBuilderError::InvalidTick(MAX_TICK + 1)
+        );
+        assert_eq!(
+            new(3000, 60, MAX_SQRT_RATIO, 0).unwrap_err(),
+            PoolBuilderError::InvalidSqrtPrice(MAX_SQRT_RATIO)
+        );
+    }
+
+    #[test]
+    fn test_prune_ticks() {
+        let mut pool = UniswapV3Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 100,
+            tick_spacing: 1,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-100, 100, 1_000_000_000_000_000_000);
+        pool.modify_position(-1000, 1000, 1_000_000_000_000_000_000);
+        pool.modify_position(-5000, 5000, 1_000_000_000_000_000_000);
+
+        let large_amount_in = U256::from(10_u128.pow(21));
+        let amount_out = pool.simulate_swap(pool.token_a, large_amount_in).unwrap();
+
+        pool.prune_ticks(1);
+        assert_eq!(pool.tick_window, Some((-1, 1)));
+        assert_eq!(
+            pool.ticks.keys().copied().collect::<Vec<_>>(),
+            vec![-100, 100]
+        );
+        assert!(pool.tick_bitmap.keys().all(|word| (-1..=1).contains(word)));
+
+        // Swaps within the tick window are unaffected
+        let small_amount_in = U256::from(10_u128.pow(15));
+        pool.simulate_swap(pool.token_a, small_amount_in).unwrap();
+
+        assert!(matches!(
+            pool.simulate_swap(pool.token_a, large_amount_in),
+            Err(SwapSimulationError::TickWordNotLoaded(-2))
+        ));
+        assert!(amount_out > U256::ZERO);
+    }
+
+    #[test]
+    fn test_prune_ticks_unpopulated_pool() {
+        let mut pool = UniswapV3Pool::default();
+        pool.prune_ticks(1);
+
+        assert_eq!(pool.tick_window, None);
+        assert!(pool.tick_is_loaded(0));
+    }
+
+    #[test]
+    fn test_swap_gas_estimate() {
+        let mut pool = UniswapV3Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 100,
+            tick_spacing: 1,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-100, 100, 1_000_000_000_000_000_000);
+        pool.modify_position(-1000, 1000, 1_000_000_000_000_000_000);
+
+        let small_amount_in = U256::from(10_u128.pow(15));
+        assert_eq!(
+            pool.ticks_crossed(pool.token_a, small_amount_in).unwrap(),
+            0
+        );
+        assert_eq!(
+            pool.swap_gas_estimate(pool.token_a, small_amount_in),
+            SWAP_BASE_GAS_ESTIMATE
+        );
+
+        // Crosses -100 and -1000 before running out of liquidity
+        let large_amount_in = U256::from(10_u128.pow(21));
+        assert_eq!(
+            pool.ticks_crossed(pool.token_a, large_amount_in).unwrap(),
+            2
+        );
+        assert_eq!(
+            pool.swap_gas_estimate(pool.token_a, large_amount_in),
+            SWAP_BASE_GAS_ESTIMATE + 2 * TICK_CROSSED_GAS_ESTIMATE
+        );
+
+        // The amount out and the gas estimate come from the same simulation
+        assert_eq!(
+            pool.simulate_swap_with_gas_estimate(pool.token_a, large_amount_in)
+                .unwrap(),
+            (
+                pool.simulate_swap(pool.token_a, large_amount_in).unwrap(),
+                SWAP_BASE_GAS_ESTIMATE + 2 * TICK_CROSSED_GAS_ESTIMATE
+            )
+        );
+    }
+
+    #[test]
+    fn test_tick_maps_json_round_trip() {
+        let mut pool = UniswapV3Pool {
+            tick_spacing: 10,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-887270, 887270, 1_000_000);
+        pool.modify_position(-100, 200, 500);
+
+        let json = serde_json::to_string(&pool).unwrap();
+        assert!(json.contains("\"-887270\""));
+
+        let decoded: UniswapV3Pool = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.tick_bitmap, pool.tick_bitmap);
+        assert_eq!(
+            decoded.ticks.keys().collect::<Vec<_>>(),
+            pool.ticks.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(decoded.ticks[&-100].liquidity_net, 500);
+
+        // Pools also round-trip through `serde_json::Value`
+        let value = serde_json::to_value(&pool).unwrap();
+        let decoded: UniswapV3Pool = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.tick_bitmap, pool.tick_bitmap);
+        assert_eq!(decoded.ticks.len(), pool.ticks.len());
+    }
+
+    #[test]
+    fn test_liquidity_distribution() {
+        let mut pool = UniswapV3Pool {
+            tick: 0,
+            tick_spacing: 10,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+
+        pool.modify_position(-100, 100, 1000);
+        pool.modify_position(-50, 200, 500);
+
+        let buckets = pool.liquidity_distribution(300).unwrap();
+        let ranges = buckets
+            .iter()
+            .map(|bucket| (bucket.tick_lower, bucket.tick_upper, bucket.liquidity))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ranges,
+            vec![
+                (-300, -100, 0),
+                (-100, -50, 1000),
+                (-50, 100, 1500),
+                (100, 200, 500),
+                (200, 300, 0),
+            ]
+        );
+
+        // Ranges entirely below the current price only hold token 1, above only token 0
+        assert!(buckets[1].amount_0.is_zero() && !buckets[1].amount_1.is_zero());
+        assert!(!buckets[3].amount_0.is_zero() && buckets[3].amount_1.is_zero());
+    }
+
+    #[test]
+    fn test_swap_inner_matches_simulate_swap_mut() {
+        let mut pool = UniswapV3Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 3000,
+            tick_spacing: 60,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-600, 600, 1_000_000_000_000_000_000);
+        pool.modify_position(-120, 1200, 1_000_000_000_000_000_000);
+
+        let amount_in = U256::from(100_000_000_000_000_000_u128);
+        let state = pool.swap_inner(pool.token_a, amount_in).unwrap();
+        let amount_out = pool.simulate_swap_mut(pool.token_a, amount_in).unwrap();
+
+        assert!(state.amount_specified_remaining.is_zero());
+        assert_eq!((-state.amount_calculated).into_raw(), amount_out);
+        assert_eq!(state.sqrt_price_x_96, pool.sqrt_price);
+        assert_eq!(state.tick, pool.tick);
+        assert_eq!(state.liquidity, pool.liquidity);
+    }
+
+    #[test]
+    fn test_max_input_for_slippage() {
+        let mut pool = UniswapV3Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 3000,
+            tick: 0,
+            tick_spacing: 60,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-600, 600, 1_000_000_000_000_000_000);
+        pool.modify_position(-6000, 6000, 1_000_000_000_000_000_000);
+
+        // At a price of 1, the fee adjusted spot price is 0.997 and a 1% bound allows 0.98703
+        // (rounded down, so allow one unit of rounding)
+        let within_bound = |amount_in: U256, amount_out: U256| {
+            (amount_out + U256_1) * U256::from(100_000) >= amount_in * U256::from(98_703)
+        };
+
+        for token_in in [pool.token_a, pool.token_b] {
+            let max_amount_in = pool.max_input_for_slippage(token_in, 100).unwrap();
+            assert!(max_amount_in > U256::ZERO);
+
+            let amount_out = pool.simulate_swap(token_in, max_amount_in).unwrap();
+            assert!(within_bound(max_amount_in, amount_out));
+
+            let larger_amount_in = max_amount_in * U256::from(101) / U256::from(100);
+            let amount_out = pool.simulate_swap(token_in, larger_amount_in).unwrap();
+            assert!(!within_bound(larger_amount_in, amount_out));
+        }
+    }
+
     #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
     async fn test_calculate_price() {
         let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
         let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
@@ -1935,4 +3661,176 @@ mod test {
         assert_eq!(float_price_a, 0.0006081236083117488);
         assert_eq!(float_price_b, 1644.4025299004006);
     }
+
+    #[test]
+    fn test_calculate_price_token_order() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let pool = UniswapV3Pool {
+            token_a: usdc,
+            token_a_decimals: 6,
+            token_b: weth,
+            token_b_decimals: 18,
+            sqrt_price: U256::from(1) << 96,
+            ..Default::default()
+        };
+        // Same pool, with token a being token 1
+        let reversed_pool = UniswapV3Pool {
+            token_a: weth,
+            token_a_decimals: 18,
+            token_b: usdc,
+            token_b_decimals: 6,
+            ..pool.clone()
+        };
+
+        assert_eq!(
+            pool.calculate_price(usdc).unwrap(),
+            reversed_pool.calculate_price(usdc).unwrap()
+        );
+        assert_eq!(
+            pool.calculate_price(weth).unwrap(),
+            reversed_pool.calculate_price(weth).unwrap()
+        );
+        assert_eq!(reversed_pool.get_token_out(usdc), weth);
+        assert_eq!(reversed_pool.get_token_out(weth), usdc);
+    }
+
+    fn normalized_bitmap(pool: &UniswapV3Pool) -> BTreeMap<i16, U256> {
+        pool.tick_bitmap
+            .iter()
+            .filter(|(_, word)| !word.is_zero())
+            .map(|(word_pos, word)| (*word_pos, *word))
+            .collect()
+    }
+
+    /// Mint and burn sequence, as `(tick_lower, tick_upper, liquidity_delta)` positions with tick spacing 10.
+    ///
+    /// Burns only remove liquidity from previous mints, so that the sequence is valid.
+    fn positions() -> impl proptest::strategy::Strategy<Value = Vec<(i32, i32, i128)>> {
+        use proptest::prelude::*;
+
+        prop::collection::vec(
+            (-100..100_i32, 1..50_i32, 1..u64::MAX, any::<bool>()),
+            1..50,
+        )
+        .prop_map(|ops| {
+            let mut positions: Vec<(i32, i32, i128)> = vec![];
+            let mut sequence = vec![];
+
+            for (lower, width, liquidity, burn) in ops {
+                if burn && !positions.is_empty() {
+                    let index = liquidity as usize % positions.len();
+                    let (tick_lower, tick_upper, minted) = positions[index];
+                    let burned = (liquidity as i128 % minted) + 1;
+
+                    if burned == minted {
+                        positions.swap_remove(index);
+                    } else {
+                        positions[index].2 -= burned;
+                    }
+
+                    sequence.push((tick_lower, tick_upper, -burned));
+                } else {
+                    let position = (lower * 10, (lower + width) * 10, liquidity as i128);
+                    positions.push(position);
+                    sequence.push(position);
+                }
+            }
+
+            sequence
+        })
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_liquidity_net_sums_to_zero(sequence in positions()) {
+            let mut pool = UniswapV3Pool {
+                tick_spacing: 10,
+                ..Default::default()
+            };
+
+            for (tick_lower, tick_upper, liquidity_delta) in sequence {
+                pool.modify_position(tick_lower, tick_upper, liquidity_delta);
+            }
+
+            let liquidity_net = pool.ticks.values().map(|info| info.liquidity_net).sum::<i128>();
+            proptest::prop_assert_eq!(liquidity_net, 0);
+
+            // Every tracked tick is initialized and flipped in the bitmap
+            for (tick, info) in pool.ticks.iter() {
+                proptest::prop_assert!(info.initialized && info.liquidity_gross > 0);
+                let (word_pos, bit_pos) =
+                    uniswap_v3_math::tick_bitmap::position(*tick / pool.tick_spacing);
+                let word = pool.tick_bitmap.get(&word_pos).copied().unwrap_or_default();
+                proptest::prop_assert!(word.bit(bit_pos as usize));
+            }
+        }
+
+        #[test]
+        fn test_liquidity_matches_recomputation(sequence in positions(), tick in -1500..1500_i32) {
+            let mut pool = UniswapV3Pool {
+                tick_spacing: 10,
+                tick,
+                ..Default::default()
+            };
+
+            for (tick_lower, tick_upper, liquidity_delta) in sequence {
+                pool.modify_position(tick_lower, tick_upper, liquidity_delta);
+            }
+
+            // Active liquidity is the sum of liquidity net of all ticks at or below the current tick
+            let recomputed = pool
+                .ticks
+                .range(..=tick)
+                .map(|(_, info)| info.liquidity_net)
+                .sum::<i128>();
+
+            proptest::prop_assert_eq!(pool.liquidity as i128, recomputed);
+        }
+
+        #[test]
+        fn test_flip_tick_round_trip(sequence in positions(), tick in -100..100_i32) {
+            let mut pool = UniswapV3Pool {
+                tick_spacing: 10,
+                ..Default::default()
+            };
+
+            for (tick_lower, tick_upper, liquidity_delta) in sequence {
+                pool.modify_position(tick_lower, tick_upper, liquidity_delta);
+            }
+
+            let bitmap = normalized_bitmap(&pool);
+            pool.flip_tick(tick * 10, pool.tick_spacing);
+            proptest::prop_assert_ne!(&normalized_bitmap(&pool), &bitmap);
+            pool.flip_tick(tick * 10, pool.tick_spacing);
+            proptest::prop_assert_eq!(normalized_bitmap(&pool), bitmap);
+        }
+
+        #[test]
+        fn test_update_position_inverse(
+            sequence in positions(),
+            lower in -100..100_i32,
+            width in 1..50_i32,
+            liquidity in 1..u64::MAX,
+        ) {
+            let mut pool = UniswapV3Pool {
+                tick_spacing: 10,
+                ..Default::default()
+            };
+
+            for (tick_lower, tick_upper, liquidity_delta) in sequence {
+                pool.update_position(tick_lower, tick_upper, liquidity_delta);
+            }
+
+            let ticks = pool.ticks.clone();
+            let bitmap = normalized_bitmap(&pool);
+
+            let (tick_lower, tick_upper) = (lower * 10, (lower + width) * 10);
+            pool.update_position(tick_lower, tick_upper, liquidity as i128);
+            pool.update_position(tick_lower, tick_upper, -(liquidity as i128));
+
+            proptest::prop_assert_eq!(pool.ticks, ticks);
+            proptest::prop_assert_eq!(normalized_bitmap(&pool), bitmap);
+        }
+    }
 }
@@ -0,0 +1,203 @@
+//! Quotes of Uniswap's QuoterV2, to cross-check local swap simulations against the chain.
+//!
+//! QuoterV2 executes the swaps and reverts with their outcome, so quotes are obtained with `eth_call` and cost no gas.
+//! On top of the amount out, quotes include the price after the swap, the initialized ticks crossed and an estimate
+//! of the gas used by the swap.
+
+use std::sync::Arc;
+
+use alloy::{
+    network::Network,
+    primitives::{address, Address, Bytes, U256},
+    providers::Provider,
+    rpc::types::eth::{BlockId, BlockNumberOrTag},
+    sol,
+    transports::Transport,
+};
+
+use crate::errors::AMMError;
+
+use super::UniswapV3Pool;
+
+/// Address of Uniswap's QuoterV2 on Ethereum mainnet.
+pub const QUOTER_V2_ADDRESS: Address = address!("61fFE014bA17989E743c5F6cB21bF9697530B21e");
+
+sol! {
+    /// Interface of the QuoterV2
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract IQuoterV2 {
+        struct QuoteExactInputSingleParams {
+            address tokenIn;
+            address tokenOut;
+            uint256 amountIn;
+            uint24 fee;
+            uint160 sqrtPriceLimitX96;
+        }
+
+        function quoteExactInputSingle(QuoteExactInputSingleParams memory params) external returns (uint256 amountOut, uint160 sqrtPriceX96After, uint32 initializedTicksCrossed, uint256 gasEstimate);
+        function quoteExactInput(bytes memory path, uint256 amountIn) external returns (uint256 amountOut, uint160[] memory sqrtPriceX96AfterList, uint32[] memory initializedTicksCrossedList, uint256 gasEstimate);
+    }
+}
+
+/// Quote of a swap in a single pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuoterV2Quote {
+    pub amount_out: U256,
+    /// Price of the pool after the swap.
+    pub sqrt_price_x_96_after: U256,
+    pub initialized_ticks_crossed: u32,
+    /// Gas used by the swap, as estimated by the quoter.
+    pub gas_estimate: U256,
+}
+
+/// Quote of a swap along a path of pools, with the price and initialized ticks crossed of each pool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuoterV2PathQuote {
+    pub amount_out: U256,
+    pub sqrt_price_x_96_after: Vec<U256>,
+    pub initialized_ticks_crossed: Vec<u32>,
+    pub gas_estimate: U256,
+}
+
+/// Quotes a swap of `amount_in` of `token_in` for `token_out` in the pool with `fee`, at `block_number` or the latest
+/// block if `None`.
+pub async fn quote_exact_input_single<T, N, P>(
+    quoter: Address,
+    token_in: Address,
+    token_out: Address,
+    fee: u32,
+    amount_in: U256,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<QuoterV2Quote, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let quote = IQuoterV2::new(quoter, provider)
+        .quoteExactInputSingle(IQuoterV2::QuoteExactInputSingleParams {
+            tokenIn: token_in,
+            tokenOut: token_out,
+            amountIn: amount_in,
+            fee,
+            sqrtPriceLimitX96: U256::ZERO,
+        })
+        .block(block_id(block_number))
+        .call()
+        .await?;
+
+    Ok(QuoterV2Quote {
+        amount_out: quote.amountOut,
+        sqrt_price_x_96_after: quote.sqrtPriceX96After,
+        initialized_ticks_crossed: quote.initializedTicksCrossed,
+        gas_estimate: quote.gasEstimate,
+    })
+}
+
+/// Quotes a swap of `amount_in` of `token_in` in `pool`, see [`quote_exact_input_single`].
+pub async fn quote_pool<T, N, P>(
+    quoter: Address,
+    pool: &UniswapV3Pool,
+    token_in: Address,
+    amount_in: U256,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<QuoterV2Quote, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let token_out = if token_in == pool.token_a {
+        pool.token_b
+    } else {
+        pool.token_a
+    };
+
+    quote_exact_input_single(
+        quoter,
+        token_in,
+        token_out,
+        pool.fee,
+        amount_in,
+        block_number,
+        provider,
+    )
+    .await
+}
+
+/// Quotes a swap of `amount_in` of `tokens[0]` along the pools between consecutive `tokens` with `fees`, at
+/// `block_number` or the latest block if `None`.
+///
+/// There must be one fee per pair of consecutive tokens.
+pub async fn quote_exact_input<T, N, P>(
+    quoter: Address,
+    tokens: &[Address],
+    fees: &[u32],
+    amount_in: U256,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<QuoterV2PathQuote, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let quote = IQuoterV2::new(quoter, provider)
+        .quoteExactInput(encode_path(tokens, fees), amount_in)
+        .block(block_id(block_number))
+        .call()
+        .await?;
+
+    Ok(QuoterV2PathQuote {
+        amount_out: quote.amountOut,
+        sqrt_price_x_96_after: quote.sqrtPriceX96AfterList,
+        initialized_ticks_crossed: quote.initializedTicksCrossedList,
+        gas_estimate: quote.gasEstimate,
+    })
+}
+
+/// Encodes a swap path as expected by the Uniswap V3 periphery, each token followed by the 3 byte fee of the pool
+/// to the next token.
+pub fn encode_path(tokens: &[Address], fees: &[u32]) -> Bytes {
+    let mut path = Vec::with_capacity(tokens.len() * 23);
+
+    for (i, token) in tokens.iter().enumerate() {
+        path.extend_from_slice(token.as_slice());
+        if let Some(fee) = fees.get(i).filter(|_| i + 1 < tokens.len()) {
+            path.extend_from_slice(&fee.to_be_bytes()[1..]);
+        }
+    }
+
+    path.into()
+}
+
+fn block_id(block_number: Option<u64>) -> BlockId {
+    block_number.map_or(BlockId::Number(BlockNumberOrTag::Latest), BlockId::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, Address};
+
+    use super::encode_path;
+
+    #[test]
+    fn test_encode_path() {
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let dai = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+
+        let path = encode_path(&[usdc, weth, dai], &[500, 3000]);
+        assert_eq!(path.len(), 20 * 3 + 3 * 2);
+        assert_eq!(Address::from_slice(&path[..20]), usdc);
+        assert_eq!(path[20..23], [0x00, 0x01, 0xf4]);
+        assert_eq!(Address::from_slice(&path[23..43]), weth);
+        assert_eq!(path[43..46], [0x00, 0x0b, 0xb8]);
+        assert_eq!(Address::from_slice(&path[46..]), dai);
+
+        assert_eq!(encode_path(&[usdc], &[]).len(), 20);
+    }
+}
@@ -0,0 +1,84 @@
+//! Serde helpers for the tick maps of a [`UniswapV3Pool`](super::UniswapV3Pool).
+//!
+//! Human readable formats (e.g. JSON) get maps sorted by tick with decimal string keys, so that pools round-trip
+//! through JSON and `serde_json::Value`. Binary formats keep the integer keys.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    str::FromStr,
+};
+
+use alloy::primitives::U256;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Info;
+
+/// (De)serializes `UniswapV3Pool::tick_bitmap`.
+pub mod tick_bitmap {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        tick_bitmap: &HashMap<i16, U256>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize_map(tick_bitmap.iter().collect(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<i16, U256>, D::Error> {
+        deserialize_map(deserializer)
+    }
+}
+
+/// (De)serializes `UniswapV3Pool::ticks`.
+pub mod ticks {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        ticks: &BTreeMap<i32, Info>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize_map(ticks.iter().collect(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<i32, Info>, D::Error> {
+        deserialize_map(deserializer)
+    }
+}
+
+fn serialize_map<K, V, S>(map: BTreeMap<&K, &V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Display + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    if serializer.is_human_readable() {
+        serializer.collect_map(map.into_iter().map(|(key, value)| (key.to_string(), value)))
+    } else {
+        serializer.collect_map(map)
+    }
+}
+
+fn deserialize_map<'de, K, V, M, D>(deserializer: D) -> Result<M, D::Error>
+where
+    K: FromStr + Ord + Deserialize<'de>,
+    K::Err: Display,
+    V: Deserialize<'de>,
+    M: FromIterator<(K, V)>,
+    D: Deserializer<'de>,
+{
+    if deserializer.is_human_readable() {
+        BTreeMap::<String, V>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| Ok((key.parse().map_err(D::Error::custom)?, value)))
+            .collect()
+    } else {
+        Ok(BTreeMap::<K, V>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
@@ -0,0 +1,161 @@
+//! Conversion of Uniswap V3 subgraph entities into pools.
+
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::errors::AMMError;
+
+use super::{tick_spacing_for_fee, Info, UniswapV3Pool};
+
+impl UniswapV3Pool {
+    /// Creates a pool from a `Pool` entity of the Uniswap V3 subgraph.
+    ///
+    /// Expects the `id`, `token0 { id decimals }`, `token1 { id decimals }`, `feeTier`, `liquidity`, `sqrtPrice` and `tick`
+    /// fields, and loads tick data from `ticks { tickIdx liquidityGross liquidityNet }` if present.
+    ///
+    /// The tick spacing is read from the `tickSpacing` field of subgraphs exposing it, so that fee tiers enabled with
+    /// `enableFeeAmount` are supported, and derived from the default fee tiers otherwise.
+    pub fn from_subgraph(pool: &Value) -> Result<Self, AMMError> {
+        let fee = parse(pool, "feeTier")?;
+
+        let mut uniswap_v3_pool = UniswapV3Pool {
+            address: parse(pool, "id")?,
+            token_a: parse(&pool["token0"], "id")?,
+            token_a_decimals: parse(&pool["token0"], "decimals")?,
+            token_b: parse(&pool["token1"], "id")?,
+            token_b_decimals: parse(&pool["token1"], "decimals")?,
+            liquidity: parse(pool, "liquidity")?,
+            sqrt_price: parse(pool, "sqrtPrice")?,
+            fee,
+            // The tick is null for pools that are not initialized
+            tick: if pool["tick"].is_null() {
+                0
+            } else {
+                parse(pool, "tick")?
+            },
+            tick_spacing: if pool["tickSpacing"].is_null() {
+                tick_spacing_for_fee(fee)
+                    .ok_or_else(|| AMMError::SubgraphError(format!("Unknown fee tier: {fee}")))?
+            } else {
+                parse(pool, "tickSpacing")?
+            },
+            ..Default::default()
+        };
+
+        if let Some(ticks) = pool["ticks"].as_array() {
+            uniswap_v3_pool.insert_subgraph_ticks(ticks)?;
+        }
+
+        Ok(uniswap_v3_pool)
+    }
+
+    /// Inserts the initialized ticks from `Tick` entities of the Uniswap V3 subgraph and flips them in the `tick_bitmap`.
+    pub fn insert_subgraph_ticks(&mut self, ticks: &[Value]) -> Result<(), AMMError> {
+        for tick in ticks {
+            let tick_idx: i32 = parse(tick, "tickIdx")?;
+            let liquidity_gross: u128 = parse(tick, "liquidityGross")?;
+            let liquidity_net: i128 = parse(tick, "liquidityNet")?;
+
+            if liquidity_gross == 0 {
+                continue;
+            }
+
+            if self
+                .ticks
+                .insert(tick_idx, Info::new(liquidity_gross, liquidity_net, true))
+                .is_none()
+            {
+                self.flip_tick(tick_idx, self.tick_spacing);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a field of a subgraph entity. `BigInt` fields are returned as strings, other scalars as JSON values.
+fn parse<T: FromStr>(entity: &Value, field: &str) -> Result<T, AMMError> {
+    let value = match &entity[field] {
+        Value::String(value) => value.clone(),
+        Value::Number(value) => value.to_string(),
+        _ => return Err(AMMError::SubgraphError(format!("Missing field: {field}"))),
+    };
+
+    value
+        .parse()
+        .map_err(|_| AMMError::SubgraphError(format!("Invalid {field}: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, U256};
+    use serde_json::json;
+
+    use crate::amm::{uniswap_v3::UniswapV3Pool, AutomatedMarketMaker};
+
+    #[test]
+    fn test_from_subgraph() {
+        let pool = UniswapV3Pool::from_subgraph(&json!({
+            "id": "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640",
+            "token0": { "id": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "decimals": "6" },
+            "token1": { "id": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "decimals": "18" },
+            "feeTier": "500",
+            "liquidity": "1000",
+            "sqrtPrice": "79228162514264337593543950336",
+            "tick": "0",
+            "ticks": [
+                { "tickIdx": "-100", "liquidityGross": "1000", "liquidityNet": "1000" },
+                { "tickIdx": "100", "liquidityGross": "1000", "liquidityNet": "-1000" },
+                { "tickIdx": "200", "liquidityGross": "0", "liquidityNet": "0" }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            pool.address,
+            address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")
+        );
+        assert_eq!(pool.token_a_decimals, 6);
+        assert_eq!(pool.token_b_decimals, 18);
+        assert_eq!(pool.tick_spacing, 10);
+        assert_eq!(pool.sqrt_price, U256::from(1) << 96);
+        assert_eq!(
+            pool.ticks.keys().copied().collect::<Vec<_>>(),
+            vec![-100, 100]
+        );
+        assert_eq!(pool.next_initialized_tick(0, false), Some(100));
+
+        // A swap within the range only uses the subgraph tick data
+        pool.simulate_swap(pool.token_a, U256::from(10)).unwrap();
+    }
+
+    #[test]
+    fn test_from_subgraph_tick_spacing() {
+        let pool = |fee_tier: &str, tick_spacing: Option<&str>| {
+            let mut pool = json!({
+                "id": "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640",
+                "token0": { "id": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "decimals": "6" },
+                "token1": { "id": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "decimals": "18" },
+                "feeTier": fee_tier,
+                "liquidity": "0",
+                "sqrtPrice": "79228162514264337593543950336",
+                "tick": "0"
+            });
+            if let Some(tick_spacing) = tick_spacing {
+                pool["tickSpacing"] = json!(tick_spacing);
+            }
+
+            UniswapV3Pool::from_subgraph(&pool)
+        };
+
+        assert_eq!(pool("3000", None).unwrap().tick_spacing, 60);
+        assert_eq!(pool("200", Some("4")).unwrap().tick_spacing, 4);
+        assert!(pool("200", None).is_err());
+    }
+
+    #[test]
+    fn test_from_subgraph_invalid() {
+        assert!(UniswapV3Pool::from_subgraph(&json!({ "id": "0x88e6" })).is_err());
+    }
+}
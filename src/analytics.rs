@@ -0,0 +1,287 @@
+//! Price candles and volume series folded from Uniswap V2 and V3 Swap logs.
+//!
+//! Candles are bucketed by block number, every `interval` blocks, and priced with the execution price of the swaps,
+//! in token 1 per token 0 adjusted for the token decimals.
+
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::{
+    primitives::{Address, I256, U256},
+    rpc::types::eth::Log,
+    sol_types::SolEvent,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amm::{uniswap_v2::IUniswapV2Pair, uniswap_v3::IUniswapV3Pool, AutomatedMarketMaker, AMM},
+    core::price::amounts_to_price,
+    errors::EventLogError,
+};
+
+/// A swap decoded from a Uniswap V2 or V3 Swap log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Swap {
+    pub pool: Address,
+    pub block_number: u64,
+    /// Change of the token 0 balance of the pool, positive when token 0 is swapped in.
+    pub amount_0: I256,
+    /// Change of the token 1 balance of the pool, positive when token 1 is swapped in.
+    pub amount_1: I256,
+}
+
+impl Swap {
+    /// Decodes a Uniswap V2 or V3 Swap log, returning `None` for other logs.
+    pub fn decode_log(log: &Log) -> Result<Option<Self>, EventLogError> {
+        let Some(&event_signature) = log.topics().first() else {
+            return Ok(None);
+        };
+        let block_number = log
+            .block_number
+            .ok_or(EventLogError::LogBlockNumberNotFound)?;
+
+        let (amount_0, amount_1) = if event_signature == IUniswapV2Pair::Swap::SIGNATURE_HASH {
+            let swap_event = IUniswapV2Pair::Swap::decode_log(log.as_ref(), true)?;
+            (
+                I256::from_raw(swap_event.amount0In) - I256::from_raw(swap_event.amount0Out),
+                I256::from_raw(swap_event.amount1In) - I256::from_raw(swap_event.amount1Out),
+            )
+        } else if event_signature == IUniswapV3Pool::Swap::SIGNATURE_HASH {
+            let swap_event = IUniswapV3Pool::Swap::decode_log(log.as_ref(), true)?;
+            (swap_event.amount0, swap_event.amount1)
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            pool: log.address(),
+            block_number,
+            amount_0,
+            amount_1,
+        }))
+    }
+
+    /// Returns the execution price of the swap in token 1 per token 0, adjusted for the token decimals.
+    pub fn price(&self, token_0_decimals: u8, token_1_decimals: u8) -> f64 {
+        amounts_to_price(
+            self.amount_0.unsigned_abs(),
+            self.amount_1.unsigned_abs(),
+            token_0_decimals,
+            token_1_decimals,
+        )
+    }
+}
+
+/// Price candle and volume of a pool over `interval` blocks starting at `start_block`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub start_block: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Amount of token 0 swapped in either direction.
+    pub volume_0: U256,
+    /// Amount of token 1 swapped in either direction.
+    pub volume_1: U256,
+    pub swaps: u64,
+}
+
+impl Candle {
+    fn new(start_block: u64, price: f64) -> Self {
+        Self {
+            start_block,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume_0: U256::ZERO,
+            volume_1: U256::ZERO,
+            swaps: 0,
+        }
+    }
+}
+
+/// Candles of a pool, keyed by their start block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandleSeries {
+    pub interval: u64,
+    pub token_0_decimals: u8,
+    pub token_1_decimals: u8,
+    pub candles: BTreeMap<u64, Candle>,
+}
+
+impl CandleSeries {
+    /// Creates an empty series with candles of `interval` blocks, raising a zero interval to one.
+    pub fn new(interval: u64, token_0_decimals: u8, token_1_decimals: u8) -> Self {
+        Self {
+            interval: interval.max(1),
+            token_0_decimals,
+            token_1_decimals,
+            candles: BTreeMap::new(),
+        }
+    }
+
+    /// Creates an empty series for the tokens of `amm`, returns `None` if the AMM does not emit Swap logs.
+    pub fn for_amm(amm: &AMM, interval: u64) -> Option<Self> {
+        match amm {
+            AMM::UniswapV2Pool(pool) => Some(Self::new(
+                interval,
+                pool.token_a_decimals,
+                pool.token_b_decimals,
+            )),
+            AMM::UniswapV3Pool(pool) => Some(Self::new(
+                interval,
+                pool.token_a_decimals,
+                pool.token_b_decimals,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Folds `swap` into its candle. Swaps must be applied in order.
+    pub fn apply(&mut self, swap: &Swap) {
+        let start_block = swap.block_number - swap.block_number % self.interval;
+        let price = swap.price(self.token_0_decimals, self.token_1_decimals);
+
+        let candle = self
+            .candles
+            .entry(start_block)
+            .or_insert_with(|| Candle::new(start_block, price));
+
+        candle.high = candle.high.max(price);
+        candle.low = candle.low.min(price);
+        candle.close = price;
+        candle.volume_0 += swap.amount_0.unsigned_abs();
+        candle.volume_1 += swap.amount_1.unsigned_abs();
+        candle.swaps += 1;
+    }
+
+    /// Returns the volume of token 0 and token 1 per candle start block, with zero volume for intervals without swaps
+    /// between the first and last candles.
+    pub fn volume_series(&self) -> Vec<(u64, U256, U256)> {
+        let (Some(first), Some(last)) = (
+            self.candles.keys().next().copied(),
+            self.candles.keys().next_back().copied(),
+        ) else {
+            return vec![];
+        };
+
+        (first..=last)
+            .step_by(self.interval as usize)
+            .map(|start_block| match self.candles.get(&start_block) {
+                Some(candle) => (start_block, candle.volume_0, candle.volume_1),
+                None => (start_block, U256::ZERO, U256::ZERO),
+            })
+            .collect()
+    }
+}
+
+/// Folds the Swap logs of `amms` into candle series of `interval` blocks, keyed by pool address.
+///
+/// `logs` must be ordered, logs of other addresses and events are skipped.
+pub fn fold_swap_logs(
+    amms: &[AMM],
+    logs: &[Log],
+    interval: u64,
+) -> Result<HashMap<Address, CandleSeries>, EventLogError> {
+    let mut series: HashMap<Address, CandleSeries> = amms
+        .iter()
+        .filter_map(|amm| {
+            CandleSeries::for_amm(amm, interval).map(|series| (amm.address(), series))
+        })
+        .collect();
+
+    for log in logs {
+        if !series.contains_key(&log.address()) {
+            continue;
+        }
+
+        if let Some(swap) = Swap::decode_log(log)? {
+            if let Some(series) = series.get_mut(&swap.pool) {
+                series.apply(&swap);
+            }
+        }
+    }
+
+    Ok(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog, I256, U256},
+        rpc::types::eth::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::amm::{
+        uniswap_v2::{IUniswapV2Pair, UniswapV2Pool},
+        AMM,
+    };
+
+    use super::{fold_swap_logs, Swap};
+
+    fn swap_log(address: Address, block_number: u64, amount_0_in: u64, amount_1_out: u64) -> Log {
+        let event = IUniswapV2Pair::Swap {
+            sender: Address::ZERO,
+            amount0In: U256::from(amount_0_in),
+            amount1In: U256::ZERO,
+            amount0Out: U256::ZERO,
+            amount1Out: U256::from(amount_1_out),
+            to: Address::ZERO,
+        };
+
+        Log {
+            inner: PrimitiveLog {
+                address,
+                data: event.encode_log_data(),
+            },
+            block_number: Some(block_number),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fold_swap_logs() {
+        let pool = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let amms = vec![AMM::UniswapV2Pool(UniswapV2Pool {
+            address: pool,
+            token_a_decimals: 18,
+            token_b_decimals: 18,
+            ..Default::default()
+        })];
+
+        let logs = vec![
+            swap_log(pool, 100, 10, 20),
+            swap_log(pool, 101, 10, 40),
+            swap_log(pool, 105, 10, 10),
+            swap_log(Address::ZERO, 105, 1, 1000),
+            swap_log(pool, 125, 10, 30),
+        ];
+
+        let swap = Swap::decode_log(&logs[0]).unwrap().unwrap();
+        assert_eq!(swap.amount_0, I256::from_raw(U256::from(10)));
+        assert_eq!(swap.amount_1, -I256::from_raw(U256::from(20)));
+
+        let series = &fold_swap_logs(&amms, &logs, 10).unwrap()[&pool];
+        assert_eq!(series.candles.len(), 2);
+
+        let candle = series.candles[&100];
+        assert_eq!(
+            (candle.open, candle.high, candle.low, candle.close),
+            (2.0, 4.0, 1.0, 1.0)
+        );
+        assert_eq!(candle.volume_0, U256::from(30));
+        assert_eq!(candle.volume_1, U256::from(70));
+        assert_eq!(candle.swaps, 3);
+
+        assert_eq!(
+            series.volume_series(),
+            vec![
+                (100, U256::from(30), U256::from(70)),
+                (110, U256::ZERO, U256::ZERO),
+                (120, U256::from(10), U256::from(30)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Replays historical logs over a set of AMMs block by block, with the same log handling as live syncing.
+//!
+//! ```ignore
+//! let mut amms = vec![pool];
+//! sync::populate_data_at_block(&mut amms, creation_block, from_block - 1, &config, provider.clone()).await?;
+//!
+//! backtest::backtest(amms, from_block, to_block, &config, provider, |block| {
+//!     for address in block.updated_amms {
+//!         let amm = &block.state[address];
+//!         // Evaluate the strategy against the state at the end of `block.block_number`
+//!     }
+//! })
+//! .await?;
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use alloy::{
+    network::Network,
+    primitives::{Address, B256},
+    providers::Provider,
+    rpc::types::eth::{Filter, Log},
+    transports::Transport,
+};
+use futures::StreamExt;
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    errors::{AMMError, EventLogError},
+    sync::config::SyncConfig,
+};
+
+/// State of the AMMs at the end of a block.
+#[derive(Debug, Clone, Copy)]
+pub struct BacktestBlock<'a> {
+    pub block_number: u64,
+    pub state: &'a HashMap<Address, AMM>,
+    /// AMMs updated by the logs of the block, in the order of their first log.
+    pub updated_amms: &'a [Address],
+}
+
+/// Replays the logs of `amms` from `from_block` to `to_block` inclusive, invoking `on_block` with the state at the
+/// end of every block of the range, including blocks without logs.
+///
+/// `amms` must hold their state as of `from_block - 1`, e.g. from [`crate::sync::populate_data_at_block`], and the
+/// provider must serve logs for the whole range. Only the logs of `amms` and of the singleton contracts of their pools
+/// are fetched, in ranges of `config.step` blocks, up to `config.max_concurrency` ranges at a time. Returns the state
+/// at the end of `to_block`.
+pub async fn backtest<T, N, P, F>(
+    amms: Vec<AMM>,
+    from_block: u64,
+    to_block: u64,
+    config: &SyncConfig,
+    provider: Arc<P>,
+    mut on_block: F,
+) -> Result<HashMap<Address, AMM>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+    F: FnMut(BacktestBlock<'_>),
+{
+    let mut event_signatures: Vec<B256> = vec![];
+    for amm in amms.iter() {
+        for event_signature in amm.sync_on_event_signatures() {
+            if !event_signatures.contains(&event_signature) {
+                event_signatures.push(event_signature);
+            }
+        }
+    }
+
+    // Pools of singleton contracts have their logs emitted by the contract
+    let addresses = amms
+        .iter()
+        .map(|amm| match amm {
+            AMM::AmbientPool(pool) => pool.dex,
+            AMM::BancorV3Pool(pool) => pool.network,
+            _ => amm.address(),
+        })
+        .collect::<HashSet<Address>>()
+        .into_iter()
+        .collect::<Vec<Address>>();
+
+    let mut state: HashMap<Address, AMM> =
+        amms.into_iter().map(|amm| (amm.address(), amm)).collect();
+
+    let mut block_ranges = vec![];
+    let mut start_block = from_block;
+    while start_block <= to_block {
+        let end_block = (start_block + config.step - 1).min(to_block);
+        block_ranges.push((start_block, end_block));
+        start_block += config.step;
+    }
+
+    let filter = Filter::new()
+        .address(addresses)
+        .event_signature(event_signatures);
+    let retry = config.retry;
+    let mut futures = futures::stream::iter(block_ranges.into_iter().map(|(start, end)| {
+        let provider = provider.clone();
+        let filter = filter.clone().from_block(start).to_block(end);
+
+        async move {
+            let logs = retry.retry(|| provider.get_logs(&filter)).await;
+            logs.map(|logs| (start, end, logs))
+        }
+    }))
+    .buffered(config.max_concurrency);
+
+    while let Some(result) = futures.next().await {
+        let (start, end, logs) = result.map_err(AMMError::TransportError)?;
+        replay_logs(&mut state, logs, start, end, &mut on_block)?;
+    }
+
+    Ok(state)
+}
+
+/// Applies `logs`, ordered by block, to the AMMs in `state`, invoking `on_block` at the end of every block from
+/// `from_block` to `to_block` inclusive.
+///
+/// Logs of addresses that are not in `state` are skipped.
+pub fn replay_logs<F>(
+    state: &mut HashMap<Address, AMM>,
+    logs: Vec<Log>,
+    from_block: u64,
+    to_block: u64,
+    mut on_block: F,
+) -> Result<(), EventLogError>
+where
+    F: FnMut(BacktestBlock<'_>),
+{
+    let mut logs = logs.into_iter().peekable();
+
+    for block_number in from_block..=to_block {
+        let mut updated_amms = vec![];
+        let mut updated_amms_set = HashSet::new();
+
+        while let Some(log) = logs.peek() {
+            let log_block_number = log
+                .block_number
+                .ok_or(EventLogError::LogBlockNumberNotFound)?;
+            if log_block_number > block_number {
+                break;
+            }
+
+            let log = logs.next().expect("log was peeked");
+            if let Some(amm) = state.get_mut(&log.address()) {
+                if updated_amms_set.insert(log.address()) {
+                    updated_amms.push(log.address());
+                }
+
+                amm.sync_from_log(log)?;
+            }
+        }
+
+        on_block(BacktestBlock {
+            block_number,
+            state,
+            updated_amms: &updated_amms,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog},
+        rpc::types::eth::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::amm::{
+        uniswap_v2::{IUniswapV2Pair, UniswapV2Pool},
+        AMM,
+    };
+
+    use super::replay_logs;
+
+    fn sync_log(address: Address, block_number: u64, reserve_0: u128, reserve_1: u128) -> Log {
+        let event = IUniswapV2Pair::Sync {
+            reserve0: reserve_0,
+            reserve1: reserve_1,
+        };
+
+        Log {
+            inner: PrimitiveLog {
+                address,
+                data: event.encode_log_data(),
+            },
+            block_number: Some(block_number),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_replay_logs() {
+        let pool_a = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let pool_b = address!("397FF1542f962076d0BFE58eA045FfA2d347ACa0");
+        let mut state: HashMap<Address, AMM> = [pool_a, pool_b]
+            .into_iter()
+            .map(|address| {
+                let pool = AMM::UniswapV2Pool(UniswapV2Pool {
+                    address,
+                    ..Default::default()
+                });
+                (address, pool)
+            })
+            .collect();
+
+        let logs = vec![
+            sync_log(pool_a, 11, 100, 200),
+            sync_log(pool_b, 11, 300, 400),
+            sync_log(pool_a, 11, 110, 190),
+            // Not in the state
+            sync_log(Address::ZERO, 12, 1, 1),
+            sync_log(pool_b, 13, 310, 390),
+        ];
+
+        let mut blocks = vec![];
+        replay_logs(&mut state, logs, 10, 14, |block| {
+            let reserves = |address| match &block.state[&address] {
+                AMM::UniswapV2Pool(pool) => (pool.reserve_0, pool.reserve_1),
+                _ => unreachable!(),
+            };
+
+            blocks.push((
+                block.block_number,
+                block.updated_amms.to_vec(),
+                reserves(pool_a),
+                reserves(pool_b),
+            ));
+        })
+        .unwrap();
+
+        assert_eq!(
+            blocks,
+            vec![
+                (10, vec![], (0, 0), (0, 0)),
+                (11, vec![pool_a, pool_b], (110, 190), (300, 400)),
+                (12, vec![], (110, 190), (300, 400)),
+                (13, vec![pool_b], (110, 190), (310, 390)),
+                (14, vec![], (110, 190), (310, 390)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Command line interface to discover, sync and quote AMMs.
+//!
+//! ```sh
+//! amms-cli discover --factory 0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f --from-block 10000835 --checkpoint state.json
+//! amms-cli sync --checkpoint state.json
+//! amms-cli quote --pool 0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc --amount 1000000 --checkpoint state.json
+//! amms-cli watch --checkpoint state.json
+//! ```
+
+use std::sync::Arc;
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::ProviderBuilder,
+    rpc::client::WsConnect,
+};
+use amms::{
+    amm::{
+        factory::Factory,
+        uniswap_v2::{factory::UniswapV2Factory, UniswapV2Pool},
+        uniswap_v3::{factory::UniswapV3Factory, UniswapV3Pool},
+        AutomatedMarketMaker, AMM,
+    },
+    state_space::StateSpaceManager,
+    sync::{self, checkpoint},
+};
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Debug, Parser)]
+#[command(name = "amms-cli", version, about = "Discover, sync and quote AMMs")]
+struct Cli {
+    /// HTTP RPC endpoint.
+    #[arg(long, env = "ETHEREUM_RPC_ENDPOINT", global = true)]
+    rpc_url: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Syncs all AMMs deployed by a factory and writes them to a checkpoint.
+    Discover {
+        /// Address of the factory.
+        #[arg(long)]
+        factory: Address,
+        /// Protocol of the factory.
+        #[arg(long, value_enum, default_value_t = Protocol::UniswapV2)]
+        protocol: Protocol,
+        /// Block at which the factory was deployed.
+        #[arg(long)]
+        from_block: u64,
+        /// Fee of the Uniswap V2 pairs, 300 is 0.3%.
+        #[arg(long, default_value_t = 300)]
+        fee: u32,
+        /// Path of the checkpoint to write.
+        #[arg(long, default_value = "state.json")]
+        checkpoint: String,
+        /// Number of blocks per `eth_getLogs` request.
+        #[arg(long, default_value_t = 10_000)]
+        step: u64,
+    },
+    /// Syncs the AMMs of a checkpoint to the latest block, adding the AMMs deployed since.
+    Sync {
+        /// Path of the checkpoint to update.
+        #[arg(long)]
+        checkpoint: String,
+        /// Number of blocks per `eth_getLogs` request.
+        #[arg(long, default_value_t = 10_000)]
+        step: u64,
+    },
+    /// Quotes a swap in a pool.
+    Quote {
+        /// Address of the pool.
+        #[arg(long)]
+        pool: Address,
+        /// Amount in, in the smallest unit of the input token.
+        #[arg(long)]
+        amount: U256,
+        /// Input token, token 0 of the pool by default.
+        #[arg(long)]
+        token_in: Option<Address>,
+        /// Checkpoint to read the pool from, the pool is fetched from the RPC endpoint otherwise.
+        #[arg(long)]
+        checkpoint: Option<String>,
+        /// Protocol of the pool, when fetched from the RPC endpoint.
+        #[arg(long, value_enum, default_value_t = Protocol::UniswapV2)]
+        protocol: Protocol,
+        /// Fee of a Uniswap V2 pool, 300 is 0.3%.
+        #[arg(long, default_value_t = 300)]
+        fee: u32,
+        /// Block at which a Uniswap V3 pool was created, to load its ticks from.
+        #[arg(long, default_value_t = 0)]
+        from_block: u64,
+    },
+    /// Streams the prices of the AMMs of a checkpoint as they change.
+    Watch {
+        /// Path of the checkpoint to watch.
+        #[arg(long)]
+        checkpoint: String,
+        /// Websocket RPC endpoint.
+        #[arg(long, env = "ETHEREUM_WS_ENDPOINT")]
+        ws_url: String,
+        /// Only print the prices of these AMMs.
+        #[arg(long, value_delimiter = ',')]
+        pools: Vec<Address>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Protocol {
+    UniswapV2,
+    UniswapV3,
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Discover {
+            factory,
+            protocol,
+            from_block,
+            fee,
+            checkpoint,
+            step,
+        } => {
+            let factory = match protocol {
+                Protocol::UniswapV2 => {
+                    Factory::UniswapV2Factory(UniswapV2Factory::new(factory, from_block, fee))
+                }
+                Protocol::UniswapV3 => {
+                    Factory::UniswapV3Factory(UniswapV3Factory::new(factory, from_block))
+                }
+            };
+
+            let (amms, block_number) = sync::sync_amms(
+                vec![factory],
+                Arc::new(ProviderBuilder::new().on_http(rpc_url(cli.rpc_url)?.parse()?)),
+                Some(&checkpoint),
+                step,
+            )
+            .await?;
+
+            println!(
+                "Synced {} AMMs to block {block_number}, written to {checkpoint}",
+                amms.len()
+            );
+        }
+
+        Command::Sync { checkpoint, step } => {
+            let (factories, amms) = checkpoint::sync_amms_from_checkpoint(
+                &checkpoint,
+                step,
+                Arc::new(ProviderBuilder::new().on_http(rpc_url(cli.rpc_url)?.parse()?)),
+            )
+            .await?;
+
+            println!(
+                "Synced {} AMMs of {} factories, written to {checkpoint}",
+                amms.len(),
+                factories.len()
+            );
+        }
+
+        Command::Quote {
+            pool,
+            amount,
+            token_in,
+            checkpoint,
+            protocol,
+            fee,
+            from_block,
+        } => {
+            let amm = if let Some(checkpoint) = checkpoint {
+                checkpoint::deconstruct_checkpoint(&checkpoint)?
+                    .0
+                    .into_iter()
+                    .find(|amm| amm.address() == pool)
+                    .ok_or_else(|| eyre::eyre!("{pool} is not in {checkpoint}"))?
+            } else {
+                let provider =
+                    Arc::new(ProviderBuilder::new().on_http(rpc_url(cli.rpc_url)?.parse()?));
+                match protocol {
+                    Protocol::UniswapV2 => AMM::UniswapV2Pool(
+                        UniswapV2Pool::new_from_address(pool, fee, provider).await?,
+                    ),
+                    Protocol::UniswapV3 => AMM::UniswapV3Pool(
+                        UniswapV3Pool::new_from_address(pool, from_block, provider).await?,
+                    ),
+                }
+            };
+
+            let token_in = token_in.unwrap_or(amm.tokens()[0]);
+            let token_out = amm.get_token_out(token_in);
+            let amount_out = amm.simulate_swap(token_in, amount)?;
+
+            println!("{amount} {token_in} -> {amount_out} {token_out}");
+            println!("Spot price: {}", amm.calculate_price(token_in)?);
+        }
+
+        Command::Watch {
+            checkpoint,
+            ws_url,
+            pools,
+        } => {
+            let (amms, block_number) = checkpoint::deconstruct_checkpoint(&checkpoint)?;
+            let provider = Arc::new(ProviderBuilder::new().on_ws(WsConnect::new(ws_url)).await?);
+
+            let state_space_manager =
+                StateSpaceManager::new(amms, block_number, 100, 100, provider);
+            let (mut rx, _join_handles) = state_space_manager.subscribe_state_changes().await?;
+
+            while let Some(state_changes) = rx.recv().await {
+                for address in state_changes {
+                    if !pools.is_empty() && !pools.contains(&address) {
+                        continue;
+                    }
+
+                    if let Some(amm) = state_space_manager.get_amm(address).await {
+                        // A pool without a price, e.g. emptied, does not stop the watch
+                        let base_token = amm.tokens()[0];
+                        match amm.calculate_price(base_token) {
+                            Ok(price) => println!("{address}: {price}"),
+                            Err(error) => {
+                                tracing::warn!(?error, ?address, "Could not calculate the price")
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn rpc_url(rpc_url: Option<String>) -> eyre::Result<String> {
+    rpc_url
+        .ok_or_else(|| eyre::eyre!("Missing RPC endpoint, set --rpc-url or ETHEREUM_RPC_ENDPOINT"))
+}
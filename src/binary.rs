@@ -0,0 +1,156 @@
+//! Binary (de)serialization of AMMs, state spaces and checkpoints with bincode.
+//!
+//! Every payload is prefixed with a header holding [`MAGIC`] and [`FORMAT_VERSION`], so that data written by an
+//! incompatible version of the crate is rejected instead of being misread.
+
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{errors::CheckpointError, sync::checkpoint::Checkpoint};
+
+/// Magic bytes at the start of every binary payload.
+pub const MAGIC: [u8; 4] = *b"AMMS";
+
+/// Version of the binary format, bumped on any change to the serialized layout of the AMMs.
+pub const FORMAT_VERSION: u32 = 4;
+
+const HEADER_LEN: usize = MAGIC.len() + std::mem::size_of::<u32>();
+
+/// Serializes `value` into a versioned binary payload.
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, CheckpointError> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bincode::serialize_into(&mut bytes, value)?;
+
+    Ok(bytes)
+}
+
+/// Deserializes a value from a binary payload written by [`to_bytes`].
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CheckpointError> {
+    let version = read_header(bytes)?;
+    if version != FORMAT_VERSION {
+        return Err(CheckpointError::UnsupportedBinaryVersion(version));
+    }
+
+    Ok(bincode::deserialize(&bytes[HEADER_LEN..])?)
+}
+
+/// Returns the format version of a binary payload.
+pub fn read_header(bytes: &[u8]) -> Result<u32, CheckpointError> {
+    if bytes.len() < HEADER_LEN || bytes[..MAGIC.len()] != MAGIC {
+        return Err(CheckpointError::InvalidBinaryHeader);
+    }
+
+    let version = bytes[MAGIC.len()..HEADER_LEN]
+        .try_into()
+        .map_err(|_| CheckpointError::InvalidBinaryHeader)?;
+
+    Ok(u32::from_le_bytes(version))
+}
+
+/// Writes a checkpoint to `path` in the binary format.
+pub fn write_checkpoint<P: AsRef<Path>>(
+    checkpoint: &Checkpoint,
+    path: P,
+) -> Result<(), CheckpointError> {
+    std::fs::write(path, to_bytes(checkpoint)?)?;
+    Ok(())
+}
+
+/// Reads a checkpoint written by [`write_checkpoint`] from `path`.
+pub fn read_checkpoint<P: AsRef<Path>>(path: P) -> Result<Checkpoint, CheckpointError> {
+    from_bytes(&std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use alloy::primitives::{address, U256};
+
+    use crate::{
+        amm::{
+            metadata::{AmmMetadata, AmmMetadataMap},
+            uniswap_v2::UniswapV2Pool,
+            uniswap_v3::{Info, UniswapV3Pool},
+            AMM,
+        },
+        errors::CheckpointError,
+        sync::checkpoint::Checkpoint,
+    };
+
+    use super::{from_bytes, read_header, to_bytes, FORMAT_VERSION, MAGIC};
+
+    #[test]
+    fn test_round_trip() {
+        let mut ticks = BTreeMap::new();
+        ticks.insert(-887270, Info::new(100, 100, true));
+        ticks.insert(887270, Info::new(100, -100, true));
+
+        let mut metadata = AmmMetadata::new();
+        metadata.tag("stable");
+        metadata.set_score("depth", 1.5);
+
+        let checkpoint = Checkpoint::new(
+            1_700_000_000,
+            19_000_000,
+            vec![],
+            vec![
+                AMM::UniswapV2Pool(UniswapV2Pool {
+                    address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+                    reserve_0: 1_000_000,
+                    reserve_1: 2_000_000,
+                    fee: 300,
+                    ..Default::default()
+                }),
+                AMM::UniswapV3Pool(UniswapV3Pool {
+                    address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+                    sqrt_price: U256::from(1) << 96,
+                    liquidity: 100,
+                    ticks,
+                    ..Default::default()
+                }),
+            ],
+        )
+        .with_metadata(AmmMetadataMap::from([(
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            metadata,
+        )]));
+
+        let bytes = to_bytes(&checkpoint).unwrap();
+        assert_eq!(read_header(&bytes).unwrap(), FORMAT_VERSION);
+
+        let decoded: Checkpoint = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.block_number, checkpoint.block_number);
+        assert_eq!(decoded.metadata, checkpoint.metadata);
+        assert_eq!(
+            serde_json::to_string(&decoded.amms).unwrap(),
+            serde_json::to_string(&checkpoint.amms).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_header() {
+        let bytes = to_bytes(&42_u64).unwrap();
+
+        // JSON checkpoints and truncated payloads are not misread
+        assert!(matches!(
+            from_bytes::<u64>(b"{\"timestamp\":0}"),
+            Err(CheckpointError::InvalidBinaryHeader)
+        ));
+        assert!(matches!(
+            from_bytes::<u64>(&bytes[..3]),
+            Err(CheckpointError::InvalidBinaryHeader)
+        ));
+
+        let mut future_version = MAGIC.to_vec();
+        future_version.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        future_version.extend_from_slice(&bytes[8..]);
+        assert!(matches!(
+            from_bytes::<u64>(&future_version),
+            Err(CheckpointError::UnsupportedBinaryVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+//! USD prices of tokens from Chainlink aggregator feeds.
+//!
+//! Feed prices do not move with on-chain stable pools, so they denominate the value filter, TVL and profits in USD
+//! even when those pools are imbalanced. Answers that are not positive or older than the feed's maximum staleness
+//! are rejected.
+
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::{
+    network::Network,
+    primitives::{address, Address, I256},
+    providers::Provider,
+    rpc::types::eth::{BlockId, BlockNumberOrTag},
+    sol,
+    transports::Transport,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ChainlinkError;
+
+/// ETH/USD feed on Ethereum mainnet.
+pub const ETH_USD_FEED: Address = address!("5f4eC3Df9cbd43714FE2740F5E3616155c5b8419");
+/// BTC/USD feed on Ethereum mainnet.
+pub const BTC_USD_FEED: Address = address!("F4030086522a5bEEa4988F8cA5B36dbC97BeE88c");
+/// USDC/USD feed on Ethereum mainnet.
+pub const USDC_USD_FEED: Address = address!("8fFfFfd4AfB6115b954Bd326cbe7B4BA576818f6");
+/// USDT/USD feed on Ethereum mainnet.
+pub const USDT_USD_FEED: Address = address!("3E7d1eAB13ad0104d2750B8863b489D65364e32D");
+/// DAI/USD feed on Ethereum mainnet.
+pub const DAI_USD_FEED: Address = address!("Aed0c38402a5d19df6E4c03F4E2DceD6e29c1ee9");
+
+sol! {
+    /// Interface of the Chainlink aggregators
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract IAggregatorV3 {
+        function decimals() external view returns (uint8);
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    }
+}
+
+/// Latest answer of a feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedAnswer {
+    pub answer: I256,
+    pub decimals: u8,
+    /// Unix timestamp in seconds of the round of the answer.
+    pub updated_at: u64,
+}
+
+impl FeedAnswer {
+    /// Returns the answer adjusted for the feed decimals.
+    pub fn price(&self) -> f64 {
+        let answer: f64 = self.answer.to_string().parse().unwrap_or(f64::NAN);
+        answer / 10f64.powi(self.decimals as i32)
+    }
+
+    /// Returns whether the answer is older than `max_staleness` seconds at `timestamp`.
+    pub fn is_stale(&self, timestamp: u64, max_staleness: u64) -> bool {
+        timestamp.saturating_sub(self.updated_at) > max_staleness
+    }
+}
+
+/// Feed pricing `token` in USD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainlinkFeed {
+    pub token: Address,
+    pub aggregator: Address,
+    /// Maximum age in seconds of an answer, usually the heartbeat of the feed.
+    pub max_staleness: u64,
+}
+
+impl ChainlinkFeed {
+    pub fn new(token: Address, aggregator: Address, max_staleness: u64) -> Self {
+        Self {
+            token,
+            aggregator,
+            max_staleness,
+        }
+    }
+}
+
+/// USD prices of the tokens of the configured feeds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainlinkOracle {
+    feeds: HashMap<Address, ChainlinkFeed>,
+}
+
+impl ChainlinkOracle {
+    pub fn new(feeds: impl IntoIterator<Item = ChainlinkFeed>) -> Self {
+        Self {
+            feeds: feeds.into_iter().map(|feed| (feed.token, feed)).collect(),
+        }
+    }
+
+    /// Returns an oracle with the Ethereum mainnet feeds of WETH, WBTC, USDC, USDT and DAI.
+    pub fn mainnet() -> Self {
+        Self::new([
+            ChainlinkFeed::new(
+                address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+                ETH_USD_FEED,
+                3600,
+            ),
+            ChainlinkFeed::new(
+                address!("2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599"),
+                BTC_USD_FEED,
+                3600,
+            ),
+            ChainlinkFeed::new(
+                address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+                USDC_USD_FEED,
+                86400,
+            ),
+            ChainlinkFeed::new(
+                address!("dAC17F958D2ee523a2206206994597C13D831ec7"),
+                USDT_USD_FEED,
+                86400,
+            ),
+            ChainlinkFeed::new(
+                address!("6B175474E89094C44Da98b954EedeAC495271d0F"),
+                DAI_USD_FEED,
+                3600,
+            ),
+        ])
+    }
+
+    /// Adds or replaces the feed of `feed.token`.
+    pub fn insert(&mut self, feed: ChainlinkFeed) -> Option<ChainlinkFeed> {
+        self.feeds.insert(feed.token, feed)
+    }
+
+    pub fn feed(&self, token: &Address) -> Option<&ChainlinkFeed> {
+        self.feeds.get(token)
+    }
+
+    pub fn feeds(&self) -> impl Iterator<Item = &ChainlinkFeed> {
+        self.feeds.values()
+    }
+
+    /// Returns the USD price of `token` at `block_number` or the latest block if `None`, rejecting answers older
+    /// than the feed's maximum staleness at `timestamp`.
+    pub async fn usd_price<T, N, P>(
+        &self,
+        token: Address,
+        timestamp: u64,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<f64, ChainlinkError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let feed = self
+            .feeds
+            .get(&token)
+            .ok_or(ChainlinkError::FeedNotFound(token))?;
+        let answer = latest_answer(feed.aggregator, block_number, provider).await?;
+
+        check_answer(feed, &answer, timestamp)?;
+
+        Ok(answer.price())
+    }
+
+    /// Returns the USD prices of the tokens of all feeds, see [`ChainlinkOracle::usd_price`].
+    pub async fn usd_prices<T, N, P>(
+        &self,
+        timestamp: u64,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<HashMap<Address, f64>, ChainlinkError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut prices = HashMap::with_capacity(self.feeds.len());
+
+        for feed in self.feeds.values() {
+            let answer = latest_answer(feed.aggregator, block_number, provider.clone()).await?;
+            check_answer(feed, &answer, timestamp)?;
+            prices.insert(feed.token, answer.price());
+        }
+
+        Ok(prices)
+    }
+}
+
+/// Returns the latest answer of `aggregator` at `block_number` or the latest block if `None`.
+pub async fn latest_answer<T, N, P>(
+    aggregator: Address,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<FeedAnswer, ChainlinkError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let block_id = block_number.map_or(BlockId::Number(BlockNumberOrTag::Latest), BlockId::from);
+    let feed = IAggregatorV3::new(aggregator, provider);
+
+    let decimals = feed.decimals().block(block_id).call().await?._0;
+    let round = feed.latestRoundData().block(block_id).call().await?;
+
+    Ok(FeedAnswer {
+        answer: round.answer,
+        decimals,
+        updated_at: round.updatedAt.saturating_to(),
+    })
+}
+
+fn check_answer(
+    feed: &ChainlinkFeed,
+    answer: &FeedAnswer,
+    timestamp: u64,
+) -> Result<(), ChainlinkError> {
+    if !answer.answer.is_positive() {
+        return Err(ChainlinkError::InvalidAnswer(
+            feed.aggregator,
+            answer.answer,
+        ));
+    }
+
+    if answer.is_stale(timestamp, feed.max_staleness) {
+        return Err(ChainlinkError::StaleAnswer(
+            feed.aggregator,
+            answer.updated_at,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, I256, U256};
+
+    use crate::errors::ChainlinkError;
+
+    use super::{check_answer, ChainlinkFeed, ChainlinkOracle, FeedAnswer, ETH_USD_FEED};
+
+    #[test]
+    fn test_feed_answer() {
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let feed = ChainlinkOracle::mainnet().feed(&weth).copied().unwrap();
+        assert_eq!(feed.aggregator, ETH_USD_FEED);
+
+        let answer = FeedAnswer {
+            answer: I256::from_raw(U256::from(312_345_000_000_u64)),
+            decimals: 8,
+            updated_at: 1_700_000_000,
+        };
+        assert_eq!(answer.price(), 3123.45);
+        assert!(check_answer(&feed, &answer, 1_700_003_600).is_ok());
+        assert!(matches!(
+            check_answer(&feed, &answer, 1_700_003_601),
+            Err(ChainlinkError::StaleAnswer(ETH_USD_FEED, 1_700_000_000))
+        ));
+
+        let negative = FeedAnswer {
+            answer: I256::MINUS_ONE,
+            ..answer
+        };
+        assert!(matches!(
+            check_answer(&feed, &negative, 1_700_000_000),
+            Err(ChainlinkError::InvalidAnswer(ETH_USD_FEED, _))
+        ));
+
+        let mut oracle = ChainlinkOracle::default();
+        assert!(oracle
+            .insert(ChainlinkFeed::new(weth, ETH_USD_FEED, 60))
+            .is_none());
+        assert_eq!(oracle.feeds().count(), 1);
+    }
+}
@@ -0,0 +1,482 @@
+//! Chain presets: canonical factories, reference tokens and finality parameters per chain id.
+//!
+//! ```ignore
+//! let chain = ChainConfig::mainnet();
+//! let (amms, block_number) = sync::sync_chain(&chain, provider, None).await?;
+//! ```
+//!
+//! On Arbitrum, `block.number` in contracts returns an approximate L1 block number while logs and RPC methods use L2
+//! block numbers, which are produced every 250ms. Presets and [`ChainConfig::log_step`] are in L2 blocks, use
+//! [`arbitrum_l1_block_number`] and [`arbitrum_l2_block_number`] to map between the two.
+
+use std::time::Duration;
+
+use alloy::primitives::{address, Address};
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+#[cfg(feature = "provider")]
+use serde_json::Value;
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+#[cfg(feature = "provider")]
+use crate::{
+    amm::{
+        factory::Factory, uniswap_v2::factory::UniswapV2Factory,
+        uniswap_v3::factory::UniswapV3Factory,
+    },
+    errors::AMMError,
+    sync::config::{BatchStrategy, SyncConfig},
+};
+
+pub const MAINNET_CHAIN_ID: u64 = 1;
+pub const OPTIMISM_CHAIN_ID: u64 = 10;
+pub const BSC_CHAIN_ID: u64 = 56;
+pub const POLYGON_CHAIN_ID: u64 = 137;
+pub const BASE_CHAIN_ID: u64 = 8453;
+pub const ARBITRUM_CHAIN_ID: u64 = 42161;
+pub const ZKSYNC_ERA_CHAIN_ID: u64 = 324;
+pub const ZKSYNC_SEPOLIA_CHAIN_ID: u64 = 300;
+
+/// Protocol of a factory preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactoryKind {
+    /// Uniswap V2 style factory, with the fee of its pairs (300 is 0.3%).
+    UniswapV2 {
+        fee: u32,
+    },
+    UniswapV3,
+}
+
+/// A factory deployed on a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FactoryPreset {
+    pub name: &'static str,
+    pub kind: FactoryKind,
+    pub address: Address,
+    pub creation_block: u64,
+}
+
+impl FactoryPreset {
+    pub const fn uniswap_v2(
+        name: &'static str,
+        address: Address,
+        creation_block: u64,
+        fee: u32,
+    ) -> Self {
+        Self {
+            name,
+            kind: FactoryKind::UniswapV2 { fee },
+            address,
+            creation_block,
+        }
+    }
+
+    pub const fn uniswap_v3(name: &'static str, address: Address, creation_block: u64) -> Self {
+        Self {
+            name,
+            kind: FactoryKind::UniswapV3,
+            address,
+            creation_block,
+        }
+    }
+
+    /// Returns the factory to sync AMMs from.
+    #[cfg(feature = "provider")]
+    pub fn factory(&self) -> Factory {
+        match self.kind {
+            FactoryKind::UniswapV2 { fee } => Factory::UniswapV2Factory(UniswapV2Factory::new(
+                self.address,
+                self.creation_block,
+                fee,
+            )),
+            FactoryKind::UniswapV3 => {
+                Factory::UniswapV3Factory(UniswapV3Factory::new(self.address, self.creation_block))
+            }
+        }
+    }
+}
+
+/// Presets of a chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub name: &'static str,
+    /// Canonical factories of the chain.
+    pub factories: Vec<FactoryPreset>,
+    /// Wrapped native token, e.g. WETH on Ethereum and WBNB on BSC.
+    pub wrapped_native: Address,
+    pub weth: Address,
+    pub usdc: Address,
+    /// Number of blocks behind the chain head that are considered final.
+    pub reorg_depth: u64,
+    /// Average time between blocks.
+    pub block_time: Duration,
+    /// Block range of each `eth_getLogs` request, larger on chains with short block times.
+    pub log_step: u64,
+}
+
+impl ChainConfig {
+    /// Returns the presets of `chain_id`, if the chain is known.
+    pub fn from_chain_id(chain_id: u64) -> Option<Self> {
+        match chain_id {
+            MAINNET_CHAIN_ID => Some(Self::mainnet()),
+            OPTIMISM_CHAIN_ID => Some(Self::optimism()),
+            BSC_CHAIN_ID => Some(Self::bsc()),
+            POLYGON_CHAIN_ID => Some(Self::polygon()),
+            BASE_CHAIN_ID => Some(Self::base()),
+            ARBITRUM_CHAIN_ID => Some(Self::arbitrum()),
+            _ => None,
+        }
+    }
+
+    /// Returns the presets of all known chains.
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::mainnet(),
+            Self::optimism(),
+            Self::bsc(),
+            Self::polygon(),
+            Self::base(),
+            Self::arbitrum(),
+        ]
+    }
+
+    pub fn mainnet() -> Self {
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        Self {
+            chain_id: MAINNET_CHAIN_ID,
+            name: "mainnet",
+            factories: vec![
+                FactoryPreset::uniswap_v2(
+                    "Uniswap V2",
+                    address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
+                    10000835,
+                    300,
+                ),
+                FactoryPreset::uniswap_v2(
+                    "SushiSwap",
+                    address!("C0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac"),
+                    10794229,
+                    300,
+                ),
+                FactoryPreset::uniswap_v3(
+                    "Uniswap V3",
+                    address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+                    12369621,
+                ),
+            ],
+            wrapped_native: weth,
+            weth,
+            usdc: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+            reorg_depth: 3,
+            block_time: Duration::from_secs(12),
+            log_step: 100_000,
+        }
+    }
+
+    pub fn optimism() -> Self {
+        let weth = address!("4200000000000000000000000000000000000006");
+        Self {
+            chain_id: OPTIMISM_CHAIN_ID,
+            name: "optimism",
+            factories: vec![FactoryPreset::uniswap_v3(
+                "Uniswap V3",
+                address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+                0,
+            )],
+            wrapped_native: weth,
+            weth,
+            usdc: address!("0b2C639c533813f4Aa9D7837CAf62653d097Ff85"),
+            reorg_depth: 1,
+            block_time: Duration::from_secs(2),
+            log_step: 500_000,
+        }
+    }
+
+    pub fn bsc() -> Self {
+        Self {
+            chain_id: BSC_CHAIN_ID,
+            name: "bsc",
+            factories: vec![
+                FactoryPreset::uniswap_v2(
+                    "PancakeSwap V2",
+                    address!("cA143Ce32Fe78f1f7019d7d551a6402fC5350c73"),
+                    6809737,
+                    250,
+                ),
+                FactoryPreset::uniswap_v3(
+                    "Uniswap V3",
+                    address!("dB1d10011AD0Ff90774D0C6Bb92e5C5c8b4461F7"),
+                    26324014,
+                ),
+            ],
+            wrapped_native: address!("bb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c"),
+            weth: address!("2170Ed0880ac9A755fd29B2688956BD959F933F8"),
+            usdc: address!("8AC76a51cc950d9822D68b83fE1Ad97B32Cd580d"),
+            reorg_depth: 15,
+            block_time: Duration::from_secs(3),
+            log_step: 100_000,
+        }
+    }
+
+    pub fn polygon() -> Self {
+        Self {
+            chain_id: POLYGON_CHAIN_ID,
+            name: "polygon",
+            factories: vec![
+                FactoryPreset::uniswap_v2(
+                    "QuickSwap",
+                    address!("5757371414417b8C6CAad45bAeF941aBc7d3Ab32"),
+                    4931780,
+                    300,
+                ),
+                FactoryPreset::uniswap_v3(
+                    "Uniswap V3",
+                    address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+                    22757547,
+                ),
+            ],
+            wrapped_native: address!("0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270"),
+            weth: address!("7ceB23fD6bC0adD59E62ac25578270cFf1b9f619"),
+            usdc: address!("3c499c542cEF5E3811e1192ce70d8cC03d5c3359"),
+            reorg_depth: 32,
+            block_time: Duration::from_secs(2),
+            log_step: 100_000,
+        }
+    }
+
+    pub fn base() -> Self {
+        let weth = address!("4200000000000000000000000000000000000006");
+        Self {
+            chain_id: BASE_CHAIN_ID,
+            name: "base",
+            factories: vec![
+                FactoryPreset::uniswap_v2(
+                    "Uniswap V2",
+                    address!("8909Dc15e40173Ff4699343b6eB8132c65e18eC6"),
+                    6601915,
+                    300,
+                ),
+                FactoryPreset::uniswap_v3(
+                    "Uniswap V3",
+                    address!("33128a8fC17869897dcE68Ed026d694621f6FDfD"),
+                    1371680,
+                ),
+            ],
+            wrapped_native: weth,
+            weth,
+            usdc: address!("833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"),
+            reorg_depth: 1,
+            block_time: Duration::from_secs(2),
+            log_step: 500_000,
+        }
+    }
+
+    pub fn arbitrum() -> Self {
+        let weth = address!("82aF49447D8a07e3bd95BD0d56f35241523fBab1");
+        Self {
+            chain_id: ARBITRUM_CHAIN_ID,
+            name: "arbitrum",
+            factories: vec![
+                FactoryPreset::uniswap_v2(
+                    "SushiSwap",
+                    address!("c35DADB65012eC5796536bD9864eD8773aBc74C4"),
+                    70,
+                    300,
+                ),
+                FactoryPreset::uniswap_v3(
+                    "Uniswap V3",
+                    address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+                    165,
+                ),
+            ],
+            wrapped_native: weth,
+            weth,
+            usdc: address!("af88d065e77c8cC2239327C5EDb3A432268e5831"),
+            reorg_depth: 0,
+            block_time: Duration::from_millis(250),
+            log_step: 2_000_000,
+        }
+    }
+
+    /// Returns the factory preset named `name`, e.g. "Uniswap V3".
+    pub fn factory_preset(&self, name: &str) -> Option<&FactoryPreset> {
+        self.factories.iter().find(|preset| preset.name == name)
+    }
+
+    /// Returns the factories of the chain to sync AMMs from.
+    #[cfg(feature = "provider")]
+    pub fn factories(&self) -> Vec<Factory> {
+        self.factories.iter().map(FactoryPreset::factory).collect()
+    }
+
+    /// Returns the first block of the factories of the chain, where discovery can start from.
+    pub fn start_block(&self) -> u64 {
+        self.factories
+            .iter()
+            .map(|preset| preset.creation_block)
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Returns a [`SyncConfig`] syncing to the reorg depth of the chain, with the log step and batch strategy of the
+    /// chain.
+    #[cfg(feature = "provider")]
+    pub fn sync_config(&self) -> SyncConfig {
+        SyncConfig::builder()
+            .step(self.log_step)
+            .finality_depth(self.reorg_depth)
+            .batch_strategy(BatchStrategy::for_chain(self.chain_id))
+            .build()
+    }
+}
+
+/// Returns the L1 block number of the Arbitrum L2 block `block_number`, as returned by `block.number` in contracts.
+#[cfg(feature = "provider")]
+pub async fn arbitrum_l1_block_number<T, N, P>(
+    block_number: u64,
+    provider: Arc<P>,
+) -> Result<u64, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let block = provider
+        .client()
+        .request::<_, Option<Value>>(
+            "eth_getBlockByNumber",
+            (format!("0x{block_number:x}"), false),
+        )
+        .await?
+        .ok_or(AMMError::BlockNumberNotFound)?;
+
+    parse_l1_block_number(&block).ok_or(AMMError::BlockNumberNotFound)
+}
+
+/// Returns the first Arbitrum L2 block whose L1 block number is at least `l1_block_number`, or the latest block if
+/// the L1 block is not reached yet.
+///
+/// L1 block numbers of L2 blocks are non decreasing, so the block is found by binary search in `log2` of the chain
+/// height requests.
+#[cfg(feature = "provider")]
+pub async fn arbitrum_l2_block_number<T, N, P>(
+    l1_block_number: u64,
+    provider: Arc<P>,
+) -> Result<u64, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let (mut low, mut high) = (0, provider.get_block_number().await?);
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if arbitrum_l1_block_number(mid, provider.clone()).await? < l1_block_number {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok(low)
+}
+
+/// Returns the `l1BlockNumber` field of an Arbitrum block.
+#[cfg(feature = "provider")]
+fn parse_l1_block_number(block: &Value) -> Option<u64> {
+    let l1_block_number = block.get("l1BlockNumber")?.as_str()?;
+    u64::from_str_radix(l1_block_number.trim_start_matches("0x"), 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use alloy::primitives::{Address, U256};
+
+    use crate::amm::{uniswap_v2::UniswapV2Pool, AutomatedMarketMaker};
+
+    use super::{ChainConfig, FactoryKind};
+
+    #[test]
+    fn test_chain_presets() {
+        let chains = ChainConfig::all();
+        assert_eq!(
+            chains
+                .iter()
+                .map(|chain| chain.chain_id)
+                .collect::<HashSet<_>>()
+                .len(),
+            chains.len()
+        );
+
+        for chain in chains {
+            assert_eq!(
+                ChainConfig::from_chain_id(chain.chain_id),
+                Some(chain.clone())
+            );
+            assert!(!chain.factories.is_empty());
+            assert!(!chain.usdc.is_zero() && !chain.weth.is_zero());
+        }
+
+        assert_eq!(ChainConfig::from_chain_id(0), None);
+        assert_eq!(
+            ChainConfig::mainnet()
+                .factory_preset("Uniswap V2")
+                .unwrap()
+                .kind,
+            FactoryKind::UniswapV2 { fee: 300 }
+        );
+    }
+
+    #[test]
+    fn test_bsc_presets() {
+        let FactoryKind::UniswapV2 { fee } = ChainConfig::bsc()
+            .factory_preset("PancakeSwap V2")
+            .unwrap()
+            .kind
+        else {
+            unreachable!()
+        };
+
+        // PancakeSwap V2 pairs charge 0.25%
+        let pool = UniswapV2Pool {
+            token_a: Address::repeat_byte(0x01),
+            token_b: Address::repeat_byte(0x02),
+            reserve_0: 1_000_000,
+            reserve_1: 2_000_000,
+            fee,
+            ..Default::default()
+        };
+
+        // 1000 * 9975 * 2_000_000 / (1_000_000 * 10_000 + 1000 * 9975), as `getAmountOut` of the PancakeSwap router
+        assert_eq!(
+            pool.simulate_swap(pool.token_a, U256::from(1_000)).unwrap(),
+            U256::from(1993)
+        );
+    }
+
+    #[test]
+    fn test_arbitrum_presets() {
+        let arbitrum = ChainConfig::arbitrum();
+        assert_eq!(arbitrum.start_block(), 70);
+        assert!(arbitrum.log_step > ChainConfig::mainnet().log_step);
+
+        #[cfg(feature = "provider")]
+        assert_eq!(arbitrum.sync_config().step, arbitrum.log_step);
+    }
+
+    #[cfg(feature = "provider")]
+    #[test]
+    fn test_parse_l1_block_number() {
+        let block = serde_json::json!({ "number": "0xa4a8c1e", "l1BlockNumber": "0x12a05f2" });
+        assert_eq!(super::parse_l1_block_number(&block), Some(19_531_250));
+        assert_eq!(
+            super::parse_l1_block_number(&serde_json::json!({ "number": "0x1" })),
+            None
+        );
+    }
+}
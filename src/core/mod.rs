@@ -0,0 +1,10 @@
+//! Pure AMM math: constant product swaps, the Uniswap V3 tick walk and price conversions.
+//!
+//! The functions of this module only take plain values (or a [`uniswap_v3::TickSource`] for tick data) and do not
+//! depend on providers or async runtimes, so that simulations can be reused outside of a synced state space. The
+//! pools in [`crate::amm`] are built on top of them. The module is always compiled with the rest of the crate and
+//! still requires `std`.
+
+pub mod price;
+pub mod uniswap_v2;
+pub mod uniswap_v3;
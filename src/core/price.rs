@@ -0,0 +1,389 @@
+//! Conversions between reserves, ticks and prices.
+//!
+//! Prices are of token 0 in terms of token 1 and adjusted for the token decimals, as `f64`. Conversions to ticks and
+//! sqrt prices go through the exact tick math, so that a price converts to the same tick as the pool would.
+
+use core::cmp::Ordering;
+
+use alloy::primitives::U256;
+use num_bigfloat::BigFloat;
+use uniswap_v3_math::tick_math::{self, MAX_SQRT_RATIO, MIN_SQRT_RATIO};
+
+use crate::{
+    amm::consts::*,
+    core::uniswap_v3::{check_sqrt_price, check_tick},
+    errors::ArithmeticError,
+};
+
+/// Returns the price of token 0 in terms of token 1 at `tick`, adjusted for the token decimals.
+pub fn tick_to_price(tick: i32, token_0_decimals: u8, token_1_decimals: u8) -> f64 {
+    let shift = token_0_decimals as i8 - token_1_decimals as i8;
+
+    match shift.cmp(&0) {
+        Ordering::Less => 1.0001_f64.powi(tick) / 10_f64.powi(-shift as i32),
+        Ordering::Greater => 1.0001_f64.powi(tick) * 10_f64.powi(shift as i32),
+        Ordering::Equal => 1.0001_f64.powi(tick),
+    }
+}
+
+/// Returns the price of token 0 in terms of token 1 at the Q64.96 `sqrt_price_x_96`, adjusted for the token decimals.
+///
+/// The sqrt price is converted to `f64` before squaring, so that prices near the bounds do not overflow.
+pub fn sqrt_price_x96_to_price(
+    sqrt_price_x_96: U256,
+    token_0_decimals: u8,
+    token_1_decimals: u8,
+) -> f64 {
+    let sqrt_price = u256_to_f64(sqrt_price_x_96) / 2_f64.powi(96);
+    shift_decimals(sqrt_price * sqrt_price, token_0_decimals, token_1_decimals)
+}
+
+/// Returns the Q64.96 sqrt price of `price`, the price of token 0 in terms of token 1 adjusted for the token decimals.
+///
+/// The sqrt price is clamped to the range of the tick math.
+pub fn price_to_sqrt_price_x96(
+    price: f64,
+    token_0_decimals: u8,
+    token_1_decimals: u8,
+) -> Result<U256, ArithmeticError> {
+    if !price.is_finite() || price <= 0.0 {
+        return Err(ArithmeticError::InvalidPrice);
+    }
+
+    let raw_price = shift_decimals(price, token_1_decimals, token_0_decimals);
+    let sqrt_price_x_96 = f64_to_u256(raw_price.sqrt() * 2_f64.powi(96)).unwrap_or(MAX_SQRT_RATIO);
+
+    Ok(sqrt_price_x_96.clamp(MIN_SQRT_RATIO, MAX_SQRT_RATIO - U256_1))
+}
+
+/// Returns the tick of the Q64.96 `sqrt_price_x_96`, the greatest tick whose sqrt price is at most `sqrt_price_x_96`.
+pub fn sqrt_price_to_tick(sqrt_price_x_96: U256) -> Result<i32, ArithmeticError> {
+    check_sqrt_price(sqrt_price_x_96)?;
+    Ok(tick_math::get_tick_at_sqrt_ratio(sqrt_price_x_96)?)
+}
+
+/// Returns the tick of `price`, the price of token 0 in terms of token 1 adjusted for the token decimals.
+///
+/// Prices beyond the tick bounds are clamped to the min and max tick.
+pub fn price_to_tick(
+    price: f64,
+    token_0_decimals: u8,
+    token_1_decimals: u8,
+) -> Result<i32, ArithmeticError> {
+    sqrt_price_to_tick(price_to_sqrt_price_x96(
+        price,
+        token_0_decimals,
+        token_1_decimals,
+    )?)
+}
+
+/// Returns the Q64.96 sqrt price at `tick`.
+pub fn tick_to_sqrt_price_x96(tick: i32) -> Result<U256, ArithmeticError> {
+    check_tick(tick)?;
+    Ok(tick_math::get_sqrt_ratio_at_tick(tick)?)
+}
+
+/// Returns `price` multiplied by `10^(decimals_a - decimals_b)`.
+fn shift_decimals(price: f64, decimals_a: u8, decimals_b: u8) -> f64 {
+    let shift = decimals_a as i8 - decimals_b as i8;
+
+    match shift.cmp(&0) {
+        Ordering::Less => price / 10_f64.powi(-shift as i32),
+        Ordering::Greater => price * 10_f64.powi(shift as i32),
+        Ordering::Equal => price,
+    }
+}
+
+/// Converts the integer part of a finite non negative `x` to a U256, `None` if it does not fit.
+pub fn f64_to_u256(x: f64) -> Option<U256> {
+    if !x.is_finite() || x < 0.0 {
+        return None;
+    }
+
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32;
+    let mantissa = bits & ((1 << 52) - 1);
+    if exponent == 0 {
+        // Subnormal numbers are below 1
+        return Some(U256::ZERO);
+    }
+
+    let mantissa = U256::from(mantissa | (1 << 52));
+    let shift = exponent - 1075;
+    if shift >= 0 {
+        (shift <= 203).then(|| mantissa << shift as usize)
+    } else if shift > -53 {
+        Some(mantissa >> (-shift) as usize)
+    } else {
+        Some(U256::ZERO)
+    }
+}
+
+/// Returns the price of token 0 in terms of token 1 as a Q64 fixed point number, adjusted for the token decimals.
+///
+/// Returns `1` if the reserve of token 0 is empty.
+pub fn reserves_to_price_64_x_64(
+    reserve_0: u128,
+    reserve_1: u128,
+    token_0_decimals: u8,
+    token_1_decimals: u8,
+) -> Result<u128, ArithmeticError> {
+    let decimal_shift = token_0_decimals as i8 - token_1_decimals as i8;
+
+    let (r_0, r_1) = if decimal_shift < 0 {
+        (
+            U256::from(reserve_0) * U256::from(10u128.pow(decimal_shift.unsigned_abs() as u32)),
+            U256::from(reserve_1),
+        )
+    } else {
+        (
+            U256::from(reserve_0),
+            U256::from(reserve_1) * U256::from(10u128.pow(decimal_shift as u32)),
+        )
+    };
+
+    if r_0.is_zero() {
+        Ok(U128_0X10000000000000000)
+    } else {
+        div_uu(r_1, r_0)
+    }
+}
+
+pub fn div_uu(x: U256, y: U256) -> Result<u128, ArithmeticError> {
+    if !y.is_zero() {
+        let mut answer;
+
+        if x <= U256_0XFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF {
+            answer = (x << U256_64) / y;
+        } else {
+            let mut msb = U256_192;
+            let mut xc = x >> U256_192;
+
+            if xc >= U256_0X100000000 {
+                xc >>= U256_32;
+                msb += U256_32;
+            }
+
+            if xc >= U256_0X10000 {
+                xc >>= U256_16;
+                msb += U256_16;
+            }
+
+            if xc >= U256_0X100 {
+                xc >>= U256_8;
+                msb += U256_8;
+            }
+
+            if xc >= U256_16 {
+                xc >>= U256_4;
+                msb += U256_4;
+            }
+
+            if xc >= U256_4 {
+                xc >>= U256_2;
+                msb += U256_2;
+            }
+
+            if xc >= U256_2 {
+                msb += U256_1;
+            }
+
+            answer = (x << (U256_255 - msb)) / (((y - U256_1) >> (msb - U256_191)) + U256_1);
+        }
+
+        if answer > U256_0XFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF {
+            return Ok(0);
+        }
+
+        let hi = answer * (y >> U256_128);
+        let mut lo = answer * (y & U256_0XFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF);
+
+        let mut xh = x >> U256_192;
+        let mut xl = x << U256_64;
+
+        if xl < lo {
+            xh -= U256_1;
+        }
+
+        xl = xl.overflowing_sub(lo).0;
+        lo = hi << U256_128;
+
+        if xl < lo {
+            xh -= U256_1;
+        }
+
+        xl = xl.overflowing_sub(lo).0;
+
+        if xh != hi >> U256_128 {
+            return Err(ArithmeticError::RoundingError);
+        }
+
+        answer += xl / y;
+
+        if answer > U256_0XFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF {
+            return Ok(0_u128);
+        }
+
+        Ok(answer.to::<u128>())
+    } else {
+        Err(ArithmeticError::YIsZero)
+    }
+}
+
+/// Converts a Q64 fixed point to a Q16 fixed point -> f64
+pub fn q64_to_f64(x: u128) -> f64 {
+    BigFloat::from(x)
+        .div(&BigFloat::from(U128_0X10000000000000000))
+        .to_f64()
+}
+
+/// Converts `x` to the nearest f64.
+pub fn u256_to_f64(x: U256) -> f64 {
+    x.as_limbs()
+        .iter()
+        .rev()
+        .fold(0.0, |acc, limb| acc * 2_f64.powi(64) + *limb as f64)
+}
+
+/// Returns the price of token 0 in terms of token 1 of a trade of `amount_0` against `amount_1`, adjusted for the
+/// token decimals.
+///
+/// Returns `0` if `amount_0` is zero.
+pub fn amounts_to_price(
+    amount_0: U256,
+    amount_1: U256,
+    token_0_decimals: u8,
+    token_1_decimals: u8,
+) -> f64 {
+    if amount_0.is_zero() {
+        return 0.0;
+    }
+
+    let price = u256_to_f64(amount_1) / u256_to_f64(amount_0);
+    let shift = token_0_decimals as i8 - token_1_decimals as i8;
+
+    match shift.cmp(&0) {
+        Ordering::Less => price / 10_f64.powi(-shift as i32),
+        Ordering::Greater => price * 10_f64.powi(shift as i32),
+        Ordering::Equal => price,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+    use uniswap_v3_math::tick_math::MAX_SQRT_RATIO;
+
+    use super::{
+        amounts_to_price, div_uu, f64_to_u256, price_to_sqrt_price_x96, price_to_tick, q64_to_f64,
+        reserves_to_price_64_x_64, sqrt_price_to_tick, sqrt_price_x96_to_price, tick_to_price,
+        tick_to_sqrt_price_x96, u256_to_f64,
+    };
+    use crate::errors::ArithmeticError;
+
+    #[test]
+    fn test_div_uu() {
+        assert_eq!(div_uu(U256::from(1), U256::from(2)).unwrap(), 1 << 63);
+        assert_eq!(
+            q64_to_f64(div_uu(U256::from(3), U256::from(4)).unwrap()),
+            0.75
+        );
+        assert!(div_uu(U256::from(1), U256::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_reserves_to_price_64_x_64() {
+        // 1 token 0 (6 decimals) for 2 token 1 (18 decimals)
+        let price = reserves_to_price_64_x_64(1_000_000, 2_000_000_000_000_000_000, 6, 18).unwrap();
+        assert_eq!(q64_to_f64(price), 2.0);
+
+        let price = reserves_to_price_64_x_64(2_000_000_000_000_000_000, 1_000_000, 18, 6).unwrap();
+        assert_eq!(q64_to_f64(price), 0.5);
+
+        assert_eq!(reserves_to_price_64_x_64(0, 1, 18, 18).unwrap(), 1 << 64);
+    }
+
+    #[test]
+    fn test_tick_to_price() {
+        assert_eq!(tick_to_price(0, 18, 18), 1.0);
+        assert_eq!(tick_to_price(0, 6, 18), 1e-12);
+        assert!((tick_to_price(-276324, 18, 6) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_sqrt_price_conversions() {
+        for tick in [-276324, -1, 0, 1, 200_000] {
+            let sqrt_price_x_96 = tick_to_sqrt_price_x96(tick).unwrap();
+            assert_eq!(sqrt_price_to_tick(sqrt_price_x_96).unwrap(), tick);
+
+            let price = sqrt_price_x96_to_price(sqrt_price_x_96, 18, 6);
+            assert!((price / tick_to_price(tick, 18, 6) - 1.0).abs() < 1e-9);
+        }
+
+        assert_eq!(sqrt_price_x96_to_price(U256::from(1) << 96, 18, 18), 1.0);
+        assert_eq!(sqrt_price_x96_to_price(U256::from(1) << 96, 6, 18), 1e-12);
+        assert_eq!(
+            price_to_sqrt_price_x96(1.0, 18, 18).unwrap(),
+            U256::from(1) << 96
+        );
+
+        assert!(matches!(
+            tick_to_sqrt_price_x96(887273),
+            Err(ArithmeticError::TickOutOfBounds(887273))
+        ));
+        assert!(matches!(
+            sqrt_price_to_tick(U256::ZERO),
+            Err(ArithmeticError::SqrtPriceOutOfBounds(_))
+        ));
+        assert!(matches!(
+            sqrt_price_to_tick(MAX_SQRT_RATIO),
+            Err(ArithmeticError::SqrtPriceOutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn test_price_to_tick() {
+        assert_eq!(price_to_tick(1.0, 18, 18).unwrap(), 0);
+        // Just above the price of tick 100, the tick of the price is 100 and not 99
+        assert_eq!(
+            price_to_tick(tick_to_price(100, 18, 18) * (1.0 + 1e-12), 18, 18).unwrap(),
+            100
+        );
+        // 1 DAI (18 decimals) per USDC (6 decimals)
+        assert_eq!(price_to_tick(1.0, 6, 18).unwrap(), 276324);
+
+        assert_eq!(price_to_tick(1e300, 18, 18).unwrap(), 887271);
+        assert!(matches!(
+            price_to_tick(0.0, 18, 18),
+            Err(ArithmeticError::InvalidPrice)
+        ));
+        assert!(matches!(
+            price_to_tick(f64::NAN, 18, 18),
+            Err(ArithmeticError::InvalidPrice)
+        ));
+    }
+
+    #[test]
+    fn test_f64_to_u256() {
+        assert_eq!(f64_to_u256(0.5), Some(U256::ZERO));
+        assert_eq!(f64_to_u256(3.0), Some(U256::from(3)));
+        assert_eq!(f64_to_u256(2_f64.powi(200)), Some(U256::from(1) << 200));
+        assert_eq!(f64_to_u256(2_f64.powi(256)), None);
+        assert_eq!(f64_to_u256(-1.0), None);
+    }
+
+    #[test]
+    fn test_amounts_to_price() {
+        assert_eq!(
+            amounts_to_price(
+                U256::from(1_000_000),
+                U256::from(2_000_000_000_000_000_000_u128),
+                6,
+                18
+            ),
+            2.0
+        );
+        assert_eq!(amounts_to_price(U256::ZERO, U256::from(1), 18, 18), 0.0);
+        assert_eq!(u256_to_f64(U256::from(1) << 64), 2_f64.powi(64));
+    }
+}
@@ -0,0 +1,212 @@
+//! Constant product (`x * y = k`) math of Uniswap V2 style pools.
+
+use alloy::primitives::U256;
+
+/// Denominator of the fees of Uniswap V2 style pools, a fee of 300 is 0.3%.
+const FEE_DENOMINATOR: u32 = 100_000;
+
+/// Returns the fee adjusted input per [`FEE_DENOMINATOR`] units of input, e.g. 99_700 for a fee of 300 (0.3%) and
+/// 99_750 for a fee of 250 (0.25%).
+pub fn fee_multiplier(fee: u32) -> u32 {
+    FEE_DENOMINATOR - fee.min(FEE_DENOMINATOR)
+}
+
+/// Calculates the amount received for a given `amount_in` `reserve_in` and `reserve_out`.
+pub fn get_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee: u32) -> U256 {
+    if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return U256::ZERO;
+    }
+
+    let amount_in_with_fee = amount_in * U256::from(fee_multiplier(fee));
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * U256::from(FEE_DENOMINATOR) + amount_in_with_fee;
+
+    numerator / denominator
+}
+
+/// Calculates the amount required to receive `amount_out`, as `getAmountIn` of the Uniswap V2 router.
+///
+/// Returns `None` if the pool does not hold enough of the token out.
+pub fn get_amount_in(
+    amount_out: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee: u32,
+) -> Option<U256> {
+    if amount_out.is_zero() {
+        return Some(U256::ZERO);
+    }
+
+    if reserve_in.is_zero() || amount_out >= reserve_out {
+        return None;
+    }
+
+    let numerator = reserve_in
+        .checked_mul(amount_out)?
+        .checked_mul(U256::from(FEE_DENOMINATOR))?;
+    let denominator = (reserve_out - amount_out) * U256::from(fee_multiplier(fee));
+
+    Some(numerator / denominator + U256::from(1))
+}
+
+/// Calculates the amount received for a given `amount_in` `reserve_in` and `reserve_out`, with a fee in basis points.
+pub fn get_amount_out_bps(
+    amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_bps: u32,
+) -> U256 {
+    if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return U256::ZERO;
+    }
+
+    let amount_in_with_fee = amount_in * U256::from(10_000 - fee_bps.min(10_000));
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * U256::from(10_000) + amount_in_with_fee;
+
+    numerator / denominator
+}
+
+/// Returns the largest amount that can be swapped while the execution price stays within `max_slippage_bps` of the
+/// fee adjusted spot price.
+///
+/// With `f` the fee adjusted input, the execution price relative to spot is `r_in / (r_in + f * x)`,
+/// so the bound holds for `x <= r_in * bps / ((10_000 - bps) * f)`.
+pub fn max_input_for_slippage(reserve_in: U256, fee: u32, max_slippage_bps: u32) -> U256 {
+    if reserve_in.is_zero() {
+        return U256::ZERO;
+    }
+
+    if max_slippage_bps >= 10_000 {
+        return U256::MAX;
+    }
+
+    let numerator = reserve_in * U256::from(FEE_DENOMINATOR) * U256::from(max_slippage_bps);
+    let denominator = U256::from(10_000 - max_slippage_bps) * U256::from(fee_multiplier(fee));
+
+    numerator / denominator
+}
+
+/// Returns `amount` net of a transfer tax of `tax_bps` basis points.
+pub fn apply_transfer_tax(amount: U256, tax_bps: u32) -> U256 {
+    if tax_bps == 0 {
+        return amount;
+    }
+
+    amount * U256::from(10_000 - tax_bps.min(10_000)) / U256::from(10_000)
+}
+
+/// Returns the smallest amount that is worth at least `amount` net of a transfer tax of `tax_bps` basis points.
+///
+/// Returns `None` if the tax is 100%.
+pub fn amount_before_transfer_tax(amount: U256, tax_bps: u32) -> Option<U256> {
+    if tax_bps == 0 {
+        return Some(amount);
+    }
+
+    if tax_bps >= 10_000 {
+        return None;
+    }
+
+    let net = U256::from(10_000 - tax_bps);
+    Some((amount * U256::from(10_000) + net - U256::from(1)) / net)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::{
+        amount_before_transfer_tax, apply_transfer_tax, fee_multiplier, get_amount_in,
+        get_amount_out, get_amount_out_bps, max_input_for_slippage,
+    };
+
+    #[test]
+    fn test_get_amount_out() {
+        assert_eq!(fee_multiplier(300), 99_700);
+        assert_eq!(fee_multiplier(250), 99_750);
+
+        let amount_out = get_amount_out(
+            U256::from(1_000),
+            U256::from(1_000_000),
+            U256::from(2_000_000),
+            300,
+        );
+        // 1000 * 997 * 2_000_000 / (1_000_000 * 1000 + 1000 * 997)
+        assert_eq!(amount_out, U256::from(1992));
+
+        assert_eq!(
+            get_amount_out(U256::from(1_000), U256::ZERO, U256::from(1), 300),
+            U256::ZERO
+        );
+    }
+
+    #[test]
+    fn test_get_amount_in() {
+        let (reserve_in, reserve_out) = (U256::from(1_000_000), U256::from(2_000_000));
+
+        // 1_000_000 * 1992 * 1000 / ((2_000_000 - 1992) * 997) + 1
+        let amount_in = get_amount_in(U256::from(1992), reserve_in, reserve_out, 300).unwrap();
+        assert_eq!(amount_in, U256::from(1000));
+        assert!(get_amount_out(amount_in, reserve_in, reserve_out, 300) >= U256::from(1992));
+
+        assert_eq!(
+            get_amount_in(U256::ZERO, reserve_in, reserve_out, 300),
+            Some(U256::ZERO)
+        );
+        assert_eq!(
+            get_amount_in(reserve_out, reserve_in, reserve_out, 300),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_amount_out_bps() {
+        let (reserve_in, reserve_out) = (U256::from(1_000_000), U256::from(2_000_000));
+        assert_eq!(
+            get_amount_out_bps(U256::from(1_000), reserve_in, reserve_out, 30),
+            get_amount_out(U256::from(1_000), reserve_in, reserve_out, 300)
+        );
+
+        // Fees that are not a multiple of 10 bps are not rounded
+        // 1000 * 9975 * 2_000_000 / (1_000_000 * 10_000 + 1000 * 9975)
+        assert_eq!(
+            get_amount_out_bps(U256::from(1_000), reserve_in, reserve_out, 25),
+            U256::from(1993)
+        );
+        assert_eq!(
+            get_amount_out(U256::from(1_000), reserve_in, reserve_out, 250),
+            U256::from(1993)
+        );
+    }
+
+    #[test]
+    fn test_max_input_for_slippage() {
+        assert_eq!(
+            max_input_for_slippage(U256::from(997), 300, 5_000),
+            U256::from(1_000)
+        );
+        assert_eq!(max_input_for_slippage(U256::ZERO, 300, 100), U256::ZERO);
+        assert_eq!(
+            max_input_for_slippage(U256::from(1), 300, 10_000),
+            U256::MAX
+        );
+    }
+
+    #[test]
+    fn test_apply_transfer_tax() {
+        assert_eq!(apply_transfer_tax(U256::from(1_000), 0), U256::from(1_000));
+        assert_eq!(apply_transfer_tax(U256::from(1_000), 500), U256::from(950));
+        assert_eq!(apply_transfer_tax(U256::from(1_000), 20_000), U256::ZERO);
+    }
+
+    #[test]
+    fn test_amount_before_transfer_tax() {
+        let amount = amount_before_transfer_tax(U256::from(999), 500).unwrap();
+        assert_eq!(amount, U256::from(1052));
+        assert!(apply_transfer_tax(amount, 500) >= U256::from(999));
+        assert!(apply_transfer_tax(amount - U256::from(1), 500) < U256::from(999));
+
+        assert_eq!(amount_before_transfer_tax(U256::from(1), 10_000), None);
+    }
+}
@@ -0,0 +1,527 @@
+//! Tick walk of Uniswap V3 style pools.
+
+use core::ops::ControlFlow;
+
+use alloy::primitives::{I256, U256};
+use uniswap_v3_math::{
+    error::UniswapV3MathError,
+    tick_math::{MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK},
+};
+
+use crate::{
+    amm::consts::U256_1,
+    errors::{ArithmeticError, SwapSimulationError},
+};
+
+/// Fees are in hundredths of a basis point and below 100%.
+pub const MAX_FEE: u32 = 1_000_000;
+
+/// Max tick spacing accepted by the factory.
+pub const MAX_TICK_SPACING: i32 = 16384;
+
+/// Tick data of a pool, so that the tick walk does not depend on how ticks are stored.
+pub trait TickSource {
+    /// Returns the word of the tick bitmap at `word_pos`, zero if it is not set.
+    fn tick_bitmap_word(&self, word_pos: i16) -> U256;
+
+    /// Returns the net liquidity of `tick`, zero if it is not initialized.
+    fn liquidity_net(&self, tick: i32) -> i128;
+
+    /// Returns an error if the tick data around `tick` is not available, all tick data is available by default.
+    fn check_tick_loaded(&self, _tick: i32) -> Result<(), SwapSimulationError> {
+        Ok(())
+    }
+}
+
+/// State of a simulated swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentState {
+    /// Amount of token in that has not been swapped.
+    pub amount_specified_remaining: I256,
+    /// Negated amount of token out that has been calculated.
+    pub amount_calculated: I256,
+    /// Price of the pool.
+    pub sqrt_price_x_96: U256,
+    /// Tick of the pool.
+    pub tick: i32,
+    /// Available liquidity in the current tick range.
+    pub liquidity: u128,
+}
+
+/// Returns an error if `tick` is outside of the tick bounds.
+pub fn check_tick(tick: i32) -> Result<(), ArithmeticError> {
+    if (MIN_TICK..=MAX_TICK).contains(&tick) {
+        Ok(())
+    } else {
+        Err(ArithmeticError::TickOutOfBounds(tick))
+    }
+}
+
+/// Returns an error if `sqrt_price_x_96` is outside of the sqrt prices of the tick math, `MAX_SQRT_RATIO` excluded.
+pub fn check_sqrt_price(sqrt_price_x_96: U256) -> Result<(), ArithmeticError> {
+    if (MIN_SQRT_RATIO..MAX_SQRT_RATIO).contains(&sqrt_price_x_96) {
+        Ok(())
+    } else {
+        Err(ArithmeticError::SqrtPriceOutOfBounds(sqrt_price_x_96))
+    }
+}
+
+/// Returns an error if `fee` is not below [`MAX_FEE`] or `tick_spacing` is not within `1..=MAX_TICK_SPACING`.
+pub fn check_fee_and_tick_spacing(fee: u32, tick_spacing: i32) -> Result<(), SwapSimulationError> {
+    if fee >= MAX_FEE {
+        return Err(SwapSimulationError::InvalidFee(fee));
+    }
+
+    if !(1..=MAX_TICK_SPACING).contains(&tick_spacing) {
+        return Err(SwapSimulationError::InvalidTickSpacing(tick_spacing));
+    }
+
+    Ok(())
+}
+
+/// Returns the next initialized tick within the tick bitmap word of `tick`, at or below `tick` if `lte` is true and
+/// above `tick` otherwise, along with whether it is initialized.
+///
+/// Returns the last tick of the word in the direction of the search if no tick is initialized.
+pub fn next_initialized_tick_within_one_word<S: TickSource + ?Sized>(
+    source: &S,
+    tick: i32,
+    tick_spacing: i32,
+    lte: bool,
+) -> Result<(i32, bool), UniswapV3MathError> {
+    let compressed = if tick < 0 && tick % tick_spacing != 0 {
+        (tick / tick_spacing) - 1
+    } else {
+        tick / tick_spacing
+    };
+
+    if lte {
+        let (word_pos, bit_pos) = uniswap_v3_math::tick_bitmap::position(compressed);
+        let mask = (U256_1 << bit_pos as usize) - U256_1 + (U256_1 << bit_pos as usize);
+        let masked = source.tick_bitmap_word(word_pos) & mask;
+
+        let initialized = !masked.is_zero();
+        let next = if initialized {
+            let most_significant_bit = (masked.bit_len() - 1) as i32;
+            (compressed - (bit_pos as i32 - most_significant_bit)) * tick_spacing
+        } else {
+            (compressed - bit_pos as i32) * tick_spacing
+        };
+
+        Ok((next, initialized))
+    } else {
+        let (word_pos, bit_pos) = uniswap_v3_math::tick_bitmap::position(compressed + 1);
+        let mask = !((U256_1 << bit_pos as usize) - U256_1);
+        let masked = source.tick_bitmap_word(word_pos) & mask;
+
+        let initialized = !masked.is_zero();
+        let next = if initialized {
+            let least_significant_bit = masked.trailing_zeros() as i32;
+            (compressed + 1 + (least_significant_bit - bit_pos as i32)) * tick_spacing
+        } else {
+            (compressed + 1 + (u8::MAX - bit_pos) as i32) * tick_spacing
+        };
+
+        Ok((next, initialized))
+    }
+}
+
+/// Direction of a walk over the initialized ticks of a pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickDirection {
+    /// Towards lower ticks, as when swapping token 0 for token 1.
+    Down,
+    /// Towards higher ticks, as when swapping token 1 for token 0.
+    Up,
+}
+
+/// Iterator over the initialized ticks of a pool and their net liquidity, in the order they would be crossed by a
+/// swap, see [`initialized_ticks`].
+#[derive(Debug)]
+pub struct InitializedTicks<'a, S: ?Sized> {
+    source: &'a S,
+    tick_spacing: i32,
+    direction: TickDirection,
+    /// Tick to search the next initialized tick from, `None` once the walk is over.
+    cursor: Option<i32>,
+}
+
+/// Returns an iterator over the initialized ticks of `source` from `tick` in `direction`, derived from the tick
+/// bitmap.
+///
+/// Walking down includes `tick` itself, walking up starts strictly above `tick`, the same as a swap from `tick`. The
+/// walk ends at the tick bounds or at the first tick whose tick data is not loaded.
+pub fn initialized_ticks<S: TickSource + ?Sized>(
+    source: &S,
+    tick: i32,
+    tick_spacing: i32,
+    direction: TickDirection,
+) -> InitializedTicks<'_, S> {
+    InitializedTicks {
+        source,
+        tick_spacing: tick_spacing.max(1),
+        direction,
+        cursor: Some(tick.clamp(MIN_TICK, MAX_TICK)),
+    }
+}
+
+impl<S: TickSource + ?Sized> Iterator for InitializedTicks<'_, S> {
+    type Item = (i32, i128);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let tick = self.cursor.take()?;
+            let lte = self.direction == TickDirection::Down;
+
+            let (tick_next, initialized) =
+                next_initialized_tick_within_one_word(self.source, tick, self.tick_spacing, lte)
+                    .ok()?;
+            if !(MIN_TICK..=MAX_TICK).contains(&tick_next)
+                || self.source.check_tick_loaded(tick_next).is_err()
+            {
+                return None;
+            }
+
+            // Search the next word, or past the initialized tick, on the next call
+            self.cursor = match self.direction {
+                TickDirection::Down => Some(tick_next - 1).filter(|tick| *tick >= MIN_TICK),
+                TickDirection::Up => Some(tick_next).filter(|tick| *tick < MAX_TICK),
+            };
+
+            if initialized {
+                return Some((tick_next, self.source.liquidity_net(tick_next)));
+            }
+        }
+    }
+}
+
+/// Walks the initialized ticks of `source` from `state` until `state.amount_specified_remaining` is swapped or the
+/// liquidity is exhausted, returning the end state of the swap.
+pub fn swap<S: TickSource + ?Sized>(
+    source: &S,
+    state: CurrentState,
+    tick_spacing: i32,
+    fee: u32,
+    zero_for_one: bool,
+) -> Result<CurrentState, SwapSimulationError> {
+    swap_with_step_callback(source, state, tick_spacing, fee, zero_for_one, |_| {
+        ControlFlow::Continue(())
+    })
+}
+
+/// Same as [`swap`], calling `on_tick` with the tick, its net liquidity and the amount in swapped so far, including
+/// fees, every time an initialized tick is crossed.
+///
+/// The swap stops at the crossed tick if `on_tick` breaks, e.g. once the price moved past a threshold, and the state
+/// at the tick is returned.
+pub fn swap_with_tick_callback<S, F>(
+    source: &S,
+    state: CurrentState,
+    tick_spacing: i32,
+    fee: u32,
+    zero_for_one: bool,
+    mut on_tick: F,
+) -> Result<CurrentState, SwapSimulationError>
+where
+    S: TickSource + ?Sized,
+    F: FnMut(i32, i128, U256) -> ControlFlow<()>,
+{
+    let mut amount_in = U256::ZERO;
+
+    swap_with_step_callback(source, state, tick_spacing, fee, zero_for_one, |step| {
+        amount_in += step.amount_in + step.fee_amount;
+
+        if step.crossed_initialized_tick {
+            on_tick(
+                step.tick_next,
+                source.liquidity_net(step.tick_next),
+                amount_in,
+            )
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+}
+
+/// A step of a swap, within a range of constant liquidity up to the next initialized tick or the end of a tick
+/// bitmap word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    /// Tick of the pool at the start of the step.
+    pub tick: i32,
+    /// Active liquidity during the step.
+    pub liquidity: u128,
+    pub sqrt_price_start_x_96: U256,
+    pub sqrt_price_end_x_96: U256,
+    /// Tick the step swaps towards.
+    pub tick_next: i32,
+    /// Whether the step reached `tick_next` and it is initialized, the liquidity changing for the next step.
+    pub crossed_initialized_tick: bool,
+    /// Amount in swapped by the step, excluding fees.
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Fees of the step, in the token in.
+    pub fee_amount: U256,
+}
+
+/// Same as [`swap`], calling `on_step` after every step of the swap, e.g. to split the fees of each step between the
+/// liquidity providers in range.
+///
+/// The swap stops after the step if `on_step` breaks, and the state at the end of the step is returned.
+pub fn swap_with_step_callback<S, F>(
+    source: &S,
+    state: CurrentState,
+    tick_spacing: i32,
+    fee: u32,
+    zero_for_one: bool,
+    mut on_step: F,
+) -> Result<CurrentState, SwapSimulationError>
+where
+    S: TickSource + ?Sized,
+    F: FnMut(&SwapStep) -> ControlFlow<()>,
+{
+    check_fee_and_tick_spacing(fee, tick_spacing)?;
+    check_tick(state.tick)?;
+    check_sqrt_price(state.sqrt_price_x_96)?;
+
+    // Set sqrt_price_limit_x_96 to the max or min sqrt price in the pool depending on zero_for_one
+    let sqrt_price_limit_x_96 = if zero_for_one {
+        MIN_SQRT_RATIO + U256_1
+    } else {
+        MAX_SQRT_RATIO - U256_1
+    };
+
+    let mut current_state = state;
+
+    while current_state.amount_specified_remaining != I256::ZERO
+        && current_state.sqrt_price_x_96 != sqrt_price_limit_x_96
+    {
+        let sqrt_price_start_x_96 = current_state.sqrt_price_x_96;
+
+        // Get the next tick from the current tick
+        let (tick_next, initialized) = next_initialized_tick_within_one_word(
+            source,
+            current_state.tick,
+            tick_spacing,
+            zero_for_one,
+        )?;
+        source.check_tick_loaded(tick_next)?;
+
+        // ensure that we do not overshoot the min/max tick, as the tick bitmap is not aware of these bounds
+        // Note: this could be removed as we are clamping in the batch contract
+        let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+
+        // Get the next sqrt price from the input amount
+        let sqrt_price_next_x96 = uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(tick_next)?;
+
+        // Target spot price, bounded by the price limit
+        let swap_target_sqrt_ratio = if zero_for_one {
+            sqrt_price_next_x96.max(sqrt_price_limit_x_96)
+        } else {
+            sqrt_price_next_x96.min(sqrt_price_limit_x_96)
+        };
+
+        let step_tick = current_state.tick;
+        let step_liquidity = current_state.liquidity;
+
+        // Compute swap step and update the current state
+        let (sqrt_price_x_96, amount_in, amount_out, fee_amount) =
+            uniswap_v3_math::swap_math::compute_swap_step(
+                current_state.sqrt_price_x_96,
+                swap_target_sqrt_ratio,
+                current_state.liquidity,
+                current_state.amount_specified_remaining,
+                fee,
+            )?;
+        current_state.sqrt_price_x_96 = sqrt_price_x_96;
+
+        // Decrement the amount remaining to be swapped and amount received from the step
+        current_state.amount_specified_remaining = current_state
+            .amount_specified_remaining
+            .overflowing_sub(I256::from_raw(amount_in.overflowing_add(fee_amount).0))
+            .0;
+
+        current_state.amount_calculated -= I256::from_raw(amount_out);
+
+        // If the price moved all the way to the next price, recompute the liquidity change for the next iteration
+        let reached_tick_next = current_state.sqrt_price_x_96 == sqrt_price_next_x96;
+        if reached_tick_next {
+            if initialized {
+                let liquidity_net = source.liquidity_net(tick_next);
+
+                // we are on a tick boundary, and the next tick is initialized, so we must charge a protocol fee
+                let liquidity_net = if zero_for_one {
+                    -liquidity_net
+                } else {
+                    liquidity_net
+                };
+
+                current_state.liquidity = if liquidity_net < 0 {
+                    current_state
+                        .liquidity
+                        .checked_sub(liquidity_net.unsigned_abs())
+                        .ok_or(SwapSimulationError::LiquidityUnderflow)?
+                } else {
+                    current_state.liquidity + (liquidity_net as u128)
+                };
+            }
+            // Increment the current tick
+            current_state.tick = if zero_for_one {
+                tick_next.wrapping_sub(1)
+            } else {
+                tick_next
+            };
+            // If the current_state sqrt price is not equal to the step sqrt price, then we are not on the same tick.
+            // Update the current_state.tick to the tick at the current_state.sqrt_price_x_96
+        } else if current_state.sqrt_price_x_96 != sqrt_price_start_x_96 {
+            current_state.tick =
+                uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(current_state.sqrt_price_x_96)?;
+        }
+
+        let step = SwapStep {
+            tick: step_tick,
+            liquidity: step_liquidity,
+            sqrt_price_start_x_96,
+            sqrt_price_end_x_96: current_state.sqrt_price_x_96,
+            tick_next,
+            crossed_initialized_tick: reached_tick_next && initialized,
+            amount_in,
+            amount_out,
+            fee_amount,
+        };
+        if on_step(&step).is_break() {
+            break;
+        }
+    }
+
+    Ok(current_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::{I256, U256};
+
+    use super::{
+        initialized_ticks, next_initialized_tick_within_one_word, swap, CurrentState,
+        TickDirection, TickSource,
+    };
+    use crate::errors::{ArithmeticError, SwapSimulationError};
+
+    struct Bitmap(HashMap<i16, U256>);
+
+    impl TickSource for Bitmap {
+        fn tick_bitmap_word(&self, word_pos: i16) -> U256 {
+            self.0.get(&word_pos).copied().unwrap_or_default()
+        }
+
+        fn liquidity_net(&self, _tick: i32) -> i128 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_next_initialized_tick_within_one_word() {
+        let mut bitmap = HashMap::new();
+        bitmap.insert(-1, U256::from(0b1001) << 250);
+        bitmap.insert(0, U256::from(0b100101));
+        bitmap.insert(3, U256::from(1) << 255);
+        let source = Bitmap(bitmap.clone());
+
+        for tick_spacing in [1, 10, 60] {
+            for tick in (-1024..1024).chain([-256 * 60, 255 * 60, 1023 * 60]) {
+                for lte in [true, false] {
+                    assert_eq!(
+                        next_initialized_tick_within_one_word(&source, tick, tick_spacing, lte)
+                            .unwrap(),
+                        uniswap_v3_math::tick_bitmap::next_initialized_tick_within_one_word(
+                            &bitmap,
+                            tick,
+                            tick_spacing,
+                            lte
+                        )
+                        .unwrap(),
+                        "tick {tick}, tick spacing {tick_spacing}, lte {lte}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_initialized_ticks() {
+        let mut bitmap = HashMap::new();
+        bitmap.insert(-1, U256::from(0b1001) << 250);
+        bitmap.insert(0, U256::from(0b100101));
+        bitmap.insert(3, U256::from(1) << 255);
+        let source = Bitmap(bitmap);
+
+        let ticks = |tick, direction| {
+            initialized_ticks(&source, tick, 10, direction)
+                .map(|(tick, _)| tick)
+                .collect::<Vec<i32>>()
+        };
+
+        assert_eq!(ticks(20, TickDirection::Down), vec![20, 0, -30, -60]);
+        assert_eq!(ticks(20, TickDirection::Up), vec![50, 1023 * 10]);
+        assert_eq!(ticks(-60, TickDirection::Down), vec![-60]);
+        assert_eq!(ticks(-61, TickDirection::Down), Vec::<i32>::new());
+        assert_eq!(
+            ticks(-61, TickDirection::Up),
+            vec![-60, -30, 0, 20, 50, 10230]
+        );
+    }
+
+    #[test]
+    fn test_swap_input_validation() {
+        let source = Bitmap(HashMap::new());
+        let state = CurrentState {
+            amount_specified_remaining: I256::ONE,
+            amount_calculated: I256::ZERO,
+            sqrt_price_x_96: U256::from(1) << 96,
+            tick: 0,
+            liquidity: 1_000_000,
+        };
+
+        assert!(swap(&source, state, 60, 3000, true).is_ok());
+        assert!(matches!(
+            swap(&source, state, 60, 1_000_000, true),
+            Err(SwapSimulationError::InvalidFee(1_000_000))
+        ));
+        assert!(matches!(
+            swap(&source, state, 0, 3000, true),
+            Err(SwapSimulationError::InvalidTickSpacing(0))
+        ));
+        assert!(matches!(
+            swap(
+                &source,
+                CurrentState {
+                    tick: -887273,
+                    ..state
+                },
+                60,
+                3000,
+                true
+            ),
+            Err(SwapSimulationError::ArithmeticError(
+                ArithmeticError::TickOutOfBounds(-887273)
+            ))
+        ));
+        assert!(matches!(
+            swap(
+                &source,
+                CurrentState {
+                    sqrt_price_x_96: U256::ZERO,
+                    ..state
+                },
+                60,
+                3000,
+                true
+            ),
+            Err(SwapSimulationError::ArithmeticError(
+                ArithmeticError::SqrtPriceOutOfBounds(_)
+            ))
+        ));
+    }
+}
@@ -0,0 +1,396 @@
+//! Pluggable data sources.
+//!
+//! A [`DataSource`] serves the block numbers, logs, static calls and bytecode used to populate, sync and discover
+//! AMMs, e.g. from a direct database reader, a caching proxy or a test mock. [`data_source_provider`] wraps a data
+//! source in a provider, so that it can be used wherever the crate expects a `Provider` (`populate_data`,
+//! `populate_tick_data`, factory discovery, ...). RPC providers are used as is.
+
+use std::{
+    marker::PhantomData,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use alloy::{
+    network::Network,
+    primitives::{Address, Bytes, U64},
+    providers::{Provider, ProviderBuilder},
+    rpc::{
+        client::RpcClient,
+        json_rpc::{
+            ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload,
+            SerializedRequest,
+        },
+        types::eth::{BlockId, BlockNumberOrTag, Filter, Log, TransactionRequest},
+    },
+    transports::{Transport, TransportError, TransportErrorKind, TransportFut},
+};
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{value::RawValue, Value};
+use tower::Service;
+
+use crate::errors::AMMError;
+
+/// Source of the chain data needed to populate, sync and discover AMMs.
+///
+/// `block_number` is `None` for the latest block.
+#[async_trait]
+pub trait DataSource: Send + Sync + 'static {
+    /// Returns the latest block number.
+    async fn block_number(&self) -> Result<u64, AMMError>;
+
+    /// Returns the chain id.
+    async fn chain_id(&self) -> Result<u64, AMMError>;
+
+    /// Returns the logs matching `filter`.
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, AMMError>;
+
+    /// Executes a static call and returns its return data.
+    ///
+    /// Calls without a `to` address deploy and run the code in `input`, like the batch request contracts of the crate.
+    async fn call(
+        &self,
+        tx: &TransactionRequest,
+        block_number: Option<u64>,
+    ) -> Result<Bytes, AMMError>;
+
+    /// Returns the runtime bytecode at `address`.
+    async fn get_code(
+        &self,
+        address: Address,
+        block_number: Option<u64>,
+    ) -> Result<Bytes, AMMError>;
+}
+
+/// [`DataSource`] backed by a JSON-RPC provider, e.g. to be wrapped by a caching data source.
+#[derive(Debug)]
+pub struct RpcDataSource<T, N, P> {
+    provider: Arc<P>,
+    transport: PhantomData<T>,
+    network: PhantomData<N>,
+}
+
+impl<T, N, P> RpcDataSource<T, N, P> {
+    pub fn new(provider: Arc<P>) -> Self {
+        Self {
+            provider,
+            transport: PhantomData,
+            network: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, N, P> DataSource for RpcDataSource<T, N, P>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    async fn block_number(&self) -> Result<u64, AMMError> {
+        Ok(self.provider.get_block_number().await?)
+    }
+
+    async fn chain_id(&self) -> Result<u64, AMMError> {
+        Ok(self.provider.get_chain_id().await?)
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, AMMError> {
+        Ok(self.provider.get_logs(filter).await?)
+    }
+
+    async fn call(
+        &self,
+        tx: &TransactionRequest,
+        block_number: Option<u64>,
+    ) -> Result<Bytes, AMMError> {
+        Ok(self
+            .provider
+            .client()
+            .request("eth_call", (tx, block_id(block_number)))
+            .await?)
+    }
+
+    async fn get_code(
+        &self,
+        address: Address,
+        block_number: Option<u64>,
+    ) -> Result<Bytes, AMMError> {
+        Ok(self
+            .provider
+            .client()
+            .request("eth_getCode", (address, block_id(block_number)))
+            .await?)
+    }
+}
+
+/// Transport that answers requests from a [`DataSource`].
+///
+/// Supports `eth_blockNumber`, `eth_chainId`, `eth_getLogs`, `eth_call` (without state overrides) and `eth_getCode`.
+/// Failed requests of a batch are answered with an error payload, so that they do not fail the rest of the batch.
+#[derive(Debug)]
+pub struct DataSourceTransport<D> {
+    source: Arc<D>,
+}
+
+impl<D> DataSourceTransport<D> {
+    pub fn new(source: Arc<D>) -> Self {
+        Self { source }
+    }
+}
+
+impl<D> Clone for DataSourceTransport<D> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+        }
+    }
+}
+
+impl<D: DataSource> Service<RequestPacket> for DataSourceTransport<D> {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let source = self.source.clone();
+
+        Box::pin(async move {
+            match request {
+                RequestPacket::Single(request) => {
+                    Ok(ResponsePacket::Single(respond(&*source, &request).await?))
+                }
+                RequestPacket::Batch(requests) => {
+                    let mut responses = Vec::with_capacity(requests.len());
+                    for request in requests.iter() {
+                        let response = match respond(&*source, request).await {
+                            Ok(response) => response,
+                            Err(err) => error_response(request, err),
+                        };
+                        responses.push(response);
+                    }
+
+                    Ok(ResponsePacket::Batch(responses))
+                }
+            }
+        })
+    }
+}
+
+/// Returns a provider that serves requests from `source`.
+pub fn data_source_provider<D: DataSource>(
+    source: Arc<D>,
+) -> impl Provider<DataSourceTransport<D>> {
+    ProviderBuilder::new().on_client(RpcClient::new(DataSourceTransport::new(source), true))
+}
+
+async fn respond<D: DataSource>(
+    source: &D,
+    request: &SerializedRequest,
+) -> Result<Response, TransportError> {
+    let params: Vec<Value> = match request.params() {
+        Some(params) => serde_json::from_str(params.get()).map_err(TransportError::deser_err)?,
+        None => vec![],
+    };
+
+    let result = match request.method() {
+        "eth_blockNumber" => to_raw(&U64::from(
+            source.block_number().await.map_err(transport_error)?,
+        ))?,
+
+        "eth_chainId" => to_raw(&U64::from(
+            source.chain_id().await.map_err(transport_error)?,
+        ))?,
+
+        "eth_getLogs" => {
+            let filter: Filter = param(&params, 0)?;
+            to_raw(&source.get_logs(&filter).await.map_err(transport_error)?)?
+        }
+
+        "eth_call" => {
+            if params.len() > 2 {
+                return Err(TransportErrorKind::custom_str(
+                    "state overrides are not supported by data sources",
+                ));
+            }
+
+            let tx: TransactionRequest = param(&params, 0)?;
+            let block_number = block_number(param(&params, 1)?)?;
+            to_raw(
+                &source
+                    .call(&tx, block_number)
+                    .await
+                    .map_err(transport_error)?,
+            )?
+        }
+
+        "eth_getCode" => {
+            let address: Address = param(&params, 0)?;
+            let block_number = block_number(param(&params, 1)?)?;
+            to_raw(
+                &source
+                    .get_code(address, block_number)
+                    .await
+                    .map_err(transport_error)?,
+            )?
+        }
+
+        method => {
+            return Err(TransportErrorKind::custom_str(&format!(
+                "{method} is not supported by data sources"
+            )))
+        }
+    };
+
+    Ok(Response {
+        id: request.id().clone(),
+        payload: ResponsePayload::Success(result),
+    })
+}
+
+/// Returns the error response to a request of a batch.
+fn error_response(request: &SerializedRequest, err: TransportError) -> Response {
+    Response {
+        id: request.id().clone(),
+        payload: ResponsePayload::Failure(ErrorPayload {
+            // Server error of the JSON-RPC spec
+            code: -32000,
+            message: err.to_string().into(),
+            data: None,
+        }),
+    }
+}
+
+fn param<T: DeserializeOwned>(params: &[Value], idx: usize) -> Result<T, TransportError> {
+    serde_json::from_value(params.get(idx).cloned().unwrap_or(Value::Null))
+        .map_err(TransportError::deser_err)
+}
+
+fn to_raw<T: Serialize>(value: &T) -> Result<Box<RawValue>, TransportError> {
+    serde_json::value::to_raw_value(value).map_err(TransportError::ser_err)
+}
+
+fn transport_error(err: AMMError) -> TransportError {
+    match err {
+        AMMError::TransportError(err) => err,
+        err => TransportErrorKind::custom_str(&err.to_string()),
+    }
+}
+
+fn block_id(block_number: Option<u64>) -> BlockId {
+    block_number.map_or(BlockId::Number(BlockNumberOrTag::Latest), BlockId::from)
+}
+
+fn block_number(block: Option<BlockId>) -> Result<Option<u64>, TransportError> {
+    match block {
+        Some(BlockId::Number(BlockNumberOrTag::Number(block_number))) => Ok(Some(block_number)),
+        Some(BlockId::Hash(_)) => Err(TransportErrorKind::custom_str(
+            "block hashes are not supported by data sources",
+        )),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{
+        primitives::{address, Address, Bytes},
+        providers::Provider,
+        rpc::{
+            json_rpc::{Id, Request, RequestPacket, ResponsePacket, ResponsePayload},
+            types::eth::{Filter, Log, TransactionRequest},
+        },
+    };
+    use async_trait::async_trait;
+    use serde_json::Value;
+    use tower::Service;
+
+    use crate::errors::AMMError;
+
+    use super::{data_source_provider, DataSource, DataSourceTransport};
+
+    struct MockDataSource;
+
+    #[async_trait]
+    impl DataSource for MockDataSource {
+        async fn block_number(&self) -> Result<u64, AMMError> {
+            Ok(19_000_000)
+        }
+
+        async fn chain_id(&self) -> Result<u64, AMMError> {
+            Ok(1)
+        }
+
+        async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>, AMMError> {
+            Ok(vec![])
+        }
+
+        async fn call(
+            &self,
+            _tx: &TransactionRequest,
+            _block_number: Option<u64>,
+        ) -> Result<Bytes, AMMError> {
+            Err(AMMError::PoolDataError)
+        }
+
+        async fn get_code(
+            &self,
+            _address: Address,
+            block_number: Option<u64>,
+        ) -> Result<Bytes, AMMError> {
+            Ok(Bytes::from(block_number.unwrap_or_default().to_be_bytes()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_data_source_provider() {
+        let provider = data_source_provider(Arc::new(MockDataSource));
+
+        assert_eq!(provider.get_block_number().await.unwrap(), 19_000_000);
+        assert_eq!(provider.get_chain_id().await.unwrap(), 1);
+        assert!(provider.get_logs(&Filter::new()).await.unwrap().is_empty());
+        assert_eq!(
+            provider
+                .client()
+                .request::<_, Bytes>(
+                    "eth_getCode",
+                    (address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"), "0x64")
+                )
+                .await
+                .unwrap(),
+            Bytes::from(100_u64.to_be_bytes())
+        );
+        assert!(provider.get_gas_price().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_with_failed_request() {
+        let mut transport = DataSourceTransport::new(Arc::new(MockDataSource));
+        let request = |id, method: &'static str| {
+            Request::new(method, Id::Number(id), Vec::<Value>::new())
+                .serialize()
+                .unwrap()
+        };
+
+        // The unsupported request fails on its own, without failing the batch
+        let ResponsePacket::Batch(responses) = transport
+            .call(RequestPacket::Batch(vec![
+                request(1, "eth_blockNumber"),
+                request(2, "eth_gasPrice"),
+            ]))
+            .await
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        assert!(matches!(responses[0].payload, ResponsePayload::Success(_)));
+        assert!(matches!(responses[1].payload, ResponsePayload::Failure(_)));
+    }
+}
@@ -32,6 +32,10 @@ impl DiscoverableFactory {
 }
 
 // Returns a vec of empty factories that match one of the Factory interfaces specified by each DiscoverableFactory
+#[cfg_attr(
+    feature = "tracing-spans",
+    tracing::instrument(skip(factories, provider), level = "debug")
+)]
 pub async fn discover_factories<T, N, P>(
     factories: Vec<DiscoverableFactory>,
     number_of_amms_threshold: u64,
@@ -74,6 +78,9 @@ where
             .get_logs(&block_filter.from_block(from_block).to_block(target_block))
             .await?;
 
+        #[cfg(feature = "tracing-spans")]
+        tracing::debug!(from_block, target_block, logs = logs.len(), "Fetched logs");
+
         for log in logs {
             tracing::trace!("found matching event at factory {}", log.address());
             if let Some((_, amms_length)) = identified_factories.get_mut(&log.address()) {
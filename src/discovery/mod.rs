@@ -1,2 +1,3 @@
 pub mod erc_4626;
 pub mod factory;
+pub mod subgraph;
@@ -0,0 +1,173 @@
+//! Discovery of Uniswap V3 pools and tick data from a Uniswap V3 subgraph, as an alternative to replaying logs.
+
+use futures::{stream, StreamExt, TryStreamExt};
+use serde_json::{json, Value};
+
+use crate::{amm::uniswap_v3::UniswapV3Pool, errors::AMMError};
+
+/// Max number of entities per subgraph query.
+pub const PAGE_SIZE: usize = 1000;
+
+/// Max number of concurrent tick queries.
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+const POOLS_QUERY: &str = r#"
+query Pools($block: Int!, $lastId: String!, $first: Int!) {
+    pools(block: { number: $block }, first: $first, orderBy: id, where: { id_gt: $lastId, liquidity_gt: 0 }) {
+        id
+        token0 { id decimals }
+        token1 { id decimals }
+        feeTier
+        liquidity
+        sqrtPrice
+        tick
+    }
+}
+"#;
+
+const TICKS_QUERY: &str = r#"
+query Ticks($block: Int!, $pool: String!, $lastTick: BigInt!, $first: Int!) {
+    ticks(block: { number: $block }, first: $first, orderBy: tickIdx, where: { pool: $pool, tickIdx_gt: $lastTick, liquidityGross_gt: 0 }) {
+        tickIdx
+        liquidityGross
+        liquidityNet
+    }
+}
+"#;
+
+const META_QUERY: &str = "{ _meta { block { number } } }";
+
+/// Loads all Uniswap V3 pools with liquidity from the subgraph at `subgraph_url`.
+///
+/// All queries are pinned to `block_number`, or to the latest block indexed by the subgraph if `None`.
+/// Tick data is loaded if `include_ticks` is set.
+/// Returns the pools and the block number of their state, from which the pools can be kept in sync through logs.
+pub async fn get_uniswap_v3_pools(
+    subgraph_url: &str,
+    block_number: Option<u64>,
+    include_ticks: bool,
+) -> Result<(Vec<UniswapV3Pool>, u64), AMMError> {
+    let client = reqwest::Client::new();
+
+    let block_number = match block_number {
+        Some(block_number) => block_number,
+        None => {
+            let meta = query(&client, subgraph_url, META_QUERY, json!({})).await?;
+            meta["_meta"]["block"]["number"]
+                .as_u64()
+                .ok_or(AMMError::SubgraphError("Missing indexed block".to_string()))?
+        }
+    };
+
+    let mut pools = vec![];
+    let mut last_id = String::new();
+    loop {
+        let data = query(
+            &client,
+            subgraph_url,
+            POOLS_QUERY,
+            json!({ "block": block_number, "lastId": last_id, "first": PAGE_SIZE }),
+        )
+        .await?;
+
+        let page = entities(&data, "pools")?;
+        for pool in page {
+            pools.push(UniswapV3Pool::from_subgraph(pool)?);
+        }
+
+        match page.last().and_then(|pool| pool["id"].as_str()) {
+            Some(id) if page.len() == PAGE_SIZE => last_id = id.to_string(),
+            _ => break,
+        }
+    }
+
+    tracing::info!(
+        pools = pools.len(),
+        block_number,
+        "Loaded pools from subgraph"
+    );
+
+    if include_ticks {
+        pools = stream::iter(pools)
+            .map(|mut pool| {
+                let client = client.clone();
+                async move {
+                    load_ticks(&client, subgraph_url, block_number, &mut pool).await?;
+                    Ok::<_, AMMError>(pool)
+                }
+            })
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .try_collect()
+            .await?;
+    }
+
+    Ok((pools, block_number))
+}
+
+/// Loads the initialized ticks of `pool` from the subgraph at `subgraph_url`.
+async fn load_ticks(
+    client: &reqwest::Client,
+    subgraph_url: &str,
+    block_number: u64,
+    pool: &mut UniswapV3Pool,
+) -> Result<(), AMMError> {
+    // Ticks are paged by index, starting below the min tick
+    let mut last_tick = (uniswap_v3_math::tick_math::MIN_TICK - 1).to_string();
+    loop {
+        let data = query(
+            client,
+            subgraph_url,
+            TICKS_QUERY,
+            json!({
+                "block": block_number,
+                "pool": pool.address.to_string().to_lowercase(),
+                "lastTick": last_tick,
+                "first": PAGE_SIZE,
+            }),
+        )
+        .await?;
+
+        let page = entities(&data, "ticks")?;
+        pool.insert_subgraph_ticks(page)?;
+
+        match page.last().and_then(|tick| tick["tickIdx"].as_str()) {
+            Some(tick) if page.len() == PAGE_SIZE => last_tick = tick.to_string(),
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Executes a GraphQL query, returning its `data`.
+async fn query(
+    client: &reqwest::Client,
+    subgraph_url: &str,
+    query: &str,
+    variables: Value,
+) -> Result<Value, AMMError> {
+    let body = serde_json::to_string(&json!({ "query": query, "variables": variables }))?;
+
+    let response = client
+        .post(subgraph_url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    let mut response: Value = serde_json::from_str(&response)?;
+    if let Some(errors) = response.get("errors") {
+        return Err(AMMError::SubgraphError(errors.to_string()));
+    }
+
+    Ok(response["data"].take())
+}
+
+fn entities<'a>(data: &'a Value, entity: &str) -> Result<&'a Vec<Value>, AMMError> {
+    data[entity]
+        .as_array()
+        .ok_or(AMMError::SubgraphError(format!(
+            "Missing {entity} in response"
+        )))
+}
@@ -1,21 +1,26 @@
-use alloy::primitives::{Address, U256};
+use alloy::primitives::{Address, Bytes, I256, U256};
+#[cfg(feature = "provider")]
 use alloy::transports::TransportError;
 
-use std::time::SystemTimeError;
+use std::{fmt, time::SystemTimeError};
 use thiserror::Error;
+#[cfg(feature = "provider")]
 use tokio::task::JoinError;
 use uniswap_v3_math::error::UniswapV3MathError;
 
 #[derive(Error, Debug)]
 pub enum AMMError {
+    #[cfg(feature = "provider")]
     #[error(transparent)]
     TransportError(#[from] TransportError),
+    #[cfg(feature = "provider")]
     #[error(transparent)]
     ContractError(#[from] alloy::contract::Error),
     #[error(transparent)]
     ABICodecError(#[from] alloy::dyn_abi::Error),
     #[error(transparent)]
     EthABIError(#[from] alloy::sol_types::Error),
+    #[cfg(feature = "provider")]
     #[error(transparent)]
     JoinError(#[from] JoinError),
     #[error(transparent)]
@@ -56,6 +61,160 @@ pub enum AMMError {
     CheckpointError(#[from] CheckpointError),
     #[error(transparent)]
     EyreError(#[from] eyre::Error),
+    #[cfg(feature = "provider")]
+    #[error(transparent)]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("Subgraph error: {0}")]
+    SubgraphError(String),
+    #[error("On-chain swap simulation is not supported for the AMM at {0}")]
+    UnsupportedOnchainSimulation(Address),
+    #[error("Unexpected swap executor output: {0}")]
+    UnexpectedSwapOutput(Bytes),
+    #[error("{context} failed: {source}")]
+    Context {
+        context: ErrorContext,
+        #[source]
+        source: Box<AMMError>,
+    },
+}
+
+impl AMMError {
+    /// Wraps the error with the pool, block and operation during which it occurred.
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        AMMError::Context {
+            context,
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the outermost context of the error, if any.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            AMMError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Returns the underlying error, without its contexts.
+    pub fn root_cause(&self) -> &AMMError {
+        match self {
+            AMMError::Context { source, .. } => source.root_cause(),
+            err => err,
+        }
+    }
+
+    /// Returns whether the error originates from the provider or a remote data source, as opposed to the pool logic.
+    ///
+    /// Provider errors are usually transient and can be retried, logic errors are not.
+    pub fn is_provider_error(&self) -> bool {
+        match self.root_cause() {
+            #[cfg(feature = "provider")]
+            AMMError::TransportError(_)
+            | AMMError::ContractError(_)
+            | AMMError::ReqwestError(_) => true,
+            AMMError::SubgraphError(_)
+            | AMMError::BatchRequestError(_)
+            | AMMError::PoolDataError => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the error originates from the pool logic (e.g. arithmetic, swap simulation or log decoding).
+    pub fn is_logic_error(&self) -> bool {
+        matches!(
+            self.root_cause(),
+            AMMError::ArithmeticError(_)
+                | AMMError::SwapSimulationError(_)
+                | AMMError::EventLogError(_)
+                | AMMError::UniswapV3MathError(_)
+                | AMMError::NoInitializedTicks
+                | AMMError::NoLiquidityNet
+                | AMMError::IncongruentAMMs
+                | AMMError::InvalidERC4626Fee
+                | AMMError::UnrecognizedPoolCreatedEventLog
+        )
+    }
+}
+
+/// Operation during which an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Populate,
+    Sync,
+    SyncFromLog,
+    Simulate,
+    Discover,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Populate => write!(f, "populate"),
+            Operation::Sync => write!(f, "sync"),
+            Operation::SyncFromLog => write!(f, "sync from log"),
+            Operation::Simulate => write!(f, "simulate"),
+            Operation::Discover => write!(f, "discover"),
+        }
+    }
+}
+
+/// Pool, block and operation during which an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub operation: Operation,
+    pub address: Option<Address>,
+    pub block_number: Option<u64>,
+}
+
+impl ErrorContext {
+    pub fn new(operation: Operation, address: Option<Address>, block_number: Option<u64>) -> Self {
+        Self {
+            operation,
+            address,
+            block_number,
+        }
+    }
+
+    pub fn populate(address: Address, block_number: Option<u64>) -> Self {
+        Self::new(Operation::Populate, Some(address), block_number)
+    }
+
+    pub fn sync(address: Address) -> Self {
+        Self::new(Operation::Sync, Some(address), None)
+    }
+
+    pub fn sync_from_log(address: Address, block_number: Option<u64>) -> Self {
+        Self::new(Operation::SyncFromLog, Some(address), block_number)
+    }
+
+    pub fn simulate(address: Address) -> Self {
+        Self::new(Operation::Simulate, Some(address), None)
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(address) = self.address {
+            write!(f, " of {address}")?;
+        }
+        if let Some(block_number) = self.block_number {
+            write!(f, " at block {block_number}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Attaches an [`ErrorContext`] to the error of a result.
+pub trait ResultExt<T> {
+    fn context(self, context: ErrorContext) -> Result<T, AMMError>;
+}
+
+impl<T, E: Into<AMMError>> ResultExt<T> for Result<T, E> {
+    fn context(self, context: ErrorContext) -> Result<T, AMMError> {
+        self.map_err(|err| err.into().with_context(context))
+    }
 }
 
 #[derive(Error, Debug)]
@@ -70,6 +229,20 @@ pub enum ArithmeticError {
     SqrtPriceOverflow,
     #[error("U128 conversion error")]
     U128ConversionError,
+    #[error("Virtual reserves overflow u128")]
+    ReserveOverflow,
+    #[error("Liquidity underflow")]
+    LiquidityUnderflow,
+    #[error("Invariant did not converge")]
+    InvariantDidNotConverge,
+    #[error("Unsafe values for the invariant")]
+    UnsafeInvariantValues,
+    #[error("Price is not a finite positive number")]
+    InvalidPrice,
+    #[error("Tick {0} is out of bounds")]
+    TickOutOfBounds(i32),
+    #[error("Sqrt price {0} is out of bounds")]
+    SqrtPriceOutOfBounds(U256),
     #[error(transparent)]
     UniswapV3MathError(#[from] UniswapV3MathError),
 }
@@ -78,8 +251,14 @@ pub enum ArithmeticError {
 pub enum EventLogError {
     #[error("Invalid event signature")]
     InvalidEventSignature,
+    #[error("Invalid event data")]
+    InvalidEventData,
     #[error("Log Block number not found")]
     LogBlockNumberNotFound,
+    #[error("Reserve underflow applying log amounts")]
+    ReserveUnderflow,
+    #[error("Reserve overflow applying log amounts")]
+    ReserveOverflow,
     #[error(transparent)]
     EthABIError(#[from] alloy::sol_types::Error),
     #[error(transparent)]
@@ -94,6 +273,44 @@ pub enum SwapSimulationError {
     UniswapV3MathError(#[from] UniswapV3MathError),
     #[error("Liquidity underflow")]
     LiquidityUnderflow,
+    #[error("Tick data for word {0} is not loaded")]
+    TickWordNotLoaded(i16),
+    #[error("Insufficient liquidity for the amount out")]
+    InsufficientLiquidity,
+    #[error("Exact output swaps are not supported by the AMM")]
+    UnsupportedExactOutput,
+    #[error("Invalid fee: {0}")]
+    InvalidFee(u32),
+    #[error("Invalid tick spacing: {0}")]
+    InvalidTickSpacing(i32),
+    #[error("Invalid tick range: {0}..{1}")]
+    InvalidTickRange(i32, i32),
+    #[error("A price source is required to convert the gas cost from the native token to {0}")]
+    MissingPriceSource(Address),
+    #[error("Price source {0} does not swap the native token for the token out")]
+    InvalidPriceSource(Address),
+    #[error(transparent)]
+    ArithmeticError(#[from] ArithmeticError),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PoolBuilderError {
+    #[error("Missing required field: {0}")]
+    MissingField(&'static str),
+    #[error("Pool tokens are identical")]
+    IdenticalTokens,
+    #[error("Pool tokens are not sorted")]
+    UnsortedTokens,
+    #[error("Invalid fee: {0}")]
+    InvalidFee(u32),
+    #[error("Invalid tick spacing: {0}")]
+    InvalidTickSpacing(i32),
+    #[error("Invalid tick: {0}")]
+    InvalidTick(i32),
+    #[error("Invalid sqrt price: {0}")]
+    InvalidSqrtPrice(U256),
+    #[error("Balances and parameters do not define a valid invariant")]
+    InvalidInvariant,
 }
 
 #[derive(Error, Debug)]
@@ -104,4 +321,92 @@ pub enum CheckpointError {
     SerdeJsonError(#[from] serde_json::error::Error),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
+    #[cfg(feature = "bincode")]
+    #[error(transparent)]
+    BincodeError(#[from] bincode::Error),
+    #[error("Invalid binary header")]
+    InvalidBinaryHeader,
+    #[error("Unsupported binary format version: {0}")]
+    UnsupportedBinaryVersion(u32),
+    #[error("Unsupported checkpoint version: {0}")]
+    UnsupportedCheckpointVersion(u64),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ExecutionError {
+    #[error("Route has no pools")]
+    EmptyRoute,
+    #[error("Pool {0} is not supported by the router")]
+    UnsupportedPool(Address),
+    #[error("Token {0} is not traded by pool {1}")]
+    TokenNotInPool(Address, Address),
+    #[error("Swap simulation in pool {0} failed: {1}")]
+    SwapSimulationFailed(Address, String),
+    #[error("Amount out {amount_out} is below the minimum amount out {amount_out_min}")]
+    InsufficientOutputAmount {
+        amount_out: U256,
+        amount_out_min: U256,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum RfqError {
+    #[cfg(feature = "provider")]
+    #[error(transparent)]
+    ReqwestError(#[from] reqwest::Error),
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::error::Error),
+    #[error("Quote request rejected: {0}")]
+    QuoteRejected(String),
+    #[error("Invalid quote response: {0}")]
+    InvalidResponse(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ChainlinkError {
+    #[cfg(feature = "provider")]
+    #[error(transparent)]
+    ContractError(#[from] alloy::contract::Error),
+    #[error("No feed for token {0}")]
+    FeedNotFound(Address),
+    #[error("Invalid answer of feed {0}: {1}")]
+    InvalidAnswer(Address, I256),
+    #[error("Stale answer of feed {0}, updated at {1}")]
+    StaleAnswer(Address, u64),
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::address, transports::TransportErrorKind};
+
+    use super::{AMMError, ErrorContext, EventLogError, Operation, ResultExt};
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_context() {
+        assert_send_sync::<AMMError>();
+
+        let address = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let result: Result<(), EventLogError> = Err(EventLogError::InvalidEventSignature);
+        let err = result
+            .context(ErrorContext::sync_from_log(address, Some(100)))
+            .unwrap_err();
+
+        assert_eq!(
+            err.context().map(|context| context.operation),
+            Some(Operation::SyncFromLog)
+        );
+        assert!(err.is_logic_error() && !err.is_provider_error());
+        assert_eq!(
+            err.to_string(),
+            format!("sync from log of {address} at block 100 failed: Invalid event signature")
+        );
+
+        let err = AMMError::TransportError(TransportErrorKind::custom_str("timeout"))
+            .with_context(ErrorContext::populate(address, None))
+            .with_context(ErrorContext::new(Operation::Discover, None, Some(100)));
+        assert!(err.is_provider_error());
+        assert!(matches!(err.root_cause(), AMMError::TransportError(_)));
+    }
 }
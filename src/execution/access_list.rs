@@ -0,0 +1,233 @@
+//! EIP-2930 access lists for swaps along a [`Route`].
+
+use alloy::{
+    primitives::{keccak256, Address, B256, U256},
+    rpc::types::eth::{AccessList, AccessListItem},
+};
+
+use crate::{
+    amm::{uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, AMM},
+    core::uniswap_v3::next_initialized_tick_within_one_word,
+    errors::ExecutionError,
+    routing::Route,
+};
+
+/// Storage slot of `token0` in a UniswapV2Pair.
+pub const V2_TOKEN_0_SLOT: u64 = 6;
+/// Storage slot of `token1` in a UniswapV2Pair.
+pub const V2_TOKEN_1_SLOT: u64 = 7;
+/// Storage slot of the packed `reserve0`, `reserve1` and `blockTimestampLast` in a UniswapV2Pair.
+pub const V2_RESERVES_SLOT: u64 = 8;
+/// Storage slot of `price0CumulativeLast` in a UniswapV2Pair.
+pub const V2_PRICE_0_CUMULATIVE_SLOT: u64 = 9;
+/// Storage slot of `price1CumulativeLast` in a UniswapV2Pair.
+pub const V2_PRICE_1_CUMULATIVE_SLOT: u64 = 10;
+/// Storage slot of the reentrancy lock in a UniswapV2Pair.
+pub const V2_UNLOCKED_SLOT: u64 = 12;
+
+/// Storage slot of `slot0` in a UniswapV3Pool.
+pub const V3_SLOT_0_SLOT: u64 = 0;
+/// Storage slot of `feeGrowthGlobal0X128` in a UniswapV3Pool.
+pub const V3_FEE_GROWTH_GLOBAL_0_SLOT: u64 = 1;
+/// Storage slot of `feeGrowthGlobal1X128` in a UniswapV3Pool.
+pub const V3_FEE_GROWTH_GLOBAL_1_SLOT: u64 = 2;
+/// Storage slot of `protocolFees` in a UniswapV3Pool.
+pub const V3_PROTOCOL_FEES_SLOT: u64 = 3;
+/// Storage slot of `liquidity` in a UniswapV3Pool.
+pub const V3_LIQUIDITY_SLOT: u64 = 4;
+/// Storage slot of the `ticks` mapping in a UniswapV3Pool.
+pub const V3_TICKS_SLOT: u64 = 5;
+/// Storage slot of the `tickBitmap` mapping in a UniswapV3Pool.
+pub const V3_TICK_BITMAP_SLOT: u64 = 6;
+/// Number of storage slots of a `Tick.Info`.
+pub const V3_TICK_INFO_SLOTS: u64 = 4;
+
+/// Returns the access list of a swap along `route`: the pools with the storage slots read and written by their swaps,
+/// and the token contracts.
+///
+/// Uniswap V3 pools include the tick bitmap word of the current tick and the next initialized tick in the direction
+/// of the swap, further ticks crossed by large swaps are not included. Other AMMs and tokens are included without
+/// storage keys, as their layout is not known.
+pub fn access_list(route: &Route) -> Result<AccessList, ExecutionError> {
+    let tokens = route.tokens()?;
+    let mut items: Vec<AccessListItem> = vec![];
+
+    for (pool, token_in) in route.pools.iter().zip(tokens.iter()) {
+        let storage_keys = match pool {
+            AMM::UniswapV2Pool(_) => v2_storage_keys(),
+            AMM::UniswapV3Pool(pool) => v3_storage_keys(pool, *token_in == pool.token_a),
+            _ => vec![],
+        };
+
+        insert(&mut items, pool.address(), storage_keys);
+    }
+
+    for token in tokens {
+        insert(&mut items, token, vec![]);
+    }
+
+    Ok(AccessList(items))
+}
+
+/// Returns the storage slots read and written by a swap in a Uniswap V2 pair.
+pub fn v2_storage_keys() -> Vec<B256> {
+    [
+        V2_TOKEN_0_SLOT,
+        V2_TOKEN_1_SLOT,
+        V2_RESERVES_SLOT,
+        V2_PRICE_0_CUMULATIVE_SLOT,
+        V2_PRICE_1_CUMULATIVE_SLOT,
+        V2_UNLOCKED_SLOT,
+    ]
+    .into_iter()
+    .map(slot)
+    .collect()
+}
+
+/// Returns the storage slots read and written by a swap in a Uniswap V3 pool, up to the next initialized tick.
+pub fn v3_storage_keys(pool: &UniswapV3Pool, zero_for_one: bool) -> Vec<B256> {
+    let fee_growth_global_slot = if zero_for_one {
+        V3_FEE_GROWTH_GLOBAL_0_SLOT
+    } else {
+        V3_FEE_GROWTH_GLOBAL_1_SLOT
+    };
+
+    let mut storage_keys: Vec<B256> = [
+        V3_SLOT_0_SLOT,
+        fee_growth_global_slot,
+        V3_PROTOCOL_FEES_SLOT,
+        V3_LIQUIDITY_SLOT,
+    ]
+    .into_iter()
+    .map(slot)
+    .collect();
+
+    if pool.tick_spacing == 0 {
+        return storage_keys;
+    }
+
+    let compressed = pool.tick.div_euclid(pool.tick_spacing);
+    let word_pos = if zero_for_one {
+        compressed >> 8
+    } else {
+        (compressed + 1) >> 8
+    };
+    storage_keys.push(mapping_slot(word_pos as i64, V3_TICK_BITMAP_SLOT));
+
+    if let Ok((tick_next, true)) =
+        next_initialized_tick_within_one_word(pool, pool.tick, pool.tick_spacing, zero_for_one)
+    {
+        let tick_slot = U256::from_be_bytes(mapping_slot(tick_next as i64, V3_TICKS_SLOT).0);
+        storage_keys.extend(
+            (0..V3_TICK_INFO_SLOTS).map(|offset| B256::from(tick_slot + U256::from(offset))),
+        );
+    }
+
+    storage_keys
+}
+
+/// Returns the storage key of `slot`.
+fn slot(slot: u64) -> B256 {
+    B256::from(U256::from(slot))
+}
+
+/// Returns the storage key of `key` in a mapping at `slot`, `keccak256(abi.encode(key, slot))`.
+fn mapping_slot(key: i64, slot: u64) -> B256 {
+    // Signed keys are sign extended
+    let mut preimage = [if key < 0 { 0xff } else { 0 }; 64];
+    preimage[24..32].copy_from_slice(&key.to_be_bytes());
+    preimage[32..].copy_from_slice(&U256::from(slot).to_be_bytes::<32>());
+    keccak256(preimage)
+}
+
+/// Adds `storage_keys` of `address` to `items`, merging them with an existing item of `address`.
+fn insert(items: &mut Vec<AccessListItem>, address: Address, storage_keys: Vec<B256>) {
+    let item = match items.iter_mut().find(|item| item.address == address) {
+        Some(item) => item,
+        None => {
+            items.push(AccessListItem {
+                address,
+                storage_keys: vec![],
+            });
+            items.last_mut().unwrap()
+        }
+    };
+
+    for key in storage_keys {
+        if !item.storage_keys.contains(&key) {
+            item.storage_keys.push(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::{address, keccak256, U256};
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AMM},
+        routing::Route,
+    };
+
+    use super::{access_list, mapping_slot, V3_TICK_BITMAP_SLOT};
+
+    #[test]
+    fn test_access_list() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let link = address!("514910771af9ca656af840dff83e8264ecf986ca");
+
+        let usdc_weth = UniswapV2Pool {
+            address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            token_a: usdc,
+            token_b: weth,
+            ..Default::default()
+        };
+        let mut tick_bitmap = HashMap::new();
+        tick_bitmap.insert(0, U256::from(1) << 10);
+        let link_weth = UniswapV3Pool {
+            address: address!("a6Cc3C2531FdaA6Ae1A3CA84c2855806728693e8"),
+            token_a: link,
+            token_b: weth,
+            tick: 100,
+            tick_spacing: 60,
+            tick_bitmap,
+            ..Default::default()
+        };
+
+        let route = Route::new(
+            usdc,
+            vec![
+                AMM::UniswapV2Pool(usdc_weth.clone()),
+                AMM::UniswapV3Pool(link_weth.clone()),
+            ],
+        );
+        let access_list = access_list(&route).unwrap();
+
+        let addresses = access_list
+            .0
+            .iter()
+            .map(|item| item.address)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            addresses,
+            vec![usdc_weth.address, link_weth.address, usdc, weth, link]
+        );
+
+        assert_eq!(access_list.0[0].storage_keys.len(), 6);
+        // slot0, fee growth, protocol fees, liquidity, bitmap word and the 4 slots of tick 600
+        assert_eq!(access_list.0[1].storage_keys.len(), 9);
+        assert!(access_list.0[1]
+            .storage_keys
+            .contains(&mapping_slot(0, V3_TICK_BITMAP_SLOT)));
+        assert!(access_list.0[2].storage_keys.is_empty());
+
+        // keccak256(abi.encode(int16(-1), uint256(6)))
+        assert_eq!(
+            mapping_slot(-1, V3_TICK_BITMAP_SLOT),
+            keccak256([[0xff; 32], U256::from(6).to_be_bytes::<32>()].concat())
+        );
+    }
+}
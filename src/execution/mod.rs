@@ -0,0 +1,400 @@
+//! Router calldata for executing [`Route`]s.
+//!
+//! Encodes `exactInputSingle`/`exactInput` calls of Uniswap's SwapRouter02, `swapExactTokensForTokens` calls of the
+//! Uniswap V2 router and `execute` calls of the UniversalRouter. Multi-hop Uniswap V3 routes are encoded as packed
+//! paths of tokens and fees, Uniswap V2 routes as token arrays.
+
+pub mod access_list;
+
+use alloy::{
+    primitives::{address, Address, Bytes, U256},
+    sol,
+    sol_types::{SolCall, SolValue},
+};
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    errors::ExecutionError,
+    routing::Route,
+};
+
+/// Address of SwapRouter02 on Ethereum mainnet.
+pub const SWAP_ROUTER_02_ADDRESS: Address = address!("68b3465833fb72A70ecDF485E0e4C7bD8665Fc45");
+
+/// Address of the Uniswap V2 router on Ethereum mainnet.
+pub const UNISWAP_V2_ROUTER_ADDRESS: Address = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+
+/// Address of the UniversalRouter on Ethereum mainnet.
+pub const UNIVERSAL_ROUTER_ADDRESS: Address = address!("3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD");
+
+/// UniversalRouter command swapping an exact amount in through a Uniswap V3 path.
+pub const V3_SWAP_EXACT_IN: u8 = 0x00;
+
+/// UniversalRouter command swapping an exact amount in through a Uniswap V2 path.
+pub const V2_SWAP_EXACT_IN: u8 = 0x08;
+
+/// UniversalRouter recipient standing for the router itself, holding the output between commands.
+pub const ADDRESS_THIS: Address = address!("0000000000000000000000000000000000000002");
+
+/// UniversalRouter amount standing for the whole balance of the router.
+pub const CONTRACT_BALANCE: U256 = U256::from_limbs([0, 0, 0, 1 << 63]);
+
+sol! {
+    /// Interface of SwapRouter02
+    #[derive(Debug, PartialEq, Eq)]
+    contract ISwapRouter02 {
+        struct ExactInputSingleParams {
+            address tokenIn;
+            address tokenOut;
+            uint24 fee;
+            address recipient;
+            uint256 amountIn;
+            uint256 amountOutMinimum;
+            uint160 sqrtPriceLimitX96;
+        }
+
+        struct ExactInputParams {
+            bytes path;
+            address recipient;
+            uint256 amountIn;
+            uint256 amountOutMinimum;
+        }
+
+        function exactInputSingle(ExactInputSingleParams calldata params) external payable returns (uint256 amountOut);
+        function exactInput(ExactInputParams calldata params) external payable returns (uint256 amountOut);
+    }
+}
+
+sol! {
+    /// Interface of the Uniswap V2 router
+    #[derive(Debug, PartialEq, Eq)]
+    contract IUniswapV2Router02 {
+        function swapExactTokensForTokens(uint256 amountIn, uint256 amountOutMin, address[] calldata path, address to, uint256 deadline) external returns (uint256[] memory amounts);
+    }
+}
+
+sol! {
+    /// Interface of the UniversalRouter
+    #[derive(Debug, PartialEq, Eq)]
+    contract IUniversalRouter {
+        function execute(bytes calldata commands, bytes[] calldata inputs, uint256 deadline) external payable;
+    }
+}
+
+/// Encodes the Uniswap V3 path of `route`, the tokens interleaved with the 3 byte fees of the pools.
+///
+/// Fails if the route contains pools other than Uniswap V3 pools.
+pub fn encode_v3_path(route: &Route) -> Result<Bytes, ExecutionError> {
+    v3_path(&route.tokens()?, &route.pools)
+}
+
+/// Returns the Uniswap V2 path of `route`, the tokens along the route.
+///
+/// Fails if the route contains pools other than Uniswap V2 pools.
+pub fn encode_v2_path(route: &Route) -> Result<Vec<Address>, ExecutionError> {
+    v2_path(&route.tokens()?, &route.pools)
+}
+
+fn v3_path(tokens: &[Address], pools: &[AMM]) -> Result<Bytes, ExecutionError> {
+    let mut path = tokens[0].to_vec();
+    for (pool, token_out) in pools.iter().zip(tokens.iter().skip(1)) {
+        let AMM::UniswapV3Pool(pool) = pool else {
+            return Err(ExecutionError::UnsupportedPool(pool.address()));
+        };
+
+        path.extend_from_slice(&pool.fee.to_be_bytes()[1..]);
+        path.extend_from_slice(token_out.as_slice());
+    }
+
+    Ok(path.into())
+}
+
+fn v2_path(tokens: &[Address], pools: &[AMM]) -> Result<Vec<Address>, ExecutionError> {
+    if let Some(pool) = pools
+        .iter()
+        .find(|pool| !matches!(pool, AMM::UniswapV2Pool(_)))
+    {
+        return Err(ExecutionError::UnsupportedPool(pool.address()));
+    }
+
+    Ok(tokens.to_vec())
+}
+
+/// Returns the SwapRouter02 calldata swapping `amount_in` along a route of Uniswap V3 pools, `exactInputSingle` for a
+/// single pool and `exactInput` otherwise.
+pub fn swap_router_02_calldata(
+    route: &Route,
+    amount_in: U256,
+    amount_out_minimum: U256,
+    recipient: Address,
+) -> Result<Bytes, ExecutionError> {
+    let path = encode_v3_path(route)?;
+
+    let calldata = if let [AMM::UniswapV3Pool(pool)] = route.pools.as_slice() {
+        ISwapRouter02::exactInputSingleCall {
+            params: ISwapRouter02::ExactInputSingleParams {
+                tokenIn: route.token_in,
+                tokenOut: route.token_out()?,
+                fee: pool.fee,
+                recipient,
+                amountIn: amount_in,
+                amountOutMinimum: amount_out_minimum,
+                sqrtPriceLimitX96: U256::ZERO,
+            },
+        }
+        .abi_encode()
+    } else {
+        ISwapRouter02::exactInputCall {
+            params: ISwapRouter02::ExactInputParams {
+                path,
+                recipient,
+                amountIn: amount_in,
+                amountOutMinimum: amount_out_minimum,
+            },
+        }
+        .abi_encode()
+    };
+
+    Ok(calldata.into())
+}
+
+/// Returns the Uniswap V2 router calldata swapping `amount_in` along a route of Uniswap V2 pools.
+pub fn v2_router_calldata(
+    route: &Route,
+    amount_in: U256,
+    amount_out_min: U256,
+    recipient: Address,
+    deadline: U256,
+) -> Result<Bytes, ExecutionError> {
+    Ok(IUniswapV2Router02::swapExactTokensForTokensCall {
+        amountIn: amount_in,
+        amountOutMin: amount_out_min,
+        path: encode_v2_path(route)?,
+        to: recipient,
+        deadline,
+    }
+    .abi_encode()
+    .into())
+}
+
+/// Returns the UniversalRouter calldata swapping `amount_in` along a route of Uniswap V2 and V3 pools, paid by the
+/// sender.
+///
+/// Consecutive pools of the same protocol are swapped by one command, the output of each command is held by the
+/// router and swapped in full by the next one.
+pub fn universal_router_calldata(
+    route: &Route,
+    amount_in: U256,
+    amount_out_minimum: U256,
+    recipient: Address,
+    deadline: U256,
+) -> Result<Bytes, ExecutionError> {
+    let tokens = route.tokens()?;
+
+    let mut commands = vec![];
+    let mut inputs = vec![];
+    let mut start = 0;
+    while start < route.pools.len() {
+        let is_v3 = match &route.pools[start] {
+            AMM::UniswapV3Pool(_) => true,
+            AMM::UniswapV2Pool(_) => false,
+            pool => return Err(ExecutionError::UnsupportedPool(pool.address())),
+        };
+        let end = route.pools[start..]
+            .iter()
+            .position(|pool| matches!(pool, AMM::UniswapV3Pool(_)) != is_v3)
+            .map_or(route.pools.len(), |len| start + len);
+
+        let is_first = start == 0;
+        let is_last = end == route.pools.len();
+        let segment_recipient = if is_last { recipient } else { ADDRESS_THIS };
+        let segment_amount_in = if is_first {
+            amount_in
+        } else {
+            CONTRACT_BALANCE
+        };
+        let segment_amount_out_minimum = if is_last {
+            amount_out_minimum
+        } else {
+            U256::ZERO
+        };
+
+        let segment_tokens = &tokens[start..=end];
+        let segment_pools = &route.pools[start..end];
+
+        // (recipient, amountIn, amountOutMin, path, payerIsUser)
+        let input = if is_v3 {
+            commands.push(V3_SWAP_EXACT_IN);
+            (
+                segment_recipient,
+                segment_amount_in,
+                segment_amount_out_minimum,
+                v3_path(segment_tokens, segment_pools)?,
+                is_first,
+            )
+                .abi_encode_params()
+        } else {
+            commands.push(V2_SWAP_EXACT_IN);
+            (
+                segment_recipient,
+                segment_amount_in,
+                segment_amount_out_minimum,
+                v2_path(segment_tokens, segment_pools)?,
+                is_first,
+            )
+                .abi_encode_params()
+        };
+        inputs.push(input.into());
+
+        start = end;
+    }
+
+    Ok(IUniversalRouter::executeCall {
+        commands: commands.into(),
+        inputs,
+        deadline,
+    }
+    .abi_encode()
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Bytes, U256},
+        sol_types::{SolCall, SolValue},
+    };
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AMM},
+        errors::ExecutionError,
+        routing::Route,
+    };
+
+    use super::{
+        encode_v2_path, encode_v3_path, swap_router_02_calldata, universal_router_calldata,
+        v2_router_calldata, ISwapRouter02, IUniswapV2Router02, IUniversalRouter, ADDRESS_THIS,
+        CONTRACT_BALANCE,
+    };
+
+    #[test]
+    fn test_router_calldata() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let link = address!("514910771af9ca656af840dff83e8264ecf986ca");
+        let recipient = address!("000000000000000000000000000000000000dEaD");
+
+        let usdc_weth = AMM::UniswapV3Pool(UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            token_a: usdc,
+            token_b: weth,
+            fee: 500,
+            ..Default::default()
+        });
+        let link_weth = AMM::UniswapV3Pool(UniswapV3Pool {
+            address: address!("a6Cc3C2531FdaA6Ae1A3CA84c2855806728693e8"),
+            token_a: link,
+            token_b: weth,
+            fee: 3000,
+            ..Default::default()
+        });
+
+        let route = Route::new(usdc, vec![usdc_weth.clone(), link_weth]);
+        let path = encode_v3_path(&route).unwrap();
+        assert_eq!(path.len(), 20 * 3 + 3 * 2);
+        assert_eq!(&path[..20], usdc.as_slice());
+        assert_eq!(&path[20..23], &[0x00, 0x01, 0xf4]);
+        assert_eq!(&path[23..43], weth.as_slice());
+        assert_eq!(&path[43..46], &[0x00, 0x0b, 0xb8]);
+        assert_eq!(&path[46..], link.as_slice());
+
+        let amount_in = U256::from(1_000_000);
+        let calldata = swap_router_02_calldata(&route, amount_in, U256::ZERO, recipient).unwrap();
+        let call = ISwapRouter02::exactInputCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(call.params.path, path);
+        assert_eq!(call.params.amountIn, amount_in);
+
+        let single = Route::new(usdc, vec![usdc_weth]);
+        let calldata = swap_router_02_calldata(&single, amount_in, U256::ZERO, recipient).unwrap();
+        let call = ISwapRouter02::exactInputSingleCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(call.params.tokenOut, weth);
+        assert_eq!(call.params.fee, 500);
+
+        let calldata =
+            universal_router_calldata(&route, amount_in, U256::ZERO, recipient, U256::MAX).unwrap();
+        let call = IUniversalRouter::executeCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(call.commands[..], [0x00]);
+        assert_eq!(call.inputs.len(), 1);
+
+        let v2_route = Route::new(
+            usdc,
+            vec![AMM::UniswapV2Pool(UniswapV2Pool {
+                token_a: usdc,
+                token_b: weth,
+                ..Default::default()
+            })],
+        );
+        assert!(matches!(
+            encode_v3_path(&v2_route),
+            Err(ExecutionError::UnsupportedPool(_))
+        ));
+    }
+
+    #[test]
+    fn test_v2_router_calldata() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let link = address!("514910771af9ca656af840dff83e8264ecf986ca");
+        let recipient = address!("000000000000000000000000000000000000dEaD");
+
+        let usdc_weth = AMM::UniswapV2Pool(UniswapV2Pool {
+            token_a: usdc,
+            token_b: weth,
+            ..Default::default()
+        });
+        let link_weth = AMM::UniswapV3Pool(UniswapV3Pool {
+            token_a: link,
+            token_b: weth,
+            fee: 3000,
+            ..Default::default()
+        });
+
+        let route = Route::new(usdc, vec![usdc_weth.clone()]);
+        assert_eq!(encode_v2_path(&route).unwrap(), vec![usdc, weth]);
+
+        let amount_in = U256::from(1_000_000);
+        let calldata =
+            v2_router_calldata(&route, amount_in, U256::from(1), recipient, U256::MAX).unwrap();
+        let call =
+            IUniswapV2Router02::swapExactTokensForTokensCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(call.path, vec![usdc, weth]);
+        assert_eq!(call.amountOutMin, U256::from(1));
+        assert_eq!(call.to, recipient);
+
+        // V2 then V3, the router holds the WETH between the commands
+        let route = Route::new(usdc, vec![usdc_weth, link_weth]);
+        assert!(encode_v2_path(&route).is_err());
+
+        let calldata =
+            universal_router_calldata(&route, amount_in, U256::from(1), recipient, U256::MAX)
+                .unwrap();
+        let call = IUniversalRouter::executeCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(call.commands[..], [0x08, 0x00]);
+
+        let (v2_recipient, v2_amount_in, _, v2_path, v2_payer_is_user) =
+            <(Address, U256, U256, Vec<Address>, bool)>::abi_decode_params(&call.inputs[0], true)
+                .unwrap();
+        assert_eq!(v2_recipient, ADDRESS_THIS);
+        assert_eq!(v2_amount_in, amount_in);
+        assert_eq!(v2_path, vec![usdc, weth]);
+        assert!(v2_payer_is_user);
+
+        let (v3_recipient, v3_amount_in, v3_amount_out_min, v3_path, v3_payer_is_user) =
+            <(Address, U256, U256, Bytes, bool)>::abi_decode_params(&call.inputs[1], true).unwrap();
+        assert_eq!(v3_recipient, recipient);
+        assert_eq!(v3_amount_in, CONTRACT_BALANCE);
+        assert_eq!(v3_amount_out_min, U256::from(1));
+        assert_eq!(v3_path.len(), 43);
+        assert!(!v3_payer_is_user);
+    }
+}
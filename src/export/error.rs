@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    ArrowError(#[from] arrow::error::ArrowError),
+    #[error(transparent)]
+    ParquetError(#[from] parquet::errors::ParquetError),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+}
@@ -0,0 +1,310 @@
+//! Arrow/Parquet export of pool and tick data for offline analytics (e.g. in Python or DuckDB).
+//!
+//! Amounts that do not fit in 64 bits (liquidity, reserves, sqrt prices) are exported as decimal strings,
+//! which can be cast to `HUGEINT`/`DECIMAL` by the consumer.
+
+pub mod error;
+
+use std::{fs::File, path::Path, sync::Arc};
+
+use arrow::{
+    array::{ArrayRef, Int32Array, StringArray, UInt32Array, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use parquet::arrow::ArrowWriter;
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    sync::checkpoint::Checkpoint,
+};
+
+use self::error::ExportError;
+
+/// File name of the pool export written by [`export_checkpoint`].
+pub const POOLS_FILE: &str = "pools.parquet";
+/// File name of the tick export written by [`export_checkpoint`].
+pub const TICKS_FILE: &str = "ticks.parquet";
+
+/// Returns one row per AMM with its protocol, tokens, fee and liquidity, as of `block_number`.
+///
+/// Fees are in basis points for every protocol, see [`AutomatedMarketMaker::fee_bps`]. The tokens of AMMs with a
+/// single token are null.
+pub fn pools_record_batch(amms: &[AMM], block_number: u64) -> Result<RecordBatch, ExportError> {
+    let schema = Schema::new(vec![
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("address", DataType::Utf8, false),
+        Field::new("protocol", DataType::Utf8, false),
+        Field::new("token_a", DataType::Utf8, true),
+        Field::new("token_b", DataType::Utf8, true),
+        Field::new("fee_bps", DataType::UInt32, false),
+        Field::new("liquidity", DataType::Utf8, true),
+        Field::new("sqrt_price", DataType::Utf8, true),
+        Field::new("tick", DataType::Int32, true),
+        Field::new("reserve_0", DataType::Utf8, true),
+        Field::new("reserve_1", DataType::Utf8, true),
+    ]);
+
+    let mut protocol = vec![];
+    let mut liquidity = vec![];
+    let mut sqrt_price = vec![];
+    let mut tick = vec![];
+    let mut reserve_0 = vec![];
+    let mut reserve_1 = vec![];
+
+    for amm in amms {
+        match amm {
+            AMM::UniswapV2Pool(pool) => {
+                protocol.push("uniswap_v2");
+                liquidity.push(None);
+                sqrt_price.push(None);
+                tick.push(None);
+                reserve_0.push(Some(pool.reserve_0.to_string()));
+                reserve_1.push(Some(pool.reserve_1.to_string()));
+            }
+            AMM::UniswapV3Pool(pool) => {
+                protocol.push("uniswap_v3");
+                liquidity.push(Some(pool.liquidity.to_string()));
+                sqrt_price.push(Some(pool.sqrt_price.to_string()));
+                tick.push(Some(pool.tick));
+                reserve_0.push(None);
+                reserve_1.push(None);
+            }
+            AMM::ERC4626Vault(vault) => {
+                protocol.push("erc_4626");
+                liquidity.push(None);
+                sqrt_price.push(None);
+                tick.push(None);
+                reserve_0.push(Some(vault.vault_reserve.to_string()));
+                reserve_1.push(Some(vault.asset_reserve.to_string()));
+            }
+            AMM::CurveV2Pool(pool) => {
+                protocol.push("curve_v2");
+                liquidity.push(Some(pool.d.to_string()));
+                sqrt_price.push(None);
+                tick.push(None);
+                reserve_0.push(Some(pool.balance_0.to_string()));
+                reserve_1.push(Some(pool.balance_1.to_string()));
+            }
+            AMM::RateAdapter(_) => {
+                protocol.push("rate_adapter");
+                liquidity.push(None);
+                sqrt_price.push(None);
+                tick.push(None);
+                reserve_0.push(None);
+                reserve_1.push(None);
+            }
+            AMM::BancorV3Pool(pool) => {
+                protocol.push("bancor_v3");
+                liquidity.push(None);
+                sqrt_price.push(None);
+                tick.push(None);
+                reserve_0.push(Some(pool.base_token_trading_liquidity.to_string()));
+                reserve_1.push(Some(pool.bnt_trading_liquidity.to_string()));
+            }
+            AMM::GmxMarket(market) => {
+                protocol.push("gmx");
+                liquidity.push(None);
+                sqrt_price.push(None);
+                tick.push(None);
+                reserve_0.push(Some(market.long_pool_amount.to_string()));
+                reserve_1.push(Some(market.short_pool_amount.to_string()));
+            }
+            AMM::AmbientPool(pool) => {
+                protocol.push("ambient");
+                liquidity.push(Some(pool.liquidity().to_string()));
+                sqrt_price.push(Some(pool.sqrt_price.to_string()));
+                tick.push(Some(pool.tick));
+                reserve_0.push(None);
+                reserve_1.push(None);
+            }
+            AMM::ConstantProductPool(pool) => {
+                protocol.push("constant_product");
+                liquidity.push(None);
+                sqrt_price.push(None);
+                tick.push(None);
+                reserve_0.push(Some(pool.reserve_0.to_string()));
+                reserve_1.push(Some(pool.reserve_1.to_string()));
+            }
+        }
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(vec![block_number; amms.len()])),
+        Arc::new(StringArray::from_iter_values(
+            amms.iter().map(|amm| amm.address().to_string()),
+        )),
+        Arc::new(StringArray::from(protocol)),
+        Arc::new(StringArray::from_iter(
+            amms.iter()
+                .map(|amm| amm.tokens().first().map(|token| token.to_string())),
+        )),
+        Arc::new(StringArray::from_iter(
+            amms.iter()
+                .map(|amm| amm.tokens().get(1).map(|token| token.to_string())),
+        )),
+        Arc::new(UInt32Array::from_iter_values(
+            amms.iter().map(|amm| amm.fee_bps()),
+        )),
+        Arc::new(StringArray::from(liquidity)),
+        Arc::new(StringArray::from(sqrt_price)),
+        Arc::new(Int32Array::from(tick)),
+        Arc::new(StringArray::from(reserve_0)),
+        Arc::new(StringArray::from(reserve_1)),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Returns one row per initialized tick of each Uniswap V3 pool, as of `block_number`.
+pub fn ticks_record_batch(amms: &[AMM], block_number: u64) -> Result<RecordBatch, ExportError> {
+    let schema = Schema::new(vec![
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("address", DataType::Utf8, false),
+        Field::new("tick", DataType::Int32, false),
+        Field::new("liquidity_gross", DataType::Utf8, false),
+        Field::new("liquidity_net", DataType::Utf8, false),
+    ]);
+
+    let mut address = vec![];
+    let mut tick = vec![];
+    let mut liquidity_gross = vec![];
+    let mut liquidity_net = vec![];
+
+    for amm in amms {
+        if let AMM::UniswapV3Pool(pool) = amm {
+            for (pool_tick, info) in pool.ticks.iter().filter(|(_, info)| info.initialized) {
+                address.push(pool.address.to_string());
+                tick.push(*pool_tick);
+                liquidity_gross.push(info.liquidity_gross.to_string());
+                liquidity_net.push(info.liquidity_net.to_string());
+            }
+        }
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(vec![block_number; tick.len()])),
+        Arc::new(StringArray::from(address)),
+        Arc::new(Int32Array::from(tick)),
+        Arc::new(StringArray::from(liquidity_gross)),
+        Arc::new(StringArray::from(liquidity_net)),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Writes a record batch to a Parquet file at `path`.
+pub fn write_parquet<P: AsRef<Path>>(batch: &RecordBatch, path: P) -> Result<(), ExportError> {
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)?;
+    writer.write(batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+/// Exports the AMMs of a checkpoint to [`POOLS_FILE`] in `dir`, and their ticks to [`TICKS_FILE`] if `include_ticks` is set.
+///
+/// Existing exports in `dir` are overwritten, so calling this after every checkpoint keeps the export up to date.
+pub fn export_checkpoint<P: AsRef<Path>>(
+    checkpoint: &Checkpoint,
+    dir: P,
+    include_ticks: bool,
+) -> Result<(), ExportError> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+
+    write_parquet(
+        &pools_record_batch(&checkpoint.amms, checkpoint.block_number)?,
+        dir.join(POOLS_FILE),
+    )?;
+
+    if include_ticks {
+        write_parquet(
+            &ticks_record_batch(&checkpoint.amms, checkpoint.block_number)?,
+            dir.join(TICKS_FILE),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use alloy::primitives::address;
+    use arrow::array::UInt32Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AMM},
+        sync::checkpoint::Checkpoint,
+    };
+
+    use super::{
+        export_checkpoint, pools_record_batch, ticks_record_batch, POOLS_FILE, TICKS_FILE,
+    };
+
+    fn amms() -> Vec<AMM> {
+        let mut uniswap_v3_pool = UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            fee: 500,
+            tick_spacing: 10,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        uniswap_v3_pool.modify_position(-100, 100, 1_000);
+        uniswap_v3_pool.modify_position(-200, 200, 1_000);
+
+        vec![
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+                reserve_0: 1_000,
+                reserve_1: 2_000,
+                fee: 300,
+                ..Default::default()
+            }),
+            AMM::UniswapV3Pool(uniswap_v3_pool),
+        ]
+    }
+
+    #[test]
+    fn test_record_batches() {
+        let pools = pools_record_batch(&amms(), 100).unwrap();
+        assert_eq!(pools.num_rows(), 2);
+        assert_eq!(pools.num_columns(), 11);
+
+        // Fees are normalized to basis points across protocols
+        let fee_bps = pools
+            .column_by_name("fee_bps")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt32Array>()
+            .unwrap();
+        assert_eq!(fee_bps.values(), &[30, 5]);
+
+        let ticks = ticks_record_batch(&amms(), 100).unwrap();
+        assert_eq!(ticks.num_rows(), 4);
+    }
+
+    #[test]
+    fn test_export_checkpoint() {
+        let dir = std::env::temp_dir().join("amms_test_export_checkpoint");
+        let checkpoint = Checkpoint::new(0, 100, vec![], amms());
+
+        export_checkpoint(&checkpoint, &dir, true).unwrap();
+
+        for (file, rows) in [(POOLS_FILE, 2), (TICKS_FILE, 4)] {
+            let reader =
+                ParquetRecordBatchReaderBuilder::try_new(File::open(dir.join(file)).unwrap())
+                    .unwrap()
+                    .build()
+                    .unwrap();
+
+            let exported_rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+            assert_eq!(exported_rows, rows);
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+use std::{collections::HashSet, sync::Arc};
+
+use alloy::{
+    network::Network,
+    primitives::{keccak256, Address, Bytes, B256},
+    providers::Provider,
+    transports::Transport,
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    errors::AMMError,
+};
+
+/// Max number of concurrent `eth_getCode` requests.
+const MAX_CONCURRENT_REQUESTS: usize = 32;
+
+/// Allowlist of runtime codehashes per protocol.
+///
+/// AMM variants with an empty allowlist are not verified. Note that Uniswap V3 style pools embed immutables (tokens,
+/// fee, tick spacing) in their runtime code, so each pool has its own codehash, while Uniswap V2 style pairs share
+/// the codehash of their factory deployment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodehashAllowlist {
+    pub uniswap_v2: HashSet<B256>,
+    pub uniswap_v3: HashSet<B256>,
+    pub erc_4626: HashSet<B256>,
+    #[serde(default)]
+    pub curve_v2: HashSet<B256>,
+    #[serde(default)]
+    pub rate_adapter: HashSet<B256>,
+    #[serde(default)]
+    pub bancor_v3: HashSet<B256>,
+    #[serde(default)]
+    pub gmx: HashSet<B256>,
+    /// Codehashes of the Ambient dex, which holds all Ambient pools.
+    #[serde(default)]
+    pub ambient: HashSet<B256>,
+    #[serde(default)]
+    pub constant_product: HashSet<B256>,
+}
+
+impl CodehashAllowlist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the codehashes of trusted reference AMMs to the allowlist of their protocol.
+    pub async fn insert_reference_amms<T, N, P>(
+        &mut self,
+        amms: &[AMM],
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let codehashes = get_codehashes(amms, None, provider).await?;
+
+        for (amm, codehash) in amms.iter().zip(codehashes) {
+            self.allowlist_mut(amm).insert(codehash);
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether `codehash` is allowed for `amm`, always true if the protocol of `amm` has no allowlist.
+    pub fn allows(&self, amm: &AMM, codehash: &B256) -> bool {
+        let allowlist = self.allowlist(amm);
+        allowlist.is_empty() || allowlist.contains(codehash)
+    }
+
+    fn allowlist(&self, amm: &AMM) -> &HashSet<B256> {
+        match amm {
+            AMM::UniswapV2Pool(_) => &self.uniswap_v2,
+            AMM::UniswapV3Pool(_) => &self.uniswap_v3,
+            AMM::ERC4626Vault(_) => &self.erc_4626,
+            AMM::CurveV2Pool(_) => &self.curve_v2,
+            AMM::RateAdapter(_) => &self.rate_adapter,
+            AMM::BancorV3Pool(_) => &self.bancor_v3,
+            AMM::GmxMarket(_) => &self.gmx,
+            AMM::AmbientPool(_) => &self.ambient,
+            AMM::ConstantProductPool(_) => &self.constant_product,
+        }
+    }
+
+    fn allowlist_mut(&mut self, amm: &AMM) -> &mut HashSet<B256> {
+        match amm {
+            AMM::UniswapV2Pool(_) => &mut self.uniswap_v2,
+            AMM::UniswapV3Pool(_) => &mut self.uniswap_v3,
+            AMM::ERC4626Vault(_) => &mut self.erc_4626,
+            AMM::CurveV2Pool(_) => &mut self.curve_v2,
+            AMM::RateAdapter(_) => &mut self.rate_adapter,
+            AMM::BancorV3Pool(_) => &mut self.bancor_v3,
+            AMM::GmxMarket(_) => &mut self.gmx,
+            AMM::AmbientPool(_) => &mut self.ambient,
+            AMM::ConstantProductPool(_) => &mut self.constant_product,
+        }
+    }
+}
+
+/// Result of verifying AMM bytecode against a [`CodehashAllowlist`].
+#[derive(Debug, Clone, Default)]
+pub struct BytecodeVerification {
+    /// AMMs whose codehash is allowed.
+    pub verified: Vec<AMM>,
+    /// AMMs whose codehash is not allowed, along with their codehash.
+    pub flagged: Vec<(AMM, B256)>,
+}
+
+/// Fetches the bytecode of each AMM and compares its codehash against `allowlist`.
+pub async fn verify_amm_bytecode<T, N, P>(
+    amms: Vec<AMM>,
+    allowlist: &CodehashAllowlist,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<BytecodeVerification, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    // Only fetch the bytecode of AMMs with an allowlist
+    let (checked, unchecked): (Vec<AMM>, Vec<AMM>) = amms
+        .into_iter()
+        .partition(|amm| !allowlist.allowlist(amm).is_empty());
+
+    let codehashes = get_codehashes(&checked, block_number, provider).await?;
+
+    let mut verification = BytecodeVerification {
+        verified: unchecked,
+        flagged: vec![],
+    };
+
+    for (amm, codehash) in checked.into_iter().zip(codehashes) {
+        if allowlist.allows(&amm, &codehash) {
+            verification.verified.push(amm);
+        } else {
+            tracing::debug!(address = ?amm.address(), ?codehash, "flagged AMM with unknown codehash");
+            verification.flagged.push((amm, codehash));
+        }
+    }
+
+    Ok(verification)
+}
+
+/// Filters out AMMs whose codehash is not in `allowlist`.
+pub async fn filter_unverified_amms<T, N, P>(
+    amms: Vec<AMM>,
+    allowlist: &CodehashAllowlist,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<Vec<AMM>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    Ok(verify_amm_bytecode(amms, allowlist, block_number, provider)
+        .await?
+        .verified)
+}
+
+/// Returns the runtime codehash of each AMM.
+async fn get_codehashes<T, N, P>(
+    amms: &[AMM],
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<Vec<B256>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let block = block_number.map_or("latest".to_string(), |block| format!("{block:#x}"));
+
+    stream::iter(amms.iter().map(code_address))
+        .map(|address: Address| {
+            let provider = provider.clone();
+            let block = block.clone();
+            async move {
+                let code = provider
+                    .client()
+                    .request::<_, Bytes>("eth_getCode", (address, block))
+                    .await?;
+
+                Ok::<_, AMMError>(keccak256(code))
+            }
+        })
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .try_collect()
+        .await
+}
+
+/// Returns the address holding the code of `amm`, the dex for Ambient pools.
+fn code_address(amm: &AMM) -> Address {
+    match amm {
+        AMM::AmbientPool(pool) => pool.dex,
+        _ => amm.address(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{
+        primitives::{address, keccak256, Address},
+        providers::ProviderBuilder,
+    };
+
+    use crate::amm::{uniswap_v2::UniswapV2Pool, AMM};
+
+    use super::{verify_amm_bytecode, CodehashAllowlist};
+
+    fn uniswap_v2_pool(address: Address) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_allows() {
+        let mut allowlist = CodehashAllowlist::new();
+        let amm = uniswap_v2_pool(address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"));
+
+        // Protocols without an allowlist are not verified
+        assert!(allowlist.allows(&amm, &keccak256([])));
+
+        allowlist.uniswap_v2.insert(keccak256([1]));
+        assert!(allowlist.allows(&amm, &keccak256([1])));
+        assert!(!allowlist.allows(&amm, &keccak256([])));
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_verify_amm_bytecode() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        // USDC/WETH as reference, WETH/USDT deployed by the same factory, and the USDC token as a fake pool
+        let mut allowlist = CodehashAllowlist::new();
+        allowlist
+            .insert_reference_amms(
+                &[uniswap_v2_pool(address!(
+                    "B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"
+                ))],
+                provider.clone(),
+            )
+            .await
+            .unwrap();
+
+        let verification = verify_amm_bytecode(
+            vec![
+                uniswap_v2_pool(address!("0d4a11d5EEaaC28EC3F61d100daF4d40471f1852")),
+                uniswap_v2_pool(address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48")),
+            ],
+            &allowlist,
+            None,
+            provider,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(verification.verified.len(), 1);
+        assert_eq!(verification.flagged.len(), 1);
+    }
+}
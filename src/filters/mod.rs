@@ -1,8 +1,18 @@
 use crate::amm::AMM;
 
 pub mod address;
+#[cfg(feature = "provider")]
+pub mod codehash;
+pub mod rank;
+#[cfg(feature = "provider")]
+pub mod reserves;
+#[cfg(feature = "provider")]
 pub mod value;
 
+#[cfg(feature = "provider")]
+pub use rank::top_n_by_liquidity;
+pub use rank::top_n_by_volume;
+
 pub fn filter_empty_amms(amms: Vec<AMM>) -> Vec<AMM> {
     let mut cleaned_amms = vec![];
 
@@ -23,6 +33,40 @@ pub fn filter_empty_amms(amms: Vec<AMM>) -> Vec<AMM> {
                     cleaned_amms.push(amm)
                 }
             }
+            AMM::CurveV2Pool(ref curve_v2_pool) => {
+                if !curve_v2_pool.token_a.is_zero() && !curve_v2_pool.token_b.is_zero() {
+                    cleaned_amms.push(amm)
+                }
+            }
+            AMM::RateAdapter(ref rate_adapter) => {
+                if !rate_adapter.wrapped_token.is_zero() && !rate_adapter.underlying_token.is_zero()
+                {
+                    cleaned_amms.push(amm)
+                }
+            }
+            AMM::BancorV3Pool(ref bancor_v3_pool) => {
+                if !bancor_v3_pool.address.is_zero() && !bancor_v3_pool.base_token.is_zero() {
+                    cleaned_amms.push(amm)
+                }
+            }
+            AMM::GmxMarket(ref gmx_market) => {
+                if !gmx_market.long_token.is_zero() && !gmx_market.short_token.is_zero() {
+                    cleaned_amms.push(amm)
+                }
+            }
+            // The base token of Ambient pools is the zero address for the native token
+            AMM::AmbientPool(ref ambient_pool) => {
+                if !ambient_pool.quote_token.is_zero() {
+                    cleaned_amms.push(amm)
+                }
+            }
+            AMM::ConstantProductPool(ref constant_product_pool) => {
+                if !constant_product_pool.token_a.is_zero()
+                    && !constant_product_pool.token_b.is_zero()
+                {
+                    cleaned_amms.push(amm)
+                }
+            }
         }
     }
 
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+use alloy::primitives::Address;
+#[cfg(feature = "provider")]
+use alloy::{network::Network, primitives::U256, providers::Provider, transports::Transport};
+
+#[cfg(feature = "provider")]
+use crate::{amm::factory::Factory, errors::AMMError, filters::value::get_weth_values_in_amms};
+use crate::{amm::AMM, core::price::u256_to_f64};
+
+/// Returns the `n` AMMs with the most aggregate token value, in descending order.
+///
+/// This function uses batched static calls to get the WETH value in each AMM, see
+/// [`get_weth_values_in_amms`]. Ranking by WETH value is the same as ranking by USD value.
+#[cfg(feature = "provider")]
+pub async fn top_n_by_liquidity<T, N, P>(
+    amms: &[AMM],
+    n: usize,
+    factories: &[Factory],
+    weth: Address,
+    weth_value_in_token_to_weth_pool_threshold: U256, //This is the threshold where we will ignore any token price < threshold during batch calls
+    step: usize,
+    provider: Arc<P>,
+) -> Result<Vec<AMM>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let weth_values = get_weth_values_in_amms(
+        amms,
+        factories,
+        weth,
+        weth_value_in_token_to_weth_pool_threshold,
+        step,
+        provider,
+    )
+    .await?;
+
+    // Values are returned for every AMM, in order
+    debug_assert_eq!(weth_values.len(), amms.len());
+    let mut ranked_amms = amms.iter().zip(weth_values).collect::<Vec<_>>();
+    ranked_amms.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    Ok(ranked_amms
+        .into_iter()
+        .take(n)
+        .map(|(amm, _)| amm.clone())
+        .collect())
+}
+
+/// Returns the `n` AMMs with the most USD swap volume in their [`crate::amm::stats::PoolStats`], in descending
+/// order.
+///
+/// `usd_prices` maps tokens to their USD price per whole token. The volume of a pool is valued in token a if it is
+/// priced, otherwise in token b. Pools without stats or priced tokens have no volume.
+pub fn top_n_by_volume(amms: &[AMM], n: usize, usd_prices: &HashMap<Address, f64>) -> Vec<AMM> {
+    let mut ranked_amms = amms
+        .iter()
+        .map(|amm| (amm, usd_volume(amm, usd_prices)))
+        .collect::<Vec<_>>();
+    ranked_amms.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    ranked_amms
+        .into_iter()
+        .take(n)
+        .map(|(amm, _)| amm.clone())
+        .collect()
+}
+
+/// Returns the USD swap volume of `amm`, zero if it has no stats or priced tokens.
+pub fn usd_volume(amm: &AMM, usd_prices: &HashMap<Address, f64>) -> f64 {
+    let Some(stats) = amm.stats() else {
+        return 0.0;
+    };
+
+    let (token_a, token_a_decimals, token_b, token_b_decimals) = match amm {
+        AMM::UniswapV2Pool(pool) => (
+            pool.token_a,
+            pool.token_a_decimals,
+            pool.token_b,
+            pool.token_b_decimals,
+        ),
+        AMM::UniswapV3Pool(pool) => (
+            pool.token_a,
+            pool.token_a_decimals,
+            pool.token_b,
+            pool.token_b_decimals,
+        ),
+        AMM::ERC4626Vault(_)
+        | AMM::CurveV2Pool(_)
+        | AMM::RateAdapter(_)
+        | AMM::BancorV3Pool(_)
+        | AMM::GmxMarket(_)
+        | AMM::AmbientPool(_)
+        | AMM::ConstantProductPool(_) => return 0.0,
+    };
+
+    if let Some(price) = usd_prices.get(&token_a) {
+        u256_to_f64(stats.volume_0) / 10_f64.powi(token_a_decimals as i32) * price
+    } else if let Some(price) = usd_prices.get(&token_b) {
+        u256_to_f64(stats.volume_1) / 10_f64.powi(token_b_decimals as i32) * price
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::{address, U256};
+
+    use crate::amm::{
+        stats::{PoolStats, SwapRecord},
+        uniswap_v2::UniswapV2Pool,
+        AutomatedMarketMaker, AMM,
+    };
+
+    use super::{top_n_by_volume, usd_volume};
+
+    #[test]
+    fn test_top_n_by_volume() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let link = address!("514910771af9ca656af840dff83e8264ecf986ca");
+
+        let pool = |address, token_a, token_a_decimals, volume_0: u128| {
+            let mut stats = PoolStats::default();
+            stats.record_swap(SwapRecord {
+                amount_0_in: U256::from(volume_0),
+                ..Default::default()
+            });
+
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address,
+                token_a,
+                token_a_decimals,
+                token_b: weth,
+                token_b_decimals: 18,
+                stats,
+                ..Default::default()
+            })
+        };
+
+        // 1_000 USDC, 2 LINK and 10_000 unpriced tokens
+        let usdc_weth = pool(
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            usdc,
+            6,
+            1_000_000_000,
+        );
+        let link_weth = pool(
+            address!("a2107FA5B38d9bbd2C461D6EDf11B11A50F6b974"),
+            link,
+            18,
+            2_000_000_000_000_000_000,
+        );
+        let unpriced = pool(
+            address!("397FF1542f962076d0BFE58eA045FfA2d347ACa0"),
+            address!("6B175474E89094C44Da98b954EedeAC495271d0F"),
+            18,
+            10_000_000_000_000_000_000_000,
+        );
+
+        let usd_prices = HashMap::from([(usdc, 1.0), (link, 15.0)]);
+        assert_eq!(usd_volume(&usdc_weth, &usd_prices), 1_000.0);
+        assert_eq!(usd_volume(&link_weth, &usd_prices), 30.0);
+        assert_eq!(usd_volume(&unpriced, &usd_prices), 0.0);
+
+        let amms = vec![link_weth.clone(), unpriced, usdc_weth.clone()];
+        let top_amms = top_n_by_volume(&amms, 2, &usd_prices)
+            .iter()
+            .map(|amm| amm.address())
+            .collect::<Vec<_>>();
+        assert_eq!(top_amms, vec![usdc_weth.address(), link_weth.address()]);
+    }
+}
@@ -0,0 +1,220 @@
+//! Detection of Uniswap V2 style pools whose token balances exceed their reserves.
+//!
+//! The reserves of a pair are only updated on swaps, mints, burns and `sync`, so tokens sent to the pair without a
+//! swap (pending `skim`, donations) or tokens whose balances change on their own (rebasing, broken or malicious
+//! tokens) make the balances diverge from the reserves. Quotes of such pools often cannot be executed.
+
+use std::sync::Arc;
+
+use alloy::{network::Network, primitives::U256, providers::Provider, transports::Transport};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amm::{
+        multicall::{aggregate, call3, decode},
+        AutomatedMarketMaker, IErc20, AMM,
+    },
+    errors::AMMError,
+};
+
+/// Number of pools per Multicall3 request when fetching balances.
+pub const RESERVE_CHECK_BATCH_SIZE: usize = 250;
+
+/// Difference between the token balances and the reserves of a pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReserveAnomaly {
+    /// The balance of a token exceeds its reserve by more than the threshold, in basis points of the reserve.
+    ExcessBalance {
+        balance_0: U256,
+        balance_1: U256,
+        excess_0_bps: u32,
+        excess_1_bps: u32,
+    },
+    /// `balanceOf` reverted for a token of the pool.
+    UnreadableBalance,
+}
+
+/// Result of comparing the token balances of AMMs with their reserves.
+#[derive(Debug, Clone, Default)]
+pub struct ReserveCheck {
+    /// AMMs whose balances match their reserves, and AMMs without reserves.
+    pub healthy: Vec<AMM>,
+    /// AMMs whose balances exceed their reserves, along with the anomaly.
+    pub flagged: Vec<(AMM, ReserveAnomaly)>,
+}
+
+/// Fetches the token balances of the Uniswap V2 and constant product pools of `amms` and flags the pools where a
+/// balance exceeds its reserve by more than `max_excess_bps` basis points.
+///
+/// The reserves of the AMMs should be synced to `block_number`. Other AMMs are not checked.
+pub async fn check_reserve_balances<T, N, P>(
+    amms: Vec<AMM>,
+    max_excess_bps: u32,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<ReserveCheck, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let (checked, unchecked): (Vec<AMM>, Vec<AMM>) = amms
+        .into_iter()
+        .partition(|amm| pool_reserves(amm).is_some());
+
+    let mut check = ReserveCheck {
+        healthy: unchecked,
+        flagged: vec![],
+    };
+
+    for chunk in checked.chunks(RESERVE_CHECK_BATCH_SIZE) {
+        let calls = chunk
+            .iter()
+            .map(|amm| {
+                amm.tokens()
+                    .into_iter()
+                    .map(|token| {
+                        call3(
+                            token,
+                            IErc20::balanceOfCall {
+                                account: amm.address(),
+                            },
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        let data = aggregate(calls, block_number, provider.clone()).await?;
+
+        for (amm, data) in chunk.iter().zip(data) {
+            let reserves = pool_reserves(amm).unwrap_or_default();
+            let balances = decode::<IErc20::balanceOfCall>(&data[0])
+                .zip(decode::<IErc20::balanceOfCall>(&data[1]))
+                .map(|(balance_0, balance_1)| (balance_0._0, balance_1._0));
+
+            match reserve_anomaly(reserves, balances, max_excess_bps) {
+                Some(anomaly) => {
+                    tracing::debug!(address = ?amm.address(), ?anomaly, "flagged pool with balances exceeding reserves");
+                    check.flagged.push((amm.clone(), anomaly));
+                }
+                None => check.healthy.push(amm.clone()),
+            }
+        }
+    }
+
+    Ok(check)
+}
+
+/// Filters out pools whose balances exceed their reserves by more than `max_excess_bps` basis points, see
+/// [`check_reserve_balances`].
+pub async fn filter_reserve_anomalies<T, N, P>(
+    amms: Vec<AMM>,
+    max_excess_bps: u32,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<Vec<AMM>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    Ok(
+        check_reserve_balances(amms, max_excess_bps, block_number, provider)
+            .await?
+            .healthy,
+    )
+}
+
+/// Returns the anomaly of a pool with `reserves` and token `balances`, `None` if the balances are within
+/// `max_excess_bps` of the reserves. Balances are `None` if `balanceOf` reverted.
+pub fn reserve_anomaly(
+    reserves: (u128, u128),
+    balances: Option<(U256, U256)>,
+    max_excess_bps: u32,
+) -> Option<ReserveAnomaly> {
+    let Some((balance_0, balance_1)) = balances else {
+        return Some(ReserveAnomaly::UnreadableBalance);
+    };
+
+    let excess_0_bps = excess_bps(balance_0, reserves.0);
+    let excess_1_bps = excess_bps(balance_1, reserves.1);
+
+    (excess_0_bps > max_excess_bps || excess_1_bps > max_excess_bps).then_some(
+        ReserveAnomaly::ExcessBalance {
+            balance_0,
+            balance_1,
+            excess_0_bps,
+            excess_1_bps,
+        },
+    )
+}
+
+/// Returns the excess of `balance` over `reserve` in basis points of the reserve, saturating at `u32::MAX`.
+pub fn excess_bps(balance: U256, reserve: u128) -> u32 {
+    let excess = balance.saturating_sub(U256::from(reserve));
+    if excess.is_zero() {
+        return 0;
+    }
+
+    if reserve == 0 {
+        return u32::MAX;
+    }
+
+    (excess.saturating_mul(U256::from(10_000)) / U256::from(reserve)).saturating_to::<u32>()
+}
+
+/// Returns the reserves of pools that track them separately from their balances.
+fn pool_reserves(amm: &AMM) -> Option<(u128, u128)> {
+    match amm {
+        AMM::UniswapV2Pool(pool) => Some((pool.reserve_0, pool.reserve_1)),
+        AMM::ConstantProductPool(pool) => Some((pool.reserve_0, pool.reserve_1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::{excess_bps, reserve_anomaly, ReserveAnomaly};
+
+    #[test]
+    fn test_excess_bps() {
+        assert_eq!(excess_bps(U256::from(1_000), 1_000), 0);
+        assert_eq!(excess_bps(U256::from(900), 1_000), 0);
+        assert_eq!(excess_bps(U256::from(1_010), 1_000), 100);
+        assert_eq!(excess_bps(U256::from(1), 0), u32::MAX);
+        assert_eq!(excess_bps(U256::MAX, 1), u32::MAX);
+    }
+
+    #[test]
+    fn test_reserve_anomaly() {
+        let reserves = (1_000_000, 2_000_000);
+
+        assert_eq!(
+            reserve_anomaly(
+                reserves,
+                Some((U256::from(1_000_500), U256::from(2_000_000))),
+                10
+            ),
+            None
+        );
+        assert_eq!(
+            reserve_anomaly(
+                reserves,
+                Some((U256::from(1_000_000), U256::from(2_400_000))),
+                10
+            ),
+            Some(ReserveAnomaly::ExcessBalance {
+                balance_0: U256::from(1_000_000),
+                balance_1: U256::from(2_400_000),
+                excess_0_bps: 0,
+                excess_1_bps: 2_000,
+            })
+        );
+        assert_eq!(
+            reserve_anomaly(reserves, None, 10),
+            Some(ReserveAnomaly::UnreadableBalance)
+        );
+    }
+}
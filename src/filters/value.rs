@@ -46,6 +46,40 @@ where
 {
     let weth_usd_price = usd_weth_pool.calculate_price(weth)?;
 
+    filter_amms_below_usd_value(
+        amms,
+        factories,
+        weth_usd_price,
+        usd_value_in_pool_threshold,
+        weth,
+        weth_value_in_token_to_weth_pool_threshold,
+        step,
+        provider,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+/// Filter that removes AMMs with less aggregate token value than `usd_value_in_pool_threshold`, valuing WETH at
+/// `weth_usd_price`, e.g. the price of a Chainlink feed rather than of a possibly imbalanced on-chain pool.
+///
+/// This function uses batched static calls to get the WETH value in each AMM.
+/// Returns a vector of filtered AMMs.
+pub async fn filter_amms_below_usd_value<T, N, P>(
+    amms: Vec<AMM>,
+    factories: &[Factory],
+    weth_usd_price: f64,
+    usd_value_in_pool_threshold: f64,
+    weth: Address,
+    weth_value_in_token_to_weth_pool_threshold: U256,
+    step: usize,
+    provider: Arc<P>,
+) -> Result<Vec<AMM>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
     // Init a new vec to hold the filtered AMMs
     let mut filtered_amms = vec![];
 
@@ -111,6 +145,9 @@ where
     Ok(filtered_amms)
 }
 
+/// Returns the WETH value in each of `amms`, in the order of `amms`, fetched in batches of `step` AMMs.
+///
+/// Returns an error if a batch does not return a value for each of its AMMs.
 pub async fn get_weth_values_in_amms<T, N, P>(
     amms: &[AMM],
     factories: &[Factory],
@@ -124,15 +161,11 @@ where
     N: Network,
     P: Provider<T, N>,
 {
-    // init a new vec to hold the filtered pools
-    let mut aggregate_weth_values_in_amms = vec![];
+    let mut aggregate_weth_values_in_amms = Vec::with_capacity(amms.len());
 
-    let mut idx_from = 0;
-    let mut idx_to = if step > amms.len() { amms.len() } else { step };
-
-    for _ in (0..amms.len()).step_by(step) {
+    for chunk in amms.chunks(step.max(1)) {
         let weth_values_in_amms = get_weth_value_in_amm_batch_request(
-            &amms[idx_from..idx_to],
+            chunk,
             factories,
             weth,
             weth_value_in_token_to_weth_pool_threshold,
@@ -140,16 +173,12 @@ where
         )
         .await?;
 
-        // add weth values in pools to the aggregate array
-        aggregate_weth_values_in_amms.extend(weth_values_in_amms);
-
-        idx_from = idx_to;
-
-        if idx_to + step > amms.len() {
-            idx_to = amms.len() - 1
-        } else {
-            idx_to += step;
+        // A missing value would shift the values of the following AMMs
+        if weth_values_in_amms.len() != chunk.len() {
+            return Err(AMMError::BatchRequestError(chunk[0].address()));
         }
+
+        aggregate_weth_values_in_amms.extend(weth_values_in_amms);
     }
 
     Ok(aggregate_weth_values_in_amms)
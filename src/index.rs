@@ -0,0 +1,186 @@
+//! Lookup of pools by token pair and by token.
+
+use std::collections::{HashMap, HashSet};
+
+use alloy::primitives::Address;
+
+use crate::amm::{AutomatedMarketMaker, AMM};
+
+/// Index of pool addresses by sorted token pair and by token, maintained incrementally as pools are inserted and
+/// removed.
+#[derive(Debug, Clone, Default)]
+pub struct PoolIndex {
+    pairs: HashMap<(Address, Address), HashSet<Address>>,
+    tokens: HashMap<Address, HashSet<Address>>,
+    /// Tokens of every indexed pool.
+    pools: HashMap<Address, Vec<Address>>,
+}
+
+impl PoolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the sorted key of the pair of `token_a` and `token_b`.
+    pub fn pair_key(token_a: Address, token_b: Address) -> (Address, Address) {
+        if token_a < token_b {
+            (token_a, token_b)
+        } else {
+            (token_b, token_a)
+        }
+    }
+
+    /// Indexes `amm` under every pair of its tokens, replacing a pool with the same address.
+    pub fn insert(&mut self, amm: &AMM) {
+        self.insert_pool(amm.address(), amm.tokens());
+    }
+
+    /// Indexes the pool at `pool` trading `tokens`, replacing a pool with the same address.
+    pub fn insert_pool(&mut self, pool: Address, tokens: Vec<Address>) {
+        self.remove(pool);
+
+        for (i, token_a) in tokens.iter().enumerate() {
+            self.tokens.entry(*token_a).or_default().insert(pool);
+
+            for token_b in tokens.iter().skip(i + 1) {
+                self.pairs
+                    .entry(Self::pair_key(*token_a, *token_b))
+                    .or_default()
+                    .insert(pool);
+            }
+        }
+
+        self.pools.insert(pool, tokens);
+    }
+
+    /// Removes the pool at `pool` from the index, returns whether it was indexed.
+    pub fn remove(&mut self, pool: Address) -> bool {
+        let Some(tokens) = self.pools.remove(&pool) else {
+            return false;
+        };
+
+        for (i, token_a) in tokens.iter().enumerate() {
+            remove_from(&mut self.tokens, *token_a, pool);
+
+            for token_b in tokens.iter().skip(i + 1) {
+                remove_from(&mut self.pairs, Self::pair_key(*token_a, *token_b), pool);
+            }
+        }
+
+        true
+    }
+
+    /// Returns the pools trading `token_a` against `token_b`, in either order.
+    pub fn pools_for_pair(&self, token_a: Address, token_b: Address) -> Vec<Address> {
+        self.pairs
+            .get(&Self::pair_key(token_a, token_b))
+            .map(|pools| pools.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the pools trading `token`.
+    pub fn pools_containing(&self, token: Address) -> Vec<Address> {
+        self.tokens
+            .get(&token)
+            .map(|pools| pools.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn contains(&self, pool: Address) -> bool {
+        self.pools.contains_key(&pool)
+    }
+
+    /// Returns the number of indexed pools.
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+}
+
+impl<'a> FromIterator<&'a AMM> for PoolIndex {
+    fn from_iter<I: IntoIterator<Item = &'a AMM>>(amms: I) -> Self {
+        let mut index = Self::new();
+        for amm in amms {
+            index.insert(amm);
+        }
+
+        index
+    }
+}
+
+/// Removes `pool` from the set at `key`, dropping the set once empty.
+fn remove_from<K: std::hash::Hash + Eq>(
+    map: &mut HashMap<K, HashSet<Address>>,
+    key: K,
+    pool: Address,
+) {
+    if let Some(pools) = map.get_mut(&key) {
+        pools.remove(&pool);
+        if pools.is_empty() {
+            map.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use crate::amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AMM};
+
+    use super::PoolIndex;
+
+    #[test]
+    fn test_pool_index() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let link = address!("514910771af9ca656af840dff83e8264ecf986ca");
+
+        let usdc_weth_v2 = AMM::UniswapV2Pool(UniswapV2Pool {
+            address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            token_a: usdc,
+            token_b: weth,
+            ..Default::default()
+        });
+        let usdc_weth_v3 = AMM::UniswapV3Pool(UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            token_a: usdc,
+            token_b: weth,
+            ..Default::default()
+        });
+        let link_weth = AMM::UniswapV2Pool(UniswapV2Pool {
+            address: address!("a2107FA5B38d9bbd2C461D6EDf11B11A50F6b974"),
+            token_a: link,
+            token_b: weth,
+            ..Default::default()
+        });
+
+        let mut index = [&usdc_weth_v2, &usdc_weth_v3, &link_weth]
+            .into_iter()
+            .collect::<PoolIndex>();
+        assert_eq!(index.len(), 3);
+
+        let mut pools = index.pools_for_pair(weth, usdc);
+        pools.sort();
+        let mut expected = vec![
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+        ];
+        expected.sort();
+        assert_eq!(pools, expected);
+        assert_eq!(index.pools_containing(weth).len(), 3);
+        assert_eq!(
+            index.pools_containing(link),
+            vec![address!("a2107FA5B38d9bbd2C461D6EDf11B11A50F6b974")]
+        );
+
+        assert!(index.remove(address!("a2107FA5B38d9bbd2C461D6EDf11B11A50F6b974")));
+        assert!(!index.remove(address!("a2107FA5B38d9bbd2C461D6EDf11B11A50F6b974")));
+        assert!(index.pools_containing(link).is_empty());
+        assert!(index.pools_for_pair(link, weth).is_empty());
+        assert_eq!(index.pools_containing(weth).len(), 2);
+    }
+}
@@ -1,8 +1,46 @@
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
 
 pub mod amm;
+pub mod analytics;
+#[cfg(feature = "provider")]
+pub mod backtest;
+#[cfg(feature = "bincode")]
+pub mod binary;
+#[cfg(feature = "chainlink")]
+pub mod chainlink;
+pub mod chains;
+pub mod core;
+#[cfg(feature = "data-source")]
+pub mod data_source;
+#[cfg(feature = "provider")]
 pub mod discovery;
 pub mod errors;
+pub mod execution;
+#[cfg(feature = "parquet")]
+pub mod export;
 pub mod filters;
+pub mod index;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod positions;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "provider")]
+pub mod rfq;
+pub mod routing;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "state-space")]
 pub mod state_space;
+#[cfg(feature = "storage")]
+pub mod storage;
+#[cfg(feature = "provider")]
 pub mod sync;
+#[cfg(test)]
+mod test_utils;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod token;
+pub mod tvl;
+#[cfg(feature = "revm")]
+pub mod validation;
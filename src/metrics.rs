@@ -0,0 +1,195 @@
+//! Metrics of the state space and batch requests, recorded through the [`metrics`] facade.
+//!
+//! Install a recorder (e.g. `metrics-exporter-prometheus`) to expose them. All metrics are labeled with the
+//! `chain_id` and, where it applies, the `protocol` of the AMMs.
+
+use std::{ops::RangeInclusive, time::Duration};
+
+use alloy::rpc::types::eth::Log;
+use metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit,
+};
+
+use crate::{amm::AMM, state_space::StateSpace};
+
+/// Number of AMMs tracked by the state space.
+pub const POOLS_TRACKED: &str = "amms_pools_tracked";
+/// Number of logs applied to the state space.
+pub const LOGS_APPLIED: &str = "amms_logs_applied_total";
+/// Number of logs applied to the state space per block.
+pub const LOGS_PER_BLOCK: &str = "amms_logs_per_block";
+/// Number of blocks between the chain head and the last synced block, when a new block is received.
+pub const SYNC_LAG: &str = "amms_sync_lag_blocks";
+/// Number of failed RPC requests.
+pub const RPC_ERRORS: &str = "amms_rpc_errors_total";
+/// Latency of batch requests.
+pub const BATCH_REQUEST_LATENCY: &str = "amms_batch_request_latency_seconds";
+
+const PROTOCOLS: [&str; 9] = [
+    "uniswap_v2",
+    "uniswap_v3",
+    "erc_4626",
+    "curve_v2",
+    "rate_adapter",
+    "bancor_v3",
+    "gmx",
+    "ambient",
+    "constant_product",
+];
+
+/// Registers the descriptions of the metrics with the installed recorder.
+pub fn describe_metrics() {
+    describe_gauge!(
+        POOLS_TRACKED,
+        Unit::Count,
+        "Number of AMMs tracked by the state space"
+    );
+    describe_counter!(
+        LOGS_APPLIED,
+        Unit::Count,
+        "Number of logs applied to the state space"
+    );
+    describe_histogram!(
+        LOGS_PER_BLOCK,
+        Unit::Count,
+        "Number of logs applied per block"
+    );
+    describe_gauge!(
+        SYNC_LAG,
+        Unit::Count,
+        "Blocks between the chain head and the last synced block"
+    );
+    describe_counter!(RPC_ERRORS, Unit::Count, "Number of failed RPC requests");
+    describe_histogram!(
+        BATCH_REQUEST_LATENCY,
+        Unit::Seconds,
+        "Latency of batch requests"
+    );
+}
+
+/// Returns the `protocol` label of an AMM.
+pub fn protocol(amm: &AMM) -> &'static str {
+    match amm {
+        AMM::UniswapV2Pool(_) => PROTOCOLS[0],
+        AMM::UniswapV3Pool(_) => PROTOCOLS[1],
+        AMM::ERC4626Vault(_) => PROTOCOLS[2],
+        AMM::CurveV2Pool(_) => PROTOCOLS[3],
+        AMM::RateAdapter(_) => PROTOCOLS[4],
+        AMM::BancorV3Pool(_) => PROTOCOLS[5],
+        AMM::GmxMarket(_) => PROTOCOLS[6],
+        AMM::AmbientPool(_) => PROTOCOLS[7],
+        AMM::ConstantProductPool(_) => PROTOCOLS[8],
+    }
+}
+
+/// Records metrics for a single chain.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    chain_id: String,
+}
+
+impl Metrics {
+    pub fn new(chain_id: u64) -> Self {
+        Self {
+            chain_id: chain_id.to_string(),
+        }
+    }
+
+    /// Sets the number of tracked AMMs of each protocol.
+    pub fn record_pools_tracked(&self, state: &StateSpace) {
+        for (protocol, count) in count_by_protocol(state.values()) {
+            gauge!(POOLS_TRACKED, "chain_id" => self.chain_id.clone(), "protocol" => protocol)
+                .set(count as f64);
+        }
+    }
+
+    /// Records the logs of `blocks` that apply to AMMs in `state`.
+    pub fn record_logs_applied(
+        &self,
+        state: &StateSpace,
+        logs: &[Log],
+        blocks: RangeInclusive<u64>,
+    ) {
+        let applied = logs
+            .iter()
+            .filter_map(|log| Some((state.get(&log.address())?, log.block_number?)))
+            .collect::<Vec<_>>();
+
+        for (protocol, count) in count_by_protocol(applied.iter().map(|(amm, _)| *amm)) {
+            counter!(LOGS_APPLIED, "chain_id" => self.chain_id.clone(), "protocol" => protocol)
+                .increment(count);
+        }
+
+        for block_number in blocks {
+            let count = applied
+                .iter()
+                .filter(|(_, log_block_number)| *log_block_number == block_number)
+                .count();
+
+            histogram!(LOGS_PER_BLOCK, "chain_id" => self.chain_id.clone()).record(count as f64);
+        }
+    }
+
+    /// Sets the number of blocks between the chain head and the last synced block.
+    pub fn record_sync_lag(&self, chain_head_block_number: u64, last_synced_block: u64) {
+        gauge!(SYNC_LAG, "chain_id" => self.chain_id.clone())
+            .set(chain_head_block_number.saturating_sub(last_synced_block) as f64);
+    }
+
+    /// Counts a failed RPC request.
+    pub fn record_rpc_error(&self, method: &'static str) {
+        counter!(RPC_ERRORS, "chain_id" => self.chain_id.clone(), "method" => method).increment(1);
+    }
+
+    /// Records the latency of a batch request for AMMs of `protocol`.
+    pub fn record_batch_request_latency(&self, protocol: &'static str, latency: Duration) {
+        histogram!(BATCH_REQUEST_LATENCY, "chain_id" => self.chain_id.clone(), "protocol" => protocol)
+            .record(latency.as_secs_f64());
+    }
+}
+
+/// Counts the AMMs of each protocol.
+fn count_by_protocol<'a>(
+    amms: impl Iterator<Item = &'a AMM>,
+) -> [(&'static str, u64); PROTOCOLS.len()] {
+    let mut counts = PROTOCOLS.map(|protocol| (protocol, 0));
+    for amm in amms {
+        let protocol = protocol(amm);
+        if let Some((_, count)) = counts.iter_mut().find(|(p, _)| *p == protocol) {
+            *count += 1;
+        }
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amm::{erc_4626::ERC4626Vault, uniswap_v2::UniswapV2Pool, AMM};
+
+    use super::count_by_protocol;
+
+    #[test]
+    fn test_count_by_protocol() {
+        let amms = [
+            AMM::UniswapV2Pool(UniswapV2Pool::default()),
+            AMM::UniswapV2Pool(UniswapV2Pool::default()),
+            AMM::ERC4626Vault(ERC4626Vault::default()),
+        ];
+
+        assert_eq!(
+            count_by_protocol(amms.iter()),
+            [
+                ("uniswap_v2", 2),
+                ("uniswap_v3", 0),
+                ("erc_4626", 1),
+                ("curve_v2", 0),
+                ("rate_adapter", 0),
+                ("bancor_v3", 0),
+                ("gmx", 0),
+                ("ambient", 0),
+                ("constant_product", 0)
+            ]
+        );
+    }
+}
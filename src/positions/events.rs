@@ -0,0 +1,160 @@
+//! Liquidity and fee collection events of Uniswap V3 positions.
+//!
+//! Positions minted through a NonfungiblePositionManager are identified by the manager and their token id and emit
+//! `IncreaseLiquidity`, `DecreaseLiquidity` and `Collect` from the manager. Positions held directly in a pool are
+//! identified by the pool, their owner and their range, and emit `Mint`, `Burn` and `Collect` from the pool.
+
+use alloy::{
+    primitives::{Address, B256, U256},
+    rpc::types::eth::Log,
+    sol_types::SolEvent,
+};
+
+use crate::{amm::uniswap_v3::IUniswapV3Pool, errors::EventLogError};
+
+use super::INonfungiblePositionManager;
+
+/// Identifies a position, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PositionKey {
+    /// Token ids are only unique within a manager, e.g. forks deploy their own managers.
+    TokenId { manager: Address, token_id: U256 },
+    Pool {
+        pool: Address,
+        owner: Address,
+        tick_lower: i32,
+        tick_upper: i32,
+    },
+}
+
+/// Liquidity added to or removed from a position, with the token amounts deposited or withdrawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidityChange {
+    pub key: PositionKey,
+    pub liquidity: u128,
+    pub amount_0: U256,
+    pub amount_1: U256,
+}
+
+/// Tokens collected from a position, withdrawn liquidity and fees alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collect {
+    pub key: PositionKey,
+    pub recipient: Address,
+    pub amount_0: U256,
+    pub amount_1: U256,
+}
+
+/// A decoded position event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEvent {
+    IncreaseLiquidity(LiquidityChange),
+    DecreaseLiquidity(LiquidityChange),
+    Collect(Collect),
+}
+
+impl PositionEvent {
+    /// Signatures of the events decoded by [`PositionEvent::decode_log`].
+    pub const SIGNATURES: [B256; 6] = [
+        INonfungiblePositionManager::IncreaseLiquidity::SIGNATURE_HASH,
+        INonfungiblePositionManager::DecreaseLiquidity::SIGNATURE_HASH,
+        INonfungiblePositionManager::Collect::SIGNATURE_HASH,
+        IUniswapV3Pool::Mint::SIGNATURE_HASH,
+        IUniswapV3Pool::Burn::SIGNATURE_HASH,
+        IUniswapV3Pool::Collect::SIGNATURE_HASH,
+    ];
+
+    /// Decodes a position event of the NonfungiblePositionManager or of a pool.
+    pub fn decode_log(log: &Log) -> Result<Self, EventLogError> {
+        let event_signature = log
+            .topics()
+            .first()
+            .copied()
+            .ok_or(EventLogError::InvalidEventSignature)?;
+
+        let event = if event_signature
+            == INonfungiblePositionManager::IncreaseLiquidity::SIGNATURE_HASH
+        {
+            let event =
+                INonfungiblePositionManager::IncreaseLiquidity::decode_log(log.as_ref(), true)?;
+            PositionEvent::IncreaseLiquidity(LiquidityChange {
+                key: manager_key(log, event.tokenId),
+                liquidity: event.liquidity,
+                amount_0: event.amount0,
+                amount_1: event.amount1,
+            })
+        } else if event_signature == INonfungiblePositionManager::DecreaseLiquidity::SIGNATURE_HASH
+        {
+            let event =
+                INonfungiblePositionManager::DecreaseLiquidity::decode_log(log.as_ref(), true)?;
+            PositionEvent::DecreaseLiquidity(LiquidityChange {
+                key: manager_key(log, event.tokenId),
+                liquidity: event.liquidity,
+                amount_0: event.amount0,
+                amount_1: event.amount1,
+            })
+        } else if event_signature == INonfungiblePositionManager::Collect::SIGNATURE_HASH {
+            let event = INonfungiblePositionManager::Collect::decode_log(log.as_ref(), true)?;
+            PositionEvent::Collect(Collect {
+                key: manager_key(log, event.tokenId),
+                recipient: event.recipient,
+                amount_0: event.amount0,
+                amount_1: event.amount1,
+            })
+        } else if event_signature == IUniswapV3Pool::Mint::SIGNATURE_HASH {
+            let event = IUniswapV3Pool::Mint::decode_log(log.as_ref(), true)?;
+            PositionEvent::IncreaseLiquidity(LiquidityChange {
+                key: pool_key(log, event.owner, event.tickLower, event.tickUpper),
+                liquidity: event.amount,
+                amount_0: event.amount0,
+                amount_1: event.amount1,
+            })
+        } else if event_signature == IUniswapV3Pool::Burn::SIGNATURE_HASH {
+            let event = IUniswapV3Pool::Burn::decode_log(log.as_ref(), true)?;
+            PositionEvent::DecreaseLiquidity(LiquidityChange {
+                key: pool_key(log, event.owner, event.tickLower, event.tickUpper),
+                liquidity: event.amount,
+                amount_0: event.amount0,
+                amount_1: event.amount1,
+            })
+        } else if event_signature == IUniswapV3Pool::Collect::SIGNATURE_HASH {
+            let event = IUniswapV3Pool::Collect::decode_log(log.as_ref(), true)?;
+            PositionEvent::Collect(Collect {
+                key: pool_key(log, event.owner, event.tickLower, event.tickUpper),
+                recipient: event.recipient,
+                amount_0: U256::from(event.amount0),
+                amount_1: U256::from(event.amount1),
+            })
+        } else {
+            return Err(EventLogError::InvalidEventSignature);
+        };
+
+        Ok(event)
+    }
+
+    /// Returns the position of the event.
+    pub fn key(&self) -> PositionKey {
+        match self {
+            PositionEvent::IncreaseLiquidity(change) | PositionEvent::DecreaseLiquidity(change) => {
+                change.key
+            }
+            PositionEvent::Collect(collect) => collect.key,
+        }
+    }
+}
+
+fn manager_key(log: &Log, token_id: U256) -> PositionKey {
+    PositionKey::TokenId {
+        manager: log.address(),
+        token_id,
+    }
+}
+
+fn pool_key(log: &Log, owner: Address, tick_lower: i32, tick_upper: i32) -> PositionKey {
+    PositionKey::Pool {
+        pool: log.address(),
+        owner,
+        tick_lower,
+        tick_upper,
+    }
+}
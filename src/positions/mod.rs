@@ -0,0 +1,494 @@
+pub mod events;
+
+use std::collections::HashMap;
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+use alloy::{
+    primitives::{Address, U256},
+    rpc::types::eth::Log,
+    sol,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "provider")]
+use crate::errors::AMMError;
+use crate::{
+    amm::uniswap_v3::UniswapV3Pool,
+    core::price::{sqrt_price_x96_to_price, u256_to_f64},
+    errors::{ArithmeticError, EventLogError},
+};
+
+use self::events::{PositionEvent, PositionKey};
+
+sol! {
+    /// Interface of the Uniswap V3 NonfungiblePositionManager
+    #[derive(Debug, PartialEq, Eq)]
+    #[sol(rpc)]
+    contract INonfungiblePositionManager {
+        function positions(uint256 tokenId) external view returns (uint96 nonce, address operator, address token0, address token1, uint24 fee, int24 tickLower, int24 tickUpper, uint128 liquidity, uint256 feeGrowthInside0LastX128, uint256 feeGrowthInside1LastX128, uint128 tokensOwed0, uint128 tokensOwed1);
+        function factory() external view returns (address);
+        event IncreaseLiquidity(uint256 indexed tokenId, uint128 liquidity, uint256 amount0, uint256 amount1);
+        event DecreaseLiquidity(uint256 indexed tokenId, uint128 liquidity, uint256 amount0, uint256 amount1);
+        event Collect(uint256 indexed tokenId, address recipient, uint256 amount0, uint256 amount1);
+    }
+}
+
+/// A Uniswap V3 liquidity position minted through the NonfungiblePositionManager.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Position {
+    pub token_id: U256,
+    pub token_0: Address,
+    pub token_1: Address,
+    pub fee: u32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub tokens_owed_0: u128,
+    pub tokens_owed_1: u128,
+}
+
+/// The token amounts held by a position at a given pool price.
+///
+/// Used as the entry point of a position to compare the position against holding the tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionSnapshot {
+    pub amount_0: U256,
+    pub amount_1: U256,
+    pub sqrt_price: U256,
+}
+
+/// Valuation of a position against holding the entry amounts, denominated in token 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImpermanentLoss {
+    /// Value of the position at the current pool price.
+    pub position_value: f64,
+    /// Value of the entry amounts at the current pool price.
+    pub hodl_value: f64,
+    /// `position_value / hodl_value - 1`, zero or negative for a position without fees.
+    pub impermanent_loss: f64,
+}
+
+impl Position {
+    #[cfg(feature = "provider")]
+    /// Loads a position from the NonfungiblePositionManager by token id.
+    pub async fn new_from_token_id<T, N, P>(
+        position_manager: Address,
+        token_id: U256,
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<Self, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let position_manager = INonfungiblePositionManager::new(position_manager, provider);
+
+        let call = position_manager.positions(token_id);
+        let position = if let Some(block_number) = block_number {
+            call.block(block_number.into()).call().await?
+        } else {
+            call.call().await?
+        };
+
+        Ok(Position {
+            token_id,
+            token_0: position.token0,
+            token_1: position.token1,
+            fee: position.fee,
+            tick_lower: position.tickLower,
+            tick_upper: position.tickUpper,
+            liquidity: position.liquidity,
+            tokens_owed_0: position.tokensOwed0,
+            tokens_owed_1: position.tokensOwed1,
+        })
+    }
+
+    /// Returns whether the position belongs to the given pool.
+    pub fn is_in_pool(&self, pool: &UniswapV3Pool) -> bool {
+        self.token_0 == pool.token_a && self.token_1 == pool.token_b && self.fee == pool.fee
+    }
+
+    /// Returns whether the current tick of the pool is within the position range.
+    pub fn is_in_range(&self, pool: &UniswapV3Pool) -> bool {
+        pool.tick >= self.tick_lower && pool.tick < self.tick_upper
+    }
+
+    /// Returns the amounts of token 0 and token 1 the position is worth at the current pool price.
+    ///
+    /// Uncollected fees (`tokens_owed_0`/`tokens_owed_1`) are not included.
+    pub fn amounts(&self, pool: &UniswapV3Pool) -> Result<(U256, U256), ArithmeticError> {
+        pool.amounts_for_liquidity(self.tick_lower, self.tick_upper, self.liquidity)
+    }
+
+    /// Returns a snapshot of the position amounts at the current pool price.
+    pub fn snapshot(&self, pool: &UniswapV3Pool) -> Result<PositionSnapshot, ArithmeticError> {
+        let (amount_0, amount_1) = self.amounts(pool)?;
+
+        Ok(PositionSnapshot {
+            amount_0,
+            amount_1,
+            sqrt_price: pool.sqrt_price,
+        })
+    }
+
+    /// Returns the value of the position at the current pool price, denominated in token 1 (without decimal adjustment).
+    pub fn value_in_token_1(&self, pool: &UniswapV3Pool) -> Result<f64, ArithmeticError> {
+        let (amount_0, amount_1) = self.amounts(pool)?;
+        Ok(value_in_token_1(amount_0, amount_1, pool.sqrt_price))
+    }
+
+    /// Compares the value of the position against holding the amounts from `entry` at the current pool price.
+    pub fn impermanent_loss(
+        &self,
+        entry: &PositionSnapshot,
+        pool: &UniswapV3Pool,
+    ) -> Result<ImpermanentLoss, ArithmeticError> {
+        let position_value = self.value_in_token_1(pool)?;
+        let hodl_value = value_in_token_1(entry.amount_0, entry.amount_1, pool.sqrt_price);
+
+        let impermanent_loss = if hodl_value == 0.0 {
+            0.0
+        } else {
+            position_value / hodl_value - 1.0
+        };
+
+        Ok(ImpermanentLoss {
+            position_value,
+            hodl_value,
+            impermanent_loss,
+        })
+    }
+}
+
+/// Liquidity and token flows of a position, accumulated from its events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionLedger {
+    pub liquidity: u128,
+    pub deposited_0: U256,
+    pub deposited_1: U256,
+    pub withdrawn_0: U256,
+    pub withdrawn_1: U256,
+    pub collected_0: U256,
+    pub collected_1: U256,
+}
+
+impl PositionLedger {
+    /// Applies a liquidity change or collection of the position.
+    pub fn apply_event(&mut self, event: &PositionEvent) {
+        match event {
+            PositionEvent::IncreaseLiquidity(change) => {
+                self.liquidity = self.liquidity.saturating_add(change.liquidity);
+                self.deposited_0 += change.amount_0;
+                self.deposited_1 += change.amount_1;
+            }
+            PositionEvent::DecreaseLiquidity(change) => {
+                self.liquidity = self.liquidity.saturating_sub(change.liquidity);
+                self.withdrawn_0 += change.amount_0;
+                self.withdrawn_1 += change.amount_1;
+            }
+            PositionEvent::Collect(collect) => {
+                self.collected_0 += collect.amount_0;
+                self.collected_1 += collect.amount_1;
+            }
+        }
+    }
+
+    /// Returns the fees of token 0 and token 1 collected by the position.
+    ///
+    /// Collections do not tell withdrawn liquidity and fees apart, so the fees are the collected amounts above the
+    /// withdrawn amounts.
+    pub fn realized_fees(&self) -> (U256, U256) {
+        (
+            self.collected_0.saturating_sub(self.withdrawn_0),
+            self.collected_1.saturating_sub(self.withdrawn_1),
+        )
+    }
+}
+
+/// Tracks the ledgers of positions from their events, see [`events`].
+#[derive(Debug, Clone, Default)]
+pub struct PositionTracker {
+    position_manager: Option<Address>,
+    ledgers: HashMap<PositionKey, PositionLedger>,
+}
+
+impl PositionTracker {
+    /// Creates a tracker of the positions of `position_manager` and of the positions held directly in pools.
+    ///
+    /// The pool events of the positions owned by `position_manager` are skipped, as they are tracked by token id from
+    /// the events of the manager. Events of other managers are skipped.
+    pub fn new(position_manager: Address) -> Self {
+        Self {
+            position_manager: Some(position_manager),
+            ledgers: HashMap::new(),
+        }
+    }
+
+    /// Applies `event` to the ledger of its position.
+    pub fn apply_event(&mut self, event: &PositionEvent) {
+        let key = event.key();
+        let skipped = match key {
+            PositionKey::TokenId { manager, .. } => self
+                .position_manager
+                .is_some_and(|position_manager| manager != position_manager),
+            PositionKey::Pool { owner, .. } => Some(owner) == self.position_manager,
+        };
+        if skipped {
+            return;
+        }
+
+        self.ledgers.entry(key).or_default().apply_event(event);
+    }
+
+    /// Applies the position events of `logs` in order, skipping other logs.
+    pub fn apply_logs<'a>(
+        &mut self,
+        logs: impl IntoIterator<Item = &'a Log>,
+    ) -> Result<(), EventLogError> {
+        for log in logs {
+            match PositionEvent::decode_log(log) {
+                Ok(event) => self.apply_event(&event),
+                Err(EventLogError::InvalidEventSignature) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn ledger(&self, key: &PositionKey) -> Option<&PositionLedger> {
+        self.ledgers.get(key)
+    }
+
+    pub fn ledgers(&self) -> &HashMap<PositionKey, PositionLedger> {
+        &self.ledgers
+    }
+
+    /// Returns the fees collected by the position of `key`, zero if it has no events.
+    pub fn realized_fees(&self, key: &PositionKey) -> (U256, U256) {
+        self.ledger(key)
+            .map_or((U256::ZERO, U256::ZERO), PositionLedger::realized_fees)
+    }
+}
+
+/// Values `amount_0` and `amount_1` in token 1 at `sqrt_price`.
+fn value_in_token_1(amount_0: U256, amount_1: U256, sqrt_price: U256) -> f64 {
+    u256_to_f64(amount_0) * sqrt_price_x96_to_price(sqrt_price, 0, 0) + u256_to_f64(amount_1)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog, U256},
+        rpc::types::eth::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::amm::uniswap_v3::{IUniswapV3Pool, UniswapV3Pool};
+
+    use super::{events::PositionKey, INonfungiblePositionManager, Position, PositionTracker};
+
+    fn pool_at_tick(tick: i32) -> UniswapV3Pool {
+        UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 500,
+            tick,
+            tick_spacing: 10,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(tick).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn position(tick_lower: i32, tick_upper: i32) -> Position {
+        Position {
+            token_0: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_1: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            fee: 500,
+            tick_lower,
+            tick_upper,
+            liquidity: 1_000_000_000_000_000_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_amounts_out_of_range() {
+        let pool = pool_at_tick(0);
+
+        let (amount_0, amount_1) = position(100, 200).amounts(&pool).unwrap();
+        assert!(amount_0 > U256::ZERO);
+        assert_eq!(amount_1, U256::ZERO);
+
+        let (amount_0, amount_1) = position(-200, -100).amounts(&pool).unwrap();
+        assert_eq!(amount_0, U256::ZERO);
+        assert!(amount_1 > U256::ZERO);
+    }
+
+    #[test]
+    fn test_amounts_in_range() {
+        let pool = pool_at_tick(0);
+        let position = position(-100, 100);
+
+        assert!(position.is_in_pool(&pool));
+        assert!(position.is_in_range(&pool));
+
+        let (amount_0, amount_1) = position.amounts(&pool).unwrap();
+
+        // At a price of 1, a symmetric range holds (almost) equal amounts of both tokens
+        assert!(amount_0 > U256::ZERO);
+        assert!(amount_1 > U256::ZERO);
+        assert!(amount_0.abs_diff(amount_1) <= U256::from(10));
+    }
+
+    #[test]
+    fn test_impermanent_loss() {
+        let position = position(-1000, 1000);
+
+        let entry = position.snapshot(&pool_at_tick(0)).unwrap();
+
+        let unchanged = position.impermanent_loss(&entry, &pool_at_tick(0)).unwrap();
+        assert!(unchanged.impermanent_loss.abs() < 1e-12);
+
+        let moved = position
+            .impermanent_loss(&entry, &pool_at_tick(500))
+            .unwrap();
+        assert!(moved.impermanent_loss < 0.0);
+        assert!(moved.position_value < moved.hodl_value);
+    }
+
+    #[test]
+    fn test_position_tracker() {
+        let position_manager = address!("C36442b4a4522E871399CD717aBDD847Ab11FE88");
+        let pool = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+        let owner = Address::with_last_byte(1);
+        let log = |address, data| Log {
+            inner: PrimitiveLog { address, data },
+            ..Default::default()
+        };
+        let token_id = U256::from(1);
+
+        let logs = vec![
+            log(
+                position_manager,
+                INonfungiblePositionManager::IncreaseLiquidity {
+                    tokenId: token_id,
+                    liquidity: 1000,
+                    amount0: U256::from(500),
+                    amount1: U256::from(500),
+                }
+                .encode_log_data(),
+            ),
+            log(
+                position_manager,
+                INonfungiblePositionManager::DecreaseLiquidity {
+                    tokenId: token_id,
+                    liquidity: 400,
+                    amount0: U256::from(200),
+                    amount1: U256::from(200),
+                }
+                .encode_log_data(),
+            ),
+            log(
+                position_manager,
+                INonfungiblePositionManager::Collect {
+                    tokenId: token_id,
+                    recipient: owner,
+                    amount0: U256::from(230),
+                    amount1: U256::from(210),
+                }
+                .encode_log_data(),
+            ),
+            // Pool events of the manager are tracked by token id
+            log(
+                pool,
+                IUniswapV3Pool::Collect {
+                    owner: position_manager,
+                    recipient: owner,
+                    tickLower: -10,
+                    tickUpper: 10,
+                    amount0: 230,
+                    amount1: 210,
+                }
+                .encode_log_data(),
+            ),
+            // Position held directly in the pool
+            log(
+                pool,
+                IUniswapV3Pool::Burn {
+                    owner,
+                    tickLower: -100,
+                    tickUpper: 100,
+                    amount: 0,
+                    amount0: U256::ZERO,
+                    amount1: U256::ZERO,
+                }
+                .encode_log_data(),
+            ),
+            log(
+                pool,
+                IUniswapV3Pool::Collect {
+                    owner,
+                    recipient: owner,
+                    tickLower: -100,
+                    tickUpper: 100,
+                    amount0: 7,
+                    amount1: 0,
+                }
+                .encode_log_data(),
+            ),
+            // Events of other managers are skipped
+            log(
+                Address::with_last_byte(2),
+                INonfungiblePositionManager::IncreaseLiquidity {
+                    tokenId: token_id,
+                    liquidity: 1000,
+                    amount0: U256::from(500),
+                    amount1: U256::from(500),
+                }
+                .encode_log_data(),
+            ),
+            // Other logs are skipped
+            log(
+                pool,
+                IUniswapV3Pool::Swap {
+                    sender: owner,
+                    recipient: owner,
+                    amount0: Default::default(),
+                    amount1: Default::default(),
+                    sqrtPriceX96: U256::from(1) << 96,
+                    liquidity: 0,
+                    tick: 0,
+                }
+                .encode_log_data(),
+            ),
+        ];
+
+        let mut tracker = PositionTracker::new(position_manager);
+        tracker.apply_logs(&logs).unwrap();
+        assert_eq!(tracker.ledgers().len(), 2);
+
+        let key = PositionKey::TokenId {
+            manager: position_manager,
+            token_id,
+        };
+        assert_eq!(tracker.ledger(&key).unwrap().liquidity, 600);
+        assert_eq!(
+            tracker.realized_fees(&key),
+            (U256::from(30), U256::from(10))
+        );
+
+        let key = PositionKey::Pool {
+            pool,
+            owner,
+            tick_lower: -100,
+            tick_upper: 100,
+        };
+        assert_eq!(tracker.realized_fees(&key), (U256::from(7), U256::ZERO));
+    }
+}
@@ -0,0 +1,154 @@
+//! Python bindings for pool simulation.
+//!
+//! Exposes AMMs deserialized from JSON snapshots (the format of checkpoints and `AMM` serialization) with the exact
+//! swap and price math of the crate. Build the `amms` Python module with `maturin build --release`, see
+//! `pyproject.toml`. Addresses are hex strings, amounts are Python integers of up to 128 bits.
+//!
+//! ```python
+//! import amms
+//!
+//! pools, block_number = amms.load_checkpoint("checkpoint.json")
+//! pool = pools[0]
+//! amount_out = pool.simulate_swap(pool.tokens()[0], 10**18)
+//! ```
+
+use alloy::primitives::{Address, U256};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    sync::checkpoint::deconstruct_checkpoint,
+};
+
+/// An AMM, simulated locally.
+#[pyclass(name = "Pool")]
+#[derive(Debug, Clone)]
+pub struct PyPool {
+    pub amm: AMM,
+}
+
+#[pymethods]
+impl PyPool {
+    /// Deserializes a pool from a JSON snapshot, e.g. `{"UniswapV2Pool": {...}}`.
+    #[staticmethod]
+    pub fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self {
+            amm: serde_json::from_str(json).map_err(value_error)?,
+        })
+    }
+
+    /// Serializes the pool to a JSON snapshot.
+    pub fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.amm).map_err(value_error)
+    }
+
+    #[getter]
+    pub fn address(&self) -> String {
+        self.amm.address().to_string()
+    }
+
+    pub fn tokens(&self) -> Vec<String> {
+        self.amm
+            .tokens()
+            .into_iter()
+            .map(|token| token.to_string())
+            .collect()
+    }
+
+    /// Returns the amount of the other token received for `amount_in` of `token_in`.
+    pub fn simulate_swap(&self, token_in: &str, amount_in: u128) -> PyResult<u128> {
+        let amount_out = self
+            .amm
+            .simulate_swap(parse_address(token_in)?, U256::from(amount_in))
+            .map_err(value_error)?;
+
+        to_u128(amount_out)
+    }
+
+    /// Same as `simulate_swap`, updating the pool state with the swap.
+    pub fn simulate_swap_mut(&mut self, token_in: &str, amount_in: u128) -> PyResult<u128> {
+        let amount_out = self
+            .amm
+            .simulate_swap_mut(parse_address(token_in)?, U256::from(amount_in))
+            .map_err(value_error)?;
+
+        to_u128(amount_out)
+    }
+
+    /// Returns the price of `base_token` in terms of the other token.
+    pub fn calculate_price(&self, base_token: &str) -> PyResult<f64> {
+        self.amm
+            .calculate_price(parse_address(base_token)?)
+            .map_err(value_error)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Pool({})", self.address())
+    }
+}
+
+/// Loads the pools of a checkpoint, along with the block number it was synced to.
+#[pyfunction]
+pub fn load_checkpoint(path: &str) -> PyResult<(Vec<PyPool>, u64)> {
+    let (amms, block_number) = deconstruct_checkpoint(path).map_err(value_error)?;
+
+    Ok((
+        amms.into_iter().map(|amm| PyPool { amm }).collect(),
+        block_number,
+    ))
+}
+
+#[pymodule]
+fn amms(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPool>()?;
+    m.add_function(wrap_pyfunction!(load_checkpoint, m)?)?;
+    Ok(())
+}
+
+fn parse_address(address: &str) -> PyResult<Address> {
+    address.parse().map_err(value_error)
+}
+
+fn to_u128(amount: U256) -> PyResult<u128> {
+    amount
+        .try_into()
+        .map_err(|_| PyValueError::new_err(format!("{amount} does not fit in 128 bits")))
+}
+
+fn value_error<E: ToString>(err: E) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        amm::AMM,
+        test_utils::{usdc_weth_pool, USDC},
+    };
+
+    use super::PyPool;
+
+    #[test]
+    fn test_pool_from_json() {
+        let json = serde_json::to_string(&AMM::UniswapV2Pool(usdc_weth_pool())).unwrap();
+
+        let mut pool = PyPool::from_json(&json).unwrap();
+        let token_in = USDC.to_string();
+
+        let amount_out = pool.simulate_swap(&token_in, 1_000_000).unwrap();
+        assert!(amount_out > 0);
+        assert_eq!(
+            pool.simulate_swap_mut(&token_in, 1_000_000).unwrap(),
+            amount_out
+        );
+        assert!(pool.simulate_swap(&token_in, 1_000_000).unwrap() < amount_out);
+
+        assert!(pool.simulate_swap("not an address", 1).is_err());
+        assert_eq!(
+            PyPool::from_json(&pool.to_json().unwrap())
+                .unwrap()
+                .tokens(),
+            pool.tokens()
+        );
+    }
+}
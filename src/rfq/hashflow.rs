@@ -0,0 +1,235 @@
+//! Hashflow RFQ quotes, through the taker API of Hashflow.
+
+use alloy::primitives::{Address, U256};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::errors::RfqError;
+
+use super::{IndicativeQuote, IndicativeQuoteVenue};
+
+/// URL of the RFQ endpoint of the Hashflow taker API.
+pub const HASHFLOW_RFQ_URL: &str = "https://api.hashflow.com/taker/v3/rfq";
+
+/// Hashflow RFQ venue of a chain, requesting quotes on behalf of `trader`.
+///
+/// Quotes are signed by the market makers and can be executed through the Hashflow router until their expiry, the
+/// signature is not kept as quotes are only used to compare prices.
+#[derive(Debug, Clone)]
+pub struct HashflowVenue {
+    pub chain_id: u64,
+    /// Address that will execute the quotes, required by market makers to price them.
+    pub trader: Address,
+    /// Name of the integration, as registered with Hashflow.
+    pub source: String,
+    pub api_key: String,
+    pub url: String,
+    client: reqwest::Client,
+}
+
+/// Response of the RFQ endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RfqResponse {
+    status: String,
+    #[serde(default)]
+    quotes: Vec<RfqQuote>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RfqQuote {
+    quote_data: QuoteData,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuoteData {
+    base_token: Address,
+    quote_token: Address,
+    base_token_amount: U256,
+    quote_token_amount: U256,
+    quote_expiry: u64,
+}
+
+impl HashflowVenue {
+    pub fn new(chain_id: u64, trader: Address, source: String, api_key: String) -> Self {
+        HashflowVenue {
+            chain_id,
+            trader,
+            source,
+            api_key,
+            url: HASHFLOW_RFQ_URL.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Returns the request body of an RFQ selling `amount_in` of `token_in` for `token_out`.
+    fn request_body(&self, token_in: Address, token_out: Address, amount_in: U256) -> Value {
+        let chain = json!({ "chainType": "evm", "chainId": self.chain_id });
+
+        json!({
+            "source": self.source,
+            "baseChain": chain,
+            "quoteChain": chain,
+            "rfqs": [{
+                "baseToken": token_in,
+                "quoteToken": token_out,
+                "baseTokenAmount": amount_in.to_string(),
+                "trader": self.trader,
+                "effectiveTrader": self.trader,
+            }],
+        })
+    }
+}
+
+#[async_trait]
+impl IndicativeQuoteVenue for HashflowVenue {
+    fn name(&self) -> &str {
+        "Hashflow"
+    }
+
+    async fn quote(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+    ) -> Result<IndicativeQuote, RfqError> {
+        let body = serde_json::to_string(&self.request_body(token_in, token_out, amount_in))?;
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::AUTHORIZATION, &self.api_key)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        parse_rfq_response(&response, token_in, token_out, amount_in)
+    }
+}
+
+/// Returns the best quote of an RFQ response for selling `amount_in` of `token_in` for `token_out`.
+///
+/// Market makers may fill only part of the RFQ, quotes for a different amount or pair are rejected.
+fn parse_rfq_response(
+    response: &str,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+) -> Result<IndicativeQuote, RfqError> {
+    let response: RfqResponse = serde_json::from_str(response)?;
+    if response.status != "success" {
+        return Err(RfqError::QuoteRejected(
+            response
+                .error
+                .map_or(response.status, |error| error.to_string()),
+        ));
+    }
+
+    let quote = response
+        .quotes
+        .into_iter()
+        .map(|quote| quote.quote_data)
+        .filter(|quote| quote.base_token == token_in && quote.quote_token == token_out)
+        .max_by_key(|quote| quote.quote_token_amount)
+        .ok_or(RfqError::QuoteRejected("No quotes".to_string()))?;
+
+    if quote.base_token_amount != amount_in {
+        return Err(RfqError::InvalidResponse(format!(
+            "Quote for {} instead of {amount_in}",
+            quote.base_token_amount
+        )));
+    }
+
+    Ok(IndicativeQuote {
+        venue: "Hashflow".to_string(),
+        token_in,
+        token_out,
+        amount_in,
+        amount_out: quote.quote_token_amount,
+        expiry: quote.quote_expiry,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, Address, U256};
+
+    use crate::errors::RfqError;
+
+    use super::{parse_rfq_response, HashflowVenue};
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    #[test]
+    fn test_parse_rfq_response() {
+        let response = r#"{
+            "status": "success",
+            "rfqId": "0x01",
+            "quotes": [
+                {
+                    "quoteData": {
+                        "baseToken": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                        "quoteToken": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                        "baseTokenAmount": "1000000000",
+                        "quoteTokenAmount": "301000000000000000",
+                        "quoteExpiry": 1700000030,
+                        "pool": "0x0000000000000000000000000000000000000001"
+                    },
+                    "signature": "0x00"
+                },
+                {
+                    "quoteData": {
+                        "baseToken": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                        "quoteToken": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                        "baseTokenAmount": "1000000000",
+                        "quoteTokenAmount": "302000000000000000",
+                        "quoteExpiry": 1700000020
+                    },
+                    "signature": "0x00"
+                }
+            ]
+        }"#;
+
+        let amount_in = U256::from(1_000_000_000_u64);
+        let quote = parse_rfq_response(response, USDC, WETH, amount_in).unwrap();
+        assert_eq!(quote.amount_out, U256::from(302_000_000_000_000_000_u64));
+        assert_eq!(quote.expiry, 1_700_000_020);
+        assert!(!quote.is_expired(1_700_000_000));
+
+        // Partial fills are rejected
+        assert!(matches!(
+            parse_rfq_response(response, USDC, WETH, amount_in * U256::from(2)),
+            Err(RfqError::InvalidResponse(_))
+        ));
+
+        let response = r#"{ "status": "fail", "error": { "code": "NoQuotes" } }"#;
+        assert!(matches!(
+            parse_rfq_response(response, USDC, WETH, amount_in),
+            Err(RfqError::QuoteRejected(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_body() {
+        let trader = address!("0000000000000000000000000000000000000001");
+        let venue = HashflowVenue::new(1, trader, "amms".to_string(), "key".to_string());
+
+        let body = venue.request_body(USDC, WETH, U256::from(1_000));
+        assert_eq!(body["baseChain"]["chainId"], 1);
+        assert_eq!(body["rfqs"][0]["baseTokenAmount"], "1000");
+        assert_eq!(
+            serde_json::from_value::<Address>(body["rfqs"][0]["quoteToken"].clone()).unwrap(),
+            WETH
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Indicative quotes from non-AMM liquidity (RFQ systems, limit order books), so that routers can compare them with
+//! AMM routes.
+
+pub mod hashflow;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy::primitives::{Address, U256};
+use async_trait::async_trait;
+use futures::future;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::RfqError, routing::Route};
+
+/// Quote of a venue for swapping `amount_in` of `token_in`, valid until `expiry`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndicativeQuote {
+    /// Name of the venue that issued the quote.
+    pub venue: String,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Unix timestamp in seconds after which the quote is no longer valid.
+    pub expiry: u64,
+}
+
+impl IndicativeQuote {
+    /// Returns whether the quote has expired at `timestamp`, in seconds.
+    pub fn is_expired(&self, timestamp: u64) -> bool {
+        timestamp >= self.expiry
+    }
+}
+
+/// Venue quoting swaps off chain, e.g. an RFQ system or a limit order book.
+#[async_trait]
+pub trait IndicativeQuoteVenue: Send + Sync {
+    /// Returns the name of the venue.
+    fn name(&self) -> &str;
+
+    /// Requests a quote for swapping `amount_in` of `token_in` for `token_out`.
+    async fn quote(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+    ) -> Result<IndicativeQuote, RfqError>;
+}
+
+/// Source of the best quote of a swap.
+#[derive(Debug, Clone)]
+pub enum QuoteSource {
+    Route(Route),
+    Venue(IndicativeQuote),
+}
+
+/// Best quote of a swap across AMM routes and venues.
+#[derive(Debug, Clone)]
+pub struct BestQuote {
+    pub source: QuoteSource,
+    pub amount_out: U256,
+}
+
+/// Returns the best quote for swapping `amount_in` of `token_in` for `token_out`, across the local simulations of
+/// `routes` and the quotes of `venues`.
+///
+/// Venues are queried concurrently. Routes that fail to simulate or do not swap `token_in` for `token_out`, venues
+/// that fail to quote and expired quotes are skipped. Returns `None` if nothing quotes the swap.
+pub async fn best_quote(
+    routes: &[Route],
+    venues: &[Box<dyn IndicativeQuoteVenue>],
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+) -> Option<BestQuote> {
+    let route_quotes = routes
+        .iter()
+        .filter(|route| route.token_in == token_in && route.token_out().ok() == Some(token_out))
+        .filter_map(|route| {
+            let amount_out = route.simulate_swap(amount_in).ok()?;
+            Some(BestQuote {
+                source: QuoteSource::Route(route.clone()),
+                amount_out,
+            })
+        });
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let venue_quotes = future::join_all(
+        venues
+            .iter()
+            .map(|venue| venue.quote(token_in, token_out, amount_in)),
+    )
+    .await
+    .into_iter()
+    .zip(venues)
+    .filter_map(|(quote, venue)| match quote {
+        Ok(quote) if !quote.is_expired(timestamp) => Some(quote),
+        Ok(_) => None,
+        Err(error) => {
+            tracing::debug!(?error, venue = venue.name(), "Venue failed to quote");
+            None
+        }
+    })
+    .map(|quote| BestQuote {
+        amount_out: quote.amount_out,
+        source: QuoteSource::Venue(quote),
+    })
+    .collect::<Vec<BestQuote>>();
+
+    route_quotes
+        .chain(venue_quotes)
+        .max_by_key(|quote| quote.amount_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, Address, U256};
+    use async_trait::async_trait;
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, AMM},
+        errors::RfqError,
+        routing::Route,
+    };
+
+    use super::{best_quote, IndicativeQuote, IndicativeQuoteVenue, QuoteSource};
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    /// Venue quoting a fixed amount out.
+    struct FixedVenue {
+        amount_out: Option<U256>,
+        expiry: u64,
+    }
+
+    #[async_trait]
+    impl IndicativeQuoteVenue for FixedVenue {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        async fn quote(
+            &self,
+            token_in: Address,
+            token_out: Address,
+            amount_in: U256,
+        ) -> Result<IndicativeQuote, RfqError> {
+            let amount_out = self
+                .amount_out
+                .ok_or(RfqError::QuoteRejected("No liquidity".to_string()))?;
+
+            Ok(IndicativeQuote {
+                venue: self.name().to_string(),
+                token_in,
+                token_out,
+                amount_in,
+                amount_out,
+                expiry: self.expiry,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_best_quote() {
+        let route = Route::new(
+            USDC,
+            vec![AMM::UniswapV2Pool(UniswapV2Pool {
+                token_a: USDC,
+                token_b: WETH,
+                reserve_0: 47_092_140_895_915,
+                reserve_1: 28_396_598_565_590_008_529_300,
+                fee: 300,
+                ..Default::default()
+            })],
+        );
+        let amount_in = U256::from(1_000_000_000_u64);
+        let route_amount_out = route.simulate_swap(amount_in).unwrap();
+
+        let venues: Vec<Box<dyn IndicativeQuoteVenue>> = vec![
+            Box::new(FixedVenue {
+                amount_out: None,
+                expiry: u64::MAX,
+            }),
+            // Expired quotes are ignored
+            Box::new(FixedVenue {
+                amount_out: Some(route_amount_out * U256::from(2)),
+                expiry: 0,
+            }),
+            Box::new(FixedVenue {
+                amount_out: Some(route_amount_out + U256::from(1)),
+                expiry: u64::MAX,
+            }),
+        ];
+
+        let quote = best_quote(&[route.clone()], &venues, USDC, WETH, amount_in)
+            .await
+            .unwrap();
+        assert_eq!(quote.amount_out, route_amount_out + U256::from(1));
+        assert!(matches!(quote.source, QuoteSource::Venue(_)));
+
+        let quote = best_quote(&[route], &venues[..2], USDC, WETH, amount_in)
+            .await
+            .unwrap();
+        assert_eq!(quote.amount_out, route_amount_out);
+        assert!(matches!(quote.source, QuoteSource::Route(_)));
+
+        assert!(best_quote(&[], &venues[..2], USDC, WETH, amount_in)
+            .await
+            .is_none());
+    }
+}
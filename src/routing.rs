@@ -0,0 +1,393 @@
+//! Multi-hop swap routes.
+
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    errors::{ExecutionError, SwapSimulationError},
+    token::tax::TokenTaxRegistry,
+};
+
+/// Sequence of swaps through `pools`, each swapping the output of the previous one, starting with `token_in`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    pub token_in: Address,
+    pub pools: Vec<AMM>,
+}
+
+impl Route {
+    pub fn new(token_in: Address, pools: Vec<AMM>) -> Self {
+        Self { token_in, pools }
+    }
+
+    /// Returns the tokens along the route, from `token_in` to the token out of the last pool.
+    ///
+    /// Fails if a pool does not trade the output of the previous one.
+    pub fn tokens(&self) -> Result<Vec<Address>, ExecutionError> {
+        if self.pools.is_empty() {
+            return Err(ExecutionError::EmptyRoute);
+        }
+
+        let mut tokens = vec![self.token_in];
+        for pool in self.pools.iter() {
+            let token_in = tokens[tokens.len() - 1];
+            if !pool.tokens().contains(&token_in) {
+                return Err(ExecutionError::TokenNotInPool(token_in, pool.address()));
+            }
+
+            tokens.push(pool.get_token_out(token_in));
+        }
+
+        Ok(tokens)
+    }
+
+    /// Returns the token out of the last pool.
+    pub fn token_out(&self) -> Result<Address, ExecutionError> {
+        Ok(self.tokens()?[self.pools.len()])
+    }
+
+    /// Locally simulates the swaps along the route and returns the amount out of the last pool.
+    pub fn simulate_swap(&self, amount_in: U256) -> Result<U256, SwapSimulationError> {
+        let mut token_in = self.token_in;
+        let mut amount = amount_in;
+
+        for pool in self.pools.iter() {
+            amount = pool.simulate_swap(token_in, amount)?;
+            token_in = pool.get_token_out(token_in);
+        }
+
+        Ok(amount)
+    }
+
+    /// Same as [`Route::simulate_swap`], with the buy and sell taxes of the tokens along the route applied to every hop,
+    /// see [`TokenTaxRegistry::simulate_swap`].
+    pub fn simulate_swap_with_taxes(
+        &self,
+        amount_in: U256,
+        taxes: &TokenTaxRegistry,
+    ) -> Result<U256, SwapSimulationError> {
+        let mut token_in = self.token_in;
+        let mut amount = amount_in;
+
+        for pool in self.pools.iter() {
+            amount = taxes.simulate_swap(pool, token_in, amount)?;
+            token_in = pool.get_token_out(token_in);
+        }
+
+        Ok(amount)
+    }
+
+    /// Locally simulates the swaps along the route backwards and returns the amount in of the first pool required to
+    /// receive `amount_out` from the last pool.
+    pub fn simulate_swap_exact_output(
+        &self,
+        amount_out: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let mut tokens_in = Vec::with_capacity(self.pools.len());
+        let mut token_in = self.token_in;
+        for pool in self.pools.iter() {
+            tokens_in.push(token_in);
+            token_in = pool.get_token_out(token_in);
+        }
+
+        let mut amount = amount_out;
+        for (pool, token_in) in self.pools.iter().zip(tokens_in).rev() {
+            amount = pool.simulate_swap_exact_output(token_in, amount)?;
+        }
+
+        Ok(amount)
+    }
+}
+
+/// Outcome of the swaps along a route applied one after the other, see [`simulate_route_mut`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteSimulation {
+    /// Amount in of the route followed by the amount out of each hop.
+    pub amounts: Vec<U256>,
+    /// State of each distinct pool of the route after the swaps, in the order the pools are first used.
+    pub pools: Vec<AMM>,
+}
+
+impl RouteSimulation {
+    /// Returns the amount out of the last hop.
+    pub fn amount_out(&self) -> U256 {
+        self.amounts.last().copied().unwrap_or_default()
+    }
+}
+
+/// Simulates the swaps along `route` one after the other with [`AutomatedMarketMaker::simulate_swap_mut`] on clones
+/// of its pools, so that a hop through a pool already used by the route sees the state left by the previous hop.
+///
+/// The pools of the route are not mutated.
+pub fn simulate_route_mut(
+    route: &Route,
+    amount_in: U256,
+) -> Result<RouteSimulation, SwapSimulationError> {
+    let mut pools: Vec<AMM> = vec![];
+    let mut amounts = vec![amount_in];
+    let mut token_in = route.token_in;
+
+    for hop in route.pools.iter() {
+        let index = match pools
+            .iter()
+            .position(|pool| pool.address() == hop.address())
+        {
+            Some(index) => index,
+            None => {
+                pools.push(hop.clone());
+                pools.len() - 1
+            }
+        };
+
+        let amount = pools[index].simulate_swap_mut(token_in, amounts[amounts.len() - 1])?;
+        amounts.push(amount);
+        token_in = hop.get_token_out(token_in);
+    }
+
+    Ok(RouteSimulation { amounts, pools })
+}
+
+/// Quote of a route, net of the estimated gas cost in the token out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasAdjustedQuote {
+    pub amount_out: U256,
+    pub gas_estimate: u64,
+    /// Gas cost in the token out.
+    pub gas_cost: U256,
+    /// Amount out minus the gas cost, zero if the gas cost exceeds the amount out.
+    pub net_amount_out: U256,
+}
+
+impl Route {
+    /// Returns the estimated gas used by the swaps along the route, see
+    /// [`AutomatedMarketMaker::swap_gas_estimate`].
+    pub fn swap_gas_estimate(&self, amount_in: U256) -> Result<u64, SwapSimulationError> {
+        let mut token_in = self.token_in;
+        let mut amount = amount_in;
+        let mut gas_estimate = 0;
+
+        for pool in self.pools.iter() {
+            let (amount_out, hop_gas_estimate) =
+                pool.simulate_swap_with_gas_estimate(token_in, amount)?;
+            gas_estimate += hop_gas_estimate;
+            amount = amount_out;
+            token_in = pool.get_token_out(token_in);
+        }
+
+        Ok(gas_estimate)
+    }
+
+    /// Quotes the route and nets the estimated gas cost out of the amount out.
+    ///
+    /// The gas cost, `gas_price` per unit of gas in the native token, is converted to the token out by simulating
+    /// a swap in `price_source`, a pool of the native token and the token out. Without a price source the token out
+    /// must be the native token.
+    pub fn gas_adjusted_quote(
+        &self,
+        amount_in: U256,
+        gas_price: U256,
+        native_token: Address,
+        price_source: Option<&AMM>,
+    ) -> Result<GasAdjustedQuote, SwapSimulationError> {
+        let mut token_in = self.token_in;
+        let mut amount_out = amount_in;
+        let mut gas_estimate = 0;
+
+        for pool in self.pools.iter() {
+            let (hop_amount_out, hop_gas_estimate) =
+                pool.simulate_swap_with_gas_estimate(token_in, amount_out)?;
+            gas_estimate += hop_gas_estimate;
+            amount_out = hop_amount_out;
+            token_in = pool.get_token_out(token_in);
+        }
+
+        let native_gas_cost = gas_price * U256::from(gas_estimate);
+        let gas_cost = match price_source {
+            Some(pool) => {
+                if !pool.tokens().contains(&native_token)
+                    || pool.get_token_out(native_token) != token_in
+                {
+                    return Err(SwapSimulationError::InvalidPriceSource(pool.address()));
+                }
+
+                pool.simulate_swap(native_token, native_gas_cost)?
+            }
+            None if token_in == native_token => native_gas_cost,
+            None => return Err(SwapSimulationError::MissingPriceSource(token_in)),
+        };
+
+        Ok(GasAdjustedQuote {
+            amount_out,
+            gas_estimate,
+            gas_cost,
+            net_amount_out: amount_out.saturating_sub(gas_cost),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, U256};
+
+    use crate::{
+        amm::{
+            uniswap_v2::{UniswapV2Pool, SWAP_GAS_ESTIMATE},
+            AutomatedMarketMaker, AMM,
+        },
+        errors::{ExecutionError, SwapSimulationError},
+        test_utils::usdc_weth_pool,
+    };
+
+    use super::{simulate_route_mut, Route};
+
+    #[test]
+    fn test_route() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let link = address!("514910771af9ca656af840dff83e8264ecf986ca");
+
+        let usdc_weth = AMM::UniswapV2Pool(usdc_weth_pool());
+        let link_weth = AMM::UniswapV2Pool(UniswapV2Pool {
+            address: address!("a2107FA5B38d9bbd2C461D6EDf11B11A50F6b974"),
+            token_a: link,
+            token_a_decimals: 18,
+            token_b: weth,
+            token_b_decimals: 18,
+            reserve_0: 100_000_000_000_000_000_000_000,
+            reserve_1: 500_000_000_000_000_000_000,
+            fee: 300,
+            ..Default::default()
+        });
+
+        let route = Route::new(usdc, vec![usdc_weth.clone(), link_weth.clone()]);
+        assert_eq!(route.tokens().unwrap(), vec![usdc, weth, link]);
+        assert_eq!(
+            route.swap_gas_estimate(U256::from(1_000_000)).unwrap(),
+            2 * SWAP_GAS_ESTIMATE
+        );
+        assert_eq!(route.token_out().unwrap(), link);
+
+        let amount_in = U256::from(1_000_000);
+        let amount_weth = usdc_weth.simulate_swap(usdc, amount_in).unwrap();
+        assert_eq!(
+            route.simulate_swap(amount_in).unwrap(),
+            link_weth.simulate_swap(weth, amount_weth).unwrap()
+        );
+
+        let amount_out = route.simulate_swap(amount_in).unwrap();
+        let exact_amount_in = route.simulate_swap_exact_output(amount_out).unwrap();
+        assert!(exact_amount_in <= amount_in);
+        assert!(route.simulate_swap(exact_amount_in).unwrap() >= amount_out);
+
+        let route = Route::new(usdc, vec![link_weth]);
+        assert!(matches!(
+            route.tokens(),
+            Err(ExecutionError::TokenNotInPool(..))
+        ));
+        assert_eq!(
+            Route::new(usdc, vec![]).tokens(),
+            Err(ExecutionError::EmptyRoute)
+        );
+    }
+
+    #[test]
+    fn test_gas_adjusted_quote() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+        let usdc_weth = AMM::UniswapV2Pool(usdc_weth_pool());
+        let gas_price = U256::from(20_000_000_000_u64);
+        let amount_in = U256::from(1_000_000_000);
+
+        // WETH out, the gas cost is in the native token
+        let route = Route::new(usdc, vec![usdc_weth.clone()]);
+        let quote = route
+            .gas_adjusted_quote(amount_in, gas_price, weth, None)
+            .unwrap();
+        assert_eq!(quote.gas_cost, gas_price * U256::from(SWAP_GAS_ESTIMATE));
+        assert_eq!(quote.net_amount_out, quote.amount_out - quote.gas_cost);
+
+        // USDC out, the gas cost is priced through the pool
+        let route = Route::new(weth, vec![usdc_weth.clone()]);
+        let quote = route
+            .gas_adjusted_quote(
+                U256::from(10_u128.pow(18)),
+                gas_price,
+                weth,
+                Some(&usdc_weth),
+            )
+            .unwrap();
+        assert_eq!(
+            quote.gas_cost,
+            usdc_weth
+                .simulate_swap(weth, gas_price * U256::from(SWAP_GAS_ESTIMATE))
+                .unwrap()
+        );
+        assert!(quote.net_amount_out < quote.amount_out);
+
+        // USDC out without a price source
+        assert!(matches!(
+            route.gas_adjusted_quote(U256::from(10_u128.pow(18)), gas_price, weth, None),
+            Err(SwapSimulationError::MissingPriceSource(token)) if token == usdc
+        ));
+
+        // The price source does not trade the native token
+        let link_usdc = AMM::UniswapV2Pool(UniswapV2Pool {
+            address: address!("0000000000000000000000000000000000000001"),
+            token_a: address!("514910771af9ca656af840dff83e8264ecf986ca"),
+            token_b: usdc,
+            reserve_0: 1_000_000_000_000_000_000_000,
+            reserve_1: 1_000_000_000_000,
+            fee: 300,
+            ..Default::default()
+        });
+        assert!(matches!(
+            route.gas_adjusted_quote(
+                U256::from(10_u128.pow(18)),
+                gas_price,
+                weth,
+                Some(&link_usdc)
+            ),
+            Err(SwapSimulationError::InvalidPriceSource(address)) if address == link_usdc.address()
+        ));
+    }
+
+    #[test]
+    fn test_simulate_route_mut() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+        let usdc_weth = AMM::UniswapV2Pool(usdc_weth_pool());
+
+        // USDC -> WETH -> USDC through the same pool
+        let route = Route::new(usdc, vec![usdc_weth.clone(), usdc_weth.clone()]);
+        let amount_in = U256::from(1_000_000_000);
+        let simulation = simulate_route_mut(&route, amount_in).unwrap();
+
+        assert_eq!(simulation.amounts.len(), 3);
+        assert_eq!(simulation.amounts[0], amount_in);
+        assert_eq!(
+            simulation.amounts[1],
+            usdc_weth.simulate_swap(usdc, amount_in).unwrap()
+        );
+        // The second hop sees the reserves moved by the first one, unlike independent simulations
+        let mut pool = usdc_weth.clone();
+        pool.simulate_swap_mut(usdc, amount_in).unwrap();
+        assert_eq!(
+            simulation.amount_out(),
+            pool.simulate_swap(weth, simulation.amounts[1]).unwrap()
+        );
+        assert_ne!(
+            simulation.amount_out(),
+            route.simulate_swap(amount_in).unwrap()
+        );
+
+        assert_eq!(simulation.pools.len(), 1);
+        let AMM::UniswapV2Pool(pool) = &simulation.pools[0] else {
+            panic!("unexpected pool");
+        };
+        assert!(pool.reserve_0 > 1_000_000_000_000);
+        assert!(pool.reserve_0 < 1_000_000_000_000 + 1_000_000_000);
+    }
+}
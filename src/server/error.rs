@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+use crate::state_space::error::StateSpaceError;
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    WebSocketError(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    StateSpaceError(#[from] StateSpaceError),
+    #[error(transparent)]
+    JoinError(#[from] tokio::task::JoinError),
+    #[error("State space sync stopped")]
+    SyncStopped,
+}
@@ -0,0 +1,485 @@
+//! JSON-over-WebSocket quote service built on a [`StateSpaceManager`].
+//!
+//! Clients send requests as `{"id": 1, "method": "get_quote", "params": {...}}` and receive
+//! `{"id": 1, "result": ...}` or `{"id": 1, "error": "..."}`. Supported methods:
+//!
+//! - `get_quote` (`token_in`, `token_out`, `amount_in`, optional `pool`): quotes a swap in `pool`, or in the pool with
+//!   the best output for the pair.
+//! - `get_pools_for_pair` (`token_a`, `token_b`): returns the pools trading the pair along with their price.
+//! - `subscribe_price` (`token_a`, `token_b`): returns a subscription id, then pushes
+//!   `{"subscription": id, "result": {...}}` with the price of `token_a` in terms of `token_b` whenever a pool
+//!   trading the pair changes.
+
+pub mod error;
+
+use std::sync::Arc;
+
+use alloy::{
+    network::Network,
+    primitives::{Address, U256},
+    providers::Provider,
+    transports::Transport,
+};
+use futures::{future, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    state_space::StateSpaceManager,
+};
+
+use self::error::ServerError;
+
+/// Max number of price subscriptions of a connection.
+pub const MAX_SUBSCRIPTIONS: usize = 100;
+
+/// Request sent by a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub id: u64,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Response to a [`Request`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Update pushed to the client for a price subscription.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub subscription: u64,
+    pub result: PoolPrice,
+}
+
+/// Params of `get_quote`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuoteParams {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    #[serde(default)]
+    pub pool: Option<Address>,
+}
+
+/// Result of `get_quote`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quote {
+    pub pool: Address,
+    pub amount_out: U256,
+}
+
+/// Params of `get_pools_for_pair` and `subscribe_price`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PairParams {
+    pub token_a: Address,
+    pub token_b: Address,
+}
+
+/// Price of `token_a` in terms of `token_b` in a pool.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PoolPrice {
+    pub pool: Address,
+    pub token_a: Address,
+    pub token_b: Address,
+    pub price: f64,
+}
+
+/// Serves quotes from the state of a [`StateSpaceManager`], which is kept in sync while the server runs.
+#[derive(Debug)]
+pub struct QuoteServer<T, N, P> {
+    state_space_manager: Arc<StateSpaceManager<T, N, P>>,
+}
+
+impl<T, N, P> QuoteServer<T, N, P>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    pub fn new(state_space_manager: StateSpaceManager<T, N, P>) -> Self {
+        Self {
+            state_space_manager: Arc::new(state_space_manager),
+        }
+    }
+
+    /// Starts syncing the state space and accepts WebSocket connections on `addr` until an error occurs.
+    ///
+    /// Returns the error of the sync loop if it fails, so that quotes are never served from a stale state space.
+    pub async fn serve<A: ToSocketAddrs>(self, addr: A) -> Result<(), ServerError> {
+        let listener = TcpListener::bind(addr).await?;
+        tracing::info!(addr = ?listener.local_addr()?, "Serving quotes");
+
+        let handles = self.state_space_manager.watch_state_changes().await?;
+        let mut sync = future::select_all(handles);
+
+        loop {
+            tokio::select! {
+                (result, _, _) = &mut sync => {
+                    result??;
+                    return Err(ServerError::SyncStopped);
+                }
+
+                accepted = listener.accept() => {
+                    let (stream, peer) = accepted?;
+                    let state_space_manager = self.state_space_manager.clone();
+                    let state_changes = state_space_manager.state_change_receiver();
+
+                    tokio::spawn(async move {
+                        if let Err(err) =
+                            handle_connection(stream, state_space_manager, state_changes).await
+                        {
+                            tracing::debug!(?peer, ?err, "Connection closed");
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
+async fn handle_connection<T, N, P>(
+    stream: TcpStream,
+    state_space_manager: Arc<StateSpaceManager<T, N, P>>,
+    mut state_changes: broadcast::Receiver<Vec<Address>>,
+) -> Result<(), ServerError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    let mut ws = tokio_tungstenite::accept_async(stream).await?;
+    let mut subscriptions: Vec<(u64, PairParams)> = vec![];
+
+    loop {
+        tokio::select! {
+            message = ws.next() => {
+                let Some(message) = message else {
+                    return Ok(());
+                };
+
+                match message? {
+                    Message::Text(text) => {
+                        let response = match serde_json::from_str::<Request>(&text) {
+                            Ok(request) => {
+                                handle_request(request, &state_space_manager, &mut subscriptions).await
+                            }
+                            Err(err) => Response {
+                                id: 0,
+                                result: None,
+                                error: Some(err.to_string()),
+                            },
+                        };
+
+                        ws.send(Message::Text(serde_json::to_string(&response)?)).await?;
+                    }
+                    Message::Close(_) => return Ok(()),
+                    _ => {}
+                }
+            }
+
+            changed_amms = state_changes.recv() => {
+                let changed_amms = match changed_amms {
+                    Ok(changed_amms) => changed_amms,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!(skipped, "Connection lagging behind state changes");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                };
+
+                for address in changed_amms {
+                    let Some(amm) = state_space_manager.get_amm(address).await else {
+                        continue;
+                    };
+
+                    for (subscription, pair) in subscriptions.iter() {
+                        if let Some(price) = pool_price(&amm, pair) {
+                            let notification = Notification {
+                                subscription: *subscription,
+                                result: price,
+                            };
+                            ws.send(Message::Text(serde_json::to_string(&notification)?)).await?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Handles a request, adding price subscriptions to `subscriptions`, up to [`MAX_SUBSCRIPTIONS`].
+pub async fn handle_request<T, N, P>(
+    request: Request,
+    state_space_manager: &StateSpaceManager<T, N, P>,
+    subscriptions: &mut Vec<(u64, PairParams)>,
+) -> Response
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    let result = match request.method.as_str() {
+        "get_quote" => match serde_json::from_value(request.params) {
+            Ok(params) => get_quote(params, state_space_manager).await,
+            Err(err) => Err(err.to_string()),
+        },
+
+        "get_pools_for_pair" => match serde_json::from_value::<PairParams>(request.params) {
+            Ok(pair) => {
+                let prices = state_space_manager
+                    .get_amms_for_pair(pair.token_a, pair.token_b)
+                    .await
+                    .iter()
+                    .filter_map(|amm| pool_price(amm, &pair))
+                    .collect::<Vec<_>>();
+
+                serde_json::to_value(prices).map_err(|err| err.to_string())
+            }
+            Err(err) => Err(err.to_string()),
+        },
+
+        "subscribe_price" => match serde_json::from_value::<PairParams>(request.params) {
+            Ok(_) if subscriptions.len() >= MAX_SUBSCRIPTIONS => Err(format!(
+                "Too many subscriptions, at most {MAX_SUBSCRIPTIONS} per connection"
+            )),
+            Ok(pair) => {
+                subscriptions.push((request.id, pair));
+                Ok(Value::from(request.id))
+            }
+            Err(err) => Err(err.to_string()),
+        },
+
+        method => Err(format!("Unknown method {method}")),
+    };
+
+    match result {
+        Ok(result) => Response {
+            id: request.id,
+            result: Some(result),
+            error: None,
+        },
+        Err(err) => Response {
+            id: request.id,
+            result: None,
+            error: Some(err),
+        },
+    }
+}
+
+async fn get_quote<T, N, P>(
+    params: QuoteParams,
+    state_space_manager: &StateSpaceManager<T, N, P>,
+) -> Result<Value, String>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    let pools = match params.pool {
+        Some(pool) => {
+            // An explicit pool must trade the requested pair
+            let amm = state_space_manager
+                .get_amm(pool)
+                .await
+                .ok_or_else(|| format!("Unknown pool {pool:?}"))?;
+            if !amm.tokens().contains(&params.token_in)
+                || amm.get_token_out(params.token_in) != params.token_out
+            {
+                return Err(format!(
+                    "Pool {pool:?} does not trade {:?} for {:?}",
+                    params.token_in, params.token_out
+                ));
+            }
+
+            vec![pool]
+        }
+        None => state_space_manager
+            .get_amms_for_pair(params.token_in, params.token_out)
+            .await
+            .iter()
+            .map(|amm| amm.address())
+            .collect(),
+    };
+
+    let mut best_quote: Option<Quote> = None;
+    for pool in pools {
+        let amount_out = match state_space_manager
+            .simulate_swap(pool, params.token_in, params.amount_in)
+            .await
+        {
+            Ok(amount_out) => amount_out,
+            // Only fail if the pool was requested explicitly
+            Err(err) if params.pool.is_some() => return Err(err.to_string()),
+            Err(_) => continue,
+        };
+
+        if best_quote.map_or(true, |quote| amount_out > quote.amount_out) {
+            best_quote = Some(Quote { pool, amount_out });
+        }
+    }
+
+    let quote = best_quote
+        .ok_or_else(|| format!("No pool for {:?}/{:?}", params.token_in, params.token_out))?;
+
+    serde_json::to_value(quote).map_err(|err| err.to_string())
+}
+
+/// Returns the price of `pair.token_a` in `amm`, if it trades the pair.
+fn pool_price(amm: &AMM, pair: &PairParams) -> Option<PoolPrice> {
+    let tokens = amm.tokens();
+    if !tokens.contains(&pair.token_a) || !tokens.contains(&pair.token_b) {
+        return None;
+    }
+
+    Some(PoolPrice {
+        pool: amm.address(),
+        token_a: pair.token_a,
+        token_b: pair.token_b,
+        price: amm.calculate_price(pair.token_a).ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{
+        primitives::{address, U256},
+        providers::ProviderBuilder,
+    };
+    use serde_json::json;
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, AMM},
+        state_space::StateSpaceManager,
+        test_utils::usdc_weth_pool,
+    };
+
+    use super::{handle_request, Quote, Request, MAX_SUBSCRIPTIONS};
+
+    #[tokio::test]
+    async fn test_handle_request() {
+        let token_a = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let token_b = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let pool = |address, reserve_1| {
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address,
+                reserve_1,
+                ..usdc_weth_pool()
+            })
+        };
+        let best_pool = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+
+        // The provider is not used for Uniswap V2 simulations
+        let provider =
+            Arc::new(ProviderBuilder::new().on_http("http://localhost:8545".parse().unwrap()));
+        let state_space_manager = StateSpaceManager::new(
+            vec![
+                pool(best_pool, 500_000_000_000_000_000_000),
+                pool(
+                    address!("397FF1542f962076d0BFE58eA045FfA2d347ACa0"),
+                    400_000_000_000_000_000_000,
+                ),
+            ],
+            0,
+            100,
+            100,
+            provider,
+        );
+
+        let mut subscriptions = vec![];
+        let request = |id, method: &str, params| Request {
+            id,
+            method: method.to_string(),
+            params,
+        };
+
+        let response = handle_request(
+            request(
+                1,
+                "get_quote",
+                json!({ "token_in": token_a, "token_out": token_b, "amount_in": U256::from(1_000_000) }),
+            ),
+            &state_space_manager,
+            &mut subscriptions,
+        )
+        .await;
+        let quote: Quote = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(quote.pool, best_pool);
+
+        // An explicit pool is rejected if it does not trade the requested pair
+        let response = handle_request(
+            request(
+                1,
+                "get_quote",
+                json!({ "token_in": token_a, "token_out": token_a, "amount_in": U256::from(1_000_000), "pool": best_pool }),
+            ),
+            &state_space_manager,
+            &mut subscriptions,
+        )
+        .await;
+        assert!(response.error.is_some());
+
+        let response = handle_request(
+            request(
+                2,
+                "get_pools_for_pair",
+                json!({ "token_a": token_a, "token_b": token_b }),
+            ),
+            &state_space_manager,
+            &mut subscriptions,
+        )
+        .await;
+        assert_eq!(response.result.unwrap().as_array().unwrap().len(), 2);
+
+        let response = handle_request(
+            request(
+                3,
+                "subscribe_price",
+                json!({ "token_a": token_a, "token_b": token_b }),
+            ),
+            &state_space_manager,
+            &mut subscriptions,
+        )
+        .await;
+        assert_eq!(response.result, Some(json!(3)));
+        assert_eq!(subscriptions.len(), 1);
+
+        // Subscriptions are capped per connection
+        subscriptions.resize(MAX_SUBSCRIPTIONS, subscriptions[0]);
+        let response = handle_request(
+            request(
+                5,
+                "subscribe_price",
+                json!({ "token_a": token_a, "token_b": token_b }),
+            ),
+            &state_space_manager,
+            &mut subscriptions,
+        )
+        .await;
+        assert!(response.error.is_some());
+        assert_eq!(subscriptions.len(), MAX_SUBSCRIPTIONS);
+
+        let response = handle_request(
+            request(4, "unknown", json!(null)),
+            &state_space_manager,
+            &mut subscriptions,
+        )
+        .await;
+        assert!(response.error.is_some());
+    }
+}
@@ -0,0 +1,215 @@
+//! Record of the logs applied to each AMM of a state space, to reconstruct how an AMM got into its state.
+
+use std::collections::{HashMap, VecDeque};
+
+use alloy::{
+    primitives::{Address, B256},
+    rpc::types::eth::Log,
+};
+
+use crate::amm::{AutomatedMarketMaker, AMM};
+
+use super::{error::AuditLogError, get_block_number_from_log, StateSpace};
+
+/// A log applied to an AMM of the state space.
+#[derive(Debug, Clone)]
+pub struct AppliedLog {
+    pub block_number: u64,
+    pub transaction_index: Option<u64>,
+    pub log_index: Option<u64>,
+    /// Signature of the event, i.e. the first topic of the log.
+    pub event_signature: B256,
+    pub log: Log,
+}
+
+/// Ring buffers of the latest logs applied to each AMM, see [`super::StateSpaceManager::with_audit_log`].
+#[derive(Debug, Clone, Default)]
+pub struct AuditLog {
+    /// Number of logs kept per AMM.
+    capacity: usize,
+    logs: HashMap<Address, VecDeque<AppliedLog>>,
+    /// Block of the latest log evicted from the buffer of each AMM.
+    evicted: HashMap<Address, u64>,
+}
+
+impl AuditLog {
+    /// Returns an audit log keeping the latest `capacity` logs of each AMM.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    /// Records `log` as applied to the AMM at `amm_address`, evicting the oldest log of the AMM if its buffer is full.
+    ///
+    /// The AMM address differs from the address of the log for the pools of singleton contracts.
+    pub fn record(&mut self, amm_address: Address, log: &Log) -> Result<(), AuditLogError> {
+        let block_number = get_block_number_from_log(log)?;
+        let logs = self.logs.entry(amm_address).or_default();
+
+        if logs.len() >= self.capacity {
+            match logs.pop_front() {
+                Some(evicted_log) => {
+                    self.evicted.insert(amm_address, evicted_log.block_number);
+                }
+                // Nothing is kept with a capacity of zero
+                None => {
+                    self.evicted.insert(amm_address, block_number);
+                    return Ok(());
+                }
+            }
+        }
+
+        logs.push_back(AppliedLog {
+            block_number,
+            transaction_index: log.transaction_index,
+            log_index: log.log_index,
+            event_signature: log.topics().first().copied().unwrap_or_default(),
+            log: log.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Returns the recorded logs of the AMM at `amm_address`, oldest first.
+    pub fn logs(&self, amm_address: Address) -> Vec<AppliedLog> {
+        self.logs
+            .get(&amm_address)
+            .map(|logs| logs.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drops the logs from `block_number` onwards, after they were unwound by a reorg.
+    pub fn unwind(&mut self, block_number: u64) {
+        for logs in self.logs.values_mut() {
+            while logs
+                .back()
+                .is_some_and(|log| log.block_number >= block_number)
+            {
+                logs.pop_back();
+            }
+        }
+
+        self.logs.retain(|_, logs| !logs.is_empty());
+    }
+
+    /// Re-derives the state of `amm` by applying its recorded logs after `block_number`, the block `amm` is synced to,
+    /// e.g. the block of a checkpoint.
+    ///
+    /// Returns an error if logs of the AMM after `block_number` were evicted from the audit log.
+    pub fn replay(&self, mut amm: AMM, block_number: u64) -> Result<AMM, AuditLogError> {
+        let amm_address = amm.address();
+        if let Some(&evicted_block_number) = self.evicted.get(&amm_address) {
+            if evicted_block_number > block_number {
+                return Err(AuditLogError::LogsEvicted(amm_address, block_number));
+            }
+        }
+
+        if let Some(logs) = self.logs.get(&amm_address) {
+            for applied_log in logs.iter().filter(|log| log.block_number > block_number) {
+                amm.sync_from_log(applied_log.log.clone())?;
+            }
+        }
+
+        Ok(amm)
+    }
+
+    /// Re-derives a state space from the AMMs of a checkpoint at `block_number` and the recorded logs, see
+    /// [`AuditLog::replay`].
+    pub fn replay_state_space(
+        &self,
+        amms: Vec<AMM>,
+        block_number: u64,
+    ) -> Result<StateSpace, AuditLogError> {
+        amms.into_iter()
+            .map(|amm| {
+                let amm = self.replay(amm, block_number)?;
+                Ok((amm.address(), amm))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Log as PrimitiveLog},
+        rpc::types::eth::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::{
+        amm::{
+            uniswap_v2::{IUniswapV2Pair, UniswapV2Pool},
+            AMM,
+        },
+        state_space::error::AuditLogError,
+    };
+
+    use super::AuditLog;
+
+    #[test]
+    fn test_audit_log_replay() {
+        let pool = UniswapV2Pool {
+            address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            reserve_0: 100,
+            reserve_1: 100,
+            ..Default::default()
+        };
+
+        let sync_log = |block_number: u64, reserve: u128| Log {
+            inner: PrimitiveLog {
+                address: pool.address,
+                data: IUniswapV2Pair::Sync {
+                    reserve0: reserve,
+                    reserve1: reserve,
+                }
+                .encode_log_data(),
+            },
+            block_number: Some(block_number),
+            transaction_index: Some(0),
+            log_index: Some(0),
+            ..Default::default()
+        };
+
+        let mut audit_log = AuditLog::new(2);
+        for (block_number, reserve) in [(10, 200), (11, 300), (12, 400)] {
+            audit_log
+                .record(pool.address, &sync_log(block_number, reserve))
+                .unwrap();
+        }
+
+        let logs = audit_log.logs(pool.address);
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].block_number, 11);
+        assert_eq!(
+            logs[0].event_signature,
+            IUniswapV2Pair::Sync::SIGNATURE_HASH
+        );
+
+        let amm = audit_log
+            .replay(AMM::UniswapV2Pool(pool.clone()), 10)
+            .unwrap();
+        let AMM::UniswapV2Pool(replayed_pool) = amm else {
+            unreachable!()
+        };
+        assert_eq!(replayed_pool.reserve_0, 400);
+
+        // The log of block 10 was evicted
+        assert!(matches!(
+            audit_log.replay(AMM::UniswapV2Pool(pool.clone()), 9),
+            Err(AuditLogError::LogsEvicted(_, 9))
+        ));
+
+        audit_log.unwind(12);
+        let state_space = audit_log
+            .replay_state_space(vec![AMM::UniswapV2Pool(pool.clone())], 10)
+            .unwrap();
+        let Some(AMM::UniswapV2Pool(replayed_pool)) = state_space.get(&pool.address) else {
+            unreachable!()
+        };
+        assert_eq!(replayed_pool.reserve_0, 300);
+        assert_eq!(replayed_pool.reserve_1, 300);
+    }
+}
@@ -1,12 +1,6 @@
-// TODO: re-integrate Artemis once its migrated to Alloy
-compile_error!("Artemis does not yet support Alloy");
-
+use alloy::{network::Network, primitives::Address, providers::Provider, transports::Transport};
 use artemis_core::types::{Collector, CollectorStream};
 use async_trait::async_trait;
-use ethers::{
-    providers::{Middleware, PubsubClient},
-    types::H160,
-};
 use futures::StreamExt;
 use tokio_stream::wrappers::ReceiverStream;
 
@@ -23,47 +17,45 @@ use super::StateSpaceManager;
 ///     sync,
 /// };
 ///
+/// use alloy::{
+///     primitives::{address, Address},
+///     providers::{ProviderBuilder, WsConnect},
+///     rpc::types::eth::Transaction,
+/// };
 /// use artemis_core::{engine, types};
 /// use async_trait::async_trait;
-/// use ethers::{
-///     providers::{Http, Provider, Ws},
-///     types::{Transaction, H160},
-/// };
-/// use std::{collections::HashMap, ops::Deref, str::FromStr, sync::Arc};
+/// use std::{collections::HashMap, ops::Deref, sync::Arc};
 /// use tokio::sync::RwLock;
 ///
 /// #[tokio::main]
 /// async fn main() -> eyre::Result<()> {
 ///     tracing_subscriber::fmt::init();
-///     let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT")?;
 ///     let ws_endpoint = std::env::var("ETHEREUM_WS_ENDPOINT")?;
-///     let middleware = Arc::new(Provider::<Http>::try_from(rpc_endpoint)?);
-///     let stream_middleware: Arc<Provider<Ws>> =
-///         Arc::new(Provider::<Ws>::connect(ws_endpoint).await?);
+///     let provider = Arc::new(ProviderBuilder::new().on_ws(WsConnect::new(ws_endpoint)).await?);
 ///
 ///     let factories = vec![
 ///         //Add UniswapV2
 ///         Factory::UniswapV2Factory(UniswapV2Factory::new(
-///             H160::from_str("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f")?,
+///             address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
 ///             2638438,
 ///             300,
 ///         )),
 ///         //Add Sushiswap
 ///         Factory::UniswapV2Factory(UniswapV2Factory::new(
-///             H160::from_str("0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac")?,
+///             address!("C0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac"),
 ///             10794229,
 ///             300,
 ///         )),
 ///         //Add UniswapV3
 ///         Factory::UniswapV3Factory(UniswapV3Factory::new(
-///             H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984")?,
+///             address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
 ///             185,
 ///         )),
 ///     ];
 ///
 ///     //Sync amms
 ///     let (amms, last_synced_block) =
-///         sync::sync_amms(factories, middleware.clone(), None, 10000).await?;
+///         sync::sync_amms(factories, provider.clone(), None, 10000).await?;
 ///
 ///     //Initialize state space manager
 ///     let state_space_manager = StateSpaceManager::new(
@@ -71,8 +63,7 @@ use super::StateSpaceManager;
 ///         last_synced_block,
 ///         100,
 ///         100,
-///         middleware.clone(),
-///         stream_middleware,
+///         provider,
 ///     );
 ///
 ///     // Group amm addresses by token pairs
@@ -83,7 +74,7 @@ use super::StateSpaceManager;
 ///         pairs,
 ///     };
 ///
-///     let mut engine: engine::Engine<Vec<H160>, Transaction> = engine::Engine::new();
+///     let mut engine: engine::Engine<Vec<Address>, Transaction> = engine::Engine::new();
 ///     engine.add_collector(Box::new(state_space_manager));
 ///     engine.add_strategy(Box::new(simple_arbitrage_strategy));
 ///
@@ -96,8 +87,8 @@ use super::StateSpaceManager;
 ///     Ok(())
 /// }
 ///
-/// pub fn aggregate_pairs(state_space: &StateSpace) -> HashMap<(H160, H160), Vec<H160>> {
-///     let mut pairs: HashMap<(H160, H160), Vec<H160>> = HashMap::new();
+/// pub fn aggregate_pairs(state_space: &StateSpace) -> HashMap<(Address, Address), Vec<Address>> {
+///     let mut pairs: HashMap<(Address, Address), Vec<Address>> = HashMap::new();
 ///
 ///     for (amm_address, amm) in state_space {
 ///         let tokens = amm.tokens();
@@ -123,16 +114,16 @@ use super::StateSpaceManager;
 ///
 /// struct SimpleArbitrage {
 ///     state_space: Arc<RwLock<StateSpace>>,
-///     pairs: HashMap<(H160, H160), Vec<H160>>,
+///     pairs: HashMap<(Address, Address), Vec<Address>>,
 /// }
 ///
 /// #[async_trait]
-/// impl types::Strategy<Vec<H160>, Transaction> for SimpleArbitrage {
+/// impl types::Strategy<Vec<Address>, Transaction> for SimpleArbitrage {
 ///     async fn sync_state(&mut self) -> anyhow::Result<()> {
 ///         Ok(())
 ///     }
 ///
-///     async fn process_event(&mut self, event: Vec<H160>) -> Vec<Transaction> {
+///     async fn process_event(&mut self, event: Vec<Address>) -> Vec<Transaction> {
 ///         for addr in event {
 ///             let state_space = self.state_space.read().await;
 ///
@@ -183,17 +174,16 @@ use super::StateSpaceManager;
 /// }
 
 #[async_trait]
-impl<M, P> Collector<Vec<H160>> for StateSpaceManager<M, P>
+impl<T, N, P> Collector<Vec<Address>> for StateSpaceManager<T, N, P>
 where
-    M: Middleware,
-    M::Error: 'static,
-    P: Middleware + 'static,
-    P::Provider: PubsubClient,
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
 {
     /// Artemis collector implementation for state space manager.
     ///
-    /// Returns a `CollectorStream` of `Vec<H160>` representing the AMM addresses that incurred a state change in the block.
-    async fn get_event_stream(&self) -> anyhow::Result<CollectorStream<'_, Vec<H160>>> {
+    /// Returns a `CollectorStream` of `Vec<Address>` representing the AMM addresses that incurred a state change in the block.
+    async fn get_event_stream(&self) -> anyhow::Result<CollectorStream<'_, Vec<Address>>> {
         let (state_change_rx, mut join_handles) = self.subscribe_state_changes().await?;
 
         let stream_handle = join_handles.swap_remove(0);
@@ -216,6 +206,6 @@ where
 
         let stream = ReceiverStream::new(state_change_rx).take_until(early_handle_exit);
 
-        Ok(Box::pin(stream) as CollectorStream<'_, Vec<H160>>)
+        Ok(Box::pin(stream) as CollectorStream<'_, Vec<Address>>)
     }
 }
@@ -0,0 +1,238 @@
+//! Per-pool changes between two snapshots of a state space, e.g. two checkpoints at different block heights.
+
+use std::collections::BTreeSet;
+
+use alloy::primitives::{Address, I256, U256};
+
+use crate::amm::{uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, AMM};
+
+use super::StateSpace;
+
+/// Diffing of two state spaces, implemented for [`StateSpace`] so that it reads `StateSpace::diff(&a, &b)`.
+pub trait StateSpaceDiff {
+    /// Returns the changes from `snapshot_a` to `snapshot_b`.
+    fn diff(snapshot_a: &Self, snapshot_b: &Self) -> Diff;
+}
+
+/// Changes between two state spaces.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff {
+    /// AMMs only in the second snapshot, sorted by address.
+    pub added: Vec<Address>,
+    /// AMMs only in the first snapshot, sorted by address.
+    pub removed: Vec<Address>,
+    /// AMMs in both snapshots whose price, liquidity or initialized ticks changed, sorted by address.
+    pub changed: Vec<PoolDiff>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Changes of a single AMM between two state spaces.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PoolDiff {
+    pub address: Address,
+    /// Change of the price of the first token of the AMM, in the second token. A price that cannot be calculated
+    /// counts as zero.
+    pub price_delta: f64,
+    /// Change of the liquidity of the AMM, see [`liquidity`].
+    pub liquidity_delta: I256,
+    /// Ticks initialized in the second snapshot only, for Uniswap V3 pools.
+    pub ticks_added: Vec<i32>,
+    /// Ticks initialized in the first snapshot only, for Uniswap V3 pools.
+    pub ticks_removed: Vec<i32>,
+}
+
+impl PoolDiff {
+    /// Returns the changes of `amm_a` to `amm_b`, the same AMM in two snapshots.
+    pub fn new(amm_a: &AMM, amm_b: &AMM) -> Self {
+        let (ticks_added, ticks_removed) = match (amm_a, amm_b) {
+            (AMM::UniswapV3Pool(pool_a), AMM::UniswapV3Pool(pool_b)) => {
+                let ticks_a = initialized_ticks(pool_a);
+                let ticks_b = initialized_ticks(pool_b);
+
+                (
+                    ticks_b.difference(&ticks_a).copied().collect(),
+                    ticks_a.difference(&ticks_b).copied().collect(),
+                )
+            }
+            _ => (vec![], vec![]),
+        };
+
+        Self {
+            address: amm_b.address(),
+            price_delta: price(amm_b) - price(amm_a),
+            liquidity_delta: I256::from_raw(liquidity(amm_b)) - I256::from_raw(liquidity(amm_a)),
+            ticks_added,
+            ticks_removed,
+        }
+    }
+
+    /// Returns whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.price_delta == 0.0
+            && self.liquidity_delta.is_zero()
+            && self.ticks_added.is_empty()
+            && self.ticks_removed.is_empty()
+    }
+}
+
+impl StateSpaceDiff for StateSpace {
+    fn diff(snapshot_a: &Self, snapshot_b: &Self) -> Diff {
+        let mut diff = Diff::default();
+
+        for (address, amm_a) in snapshot_a.iter() {
+            match snapshot_b.get(address) {
+                Some(amm_b) => {
+                    let pool_diff = PoolDiff::new(amm_a, amm_b);
+                    if !pool_diff.is_empty() {
+                        diff.changed.push(pool_diff);
+                    }
+                }
+                None => diff.removed.push(*address),
+            }
+        }
+
+        diff.added = snapshot_b
+            .keys()
+            .filter(|address| !snapshot_a.contains_key(address))
+            .copied()
+            .collect();
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.changed.sort_by_key(|pool_diff| pool_diff.address);
+
+        diff
+    }
+}
+
+/// Returns the liquidity of `amm`: the virtual liquidity `sqrt(reserve_0 * reserve_1)` of Uniswap V2 and constant
+/// product pools, the
+/// in range liquidity of Uniswap V3 pools, the asset reserve of ERC4626 vaults and the invariant of Curve V2 pools.
+/// Rate adapters have no reserves and count as zero, Bancor V3 pools count their BNT trading liquidity, GMX markets
+/// their long token pool amount and Ambient pools their ambient and in range concentrated liquidity.
+pub fn liquidity(amm: &AMM) -> U256 {
+    match amm {
+        AMM::UniswapV2Pool(pool) => {
+            (U256::from(pool.reserve_0) * U256::from(pool.reserve_1)).root(2)
+        }
+        AMM::UniswapV3Pool(pool) => U256::from(pool.liquidity),
+        AMM::ERC4626Vault(vault) => vault.asset_reserve,
+        AMM::CurveV2Pool(pool) => pool.d,
+        AMM::RateAdapter(_) => U256::ZERO,
+        AMM::BancorV3Pool(pool) => U256::from(pool.bnt_trading_liquidity),
+        AMM::GmxMarket(market) => market.long_pool_amount,
+        AMM::AmbientPool(pool) => U256::from(pool.liquidity()),
+        AMM::ConstantProductPool(pool) => {
+            (U256::from(pool.reserve_0) * U256::from(pool.reserve_1)).root(2)
+        }
+    }
+}
+
+fn price(amm: &AMM) -> f64 {
+    amm.tokens()
+        .first()
+        .and_then(|base_token| amm.calculate_price(*base_token).ok())
+        .unwrap_or(0.0)
+}
+
+fn initialized_ticks(pool: &UniswapV3Pool) -> BTreeSet<i32> {
+    pool.ticks
+        .iter()
+        .filter(|(_, info)| info.initialized)
+        .map(|(tick, _)| *tick)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, I256};
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AMM},
+        state_space::initialize_state_space,
+    };
+
+    use super::{StateSpace, StateSpaceDiff};
+
+    #[test]
+    fn test_state_space_diff() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+        let v2_pool = UniswapV2Pool {
+            address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            token_a: usdc,
+            token_a_decimals: 6,
+            token_b: weth,
+            token_b_decimals: 18,
+            reserve_0: 4_000_000_000,
+            reserve_1: 10_000_000_000_000_000_000,
+            fee: 300,
+            ..Default::default()
+        };
+        let mut v3_pool = UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            token_a: usdc,
+            token_a_decimals: 6,
+            token_b: weth,
+            token_b_decimals: 18,
+            tick_spacing: 10,
+            ..Default::default()
+        };
+        v3_pool.modify_position(-100, 100, 1_000);
+        let removed_pool = UniswapV2Pool {
+            address: address!("397FF1542f962076d0BFE58eA045FfA2d347ACa0"),
+            ..v2_pool.clone()
+        };
+
+        let snapshot_a = initialize_state_space(vec![
+            AMM::UniswapV2Pool(v2_pool.clone()),
+            AMM::UniswapV3Pool(v3_pool.clone()),
+            AMM::UniswapV2Pool(removed_pool.clone()),
+        ]);
+        assert!(StateSpace::diff(&snapshot_a, &snapshot_a).is_empty());
+
+        let added_pool = UniswapV2Pool {
+            address: address!("a2107FA5B38d9bbd2C461D6EDf11B11A50F6b974"),
+            ..v2_pool.clone()
+        };
+        let mut v2_pool_b = v2_pool.clone();
+        v2_pool_b.reserve_0 *= 4;
+        v2_pool_b.reserve_1 *= 4;
+        let mut v3_pool_b = v3_pool.clone();
+        v3_pool_b.modify_position(-100, 100, -1_000);
+        v3_pool_b.modify_position(-200, 200, 500);
+
+        let snapshot_b = initialize_state_space(vec![
+            AMM::UniswapV2Pool(v2_pool_b),
+            AMM::UniswapV3Pool(v3_pool_b),
+            AMM::UniswapV2Pool(added_pool.clone()),
+        ]);
+
+        let diff = StateSpace::diff(&snapshot_a, &snapshot_b);
+        assert_eq!(diff.added, vec![added_pool.address]);
+        assert_eq!(diff.removed, vec![removed_pool.address]);
+        assert_eq!(diff.changed.len(), 2);
+
+        let v3_diff = &diff.changed[0];
+        assert_eq!(v3_diff.address, v3_pool.address);
+        assert_eq!(v3_diff.liquidity_delta, I256::try_from(-500).unwrap());
+        assert_eq!(v3_diff.ticks_added, vec![-200, 200]);
+        assert_eq!(v3_diff.ticks_removed, vec![-100, 100]);
+
+        // Scaling both reserves keeps the price and scales the virtual liquidity
+        let v2_diff = &diff.changed[1];
+        assert_eq!(v2_diff.address, v2_pool.address);
+        assert_eq!(v2_diff.price_delta, 0.0);
+        assert_eq!(
+            v2_diff.liquidity_delta,
+            I256::try_from(600_000_000_000_000_u128).unwrap()
+        );
+        assert!(v2_diff.ticks_added.is_empty());
+    }
+}
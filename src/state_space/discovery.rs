@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use alloy::{
+    network::Network,
+    primitives::{Address, B256, U256},
+    providers::Provider,
+    rpc::types::eth::Log,
+    transports::Transport,
+};
+
+use crate::{
+    amm::{
+        factory::{AutomatedMarketMakerFactory, Factory},
+        AMM,
+    },
+    errors::AMMError,
+    filters,
+    sync::{self, config::SyncConfig},
+};
+
+/// Discovers the AMMs created by `factories` while a state space is live, see
+/// [`super::StateSpaceManager::with_amm_discovery`].
+#[derive(Debug, Clone)]
+pub struct AmmDiscovery {
+    pub factories: Vec<Factory>,
+    /// Drops discovered AMMs holding less WETH value than the threshold.
+    pub weth_value_filter: Option<WethValueFilter>,
+    /// Config used to populate the discovered AMMs.
+    pub config: SyncConfig,
+}
+
+/// Threshold of the WETH value held by discovered AMMs, see [`filters::value::filter_amms_below_weth_threshold`].
+#[derive(Debug, Clone, Copy)]
+pub struct WethValueFilter {
+    pub weth: Address,
+    pub weth_value_in_pool_threshold: U256,
+    pub weth_value_in_token_to_weth_pool_threshold: U256,
+    /// Number of AMMs per batch request.
+    pub step: usize,
+}
+
+impl AmmDiscovery {
+    pub fn new(factories: Vec<Factory>) -> Self {
+        Self {
+            factories,
+            weth_value_filter: None,
+            config: SyncConfig::default(),
+        }
+    }
+
+    pub fn with_weth_value_filter(mut self, weth_value_filter: WethValueFilter) -> Self {
+        self.weth_value_filter = Some(weth_value_filter);
+        self
+    }
+
+    pub fn with_config(mut self, config: SyncConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the creation event signatures of the factories.
+    pub fn event_signatures(&self) -> Vec<B256> {
+        let mut event_signatures = vec![];
+        for factory in self.factories.iter() {
+            let event_signature = factory.amm_created_event_signature();
+            if !event_signatures.contains(&event_signature) {
+                event_signatures.push(event_signature);
+            }
+        }
+
+        event_signatures
+    }
+
+    /// Returns the AMMs created by the factories in `logs`, populated as of `block_number` and value filtered.
+    pub async fn amms_created_in_logs<T, N, P>(
+        &self,
+        logs: &[Log],
+        block_number: u64,
+        provider: Arc<P>,
+    ) -> Result<Vec<AMM>, AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut amms = vec![];
+        for factory in self.factories.iter() {
+            amms.extend(factory.amms_created_in_block(logs)?);
+        }
+
+        if amms.is_empty() {
+            return Ok(amms);
+        }
+
+        // Tick data of Uniswap V3 pools is rebuilt from the first log of the range, before the pools were created
+        let from_block = logs
+            .iter()
+            .filter_map(|log| log.block_number)
+            .min()
+            .unwrap_or(block_number);
+        sync::populate_data_at_block(
+            &mut amms,
+            from_block,
+            block_number,
+            &self.config,
+            provider.clone(),
+        )
+        .await?;
+
+        let amms = filters::filter_empty_amms(amms);
+
+        match self.weth_value_filter {
+            Some(weth_value_filter) if !amms.is_empty() => {
+                filters::value::filter_amms_below_weth_threshold(
+                    amms,
+                    &self.factories,
+                    weth_value_filter.weth,
+                    weth_value_filter.weth_value_in_pool_threshold,
+                    weth_value_filter.weth_value_in_token_to_weth_pool_threshold,
+                    weth_value_filter.step,
+                    provider,
+                )
+                .await
+            }
+            _ => Ok(amms),
+        }
+    }
+}
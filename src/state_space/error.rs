@@ -1,9 +1,11 @@
-use crate::errors::{AMMError, ArithmeticError, EventLogError};
+use crate::errors::{AMMError, ArithmeticError, EventLogError, SwapSimulationError};
 
 use alloy::{primitives::Address, rpc::types::eth::Block, transports::TransportError};
 
 use thiserror::Error;
 
+use super::multi_chain::PoolId;
+
 #[derive(Error, Debug)]
 pub enum StateSpaceError {
     #[error(transparent)]
@@ -26,16 +28,30 @@ pub enum StateSpaceError {
     EventLogError(#[from] EventLogError),
     #[error(transparent)]
     StateChangeError(#[from] StateChangeError),
+    #[error(transparent)]
+    AuditLogError(#[from] AuditLogError),
     #[error("Block number not found")]
     BlockNumberNotFound,
     #[error(transparent)]
     StateChangeSendError(#[from] tokio::sync::mpsc::error::SendError<Vec<Address>>),
     #[error(transparent)]
+    PoolIdSendError(#[from] tokio::sync::mpsc::error::SendError<Vec<PoolId>>),
+    #[error(transparent)]
     BlockSendError(#[from] tokio::sync::mpsc::error::SendError<Block>),
     #[error("Already listening for state changes")]
     AlreadyListeningForStateChanges,
     #[error(transparent)]
     JoinError(#[from] tokio::task::JoinError),
+    #[error(transparent)]
+    SwapSimulationError(#[from] SwapSimulationError),
+    #[error("AMM {0} not found in the state space")]
+    AMMNotFound(Address),
+    #[error("Chain {0} not found in the state space")]
+    ChainNotFound(u64),
+    #[error("Token {0} is not traded by AMM {1}")]
+    TokenNotInAMM(Address, Address),
+    #[error("Liquidity changes are not supported for AMM {0}")]
+    UnsupportedLiquidityChange(Address),
 }
 
 #[derive(Error, Debug)]
@@ -49,3 +65,11 @@ pub enum StateChangeError {
     #[error(transparent)]
     EventLogError(#[from] EventLogError),
 }
+
+#[derive(Error, Debug)]
+pub enum AuditLogError {
+    #[error("Logs of AMM {0} after block {1} were evicted from the audit log")]
+    LogsEvicted(Address, u64),
+    #[error(transparent)]
+    EventLogError(#[from] EventLogError),
+}
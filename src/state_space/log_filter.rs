@@ -0,0 +1,213 @@
+//! Log filters restricted to the addresses of tracked AMMs.
+//!
+//! Filtering logs by event signature only, as the state space does, returns the logs of every pool sharing the
+//! signatures of the tracked AMMs. [`LogFilterBuilder`] builds filters for the union of the addresses emitting the
+//! logs of the tracked AMMs and their event signatures, chunked so that each filter stays within the address limit
+//! of the provider.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use alloy::{
+    network::Network,
+    primitives::{Address, B256},
+    providers::Provider,
+    rpc::types::eth::{Filter, Log},
+    transports::Transport,
+};
+use futures::future;
+
+use crate::amm::{AutomatedMarketMaker, AMM};
+
+use super::error::StateSpaceError;
+
+/// Default max number of addresses per filter, accepted by most providers.
+pub const DEFAULT_MAX_ADDRESSES_PER_FILTER: usize = 1000;
+
+/// Builds the log filters of a set of AMMs, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct LogFilterBuilder {
+    addresses: BTreeSet<Address>,
+    event_signatures: BTreeSet<B256>,
+    max_addresses_per_filter: usize,
+}
+
+impl Default for LogFilterBuilder {
+    fn default() -> Self {
+        Self {
+            addresses: BTreeSet::new(),
+            event_signatures: BTreeSet::new(),
+            max_addresses_per_filter: DEFAULT_MAX_ADDRESSES_PER_FILTER,
+        }
+    }
+}
+
+impl LogFilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the max number of addresses per filter, at least 1.
+    pub fn max_addresses_per_filter(mut self, max_addresses_per_filter: usize) -> Self {
+        self.max_addresses_per_filter = max_addresses_per_filter.max(1);
+        self
+    }
+
+    /// Adds the address emitting the logs of `amm` and its event signatures.
+    pub fn amm(mut self, amm: &AMM) -> Self {
+        self.addresses.insert(log_address(amm));
+        self.event_signatures.extend(amm.sync_on_event_signatures());
+        self
+    }
+
+    /// Adds the addresses emitting the logs of `amms` and their event signatures.
+    pub fn amms<'a>(self, amms: impl IntoIterator<Item = &'a AMM>) -> Self {
+        amms.into_iter().fold(self, |builder, amm| builder.amm(amm))
+    }
+
+    /// Adds addresses whose logs should be fetched along with the logs of the AMMs, e.g. factories.
+    pub fn addresses(mut self, addresses: impl IntoIterator<Item = Address>) -> Self {
+        self.addresses.extend(addresses);
+        self
+    }
+
+    /// Adds event signatures to the filters.
+    pub fn event_signatures(mut self, event_signatures: impl IntoIterator<Item = B256>) -> Self {
+        self.event_signatures.extend(event_signatures);
+        self
+    }
+
+    /// Returns the filters for the logs with any of the event signatures emitted by any of the addresses, one filter
+    /// per chunk of addresses.
+    pub fn build(&self) -> Vec<Filter> {
+        let event_signatures = self.event_signatures.iter().copied().collect::<Vec<B256>>();
+
+        self.addresses
+            .iter()
+            .copied()
+            .collect::<Vec<Address>>()
+            .chunks(self.max_addresses_per_filter)
+            .map(|addresses| {
+                Filter::new()
+                    .address(addresses.to_vec())
+                    .event_signature(event_signatures.clone())
+            })
+            .collect()
+    }
+
+    /// Fetches the logs of the filters between `from_block` and `to_block` inclusive, in block order.
+    pub async fn get_logs<T, N, P>(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        provider: Arc<P>,
+    ) -> Result<Vec<Log>, StateSpaceError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let filters = self
+            .build()
+            .into_iter()
+            .map(|filter| filter.from_block(from_block).to_block(to_block))
+            .collect::<Vec<Filter>>();
+
+        let logs =
+            future::try_join_all(filters.iter().map(|filter| provider.get_logs(filter))).await?;
+
+        Ok(merge_logs(logs))
+    }
+}
+
+/// Merges the logs of several filters in block order, by block number and log index.
+pub fn merge_logs(logs: Vec<Vec<Log>>) -> Vec<Log> {
+    let mut logs = logs.into_iter().flatten().collect::<Vec<Log>>();
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+    logs
+}
+
+/// Returns the address emitting the logs of `amm`, the dex for Ambient pools and the network for Bancor V3 pools.
+pub fn log_address(amm: &AMM) -> Address {
+    match amm {
+        AMM::AmbientPool(pool) => pool.dex,
+        AMM::BancorV3Pool(pool) => pool.network,
+        _ => amm.address(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, Address, Log as PrimitiveLog},
+        rpc::types::eth::Log,
+    };
+
+    use crate::amm::{
+        uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, AMM,
+    };
+
+    use super::{merge_logs, LogFilterBuilder};
+
+    fn uniswap_v2_pool(address: Address) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_build() {
+        let amms = (1..=5_u8)
+            .map(|i| uniswap_v2_pool(Address::with_last_byte(i)))
+            .chain([AMM::UniswapV3Pool(UniswapV3Pool {
+                address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+                ..Default::default()
+            })])
+            .collect::<Vec<AMM>>();
+
+        let filters = LogFilterBuilder::new()
+            .max_addresses_per_filter(2)
+            .amms(&amms)
+            .build();
+        assert_eq!(filters.len(), 3);
+
+        let addresses = filters
+            .iter()
+            .flat_map(|filter| filter.address.iter().copied())
+            .collect::<Vec<Address>>();
+        assert_eq!(addresses.len(), 6);
+        assert!(amms.iter().all(|amm| addresses.contains(&amm.address())));
+
+        for amm in amms.iter() {
+            for event_signature in amm.sync_on_event_signatures() {
+                assert!(filters
+                    .iter()
+                    .all(|filter| filter.topics[0].matches(&event_signature)));
+            }
+        }
+
+        assert!(LogFilterBuilder::new().build().is_empty());
+    }
+
+    #[test]
+    fn test_merge_logs() {
+        let log = |block_number, log_index| Log {
+            inner: PrimitiveLog::default(),
+            block_number: Some(block_number),
+            log_index: Some(log_index),
+            ..Default::default()
+        };
+
+        let logs = merge_logs(vec![
+            vec![log(1, 0), log(2, 5)],
+            vec![log(1, 3), log(2, 1)],
+            vec![],
+        ]);
+        assert_eq!(
+            logs.iter()
+                .map(|log| (log.block_number.unwrap(), log.log_index.unwrap()))
+                .collect::<Vec<(u64, u64)>>(),
+            vec![(1, 0), (1, 3), (2, 1), (2, 5)]
+        );
+    }
+}
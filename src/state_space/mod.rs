@@ -1,21 +1,48 @@
+pub mod audit;
 #[cfg(feature = "artemis")]
 pub mod collector;
+pub mod diff;
+pub mod discovery;
 pub mod error;
-
+pub mod log_filter;
+pub mod multi_chain;
+pub mod price_stream;
+pub mod quote_cache;
+pub mod sandbox;
+#[cfg(feature = "arc-swap")]
+pub mod snapshot;
+pub mod versions;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
 use crate::{
-    amm::{AutomatedMarketMaker, AMM},
-    errors::EventLogError,
+    amm::{
+        ambient,
+        bancor_v3::{IBancorNetwork, BNT},
+        metadata::{AmmMetadata, AmmMetadataMap},
+        AutomatedMarketMaker, AMM,
+    },
+    errors::{EventLogError, SwapSimulationError},
+    index::PoolIndex,
+    tvl::{PriceOracle, TvlReport},
 };
 use alloy::{
     network::Network,
-    primitives::{Address, B256},
+    primitives::{Address, B256, U256},
     providers::Provider,
     rpc::types::eth::{Block, Filter, Log},
+    sol_types::SolEvent,
     transports::Transport,
 };
 use arraydeque::ArrayDeque;
-use error::{StateChangeError, StateSpaceError};
+use audit::AuditLog;
+use discovery::AmmDiscovery;
+use error::{AuditLogError, StateChangeError, StateSpaceError};
 use futures::StreamExt;
+use price_stream::{best_pair_price, PairPrice};
+use quote_cache::QuoteCache;
+#[cfg(feature = "arc-swap")]
+use snapshot::SnapshotStateSpace;
 use std::{
     collections::{HashMap, HashSet},
     marker::PhantomData,
@@ -23,11 +50,13 @@ use std::{
 };
 use tokio::{
     sync::{
+        broadcast,
         mpsc::{Receiver, Sender},
         RwLock,
     },
     task::JoinHandle,
 };
+use versions::AmmVersions;
 
 // TODO: bench this with a dashmap
 pub type StateSpace = HashMap<Address, AMM>;
@@ -40,6 +69,27 @@ pub struct StateSpaceManager<T, N, P> {
     stream_buffer: usize,
     state_change_buffer: usize,
     state_change_cache: Arc<RwLock<StateChangeCache>>,
+    tick_prune_radius: Option<i16>,
+    /// Number of blocks behind the chain head that logs are applied to the state space.
+    finality_depth: u64,
+    /// AMMs changed by the logs of the blocks within `finality_depth` of the chain head, applied on top of the state
+    /// space.
+    head_overlay: Option<Arc<RwLock<StateSpace>>>,
+    /// Log filter of the AMMs in the state space, rebuilt as AMMs are added and removed.
+    filter: Arc<RwLock<Filter>>,
+    index: Arc<RwLock<PoolIndex>>,
+    /// AMMs removed from the state space until they are resumed.
+    paused: Arc<RwLock<StateSpace>>,
+    /// User metadata of the AMMs, kept until the AMMs are removed.
+    metadata: Arc<RwLock<AmmMetadataMap>>,
+    discovery: Option<Arc<AmmDiscovery>>,
+    audit_log: Option<Arc<RwLock<AuditLog>>>,
+    quote_cache: Option<Arc<RwLock<QuoteCache>>>,
+    versions: Arc<RwLock<AmmVersions>>,
+    /// Addresses of the AMMs updated by the running sync loop, published for any number of subscribers.
+    state_change_tx: broadcast::Sender<Vec<Address>>,
+    #[cfg(feature = "arc-swap")]
+    snapshots: Option<SnapshotStateSpace>,
     provider: Arc<P>,
     transport: PhantomData<T>,
     network: PhantomData<N>,
@@ -62,6 +112,8 @@ where
             .into_iter()
             .map(|amm| (amm.address(), amm))
             .collect::<HashMap<Address, AMM>>();
+        let filter = event_filter(state.values(), None);
+        let index = state.values().collect::<PoolIndex>();
 
         Self {
             state: Arc::new(RwLock::new(state)),
@@ -69,31 +121,381 @@ where
             stream_buffer,
             state_change_buffer,
             state_change_cache: Arc::new(RwLock::new(ArrayDeque::new())),
+            tick_prune_radius: None,
+            finality_depth: 0,
+            head_overlay: None,
+            filter: Arc::new(RwLock::new(filter)),
+            index: Arc::new(RwLock::new(index)),
+            paused: Arc::new(RwLock::new(HashMap::new())),
+            metadata: Arc::new(RwLock::new(AmmMetadataMap::new())),
+            discovery: None,
+            audit_log: None,
+            quote_cache: None,
+            versions: Arc::new(RwLock::new(AmmVersions::new())),
+            state_change_tx: broadcast::channel(state_change_buffer.max(1)).0,
+            #[cfg(feature = "arc-swap")]
+            snapshots: None,
             provider,
             transport: PhantomData,
             network: PhantomData,
         }
     }
 
-    pub async fn filter(&self) -> Filter {
-        let mut event_signatures: Vec<B256> = vec![];
-        let mut amm_variants = HashSet::new();
-
-        for amm in self.state.read().await.values() {
-            let variant = match amm {
-                AMM::UniswapV2Pool(_) => 0,
-                AMM::UniswapV3Pool(_) => 1,
-                AMM::ERC4626Vault(_) => 2,
-            };
+    /// Keeps tick data for Uniswap V3 pools within `radius_words` words of the `tick_bitmap` around the current tick.
+    ///
+    /// Pools are pruned as their state changes, and tick data is reloaded when a simulation through
+    /// [`StateSpaceManager::simulate_swap`] reaches the pruned ticks.
+    pub async fn with_tick_pruning(mut self, radius_words: i16) -> Self {
+        self.tick_prune_radius = Some(radius_words);
+
+        for amm in self.state.write().await.values_mut() {
+            if let AMM::UniswapV3Pool(pool) = amm {
+                pool.prune_ticks(radius_words);
+            }
+        }
+
+        self
+    }
+
+    /// Only applies logs to the state space once they are `finality_depth` blocks deep, so that reorgs shallower than
+    /// `finality_depth` do not need to be unwound.
+    ///
+    /// The AMMs should be synced to `finality_depth` blocks behind the chain head, see [`SyncConfig::finality_depth`].
+    /// Reorgs deeper than `finality_depth` are still unwound from the state change cache.
+    ///
+    /// [`SyncConfig::finality_depth`]: crate::sync::config::SyncConfig::finality_depth
+    pub fn with_finality_depth(mut self, finality_depth: u64) -> Self {
+        self.finality_depth = finality_depth;
+        self
+    }
+
+    /// Keeps an overlay of the AMMs changed by the logs that are not `finality_depth` blocks deep yet, rebuilt on
+    /// every block and readable through [`StateSpaceManager::get_amm_at_head`].
+    pub fn with_head_overlay(mut self) -> Self {
+        self.head_overlay = Some(Arc::new(RwLock::new(HashMap::new())));
+        self
+    }
+
+    /// Adds the AMMs created by the factories of `discovery` to the state space as their creation logs are received.
+    ///
+    /// New AMMs are populated as of the block their logs are received in, value filtered, and reported as updated by
+    /// [`StateSpaceManager::subscribe_state_changes`].
+    pub async fn with_amm_discovery(mut self, discovery: AmmDiscovery) -> Self {
+        let discovery = Arc::new(discovery);
+
+        {
+            let state = self.state.read().await;
+            *self.filter.write().await = event_filter(state.values(), Some(&discovery));
+        }
+
+        self.discovery = Some(discovery);
+        self
+    }
+
+    /// Sets the user metadata of the AMMs, e.g. the metadata of a checkpoint.
+    pub fn with_metadata(mut self, metadata: AmmMetadataMap) -> Self {
+        self.metadata = Arc::new(RwLock::new(metadata));
+        self
+    }
+
+    /// Records the latest `capacity` logs applied to each AMM, see [`AuditLog`].
+    ///
+    /// Logs unwound by a reorg are dropped from the audit log along with their state changes.
+    pub fn with_audit_log(mut self, capacity: usize) -> Self {
+        self.audit_log = Some(Arc::new(RwLock::new(AuditLog::new(capacity))));
+        self
+    }
+
+    /// Returns the audit log of the state space, if it is enabled.
+    pub fn audit_log(&self) -> Option<Arc<RwLock<AuditLog>>> {
+        self.audit_log.clone()
+    }
+
+    /// Caches the results of [`StateSpaceManager::simulate_swap`] until the next block, or until the AMM receives a
+    /// log.
+    pub fn with_quote_cache(mut self, mut quote_cache: QuoteCache) -> Self {
+        quote_cache.update(self.latest_synced_block, &[]);
+        self.quote_cache = Some(Arc::new(RwLock::new(quote_cache)));
+        self
+    }
+
+    /// Publishes an immutable snapshot of the state space after every block, readable without locks through
+    /// [`StateSpaceManager::snapshots`].
+    #[cfg(feature = "arc-swap")]
+    pub async fn with_snapshots(mut self) -> Self {
+        let snapshots =
+            SnapshotStateSpace::new(&*self.state.read().await, self.latest_synced_block);
+        self.snapshots = Some(snapshots);
+
+        self
+    }
+
+    /// Returns the snapshots of the state space, if they are enabled.
+    #[cfg(feature = "arc-swap")]
+    pub fn snapshots(&self) -> Option<SnapshotStateSpace> {
+        self.snapshots.clone()
+    }
+
+    /// Publishes the AMM at `amm_address` to the latest snapshot after it was added or removed.
+    #[cfg(feature = "arc-swap")]
+    async fn publish_snapshot(&self, amm_address: Address) {
+        if let Some(snapshots) = &self.snapshots {
+            let block_number = snapshots.load().block_number;
+            snapshots.publish(&*self.state.read().await, &[amm_address], block_number);
+        }
+    }
+
+    /// Returns the version of the AMM at `amm_address`, bumped every time the AMM changes.
+    pub async fn amm_version(&self, amm_address: Address) -> u64 {
+        self.versions.read().await.version(amm_address)
+    }
+
+    /// Returns a receiver of the version of the AMM at `amm_address`, which is notified every time the AMM changes.
+    pub async fn subscribe_amm(&self, amm_address: Address) -> tokio::sync::watch::Receiver<u64> {
+        self.versions.write().await.subscribe(amm_address)
+    }
+
+    /// Returns a copy of the AMM at `amm_address`, if it is in the state space.
+    pub async fn get_amm(&self, amm_address: Address) -> Option<AMM> {
+        self.state.read().await.get(&amm_address).cloned()
+    }
+
+    /// Returns a copy of the AMM at `amm_address` as of the chain head, with the logs of the head overlay applied.
+    ///
+    /// Same as [`StateSpaceManager::get_amm`] without a head overlay.
+    pub async fn get_amm_at_head(&self, amm_address: Address) -> Option<AMM> {
+        if let Some(head_overlay) = &self.head_overlay {
+            if let Some(amm) = head_overlay.read().await.get(&amm_address) {
+                return Some(amm.clone());
+            }
+        }
+
+        self.get_amm(amm_address).await
+    }
+
+    /// Returns the value locked in the AMMs of the state space in USD, per protocol and per token.
+    ///
+    /// Call it on demand or on every state change, e.g. from [`StateSpaceManager::subscribe_state_changes`], to
+    /// refresh the report every block.
+    pub async fn tvl_report(&self, price_oracle: &impl PriceOracle) -> TvlReport {
+        TvlReport::new(self.state.read().await.values(), price_oracle)
+    }
+
+    /// Returns a copy of the AMMs trading `token_a` against `token_b`.
+    pub async fn get_amms_for_pair(&self, token_a: Address, token_b: Address) -> Vec<AMM> {
+        let pools = self.index.read().await.pools_for_pair(token_a, token_b);
+        let state = self.state.read().await;
+
+        pools
+            .into_iter()
+            .filter_map(|address| state.get(&address).cloned())
+            .collect()
+    }
+
+    /// Adds `amm` to the state space, replacing the AMM at the same address, and returns the replaced AMM.
+    ///
+    /// `amm` must be populated as of the latest block handled by the state space. Running subscriptions start
+    /// fetching the logs of the AMM from the next block.
+    pub async fn add_amm(&self, amm: AMM) -> Option<AMM> {
+        let amm_address = amm.address();
+        self.paused.write().await.remove(&amm_address);
+        self.invalidate_quotes(amm_address).await;
+
+        let replaced_amm = insert_amms(
+            &self.state,
+            &self.index,
+            &self.filter,
+            self.discovery.as_deref(),
+            vec![amm],
+            self.tick_prune_radius,
+        )
+        .await
+        .pop()
+        .flatten();
+
+        #[cfg(feature = "arc-swap")]
+        self.publish_snapshot(amm_address).await;
+        self.versions.write().await.bump(amm_address);
+
+        replaced_amm
+    }
+
+    /// Removes the AMM at `amm_address` from the state space, whether it is paused or not, along with its metadata.
+    pub async fn remove_amm(&self, amm_address: Address) -> Option<AMM> {
+        self.invalidate_quotes(amm_address).await;
+        self.index.write().await.remove(amm_address);
+
+        let mut state = self.state.write().await;
+        let removed_amm = match state.remove(&amm_address) {
+            Some(amm) => Some(amm),
+            None => self.paused.write().await.remove(&amm_address),
+        };
+        *self.filter.write().await = event_filter(state.values(), self.discovery.as_deref());
+        drop(state);
+        self.metadata.write().await.remove(&amm_address);
+
+        #[cfg(feature = "arc-swap")]
+        self.publish_snapshot(amm_address).await;
+        self.versions.write().await.bump(amm_address);
+
+        removed_amm
+    }
+
+    /// Stops applying logs to the AMM at `amm_address` and removes it from queries until it is resumed.
+    ///
+    /// Returns whether the AMM was in the state space.
+    pub async fn pause_amm(&self, amm_address: Address) -> bool {
+        self.invalidate_quotes(amm_address).await;
+        let Some(amm) = self.state.write().await.remove(&amm_address) else {
+            return false;
+        };
+
+        self.paused.write().await.insert(amm_address, amm);
+
+        #[cfg(feature = "arc-swap")]
+        self.publish_snapshot(amm_address).await;
+        self.versions.write().await.bump(amm_address);
+
+        true
+    }
+
+    /// Adds a paused AMM back to the state space, returns whether the AMM was paused.
+    ///
+    /// The AMM keeps its state from when it was paused, add an up to date AMM with [`StateSpaceManager::add_amm`]
+    /// instead if it changed in the meantime.
+    pub async fn resume_amm(&self, amm_address: Address) -> bool {
+        let Some(amm) = self.paused.write().await.remove(&amm_address) else {
+            return false;
+        };
+
+        self.state.write().await.insert(amm_address, amm);
+
+        #[cfg(feature = "arc-swap")]
+        self.publish_snapshot(amm_address).await;
+        self.versions.write().await.bump(amm_address);
+
+        true
+    }
+
+    async fn invalidate_quotes(&self, amm_address: Address) {
+        if let Some(quote_cache) = &self.quote_cache {
+            quote_cache.write().await.invalidate(amm_address);
+        }
+    }
+
+    /// Returns the addresses of the paused AMMs.
+    pub async fn paused_amms(&self) -> Vec<Address> {
+        self.paused.read().await.keys().copied().collect()
+    }
+
+    /// Returns a copy of the user metadata of the AMM at `amm_address`, if any.
+    pub async fn metadata(&self, amm_address: Address) -> Option<AmmMetadata> {
+        self.metadata.read().await.get(&amm_address).cloned()
+    }
+
+    /// Returns a copy of the user metadata of all AMMs, e.g. to write it to a checkpoint with
+    /// [`Checkpoint::with_metadata`](crate::sync::checkpoint::Checkpoint::with_metadata).
+    pub async fn all_metadata(&self) -> AmmMetadataMap {
+        self.metadata.read().await.clone()
+    }
+
+    /// Sets the user metadata of the AMM at `amm_address`, returning its previous metadata if any.
+    pub async fn set_metadata(
+        &self,
+        amm_address: Address,
+        metadata: AmmMetadata,
+    ) -> Option<AmmMetadata> {
+        self.metadata.write().await.insert(amm_address, metadata)
+    }
+
+    /// Updates the user metadata of the AMM at `amm_address` in place, starting from empty metadata if it has none.
+    pub async fn update_metadata(
+        &self,
+        amm_address: Address,
+        update: impl FnOnce(&mut AmmMetadata),
+    ) {
+        update(self.metadata.write().await.entry(amm_address).or_default());
+    }
+
+    /// Returns the addresses of the AMMs tagged with `tag`.
+    pub async fn amms_with_tag(&self, tag: &str) -> Vec<Address> {
+        self.metadata
+            .read()
+            .await
+            .iter()
+            .filter(|(_, metadata)| metadata.has_tag(tag))
+            .map(|(address, _)| *address)
+            .collect()
+    }
+
+    /// Locally simulates a swap in the AMM at `amm_address`.
+    ///
+    /// If tick pruning is enabled and the swap reaches unloaded tick data, the missing ticks are fetched and the swap is retried.
+    /// If a quote cache is enabled, `amount_in` is rounded down to its bucket, see [`QuoteCache::amount_bucket`].
+    pub async fn simulate_swap(
+        &self,
+        amm_address: Address,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, StateSpaceError> {
+        let Some(quote_cache) = &self.quote_cache else {
+            return self
+                .simulate_swap_uncached(amm_address, token_in, amount_in)
+                .await;
+        };
+
+        let key = quote_cache
+            .read()
+            .await
+            .key(amm_address, token_in, amount_in);
+        if let Some(amount_out) = quote_cache.read().await.get(&key) {
+            return Ok(amount_out);
+        }
+
+        let amount_out = self
+            .simulate_swap_uncached(amm_address, token_in, key.amount_bucket)
+            .await?;
+        quote_cache.write().await.insert(key, amount_out);
+
+        Ok(amount_out)
+    }
 
-            if !amm_variants.contains(&variant) {
-                amm_variants.insert(variant);
-                event_signatures.extend(amm.sync_on_event_signatures());
+    async fn simulate_swap_uncached(
+        &self,
+        amm_address: Address,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, StateSpaceError> {
+        loop {
+            let result = self
+                .state
+                .read()
+                .await
+                .get(&amm_address)
+                .ok_or(StateSpaceError::AMMNotFound(amm_address))?
+                .simulate_swap(token_in, amount_in);
+
+            match (result, self.tick_prune_radius) {
+                (Err(SwapSimulationError::TickWordNotLoaded(word)), Some(radius_words)) => {
+                    let mut state = self.state.write().await;
+                    if let Some(AMM::UniswapV3Pool(pool)) = state.get_mut(&amm_address) {
+                        pool.load_tick_words(
+                            word.saturating_sub(radius_words),
+                            word.saturating_add(radius_words),
+                            None,
+                            self.provider.clone(),
+                        )
+                        .await?;
+                    }
+                }
+                (result, _) => return Ok(result?),
             }
         }
+    }
 
-        // Create a new filter
-        Filter::new().event_signature(event_signatures)
+    /// Returns the log filter of the AMMs in the state space.
+    pub async fn filter(&self) -> Filter {
+        self.filter.read().await.clone()
     }
 
     /// Listens to new blocks and handles state changes, sending a Vec<H160> containing each AMM address that incurred a state change in the block.
@@ -106,96 +508,93 @@ where
         ),
         StateSpaceError,
     > {
-        let mut last_synced_block = self.latest_synced_block;
+        let (amms_updated_tx, amms_updated_rx) =
+            tokio::sync::mpsc::channel(self.state_change_buffer);
 
-        let (stream_tx, mut stream_rx): (Sender<Block>, Receiver<Block>) =
-            tokio::sync::mpsc::channel(self.stream_buffer);
+        let handles = self.spawn_sync_loop(Some(amms_updated_tx)).await?;
 
-        let provider = self.provider.clone();
-        let stream_handle = tokio::spawn(async move {
-            let subscription = provider.subscribe_blocks().await?;
-            let mut block_stream = subscription.into_stream();
-            while let Some(block) = block_stream.next().await {
-                stream_tx.send(block).await?;
-            }
+        Ok((amms_updated_rx, handles))
+    }
 
-            Ok::<(), StateSpaceError>(())
-        });
+    /// Returns a receiver of the addresses of the AMMs updated in each block by the sync loop started with
+    /// [`StateSpaceManager::subscribe_state_changes`] or [`StateSpaceManager::watch_state_changes`].
+    ///
+    /// Any number of receivers can be created without starting another sync loop. A receiver that falls more than
+    /// `state_change_buffer` blocks behind skips the oldest updates.
+    pub fn state_change_receiver(&self) -> broadcast::Receiver<Vec<Address>> {
+        self.state_change_tx.subscribe()
+    }
 
-        let (amms_updated_tx, amms_updated_rx) =
-            tokio::sync::mpsc::channel(self.state_change_buffer);
+    /// Sends the best prices of `base_token` in `quote_token` across the pools of the pair whenever any of them
+    /// changes.
+    ///
+    /// Prices are computed from the updates published by the running sync loop, see
+    /// [`StateSpaceManager::state_change_receiver`], so either [`StateSpaceManager::subscribe_state_changes`] or
+    /// [`StateSpaceManager::watch_state_changes`] must be running for prices to be sent. Prices are sent at most once
+    /// per block, after the logs of the block are applied.
+    pub fn price_stream(
+        &self,
+        base_token: Address,
+        quote_token: Address,
+    ) -> (Receiver<PairPrice>, JoinHandle<Result<(), StateSpaceError>>) {
+        let mut amms_updated_rx = self.state_change_receiver();
+        let (price_tx, price_rx) = tokio::sync::mpsc::channel(self.state_change_buffer.max(1));
 
         let state = self.state.clone();
-        let provider = self.provider.clone();
-        let filter = self.filter().await;
-        let state_change_cache = self.state_change_cache.clone();
-
-        let updated_amms_handle: JoinHandle<Result<(), StateSpaceError>> =
-            tokio::spawn(async move {
-                while let Some(block) = stream_rx.recv().await {
-                    if let Some(chain_head_block_number) = block.header.number {
-                        // If there is a reorg, unwind state changes from last_synced block to the chain head block number
-                        if chain_head_block_number <= last_synced_block {
-                            tracing::trace!(
-                                chain_head_block_number,
-                                last_synced_block,
-                                "reorg detected, unwinding state changes"
-                            );
-                            unwind_state_changes(
-                                state.clone(),
-                                state_change_cache.clone(),
-                                chain_head_block_number,
-                            )
-                            .await?;
-
-                            // set the last synced block to the head block number
-                            last_synced_block = chain_head_block_number - 1;
-                        }
+        let index = self.index.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                let amms_updated = match amms_updated_rx.recv().await {
+                    Ok(amms_updated) => Some(amms_updated),
+                    // Updates were skipped, so the pools of the pair may have changed
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::debug!(skipped, "price stream lagged behind state changes");
+                        None
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
 
-                        let from_block: u64 = last_synced_block + 1;
-                        let logs = provider
-                            .get_logs(
-                                &filter
-                                    .clone()
-                                    .from_block(from_block)
-                                    .to_block(chain_head_block_number),
-                            )
-                            .await?;
-
-                        if logs.is_empty() {
-                            for block_number in from_block..=chain_head_block_number {
-                                add_state_change_to_cache(
-                                    state_change_cache.clone(),
-                                    StateChange::new(None, block_number),
-                                )
-                                .await?;
-                            }
-                        } else {
-                            let amms_updated = handle_state_changes_from_logs(
-                                state.clone(),
-                                state_change_cache.clone(),
-                                logs,
-                            )
-                            .await?;
-
-                            amms_updated_tx.send(amms_updated).await?;
-                        }
+                let pools = index.read().await.pools_for_pair(base_token, quote_token);
+                if let Some(amms_updated) = amms_updated {
+                    if !amms_updated.iter().any(|address| pools.contains(address)) {
+                        continue;
+                    }
+                }
 
-                        last_synced_block = chain_head_block_number;
-                    } else {
-                        return Err(StateSpaceError::BlockNumberNotFound);
+                let price = {
+                    let state = state.read().await;
+                    best_pair_price(
+                        pools.iter().filter_map(|address| state.get(address)),
+                        base_token,
+                        quote_token,
+                    )
+                };
+
+                if let Some(price) = price {
+                    if price_tx.send(price).await.is_err() {
+                        break;
                     }
                 }
+            }
 
-                Ok::<(), StateSpaceError>(())
-            });
+            Ok::<(), StateSpaceError>(())
+        });
 
-        Ok((amms_updated_rx, vec![stream_handle, updated_amms_handle]))
+        (price_rx, handle)
     }
 
     /// Listens to new blocks and handles state changes
     pub async fn watch_state_changes(
         &self,
+    ) -> Result<Vec<JoinHandle<Result<(), StateSpaceError>>>, StateSpaceError> {
+        self.spawn_sync_loop(None).await
+    }
+
+    /// Spawns the block stream and the sync loop handling each new block, sending the addresses of the updated AMMs
+    /// to `amms_updated_tx` if any.
+    async fn spawn_sync_loop(
+        &self,
+        amms_updated_tx: Option<Sender<Vec<Address>>>,
     ) -> Result<Vec<JoinHandle<Result<(), StateSpaceError>>>, StateSpaceError> {
         let mut last_synced_block = self.latest_synced_block;
 
@@ -213,58 +612,43 @@ where
             Ok::<(), StateSpaceError>(())
         });
 
-        let state = self.state.clone();
-        let provider = self.provider.clone();
-        let filter = self.filter().await;
-        let state_change_cache = self.state_change_cache.clone();
+        let sync_loop = SyncLoop {
+            state: self.state.clone(),
+            provider: self.provider.clone(),
+            filter: self.filter.clone(),
+            index: self.index.clone(),
+            discovery: self.discovery.clone(),
+            audit_log: self.audit_log.clone(),
+            quote_cache: self.quote_cache.clone(),
+            versions: self.versions.clone(),
+            state_change_tx: self.state_change_tx.clone(),
+            #[cfg(feature = "arc-swap")]
+            snapshots: self.snapshots.clone(),
+            state_change_cache: self.state_change_cache.clone(),
+            tick_prune_radius: self.tick_prune_radius,
+            finality_depth: self.finality_depth,
+            head_overlay: self.head_overlay.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::new(self.provider.get_chain_id().await?),
+            transport: PhantomData,
+            network: PhantomData,
+        };
 
         let updated_amms_handle: JoinHandle<Result<(), StateSpaceError>> =
             tokio::spawn(async move {
                 while let Some(block) = stream_rx.recv().await {
-                    if let Some(chain_head_block_number) = block.header.number {
-                        // If there is a reorg, unwind state changes from last_synced block to the chain head block number
-                        if chain_head_block_number <= last_synced_block {
-                            unwind_state_changes(
-                                state.clone(),
-                                state_change_cache.clone(),
-                                chain_head_block_number,
-                            )
-                            .await?;
-
-                            // set the last synced block to the head block number
-                            last_synced_block = chain_head_block_number - 1;
-                        }
+                    let Some(chain_head_block_number) = block.header.number else {
+                        return Err(StateSpaceError::BlockNumberNotFound);
+                    };
 
-                        let from_block: u64 = last_synced_block + 1;
-                        let logs = provider
-                            .get_logs(
-                                &filter
-                                    .clone()
-                                    .from_block(from_block)
-                                    .to_block(chain_head_block_number),
-                            )
-                            .await?;
-
-                        if logs.is_empty() {
-                            for block_number in from_block..=chain_head_block_number {
-                                add_state_change_to_cache(
-                                    state_change_cache.clone(),
-                                    StateChange::new(None, block_number),
-                                )
-                                .await?;
-                            }
-                        } else {
-                            let _amms_updated = handle_state_changes_from_logs(
-                                state.clone(),
-                                state_change_cache.clone(),
-                                logs,
-                            )
-                            .await?;
-                        }
+                    let amms_updated = sync_loop
+                        .handle_block(chain_head_block_number, &mut last_synced_block)
+                        .await?;
 
-                        last_synced_block = chain_head_block_number;
-                    } else {
-                        return Err(StateSpaceError::BlockNumberNotFound);
+                    if let (Some(amms_updated_tx), Some(amms_updated)) =
+                        (&amms_updated_tx, amms_updated)
+                    {
+                        amms_updated_tx.send(amms_updated).await?;
                     }
                 }
 
@@ -275,6 +659,347 @@ where
     }
 }
 
+/// State space handles moved into the sync loop spawned by [`StateSpaceManager::subscribe_state_changes`] and
+/// [`StateSpaceManager::watch_state_changes`].
+struct SyncLoop<T, N, P> {
+    state: Arc<RwLock<StateSpace>>,
+    provider: Arc<P>,
+    filter: Arc<RwLock<Filter>>,
+    index: Arc<RwLock<PoolIndex>>,
+    discovery: Option<Arc<AmmDiscovery>>,
+    audit_log: Option<Arc<RwLock<AuditLog>>>,
+    quote_cache: Option<Arc<RwLock<QuoteCache>>>,
+    versions: Arc<RwLock<AmmVersions>>,
+    state_change_tx: broadcast::Sender<Vec<Address>>,
+    #[cfg(feature = "arc-swap")]
+    snapshots: Option<SnapshotStateSpace>,
+    state_change_cache: Arc<RwLock<StateChangeCache>>,
+    tick_prune_radius: Option<i16>,
+    finality_depth: u64,
+    head_overlay: Option<Arc<RwLock<StateSpace>>>,
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
+    transport: PhantomData<T>,
+    network: PhantomData<N>,
+}
+
+impl<T, N, P> SyncLoop<T, N, P>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    /// Handles a new chain head: unwinds the state changes of reorged blocks, applies the logs of the blocks
+    /// `finality_depth` blocks deep and rebuilds the head overlay, advancing `last_synced_block`.
+    ///
+    /// Returns the addresses of the AMMs updated or created by the applied logs, or `None` if no logs were applied.
+    async fn handle_block(
+        &self,
+        chain_head_block_number: u64,
+        last_synced_block: &mut u64,
+    ) -> Result<Option<Vec<Address>>, StateSpaceError> {
+        let state = &self.state;
+        let filter = &self.filter;
+        let index = &self.index;
+        let state_change_cache = &self.state_change_cache;
+        let mut amms_updated = None;
+
+        // If there is a reorg, unwind state changes from last_synced block to the chain head block number
+        if chain_head_block_number <= *last_synced_block {
+            tracing::trace!(
+                chain_head_block_number,
+                last_synced_block = *last_synced_block,
+                "reorg detected, unwinding state changes"
+            );
+            unwind_state_changes(
+                state.clone(),
+                state_change_cache.clone(),
+                chain_head_block_number,
+            )
+            .await?;
+
+            if let Some(audit_log) = &self.audit_log {
+                audit_log.write().await.unwind(chain_head_block_number);
+            }
+
+            #[cfg(feature = "arc-swap")]
+            if let Some(snapshots) = &self.snapshots {
+                snapshots.publish_all(&*state.read().await, chain_head_block_number - 1);
+            }
+
+            // Any AMM may have been unwound
+            bump_versions(&self.versions, state.read().await.keys()).await;
+
+            // set the last synced block to the head block number
+            *last_synced_block = chain_head_block_number - 1;
+        }
+
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_sync_lag(chain_head_block_number, *last_synced_block);
+
+        // Logs are applied once they are `finality_depth` blocks deep
+        let finalized_block_number = chain_head_block_number.saturating_sub(self.finality_depth);
+        if finalized_block_number > *last_synced_block {
+            let from_block: u64 = *last_synced_block + 1;
+            let log_filter = filter
+                .read()
+                .await
+                .clone()
+                .from_block(from_block)
+                .to_block(finalized_block_number);
+            let logs = self.provider.get_logs(&log_filter).await;
+
+            #[cfg(feature = "metrics")]
+            if logs.is_err() {
+                self.metrics.record_rpc_error("eth_getLogs");
+            }
+
+            let logs = logs?;
+
+            #[cfg(feature = "metrics")]
+            {
+                let state = state.read().await;
+                self.metrics.record_pools_tracked(&state);
+                self.metrics.record_logs_applied(
+                    &state,
+                    &logs,
+                    from_block..=finalized_block_number,
+                );
+            }
+
+            if logs.is_empty() {
+                for block_number in from_block..=finalized_block_number {
+                    add_state_change_to_cache(
+                        state_change_cache.clone(),
+                        StateChange::new(None, block_number),
+                    )
+                    .await?;
+                }
+
+                if let Some(quote_cache) = &self.quote_cache {
+                    quote_cache
+                        .write()
+                        .await
+                        .update(finalized_block_number, &[]);
+                }
+
+                #[cfg(feature = "arc-swap")]
+                if let Some(snapshots) = &self.snapshots {
+                    snapshots.publish(&*state.read().await, &[], finalized_block_number);
+                }
+            } else {
+                let amms_created = match &self.discovery {
+                    // A failed discovery skips the new AMMs instead of ending the subscription
+                    Some(discovery) => discovery
+                        .amms_created_in_logs(&logs, finalized_block_number, self.provider.clone())
+                        .await
+                        .unwrap_or_else(|error| {
+                            tracing::warn!(
+                                ?error,
+                                block_number = finalized_block_number,
+                                "Could not discover the AMMs created in the block"
+                            );
+                            vec![]
+                        }),
+                    None => vec![],
+                };
+
+                if let Some(audit_log) = &self.audit_log {
+                    record_applied_logs(state, index, audit_log, &logs).await?;
+                }
+
+                let mut amms_updated_in_block = handle_state_changes_from_logs_with_index(
+                    state.clone(),
+                    index.clone(),
+                    state_change_cache.clone(),
+                    logs,
+                )
+                .await?;
+
+                if let Some(radius_words) = self.tick_prune_radius {
+                    prune_ticks(state.clone(), &amms_updated_in_block, radius_words).await;
+                }
+
+                if let Some(quote_cache) = &self.quote_cache {
+                    quote_cache
+                        .write()
+                        .await
+                        .update(finalized_block_number, &amms_updated_in_block);
+                }
+
+                // New AMMs are populated as of the last applied block, so they are added after the logs are applied
+                let amms_created_addresses = amms_created
+                    .iter()
+                    .map(|amm| amm.address())
+                    .collect::<Vec<_>>();
+                insert_amms(
+                    state,
+                    index,
+                    filter,
+                    self.discovery.as_deref(),
+                    amms_created,
+                    self.tick_prune_radius,
+                )
+                .await;
+
+                for address in amms_created_addresses {
+                    if !amms_updated_in_block.contains(&address) {
+                        amms_updated_in_block.push(address);
+                    }
+                }
+
+                #[cfg(feature = "arc-swap")]
+                if let Some(snapshots) = &self.snapshots {
+                    snapshots.publish(
+                        &*state.read().await,
+                        &amms_updated_in_block,
+                        finalized_block_number,
+                    );
+                }
+
+                bump_versions(&self.versions, amms_updated_in_block.iter()).await;
+
+                // Sending only fails when there are no broadcast subscribers
+                let _ = self.state_change_tx.send(amms_updated_in_block.clone());
+                amms_updated = Some(amms_updated_in_block);
+            }
+
+            *last_synced_block = finalized_block_number;
+        }
+
+        if let Some(head_overlay) = &self.head_overlay {
+            update_head_overlay(
+                state,
+                index,
+                head_overlay,
+                filter,
+                *last_synced_block + 1,
+                chain_head_block_number,
+                self.provider.clone(),
+            )
+            .await?;
+        }
+
+        Ok(amms_updated)
+    }
+}
+
+/// Returns the filter of the logs that update `amms`, and of the creation logs of the `discovery` factories.
+fn event_filter<'a>(
+    amms: impl Iterator<Item = &'a AMM>,
+    discovery: Option<&AmmDiscovery>,
+) -> Filter {
+    // The signatures depend on the configuration of each AMM, e.g. Uniswap V2 pools syncing from amount logs
+    let mut event_signatures: HashSet<B256> = amms
+        .flat_map(|amm| amm.sync_on_event_signatures())
+        .collect();
+
+    if let Some(discovery) = discovery {
+        event_signatures.extend(discovery.event_signatures());
+    }
+
+    Filter::new().event_signature(event_signatures.into_iter().collect::<Vec<_>>())
+}
+
+/// Adds `amms` to the state space and rebuilds its filter, returning the AMM replaced by each AMM.
+async fn insert_amms(
+    state: &RwLock<StateSpace>,
+    index: &RwLock<PoolIndex>,
+    filter: &RwLock<Filter>,
+    discovery: Option<&AmmDiscovery>,
+    amms: Vec<AMM>,
+    tick_prune_radius: Option<i16>,
+) -> Vec<Option<AMM>> {
+    if amms.is_empty() {
+        return vec![];
+    }
+
+    let mut index = index.write().await;
+    let mut state = state.write().await;
+    let mut replaced_amms = vec![];
+
+    for mut amm in amms {
+        if let (AMM::UniswapV3Pool(pool), Some(radius_words)) = (&mut amm, tick_prune_radius) {
+            pool.prune_ticks(radius_words);
+        }
+
+        index.insert(&amm);
+        replaced_amms.push(state.insert(amm.address(), amm));
+    }
+
+    *filter.write().await = event_filter(state.values(), discovery);
+
+    replaced_amms
+}
+
+/// Rebuilds `head_overlay` from the logs of the AMMs in the state space between `from_block` and `to_block`.
+async fn update_head_overlay<T, N, P>(
+    state: &RwLock<StateSpace>,
+    index: &RwLock<PoolIndex>,
+    head_overlay: &RwLock<StateSpace>,
+    filter: &RwLock<Filter>,
+    from_block: u64,
+    to_block: u64,
+    provider: Arc<P>,
+) -> Result<(), StateSpaceError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let logs = if from_block > to_block {
+        vec![]
+    } else {
+        let filter = filter
+            .read()
+            .await
+            .clone()
+            .from_block(from_block)
+            .to_block(to_block);
+        provider.get_logs(&filter).await?
+    };
+
+    let index = index.read().await;
+    let overlay = overlay_logs_with_index(&*state.read().await, &index, logs)?;
+    *head_overlay.write().await = overlay;
+
+    Ok(())
+}
+
+/// Applies `logs` to copies of the AMMs of `state` they update, returning the updated AMMs.
+///
+/// The pool index of `state` is rebuilt on every call, which is O(N) in the number of AMMs, use
+/// [`overlay_logs_with_index`] to reuse an index kept up to date with the state space.
+pub fn overlay_logs(state: &StateSpace, logs: Vec<Log>) -> Result<StateSpace, EventLogError> {
+    overlay_logs_with_index(state, &state.values().collect(), logs)
+}
+
+/// [`overlay_logs`] with the pools of singleton contracts looked up in `index`, the pool index of `state`.
+pub fn overlay_logs_with_index(
+    state: &StateSpace,
+    index: &PoolIndex,
+    logs: Vec<Log>,
+) -> Result<StateSpace, EventLogError> {
+    let mut overlay = StateSpace::new();
+
+    for log in logs {
+        for amm_address in log_amm_addresses(state, index, &log) {
+            if !overlay.contains_key(&amm_address) {
+                if let Some(amm) = state.get(&amm_address) {
+                    overlay.insert(amm_address, amm.clone());
+                }
+            }
+
+            if let Some(amm) = overlay.get_mut(&amm_address) {
+                amm.sync_from_log(log.clone())?;
+            }
+        }
+    }
+
+    Ok(overlay)
+}
+
 pub fn initialize_state_space(amms: Vec<AMM>) -> StateSpace {
     amms.into_iter()
         .map(|amm| (amm.address(), amm))
@@ -349,10 +1074,38 @@ async fn add_state_change_to_cache(
     Ok(())
 }
 
+/// Applies `logs` to the AMMs of `state`, recording their previous state in `state_change_cache`, and returns the
+/// addresses of the updated AMMs.
+///
+/// The pool index of `state` is rebuilt on every call, which is O(N) in the number of AMMs, use
+/// [`handle_state_changes_from_logs_with_index`] to reuse an index kept up to date with the state space.
 pub async fn handle_state_changes_from_logs(
     state: Arc<RwLock<StateSpace>>,
     state_change_cache: Arc<RwLock<StateChangeCache>>,
     logs: Vec<Log>,
+) -> Result<Vec<Address>, StateChangeError> {
+    let index = state.read().await.values().collect::<PoolIndex>();
+
+    handle_state_changes_from_logs_with_index(
+        state,
+        Arc::new(RwLock::new(index)),
+        state_change_cache,
+        logs,
+    )
+    .await
+}
+
+/// [`handle_state_changes_from_logs`] with the pools of singleton contracts looked up in `index`, the pool index of
+/// `state`.
+#[cfg_attr(
+    feature = "tracing-spans",
+    tracing::instrument(skip_all, fields(logs = logs.len()), level = "debug")
+)]
+pub async fn handle_state_changes_from_logs_with_index(
+    state: Arc<RwLock<StateSpace>>,
+    index: Arc<RwLock<PoolIndex>>,
+    state_change_cache: Arc<RwLock<StateChangeCache>>,
+    logs: Vec<Log>,
 ) -> Result<Vec<Address>, StateChangeError> {
     let mut updated_amms_set = HashSet::new();
     let mut updated_amms = vec![];
@@ -367,15 +1120,33 @@ pub async fn handle_state_changes_from_logs(
     for log in logs.into_iter() {
         let log_block_number = get_block_number_from_log(&log)?;
 
-        // check if the log is from an amm in the state space
-        if let Some(amm) = state.write().await.get_mut(&log.address()) {
-            if !updated_amms_set.contains(&log.address()) {
-                updated_amms_set.insert(log.address());
-                updated_amms.push(log.address());
+        {
+            let index = index.read().await;
+            let mut state = state.write().await;
+
+            // check if the log is from an amm in the state space
+            if let Some(amm) = state.get_mut(&log.address()) {
+                apply_log(
+                    amm,
+                    log,
+                    &mut updated_amms_set,
+                    &mut updated_amms,
+                    &mut state_changes,
+                )?;
+            } else {
+                // Pools of singleton contracts are routed by the content of the logs of the contract
+                for amm_address in singleton_amm_addresses(&state, &index, &log) {
+                    if let Some(amm) = state.get_mut(&amm_address) {
+                        apply_log(
+                            amm,
+                            log.clone(),
+                            &mut updated_amms_set,
+                            &mut updated_amms,
+                            &mut state_changes,
+                        )?;
+                    }
+                }
             }
-
-            state_changes.push(amm.clone());
-            amm.sync_from_log(log)?;
         }
 
         // Commit state changes if the block has changed since last log
@@ -416,6 +1187,109 @@ pub async fn handle_state_changes_from_logs(
     Ok(updated_amms)
 }
 
+/// Applies `log` to `amm`, recording the state of the AMM before the change.
+fn apply_log(
+    amm: &mut AMM,
+    log: Log,
+    updated_amms_set: &mut HashSet<Address>,
+    updated_amms: &mut Vec<Address>,
+    state_changes: &mut Vec<AMM>,
+) -> Result<(), StateChangeError> {
+    let amm_address = amm.address();
+    if updated_amms_set.insert(amm_address) {
+        updated_amms.push(amm_address);
+    }
+
+    state_changes.push(amm.clone());
+
+    #[cfg(feature = "tracing-spans")]
+    tracing::trace!(address = ?amm_address, block_number = ?log.block_number, "Applying log");
+
+    amm.sync_from_log(log)?;
+
+    Ok(())
+}
+
+/// Returns the addresses of the AMMs in the state space that `log` applies to: the AMM at the address of the log, or the
+/// AMMs of a singleton contract, see [`singleton_amm_addresses`].
+fn log_amm_addresses(state: &StateSpace, index: &PoolIndex, log: &Log) -> Vec<Address> {
+    if state.contains_key(&log.address()) {
+        vec![log.address()]
+    } else {
+        singleton_amm_addresses(state, index, log)
+    }
+}
+
+/// Returns the addresses of the AMMs in the state space that `log` applies to, for AMMs living in a singleton contract:
+/// the Bancor V3 pools traded in by a `TokensTraded` log of the Bancor network, or the Ambient pool of a swap or
+/// liquidity log of the Ambient dex.
+///
+/// Bancor V3 pools are looked up in `index` by the tokens traded, so routing a log does not scan the state space.
+fn singleton_amm_addresses(state: &StateSpace, index: &PoolIndex, log: &Log) -> Vec<Address> {
+    let topics = log.topics();
+    if topics.first() == Some(&IBancorNetwork::TokensTraded::SIGNATURE_HASH) {
+        return topics
+            .iter()
+            .skip(2)
+            .map(|topic| Address::from_word(*topic))
+            .filter(|token| *token != BNT)
+            .flat_map(|token| index.pools_for_pair(token, BNT))
+            .filter(|address| match state.get(address) {
+                Some(AMM::BancorV3Pool(pool)) => pool.is_traded_in(log),
+                _ => false,
+            })
+            .collect();
+    }
+
+    match ambient::pool_address_from_log(log).and_then(|address| state.get(&address)) {
+        Some(AMM::AmbientPool(pool)) if pool.dex == log.address() => vec![pool.address],
+        _ => vec![],
+    }
+}
+
+/// Records the logs of the AMMs in the state space in `audit_log` under the AMMs they apply to, before they are
+/// applied. Logs of singleton contracts are recorded under each of the pools they are routed to.
+async fn record_applied_logs(
+    state: &RwLock<StateSpace>,
+    index: &RwLock<PoolIndex>,
+    audit_log: &RwLock<AuditLog>,
+    logs: &[Log],
+) -> Result<(), AuditLogError> {
+    let index = index.read().await;
+    let state = state.read().await;
+    let mut audit_log = audit_log.write().await;
+
+    for log in logs {
+        for amm_address in log_amm_addresses(&state, &index, log) {
+            audit_log.record(amm_address, log)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Bumps the versions of the AMMs at `amm_addresses` once their changes are applied.
+async fn bump_versions(
+    versions: &RwLock<AmmVersions>,
+    amm_addresses: impl Iterator<Item = &Address>,
+) {
+    let mut versions = versions.write().await;
+    for amm_address in amm_addresses {
+        versions.bump(*amm_address);
+    }
+}
+
+/// Prunes the tick data of the updated Uniswap V3 pools around their current tick.
+async fn prune_ticks(state: Arc<RwLock<StateSpace>>, amms_updated: &[Address], radius_words: i16) {
+    let mut state = state.write().await;
+
+    for address in amms_updated {
+        if let Some(AMM::UniswapV3Pool(pool)) = state.get_mut(address) {
+            pool.prune_ticks(radius_words);
+        }
+    }
+}
+
 pub fn get_block_number_from_log(log: &Log) -> Result<u64, EventLogError> {
     if let Some(block_number) = log.block_number {
         Ok(block_number)
@@ -428,11 +1302,107 @@ pub fn get_block_number_from_log(log: &Log) -> Result<u64, EventLogError> {
 mod tests {
     use std::{default, sync::Arc};
 
-    use crate::amm::{uniswap_v2::UniswapV2Pool, AMM};
+    use crate::amm::{
+        bancor_v3::BancorV3Pool,
+        uniswap_v2::{IUniswapV2Pair, UniswapV2Pool},
+        AMM,
+    };
     use alloy::{providers::ProviderBuilder, rpc::client::WsConnect};
 
     use super::*;
 
+    #[test]
+    fn test_singleton_amm_addresses() {
+        let network = Address::repeat_byte(0xee);
+        let link = Address::repeat_byte(0x01);
+        let dai = Address::repeat_byte(0x02);
+        let weth = Address::repeat_byte(0x03);
+
+        let pool = |address: u8, base_token| {
+            AMM::BancorV3Pool(BancorV3Pool {
+                address: Address::with_last_byte(address),
+                ..BancorV3Pool::new(base_token, network, Address::ZERO, Address::ZERO)
+            })
+        };
+        let state = initialize_state_space(vec![pool(1, link), pool(2, dai), pool(3, weth)]);
+        let index = state.values().collect::<PoolIndex>();
+
+        // Two leg trade of LINK for DAI, through BNT
+        let log = Log {
+            inner: alloy::primitives::Log {
+                address: network,
+                data: IBancorNetwork::TokensTraded {
+                    contextId: B256::ZERO,
+                    sourceToken: link,
+                    targetToken: dai,
+                    sourceAmount: U256::from(1),
+                    targetAmount: U256::from(1),
+                    bntAmount: U256::from(1),
+                    targetFeeAmount: U256::ZERO,
+                    bntFeeAmount: U256::ZERO,
+                    trader: Address::ZERO,
+                }
+                .encode_log_data(),
+            },
+            ..default::Default::default()
+        };
+
+        let mut amm_addresses = singleton_amm_addresses(&state, &index, &log);
+        amm_addresses.sort();
+        assert_eq!(
+            amm_addresses,
+            vec![Address::with_last_byte(1), Address::with_last_byte(2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_applied_singleton_logs() -> eyre::Result<()> {
+        let network = Address::repeat_byte(0xee);
+        let link = Address::repeat_byte(0x01);
+        let dai = Address::repeat_byte(0x02);
+
+        let pool = |address: u8, base_token| {
+            AMM::BancorV3Pool(BancorV3Pool {
+                address: Address::with_last_byte(address),
+                ..BancorV3Pool::new(base_token, network, Address::ZERO, Address::ZERO)
+            })
+        };
+        let state = initialize_state_space(vec![pool(1, link), pool(2, dai)]);
+        let index = RwLock::new(state.values().collect::<PoolIndex>());
+        let state = RwLock::new(state);
+        let audit_log = RwLock::new(AuditLog::new(10));
+
+        let log = Log {
+            inner: alloy::primitives::Log {
+                address: network,
+                data: IBancorNetwork::TokensTraded {
+                    contextId: B256::ZERO,
+                    sourceToken: link,
+                    targetToken: dai,
+                    sourceAmount: U256::from(1),
+                    targetAmount: U256::from(1),
+                    bntAmount: U256::from(1),
+                    targetFeeAmount: U256::ZERO,
+                    bntFeeAmount: U256::ZERO,
+                    trader: Address::ZERO,
+                }
+                .encode_log_data(),
+            },
+            block_number: Some(1),
+            ..default::Default::default()
+        };
+
+        record_applied_logs(&state, &index, &audit_log, &[log]).await?;
+
+        // The log of the network is recorded under both pools it is routed to
+        let audit_log = audit_log.read().await;
+        assert_eq!(audit_log.logs(Address::with_last_byte(1)).len(), 1);
+        assert_eq!(audit_log.logs(Address::with_last_byte(2)).len(), 1);
+        assert!(audit_log.logs(network).is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_add_state_changes() -> eyre::Result<()> {
         let state_change_cache = Arc::new(RwLock::new(StateChangeCache::new()));
@@ -509,6 +1479,60 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_add_remove_and_pause_amms() -> eyre::Result<()> {
+        let token_a = alloy::primitives::address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let token_b = alloy::primitives::address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let pool = |address| {
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address,
+                token_a,
+                token_b,
+                ..default::Default::default()
+            })
+        };
+        let pool_a = Address::with_last_byte(1);
+        let pool_b = Address::with_last_byte(2);
+
+        // The provider is not used without subscriptions
+        let provider = Arc::new(ProviderBuilder::new().on_http("http://localhost:8545".parse()?));
+        let state_space_manager = StateSpaceManager::new(vec![pool(pool_a)], 0, 100, 100, provider);
+
+        assert!(state_space_manager.add_amm(pool(pool_b)).await.is_none());
+        assert_eq!(
+            state_space_manager
+                .get_amms_for_pair(token_b, token_a)
+                .await
+                .len(),
+            2
+        );
+
+        assert!(state_space_manager.pause_amm(pool_a).await);
+        assert!(!state_space_manager.pause_amm(pool_a).await);
+        assert!(state_space_manager.get_amm(pool_a).await.is_none());
+        assert_eq!(state_space_manager.paused_amms().await, vec![pool_a]);
+        assert_eq!(
+            state_space_manager
+                .get_amms_for_pair(token_a, token_b)
+                .await
+                .len(),
+            1
+        );
+
+        assert!(state_space_manager.resume_amm(pool_a).await);
+        assert!(state_space_manager.get_amm(pool_a).await.is_some());
+
+        assert!(state_space_manager.remove_amm(pool_b).await.is_some());
+        assert!(state_space_manager.remove_amm(pool_b).await.is_none());
+        assert!(state_space_manager.get_amm(pool_b).await.is_none());
+
+        // Removing every Uniswap V2 pool drops their events from the filter
+        assert!(state_space_manager.remove_amm(pool_a).await.is_some());
+        assert!(state_space_manager.filter().await.topics[0].is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_add_empty_state_changes() -> eyre::Result<()> {
         let last_synced_block = 0;
@@ -529,4 +1553,49 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_overlay_logs() -> eyre::Result<()> {
+        let pool_address = Address::with_last_byte(1);
+        let state = initialize_state_space(vec![AMM::UniswapV2Pool(UniswapV2Pool {
+            address: pool_address,
+            reserve_0: 100,
+            reserve_1: 200,
+            ..default::Default::default()
+        })]);
+
+        let sync_log = |address, reserve0| Log {
+            inner: alloy::primitives::Log {
+                address,
+                data: IUniswapV2Pair::Sync {
+                    reserve0,
+                    reserve1: 400,
+                }
+                .encode_log_data(),
+            },
+            block_number: Some(101),
+            ..default::Default::default()
+        };
+
+        let overlay = overlay_logs(
+            &state,
+            vec![
+                sync_log(pool_address, 300),
+                sync_log(Address::with_last_byte(2), 1),
+            ],
+        )?;
+
+        // Only the AMMs of the state space are overlaid, and the state space is left untouched
+        assert_eq!(overlay.len(), 1);
+        let Some(AMM::UniswapV2Pool(pool)) = overlay.get(&pool_address) else {
+            panic!("Unexpected AMM variant")
+        };
+        assert_eq!((pool.reserve_0, pool.reserve_1), (300, 400));
+        let Some(AMM::UniswapV2Pool(pool)) = state.get(&pool_address) else {
+            panic!("Unexpected AMM variant")
+        };
+        assert_eq!((pool.reserve_0, pool.reserve_1), (100, 200));
+
+        Ok(())
+    }
 }
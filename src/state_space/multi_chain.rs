@@ -0,0 +1,233 @@
+//! State spaces of several chains behind one interface, with pools identified by chain id and address.
+
+use std::{collections::HashMap, fmt};
+
+use alloy::{
+    network::Network,
+    primitives::{Address, U256},
+    providers::Provider,
+    transports::Transport,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc::Receiver, task::JoinHandle};
+
+use crate::amm::{AutomatedMarketMaker, AMM};
+
+use super::{error::StateSpaceError, StateSpaceManager};
+
+/// Identifies a pool across chains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PoolId {
+    pub chain_id: u64,
+    pub address: Address,
+}
+
+impl PoolId {
+    pub fn new(chain_id: u64, address: Address) -> Self {
+        Self { chain_id, address }
+    }
+}
+
+impl fmt::Display for PoolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.chain_id, self.address)
+    }
+}
+
+/// Runs one [`StateSpaceManager`] per chain, each with its own provider, and merges their state changes.
+#[derive(Debug)]
+pub struct MultiChainStateSpace<T, N, P> {
+    managers: HashMap<u64, StateSpaceManager<T, N, P>>,
+    state_change_buffer: usize,
+}
+
+impl<T, N, P> MultiChainStateSpace<T, N, P>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    pub fn new(state_change_buffer: usize) -> Self {
+        Self {
+            managers: HashMap::new(),
+            state_change_buffer,
+        }
+    }
+
+    /// Adds the state space of `chain_id`, replacing the existing one.
+    pub fn with_chain(mut self, chain_id: u64, manager: StateSpaceManager<T, N, P>) -> Self {
+        self.add_chain(chain_id, manager);
+        self
+    }
+
+    /// Adds the state space of `chain_id`, returning the replaced one.
+    pub fn add_chain(
+        &mut self,
+        chain_id: u64,
+        manager: StateSpaceManager<T, N, P>,
+    ) -> Option<StateSpaceManager<T, N, P>> {
+        self.managers.insert(chain_id, manager)
+    }
+
+    pub fn remove_chain(&mut self, chain_id: u64) -> Option<StateSpaceManager<T, N, P>> {
+        self.managers.remove(&chain_id)
+    }
+
+    /// Returns the state space of `chain_id`.
+    pub fn chain(&self, chain_id: u64) -> Option<&StateSpaceManager<T, N, P>> {
+        self.managers.get(&chain_id)
+    }
+
+    pub fn chain_ids(&self) -> Vec<u64> {
+        self.managers.keys().copied().collect()
+    }
+
+    /// Returns a copy of the AMM at `pool_id`, if its chain and the AMM are in the state space.
+    pub async fn get_amm(&self, pool_id: PoolId) -> Option<AMM> {
+        self.chain(pool_id.chain_id)?.get_amm(pool_id.address).await
+    }
+
+    /// Returns a copy of the AMMs trading `token_a` against `token_b` on `chain_id`.
+    pub async fn get_amms_for_pair(
+        &self,
+        chain_id: u64,
+        token_a: Address,
+        token_b: Address,
+    ) -> Vec<(PoolId, AMM)> {
+        match self.chain(chain_id) {
+            Some(manager) => manager
+                .get_amms_for_pair(token_a, token_b)
+                .await
+                .into_iter()
+                .map(|amm| (PoolId::new(chain_id, amm.address()), amm))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Locally simulates a swap in the AMM at `pool_id`, see [`StateSpaceManager::simulate_swap`].
+    pub async fn simulate_swap(
+        &self,
+        pool_id: PoolId,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, StateSpaceError> {
+        self.chain(pool_id.chain_id)
+            .ok_or(StateSpaceError::ChainNotFound(pool_id.chain_id))?
+            .simulate_swap(pool_id.address, token_in, amount_in)
+            .await
+    }
+
+    /// Listens to new blocks on every chain and handles state changes, sending the ids of the AMMs that incurred a
+    /// state change in a block of any chain.
+    pub async fn subscribe_state_changes(
+        &self,
+    ) -> Result<
+        (
+            Receiver<Vec<PoolId>>,
+            Vec<JoinHandle<Result<(), StateSpaceError>>>,
+        ),
+        StateSpaceError,
+    > {
+        let (pools_updated_tx, pools_updated_rx) =
+            tokio::sync::mpsc::channel(self.state_change_buffer);
+        let mut handles = vec![];
+
+        for (&chain_id, manager) in self.managers.iter() {
+            let (mut amms_updated_rx, chain_handles) = manager.subscribe_state_changes().await?;
+            handles.extend(chain_handles);
+
+            let pools_updated_tx = pools_updated_tx.clone();
+            handles.push(tokio::spawn(async move {
+                while let Some(amms_updated) = amms_updated_rx.recv().await {
+                    let pools_updated = amms_updated
+                        .into_iter()
+                        .map(|address| PoolId::new(chain_id, address))
+                        .collect();
+
+                    pools_updated_tx.send(pools_updated).await?;
+                }
+
+                Ok::<(), StateSpaceError>(())
+            }));
+        }
+
+        Ok((pools_updated_rx, handles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{
+        primitives::{address, U256},
+        providers::ProviderBuilder,
+    };
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, AutomatedMarketMaker, AMM},
+        state_space::{error::StateSpaceError, StateSpaceManager},
+        test_utils::usdc_weth_pool,
+    };
+
+    use super::{MultiChainStateSpace, PoolId};
+
+    #[tokio::test]
+    async fn test_multi_chain_state_space() {
+        let token_a = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let token_b = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        // The same pool address on two chains, with different reserves
+        let pool_address = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let pool = |reserve_1| {
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address: pool_address,
+                reserve_1,
+                ..usdc_weth_pool()
+            })
+        };
+
+        // The providers are not used for Uniswap V2 simulations
+        let manager = |amm| {
+            let provider =
+                Arc::new(ProviderBuilder::new().on_http("http://localhost:8545".parse().unwrap()));
+            StateSpaceManager::new(vec![amm], 0, 100, 100, provider)
+        };
+
+        let mainnet_pool = pool(500_000_000_000_000_000_000);
+        let base_pool = pool(400_000_000_000_000_000_000);
+        let state_space = MultiChainStateSpace::new(100)
+            .with_chain(1, manager(mainnet_pool.clone()))
+            .with_chain(8453, manager(base_pool.clone()));
+
+        let amount_in = U256::from(1_000_000);
+        assert_eq!(
+            state_space
+                .simulate_swap(PoolId::new(1, pool_address), token_a, amount_in)
+                .await
+                .unwrap(),
+            mainnet_pool.simulate_swap(token_a, amount_in).unwrap()
+        );
+        assert_eq!(
+            state_space
+                .simulate_swap(PoolId::new(8453, pool_address), token_a, amount_in)
+                .await
+                .unwrap(),
+            base_pool.simulate_swap(token_a, amount_in).unwrap()
+        );
+        assert!(matches!(
+            state_space
+                .simulate_swap(PoolId::new(10, pool_address), token_a, amount_in)
+                .await,
+            Err(StateSpaceError::ChainNotFound(10))
+        ));
+
+        let amms = state_space.get_amms_for_pair(8453, token_b, token_a).await;
+        assert_eq!(amms.len(), 1);
+        assert_eq!(amms[0].0, PoolId::new(8453, pool_address));
+        assert!(state_space
+            .get_amms_for_pair(10, token_a, token_b)
+            .await
+            .is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! Best prices of a token pair across the pools of the state space, see
+//! [`super::StateSpaceManager::price_stream`].
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::amm::{AutomatedMarketMaker, AMM};
+
+/// Price of a pool, in quote token per base token.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PoolPrice {
+    pub pool: Address,
+    pub price: f64,
+}
+
+/// Best prices of `base_token` in `quote_token` across the pools of the pair, net of the pool fees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PairPrice {
+    pub base_token: Address,
+    pub quote_token: Address,
+    /// Highest price received when selling the base token.
+    pub best_bid: PoolPrice,
+    /// Lowest price paid when buying the base token.
+    pub best_ask: PoolPrice,
+}
+
+/// Returns the best prices of `base_token` in `quote_token` across `amms`, or `None` if no AMM trades the pair at a
+/// valid price.
+pub fn best_pair_price<'a>(
+    amms: impl IntoIterator<Item = &'a AMM>,
+    base_token: Address,
+    quote_token: Address,
+) -> Option<PairPrice> {
+    let mut best: Option<(PoolPrice, PoolPrice)> = None;
+
+    for amm in amms {
+        let tokens = amm.tokens();
+        if !tokens.contains(&base_token) || !tokens.contains(&quote_token) {
+            continue;
+        }
+
+        let Ok(price) = amm.calculate_price(base_token) else {
+            continue;
+        };
+        if !price.is_finite() || price <= 0.0 {
+            continue;
+        }
+
+        let fee = amm.fee_bps() as f64 / 10_000.0;
+        let bid = PoolPrice {
+            pool: amm.address(),
+            price: price * (1.0 - fee),
+        };
+        let ask = PoolPrice {
+            pool: amm.address(),
+            price: price / (1.0 - fee),
+        };
+
+        best = Some(match best {
+            Some((best_bid, best_ask)) => (
+                if bid.price > best_bid.price {
+                    bid
+                } else {
+                    best_bid
+                },
+                if ask.price < best_ask.price {
+                    ask
+                } else {
+                    best_ask
+                },
+            ),
+            None => (bid, ask),
+        });
+    }
+
+    best.map(|(best_bid, best_ask)| PairPrice {
+        base_token,
+        quote_token,
+        best_bid,
+        best_ask,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use crate::amm::{uniswap_v2::UniswapV2Pool, AMM};
+
+    use super::best_pair_price;
+
+    #[test]
+    fn test_best_pair_price() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let link = address!("514910771af9ca656af840dff83e8264ecf986ca");
+
+        let pool = |address, reserve_0: u128, token_b| {
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address,
+                token_a: usdc,
+                token_a_decimals: 6,
+                token_b,
+                token_b_decimals: 18,
+                reserve_0,
+                reserve_1: 1_000_000_000_000_000_000_000,
+                fee: 300,
+                ..Default::default()
+            })
+        };
+        let cheap = address!("1111111111111111111111111111111111111111");
+        let expensive = address!("2222222222222222222222222222222222222222");
+        let amms = [
+            pool(cheap, 1_900_000_000_000, weth),
+            pool(expensive, 2_100_000_000_000, weth),
+            pool(
+                address!("3333333333333333333333333333333333333333"),
+                1_000_000_000_000,
+                link,
+            ),
+        ];
+
+        let price = best_pair_price(&amms, weth, usdc).unwrap();
+        assert_eq!(price.best_bid.pool, expensive);
+        assert_eq!(price.best_ask.pool, cheap);
+        assert!(price.best_bid.price < 2100.0 && price.best_bid.price > 2090.0);
+        assert!(price.best_ask.price > 1900.0 && price.best_ask.price < 1910.0);
+
+        assert!(best_pair_price(
+            &amms,
+            weth,
+            address!("4444444444444444444444444444444444444444")
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,151 @@
+//! Memoization of swap simulations within a block.
+
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, U256};
+
+/// Key of a cached quote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QuoteKey {
+    pub pool: Address,
+    pub token_in: Address,
+    pub amount_bucket: U256,
+    pub block_number: u64,
+}
+
+/// Cache of swap simulation results, see [`super::StateSpaceManager::with_quote_cache`].
+///
+/// Quotes are kept for the block they were simulated in, and the quotes of a pool are invalidated as soon as the pool
+/// receives a log.
+#[derive(Debug, Clone)]
+pub struct QuoteCache {
+    /// Maximum number of cached quotes, further quotes are not cached until the next block.
+    capacity: usize,
+    /// Amounts in are rounded down to a multiple of the bucket size.
+    bucket_size: U256,
+    block_number: u64,
+    quotes: HashMap<QuoteKey, U256>,
+}
+
+impl QuoteCache {
+    /// Returns a cache of up to `capacity` quotes, keyed by the exact amount in.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            bucket_size: U256::from(1),
+            block_number: 0,
+            quotes: HashMap::new(),
+        }
+    }
+
+    /// Rounds amounts in down to a multiple of `bucket_size`, so that close amounts share a quote.
+    ///
+    /// Swaps are simulated with the rounded amount in, see [`QuoteCache::amount_bucket`].
+    pub fn with_bucket_size(mut self, bucket_size: U256) -> Self {
+        self.bucket_size = bucket_size.max(U256::from(1));
+        self
+    }
+
+    /// Returns the block of the cached quotes.
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    /// Returns `amount_in` rounded down to its bucket.
+    pub fn amount_bucket(&self, amount_in: U256) -> U256 {
+        amount_in - amount_in % self.bucket_size
+    }
+
+    /// Returns the key of a quote of `amount_in` of `token_in` in `pool` at the block of the cache.
+    pub fn key(&self, pool: Address, token_in: Address, amount_in: U256) -> QuoteKey {
+        QuoteKey {
+            pool,
+            token_in,
+            amount_bucket: self.amount_bucket(amount_in),
+            block_number: self.block_number,
+        }
+    }
+
+    pub fn get(&self, key: &QuoteKey) -> Option<U256> {
+        self.quotes.get(key).copied()
+    }
+
+    /// Caches `amount_out` under `key`, unless the quote is from another block or the cache is full.
+    pub fn insert(&mut self, key: QuoteKey, amount_out: U256) {
+        if key.block_number == self.block_number && self.quotes.len() < self.capacity {
+            self.quotes.insert(key, amount_out);
+        }
+    }
+
+    /// Drops the quotes of `pool`.
+    pub fn invalidate(&mut self, pool: Address) {
+        self.quotes.retain(|key, _| key.pool != pool);
+    }
+
+    /// Moves the cache to `block_number`, dropping the quotes of previous blocks and of the pools in `amms_updated`.
+    pub fn update(&mut self, block_number: u64, amms_updated: &[Address]) {
+        if block_number != self.block_number {
+            self.block_number = block_number;
+            self.quotes.clear();
+        } else {
+            for pool in amms_updated {
+                self.invalidate(*pool);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quotes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, U256};
+
+    use super::QuoteCache;
+
+    #[test]
+    fn test_quote_cache() {
+        let pool = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let other_pool = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+        let token_in = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+
+        let mut cache = QuoteCache::new(2).with_bucket_size(U256::from(100));
+        cache.update(10, &[]);
+
+        let key = cache.key(pool, token_in, U256::from(1_050));
+        assert_eq!(key.amount_bucket, U256::from(1_000));
+        cache.insert(key, U256::from(5));
+        assert_eq!(
+            cache.get(&cache.key(pool, token_in, U256::from(1_099))),
+            Some(U256::from(5))
+        );
+        assert_eq!(
+            cache.get(&cache.key(pool, token_in, U256::from(1_100))),
+            None
+        );
+
+        let other_key = cache.key(other_pool, token_in, U256::from(1_000));
+        cache.insert(other_key, U256::from(6));
+        cache.insert(
+            cache.key(other_pool, token_in, U256::from(2_000)),
+            U256::from(7),
+        );
+        assert_eq!(cache.len(), 2);
+
+        // A log of the pool in the same block only invalidates its quotes
+        cache.update(10, &[pool]);
+        assert_eq!(cache.get(&key), None);
+        assert_eq!(cache.get(&other_key), Some(U256::from(6)));
+
+        cache.update(11, &[]);
+        assert!(cache.is_empty());
+        cache.insert(other_key, U256::from(6));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,407 @@
+//! Simulation of a bundle of actions across the AMMs of a state space, e.g. to evaluate an MEV bundle.
+//!
+//! A [`Sandbox`] borrows the state space and clones an AMM the first time an action touches it, so that simulating a
+//! bundle costs the AMMs it touches rather than a clone of the whole state space. Actions are applied in order, each
+//! seeing the state left by the previous ones, and the net token balance changes of every actor are recorded.
+
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, I256, U256};
+
+use crate::amm::{AutomatedMarketMaker, AMM};
+
+use super::{error::StateSpaceError, StateSpace};
+
+/// An action of a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxAction {
+    /// Swaps `amount_in` of `token_in` for the other token of `amm`.
+    Swap {
+        actor: Address,
+        amm: Address,
+        token_in: Address,
+        amount_in: U256,
+    },
+    /// Adds `liquidity_delta` to the `tick_lower`..`tick_upper` range of a Uniswap V3 pool, or removes it if negative,
+    /// for the token amounts of the liquidity at the current price.
+    ModifyLiquidity {
+        actor: Address,
+        amm: Address,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    },
+}
+
+/// Copy on write view of a state space, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Sandbox<'a> {
+    state_space: &'a StateSpace,
+    touched: HashMap<Address, AMM>,
+    balance_changes: HashMap<Address, HashMap<Address, I256>>,
+}
+
+impl<'a> Sandbox<'a> {
+    pub fn new(state_space: &'a StateSpace) -> Self {
+        Self {
+            state_space,
+            touched: HashMap::new(),
+            balance_changes: HashMap::new(),
+        }
+    }
+
+    /// Returns the AMM at `address` with the actions applied so far.
+    pub fn get_amm(&self, address: Address) -> Option<&AMM> {
+        self.touched
+            .get(&address)
+            .or_else(|| self.state_space.get(&address))
+    }
+
+    /// Returns the AMMs touched by the actions applied so far, with their updated state.
+    pub fn touched_amms(&self) -> &HashMap<Address, AMM> {
+        &self.touched
+    }
+
+    /// Consumes the sandbox, returning the touched AMMs.
+    pub fn into_touched_amms(self) -> HashMap<Address, AMM> {
+        self.touched
+    }
+
+    /// Returns the net token balance changes of every actor, positive for tokens received.
+    pub fn balance_changes(&self) -> &HashMap<Address, HashMap<Address, I256>> {
+        &self.balance_changes
+    }
+
+    /// Returns the net change of the `token` balance of `actor`.
+    pub fn balance_change(&self, actor: Address, token: Address) -> I256 {
+        self.balance_changes
+            .get(&actor)
+            .and_then(|balances| balances.get(&token))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Applies `actions` in order.
+    ///
+    /// Fails at the first action that cannot be applied, leaving the actions before it applied.
+    pub fn apply_all<'b>(
+        &mut self,
+        actions: impl IntoIterator<Item = &'b SandboxAction>,
+    ) -> Result<(), StateSpaceError> {
+        for action in actions {
+            self.apply(action)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies `action` to the state of its AMM and records the token balance changes of its actor.
+    pub fn apply(&mut self, action: &SandboxAction) -> Result<(), StateSpaceError> {
+        match *action {
+            SandboxAction::Swap {
+                actor,
+                amm,
+                token_in,
+                amount_in,
+            } => {
+                let amm = self.amm_mut(amm)?;
+                if !amm.tokens().contains(&token_in) {
+                    return Err(StateSpaceError::TokenNotInAMM(token_in, amm.address()));
+                }
+
+                let token_out = amm.get_token_out(token_in);
+                let amount_out = amm.simulate_swap_mut(token_in, amount_in)?;
+
+                self.record(actor, token_in, -I256::from_raw(amount_in));
+                self.record(actor, token_out, I256::from_raw(amount_out));
+            }
+            SandboxAction::ModifyLiquidity {
+                actor,
+                amm,
+                tick_lower,
+                tick_upper,
+                liquidity_delta,
+            } => {
+                let AMM::UniswapV3Pool(pool) = self.amm_mut(amm)? else {
+                    return Err(StateSpaceError::UnsupportedLiquidityChange(amm));
+                };
+
+                pool.check_position(tick_lower, tick_upper, liquidity_delta)?;
+
+                let (amount_0, amount_1) = pool.amounts_for_liquidity(
+                    tick_lower,
+                    tick_upper,
+                    liquidity_delta.unsigned_abs(),
+                )?;
+                let (token_0, token_1) = (pool.token0(), pool.token1());
+                pool.modify_position(tick_lower, tick_upper, liquidity_delta);
+
+                let (amount_0, amount_1) = if liquidity_delta < 0 {
+                    (I256::from_raw(amount_0), I256::from_raw(amount_1))
+                } else {
+                    (-I256::from_raw(amount_0), -I256::from_raw(amount_1))
+                };
+                self.record(actor, token_0, amount_0);
+                self.record(actor, token_1, amount_1);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the touched AMM at `address`, cloning it from the state space the first time it is touched.
+    fn amm_mut(&mut self, address: Address) -> Result<&mut AMM, StateSpaceError> {
+        if !self.touched.contains_key(&address) {
+            let amm = self
+                .state_space
+                .get(&address)
+                .ok_or(StateSpaceError::AMMNotFound(address))?;
+            self.touched.insert(address, amm.clone());
+        }
+
+        Ok(self
+            .touched
+            .get_mut(&address)
+            .expect("touched AMM was inserted"))
+    }
+
+    fn record(&mut self, actor: Address, token: Address, amount: I256) {
+        *self
+            .balance_changes
+            .entry(actor)
+            .or_default()
+            .entry(token)
+            .or_default() += amount;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, I256, U256};
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, AMM},
+        errors::SwapSimulationError,
+        state_space::{error::StateSpaceError, initialize_state_space},
+        test_utils::{usdc_weth_pool, USDC, WETH},
+    };
+
+    use super::{Sandbox, SandboxAction};
+
+    fn uniswap_v2_pool(address: Address) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address,
+            reserve_1: 1_000_000_000_000,
+            ..usdc_weth_pool()
+        })
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let pool_a = Address::with_last_byte(1);
+        let pool_b = Address::with_last_byte(2);
+        let untouched = Address::with_last_byte(3);
+        let state_space = initialize_state_space(vec![
+            uniswap_v2_pool(pool_a),
+            uniswap_v2_pool(pool_b),
+            uniswap_v2_pool(untouched),
+        ]);
+
+        let searcher = Address::with_last_byte(10);
+        let victim = Address::with_last_byte(11);
+        let amount_in = U256::from(1_000_000_000);
+
+        let mut sandbox = Sandbox::new(&state_space);
+        sandbox
+            .apply_all(&[
+                SandboxAction::Swap {
+                    actor: searcher,
+                    amm: pool_a,
+                    token_in: USDC,
+                    amount_in,
+                },
+                SandboxAction::Swap {
+                    actor: victim,
+                    amm: pool_a,
+                    token_in: USDC,
+                    amount_in,
+                },
+                SandboxAction::Swap {
+                    actor: searcher,
+                    amm: pool_b,
+                    token_in: USDC,
+                    amount_in,
+                },
+            ])
+            .unwrap();
+
+        // Only the touched pools are cloned, the state space is left as is
+        assert_eq!(sandbox.touched_amms().len(), 2);
+        assert!(!sandbox.touched_amms().contains_key(&untouched));
+        assert_eq!(
+            state_space[&pool_a].simulate_swap(USDC, amount_in).unwrap(),
+            uniswap_v2_pool(pool_a)
+                .simulate_swap(USDC, amount_in)
+                .unwrap()
+        );
+        assert!(
+            sandbox
+                .get_amm(pool_a)
+                .unwrap()
+                .simulate_swap(USDC, amount_in)
+                .unwrap()
+                < state_space[&pool_a].simulate_swap(USDC, amount_in).unwrap()
+        );
+
+        // The victim swaps after the searcher, at a worse price
+        let searcher_weth = sandbox.balance_change(searcher, WETH);
+        let victim_weth = sandbox.balance_change(victim, WETH);
+        assert!(victim_weth > I256::ZERO);
+        assert!(victim_weth < searcher_weth / I256::try_from(2).unwrap());
+        assert_eq!(
+            sandbox.balance_change(searcher, USDC),
+            -I256::from_raw(amount_in * U256::from(2))
+        );
+        assert!(matches!(
+            sandbox.apply(&SandboxAction::Swap {
+                actor: searcher,
+                amm: pool_a,
+                token_in: Address::ZERO,
+                amount_in,
+            }),
+            Err(StateSpaceError::TokenNotInAMM(..))
+        ));
+        assert!(matches!(
+            sandbox.apply(&SandboxAction::ModifyLiquidity {
+                actor: searcher,
+                amm: pool_a,
+                tick_lower: -60,
+                tick_upper: 60,
+                liquidity_delta: 1,
+            }),
+            Err(StateSpaceError::UnsupportedLiquidityChange(_))
+        ));
+        assert!(matches!(
+            sandbox.apply(&SandboxAction::Swap {
+                actor: searcher,
+                amm: Address::ZERO,
+                token_in: USDC,
+                amount_in,
+            }),
+            Err(StateSpaceError::AMMNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_sandbox_modify_liquidity() {
+        let address = Address::with_last_byte(1);
+        let state_space = initialize_state_space(vec![AMM::UniswapV3Pool(UniswapV3Pool {
+            address,
+            token_a: USDC,
+            token_b: WETH,
+            fee: 500,
+            tick_spacing: 10,
+            sqrt_price: U256::from(1) << 96,
+            ..Default::default()
+        })]);
+        let actor = Address::with_last_byte(10);
+
+        let mut sandbox = Sandbox::new(&state_space);
+        sandbox
+            .apply(&SandboxAction::ModifyLiquidity {
+                actor,
+                amm: address,
+                tick_lower: -100,
+                tick_upper: 100,
+                liquidity_delta: 1_000_000_000,
+            })
+            .unwrap();
+
+        let AMM::UniswapV3Pool(pool) = sandbox.get_amm(address).unwrap() else {
+            panic!("Unexpected AMM variant")
+        };
+        assert_eq!(pool.liquidity, 1_000_000_000);
+        assert!(sandbox.balance_change(actor, USDC) < I256::ZERO);
+        assert!(sandbox.balance_change(actor, WETH) < I256::ZERO);
+
+        sandbox
+            .apply(&SandboxAction::ModifyLiquidity {
+                actor,
+                amm: address,
+                tick_lower: -100,
+                tick_upper: 100,
+                liquidity_delta: -1_000_000_000,
+            })
+            .unwrap();
+        assert_eq!(sandbox.balance_change(actor, USDC), I256::ZERO);
+        assert_eq!(sandbox.balance_change(actor, WETH), I256::ZERO);
+
+        assert!(sandbox
+            .apply(&SandboxAction::ModifyLiquidity {
+                actor,
+                amm: address,
+                tick_lower: -100,
+                tick_upper: 100,
+                liquidity_delta: -1,
+            })
+            .is_err());
+
+        // Invalid and misaligned ranges are rejected
+        for (tick_lower, tick_upper) in [(100, -100), (-105, 100), (-100, i32::MAX)] {
+            assert!(matches!(
+                sandbox.apply(&SandboxAction::ModifyLiquidity {
+                    actor,
+                    amm: address,
+                    tick_lower,
+                    tick_upper,
+                    liquidity_delta: 1,
+                }),
+                Err(StateSpaceError::SwapSimulationError(_))
+            ));
+        }
+
+        // Removing liquidity from ticks of a range out of the current price without liquidity is rejected
+        assert!(matches!(
+            sandbox.apply(&SandboxAction::ModifyLiquidity {
+                actor,
+                amm: address,
+                tick_lower: 100,
+                tick_upper: 200,
+                liquidity_delta: -1,
+            }),
+            Err(StateSpaceError::SwapSimulationError(
+                SwapSimulationError::LiquidityUnderflow
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_sandbox_modify_liquidity_token_order() {
+        // Token a is token 1 of the pool, as the token addresses are not sorted
+        let address = Address::with_last_byte(1);
+        let state_space = initialize_state_space(vec![AMM::UniswapV3Pool(UniswapV3Pool {
+            address,
+            token_a: WETH,
+            token_b: USDC,
+            fee: 500,
+            tick_spacing: 10,
+            sqrt_price: U256::from(1) << 96,
+            ..Default::default()
+        })]);
+        let actor = Address::with_last_byte(10);
+
+        // A range above the current price is backed by token 0 only
+        let mut sandbox = Sandbox::new(&state_space);
+        sandbox
+            .apply(&SandboxAction::ModifyLiquidity {
+                actor,
+                amm: address,
+                tick_lower: 100,
+                tick_upper: 200,
+                liquidity_delta: 1_000_000_000,
+            })
+            .unwrap();
+        assert!(sandbox.balance_change(actor, USDC) < I256::ZERO);
+        assert_eq!(sandbox.balance_change(actor, WETH), I256::ZERO);
+    }
+}
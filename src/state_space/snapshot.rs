@@ -0,0 +1,156 @@
+//! Immutable snapshots of a state space, published by the sync task and read without locks.
+
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::primitives::{Address, U256};
+use arc_swap::ArcSwap;
+
+use crate::amm::{AutomatedMarketMaker, AMM};
+
+use super::{error::StateSpaceError, StateSpace};
+
+/// The AMMs of a state space as of a block.
+///
+/// AMMs are shared between snapshots, so publishing a snapshot only deep clones the AMMs that changed. The map itself
+/// is still copied, see [`SnapshotStateSpace::publish`].
+#[derive(Debug, Clone, Default)]
+pub struct StateSnapshot {
+    pub block_number: u64,
+    pub amms: HashMap<Address, Arc<AMM>>,
+}
+
+impl StateSnapshot {
+    pub fn get_amm(&self, amm_address: Address) -> Option<&AMM> {
+        self.amms.get(&amm_address).map(Arc::as_ref)
+    }
+
+    /// Locally simulates a swap in the AMM at `amm_address`.
+    pub fn simulate_swap(
+        &self,
+        amm_address: Address,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, StateSpaceError> {
+        Ok(self
+            .get_amm(amm_address)
+            .ok_or(StateSpaceError::AMMNotFound(amm_address))?
+            .simulate_swap(token_in, amount_in)?)
+    }
+}
+
+/// Latest [`StateSnapshot`] of a state space, see [`super::StateSpaceManager::with_snapshots`].
+///
+/// Readers load the latest snapshot without waiting on the sync task, and keep a consistent view of the state space
+/// for as long as they hold it. Clones share the same snapshots.
+#[derive(Debug, Clone)]
+pub struct SnapshotStateSpace {
+    snapshot: Arc<ArcSwap<StateSnapshot>>,
+}
+
+impl SnapshotStateSpace {
+    pub fn new(state: &StateSpace, block_number: u64) -> Self {
+        Self {
+            snapshot: Arc::new(ArcSwap::from_pointee(snapshot_of(state, block_number))),
+        }
+    }
+
+    /// Returns the latest snapshot.
+    pub fn load(&self) -> Arc<StateSnapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// Publishes a snapshot as of `block_number`, copying the AMMs at `amms_updated` from `state`.
+    ///
+    /// AMMs at `amms_updated` that are not in `state` are removed from the snapshot. The map of the previous snapshot
+    /// is cloned, which is O(N) in the number of AMMs of the state space even though only the pointers to the unchanged
+    /// AMMs are copied.
+    pub fn publish(&self, state: &StateSpace, amms_updated: &[Address], block_number: u64) {
+        let mut snapshot = StateSnapshot {
+            block_number,
+            amms: self.snapshot.load().amms.clone(),
+        };
+
+        for amm_address in amms_updated {
+            match state.get(amm_address) {
+                Some(amm) => {
+                    snapshot.amms.insert(*amm_address, Arc::new(amm.clone()));
+                }
+                None => {
+                    snapshot.amms.remove(amm_address);
+                }
+            }
+        }
+
+        self.snapshot.store(Arc::new(snapshot));
+    }
+
+    /// Publishes a snapshot of all of `state` as of `block_number`.
+    pub fn publish_all(&self, state: &StateSpace, block_number: u64) {
+        self.snapshot
+            .store(Arc::new(snapshot_of(state, block_number)));
+    }
+}
+
+fn snapshot_of(state: &StateSpace, block_number: u64) -> StateSnapshot {
+    StateSnapshot {
+        block_number,
+        amms: state
+            .iter()
+            .map(|(address, amm)| (*address, Arc::new(amm.clone())))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, AMM},
+        state_space::initialize_state_space,
+    };
+
+    use super::SnapshotStateSpace;
+
+    #[test]
+    fn test_publish_snapshots() {
+        let pool = UniswapV2Pool {
+            address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            reserve_0: 100,
+            reserve_1: 100,
+            ..Default::default()
+        };
+        let other_pool = UniswapV2Pool {
+            address: address!("a2107FA5B38d9bbd2C461D6EDf11B11A50F6b974"),
+            ..pool.clone()
+        };
+
+        let mut state = initialize_state_space(vec![
+            AMM::UniswapV2Pool(pool.clone()),
+            AMM::UniswapV2Pool(other_pool.clone()),
+        ]);
+        let snapshots = SnapshotStateSpace::new(&state, 10);
+        let snapshot_10 = snapshots.load();
+
+        if let Some(AMM::UniswapV2Pool(pool)) = state.get_mut(&pool.address) {
+            pool.reserve_0 = 200;
+        }
+        state.remove(&other_pool.address);
+        snapshots.publish(&state, &[pool.address, other_pool.address], 11);
+
+        // Readers holding a snapshot keep their view
+        let Some(AMM::UniswapV2Pool(pool_10)) = snapshot_10.get_amm(pool.address) else {
+            unreachable!()
+        };
+        assert_eq!(pool_10.reserve_0, 100);
+        assert!(snapshot_10.get_amm(other_pool.address).is_some());
+
+        let snapshot_11 = snapshots.load();
+        assert_eq!(snapshot_11.block_number, 11);
+        let Some(AMM::UniswapV2Pool(pool_11)) = snapshot_11.get_amm(pool.address) else {
+            unreachable!()
+        };
+        assert_eq!(pool_11.reserve_0, 200);
+        assert!(snapshot_11.get_amm(other_pool.address).is_none());
+    }
+}
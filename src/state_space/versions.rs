@@ -0,0 +1,74 @@
+//! Version numbers of the AMMs of a state space, bumped on every state change.
+
+use std::collections::HashMap;
+
+use alloy::primitives::Address;
+use tokio::sync::watch;
+
+/// Version of each AMM of a state space, see [`super::StateSpaceManager::subscribe_amm`].
+///
+/// Versions start at zero and are bumped every time the AMM changes, so a consumer can tell whether an AMM changed
+/// since it last read it by comparing versions.
+#[derive(Debug, Default)]
+pub struct AmmVersions {
+    versions: HashMap<Address, watch::Sender<u64>>,
+}
+
+impl AmmVersions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the version of the AMM at `amm_address`.
+    pub fn version(&self, amm_address: Address) -> u64 {
+        self.versions
+            .get(&amm_address)
+            .map(|version| *version.borrow())
+            .unwrap_or_default()
+    }
+
+    /// Bumps the version of the AMM at `amm_address`, notifying its subscribers.
+    pub fn bump(&mut self, amm_address: Address) {
+        self.versions
+            .entry(amm_address)
+            .or_insert_with(|| watch::Sender::new(0))
+            .send_modify(|version| *version += 1);
+    }
+
+    /// Returns a receiver of the version of the AMM at `amm_address`, which is notified when the version is bumped.
+    pub fn subscribe(&mut self, amm_address: Address) -> watch::Receiver<u64> {
+        self.versions
+            .entry(amm_address)
+            .or_insert_with(|| watch::Sender::new(0))
+            .subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::AmmVersions;
+
+    #[test]
+    fn test_amm_versions() {
+        let pool = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let other_pool = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+
+        let mut versions = AmmVersions::new();
+        assert_eq!(versions.version(pool), 0);
+
+        let mut receiver = versions.subscribe(pool);
+        assert!(!receiver.has_changed().unwrap());
+
+        versions.bump(pool);
+        versions.bump(other_pool);
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(*receiver.borrow_and_update(), 1);
+        assert_eq!(versions.version(other_pool), 1);
+
+        versions.bump(pool);
+        versions.bump(pool);
+        assert_eq!(*receiver.borrow_and_update(), 3);
+    }
+}
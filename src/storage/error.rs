@@ -0,0 +1,16 @@
+use alloy::primitives::Address;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    SqliteError(#[from] rusqlite::Error),
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::error::Error),
+    #[error("Invalid tick data for AMM {0}")]
+    InvalidTickData(Address),
+    #[error("No synced block in the store")]
+    EmptyStore,
+}
@@ -0,0 +1,68 @@
+//! Persistent storage of pool state and sync progress.
+//!
+//! [`StateStore`] persists AMMs incrementally per block, so that a state space can be hydrated on startup
+//! without re-syncing from a file checkpoint.
+
+pub mod error;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use alloy::primitives::Address;
+
+use crate::amm::AMM;
+
+use self::error::StorageError;
+
+#[cfg(feature = "state-space")]
+use crate::state_space::StateSpaceManager;
+#[cfg(feature = "state-space")]
+use alloy::{network::Network, providers::Provider, transports::Transport};
+#[cfg(feature = "state-space")]
+use std::sync::Arc;
+
+/// A persistent store of AMMs and sync progress.
+pub trait StateStore {
+    /// Inserts or replaces `amms` and records `block_number` as the latest synced block, atomically.
+    ///
+    /// Only the AMMs that changed in the block need to be passed.
+    fn commit_block(&mut self, amms: &[AMM], block_number: u64) -> Result<(), StorageError>;
+
+    /// Removes the AMMs at `addresses` from the store.
+    fn remove_amms(&mut self, addresses: &[Address]) -> Result<(), StorageError>;
+
+    /// Returns the AMM at `address`, if it is in the store.
+    fn load_amm(&self, address: Address) -> Result<Option<AMM>, StorageError>;
+
+    /// Returns all AMMs in the store.
+    fn load_amms(&self) -> Result<Vec<AMM>, StorageError>;
+
+    /// Returns the latest block committed to the store, `None` if the store is empty.
+    fn latest_synced_block(&self) -> Result<Option<u64>, StorageError>;
+}
+
+/// Hydrates a [`StateSpaceManager`] from the AMMs and sync progress in `store`.
+#[cfg(feature = "state-space")]
+pub fn hydrate_state_space<S, T, N, P>(
+    store: &S,
+    stream_buffer: usize,
+    state_change_buffer: usize,
+    provider: Arc<P>,
+) -> Result<StateSpaceManager<T, N, P>, StorageError>
+where
+    S: StateStore,
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    let latest_synced_block = store
+        .latest_synced_block()?
+        .ok_or(StorageError::EmptyStore)?;
+
+    Ok(StateSpaceManager::new(
+        store.load_amms()?,
+        latest_synced_block,
+        stream_buffer,
+        state_change_buffer,
+        provider,
+    ))
+}
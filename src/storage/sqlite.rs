@@ -0,0 +1,326 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use alloy::primitives::{Address, U256};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::amm::{
+    uniswap_v3::{Info, UniswapV3Pool},
+    AutomatedMarketMaker, AMM,
+};
+
+use super::{error::StorageError, StateStore};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS amms (
+        address BLOB PRIMARY KEY,
+        block_number INTEGER NOT NULL,
+        amm TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS ticks (
+        address BLOB NOT NULL,
+        tick INTEGER NOT NULL,
+        liquidity_gross TEXT NOT NULL,
+        liquidity_net TEXT NOT NULL,
+        initialized INTEGER NOT NULL,
+        PRIMARY KEY (address, tick)
+    );
+    CREATE TABLE IF NOT EXISTS tick_bitmap (
+        address BLOB NOT NULL,
+        word INTEGER NOT NULL,
+        bitmap BLOB NOT NULL,
+        PRIMARY KEY (address, word)
+    );
+    CREATE TABLE IF NOT EXISTS sync_progress (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        block_number INTEGER NOT NULL
+    );
+";
+
+/// A [`StateStore`] backed by a SQLite database.
+///
+/// Uniswap V3 tick data is stored in separate tables, so that an update only rewrites the ticks of the changed pools.
+#[derive(Debug)]
+pub struct SqliteStateStore {
+    connection: Connection,
+}
+
+impl SqliteStateStore {
+    /// Opens the database at `path`, creating the tables if they do not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StorageError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Opens an in-memory database.
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, StorageError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    fn load_tick_data(&self, pool: &mut UniswapV3Pool) -> Result<(), StorageError> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT tick, liquidity_gross, liquidity_net, initialized FROM ticks WHERE address = ?1",
+        )?;
+        let ticks = statement
+            .query_map(params![pool.address.as_slice()], |row| {
+                Ok((
+                    row.get::<_, i32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, bool>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        pool.ticks = ticks
+            .into_iter()
+            .map(|(tick, liquidity_gross, liquidity_net, initialized)| {
+                Some((
+                    tick,
+                    Info::new(
+                        liquidity_gross.parse().ok()?,
+                        liquidity_net.parse().ok()?,
+                        initialized,
+                    ),
+                ))
+            })
+            .collect::<Option<BTreeMap<i32, Info>>>()
+            .ok_or(StorageError::InvalidTickData(pool.address))?;
+
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT word, bitmap FROM tick_bitmap WHERE address = ?1")?;
+        let words = statement
+            .query_map(params![pool.address.as_slice()], |row| {
+                Ok((row.get::<_, i16>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        pool.tick_bitmap = words
+            .into_iter()
+            .map(|(word, bitmap)| Some((word, U256::try_from_be_slice(&bitmap)?)))
+            .collect::<Option<HashMap<i16, U256>>>()
+            .ok_or(StorageError::InvalidTickData(pool.address))?;
+
+        Ok(())
+    }
+
+    fn load_amm_from_json(&self, amm: &str) -> Result<AMM, StorageError> {
+        let mut amm: AMM = serde_json::from_str(amm)?;
+        if let AMM::UniswapV3Pool(pool) = &mut amm {
+            self.load_tick_data(pool)?;
+        }
+
+        Ok(amm)
+    }
+}
+
+impl StateStore for SqliteStateStore {
+    fn commit_block(&mut self, amms: &[AMM], block_number: u64) -> Result<(), StorageError> {
+        let transaction = self.connection.transaction()?;
+
+        for amm in amms {
+            let address = amm.address();
+
+            let amm = match amm {
+                AMM::UniswapV3Pool(pool) => {
+                    transaction.execute(
+                        "DELETE FROM ticks WHERE address = ?1",
+                        params![address.as_slice()],
+                    )?;
+                    transaction.execute(
+                        "DELETE FROM tick_bitmap WHERE address = ?1",
+                        params![address.as_slice()],
+                    )?;
+
+                    let mut insert_tick = transaction.prepare_cached(
+                        "INSERT INTO ticks (address, tick, liquidity_gross, liquidity_net, initialized) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    )?;
+                    for (tick, info) in pool.ticks.iter() {
+                        insert_tick.execute(params![
+                            address.as_slice(),
+                            tick,
+                            info.liquidity_gross.to_string(),
+                            info.liquidity_net.to_string(),
+                            info.initialized,
+                        ])?;
+                    }
+
+                    let mut insert_word = transaction.prepare_cached(
+                        "INSERT INTO tick_bitmap (address, word, bitmap) VALUES (?1, ?2, ?3)",
+                    )?;
+                    for (word, bitmap) in pool.tick_bitmap.iter() {
+                        insert_word.execute(params![
+                            address.as_slice(),
+                            word,
+                            bitmap.to_be_bytes::<32>().as_slice(),
+                        ])?;
+                    }
+
+                    // Tick data is stored in its own tables
+                    AMM::UniswapV3Pool(UniswapV3Pool {
+                        tick_bitmap: HashMap::new(),
+                        ticks: BTreeMap::new(),
+                        ..pool.clone()
+                    })
+                }
+                amm => amm.clone(),
+            };
+
+            transaction.execute(
+                "INSERT OR REPLACE INTO amms (address, block_number, amm) VALUES (?1, ?2, ?3)",
+                params![
+                    address.as_slice(),
+                    block_number as i64,
+                    serde_json::to_string(&amm)?
+                ],
+            )?;
+        }
+
+        transaction.execute(
+            "INSERT OR REPLACE INTO sync_progress (id, block_number) VALUES (0, ?1)",
+            params![block_number as i64],
+        )?;
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn remove_amms(&mut self, addresses: &[Address]) -> Result<(), StorageError> {
+        let transaction = self.connection.transaction()?;
+
+        for address in addresses {
+            for table in ["amms", "ticks", "tick_bitmap"] {
+                transaction.execute(
+                    &format!("DELETE FROM {table} WHERE address = ?1"),
+                    params![address.as_slice()],
+                )?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn load_amm(&self, address: Address) -> Result<Option<AMM>, StorageError> {
+        let amm = self
+            .connection
+            .query_row(
+                "SELECT amm FROM amms WHERE address = ?1",
+                params![address.as_slice()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        amm.map(|amm| self.load_amm_from_json(&amm)).transpose()
+    }
+
+    fn load_amms(&self) -> Result<Vec<AMM>, StorageError> {
+        let mut statement = self.connection.prepare("SELECT amm FROM amms")?;
+        let amms = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+
+        amms.iter()
+            .map(|amm| self.load_amm_from_json(amm))
+            .collect()
+    }
+
+    fn latest_synced_block(&self) -> Result<Option<u64>, StorageError> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT block_number FROM sync_progress WHERE id = 0",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .map(|block_number| block_number as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, AMM},
+        storage::StateStore,
+    };
+
+    use super::SqliteStateStore;
+
+    fn uniswap_v3_pool() -> UniswapV3Pool {
+        let mut pool = UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            tick_spacing: 10,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-887270, 887270, 1_000_000);
+        pool.modify_position(-100, 200, 500);
+        pool
+    }
+
+    #[test]
+    fn test_commit_and_load() {
+        let mut store = SqliteStateStore::open_in_memory().unwrap();
+        assert_eq!(store.latest_synced_block().unwrap(), None);
+
+        let uniswap_v2_pool = AMM::UniswapV2Pool(UniswapV2Pool {
+            address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            reserve_0: 1_000,
+            reserve_1: 2_000,
+            ..Default::default()
+        });
+        let uniswap_v3_pool = uniswap_v3_pool();
+
+        store
+            .commit_block(
+                &[
+                    uniswap_v2_pool.clone(),
+                    AMM::UniswapV3Pool(uniswap_v3_pool.clone()),
+                ],
+                100,
+            )
+            .unwrap();
+        assert_eq!(store.latest_synced_block().unwrap(), Some(100));
+        assert_eq!(store.load_amms().unwrap().len(), 2);
+
+        let Some(AMM::UniswapV3Pool(loaded)) = store.load_amm(uniswap_v3_pool.address).unwrap()
+        else {
+            panic!("Uniswap V3 pool not found");
+        };
+        assert_eq!(loaded.tick_bitmap, uniswap_v3_pool.tick_bitmap);
+        assert_eq!(
+            loaded.ticks.keys().collect::<Vec<_>>(),
+            uniswap_v3_pool.ticks.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(loaded.ticks[&887270].liquidity_net, -1_000_000);
+
+        // Incremental update of a single pool
+        let mut updated = uniswap_v3_pool.clone();
+        updated.modify_position(-100, 200, -500);
+        store
+            .commit_block(&[AMM::UniswapV3Pool(updated.clone())], 101)
+            .unwrap();
+
+        let Some(AMM::UniswapV3Pool(loaded)) = store.load_amm(updated.address).unwrap() else {
+            panic!("Uniswap V3 pool not found");
+        };
+        assert_eq!(loaded.ticks.len(), updated.ticks.len());
+        assert_eq!(store.latest_synced_block().unwrap(), Some(101));
+
+        store.remove_amms(&[uniswap_v2_pool.address()]).unwrap();
+        assert!(store.load_amm(uniswap_v2_pool.address()).unwrap().is_none());
+        assert_eq!(store.load_amms().unwrap().len(), 1);
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashSet,
     fs::read_to_string,
     panic::resume_unwind,
     sync::Arc,
@@ -13,10 +14,12 @@ use tokio::task::JoinHandle;
 
 use crate::{
     amm::{
+        batch_request::populate_amms,
         factory::{AutomatedMarketMakerFactory, Factory},
+        metadata::AmmMetadataMap,
         uniswap_v2::factory::UniswapV2Factory,
         uniswap_v3::factory::UniswapV3Factory,
-        AMM,
+        AutomatedMarketMaker, AMM,
     },
     errors::{AMMError, CheckpointError},
     filters,
@@ -24,12 +27,25 @@ use crate::{
 
 use super::amms_are_congruent;
 
+/// Version of the checkpoint schema, bumped on any change to the serialized checkpoints or AMMs that serde can not
+/// default, along with a migration in [`MIGRATIONS`].
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// Migrations of the checkpoints of each version to the next version, applied by [`migrate_checkpoint`].
+const MIGRATIONS: [fn(&mut serde_json::Value); CHECKPOINT_VERSION as usize] = [migrate_v0_to_v1];
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Checkpoint {
+    /// Version of the checkpoint schema, checkpoints written before the schema was versioned are version 0.
+    #[serde(default)]
+    pub version: u32,
     pub timestamp: usize,
     pub block_number: u64,
     pub factories: Vec<Factory>,
     pub amms: Vec<AMM>,
+    /// User metadata of the AMMs, see [`AmmMetadata`](crate::amm::metadata::AmmMetadata).
+    #[serde(default)]
+    pub metadata: AmmMetadataMap,
 }
 
 impl Checkpoint {
@@ -40,20 +56,77 @@ impl Checkpoint {
         amms: Vec<AMM>,
     ) -> Checkpoint {
         Checkpoint {
+            version: CHECKPOINT_VERSION,
             timestamp,
             block_number,
             factories,
             amms,
+            metadata: AmmMetadataMap::new(),
         }
     }
+
+    /// Attaches the user metadata of the AMMs to the checkpoint.
+    pub fn with_metadata(mut self, metadata: AmmMetadataMap) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+/// Reads a JSON checkpoint from `path`, migrating it to [`CHECKPOINT_VERSION`].
+pub fn read_checkpoint(path: &str) -> Result<Checkpoint, CheckpointError> {
+    migrate_checkpoint(serde_json::from_str(read_to_string(path)?.as_str())?)
 }
 
+/// Migrates a JSON checkpoint of any version up to [`CHECKPOINT_VERSION`] and deserializes it.
+///
+/// Returns an error for checkpoints written by a newer version of the crate.
+pub fn migrate_checkpoint(
+    mut checkpoint: serde_json::Value,
+) -> Result<Checkpoint, CheckpointError> {
+    let version = checkpoint
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or_default();
+    if version > CHECKPOINT_VERSION as u64 {
+        return Err(CheckpointError::UnsupportedCheckpointVersion(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut checkpoint);
+    }
+    checkpoint["version"] = CHECKPOINT_VERSION.into();
+
+    Ok(serde_json::from_value(checkpoint)?)
+}
+
+/// Version 1 adds the version field, the AMM fields added before it (transfer taxes, stats, factories and tick
+/// windows) are defaulted by serde.
+fn migrate_v0_to_v1(_checkpoint: &mut serde_json::Value) {}
+
 // Get all pairs from last synced block and sync reserve values for each Dex in the `dexes` vec.
 pub async fn sync_amms_from_checkpoint<T, N, P>(
     path_to_checkpoint: &str,
     step: u64,
     provider: Arc<P>,
 ) -> Result<(Vec<Factory>, Vec<AMM>), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    let (factories, amms, _) =
+        sync_amms_and_metadata_from_checkpoint(path_to_checkpoint, step, provider).await?;
+
+    Ok((factories, amms))
+}
+
+/// Syncs the AMMs of a checkpoint like [`sync_amms_from_checkpoint`], also returning the user metadata of the
+/// checkpoint AMMs that are still synced.
+pub async fn sync_amms_and_metadata_from_checkpoint<T, N, P>(
+    path_to_checkpoint: &str,
+    step: u64,
+    provider: Arc<P>,
+) -> Result<(Vec<Factory>, Vec<AMM>, AmmMetadataMap), AMMError>
 where
     T: Transport + Clone,
     N: Network,
@@ -61,11 +134,11 @@ where
 {
     let current_block = provider.get_block_number().await?;
 
-    let checkpoint: Checkpoint =
-        serde_json::from_str(read_to_string(path_to_checkpoint)?.as_str())?;
+    let checkpoint = read_checkpoint(path_to_checkpoint)?;
 
     // Sort all of the pools from the checkpoint into uniswap_v2_pools and uniswap_v3_pools pools so we can sync them concurrently
-    let (uniswap_v2_pools, uniswap_v3_pools, erc_4626_pools) = sort_amms(checkpoint.amms);
+    let (uniswap_v2_pools, uniswap_v3_pools, erc_4626_pools, factoryless_amms) =
+        sort_amms(checkpoint.amms);
 
     let mut aggregated_amms = vec![];
     let mut handles = vec![];
@@ -102,6 +175,17 @@ where
         );
     }
 
+    // Populate the AMMs without a factory to sync them with directly
+    if !factoryless_amms.is_empty() {
+        let provider = provider.clone();
+        handles.push(tokio::spawn(async move {
+            let mut amms = factoryless_amms;
+            populate_amms(&mut amms, Some(current_block), provider).await?;
+
+            Ok::<_, AMMError>(filters::filter_empty_amms(amms))
+        }));
+    }
+
     // Sync all pools from the since synced block
     handles.extend(
         get_new_amms_from_range(
@@ -128,15 +212,19 @@ where
         }
     }
 
+    // Metadata of the AMMs filtered out while syncing is dropped
+    let metadata = retain_metadata(checkpoint.metadata, &aggregated_amms);
+
     //update the sync checkpoint
-    construct_checkpoint(
+    construct_checkpoint_with_metadata(
         checkpoint.factories.clone(),
         &aggregated_amms,
+        &metadata,
         current_block,
         path_to_checkpoint,
     )?;
 
-    Ok((checkpoint.factories, aggregated_amms))
+    Ok((checkpoint.factories, aggregated_amms, metadata))
 }
 
 pub async fn get_new_amms_from_range<T, N, P>(
@@ -200,7 +288,13 @@ where
             0,
         ))),
 
-        AMM::ERC4626Vault(_) => None,
+        AMM::ERC4626Vault(_)
+        | AMM::CurveV2Pool(_)
+        | AMM::RateAdapter(_)
+        | AMM::BancorV3Pool(_)
+        | AMM::GmxMarket(_)
+        | AMM::AmbientPool(_)
+        | AMM::ConstantProductPool(_) => None,
     };
 
     // Spawn a new thread to get all pools and sync data for each dex
@@ -225,19 +319,32 @@ where
     })
 }
 
-pub fn sort_amms(amms: Vec<AMM>) -> (Vec<AMM>, Vec<AMM>, Vec<AMM>) {
+/// Sorts AMMs into Uniswap V2 pools, Uniswap V3 pools, ERC4626 vaults and the other AMMs, which have no factory.
+pub fn sort_amms(amms: Vec<AMM>) -> (Vec<AMM>, Vec<AMM>, Vec<AMM>, Vec<AMM>) {
     let mut uniswap_v2_pools = vec![];
     let mut uniswap_v3_pools = vec![];
     let mut erc_4626_vaults = vec![];
+    let mut factoryless_amms = vec![];
     for amm in amms {
         match amm {
             AMM::UniswapV2Pool(_) => uniswap_v2_pools.push(amm),
             AMM::UniswapV3Pool(_) => uniswap_v3_pools.push(amm),
             AMM::ERC4626Vault(_) => erc_4626_vaults.push(amm),
+            AMM::CurveV2Pool(_)
+            | AMM::RateAdapter(_)
+            | AMM::BancorV3Pool(_)
+            | AMM::GmxMarket(_)
+            | AMM::AmbientPool(_)
+            | AMM::ConstantProductPool(_) => factoryless_amms.push(amm),
         }
     }
 
-    (uniswap_v2_pools, uniswap_v3_pools, erc_4626_vaults)
+    (
+        uniswap_v2_pools,
+        uniswap_v3_pools,
+        erc_4626_vaults,
+        factoryless_amms,
+    )
 }
 
 pub async fn get_new_pools_from_range<T, N, P>(
@@ -284,13 +391,31 @@ pub fn construct_checkpoint(
     amms: &[AMM],
     latest_block: u64,
     checkpoint_path: &str,
+) -> Result<(), CheckpointError> {
+    construct_checkpoint_with_metadata(
+        factories,
+        amms,
+        &AmmMetadataMap::new(),
+        latest_block,
+        checkpoint_path,
+    )
+}
+
+/// Writes a checkpoint like [`construct_checkpoint`], with the user metadata of `amms`.
+pub fn construct_checkpoint_with_metadata(
+    factories: Vec<Factory>,
+    amms: &[AMM],
+    metadata: &AmmMetadataMap,
+    latest_block: u64,
+    checkpoint_path: &str,
 ) -> Result<(), CheckpointError> {
     let checkpoint = Checkpoint::new(
         SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64() as usize,
         latest_block,
         factories,
         amms.to_vec(),
-    );
+    )
+    .with_metadata(retain_metadata(metadata.clone(), amms));
 
     std::fs::write(checkpoint_path, serde_json::to_string_pretty(&checkpoint)?)?;
 
@@ -299,6 +424,335 @@ pub fn construct_checkpoint(
 
 // Deconstructs the checkpoint into a Vec<AMM>
 pub fn deconstruct_checkpoint(checkpoint_path: &str) -> Result<(Vec<AMM>, u64), CheckpointError> {
-    let checkpoint: Checkpoint = serde_json::from_str(read_to_string(checkpoint_path)?.as_str())?;
-    Ok((checkpoint.amms, checkpoint.block_number))
+    let (amms, _, block_number) = deconstruct_checkpoint_with_metadata(checkpoint_path)?;
+    Ok((amms, block_number))
+}
+
+/// Deconstructs the checkpoint into its AMMs, the user metadata of the AMMs and its block number.
+pub fn deconstruct_checkpoint_with_metadata(
+    checkpoint_path: &str,
+) -> Result<(Vec<AMM>, AmmMetadataMap, u64), CheckpointError> {
+    let checkpoint = read_checkpoint(checkpoint_path)?;
+    let metadata = retain_metadata(checkpoint.metadata, &checkpoint.amms);
+    Ok((checkpoint.amms, metadata, checkpoint.block_number))
+}
+
+/// Returns the metadata of the AMMs in `amms`.
+fn retain_metadata(mut metadata: AmmMetadataMap, amms: &[AMM]) -> AmmMetadataMap {
+    let addresses = amms.iter().map(AMM::address).collect::<HashSet<_>>();
+    metadata.retain(|address, _| addresses.contains(address));
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use crate::amm::{
+        ambient::AmbientPool,
+        bancor_v3::BancorV3Pool,
+        constant_product::ConstantProductPool,
+        curve_v2::CurveV2Pool,
+        erc_4626::ERC4626Vault,
+        gmx::GmxMarket,
+        metadata::{AmmMetadata, AmmMetadataMap},
+        rate_adapter::RateAdapter,
+        uniswap_v2::UniswapV2Pool,
+        uniswap_v3::UniswapV3Pool,
+        AMM,
+    };
+
+    use super::{
+        construct_checkpoint_with_metadata, deconstruct_checkpoint_with_metadata,
+        migrate_checkpoint, Checkpoint, CHECKPOINT_VERSION,
+    };
+
+    /// Returns the serialized field names of `amm`.
+    fn fields(amm: AMM) -> Vec<String> {
+        let value = serde_json::to_value(amm).unwrap();
+        let (_, pool) = value.as_object().unwrap().iter().next().unwrap();
+
+        let mut fields = pool
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        fields.sort();
+        fields
+    }
+
+    // If one of these tests fails, the serialized AMMs changed: bump `CHECKPOINT_VERSION` and add a migration unless
+    // the new fields are defaulted by serde.
+    #[test]
+    fn test_uniswap_v2_pool_schema() {
+        assert_eq!(
+            fields(AMM::UniswapV2Pool(UniswapV2Pool::default())),
+            vec![
+                "address",
+                "factory",
+                "fee",
+                "reserve_0",
+                "reserve_1",
+                "stats",
+                "sync_from_amount_logs",
+                "token_a",
+                "token_a_decimals",
+                "token_a_transfer_tax_bps",
+                "token_b",
+                "token_b_decimals",
+                "token_b_transfer_tax_bps",
+                "track_swap_stats",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uniswap_v3_pool_schema() {
+        assert_eq!(
+            fields(AMM::UniswapV3Pool(UniswapV3Pool::default())),
+            vec![
+                "address",
+                "factory",
+                "fee",
+                "liquidity",
+                "sqrt_price",
+                "stats",
+                "tick",
+                "tick_bitmap",
+                "tick_spacing",
+                "tick_window",
+                "ticks",
+                "token_a",
+                "token_a_decimals",
+                "token_b",
+                "token_b_decimals",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_erc_4626_vault_schema() {
+        assert_eq!(
+            fields(AMM::ERC4626Vault(ERC4626Vault::default())),
+            vec![
+                "asset_reserve",
+                "asset_token",
+                "asset_token_decimals",
+                "deposit_fee",
+                "vault_reserve",
+                "vault_token",
+                "vault_token_decimals",
+                "withdraw_fee",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_curve_v2_pool_schema() {
+        assert_eq!(
+            fields(AMM::CurveV2Pool(CurveV2Pool::default())),
+            vec![
+                "a",
+                "address",
+                "balance_0",
+                "balance_1",
+                "d",
+                "factory",
+                "fee_gamma",
+                "gamma",
+                "mid_fee",
+                "out_fee",
+                "price_scale",
+                "ramp",
+                "token_a",
+                "token_a_decimals",
+                "token_b",
+                "token_b_decimals",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rate_adapter_schema() {
+        assert_eq!(
+            fields(AMM::RateAdapter(RateAdapter::default())),
+            vec![
+                "rate",
+                "source",
+                "underlying_token",
+                "underlying_token_decimals",
+                "wrapped_token",
+                "wrapped_token_decimals",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bancor_v3_schema() {
+        assert_eq!(
+            fields(AMM::BancorV3Pool(BancorV3Pool::default())),
+            vec![
+                "address",
+                "base_token",
+                "base_token_decimals",
+                "base_token_trading_liquidity",
+                "bnt_trading_liquidity",
+                "network",
+                "network_fee_ppm",
+                "network_settings",
+                "pool_collection",
+                "trading_enabled",
+                "trading_fee_ppm",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gmx_schema() {
+        assert_eq!(
+            fields(AMM::GmxMarket(GmxMarket::default())),
+            vec![
+                "address",
+                "data_store",
+                "long_pool_amount",
+                "long_swap_impact_pool_amount",
+                "long_token",
+                "long_token_decimals",
+                "long_token_price",
+                "short_pool_amount",
+                "short_swap_impact_pool_amount",
+                "short_token",
+                "short_token_decimals",
+                "short_token_price",
+                "swap_fee_factor_negative_impact",
+                "swap_fee_factor_positive_impact",
+                "swap_impact_exponent_factor",
+                "swap_impact_factor_negative",
+                "swap_impact_factor_positive",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ambient_schema() {
+        assert_eq!(
+            fields(AMM::AmbientPool(AmbientPool::default())),
+            vec![
+                "address",
+                "ambient_liquidity",
+                "base_token",
+                "base_token_decimals",
+                "concentrated_liquidity",
+                "dex",
+                "fee",
+                "pool_idx",
+                "query",
+                "quote_token",
+                "quote_token_decimals",
+                "sqrt_price",
+                "tick",
+                "tick_bitmap",
+                "tick_size",
+                "tick_window",
+                "ticks",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constant_product_schema() {
+        assert_eq!(
+            fields(AMM::ConstantProductPool(ConstantProductPool::default())),
+            vec![
+                "address",
+                "config",
+                "factory",
+                "reserve_0",
+                "reserve_1",
+                "token_a",
+                "token_a_decimals",
+                "token_b",
+                "token_b_decimals",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_migrate_checkpoint() {
+        // Checkpoint written before the schema was versioned
+        let checkpoint = serde_json::json!({
+            "timestamp": 1_700_000_000,
+            "block_number": 19_000_000,
+            "factories": [],
+            "amms": [{
+                "UniswapV2Pool": {
+                    "address": "0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc",
+                    "token_a": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                    "token_a_decimals": 6,
+                    "token_b": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                    "token_b_decimals": 18,
+                    "reserve_0": 1_000_000,
+                    "reserve_1": 2_000_000,
+                    "fee": 300
+                }
+            }]
+        });
+
+        let checkpoint = migrate_checkpoint(checkpoint).unwrap();
+        assert_eq!(checkpoint.version, CHECKPOINT_VERSION);
+        assert_eq!(checkpoint.block_number, 19_000_000);
+        assert!(checkpoint.metadata.is_empty());
+        let AMM::UniswapV2Pool(pool) = &checkpoint.amms[0] else {
+            unreachable!()
+        };
+        assert_eq!(
+            pool.address,
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc")
+        );
+        assert_eq!(pool.factory, None);
+
+        // Current checkpoints round trip
+        let value = serde_json::to_value(Checkpoint::new(0, 1, vec![], checkpoint.amms)).unwrap();
+        assert_eq!(
+            migrate_checkpoint(value).unwrap().version,
+            CHECKPOINT_VERSION
+        );
+
+        let mut value = serde_json::json!({
+            "timestamp": 0,
+            "block_number": 1,
+            "factories": [],
+            "amms": []
+        });
+        value["version"] = (CHECKPOINT_VERSION + 1).into();
+        assert!(migrate_checkpoint(value).is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_metadata() {
+        let pool = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let removed_pool = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+        let amms = vec![AMM::UniswapV2Pool(UniswapV2Pool {
+            address: pool,
+            ..Default::default()
+        })];
+
+        let mut metadata = AmmMetadata::new();
+        metadata.tag("strategy");
+        let metadata_map =
+            AmmMetadataMap::from([(pool, metadata.clone()), (removed_pool, metadata)]);
+
+        let path = std::env::temp_dir().join("amms_test_checkpoint_metadata.json");
+        let path = path.to_str().unwrap();
+        construct_checkpoint_with_metadata(vec![], &amms, &metadata_map, 1, path).unwrap();
+
+        // Only the metadata of the checkpoint AMMs is kept
+        let (amms, metadata, block_number) = deconstruct_checkpoint_with_metadata(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(amms.len(), 1);
+        assert_eq!(block_number, 1);
+        assert_eq!(metadata.len(), 1);
+        assert!(metadata[&pool].has_tag("strategy"));
+    }
 }
@@ -0,0 +1,392 @@
+use std::{future::Future, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amm::consts::POPULATE_TICK_DATA_STEP,
+    chains::{ChainConfig, ZKSYNC_ERA_CHAIN_ID, ZKSYNC_SEPOLIA_CHAIN_ID},
+};
+
+/// Configuration for syncing and populating AMMs.
+///
+/// Use [`SyncConfig::builder`] to override the defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// Block range of each `eth_getLogs` request.
+    pub step: u64,
+    /// Maximum number of concurrent requests.
+    pub max_concurrency: usize,
+    /// Number of Uniswap V2 pools per batch request.
+    pub v2_batch_size: usize,
+    /// Number of Uniswap V3 pools per batch request.
+    pub v3_batch_size: usize,
+    /// Number of ERC4626 vaults per batch request.
+    #[serde(default = "default_erc_4626_batch_size")]
+    pub erc_4626_batch_size: usize,
+    /// Number of Curve V2 pools per batch request.
+    #[serde(default = "default_curve_v2_batch_size")]
+    pub curve_v2_batch_size: usize,
+    /// Number of rate adapters per batch request.
+    #[serde(default = "default_rate_adapter_batch_size")]
+    pub rate_adapter_batch_size: usize,
+    /// Number of Bancor V3 pools per batch request.
+    #[serde(default = "default_bancor_v3_batch_size")]
+    pub bancor_v3_batch_size: usize,
+    /// Number of GMX markets per batch request.
+    #[serde(default = "default_gmx_batch_size")]
+    pub gmx_batch_size: usize,
+    /// Number of Ambient pools per batch request, each pool queries the liquidity levels around its price.
+    #[serde(default = "default_ambient_batch_size")]
+    pub ambient_batch_size: usize,
+    /// Number of constant product pools per batch request.
+    #[serde(default = "default_constant_product_batch_size")]
+    pub constant_product_batch_size: usize,
+    /// Retry policy for failed requests.
+    pub retry: RetryPolicy,
+    /// Number of blocks behind the chain head to sync to.
+    pub finality_depth: u64,
+    /// Method used to batch pool data requests.
+    #[serde(default)]
+    pub batch_strategy: BatchStrategy,
+}
+
+/// Method used to batch pool data requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchStrategy {
+    /// Use the deployless batch contracts, falling back to Multicall3 if a deployless call fails.
+    #[default]
+    Auto,
+    /// Only use the deployless batch contracts.
+    Deployless,
+    /// Only use Multicall3, for chains that do not support deployless static calls.
+    Multicall3,
+    /// Execute each call individually, for chains that support neither deployless static calls nor Multicall3 at
+    /// its usual address.
+    Individual,
+}
+
+impl BatchStrategy {
+    /// Returns the batch strategy known to work on `chain_id`, [`BatchStrategy::Auto`] for most chains.
+    ///
+    /// zkSync Era does not support deployless static calls and deploys Multicall3 at a different address.
+    pub fn for_chain(chain_id: u64) -> Self {
+        match chain_id {
+            ZKSYNC_ERA_CHAIN_ID | ZKSYNC_SEPOLIA_CHAIN_ID => BatchStrategy::Individual,
+            _ => BatchStrategy::Auto,
+        }
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            step: POPULATE_TICK_DATA_STEP,
+            max_concurrency: 32,
+            v2_batch_size: 127,
+            v3_batch_size: 76,
+            erc_4626_batch_size: default_erc_4626_batch_size(),
+            curve_v2_batch_size: default_curve_v2_batch_size(),
+            rate_adapter_batch_size: default_rate_adapter_batch_size(),
+            bancor_v3_batch_size: default_bancor_v3_batch_size(),
+            gmx_batch_size: default_gmx_batch_size(),
+            ambient_batch_size: default_ambient_batch_size(),
+            constant_product_batch_size: default_constant_product_batch_size(),
+            retry: RetryPolicy::default(),
+            finality_depth: 0,
+            batch_strategy: BatchStrategy::default(),
+        }
+    }
+}
+
+fn default_erc_4626_batch_size() -> usize {
+    50
+}
+
+fn default_curve_v2_batch_size() -> usize {
+    50
+}
+
+fn default_rate_adapter_batch_size() -> usize {
+    100
+}
+
+fn default_bancor_v3_batch_size() -> usize {
+    100
+}
+
+fn default_gmx_batch_size() -> usize {
+    50
+}
+
+fn default_ambient_batch_size() -> usize {
+    10
+}
+
+fn default_constant_product_batch_size() -> usize {
+    127
+}
+
+impl SyncConfig {
+    pub fn builder() -> SyncConfigBuilder {
+        SyncConfigBuilder::default()
+    }
+
+    /// Returns the default config with the log step and batch strategy of `chain_id`, if the chain is known.
+    ///
+    /// Unlike [`ChainConfig::sync_config`], the config syncs to the chain head.
+    pub fn for_chain(chain_id: u64) -> Self {
+        let builder = SyncConfig::builder().batch_strategy(BatchStrategy::for_chain(chain_id));
+
+        match ChainConfig::from_chain_id(chain_id) {
+            Some(chain) => builder.step(chain.log_step).build(),
+            None => builder.build(),
+        }
+    }
+}
+
+/// Builder for a [`SyncConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct SyncConfigBuilder {
+    config: SyncConfig,
+}
+
+impl SyncConfigBuilder {
+    pub fn step(mut self, step: u64) -> Self {
+        self.config.step = step;
+        self
+    }
+
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.config.max_concurrency = max_concurrency;
+        self
+    }
+
+    pub fn v2_batch_size(mut self, v2_batch_size: usize) -> Self {
+        self.config.v2_batch_size = v2_batch_size;
+        self
+    }
+
+    pub fn v3_batch_size(mut self, v3_batch_size: usize) -> Self {
+        self.config.v3_batch_size = v3_batch_size;
+        self
+    }
+
+    pub fn erc_4626_batch_size(mut self, erc_4626_batch_size: usize) -> Self {
+        self.config.erc_4626_batch_size = erc_4626_batch_size;
+        self
+    }
+
+    pub fn curve_v2_batch_size(mut self, curve_v2_batch_size: usize) -> Self {
+        self.config.curve_v2_batch_size = curve_v2_batch_size;
+        self
+    }
+
+    pub fn rate_adapter_batch_size(mut self, rate_adapter_batch_size: usize) -> Self {
+        self.config.rate_adapter_batch_size = rate_adapter_batch_size;
+        self
+    }
+
+    pub fn bancor_v3_batch_size(mut self, bancor_v3_batch_size: usize) -> Self {
+        self.config.bancor_v3_batch_size = bancor_v3_batch_size;
+        self
+    }
+
+    pub fn gmx_batch_size(mut self, gmx_batch_size: usize) -> Self {
+        self.config.gmx_batch_size = gmx_batch_size;
+        self
+    }
+
+    pub fn ambient_batch_size(mut self, ambient_batch_size: usize) -> Self {
+        self.config.ambient_batch_size = ambient_batch_size;
+        self
+    }
+
+    pub fn constant_product_batch_size(mut self, constant_product_batch_size: usize) -> Self {
+        self.config.constant_product_batch_size = constant_product_batch_size;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.config.retry = retry;
+        self
+    }
+
+    pub fn finality_depth(mut self, finality_depth: u64) -> Self {
+        self.config.finality_depth = finality_depth;
+        self
+    }
+
+    pub fn batch_strategy(mut self, batch_strategy: BatchStrategy) -> Self {
+        self.config.batch_strategy = batch_strategy;
+        self
+    }
+
+    /// Builds the config, raising zero step, concurrency and batch sizes to one.
+    pub fn build(self) -> SyncConfig {
+        SyncConfig {
+            step: self.config.step.max(1),
+            max_concurrency: self.config.max_concurrency.max(1),
+            v2_batch_size: self.config.v2_batch_size.max(1),
+            v3_batch_size: self.config.v3_batch_size.max(1),
+            erc_4626_batch_size: self.config.erc_4626_batch_size.max(1),
+            curve_v2_batch_size: self.config.curve_v2_batch_size.max(1),
+            rate_adapter_batch_size: self.config.rate_adapter_batch_size.max(1),
+            bancor_v3_batch_size: self.config.bancor_v3_batch_size.max(1),
+            gmx_batch_size: self.config.gmx_batch_size.max(1),
+            ambient_batch_size: self.config.ambient_batch_size.max(1),
+            constant_product_batch_size: self.config.constant_product_batch_size.max(1),
+            ..self.config
+        }
+    }
+}
+
+/// Exponential backoff retry policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt.
+    pub max_retries: u32,
+    /// Backoff before the first retry in milliseconds, doubled on every retry.
+    pub initial_backoff_ms: u64,
+    /// Maximum backoff between retries in milliseconds.
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, initial_backoff_ms: u64, max_backoff_ms: u64) -> Self {
+        Self {
+            max_retries,
+            initial_backoff_ms,
+            max_backoff_ms,
+        }
+    }
+
+    /// Returns whether a request that failed on the given zero-indexed `attempt` can be retried.
+    pub fn can_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_retries
+    }
+
+    /// Returns the backoff before retrying a request that failed on the given zero-indexed `attempt`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff_ms
+            .saturating_mul(2_u64.saturating_pow(attempt));
+
+        Duration::from_millis(backoff.min(self.max_backoff_ms))
+    }
+
+    /// Runs `f` until it succeeds or the retries are exhausted, returning the last result.
+    pub async fn retry<F, Fut, R, E>(&self, f: F) -> Result<R, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, E>>,
+    {
+        self.retry_if(f, |_| true).await
+    }
+
+    /// Runs `f` until it succeeds, fails with an error that `should_retry` rejects or the retries are exhausted,
+    /// returning the last result.
+    pub async fn retry_if<F, Fut, R, E, S>(&self, mut f: F, mut should_retry: S) -> Result<R, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, E>>,
+        S: FnMut(&E) -> bool,
+    {
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Err(ref err) if self.can_retry(attempt) && should_retry(err) => {
+                    tracing::debug!(attempt, "request failed, retrying");
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{BatchStrategy, RetryPolicy, SyncConfig};
+
+    #[test]
+    fn test_builder() {
+        let config = SyncConfig::builder()
+            .step(10_000)
+            .max_concurrency(0)
+            .finality_depth(12)
+            .batch_strategy(BatchStrategy::Multicall3)
+            .build();
+
+        assert_eq!(config.step, 10_000);
+        assert_eq!(config.max_concurrency, 1);
+        assert_eq!(config.finality_depth, 12);
+        assert_eq!(config.v3_batch_size, SyncConfig::default().v3_batch_size);
+        assert_eq!(config.batch_strategy, BatchStrategy::Multicall3);
+        assert_eq!(SyncConfig::default().batch_strategy, BatchStrategy::Auto);
+    }
+
+    #[test]
+    fn test_batch_strategy_for_chain() {
+        assert_eq!(BatchStrategy::for_chain(1), BatchStrategy::Auto);
+        assert_eq!(BatchStrategy::for_chain(324), BatchStrategy::Individual);
+
+        assert_eq!(SyncConfig::for_chain(1), SyncConfig::default());
+        assert_eq!(SyncConfig::for_chain(42161).step, 2_000_000);
+        assert_eq!(SyncConfig::for_chain(42161).finality_depth, 0);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let retry = RetryPolicy::new(5, 100, 1_000);
+
+        assert_eq!(retry.backoff(0), Duration::from_millis(100));
+        assert_eq!(retry.backoff(2), Duration::from_millis(400));
+        assert_eq!(retry.backoff(10), Duration::from_millis(1_000));
+        assert!(retry.can_retry(4));
+        assert!(!retry.can_retry(5));
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let retry = RetryPolicy::new(2, 1, 1);
+
+        let mut attempts = 0;
+        let result: Result<(), ()> = retry
+            .retry(|| {
+                attempts += 1;
+                async { Err(()) }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        // Errors rejected by the predicate are returned without retrying
+        let mut attempts = 0;
+        let result: Result<(), bool> = retry
+            .retry_if(
+                || {
+                    attempts += 1;
+                    async move { Err(attempts > 1) }
+                },
+                |retryable| !retryable,
+            )
+            .await;
+
+        assert_eq!(result, Err(true));
+        assert_eq!(attempts, 2);
+    }
+}
@@ -1,18 +1,24 @@
 pub mod checkpoint;
+pub mod config;
 
 use crate::{
     amm::{
+        batch_request,
         factory::{AutomatedMarketMakerFactory, Factory},
-        uniswap_v2, uniswap_v3, AutomatedMarketMaker, AMM,
+        AutomatedMarketMaker, AMM,
     },
+    chains::ChainConfig,
     errors::AMMError,
     filters,
 };
 
 use alloy::{network::Network, providers::Provider, transports::Transport};
+use futures::StreamExt;
 
 use std::{panic::resume_unwind, sync::Arc};
 
+use self::config::SyncConfig;
+
 /// Syncs all AMMs from the supplied factories.
 ///
 /// factories - A vector of factories to sync AMMs from.
@@ -31,9 +37,53 @@ where
     N: Network,
     P: Provider<T, N> + 'static,
 {
-    tracing::info!(?step, ?factories, "Syncing AMMs");
+    let config = SyncConfig::builder().step(step).build();
+    sync_amms_with_config(factories, provider, checkpoint_path, config).await
+}
 
-    let current_block = provider.get_block_number().await?;
+/// Syncs all AMMs from the canonical factories of `chain`, to the reorg depth of the chain.
+///
+/// Returns a tuple of the synced AMMs and the last synced block number.
+pub async fn sync_chain<T, N, P>(
+    chain: &ChainConfig,
+    provider: Arc<P>,
+    checkpoint_path: Option<&str>,
+) -> Result<(Vec<AMM>, u64), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    sync_amms_with_config(
+        chain.factories(),
+        provider,
+        checkpoint_path,
+        chain.sync_config(),
+    )
+    .await
+}
+
+/// Syncs all AMMs from the supplied factories with the given [`SyncConfig`].
+///
+/// AMMs are synced to `config.finality_depth` blocks behind the chain head.
+/// Returns a tuple of the synced AMMs and the last synced block number.
+pub async fn sync_amms_with_config<T, N, P>(
+    factories: Vec<Factory>,
+    provider: Arc<P>,
+    checkpoint_path: Option<&str>,
+    config: SyncConfig,
+) -> Result<(Vec<AMM>, u64), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    tracing::info!(?config, ?factories, "Syncing AMMs");
+
+    let current_block = provider
+        .get_block_number()
+        .await?
+        .saturating_sub(config.finality_depth);
 
     // Aggregate the populated pools from each thread
     let mut aggregated_amms: Vec<AMM> = vec![];
@@ -43,16 +93,19 @@ where
     for factory in factories.clone() {
         let provider = provider.clone();
 
+        #[cfg(feature = "tracing-spans")]
+        let span = tracing::info_span!("sync_factory", factory = ?factory.address());
+
         // Spawn a new thread to get all pools and sync data for each dex
-        handles.push(tokio::spawn(async move {
+        let task = async move {
             tracing::info!(?factory, "Getting all AMMs from factory");
             // Get all of the amms from the factory
             let mut amms = factory
-                .get_all_amms(Some(current_block), provider.clone(), step)
+                .get_all_amms(Some(current_block), provider.clone(), config.step)
                 .await?;
 
             tracing::info!(?factory, "Populating AMMs from factory");
-            populate_amms(&mut amms, current_block, provider.clone()).await?;
+            populate_amms_with_config(&mut amms, current_block, &config, provider.clone()).await?;
 
             // Clean empty pools
             amms = filters::filter_empty_amms(amms);
@@ -67,7 +120,12 @@ where
             }
 
             Ok::<_, AMMError>(amms)
-        }));
+        };
+
+        #[cfg(feature = "tracing-spans")]
+        let task = tracing::Instrument::instrument(task, span);
+
+        handles.push(tokio::spawn(task));
     }
 
     for handle in handles {
@@ -116,49 +174,70 @@ pub async fn populate_amms<T, N, P>(
     block_number: u64,
     provider: Arc<P>,
 ) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    populate_amms_with_config(amms, block_number, &SyncConfig::default(), provider).await
+}
+
+/// Populates the data of congruent AMMs via batched static calls, using the batch sizes, batch strategy and retry policy from `config`.
+pub async fn populate_amms_with_config<T, N, P>(
+    amms: &mut [AMM],
+    block_number: u64,
+    config: &SyncConfig,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
 where
     T: Transport + Clone,
     N: Network,
     P: Provider<T, N>,
 {
     if amms_are_congruent(amms) {
-        match amms[0] {
-            AMM::UniswapV2Pool(_) => {
-                // Max batch size for call
-                let step = 127;
-                for amm_chunk in amms.chunks_mut(step) {
-                    uniswap_v2::batch_request::get_amm_data_batch_request(
-                        amm_chunk,
-                        provider.clone(),
-                    )
-                    .await?;
-                }
-            }
+        batch_request::populate_amms_with_config(amms, Some(block_number), config, provider)
+            .await?;
+    } else {
+        return Err(AMMError::IncongruentAMMs);
+    }
 
-            AMM::UniswapV3Pool(_) => {
-                // Max batch size for call
-                let step = 76;
-                for amm_chunk in amms.chunks_mut(step) {
-                    uniswap_v3::batch_request::get_amm_data_batch_request(
-                        amm_chunk,
-                        block_number,
-                        provider.clone(),
-                    )
-                    .await?;
-                }
-            }
+    // For each pair in the pairs vec, get the pool data
+    Ok(())
+}
 
-            // TODO: Implement batch request
-            AMM::ERC4626Vault(_) => {
-                for amm in amms {
-                    amm.populate_data(None, provider.clone()).await?;
+/// Reconstructs the full state of `amms` as of `block_number`.
+///
+/// Uniswap V3 tick data is rebuilt from the Mint and Burn logs between `from_block` and `block_number`, `from_block`
+/// must be at or before the creation of the pools. Up to `config.max_concurrency` AMMs are populated concurrently.
+pub async fn populate_data_at_block<T, N, P>(
+    amms: &mut [AMM],
+    from_block: u64,
+    block_number: u64,
+    config: &SyncConfig,
+    provider: Arc<P>,
+) -> Result<(), AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let mut futures = futures::stream::iter(amms.iter_mut().map(|amm| {
+        let provider = provider.clone();
+        async move {
+            match amm {
+                AMM::UniswapV3Pool(pool) => {
+                    pool.populate_data_at_block(from_block, block_number, config, provider)
+                        .await
                 }
+                amm => amm.populate_data(Some(block_number), provider).await,
             }
         }
-    } else {
-        return Err(AMMError::IncongruentAMMs);
+    }))
+    .buffer_unordered(config.max_concurrency);
+
+    while let Some(result) = futures.next().await {
+        result?;
     }
 
-    // For each pair in the pairs vec, get the pool data
     Ok(())
 }
@@ -0,0 +1,23 @@
+//! Pools shared by the unit tests.
+
+use alloy::primitives::{address, Address};
+
+use crate::amm::uniswap_v2::UniswapV2Pool;
+
+pub(crate) const USDC: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+pub(crate) const WETH: Address = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+/// The mainnet USDC/WETH Uniswap V2 pair, with reserves of 1M USDC and 500 WETH.
+pub(crate) fn usdc_weth_pool() -> UniswapV2Pool {
+    UniswapV2Pool {
+        address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+        token_a: USDC,
+        token_a_decimals: 6,
+        token_b: WETH,
+        token_b_decimals: 18,
+        reserve_0: 1_000_000_000_000,
+        reserve_1: 500_000_000_000_000_000_000,
+        fee: 300,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,244 @@
+//! Recorded RPC fixtures, so that tests run deterministically without an RPC endpoint.
+//!
+//! Wrap a live transport in a [`RecordingTransport`] to record the responses of a test into an [`RpcFixture`], save
+//! it under [`FIXTURES_DIR`], then run the test offline against a [`ReplayTransport`] built from the fixture.
+//!
+//! Tests that still read `ETHEREUM_RPC_ENDPOINT` are `#[ignore]`d and only run with `cargo test -- --ignored`. To
+//! port one, run it once with its provider built on a [`RecordingTransport`], save the fixture as
+//! `fixtures/<test name>.json` and build the provider with [`replay_provider`] instead, then drop the `#[ignore]`.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use alloy::{
+    providers::{Provider, ProviderBuilder},
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest},
+    },
+    transports::{Transport, TransportError, TransportErrorKind, TransportFut},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value};
+use tower::Service;
+
+use crate::errors::AMMError;
+
+/// Directory of the recorded fixtures.
+pub const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+/// A recorded JSON-RPC call and its result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub method: String,
+    pub params: Value,
+    pub result: Value,
+}
+
+/// A set of recorded JSON-RPC calls.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcFixture {
+    pub calls: Vec<RecordedCall>,
+}
+
+impl RpcFixture {
+    /// Writes the fixture to `path` as JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AMMError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Reads a fixture written by [`RpcFixture::save`] from `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AMMError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Transport that forwards requests to `inner` and records the successful responses.
+#[derive(Debug, Clone)]
+pub struct RecordingTransport<T> {
+    inner: T,
+    calls: Arc<Mutex<Vec<RecordedCall>>>,
+}
+
+impl<T> RecordingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            calls: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Returns the calls recorded so far.
+    pub fn fixture(&self) -> RpcFixture {
+        RpcFixture {
+            calls: self.calls.lock().expect("lock poisoned").clone(),
+        }
+    }
+}
+
+impl<T: Transport + Clone> Service<RequestPacket> for RecordingTransport<T> {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let requests = match &request {
+            RequestPacket::Single(request) => vec![request.clone()],
+            RequestPacket::Batch(requests) => requests.clone(),
+        };
+
+        let calls = self.calls.clone();
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = response.await?;
+            let responses = match &response {
+                ResponsePacket::Single(response) => std::slice::from_ref(response),
+                ResponsePacket::Batch(responses) => responses.as_slice(),
+            };
+
+            let mut calls = calls.lock().expect("lock poisoned");
+            for response in responses {
+                let ResponsePayload::Success(result) = &response.payload else {
+                    continue;
+                };
+
+                if let Some(request) = requests.iter().find(|r| r.id() == &response.id) {
+                    calls.push(RecordedCall {
+                        method: request.method().to_string(),
+                        params: params(request),
+                        result: serde_json::from_str(result.get())
+                            .map_err(TransportError::ser_err)?,
+                    });
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Transport that answers requests from an [`RpcFixture`], failing on requests that were not recorded.
+#[derive(Debug, Clone)]
+pub struct ReplayTransport {
+    results: Arc<HashMap<(String, String), Value>>,
+}
+
+impl ReplayTransport {
+    pub fn new(fixture: RpcFixture) -> Self {
+        let results = fixture
+            .calls
+            .into_iter()
+            .map(|call| ((call.method, call.params.to_string()), call.result))
+            .collect();
+
+        Self {
+            results: Arc::new(results),
+        }
+    }
+
+    fn respond(&self, request: &SerializedRequest) -> Result<Response, TransportError> {
+        let key = (request.method().to_string(), params(request).to_string());
+        let result = self.results.get(&key).ok_or_else(|| {
+            TransportErrorKind::custom_str(&format!("no recorded response for {} {}", key.0, key.1))
+        })?;
+
+        Ok(Response {
+            id: request.id().clone(),
+            payload: ResponsePayload::Success(
+                RawValue::from_string(result.to_string()).map_err(TransportError::ser_err)?,
+            ),
+        })
+    }
+}
+
+impl Service<RequestPacket> for ReplayTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let response = match request {
+            RequestPacket::Single(request) => self.respond(&request).map(ResponsePacket::Single),
+            RequestPacket::Batch(requests) => requests
+                .iter()
+                .map(|request| self.respond(request))
+                .collect::<Result<Vec<_>, _>>()
+                .map(ResponsePacket::Batch),
+        };
+
+        Box::pin(async move { response })
+    }
+}
+
+/// Returns a provider that replays `fixture`.
+pub fn replay_provider(fixture: RpcFixture) -> impl Provider<ReplayTransport> {
+    ProviderBuilder::new().on_client(RpcClient::new(ReplayTransport::new(fixture), true))
+}
+
+/// Returns the params of a request in a canonical form, with sorted object keys and `null` for empty params.
+fn params(request: &SerializedRequest) -> Value {
+    match request
+        .params()
+        .and_then(|params| serde_json::from_str(params.get()).ok())
+    {
+        Some(Value::Array(params)) if params.is_empty() => Value::Null,
+        Some(params) => params,
+        None => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        providers::{Provider, ProviderBuilder},
+        rpc::client::RpcClient,
+    };
+    use serde_json::json;
+
+    use super::{replay_provider, RecordedCall, RecordingTransport, ReplayTransport, RpcFixture};
+
+    fn fixture() -> RpcFixture {
+        RpcFixture {
+            calls: vec![RecordedCall {
+                method: "eth_blockNumber".to_string(),
+                params: json!(null),
+                result: json!("0x12a05f2"),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let provider = replay_provider(fixture());
+
+        assert_eq!(provider.get_block_number().await.unwrap(), 19_531_250);
+        assert!(provider.get_chain_id().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let transport = RecordingTransport::new(ReplayTransport::new(fixture()));
+        let provider = ProviderBuilder::new().on_client(RpcClient::new(transport.clone(), true));
+
+        provider.get_block_number().await.unwrap();
+
+        let path = std::env::temp_dir().join("amms_test_record_fixture.json");
+        transport.fixture().save(&path).unwrap();
+        assert_eq!(RpcFixture::load(&path).unwrap(), fixture());
+        std::fs::remove_file(path).unwrap();
+    }
+}
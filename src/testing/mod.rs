@@ -0,0 +1,336 @@
+//! Differential testing of swap simulations against on-chain quoters.
+//!
+//! [`fuzz_against_quoter`] simulates swaps of random amounts locally and compares them against Uniswap's QuoterV2,
+//! reporting the first divergence along with the full pool state, so that it can be reproduced offline.
+
+pub mod fixtures;
+
+use std::{fmt, sync::Arc};
+
+use alloy::{
+    network::Network,
+    primitives::{Address, U256},
+    providers::Provider,
+    transports::{RpcError, Transport},
+};
+
+pub use crate::amm::uniswap_v3::quoter::{IQuoterV2, QUOTER_V2_ADDRESS};
+use crate::{
+    amm::uniswap_v3::{quoter::quote_pool, UniswapV3Pool},
+    errors::AMMError,
+};
+
+/// Outcome of a swap, as returned by QuoterV2.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quote {
+    pub amount_out: U256,
+    pub sqrt_price_x_96_after: U256,
+    pub ticks_crossed: u32,
+}
+
+/// A swap for which the local simulation does not match the quoter.
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub token_in: Address,
+    pub amount_in: U256,
+    /// Quote from QuoterV2.
+    pub expected: Quote,
+    /// Quote from the local simulation, or the simulation error.
+    pub simulated: Result<Quote, String>,
+    /// Block of the pool state.
+    pub block_number: u64,
+    pub pool: UniswapV3Pool,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "swap of {} {:?} in pool {:?} at block {} diverged",
+            self.amount_in, self.token_in, self.pool.address, self.block_number
+        )?;
+        writeln!(f, "expected:  {:?}", self.expected)?;
+        writeln!(f, "simulated: {:?}", self.simulated)?;
+        write!(
+            f,
+            "pool: {}",
+            serde_json::to_string(&self.pool).map_err(|_| fmt::Error)?
+        )
+    }
+}
+
+/// Configuration of [`fuzz_against_quoter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzConfig {
+    /// Address of the QuoterV2.
+    pub quoter: Address,
+    /// Number of swaps to compare, alternating between both swap directions.
+    pub iterations: usize,
+    /// Seed of the amount generator, so that runs are reproducible.
+    pub seed: u64,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            quoter: QUOTER_V2_ADDRESS,
+            iterations: 100,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// Compares swaps of random amounts in `pool` against the QuoterV2 at `block_number`.
+///
+/// `pool` must hold the full tick data at `block_number`. Amounts are spread over the orders of magnitude of the
+/// input token, amounts that the quoter rejects (e.g. swaps exhausting the liquidity) are skipped.
+/// Returns the first divergence, if any.
+pub async fn fuzz_against_quoter<T, N, P>(
+    pool: &UniswapV3Pool,
+    block_number: u64,
+    config: FuzzConfig,
+    provider: Arc<P>,
+) -> Result<Option<Divergence>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let mut rng = XorShift(config.seed.max(1));
+
+    for i in 0..config.iterations {
+        let (token_in, decimals) = if i % 2 == 0 {
+            (pool.token_a, pool.token_a_decimals)
+        } else {
+            (pool.token_b, pool.token_b_decimals)
+        };
+
+        let amount_in = rng.amount(decimals as u32 + 6);
+
+        if let Some(divergence) = compare_with_quoter(
+            pool,
+            token_in,
+            amount_in,
+            config.quoter,
+            block_number,
+            provider.clone(),
+        )
+        .await?
+        {
+            return Ok(Some(divergence));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Compares a single swap in `pool` against the QuoterV2 at `block_number`.
+///
+/// Returns `None` if the swap matches or the quoter rejects it.
+pub async fn compare_with_quoter<T, N, P>(
+    pool: &UniswapV3Pool,
+    token_in: Address,
+    amount_in: U256,
+    quoter: Address,
+    block_number: u64,
+    provider: Arc<P>,
+) -> Result<Option<Divergence>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let result = quote_pool(
+        quoter,
+        pool,
+        token_in,
+        amount_in,
+        Some(block_number),
+        provider,
+    )
+    .await;
+
+    let expected = match result {
+        Ok(quote) => Quote {
+            amount_out: quote.amount_out,
+            sqrt_price_x_96_after: quote.sqrt_price_x_96_after,
+            ticks_crossed: quote.initialized_ticks_crossed,
+        },
+        Err(AMMError::ContractError(alloy::contract::Error::TransportError(
+            RpcError::ErrorResp(_),
+        ))) => {
+            tracing::debug!(?amount_in, ?token_in, "quoter rejected swap, skipping");
+            return Ok(None);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let simulated = simulate_quote(pool, token_in, amount_in);
+    if simulated.as_ref() == Ok(&expected) {
+        return Ok(None);
+    }
+
+    Ok(Some(Divergence {
+        token_in,
+        amount_in,
+        expected,
+        simulated,
+        block_number,
+        pool: pool.clone(),
+    }))
+}
+
+/// Simulates a swap locally, counting the initialized ticks crossed the same way as QuoterV2.
+pub fn simulate_quote(
+    pool: &UniswapV3Pool,
+    token_in: Address,
+    amount_in: U256,
+) -> Result<Quote, String> {
+    let state = pool
+        .swap_inner(token_in, amount_in)
+        .map_err(|err| err.to_string())?;
+
+    Ok(Quote {
+        ticks_crossed: count_initialized_ticks_crossed(pool, pool.tick, state.tick),
+        amount_out: (-state.amount_calculated).into_raw(),
+        sqrt_price_x_96_after: state.sqrt_price_x_96,
+    })
+}
+
+/// Counts the initialized ticks crossed by a swap from `tick_before` to `tick_after`, mirroring
+/// `PoolTicksCounter.countInitializedTicksCrossed` of QuoterV2.
+///
+/// The ticks are compressed with truncating division like in Solidity. An initialized end tick is not counted when
+/// swapping down, and an initialized start tick is not counted when swapping up.
+fn count_initialized_ticks_crossed(pool: &UniswapV3Pool, tick_before: i32, tick_after: i32) -> u32 {
+    let tick_spacing = pool.tick_spacing.max(1);
+    let initialized = |tick: i32| {
+        tick % tick_spacing == 0 && pool.ticks.get(&tick).map_or(false, |info| info.initialized)
+    };
+
+    let (lower, upper) = if tick_before < tick_after {
+        (tick_before, tick_after)
+    } else {
+        (tick_after, tick_before)
+    };
+
+    let mut ticks_crossed = pool
+        .ticks
+        .range((lower / tick_spacing) * tick_spacing..=(upper / tick_spacing) * tick_spacing)
+        .filter(|(tick, info)| info.initialized && **tick % tick_spacing == 0)
+        .count() as u32;
+
+    if tick_before > tick_after && initialized(tick_after) {
+        ticks_crossed = ticks_crossed.saturating_sub(1);
+    }
+
+    if tick_before < tick_after && initialized(tick_before) {
+        ticks_crossed = ticks_crossed.saturating_sub(1);
+    }
+
+    ticks_crossed
+}
+
+/// Minimal xorshift generator, so that the harness does not need a dependency on `rand`.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a non-zero amount with up to `max_digits` digits, uniformly distributed over the number of digits.
+    fn amount(&mut self, max_digits: u32) -> U256 {
+        let digits = 1 + self.next() % max_digits.max(1) as u64;
+        let mantissa = U256::from(1 + self.next() % 9);
+        mantissa * U256::from(10).pow(U256::from(digits - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{
+        primitives::{address, U256},
+        providers::ProviderBuilder,
+    };
+
+    use crate::amm::{uniswap_v3::UniswapV3Pool, AutomatedMarketMaker};
+
+    use super::{fuzz_against_quoter, simulate_quote, FuzzConfig, XorShift};
+
+    #[test]
+    fn test_simulate_quote() {
+        let mut pool = UniswapV3Pool {
+            token_a: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            token_b: address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            tick_spacing: 10,
+            fee: 500,
+            sqrt_price: uniswap_v3_math::tick_math::get_sqrt_ratio_at_tick(0).unwrap(),
+            ..Default::default()
+        };
+        pool.modify_position(-887270, 887270, 1_000_000_000);
+        pool.modify_position(-100, 100, 1_000_000_000_000);
+
+        // Stays within the inner range
+        let quote = simulate_quote(&pool, pool.token_a, U256::from(1_000)).unwrap();
+        assert_eq!(quote.ticks_crossed, 0);
+        assert_eq!(
+            quote.amount_out,
+            pool.simulate_swap(pool.token_a, U256::from(1_000)).unwrap()
+        );
+
+        // Crosses the lower tick of the inner range
+        let quote = simulate_quote(&pool, pool.token_a, U256::from(100_000_000_000_u64)).unwrap();
+        assert_eq!(quote.ticks_crossed, 1);
+    }
+
+    #[test]
+    fn test_amounts() {
+        let mut rng = XorShift(1);
+        for _ in 0..100 {
+            let amount = rng.amount(24);
+            assert!(amount > U256::ZERO && amount < U256::from(10).pow(U256::from(24)));
+        }
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_fuzz_against_quoter() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let mut pool = UniswapV3Pool {
+            address: address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+            ..Default::default()
+        };
+        pool.tick_spacing = pool.get_tick_spacing(provider.clone()).await.unwrap();
+        let synced_block = pool
+            .populate_tick_data(12369620, provider.clone())
+            .await
+            .unwrap();
+        pool.populate_data(Some(synced_block), provider.clone())
+            .await
+            .unwrap();
+
+        let divergence = fuzz_against_quoter(
+            &pool,
+            synced_block,
+            FuzzConfig {
+                iterations: 20,
+                ..Default::default()
+            },
+            provider,
+        )
+        .await
+        .unwrap();
+
+        if let Some(divergence) = divergence {
+            panic!("{divergence}");
+        }
+    }
+}
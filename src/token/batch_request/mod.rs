@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use alloy::{
+    dyn_abi::DynSolType,
+    network::Network,
+    primitives::{Address, Bytes},
+    providers::Provider,
+    sol,
+    transports::Transport,
+};
+
+use crate::errors::AMMError;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    IGetTokenMetadataBatchRequest,
+    "src/token/batch_request/GetTokenMetadataBatchRequestABI.json"
+}
+
+/// Number of calls made to each token, to `decimals`, `symbol`, `name` and `totalSupply` in this order.
+const CALLS_PER_TOKEN: usize = 4;
+
+/// Returns the return data of the `decimals`, `symbol`, `name` and `totalSupply` calls of each of `tokens`, in this
+/// order, with `None` for the failed calls.
+pub async fn get_token_metadata_calls_batch_request<T, N, P>(
+    tokens: &[Address],
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<Vec<Vec<Option<Bytes>>>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let Some(first_token) = tokens.first().copied() else {
+        return Ok(vec![]);
+    };
+
+    let deployer = IGetTokenMetadataBatchRequest::deploy_builder(provider, tokens.to_vec());
+    let res = if let Some(block_number) = block_number {
+        deployer.block(block_number.into()).call_raw().await?
+    } else {
+        deployer.call_raw().await?
+    };
+
+    let constructor_return = DynSolType::Array(Box::new(DynSolType::Bytes));
+    let return_data_tokens = constructor_return.abi_decode_sequence(&res)?;
+
+    let data = return_data_tokens
+        .as_array()
+        .ok_or(AMMError::BatchRequestError(first_token))?
+        .iter()
+        .map(|data| {
+            data.as_bytes()
+                .filter(|data| !data.is_empty())
+                .map(Bytes::copy_from_slice)
+        })
+        .collect::<Vec<Option<Bytes>>>();
+
+    if data.len() != tokens.len() * CALLS_PER_TOKEN {
+        return Err(AMMError::BatchRequestError(first_token));
+    }
+
+    Ok(data
+        .chunks(CALLS_PER_TOKEN)
+        .map(<[Option<Bytes>]>::to_vec)
+        .collect())
+}
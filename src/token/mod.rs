@@ -0,0 +1,529 @@
+//! Shared registry of token metadata, so that decimals, symbols and names are fetched once per token instead of
+//! once per pool.
+
+#[cfg(feature = "provider")]
+pub mod batch_request;
+pub mod tax;
+#[cfg(feature = "provider")]
+pub mod transfer_tax;
+
+#[cfg(feature = "provider")]
+use std::sync::Arc;
+use std::{collections::HashMap, path::Path};
+
+use alloy::primitives::{Address, U256};
+#[cfg(feature = "provider")]
+use alloy::{network::Network, providers::Provider, sol, transports::Transport};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "provider")]
+use crate::amm::{
+    multicall::{aggregate, call3, decode},
+    AutomatedMarketMaker, IErc20,
+};
+use crate::{
+    amm::{
+        bancor_v3::{BNT, BNT_DECIMALS},
+        uniswap_v2::UniswapV2Pool,
+        AMM,
+    },
+    errors::AMMError,
+};
+
+/// Number of tokens per batch request when populating the registry.
+pub const TOKEN_BATCH_SIZE: usize = 500;
+
+#[cfg(feature = "provider")]
+sol! {
+    /// Interface of ERC20 tokens returning `bytes32` metadata (e.g. MKR)
+    #[derive(Debug, PartialEq, Eq)]
+    contract IErc20Bytes32 {
+        function symbol() external view returns (bytes32);
+        function name() external view returns (bytes32);
+    }
+}
+
+/// Metadata of an ERC20 token.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Token {
+    pub address: Address,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+    /// Transfer tax in basis points, `None` if the token has not been checked for a transfer tax.
+    #[serde(default)]
+    pub transfer_tax_bps: Option<u32>,
+    /// Total supply as of the block the metadata was fetched at, `None` if it is unknown.
+    #[serde(default)]
+    pub total_supply: Option<U256>,
+}
+
+impl Token {
+    pub fn new(address: Address, symbol: String, name: String, decimals: u8) -> Self {
+        Self {
+            address,
+            symbol,
+            name,
+            decimals,
+            transfer_tax_bps: None,
+            total_supply: None,
+        }
+    }
+}
+
+/// Registry of token metadata, keyed by token address.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRegistry {
+    tokens: HashMap<Address, Token>,
+}
+
+impl TokenRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a token, returning the previous metadata of the token if any.
+    pub fn insert(&mut self, token: Token) -> Option<Token> {
+        self.tokens.insert(token.address, token)
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&Token> {
+        self.tokens.get(address)
+    }
+
+    pub fn get_mut(&mut self, address: &Address) -> Option<&mut Token> {
+        self.tokens.get_mut(address)
+    }
+
+    pub fn decimals(&self, address: &Address) -> Option<u8> {
+        self.tokens.get(address).map(|token| token.decimals)
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.tokens.contains_key(address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Token> {
+        self.tokens.values()
+    }
+
+    #[cfg(feature = "provider")]
+    /// Fetches the metadata of the tokens that are not in the registry yet, see
+    /// [`get_token_metadata_batch_request`].
+    pub async fn populate<T, N, P>(
+        &mut self,
+        tokens: &[Address],
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let mut missing = tokens
+            .iter()
+            .filter(|token| !self.contains(token))
+            .copied()
+            .collect::<Vec<Address>>();
+        missing.sort();
+        missing.dedup();
+
+        for token in get_token_metadata_batch_request(&missing, block_number, provider).await? {
+            self.insert(token);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "provider")]
+    /// Fetches the metadata of all tokens of `amms` that are not in the registry yet, then sets the token decimals
+    /// of the AMMs from the registry.
+    pub async fn populate_amms<T, N, P>(
+        &mut self,
+        amms: &mut [AMM],
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        let tokens = amms
+            .iter()
+            .flat_map(|amm| amm_tokens(amm))
+            .collect::<Vec<Address>>();
+        self.populate(&tokens, block_number, provider).await?;
+
+        for amm in amms.iter_mut() {
+            self.set_decimals(amm);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the token decimals of `amm` from the registry, leaving the decimals of unknown tokens untouched.
+    pub fn set_decimals(&self, amm: &mut AMM) {
+        let decimals = |token: &Address, current: &mut u8| {
+            if let Some(decimals) = self.decimals(token) {
+                *current = decimals;
+            }
+        };
+
+        match amm {
+            AMM::UniswapV2Pool(pool) => {
+                decimals(&pool.token_a, &mut pool.token_a_decimals);
+                decimals(&pool.token_b, &mut pool.token_b_decimals);
+            }
+            AMM::UniswapV3Pool(pool) => {
+                decimals(&pool.token_a, &mut pool.token_a_decimals);
+                decimals(&pool.token_b, &mut pool.token_b_decimals);
+            }
+            AMM::ERC4626Vault(vault) => {
+                decimals(&vault.vault_token, &mut vault.vault_token_decimals);
+                decimals(&vault.asset_token, &mut vault.asset_token_decimals);
+            }
+            AMM::CurveV2Pool(pool) => {
+                decimals(&pool.token_a, &mut pool.token_a_decimals);
+                decimals(&pool.token_b, &mut pool.token_b_decimals);
+            }
+            AMM::RateAdapter(adapter) => {
+                decimals(&adapter.wrapped_token, &mut adapter.wrapped_token_decimals);
+                decimals(
+                    &adapter.underlying_token,
+                    &mut adapter.underlying_token_decimals,
+                );
+            }
+            AMM::BancorV3Pool(pool) => decimals(&pool.base_token, &mut pool.base_token_decimals),
+            AMM::GmxMarket(market) => {
+                decimals(&market.long_token, &mut market.long_token_decimals);
+                decimals(&market.short_token, &mut market.short_token_decimals);
+            }
+            AMM::AmbientPool(pool) => {
+                decimals(&pool.base_token, &mut pool.base_token_decimals);
+                decimals(&pool.quote_token, &mut pool.quote_token_decimals);
+            }
+            AMM::ConstantProductPool(pool) => {
+                decimals(&pool.token_a, &mut pool.token_a_decimals);
+                decimals(&pool.token_b, &mut pool.token_b_decimals);
+            }
+        }
+    }
+
+    /// Registers the token decimals of already populated AMMs, without fetching symbols and names.
+    pub fn register_amm_decimals(&mut self, amms: &[AMM]) {
+        for amm in amms {
+            let tokens = match amm {
+                AMM::UniswapV2Pool(pool) => [
+                    (pool.token_a, pool.token_a_decimals),
+                    (pool.token_b, pool.token_b_decimals),
+                ],
+                AMM::UniswapV3Pool(pool) => [
+                    (pool.token_a, pool.token_a_decimals),
+                    (pool.token_b, pool.token_b_decimals),
+                ],
+                AMM::ERC4626Vault(vault) => [
+                    (vault.vault_token, vault.vault_token_decimals),
+                    (vault.asset_token, vault.asset_token_decimals),
+                ],
+                AMM::CurveV2Pool(pool) => [
+                    (pool.token_a, pool.token_a_decimals),
+                    (pool.token_b, pool.token_b_decimals),
+                ],
+                AMM::RateAdapter(adapter) => [
+                    (adapter.wrapped_token, adapter.wrapped_token_decimals),
+                    (adapter.underlying_token, adapter.underlying_token_decimals),
+                ],
+                AMM::BancorV3Pool(pool) => [
+                    (pool.base_token, pool.base_token_decimals),
+                    (BNT, BNT_DECIMALS),
+                ],
+                AMM::GmxMarket(market) => [
+                    (market.long_token, market.long_token_decimals),
+                    (market.short_token, market.short_token_decimals),
+                ],
+                AMM::AmbientPool(pool) => [
+                    (pool.base_token, pool.base_token_decimals),
+                    (pool.quote_token, pool.quote_token_decimals),
+                ],
+                AMM::ConstantProductPool(pool) => [
+                    (pool.token_a, pool.token_a_decimals),
+                    (pool.token_b, pool.token_b_decimals),
+                ],
+            };
+
+            for (address, decimals) in tokens {
+                if !address.is_zero() && !self.contains(&address) {
+                    self.insert(Token {
+                        address,
+                        decimals,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "provider")]
+    /// Detects the transfer tax of the tokens of `pools` that have not been checked yet, simulating a transfer of 1%
+    /// of the pool reserve from the pool.
+    ///
+    /// Tokens must be in the registry to be checked. Tokens whose transfer cannot be simulated stay unchecked.
+    pub async fn detect_transfer_taxes<T, N, P>(
+        &mut self,
+        pools: &[UniswapV2Pool],
+        block_number: Option<u64>,
+        provider: Arc<P>,
+    ) -> Result<(), AMMError>
+    where
+        T: Transport + Clone,
+        N: Network,
+        P: Provider<T, N>,
+    {
+        for pool in pools {
+            for (token, reserve) in [
+                (pool.token_a, pool.reserve_0),
+                (pool.token_b, pool.reserve_1),
+            ] {
+                if self
+                    .get(&token)
+                    .map_or(true, |token| token.transfer_tax_bps.is_some())
+                {
+                    continue;
+                }
+
+                let tax = transfer_tax::detect_transfer_tax(
+                    token,
+                    pool.address,
+                    U256::from(reserve / 100),
+                    block_number,
+                    provider.clone(),
+                )
+                .await?;
+
+                if let (Some(tax), Some(token)) = (tax, self.get_mut(&token)) {
+                    token.transfer_tax_bps = Some(tax);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the token transfer taxes of `pool` from the registry, so that swap simulations account for them.
+    pub fn set_transfer_taxes(&self, pool: &mut UniswapV2Pool) {
+        let tax = |token: &Address| {
+            self.get(token)
+                .and_then(|token| token.transfer_tax_bps)
+                .unwrap_or_default()
+        };
+
+        pool.token_a_transfer_tax_bps = tax(&pool.token_a);
+        pool.token_b_transfer_tax_bps = tax(&pool.token_b);
+    }
+
+    /// Writes the registry to `path` as JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AMMError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Reads a registry written by [`TokenRegistry::save`] from `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AMMError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+impl FromIterator<Token> for TokenRegistry {
+    fn from_iter<I: IntoIterator<Item = Token>>(iter: I) -> Self {
+        Self {
+            tokens: iter
+                .into_iter()
+                .map(|token| (token.address, token))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "provider")]
+/// Fetches the decimals, symbols, names and total supplies of `tokens` through a deployless batch request, falling
+/// back to Multicall3, in chunks of [`TOKEN_BATCH_SIZE`] tokens per request.
+///
+/// Tokens without a `decimals` function are skipped. Tokens without a readable symbol or name get empty strings, and
+/// tokens without a readable total supply get `None`.
+pub async fn get_token_metadata_batch_request<T, N, P>(
+    tokens: &[Address],
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<Vec<Token>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    let mut metadata = Vec::with_capacity(tokens.len());
+
+    for chunk in tokens.chunks(TOKEN_BATCH_SIZE) {
+        let data = match batch_request::get_token_metadata_calls_batch_request(
+            chunk,
+            block_number,
+            provider.clone(),
+        )
+        .await
+        {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::debug!(
+                    ?err,
+                    "Deployless batch request failed, falling back to Multicall3"
+                );
+
+                let calls = chunk
+                    .iter()
+                    .map(|token| {
+                        vec![
+                            call3(*token, IErc20::decimalsCall {}),
+                            call3(*token, IErc20::symbolCall {}),
+                            call3(*token, IErc20::nameCall {}),
+                            call3(*token, IErc20::totalSupplyCall {}),
+                        ]
+                    })
+                    .collect();
+                aggregate(calls, block_number, provider.clone()).await?
+            }
+        };
+
+        for (token, data) in chunk.iter().zip(data) {
+            let Some(decimals) = decode::<IErc20::decimalsCall>(&data[0]) else {
+                tracing::debug!(?token, "token has no decimals, skipping");
+                continue;
+            };
+
+            let symbol = decode::<IErc20::symbolCall>(&data[1])
+                .map(|symbol| symbol._0)
+                .or_else(|| {
+                    decode::<IErc20Bytes32::symbolCall>(&data[1])
+                        .map(|symbol| bytes32_to_string(symbol._0.as_slice()))
+                })
+                .unwrap_or_default();
+            let name = decode::<IErc20::nameCall>(&data[2])
+                .map(|name| name._0)
+                .or_else(|| {
+                    decode::<IErc20Bytes32::nameCall>(&data[2])
+                        .map(|name| bytes32_to_string(name._0.as_slice()))
+                })
+                .unwrap_or_default();
+
+            metadata.push(Token {
+                total_supply: decode::<IErc20::totalSupplyCall>(&data[3])
+                    .map(|total_supply| total_supply._0),
+                ..Token::new(*token, symbol, name, decimals._0)
+            });
+        }
+    }
+
+    Ok(metadata)
+}
+
+#[cfg(feature = "provider")]
+/// Returns the tokens of an AMM, ignoring unset addresses.
+fn amm_tokens(amm: &AMM) -> Vec<Address> {
+    amm.tokens()
+        .into_iter()
+        .filter(|token| !token.is_zero())
+        .collect()
+}
+
+#[cfg(feature = "provider")]
+fn bytes32_to_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{primitives::address, providers::ProviderBuilder};
+
+    use crate::amm::{uniswap_v2::UniswapV2Pool, uniswap_v3::UniswapV3Pool, AMM};
+
+    use super::{bytes32_to_string, Token, TokenRegistry};
+
+    #[test]
+    fn test_registry() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+        let mut registry = TokenRegistry::new();
+        registry.register_amm_decimals(&[AMM::UniswapV2Pool(UniswapV2Pool {
+            token_a: usdc,
+            token_a_decimals: 6,
+            token_b: weth,
+            token_b_decimals: 18,
+            ..Default::default()
+        })]);
+        assert_eq!(registry.decimals(&usdc), Some(6));
+        assert_eq!(registry.decimals(&weth), Some(18));
+
+        // Metadata inserted later takes precedence
+        registry.insert(Token::new(
+            usdc,
+            "USDC".to_string(),
+            "USD Coin".to_string(),
+            6,
+        ));
+        let json = serde_json::to_string(&registry).unwrap();
+        let registry: TokenRegistry = serde_json::from_str(&json).unwrap();
+        assert_eq!(registry.get(&usdc).unwrap().symbol, "USDC");
+        assert_eq!(registry.len(), 2);
+
+        let mut amm = AMM::UniswapV3Pool(UniswapV3Pool {
+            token_a: usdc,
+            token_b: weth,
+            ..Default::default()
+        });
+        registry.set_decimals(&mut amm);
+        let AMM::UniswapV3Pool(pool) = amm else {
+            unreachable!()
+        };
+        assert_eq!((pool.token_a_decimals, pool.token_b_decimals), (6, 18));
+
+        assert_eq!(bytes32_to_string(b"MKR\0\0\0"), "MKR");
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_populate() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let mkr = address!("9f8F72aA9304c8B593d555F12eF6589cC3A579A2");
+
+        let mut registry = TokenRegistry::new();
+        registry
+            .populate(&[usdc, mkr, usdc], None, provider)
+            .await
+            .unwrap();
+
+        let usdc = registry.get(&usdc).unwrap();
+        assert_eq!(usdc.decimals, 6);
+        assert_eq!(usdc.symbol, "USDC");
+        assert!(usdc
+            .total_supply
+            .is_some_and(|total_supply| !total_supply.is_zero()));
+
+        // MKR returns its symbol as bytes32
+        assert_eq!(registry.get(&mkr).unwrap().symbol, "MKR");
+    }
+}
@@ -0,0 +1,224 @@
+//! Buy and sell taxes of tokens, applied on top of the swap simulations of any AMM.
+//!
+//! Taxed tokens usually tax the transfers from their pools at a buy rate and the transfers to their pools at a sell
+//! rate. Taxes detected on chain or imported from community lists can be overridden per token, overrides taking
+//! precedence over the other taxes.
+//!
+//! AMMs applying transfer taxes themselves, e.g. [`UniswapV2Pool`](crate::amm::uniswap_v2::UniswapV2Pool) with
+//! transfer taxes set, should not be simulated with the registry, as the taxes would be applied twice.
+
+use std::{collections::HashMap, path::Path};
+
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    core::uniswap_v2::apply_transfer_tax,
+    errors::{AMMError, SwapSimulationError},
+};
+
+use super::TokenRegistry;
+
+/// Taxes of a token in basis points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TokenTax {
+    /// Tax on the tokens bought from a pool.
+    pub buy_bps: u32,
+    /// Tax on the tokens sold to a pool.
+    pub sell_bps: u32,
+}
+
+impl TokenTax {
+    pub fn new(buy_bps: u32, sell_bps: u32) -> Self {
+        Self { buy_bps, sell_bps }
+    }
+}
+
+/// Entry of a tax list imported with [`TokenTaxRegistry::import`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenTaxEntry {
+    pub address: Address,
+    #[serde(flatten)]
+    pub tax: TokenTax,
+}
+
+/// Registry of token taxes, see the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenTaxRegistry {
+    taxes: HashMap<Address, TokenTax>,
+    #[serde(default)]
+    overrides: HashMap<Address, TokenTax>,
+}
+
+impl TokenTaxRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tax of `token`, returning its previous tax if any. Overrides still take precedence.
+    pub fn insert(&mut self, token: Address, tax: TokenTax) -> Option<TokenTax> {
+        self.taxes.insert(token, tax)
+    }
+
+    /// Overrides the tax of `token`, returning its previous override if any.
+    pub fn set_override(&mut self, token: Address, tax: TokenTax) -> Option<TokenTax> {
+        self.overrides.insert(token, tax)
+    }
+
+    pub fn remove_override(&mut self, token: &Address) -> Option<TokenTax> {
+        self.overrides.remove(token)
+    }
+
+    /// Returns the tax of `token`, its override if any, zero for unknown tokens.
+    pub fn get(&self, token: &Address) -> TokenTax {
+        self.overrides
+            .get(token)
+            .or_else(|| self.taxes.get(token))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn contains(&self, token: &Address) -> bool {
+        self.overrides.contains_key(token) || self.taxes.contains_key(token)
+    }
+
+    /// Sets the taxes of a tax list, returning the number of entries.
+    pub fn import(&mut self, entries: impl IntoIterator<Item = TokenTaxEntry>) -> usize {
+        entries.into_iter().fold(0, |count, entry| {
+            self.insert(entry.address, entry.tax);
+            count + 1
+        })
+    }
+
+    /// Sets the taxes of a JSON tax list, an array of `{ "address", "buy_bps", "sell_bps" }` objects, returning the
+    /// number of entries.
+    pub fn import_json(&mut self, json: &str) -> Result<usize, AMMError> {
+        Ok(self.import(serde_json::from_str::<Vec<TokenTaxEntry>>(json)?))
+    }
+
+    /// Sets the taxes of the tokens of `registry` checked for a transfer tax, taxing buys and sells alike.
+    pub fn import_token_registry(&mut self, registry: &TokenRegistry) -> usize {
+        self.import(registry.iter().filter_map(|token| {
+            token.transfer_tax_bps.map(|tax_bps| TokenTaxEntry {
+                address: token.address,
+                tax: TokenTax::new(tax_bps, tax_bps),
+            })
+        }))
+    }
+
+    /// Returns `amount` of `token` bought from a pool, net of the buy tax.
+    pub fn apply_buy_tax(&self, token: &Address, amount: U256) -> U256 {
+        apply_transfer_tax(amount, self.get(token).buy_bps)
+    }
+
+    /// Returns `amount` of `token` sold to a pool, net of the sell tax.
+    pub fn apply_sell_tax(&self, token: &Address, amount: U256) -> U256 {
+        apply_transfer_tax(amount, self.get(token).sell_bps)
+    }
+
+    /// Simulates a swap of `amount_in` of `token_in` in `amm`, with the sell tax of the token in applied to the amount
+    /// received by the AMM and the buy tax of the token out to the amount out.
+    pub fn simulate_swap(
+        &self,
+        amm: &AMM,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let amount_out = amm.simulate_swap(token_in, self.apply_sell_tax(&token_in, amount_in))?;
+        Ok(self.apply_buy_tax(&amm.get_token_out(token_in), amount_out))
+    }
+
+    /// Writes the registry to `path` as JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AMMError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Reads a registry written by [`TokenTaxRegistry::save`] from `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AMMError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, U256};
+
+    use crate::{
+        amm::{uniswap_v2::UniswapV2Pool, AutomatedMarketMaker, AMM},
+        token::{Token, TokenRegistry},
+    };
+
+    use super::{TokenTax, TokenTaxRegistry};
+
+    #[test]
+    fn test_registry() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let taxed = address!("1111111111111111111111111111111111111111");
+
+        let mut registry = TokenTaxRegistry::new();
+        let imported = registry
+            .import_json(&format!(
+                r#"[{{ "address": "{taxed}", "buy_bps": 300, "sell_bps": 500 }}]"#
+            ))
+            .unwrap();
+        assert_eq!(imported, 1);
+        assert_eq!(registry.get(&taxed), TokenTax::new(300, 500));
+        assert_eq!(registry.get(&usdc), TokenTax::default());
+
+        // Overrides take precedence over imported taxes
+        registry.set_override(taxed, TokenTax::new(100, 100));
+        registry.insert(taxed, TokenTax::new(1000, 1000));
+        assert_eq!(registry.get(&taxed), TokenTax::new(100, 100));
+        registry.remove_override(&taxed);
+        assert_eq!(registry.get(&taxed), TokenTax::new(1000, 1000));
+
+        let json = serde_json::to_string(&registry).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TokenTaxRegistry>(&json).unwrap(),
+            registry
+        );
+
+        let mut tokens = TokenRegistry::new();
+        tokens.insert(Token {
+            address: usdc,
+            transfer_tax_bps: Some(0),
+            ..Default::default()
+        });
+        assert_eq!(registry.import_token_registry(&tokens), 1);
+        assert!(registry.contains(&usdc));
+    }
+
+    #[test]
+    fn test_simulate_swap() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let taxed = address!("1111111111111111111111111111111111111111");
+        let amm = AMM::UniswapV2Pool(UniswapV2Pool {
+            token_a: taxed,
+            token_b: usdc,
+            reserve_0: 1_000_000_000,
+            reserve_1: 1_000_000_000,
+            fee: 300,
+            ..Default::default()
+        });
+
+        let mut registry = TokenTaxRegistry::new();
+        registry.insert(taxed, TokenTax::new(300, 500));
+
+        let amount_in = U256::from(1_000_000);
+
+        // Selling the taxed token, the pool receives 95% of the amount in
+        assert_eq!(
+            registry.simulate_swap(&amm, taxed, amount_in).unwrap(),
+            amm.simulate_swap(taxed, U256::from(950_000)).unwrap()
+        );
+
+        // Buying it, 97% of the amount out is received
+        let amount_out = amm.simulate_swap(usdc, amount_in).unwrap();
+        assert_eq!(
+            registry.simulate_swap(&amm, usdc, amount_in).unwrap(),
+            amount_out * U256::from(9_700) / U256::from(10_000)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Detection of fee-on-transfer tokens.
+//!
+//! A probe contract is placed at the address of a token holder (e.g. a Uniswap V2 pair) through an `eth_call` state
+//! override. The probe transfers tokens from the holder and returns the amount received by the recipient, from which
+//! the transfer tax is derived. The storage of the token is untouched, so the holder keeps its balance.
+
+use std::sync::Arc;
+
+use alloy::{
+    network::Network,
+    primitives::{address, hex, Address, Bytes, U256},
+    providers::Provider,
+    sol_types::SolValue,
+    transports::{RpcError, Transport},
+};
+use serde_json::{json, Value};
+
+use crate::errors::AMMError;
+
+/// Recipient of the probe transfer.
+const PROBE_RECIPIENT: Address = address!("7E5F4552091A69125d5DfCb7b8C2659029395Bdf");
+
+/// Runtime code of the probe, called with `abi.encode(token, recipient, amount)`.
+///
+/// ```text
+/// before = token.balanceOf(recipient)
+/// token.transfer(recipient, amount)
+/// return token.balanceOf(recipient) - before
+/// ```
+///
+/// Reverts if any of the calls fail. The return data of `transfer` is ignored, so tokens that do not return a bool
+/// (e.g. USDT) are supported.
+const PROBE_CODE: [u8; 130] = hex!(
+    "6370a0823160e01b60005260203560045260206080602460006000355afa15607d57"
+    "63a9059cbb60e01b600052602035600452604035602452600060006044600060006000355af115607d57"
+    "6370a0823160e01b600052602035600452602060a0602460006000355afa15607d57"
+    "60805160a0510360005260206000f35b600080fd"
+);
+
+/// Detects the transfer tax of `token` by simulating a transfer of `amount` from `holder`.
+///
+/// Returns the tax in basis points, or `None` if the transfer could not be simulated (e.g. the holder balance is
+/// too low or the token blocks the transfer).
+pub async fn detect_transfer_tax<T, N, P>(
+    token: Address,
+    holder: Address,
+    amount: U256,
+    block_number: Option<u64>,
+    provider: Arc<P>,
+) -> Result<Option<u32>, AMMError>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N>,
+{
+    if amount.is_zero() {
+        return Ok(None);
+    }
+
+    let calldata = Bytes::from((token, PROBE_RECIPIENT, amount).abi_encode_params());
+    let block = block_number.map_or("latest".to_string(), |block| format!("{block:#x}"));
+
+    let tx = json!({ "to": holder, "data": calldata });
+    let mut overrides = serde_json::Map::new();
+    overrides.insert(
+        holder.to_string(),
+        json!({ "code": Bytes::from(PROBE_CODE.to_vec()) }),
+    );
+
+    let result = provider
+        .client()
+        .request::<_, Bytes>("eth_call", (tx, block, Value::Object(overrides)))
+        .await;
+
+    let return_data = match result {
+        Ok(return_data) => return_data,
+        Err(RpcError::ErrorResp(err)) => {
+            tracing::debug!(?token, ?holder, ?err, "transfer simulation reverted");
+            return Ok(None);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let Ok(received) = U256::abi_decode(&return_data, true) else {
+        return Ok(None);
+    };
+
+    Ok(Some(transfer_tax_bps(amount, received)))
+}
+
+/// Returns the transfer tax in basis points for a transfer of `amount` of which `received` arrived.
+pub fn transfer_tax_bps(amount: U256, received: U256) -> u32 {
+    if received >= amount || amount.is_zero() {
+        return 0;
+    }
+
+    let tax = (amount - received) * U256::from(10_000) / amount;
+    tax.to::<u32>()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::{
+        primitives::{address, U256},
+        providers::ProviderBuilder,
+    };
+
+    use super::{detect_transfer_tax, transfer_tax_bps};
+
+    #[test]
+    fn test_transfer_tax_bps() {
+        assert_eq!(transfer_tax_bps(U256::from(1_000), U256::from(1_000)), 0);
+        assert_eq!(transfer_tax_bps(U256::from(1_000), U256::from(950)), 500);
+        assert_eq!(transfer_tax_bps(U256::from(1_000), U256::from(1_001)), 0);
+        assert_eq!(transfer_tax_bps(U256::from(1_000), U256::ZERO), 10_000);
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignoring to not throttle the Provider on workflows
+    async fn test_detect_transfer_tax() {
+        let rpc_endpoint = std::env::var("ETHEREUM_RPC_ENDPOINT").unwrap();
+        let provider = Arc::new(ProviderBuilder::new().on_http(rpc_endpoint.parse().unwrap()));
+
+        // USDC held by the USDC/WETH Uniswap V2 pair
+        let tax = detect_transfer_tax(
+            address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            U256::from(1_000_000),
+            None,
+            provider,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(tax, Some(0));
+    }
+}
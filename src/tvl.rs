@@ -0,0 +1,264 @@
+//! Total value locked of AMMs in USD, per pool and aggregated per protocol and per token.
+//!
+//! Balances are read from the state of the AMMs: the reserves of constant product pools, the pool amounts of GMX
+//! markets, the asset reserve of ERC4626 vaults and the virtual reserves of the active liquidity of concentrated
+//! liquidity pools. Rate adapters hold no liquidity.
+
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amm::{
+        bancor_v3::{BNT, BNT_DECIMALS},
+        AutomatedMarketMaker, Protocol, AMM,
+    },
+    core::price::u256_to_f64,
+    errors::ArithmeticError,
+};
+
+/// Source of USD prices of tokens, e.g. the prices of
+/// [`ChainlinkOracle::usd_prices`](crate::chainlink::ChainlinkOracle::usd_prices).
+pub trait PriceOracle {
+    /// Returns the USD price of a whole unit of `token`, `None` if the token is not priced.
+    fn usd_price(&self, token: &Address) -> Option<f64>;
+}
+
+impl PriceOracle for HashMap<Address, f64> {
+    fn usd_price(&self, token: &Address) -> Option<f64> {
+        self.get(token).copied()
+    }
+}
+
+/// Returns the balances of the tokens of `amm`, adjusted for the token decimals.
+pub fn token_balances(amm: &AMM) -> Result<Vec<(Address, f64)>, ArithmeticError> {
+    let balance = |amount: U256, decimals: u8| u256_to_f64(amount) / 10f64.powi(decimals as i32);
+
+    Ok(match amm {
+        AMM::UniswapV2Pool(pool) => vec![
+            (
+                pool.token_a,
+                balance(U256::from(pool.reserve_0), pool.token_a_decimals),
+            ),
+            (
+                pool.token_b,
+                balance(U256::from(pool.reserve_1), pool.token_b_decimals),
+            ),
+        ],
+        AMM::UniswapV3Pool(pool) => {
+            let (reserve_0, reserve_1) = pool.calculate_virtual_reserves()?;
+            vec![
+                (
+                    pool.token0(),
+                    balance(U256::from(reserve_0), pool.token0_decimals()),
+                ),
+                (
+                    pool.token1(),
+                    balance(U256::from(reserve_1), pool.token1_decimals()),
+                ),
+            ]
+        }
+        AMM::ERC4626Vault(vault) => vec![(
+            vault.asset_token,
+            balance(vault.asset_reserve, vault.asset_token_decimals),
+        )],
+        AMM::CurveV2Pool(pool) => vec![
+            (pool.token_a, balance(pool.balance_0, pool.token_a_decimals)),
+            (pool.token_b, balance(pool.balance_1, pool.token_b_decimals)),
+        ],
+        AMM::RateAdapter(_) => vec![],
+        AMM::BancorV3Pool(pool) => vec![
+            (
+                pool.base_token,
+                balance(
+                    U256::from(pool.base_token_trading_liquidity),
+                    pool.base_token_decimals,
+                ),
+            ),
+            (
+                BNT,
+                balance(U256::from(pool.bnt_trading_liquidity), BNT_DECIMALS),
+            ),
+        ],
+        AMM::GmxMarket(market) => vec![
+            (
+                market.long_token,
+                balance(market.long_pool_amount, market.long_token_decimals),
+            ),
+            (
+                market.short_token,
+                balance(market.short_pool_amount, market.short_token_decimals),
+            ),
+        ],
+        AMM::AmbientPool(pool) => {
+            // Virtual reserves of the ambient and concentrated liquidity at the current price
+            let liquidity = (pool.ambient_liquidity + pool.concentrated_liquidity) as f64;
+            let sqrt_price = u256_to_f64(pool.sqrt_price) / 2f64.powi(96);
+            if sqrt_price == 0.0 {
+                return Err(ArithmeticError::YIsZero);
+            }
+
+            vec![
+                (
+                    pool.base_token,
+                    liquidity / sqrt_price / 10f64.powi(pool.base_token_decimals as i32),
+                ),
+                (
+                    pool.quote_token,
+                    liquidity * sqrt_price / 10f64.powi(pool.quote_token_decimals as i32),
+                ),
+            ]
+        }
+        AMM::ConstantProductPool(pool) => vec![
+            (
+                pool.token_a,
+                balance(U256::from(pool.reserve_0), pool.token_a_decimals),
+            ),
+            (
+                pool.token_b,
+                balance(U256::from(pool.reserve_1), pool.token_b_decimals),
+            ),
+        ],
+    })
+}
+
+/// Returns the value locked in `amm` in USD, `None` if a token of the AMM is not priced by `price_oracle`.
+pub fn tvl_usd(amm: &AMM, price_oracle: &impl PriceOracle) -> Result<Option<f64>, ArithmeticError> {
+    Ok(token_balances(amm)?
+        .into_iter()
+        .map(|(token, balance)| Some(balance * price_oracle.usd_price(&token)?))
+        .sum())
+}
+
+/// Value locked in a set of AMMs in USD.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TvlReport {
+    pub total_usd: f64,
+    pub by_protocol: BTreeMap<Protocol, f64>,
+    /// Value of the balances of each token across the AMMs.
+    pub by_token: HashMap<Address, f64>,
+    /// AMMs left out of the report, with a token not priced or balances that could not be computed.
+    pub unpriced: Vec<Address>,
+}
+
+impl TvlReport {
+    pub fn new<'a>(
+        amms: impl IntoIterator<Item = &'a AMM>,
+        price_oracle: &impl PriceOracle,
+    ) -> Self {
+        let mut report = Self::default();
+
+        for amm in amms {
+            let Some(values) = token_balances(amm).ok().and_then(|balances| {
+                balances
+                    .into_iter()
+                    .map(|(token, balance)| {
+                        Some((token, balance * price_oracle.usd_price(&token)?))
+                    })
+                    .collect::<Option<Vec<_>>>()
+            }) else {
+                report.unpriced.push(amm.address());
+                continue;
+            };
+
+            for (token, value) in values {
+                report.total_usd += value;
+                *report.by_protocol.entry(amm.protocol()).or_default() += value;
+                *report.by_token.entry(token).or_default() += value;
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::{address, U256};
+
+    use crate::amm::{
+        erc_4626::ERC4626Vault, rate_adapter::RateAdapter, uniswap_v2::UniswapV2Pool,
+        uniswap_v3::UniswapV3Pool, AutomatedMarketMaker, Protocol, AMM,
+    };
+
+    use super::{token_balances, tvl_usd, TvlReport};
+
+    #[test]
+    fn test_tvl() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let unpriced = address!("1111111111111111111111111111111111111111");
+
+        let usdc_weth = AMM::UniswapV2Pool(UniswapV2Pool {
+            address: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            token_a: usdc,
+            token_a_decimals: 6,
+            token_b: weth,
+            token_b_decimals: 18,
+            reserve_0: 2_000_000_000_000,
+            reserve_1: 1_000_000_000_000_000_000_000,
+            ..Default::default()
+        });
+        let weth_unpriced = AMM::UniswapV2Pool(UniswapV2Pool {
+            address: address!("2222222222222222222222222222222222222222"),
+            token_a: weth,
+            token_a_decimals: 18,
+            token_b: unpriced,
+            token_b_decimals: 18,
+            reserve_0: 1_000_000_000_000_000_000,
+            reserve_1: 1_000_000_000_000_000_000,
+            ..Default::default()
+        });
+        let vault = AMM::ERC4626Vault(ERC4626Vault {
+            vault_token: address!("3333333333333333333333333333333333333333"),
+            vault_token_decimals: 18,
+            asset_token: usdc,
+            asset_token_decimals: 6,
+            vault_reserve: U256::from(1_000_000_000_000_000_000_u128),
+            asset_reserve: U256::from(500_000_000),
+            ..Default::default()
+        });
+
+        let prices = HashMap::from([(usdc, 1.0), (weth, 2000.0)]);
+
+        assert_eq!(tvl_usd(&usdc_weth, &prices).unwrap(), Some(4_000_000.0));
+        assert_eq!(tvl_usd(&weth_unpriced, &prices).unwrap(), None);
+        assert_eq!(
+            tvl_usd(&AMM::RateAdapter(RateAdapter::default()), &prices).unwrap(),
+            Some(0.0)
+        );
+
+        let report = TvlReport::new([&usdc_weth, &weth_unpriced, &vault], &prices);
+        assert_eq!(report.total_usd, 4_000_500.0);
+        assert_eq!(report.by_protocol[&Protocol::UniswapV2], 4_000_000.0);
+        assert_eq!(report.by_protocol[&Protocol::ERC4626], 500.0);
+        assert_eq!(report.by_token[&usdc], 2_000_500.0);
+        assert_eq!(report.by_token[&weth], 2_000_000.0);
+        assert_eq!(report.unpriced, vec![weth_unpriced.address()]);
+    }
+
+    #[test]
+    fn test_token_balances_token_order() {
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+        // Token a is token 1 of the pool, the virtual reserves are in token 0 and token 1 order
+        let pool = AMM::UniswapV3Pool(UniswapV3Pool {
+            token_a: weth,
+            token_a_decimals: 18,
+            token_b: usdc,
+            token_b_decimals: 6,
+            liquidity: 1_000_000_000_000_000_000,
+            sqrt_price: U256::from(1) << 96,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            token_balances(&pool).unwrap(),
+            vec![(usdc, 1_000_000_000_000.0), (weth, 1.0)]
+        );
+    }
+}
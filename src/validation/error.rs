@@ -0,0 +1,27 @@
+use alloy::{
+    primitives::{Address, Bytes},
+    transports::TransportError,
+};
+use thiserror::Error;
+
+use crate::errors::{AMMError, SwapSimulationError};
+
+#[derive(Error, Debug)]
+pub enum ValidationError {
+    #[error(transparent)]
+    TransportError(#[from] TransportError),
+    #[error(transparent)]
+    JoinError(#[from] tokio::task::JoinError),
+    #[error(transparent)]
+    AMMError(#[from] AMMError),
+    #[error(transparent)]
+    SwapSimulationError(#[from] SwapSimulationError),
+    #[error("EVM error: {0}")]
+    EVMError(String),
+    #[error("Swap execution is not supported for the AMM at {0}")]
+    UnsupportedAMM(Address),
+    #[error("Unexpected swap execution output: {0}")]
+    UnexpectedOutput(Bytes),
+    #[error("Block {0} not found")]
+    BlockNotFound(u64),
+}
@@ -0,0 +1,321 @@
+//! Validation of local swap simulations against the pool bytecode, executed in revm.
+//!
+//! [`SwapValidator`] executes swaps against the state of a block, with accounts and storage fetched lazily from a
+//! provider, and compares the amounts with [`AutomatedMarketMaker::simulate_swap`]. This catches forks whose
+//! on-chain logic differs from the modeled protocol, e.g. modified fee or tick math.
+//!
+//! Swaps are executed through the swap executor of [`crate::amm::onchain`], so no tokens need to be funded or
+//! approved. Uniswap V3 pools, and forks with the same `swap` interface and callback arguments, are supported.
+
+pub mod error;
+
+use std::{marker::PhantomData, sync::Arc};
+
+use alloy::{
+    network::Network,
+    primitives::{keccak256, Address, Bytes, B256, I256, U256, U64},
+    providers::Provider,
+    rpc::types::eth::{Block, BlockId, BlockNumberOrTag},
+    transports::Transport,
+};
+use revm::{
+    db::CacheDB,
+    primitives::{AccountInfo, Bytecode, ExecutionResult, TransactTo},
+    DatabaseRef, Evm,
+};
+use tokio::runtime::Handle;
+
+use crate::amm::{
+    onchain::{decode_swap_deltas, SWAP_CALLER, SWAP_EXECUTOR_ADDRESS, SWAP_EXECUTOR_CODE},
+    AutomatedMarketMaker, AMM,
+};
+
+use self::error::ValidationError;
+
+/// Amounts out of a swap, simulated locally and executed in revm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapValidation {
+    pub simulated_amount_out: U256,
+    pub executed_amount_out: U256,
+}
+
+impl SwapValidation {
+    /// Returns true if the local simulation matches the execution exactly.
+    pub fn is_exact(&self) -> bool {
+        self.simulated_amount_out == self.executed_amount_out
+    }
+
+    /// Returns the absolute difference between the simulated and executed amounts out.
+    pub fn difference(&self) -> U256 {
+        if self.simulated_amount_out > self.executed_amount_out {
+            self.simulated_amount_out - self.executed_amount_out
+        } else {
+            self.executed_amount_out - self.simulated_amount_out
+        }
+    }
+}
+
+/// Executes swaps in revm against the state of a block, see the [module documentation](self).
+#[derive(Debug)]
+pub struct SwapValidator<T, N, P> {
+    provider: Arc<P>,
+    block_number: u64,
+    transport: PhantomData<T>,
+    network: PhantomData<N>,
+}
+
+impl<T, N, P> SwapValidator<T, N, P>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    /// Creates a validator executing swaps at `block_number`, which should be the block the validated AMMs are
+    /// synced to.
+    pub fn new(provider: Arc<P>, block_number: u64) -> Self {
+        Self {
+            provider,
+            block_number,
+            transport: PhantomData,
+            network: PhantomData,
+        }
+    }
+
+    /// Simulates a swap locally and executes it in revm, returning both amounts out.
+    pub async fn validate_swap(
+        &self,
+        amm: &AMM,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<SwapValidation, ValidationError> {
+        let simulated_amount_out = amm.simulate_swap(token_in, amount_in)?;
+        let executed_amount_out = self.execute_swap(amm, token_in, amount_in).await?;
+
+        Ok(SwapValidation {
+            simulated_amount_out,
+            executed_amount_out,
+        })
+    }
+
+    /// Executes a swap with the bytecode of the pool in revm and returns the amount out.
+    pub async fn execute_swap(
+        &self,
+        amm: &AMM,
+        token_in: Address,
+        amount_in: U256,
+    ) -> Result<U256, ValidationError> {
+        let AMM::UniswapV3Pool(pool) = amm else {
+            return Err(ValidationError::UnsupportedAMM(amm.address()));
+        };
+
+        let executor_input = pool.executor_swap_calldata(token_in, amount_in)?;
+
+        let block: Option<Block> = self
+            .provider
+            .client()
+            .request(
+                "eth_getBlockByNumber",
+                (BlockNumberOrTag::Number(self.block_number), false),
+            )
+            .await?;
+        let timestamp = block
+            .ok_or(ValidationError::BlockNotFound(self.block_number))?
+            .header
+            .timestamp;
+
+        let db = CacheDB::new(ProviderDB::new(
+            self.provider.clone(),
+            self.block_number,
+            Handle::current(),
+        ));
+        let block_number = self.block_number;
+
+        // The database blocks on provider requests, so the EVM runs on a blocking thread
+        let (amount_0, amount_1) = tokio::task::spawn_blocking(move || {
+            execute_swap_deltas(db, executor_input, block_number, U256::from(timestamp))
+        })
+        .await??;
+
+        // Negative deltas are received by the swapper
+        let amount_out = if token_in == pool.token_a {
+            amount_1
+        } else {
+            amount_0
+        };
+        Ok(amount_out.unsigned_abs())
+    }
+}
+
+/// Executes a swap through the swap executor and returns the token deltas of the swap.
+///
+/// `executor_input` is the pool address followed by the swap calldata, see
+/// [`swap_executor_input`](crate::amm::onchain::swap_executor_input).
+pub fn execute_swap_deltas<DB>(
+    mut db: CacheDB<DB>,
+    executor_input: Bytes,
+    block_number: u64,
+    timestamp: U256,
+) -> Result<(I256, I256), ValidationError>
+where
+    DB: DatabaseRef,
+    DB::Error: std::fmt::Debug,
+{
+    let executor_code = Bytecode::new_raw(Bytes::from_static(&SWAP_EXECUTOR_CODE));
+    db.insert_account_info(
+        SWAP_EXECUTOR_ADDRESS,
+        AccountInfo::new(U256::ZERO, 0, executor_code.hash_slow(), executor_code),
+    );
+
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .modify_block_env(|block| {
+            block.number = U256::from(block_number);
+            block.timestamp = timestamp;
+        })
+        .modify_tx_env(|tx| {
+            tx.caller = SWAP_CALLER;
+            tx.transact_to = TransactTo::Call(SWAP_EXECUTOR_ADDRESS);
+            tx.data = executor_input;
+        })
+        .build();
+
+    let result = evm
+        .transact()
+        .map_err(|err| ValidationError::EVMError(format!("{err:?}")))?
+        .result;
+
+    match result {
+        ExecutionResult::Success { output, .. } => Ok(decode_swap_deltas(output.data())?),
+        ExecutionResult::Revert { output, .. } => Err(ValidationError::UnexpectedOutput(output)),
+        ExecutionResult::Halt { reason, .. } => {
+            Err(ValidationError::EVMError(format!("{reason:?}")))
+        }
+    }
+}
+
+/// Database fetching accounts and storage at a block from a provider.
+///
+/// Requests block on `handle`, so the database must be used outside of the runtime, e.g. in
+/// [`tokio::task::spawn_blocking`]. Wrap it in a [`CacheDB`] to fetch each slot once.
+#[derive(Debug)]
+pub struct ProviderDB<T, N, P> {
+    provider: Arc<P>,
+    block: BlockId,
+    handle: Handle,
+    transport: PhantomData<T>,
+    network: PhantomData<N>,
+}
+
+impl<T, N, P> ProviderDB<T, N, P> {
+    pub fn new(provider: Arc<P>, block_number: u64, handle: Handle) -> Self {
+        Self {
+            provider,
+            block: BlockId::from(block_number),
+            handle,
+            transport: PhantomData,
+            network: PhantomData,
+        }
+    }
+}
+
+impl<T, N, P> DatabaseRef for ProviderDB<T, N, P>
+where
+    T: Transport + Clone,
+    N: Network,
+    P: Provider<T, N> + 'static,
+{
+    type Error = ValidationError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.handle.block_on(async {
+            let client = self.provider.client();
+            let balance: U256 = client
+                .request("eth_getBalance", (address, self.block))
+                .await?;
+            let nonce: U64 = client
+                .request("eth_getTransactionCount", (address, self.block))
+                .await?;
+            let code: Bytes = client.request("eth_getCode", (address, self.block)).await?;
+
+            let code = Bytecode::new_raw(code);
+            Ok(Some(AccountInfo::new(
+                balance,
+                nonce.to(),
+                code.hash_slow(),
+                code,
+            )))
+        })
+    }
+
+    fn code_by_hash_ref(&self, _code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code is returned with the account, so it is always cached
+        Ok(Bytecode::default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.handle.block_on(async {
+            Ok(self
+                .provider
+                .client()
+                .request("eth_getStorageAt", (address, index, self.block))
+                .await?)
+        })
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        // Swaps do not depend on block hashes
+        Ok(keccak256(number.to_be_bytes::<32>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, Address, Bytes, I256, U256};
+    use revm::{
+        db::{CacheDB, EmptyDB},
+        primitives::{AccountInfo, Bytecode},
+    };
+
+    use crate::amm::onchain::swap_executor_input;
+
+    use super::execute_swap_deltas;
+
+    // Calls back the caller with deltas (7, -4) and bubbles up its revert data
+    const MOCK_POOL_CODE: [u8; 57] = [
+        0x63, 0xfa, 0x46, 0x1e, 0x33, 0x60, 0xe0, 0x1b, 0x60, 0x00, 0x52, // selector
+        0x60, 0x07, 0x60, 0x04, 0x52, // amount 0
+        0x60, 0x04, 0x60, 0x00, 0x03, 0x60, 0x24, 0x52, // amount 1
+        0x60, 0x60, 0x60, 0x44, 0x52, // data offset
+        0x60, 0x00, 0x60, 0x64, 0x52, // data length
+        0x60, 0x00, 0x60, 0x00, 0x60, 0x84, 0x60, 0x00, 0x60, 0x00, 0x33, 0x5a,
+        0xf1, // call caller
+        0x3d, 0x60, 0x00, 0x60, 0x00, 0x3e, 0x3d, 0x60, 0x00, 0xfd, // bubble up revert
+    ];
+
+    #[test]
+    fn test_execute_swap_deltas() {
+        let pool = address!("88e6a0c2ddd26feeb64f039a2c41296fcb3f5640");
+        let code = Bytecode::new_raw(Bytes::from_static(&MOCK_POOL_CODE));
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            pool,
+            AccountInfo::new(U256::ZERO, 0, code.hash_slow(), code),
+        );
+
+        let (amount_0, amount_1) =
+            execute_swap_deltas(db, swap_executor_input(pool, &[0; 4]), 1, U256::from(1)).unwrap();
+        assert_eq!(amount_0, I256::from_raw(U256::from(7)));
+        assert_eq!(amount_1, -I256::from_raw(U256::from(4)));
+
+        // Executing a swap on an account without code returns no deltas
+        let db = CacheDB::new(EmptyDB::default());
+        assert!(execute_swap_deltas(
+            db,
+            swap_executor_input(Address::ZERO, &[]),
+            1,
+            U256::from(1)
+        )
+        .is_err());
+    }
+}